@@ -217,6 +217,42 @@ pub struct Arguments {
     /// `order_events` database table.
     #[clap(long, env, default_value = "30d", value_parser = humantime::parse_duration)]
     pub order_events_cleanup_threshold: Duration,
+
+    /// The maximum number of settlement events whose details are indexed in a
+    /// single database transaction.
+    #[clap(long, env, default_value = "20")]
+    pub settlement_update_batch_size: NonZeroUsize,
+
+    /// How often indexing the details of a settlement event may fail before
+    /// it gets marked as failed and is skipped from then on.
+    #[clap(long, env, default_value = "10")]
+    pub settlement_update_max_attempts: NonZeroUsize,
+
+    /// Re-enqueue all settlement events that were marked as failed on
+    /// startup.
+    #[clap(long, env, action = clap::ArgAction::Set, default_value = "false")]
+    pub requeue_failed_settlement_updates: bool,
+
+    /// If the settlement event updater hasn't completed an update in this
+    /// amount of time the pod fails the liveness check.
+    #[clap(
+        long,
+        env,
+        default_value = "5m",
+        value_parser = humantime::parse_duration,
+    )]
+    pub max_settlement_update_age: Duration,
+
+    /// The smallest auction id assigned by this environment. Settlements of
+    /// smaller auction ids are attributed to a different environment (e.g.
+    /// barn vs prod) and their auction data doesn't get indexed.
+    #[clap(long, env, default_value = "0")]
+    pub auction_id_range_start: i64,
+
+    /// The first auction id no longer assigned by this environment. Unbounded
+    /// if not set.
+    #[clap(long, env)]
+    pub auction_id_range_end: Option<i64>,
 }
 
 impl std::fmt::Display for Arguments {
@@ -253,6 +289,12 @@ impl std::fmt::Display for Arguments {
             fee_policy,
             order_events_cleanup_interval,
             order_events_cleanup_threshold,
+            settlement_update_batch_size,
+            settlement_update_max_attempts,
+            requeue_failed_settlement_updates,
+            max_settlement_update_age,
+            auction_id_range_start,
+            auction_id_range_end,
             db_url,
             insert_batch_size,
             native_price_estimation_results_required,
@@ -322,6 +364,28 @@ impl std::fmt::Display for Arguments {
             "order_events_cleanup_threshold: {:?}",
             order_events_cleanup_threshold
         )?;
+        writeln!(
+            f,
+            "settlement_update_batch_size: {}",
+            settlement_update_batch_size
+        )?;
+        writeln!(
+            f,
+            "settlement_update_max_attempts: {}",
+            settlement_update_max_attempts
+        )?;
+        writeln!(
+            f,
+            "requeue_failed_settlement_updates: {}",
+            requeue_failed_settlement_updates
+        )?;
+        writeln!(
+            f,
+            "max_settlement_update_age: {:?}",
+            max_settlement_update_age
+        )?;
+        writeln!(f, "auction_id_range_start: {}", auction_id_range_start)?;
+        writeln!(f, "auction_id_range_end: {:?}", auction_id_range_end)?;
         writeln!(f, "insert_batch_size: {}", insert_batch_size)?;
         writeln!(
             f,
@@ -1,11 +1,14 @@
-use {crate::domain, shared::remaining_amounts};
+use {crate::domain, num::Zero, shared::remaining_amounts};
 
 pub fn to_domain(
     order: model::order::Order,
     protocol_fees: Vec<domain::fee::Policy>,
 ) -> domain::Order {
     let remaining_order = remaining_amounts::Order::from(order.clone());
-    let order_is_untouched = remaining_order.executed_amount.is_zero();
+    // The executed amount of amended orders includes the amount they got
+    // reduced by so check the actual executions instead.
+    let order_is_untouched = order.metadata.executed_buy_amount.is_zero()
+        && order.metadata.executed_sell_amount.is_zero();
 
     domain::Order {
         uid: order.metadata.uid.into(),
@@ -35,5 +38,6 @@ pub fn to_domain(
         class: order.metadata.class.into(),
         app_data: order.data.app_data.into(),
         signature: order.signature.into(),
+        insufficient_balance: order.metadata.insufficient_balance,
     }
 }
@@ -1,6 +1,9 @@
 use {
     sqlx::{Executor, PgConnection, PgPool},
-    std::{num::NonZeroUsize, time::Duration},
+    std::{
+        num::{NonZeroU64, NonZeroUsize},
+        time::Duration,
+    },
     tracing::Instrument,
 };
 
@@ -15,10 +18,13 @@ pub mod onchain_order_events;
 pub mod order_events;
 mod quotes;
 pub mod recent_settlements;
+pub mod settlement_summary;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub insert_batch_size: NonZeroUsize,
+    /// How many of the most recent auctions are kept.
+    pub auction_retention: NonZeroU64,
 }
 
 #[derive(Debug, Clone)]
@@ -28,15 +34,27 @@ pub struct Postgres {
 }
 
 impl Postgres {
-    pub async fn new(url: &str, insert_batch_size: NonZeroUsize) -> sqlx::Result<Self> {
+    pub async fn new(
+        url: &str,
+        insert_batch_size: NonZeroUsize,
+        auction_retention: NonZeroU64,
+    ) -> sqlx::Result<Self> {
         Ok(Self {
             pool: PgPool::connect(url).await?,
-            config: Config { insert_batch_size },
+            config: Config {
+                insert_batch_size,
+                auction_retention,
+            },
         })
     }
 
     pub async fn with_defaults() -> sqlx::Result<Self> {
-        Self::new("postgresql://", NonZeroUsize::new(500).unwrap()).await
+        Self::new(
+            "postgresql://",
+            NonZeroUsize::new(500).unwrap(),
+            NonZeroU64::new(1).unwrap(),
+        )
+        .await
     }
 
     pub async fn update_database_metrics(&self) -> sqlx::Result<()> {
@@ -100,8 +100,12 @@ impl Postgres {
 
         let data = serde_json::to_value(auction)?;
         let mut ex = self.pool.begin().await?;
-        database::auction::delete_all_auctions(&mut ex).await?;
         let id = database::auction::save(&mut ex, &data).await?;
+        // Older auctions are kept for a while so they can be inspected through
+        // the orderbook.
+        let retention = i64::try_from(self.config.auction_retention.get()).unwrap_or(i64::MAX);
+        database::auction::delete_auctions_before(&mut ex, id.saturating_sub(retention - 1))
+            .await?;
         ex.commit().await?;
         Ok(id)
     }
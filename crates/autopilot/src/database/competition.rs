@@ -31,6 +31,9 @@ pub struct Competition {
     /// chain before this block height.
     pub block_deadline: u64,
     pub competition_simulation_block: u64,
+    /// Gas price estimated when ranking the solutions. `None` if the
+    /// estimation failed.
+    pub gas_price: Option<U256>,
     /// Winner settlement call data
     #[derivative(Debug(format_with = "shared::debug_bytes"))]
     pub call_data: Vec<u8>,
@@ -70,6 +73,7 @@ impl super::Postgres {
                     .competition_simulation_block
                     .try_into()
                     .context("convert simulation block")?,
+                gas_price: competition.gas_price.as_ref().map(u256_to_big_decimal),
             },
         )
         .await
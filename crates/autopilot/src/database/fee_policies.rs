@@ -44,6 +44,22 @@ pub async fn fetch(
     Ok(rows)
 }
 
+/// Fetches the fee policies of all orders of an auction.
+pub async fn fetch_by_auction(
+    ex: &mut PgConnection,
+    auction_id: dto::AuctionId,
+) -> Result<Vec<dto::FeePolicy>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT * FROM fee_policies
+        WHERE auction_id = $1
+        ORDER BY order_uid, application_order
+    "#;
+    sqlx::query_as::<_, dto::FeePolicy>(QUERY)
+        .bind(auction_id)
+        .fetch_all(ex)
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, database::byte_array::ByteArray, sqlx::Connection};
@@ -98,5 +114,9 @@ mod tests {
 
         let output = fetch(&mut db, 1, order_uid).await.unwrap();
         assert_eq!(output, vec![fee_policy_1, fee_policy_2, fee_policy_3]);
+
+        let output = fetch_by_auction(&mut db, 1).await.unwrap();
+        assert_eq!(output.len(), 3);
+        assert!(fetch_by_auction(&mut db, 2).await.unwrap().is_empty());
     }
 }
@@ -1,9 +1,26 @@
 use {
+    crate::decoded_settlement::{InteractionSummary, JitTrade, Metadata},
     anyhow::{Context, Result},
-    database::{byte_array::ByteArray, settlement_observations::Observation},
-    ethcontract::U256,
+    chrono::{DateTime, Utc},
+    database::{
+        byte_array::ByteArray,
+        events::EventIndex,
+        jit_orders::JitOrder,
+        late_settled_cancellations::LateSettledCancellation,
+        settlement_interactions::Interaction,
+        settlement_observations::Observation,
+        settlements::SettlementMetadata,
+        unexpected_settlement_submitters::UnexpectedSubmitter,
+    },
+    ethcontract::{H160, H256, U256},
     model::order::OrderUid,
     number::conversions::u256_to_big_decimal,
+    shared::db_order_conversions::{
+        buy_token_destination_into,
+        order_kind_into,
+        sell_token_source_into,
+        signing_scheme_into,
+    },
     sqlx::PgConnection,
 };
 
@@ -16,29 +33,136 @@ pub struct AuctionData {
     pub gas_used: U256,
     pub effective_gas_price: U256,
     pub surplus: U256,
+    /// Total fee in the native token. Always `network_fee + protocol_fee`.
     pub fee: U256,
-    pub order_executions: Vec<(OrderUid, ExecutedFee)>,
+    /// Part of the fee covering the gas costs.
+    pub network_fee: U256,
+    /// Part of the fee charged by protocol fee policies.
+    pub protocol_fee: U256,
+    pub order_executions: Vec<OrderExecution>,
+    /// Executed and internalized interactions in execution order.
+    pub interactions: Vec<InteractionSummary>,
+    /// `effective_gas_price` divided by the gas price assumed when ranking the
+    /// solutions. `None` if the gas price of the auction is unknown.
+    pub gas_price_ratio: Option<f64>,
+    /// Trades of orders that were not in the orderbook.
+    pub jit_orders: Vec<JitTrade>,
+    /// Fee a rollup charged for posting the settlement data to L1. Zero on
+    /// chains that are not rollups.
+    pub l1_fee: U256,
+    /// Total cost of the settlement in wei, i.e. the execution cost plus the
+    /// L1 data fee.
+    pub total_cost: U256,
+    /// Executed orders that were cancelled before the block of the settlement.
+    pub late_cancellations: Vec<LateCancellation>,
+}
+
+/// An order that got settled although it was cancelled before the block of the
+/// settlement, i.e. after the auction containing it was cut.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LateCancellation {
+    pub order: OrderUid,
+    /// The account that submitted the settlement.
+    pub solver: H160,
+    pub cancelled_at: DateTime<Utc>,
+    pub settled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OrderExecution {
+    pub order: OrderUid,
+    pub executed_fee: ExecutedFee,
+    /// Only set if the executed amounts could be computed from the calldata.
+    pub amounts: Option<ExecutedAmounts>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExecutedAmounts {
+    /// Sell token amount transferred from the owner including the fee.
+    pub sell_amount: U256,
+    pub buy_amount: U256,
+    /// Signed fee in the sell token.
+    pub fee_amount: U256,
+    pub sell_price: U256,
+    pub buy_price: U256,
+    /// Surplus in the native token.
+    pub surplus: U256,
+    /// Total fee in the native token.
+    pub native_fee: U256,
+    /// Part of `native_fee` covering the gas costs.
+    pub network_fee: U256,
+    /// Part of `native_fee` charged by protocol fee policies.
+    pub protocol_fee: U256,
 }
 
 #[derive(Debug, Clone)]
 pub struct SettlementUpdate {
     pub block_number: i64,
     pub log_index: i64,
+    /// Hash of the transaction that emitted the settlement event.
+    pub tx_hash: H256,
+    /// Sender of the settlement transaction.
+    pub tx_from: H160,
+    /// Nonce of the settlement transaction.
+    pub tx_nonce: i64,
     pub auction_id: AuctionId,
     /// Only set if the auction is for this environment.
     pub auction_data: Option<AuctionData>,
+    /// The winner of the auction. Only set if the settlement was submitted by
+    /// a different account.
+    pub expected_winner: Option<H160>,
+    /// The auction id the calldata claimed although a different settlement
+    /// already settled that auction. `auction_id` is the default id (0) then.
+    pub duplicate_auction_claim: Option<AuctionId>,
+    /// The metadata appended to the calldata if there was any.
+    pub metadata: Option<Metadata>,
 }
 
 impl super::Postgres {
+    /// Stores the details of a settlement event. Returns `false` without
+    /// updating anything if the event is no longer part of the indexed chain
+    /// (e.g. because its block got reorged) or was already updated. This makes
+    /// it safe to retry an update whose commit seemingly failed.
     pub async fn update_settlement_details(
         ex: &mut PgConnection,
         settlement_update: SettlementUpdate,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["update_settlement_details"])
             .start_timer();
 
+        let current = database::settlements::lock_for_update(
+            ex,
+            settlement_update.block_number,
+            settlement_update.log_index,
+            &ByteArray(settlement_update.tx_hash.0),
+        )
+        .await
+        .context("lock_settlement_for_update")?;
+        if !current {
+            return Ok(false);
+        }
+
+        // Another settlement might have been associated with the auction since
+        // the auction data was computed. Failing the update makes the next
+        // attempt record this settlement as a duplicate claim instead.
+        if settlement_update.auction_data.is_some() {
+            let settled = database::settlements::settled_by_other(
+                ex,
+                settlement_update.auction_id,
+                settlement_update.block_number,
+                settlement_update.log_index,
+            )
+            .await
+            .context("settled_by_other")?;
+            anyhow::ensure!(
+                !settled,
+                "auction {} was already settled by a different settlement",
+                settlement_update.auction_id
+            );
+        }
+
         // update settlements
         database::settlements::update_settlement_auction(
             ex,
@@ -48,34 +172,566 @@ impl super::Postgres {
         )
         .await
         .context("insert_settlement_tx_info")?;
+        database::settlements::update_settlement_sender(
+            ex,
+            settlement_update.block_number,
+            settlement_update.log_index,
+            &ByteArray(settlement_update.tx_from.0),
+            settlement_update.tx_nonce,
+        )
+        .await
+        .context("update_settlement_sender")?;
+        database::settlements::update_settlement_replaced(
+            ex,
+            settlement_update.block_number,
+            settlement_update.log_index,
+            settlement_update.auction_id,
+        )
+        .await
+        .context("update_settlement_replaced")?;
 
-        if let Some(auction_data) = settlement_update.auction_data {
-            database::settlement_observations::upsert(
+        if let Some(metadata) = &settlement_update.metadata {
+            database::settlements::update_settlement_metadata(
                 ex,
-                Observation {
+                settlement_update.block_number,
+                settlement_update.log_index,
+                &settlement_metadata(metadata),
+            )
+            .await
+            .context("update_settlement_metadata")?;
+        }
+
+        if let Some(claimed) = settlement_update.duplicate_auction_claim {
+            database::settlements::update_duplicate_auction_claim(
+                ex,
+                settlement_update.block_number,
+                settlement_update.log_index,
+                claimed,
+            )
+            .await
+            .context("update_duplicate_auction_claim")?;
+        }
+
+        if let Some(expected_winner) = settlement_update.expected_winner {
+            database::unexpected_settlement_submitters::insert(
+                ex,
+                &UnexpectedSubmitter {
                     block_number: settlement_update.block_number,
                     log_index: settlement_update.log_index,
-                    gas_used: u256_to_big_decimal(&auction_data.gas_used),
-                    effective_gas_price: u256_to_big_decimal(&auction_data.effective_gas_price),
-                    surplus: u256_to_big_decimal(&auction_data.surplus),
-                    fee: u256_to_big_decimal(&auction_data.fee),
+                    auction_id: settlement_update.auction_id,
+                    expected_winner: ByteArray(expected_winner.0),
+                    actual_sender: ByteArray(settlement_update.tx_from.0),
                 },
             )
             .await
-            .context("insert_settlement_observations")?;
+            .context("insert_unexpected_settlement_submitter")?;
+        }
 
-            for (order, executed_fee) in auction_data.order_executions {
-                database::order_execution::save(
-                    ex,
-                    &ByteArray(order.0),
-                    settlement_update.auction_id,
-                    settlement_update.block_number,
-                    &u256_to_big_decimal(&executed_fee),
-                )
-                .await
-                .context("save_order_executions")?;
-            }
+        if let Some(auction_data) = settlement_update.auction_data {
+            store_auction_data(
+                ex,
+                settlement_update.block_number,
+                settlement_update.log_index,
+                settlement_update.auction_id,
+                auction_data,
+            )
+            .await?;
+        }
+        Ok(true)
+    }
+
+    /// Stores recomputed auction data of a settlement event that was already
+    /// associated with the auction. Existing observations and executions get
+    /// overwritten. Returns `false` without updating anything if the event is
+    /// no longer part of the indexed chain or belongs to a different auction.
+    pub async fn backfill_auction_data(
+        ex: &mut PgConnection,
+        event: &EventIndex,
+        tx_hash: H256,
+        auction_id: AuctionId,
+        auction_data: AuctionData,
+    ) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["backfill_auction_data"])
+            .start_timer();
+
+        let current = database::settlements::lock_updated(
+            ex,
+            event.block_number,
+            event.log_index,
+            &ByteArray(tx_hash.0),
+            auction_id,
+        )
+        .await
+        .context("lock_updated_settlement")?;
+        if !current {
+            return Ok(false);
+        }
+        store_auction_data(
+            ex,
+            event.block_number,
+            event.log_index,
+            auction_id,
+            auction_data,
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+async fn store_auction_data(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    auction_id: AuctionId,
+    auction_data: AuctionData,
+) -> Result<()> {
+    database::settlement_observations::upsert(
+        ex,
+        Observation {
+            block_number,
+            log_index,
+            gas_used: u256_to_big_decimal(&auction_data.gas_used),
+            effective_gas_price: u256_to_big_decimal(&auction_data.effective_gas_price),
+            surplus: u256_to_big_decimal(&auction_data.surplus),
+            fee: u256_to_big_decimal(&auction_data.fee),
+            network_fee: Some(u256_to_big_decimal(&auction_data.network_fee)),
+            protocol_fee: Some(u256_to_big_decimal(&auction_data.protocol_fee)),
+            gas_price_ratio: auction_data.gas_price_ratio,
+            l1_fee: u256_to_big_decimal(&auction_data.l1_fee),
+            total_cost: u256_to_big_decimal(&auction_data.total_cost),
+        },
+    )
+    .await
+    .context("insert_settlement_observations")?;
+
+    for execution in auction_data.order_executions {
+        let order = ByteArray(execution.order.0);
+        database::order_execution::save(
+            ex,
+            &order,
+            auction_id,
+            block_number,
+            &u256_to_big_decimal(&execution.executed_fee),
+        )
+        .await
+        .context("save_order_executions")?;
+
+        if let Some(amounts) = execution.amounts {
+            database::order_execution::save_executed_amounts(
+                ex,
+                &order,
+                auction_id,
+                &database::order_execution::ExecutedAmounts {
+                    executed_sell_amount: u256_to_big_decimal(&amounts.sell_amount),
+                    executed_buy_amount: u256_to_big_decimal(&amounts.buy_amount),
+                    executed_fee_amount: u256_to_big_decimal(&amounts.fee_amount),
+                    sell_price: u256_to_big_decimal(&amounts.sell_price),
+                    buy_price: u256_to_big_decimal(&amounts.buy_price),
+                    surplus: u256_to_big_decimal(&amounts.surplus),
+                    native_fee: u256_to_big_decimal(&amounts.native_fee),
+                    network_fee: Some(u256_to_big_decimal(&amounts.network_fee)),
+                    protocol_fee: Some(u256_to_big_decimal(&amounts.protocol_fee)),
+                },
+            )
+            .await
+            .context("save_executed_amounts")?;
         }
-        Ok(())
+    }
+
+    let interactions: Vec<_> = auction_data
+        .interactions
+        .iter()
+        .enumerate()
+        .map(|(index, interaction)| Interaction {
+            index: index as i64,
+            stage: interaction.stage as i16,
+            target: ByteArray(interaction.target.0),
+            value: u256_to_big_decimal(&interaction.value),
+            selector: interaction.selector.map(ByteArray),
+            call_data_len: interaction.call_data_len as i64,
+            call_data: interaction.call_data.clone(),
+            internalized: interaction.internalized,
+        })
+        .collect();
+    let event = EventIndex {
+        block_number,
+        log_index,
+    };
+    database::settlement_interactions::insert(ex, &event, &interactions)
+        .await
+        .context("insert_settlement_interactions")?;
+
+    let jit_orders: Vec<_> = auction_data.jit_orders.iter().map(jit_order).collect();
+    database::jit_orders::insert(ex, &event, &jit_orders)
+        .await
+        .context("insert_jit_orders")?;
+
+    let late_cancellations: Vec<_> = auction_data
+        .late_cancellations
+        .iter()
+        .map(|cancellation| LateSettledCancellation {
+            order_uid: ByteArray(cancellation.order.0),
+            auction_id,
+            solver: ByteArray(cancellation.solver.0),
+            cancellation_timestamp: cancellation.cancelled_at,
+            settlement_timestamp: cancellation.settled_at,
+        })
+        .collect();
+    database::late_settled_cancellations::insert(ex, &event, &late_cancellations)
+        .await
+        .context("insert_late_settled_cancellations")?;
+    Ok(())
+}
+
+fn settlement_metadata(metadata: &Metadata) -> SettlementMetadata {
+    let (solution_id, solver_flags) = match metadata {
+        Metadata::V1(metadata) => (metadata.solution_id, metadata.solver_flags),
+        Metadata::V0 { .. } | Metadata::Unknown { .. } => (None, None),
+    };
+    SettlementMetadata {
+        version: metadata.version().into(),
+        solution_id: solution_id.map(Into::into),
+        solver_flags: solver_flags.map(Into::into),
+    }
+}
+
+fn jit_order(trade: &JitTrade) -> JitOrder {
+    let order = &trade.order;
+    JitOrder {
+        trade_index: trade.index as i64,
+        uid: trade.uid.map(|uid| ByteArray(uid.0)),
+        owner: trade.owner.map(|owner| ByteArray(owner.0)),
+        sell_token: ByteArray(order.sell_token.0),
+        buy_token: ByteArray(order.buy_token.0),
+        receiver: ByteArray(order.receiver.unwrap_or_default().0),
+        sell_amount: u256_to_big_decimal(&order.sell_amount),
+        buy_amount: u256_to_big_decimal(&order.buy_amount),
+        valid_to: order.valid_to.into(),
+        app_data: ByteArray(order.app_data.0),
+        fee_amount: u256_to_big_decimal(&order.fee_amount),
+        kind: order_kind_into(order.kind),
+        partially_fillable: order.partially_fillable,
+        signing_scheme: signing_scheme_into(trade.signing_scheme),
+        signature: trade.signature.clone(),
+        sell_token_balance: sell_token_source_into(order.sell_token_balance),
+        buy_token_balance: buy_token_destination_into(order.buy_token_balance),
+        executed_sell_amount: trade
+            .executed
+            .as_ref()
+            .map(|executed| u256_to_big_decimal(&executed.sell_amount)),
+        executed_buy_amount: trade
+            .executed
+            .as_ref()
+            .map(|executed| u256_to_big_decimal(&executed.buy_amount)),
+        executed_fee_amount: trade
+            .executed
+            .as_ref()
+            .map(|executed| u256_to_big_decimal(&executed.fee_amount)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::database::Postgres,
+        chrono::TimeZone,
+        contracts::gpv2_settlement::{event_data::Settlement, Event as ContractEvent},
+        ethcontract::{Event as EthContractEvent, EventMetadata},
+        ethrpc::current_block::RangeInclusive,
+        shared::event_handling::EventStoring,
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_reorged_settlement_is_updated_again() {
+        let mut db = Postgres::with_defaults().await.unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let tx_hash = H256::from_low_u64_be(1);
+        let settlement = |block_number| EthContractEvent {
+            data: ContractEvent::Settlement(Settlement {
+                solver: H160::from_low_u64_be(2),
+            }),
+            meta: Some(EventMetadata {
+                block_number,
+                log_index: 0,
+                transaction_hash: tx_hash,
+                ..Default::default()
+            }),
+        };
+        let update = |block_number| SettlementUpdate {
+            block_number,
+            log_index: 0,
+            tx_hash,
+            tx_from: H160::from_low_u64_be(2),
+            tx_nonce: 0,
+            auction_id: 1,
+            auction_data: Some(AuctionData {
+                order_executions: vec![OrderExecution {
+                    amounts: Some(Default::default()),
+                    ..Default::default()
+                }],
+                interactions: vec![InteractionSummary {
+                    stage: 1,
+                    target: H160::from_low_u64_be(3),
+                    value: 0.into(),
+                    selector: None,
+                    call_data_len: 0,
+                    call_data: vec![],
+                    internalized: true,
+                }],
+                ..Default::default()
+            }),
+            expected_winner: None,
+            duplicate_auction_claim: None,
+            metadata: None,
+        };
+        let order = Default::default();
+        let event = |block_number| EventIndex {
+            block_number,
+            log_index: 0,
+        };
+
+        db.append_events(vec![settlement(1)]).await.unwrap();
+        let mut ex = db.pool.begin().await.unwrap();
+        assert!(Postgres::update_settlement_details(&mut ex, update(1))
+            .await
+            .unwrap());
+        ex.commit().await.unwrap();
+
+        let mut ex = db.pool.acquire().await.unwrap();
+        let observation = database::settlement_observations::fetch(&mut ex, &ByteArray(tx_hash.0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(observation.block_number, 1);
+        assert!(
+            database::order_execution::executed_amounts(&mut ex, &order, 1)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let interactions = database::settlement_interactions::fetch(&mut ex, &event(1))
+            .await
+            .unwrap();
+        assert_eq!(interactions.len(), 1);
+        assert!(interactions[0].internalized);
+
+        // The block with the settlement gets reorged and the settlement is gone
+        // together with its details.
+        db.replace_events(vec![], RangeInclusive::try_new(1, 2).unwrap())
+            .await
+            .unwrap();
+        let mut ex = db.pool.acquire().await.unwrap();
+        assert!(
+            database::settlement_observations::fetch(&mut ex, &ByteArray(tx_hash.0))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            database::order_execution::executed_amounts(&mut ex, &order, 1)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(database::settlement_interactions::fetch(&mut ex, &event(1))
+            .await
+            .unwrap()
+            .is_empty());
+        // Updates computed before the reorg are rejected.
+        assert!(!Postgres::update_settlement_details(&mut ex, update(1))
+            .await
+            .unwrap());
+
+        // The transaction gets included in a later block and is updated again.
+        db.replace_events(vec![settlement(2)], RangeInclusive::try_new(2, 2).unwrap())
+            .await
+            .unwrap();
+        let mut ex = db.pool.acquire().await.unwrap();
+        let pending = database::settlements::get_settlements_without_auction(&mut ex, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].block_number, 2);
+        assert!(Postgres::update_settlement_details(&mut ex, update(2))
+            .await
+            .unwrap());
+        let observation = database::settlement_observations::fetch(&mut ex, &ByteArray(tx_hash.0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(observation.block_number, 2);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_duplicate_auction_claim_keeps_original_association() {
+        let mut db = Postgres::with_defaults().await.unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let settlement = |block_number: u64| EthContractEvent {
+            data: ContractEvent::Settlement(Settlement {
+                solver: H160::from_low_u64_be(2),
+            }),
+            meta: Some(EventMetadata {
+                block_number,
+                log_index: 0,
+                transaction_hash: H256::from_low_u64_be(block_number),
+                ..Default::default()
+            }),
+        };
+        let update = |block_number: u64| SettlementUpdate {
+            block_number: block_number as i64,
+            log_index: 0,
+            tx_hash: H256::from_low_u64_be(block_number),
+            tx_from: H160::from_low_u64_be(2),
+            tx_nonce: block_number as i64,
+            auction_id: 1,
+            auction_data: Some(Default::default()),
+            expected_winner: None,
+            duplicate_auction_claim: None,
+            metadata: Some(Metadata::V0 { auction_id: 1 }),
+        };
+        db.append_events(vec![settlement(1), settlement(2)])
+            .await
+            .unwrap();
+
+        let mut ex = db.pool.begin().await.unwrap();
+        assert!(Postgres::update_settlement_details(&mut ex, update(1))
+            .await
+            .unwrap());
+        ex.commit().await.unwrap();
+
+        // The second settlement claims the same auction but its auction data
+        // was computed before the first one got associated with it.
+        let mut ex = db.pool.begin().await.unwrap();
+        assert!(Postgres::update_settlement_details(&mut ex, update(2))
+            .await
+            .is_err());
+        drop(ex);
+
+        // The next attempt records the duplicate claim.
+        let duplicate = SettlementUpdate {
+            auction_id: 0,
+            auction_data: None,
+            duplicate_auction_claim: Some(1),
+            ..update(2)
+        };
+        let mut ex = db.pool.begin().await.unwrap();
+        assert!(Postgres::update_settlement_details(&mut ex, duplicate)
+            .await
+            .unwrap());
+        ex.commit().await.unwrap();
+
+        let mut ex = db.pool.acquire().await.unwrap();
+        let settlements = database::settlements::updated_settlements(&mut ex, 1..3)
+            .await
+            .unwrap();
+        let auctions: Vec<_> = settlements
+            .iter()
+            .map(|s| (s.block_number, s.auction_id, s.observed))
+            .collect();
+        assert_eq!(auctions, [(1, 1, true), (2, 0, false)]);
+        let claim: Option<i64> = sqlx::query_scalar(
+            "SELECT duplicate_auction_claim FROM settlements WHERE block_number = 2",
+        )
+        .fetch_one(&mut *ex)
+        .await
+        .unwrap();
+        assert_eq!(claim, Some(1));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_late_cancellation_is_recorded() {
+        let mut db = Postgres::with_defaults().await.unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let cancelled_at = Utc.timestamp_opt(1_000, 0).unwrap();
+        let settled_at = Utc.timestamp_opt(1_012, 0).unwrap();
+        let mut ex = db.pool.acquire().await.unwrap();
+        database::orders::insert_order(
+            &mut ex,
+            &database::orders::Order {
+                uid: ByteArray([1; 56]),
+                cancellation_timestamp: Some(cancelled_at),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        drop(ex);
+
+        let tx_hash = H256::from_low_u64_be(1);
+        let solver = H160::from_low_u64_be(2);
+        db.append_events(vec![EthContractEvent {
+            data: ContractEvent::Settlement(Settlement { solver }),
+            meta: Some(EventMetadata {
+                block_number: 1,
+                log_index: 0,
+                transaction_hash: tx_hash,
+                ..Default::default()
+            }),
+        }])
+        .await
+        .unwrap();
+
+        let mut ex = db.pool.begin().await.unwrap();
+        let cancelled = database::late_settled_cancellations::cancelled_before(
+            &mut ex,
+            &[ByteArray([1; 56])],
+            settled_at,
+        )
+        .await
+        .unwrap();
+        let update = SettlementUpdate {
+            block_number: 1,
+            log_index: 0,
+            tx_hash,
+            tx_from: solver,
+            tx_nonce: 0,
+            auction_id: 3,
+            auction_data: Some(AuctionData {
+                late_cancellations: cancelled
+                    .into_iter()
+                    .map(|order| LateCancellation {
+                        order: OrderUid(order.uid.0),
+                        solver,
+                        cancelled_at: order.cancellation_timestamp,
+                        settled_at,
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            expected_winner: None,
+            duplicate_auction_claim: None,
+            metadata: None,
+        };
+        assert!(Postgres::update_settlement_details(&mut ex, update)
+            .await
+            .unwrap());
+        ex.commit().await.unwrap();
+
+        let mut ex = db.pool.acquire().await.unwrap();
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 0,
+        };
+        assert_eq!(
+            database::late_settled_cancellations::fetch(&mut ex, &event)
+                .await
+                .unwrap(),
+            [LateSettledCancellation {
+                order_uid: ByteArray([1; 56]),
+                auction_id: 3,
+                solver: ByteArray(solver.0),
+                cancellation_timestamp: cancelled_at,
+                settlement_timestamp: settled_at,
+            }]
+        );
     }
 }
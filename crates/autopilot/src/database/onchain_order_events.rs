@@ -22,7 +22,7 @@ use {
         orders::{insert_quotes, Order, OrderClass},
         PgTransaction,
     },
-    ethcontract::{Event as EthContractEvent, H160},
+    ethcontract::{Event as EthContractEvent, H160, H256},
     ethrpc::{
         current_block::{timestamp_of_block_in_seconds, RangeInclusive},
         Web3,
@@ -31,7 +31,14 @@ use {
     itertools::multiunzip,
     model::{
         app_data::AppDataHash,
-        order::{BuyTokenDestination, OrderData, OrderKind, OrderUid, SellTokenSource},
+        order::{
+            BuyTokenDestination,
+            OrderData,
+            OrderKind,
+            OrderSource,
+            OrderUid,
+            SellTokenSource,
+        },
         signature::SigningScheme,
         DomainSeparator,
     },
@@ -40,6 +47,7 @@ use {
         db_order_conversions::{
             buy_token_destination_into,
             order_kind_into,
+            order_source_into,
             sell_token_source_into,
             signing_scheme_into,
         },
@@ -475,6 +483,7 @@ async fn parse_general_onchain_order_placement_data<'a>(
             let quote_result = get_quote(quoter, order_data, signing_scheme, &quote_id).await;
             let order_data = convert_onchain_order_placement(
                 &event,
+                meta.transaction_hash,
                 event_timestamp,
                 quote_result.clone(),
                 order_data,
@@ -493,6 +502,9 @@ async fn parse_general_onchain_order_placement_data<'a>(
                     sell_amount: u256_to_big_decimal(&quote.sell_amount),
                     buy_amount: u256_to_big_decimal(&quote.buy_amount),
                     solver: ByteArray(quote.data.solver.0),
+                    quote_id: quote.id,
+                    expiration_timestamp: Some(quote.data.expiration),
+                    verified: quote.data.verified,
                 }),
                 Err(err) => {
                     let err_label = err.to_metrics_label();
@@ -569,14 +581,21 @@ async fn get_quote(
         true => None,
         false => Some(order_data.fee_amount),
     };
-    get_quote_and_check_fee(quoter, &parameters.clone(), Some(*quote_id), fee_amount)
-        .await
-        .map_err(onchain_order_placement_error_from)
+    get_quote_and_check_fee(
+        quoter,
+        &parameters.clone(),
+        Some(*quote_id),
+        false,
+        fee_amount,
+    )
+    .await
+    .map_err(onchain_order_placement_error_from)
 }
 
 #[allow(clippy::too_many_arguments)]
 fn convert_onchain_order_placement(
     order_placement: &ContractOrderPlacement,
+    tx_hash: H256,
     event_timestamp: i64,
     quote: Result<Quote, OnchainOrderPlacementError>,
     order_data: OrderData,
@@ -624,6 +643,8 @@ fn convert_onchain_order_placement(
             true => OrderClass::Limit,
             false => OrderClass::Market,
         },
+        balance_check_skipped: false,
+        source: Some(order_source_into(&OrderSource::Onchain { tx_hash })),
     };
     let onchain_order_placement_event = OnchainOrderPlacement {
         order_uid: ByteArray(order_uid.0),
@@ -722,7 +743,7 @@ mod test {
             order_quoting::{FindQuoteError, MockOrderQuoting, Quote, QuoteData},
         },
         sqlx::PgPool,
-        std::num::NonZeroUsize,
+        std::num::{NonZeroU64, NonZeroUsize},
     };
 
     #[test]
@@ -878,8 +899,10 @@ mod test {
         let order_uid = OrderUid([9u8; 56]);
         let signing_scheme = SigningScheme::Eip1271;
         let event_timestamp = 234354345;
+        let tx_hash = H256([10u8; 32]);
         let (onchain_order_placement, order) = convert_onchain_order_placement(
             &order_placement,
+            tx_hash,
             event_timestamp,
             Ok(quote),
             order_data,
@@ -931,6 +954,8 @@ mod test {
             buy_token_balance: buy_token_destination_into(expected_order_data.buy_token_balance),
             full_fee_amount: u256_to_big_decimal(&expected_order_data.fee_amount),
             cancellation_timestamp: None,
+            balance_check_skipped: false,
+            source: Some(order_source_into(&OrderSource::Onchain { tx_hash })),
         };
         assert_eq!(onchain_order_placement, expected_onchain_order_placement);
         assert_eq!(order, expected_order);
@@ -989,8 +1014,10 @@ mod test {
         };
         let order_uid = OrderUid([9u8; 56]);
         let signing_scheme = SigningScheme::Eip1271;
+        let tx_hash = H256([10u8; 32]);
         let (onchain_order_placement, order) = convert_onchain_order_placement(
             &order_placement,
+            tx_hash,
             345634,
             Ok(quote),
             order_data,
@@ -1042,6 +1069,8 @@ mod test {
             buy_token_balance: buy_token_destination_into(expected_order_data.buy_token_balance),
             full_fee_amount: u256_to_big_decimal(&U256::zero()),
             cancellation_timestamp: None,
+            balance_check_skipped: false,
+            source: Some(order_source_into(&OrderSource::Onchain { tx_hash })),
         };
         assert_eq!(onchain_order_placement, expected_onchain_order_placement);
         assert_eq!(order, expected_order);
@@ -1235,6 +1264,7 @@ mod test {
                 pool: PgPool::connect_lazy("postgresql://").unwrap(),
                 config: Config {
                     insert_batch_size: NonZeroUsize::new(500).unwrap(),
+                    auction_retention: NonZeroU64::new(1).unwrap(),
                 },
             },
             web3,
@@ -1275,6 +1305,9 @@ mod test {
             sell_amount: u256_to_big_decimal(&quote.sell_amount),
             buy_amount: u256_to_big_decimal(&quote.buy_amount),
             solver: ByteArray(quote.data.solver.0),
+            quote_id: quote.id,
+            expiration_timestamp: Some(quote.data.expiration),
+            verified: quote.data.verified,
         };
         assert_eq!(result.1, vec![Some(expected_quote)]);
         assert_eq!(
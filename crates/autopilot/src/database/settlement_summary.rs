@@ -0,0 +1,20 @@
+use {anyhow::Context, database::settlements::SettlementSummary};
+
+impl super::Postgres {
+    /// Summarizes the settlement of an auction. `None` if the auction was not
+    /// settled or the settlement was not observed yet.
+    pub async fn settlement_summary(
+        &self,
+        auction_id: i64,
+    ) -> anyhow::Result<Option<SettlementSummary>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settlement_summary"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await.context("acquire")?;
+        database::settlements::settlement_summary(&mut ex, auction_id)
+            .await
+            .context("settlement_summary")
+    }
+}
@@ -2,6 +2,7 @@
 //! GPv2Settlement::settle function.
 
 use {
+    crate::domain::fee::Policy,
     anyhow::{Context, Result},
     bigdecimal::{Signed, Zero},
     contracts::GPv2Settlement,
@@ -12,9 +13,10 @@ use {
         signature::{Signature, SigningScheme},
         DomainSeparator,
     },
-    num::BigRational,
+    num::{BigRational, One},
     number::conversions::{big_rational_to_u256, u256_to_big_rational},
     shared::{conversions::U256Ext, external_prices::ExternalPrices},
+    std::collections::{HashMap, HashSet},
     web3::ethabi::{Function, Token},
 };
 
@@ -48,7 +50,97 @@ pub struct DecodedSettlement {
     /// Data that was appended to the regular call data of the `settle()` call
     /// as a form of on-chain meta data. This gets used to associated a
     /// settlement with an auction.
-    pub metadata: Option<Bytes<[u8; Self::META_DATA_LEN]>>,
+    pub metadata: Option<Metadata>,
+}
+
+/// Versioned trailer appended to the `settle()` call data.
+///
+/// Version 0 is the auction id as 8 big-endian bytes. Later versions end with
+/// the version byte which is preceded by the length of the payload as 2
+/// big-endian bytes and the payload itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Metadata {
+    V0 {
+        auction_id: i64,
+    },
+    /// The payload is a CBOR encoded map.
+    V1(MetadataV1),
+    /// A trailer of a version this code does not know how to parse.
+    Unknown {
+        version: u8,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataV1 {
+    pub auction_id: i64,
+    /// Id of the solution within the solver's submissions for the auction.
+    pub solution_id: Option<u64>,
+    /// Solver defined flags.
+    pub solver_flags: Option<u32>,
+}
+
+impl Metadata {
+    /// `None` if the version of the trailer is unknown.
+    pub fn auction_id(&self) -> Option<i64> {
+        match self {
+            Metadata::V0 { auction_id } => Some(*auction_id),
+            Metadata::V1(metadata) => Some(metadata.auction_id),
+            Metadata::Unknown { .. } => None,
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            Metadata::V0 { .. } => 0,
+            Metadata::V1(_) => 1,
+            Metadata::Unknown { version } => *version,
+        }
+    }
+
+    /// Returns the trailers `data` could end with together with their length,
+    /// most specific first. The ABI encoded call data preceding a trailer is
+    /// always a multiple of 32 bytes long.
+    fn candidates(data: &[u8]) -> Vec<(usize, Self)> {
+        let versioned = Self::versioned(data);
+        let mut candidates = Vec::new();
+        // A version 1 trailer only gets accepted if its payload decodes so it
+        // can't be mistaken for an auction id.
+        if let Some((len, metadata @ Metadata::V1(_))) = &versioned {
+            candidates.push((*len, metadata.clone()));
+        }
+        if data.len() % 32 == DecodedSettlement::META_DATA_LEN {
+            let (_, auction_id) = data.split_at(data.len() - DecodedSettlement::META_DATA_LEN);
+            candidates.push((
+                DecodedSettlement::META_DATA_LEN,
+                Metadata::V0 {
+                    auction_id: i64::from_be_bytes(auction_id.try_into().unwrap()),
+                },
+            ));
+        }
+        if let Some((len, metadata @ Metadata::Unknown { .. })) = versioned {
+            candidates.push((len, metadata));
+        }
+        candidates
+    }
+
+    /// Parses the trailer of version 1 or later at the end of `data`.
+    fn versioned(data: &[u8]) -> Option<(usize, Self)> {
+        let (&version, rest) = data.split_last()?;
+        let (rest, payload_len) = rest.split_at(rest.len().checked_sub(2)?);
+        let payload_len = usize::from(u16::from_be_bytes(payload_len.try_into().unwrap()));
+        let payload = rest.get(rest.len().checked_sub(payload_len)?..)?;
+        let len = payload_len + 3;
+        if version == 0 || (data.len() - len) % 32 != 0 {
+            return None;
+        }
+        let metadata = match version {
+            1 => Metadata::V1(ciborium::de::from_reader(payload).ok()?),
+            version => Metadata::Unknown { version },
+        };
+        Some((len, metadata))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -74,9 +166,24 @@ impl DecodedTrade {
 
     /// Returns the order uid of the order associated with this trade.
     pub fn uid(&self, domain_separator: &DomainSeparator, tokens: &[Address]) -> Result<OrderUid> {
-        let order = OrderData {
-            sell_token: tokens[self.sell_token_index.as_u64() as usize],
-            buy_token: tokens[self.buy_token_index.as_u64() as usize],
+        let order = self.order_data(tokens)?;
+        let owner = self.owner(domain_separator, &order)?;
+        Ok(order.uid(domain_separator, &owner))
+    }
+
+    /// Returns the signed order data of the order associated with this trade.
+    /// Fails if the trade references tokens that aren't part of the
+    /// settlement.
+    fn order_data(&self, tokens: &[Address]) -> Result<OrderData> {
+        let token = |index: U256| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| tokens.get(index).copied())
+                .with_context(|| format!("token index {index} out of range"))
+        };
+        Ok(OrderData {
+            sell_token: token(self.sell_token_index)?,
+            buy_token: token(self.buy_token_index)?,
             sell_amount: self.sell_amount,
             buy_amount: self.buy_amount,
             valid_to: self.valid_to,
@@ -87,13 +194,15 @@ impl DecodedTrade {
             receiver: Some(self.receiver),
             sell_token_balance: self.flags.sell_token_balance(),
             buy_token_balance: self.flags.buy_token_balance(),
-        };
-        let owner = self
-            .signature()
+        })
+    }
+
+    /// Recovers the owner of the order from the signature.
+    fn owner(&self, domain_separator: &DomainSeparator, order: &OrderData) -> Result<Address> {
+        self.signature()
             .context("signature is invalid")?
             .recover_owner(&self.signature.0, domain_separator, &order.hash_struct())
-            .context("cant recover owner")?;
-        Ok(order.uid(domain_separator, &owner))
+            .context("cant recover owner")
     }
 }
 
@@ -174,7 +283,7 @@ impl From<(Address, U256, Bytes<Vec<u8>>)> for DecodedInteraction {
 
 impl DecodedSettlement {
     /// Number of bytes that may be appended to the calldata to store an auction
-    /// id.
+    /// id without a version (i.e. version 0).
     pub const META_DATA_LEN: usize = 8;
 
     pub fn new(input: &[u8]) -> Result<Self, DecodingError> {
@@ -188,24 +297,51 @@ impl DecodedSettlement {
 
         // Decoding calldata without expecting metadata can succeed even if metadata
         // was appended. The other way around would not work so we do that first.
-        if let Ok(decoded) = Self::try_new(without_selector, function, true) {
-            return Ok(decoded);
+        for (len, metadata) in Metadata::candidates(without_selector) {
+            let calldata = &without_selector[..without_selector.len() - len];
+            if let Ok(decoded) = Self::try_new(calldata, function, Some(metadata)) {
+                return Ok(decoded);
+            }
         }
-        Self::try_new(without_selector, function, false).map_err(Into::into)
+        Self::try_new(without_selector, function, None).map_err(Into::into)
     }
 
-    fn try_new(data: &[u8], function: &Function, with_metadata: bool) -> Result<Self> {
-        let metadata_len = if with_metadata {
-            anyhow::ensure!(
-                data.len() % 32 == Self::META_DATA_LEN,
-                "calldata does not contain the expected number of bytes to include metadata"
-            );
-            Self::META_DATA_LEN
-        } else {
-            0
-        };
+    /// Decodes a settlement that was executed through a wrapper contract (e.g.
+    /// a multicall or a multisend) from the input of the transaction.
+    ///
+    /// The `settle` calldata gets located by its selector. Like in ABI encoded
+    /// `bytes` and in the packed encoding of multisend transactions it has to
+    /// be preceded by its length. Since arbitrary data can contain the
+    /// selector the located calldata is only accepted if it contains as many
+    /// trades as the transaction emitted `Trade` events and all of its trades
+    /// reference tokens of the settlement.
+    pub fn from_wrapped_call(input: &[u8], trade_events: usize) -> Result<Self, DecodingError> {
+        let selector = GPv2Settlement::raw_contract()
+            .abi
+            .function("settle")
+            .unwrap()
+            .selector();
+        input
+            .windows(selector.len())
+            .enumerate()
+            .filter(|(start, window)| *window == selector && *start >= 32)
+            .find_map(|(start, _)| {
+                let len = U256::from_big_endian(&input[start - 32..start]);
+                if len > U256::from(input.len() - start) {
+                    return None;
+                }
+                let settlement = Self::new(&input[start..start + len.as_usize()]).ok()?;
+                let valid = settlement.trades.len() == trade_events
+                    && settlement
+                        .trades
+                        .iter()
+                        .all(|trade| trade.order_data(&settlement.tokens).is_ok());
+                valid.then_some(settlement)
+            })
+            .ok_or(DecodingError::InvalidSelector)
+    }
 
-        let (calldata, metadata) = data.split_at(data.len() - metadata_len);
+    fn try_new(calldata: &[u8], function: &Function, metadata: Option<Metadata>) -> Result<Self> {
         let tokenized = function
             .decode_input(calldata)
             .context("tokenizing settlement calldata failed")?;
@@ -233,7 +369,7 @@ impl DecodedSettlement {
                 })
                 .collect(),
             interactions: interactions.map(|inner| inner.into_iter().map(Into::into).collect()),
-            metadata: metadata.try_into().ok().map(Bytes),
+            metadata,
         })
     }
 
@@ -273,20 +409,315 @@ impl DecodedSettlement {
                     }
                 };
 
-                Some(self.fee(trade, order, external_prices).unwrap_or_else(|| {
-                    tracing::warn!("possible incomplete fee calculation");
-                    // we should have an order execution for every trade
-                    Fees {
-                        order,
-                        kind: FeeKind::User,
-                        sell: U256::zero(),
-                        native: U256::zero(),
+                Some(self.trade_fees(trade, order, external_prices))
+            })
+            .collect()
+    }
+
+    /// Returns how every trade of the settlement got executed. The fee
+    /// policies of the orders are needed to tell which part of the fee was
+    /// charged by the protocol.
+    pub fn order_executions(
+        &self,
+        external_prices: &ExternalPrices,
+        domain_separator: &DomainSeparator,
+        fee_policies: &HashMap<OrderUid, Vec<Policy>>,
+    ) -> Vec<OrderExecution> {
+        self.trades
+            .iter()
+            .filter_map(|trade| {
+                let order = match trade.uid(domain_separator, &self.tokens) {
+                    Ok(order) => order,
+                    Err(err) => {
+                        tracing::error!(
+                            ?err,
+                            ?trade,
+                            "failed to calculate order uid, we don't know which order this trade \
+                             belongs to"
+                        );
+                        return None;
+                    }
+                };
+
+                let executed = self.executed_trade(trade, external_prices);
+                if executed.is_none() {
+                    tracing::warn!(?order, "could not compute executed amounts of trade");
+                }
+                let fees = self.trade_fees(trade, order, external_prices);
+                let policies = fee_policies
+                    .get(&order)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let protocol_fee = executed
+                    .as_ref()
+                    .and_then(|executed| {
+                        self.protocol_fee(trade, executed, policies, external_prices)
+                    })
+                    .unwrap_or_else(|| {
+                        tracing::warn!(?order, "could not compute protocol fee of trade");
+                        0.into()
+                    })
+                    // The protocol fee is part of the total fee so the network fee can't get
+                    // negative because of rounding.
+                    .min(fees.native);
+                Some(OrderExecution {
+                    fees,
+                    executed,
+                    protocol_fee,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the uids of the orders the trades belong to. Trades whose owner
+    /// can't be recovered are skipped.
+    pub fn order_uids(&self, domain_separator: &DomainSeparator) -> Vec<OrderUid> {
+        self.trades
+            .iter()
+            .filter_map(|trade| trade.uid(domain_separator, &self.tokens).ok())
+            .collect()
+    }
+
+    /// Reconstructs the orders of trades that are not in the orderbook. These
+    /// were created by the solver just in time (JIT) for the settlement.
+    /// Trades whose owner can't be recovered are always reported as JIT
+    /// orders since they can't belong to a known order.
+    pub fn jit_trades(
+        &self,
+        domain_separator: &DomainSeparator,
+        external_prices: &ExternalPrices,
+        known_orders: &HashSet<OrderUid>,
+    ) -> Vec<JitTrade> {
+        self.trades
+            .iter()
+            .enumerate()
+            .filter_map(|(index, trade)| {
+                let order = match trade.order_data(&self.tokens) {
+                    Ok(order) => order,
+                    Err(err) => {
+                        tracing::warn!(?err, index, "invalid JIT trade");
+                        return None;
+                    }
+                };
+                let owner = match trade.owner(domain_separator, &order) {
+                    Ok(owner) => Some(owner),
+                    Err(err) => {
+                        tracing::debug!(?err, index, "could not recover owner of JIT order");
+                        None
                     }
-                }))
+                };
+                let uid = owner.map(|owner| order.uid(domain_separator, &owner));
+                if uid.is_some_and(|uid| known_orders.contains(&uid)) {
+                    return None;
+                }
+                Some(JitTrade {
+                    index,
+                    uid,
+                    owner,
+                    order,
+                    signing_scheme: trade.flags.signing_scheme(),
+                    signature: trade.signature.0.clone(),
+                    executed: self.executed_trade(trade, external_prices),
+                })
             })
             .collect()
     }
 
+    /// Summarizes the interactions of the settlement in execution order.
+    ///
+    /// `uninternalized` is the settlement as proposed by the solver before its
+    /// internalizable interactions got dropped. Its interactions that are
+    /// missing from this settlement get reported as internalized. If it does
+    /// not match this settlement only the executed interactions get reported.
+    pub fn interaction_summaries(
+        &self,
+        uninternalized: Option<&DecodedSettlement>,
+    ) -> Vec<InteractionSummary> {
+        let mut summaries = Vec::new();
+        for (stage, executed) in self.interactions.iter().enumerate() {
+            let proposed = uninternalized.map(|settlement| &settlement.interactions[stage]);
+            let internalized =
+                proposed.and_then(|proposed| internalized_interactions(proposed, executed));
+            match (proposed, internalized) {
+                (Some(proposed), Some(internalized)) => {
+                    summaries.extend(proposed.iter().zip(internalized).map(
+                        |(interaction, internalized)| {
+                            InteractionSummary::new(stage, interaction, internalized)
+                        },
+                    ))
+                }
+                _ => summaries.extend(
+                    executed
+                        .iter()
+                        .map(|interaction| InteractionSummary::new(stage, interaction, false)),
+                ),
+            }
+        }
+        summaries
+    }
+
+    /// Computes the part of the fee of a trade that was charged because of the
+    /// protocol fee policies of the order in the native token. This reverses
+    /// how the driver applied the protocol fee on top of the solver's fee.
+    fn protocol_fee(
+        &self,
+        trade: &DecodedTrade,
+        executed: &ExecutedTrade,
+        policies: &[Policy],
+        external_prices: &ExternalPrices,
+    ) -> Option<U256> {
+        let Some(policy) = policies.first() else {
+            return Some(0.into());
+        };
+        if policies.len() > 1 {
+            tracing::warn!(?policies, "only the first fee policy is taken into account");
+        }
+        // Protocol fees are only charged for orders with solver determined fees.
+        if !trade.fee_amount.is_zero() {
+            return Some(0.into());
+        }
+
+        let sell_token = self.tokens.get(trade.sell_token_index.as_u64() as usize)?;
+        let buy_token = self.tokens.get(trade.buy_token_index.as_u64() as usize)?;
+        let uniform_sell_price = self.uniform_price(sell_token)?;
+        let uniform_buy_price = self.uniform_price(buy_token)?;
+        if uniform_sell_price.is_zero() {
+            return None;
+        }
+        let kind = trade.flags.order_kind();
+        let sell = u256_to_big_rational(&executed.sell_amount);
+        let buy = u256_to_big_rational(&executed.buy_amount);
+
+        let fee_from_volume = |factor: f64| -> Option<BigRational> {
+            let factor = BigRational::from_float(factor)?;
+            Some(match kind {
+                // The protocol fee reduced the executed amount so the sold volume
+                // still includes it.
+                OrderKind::Sell => &sell * factor,
+                // The protocol fee got added on top of the sold volume.
+                OrderKind::Buy => &sell * &factor / (BigRational::one() + &factor),
+            })
+        };
+        let fee = match *policy {
+            Policy::Volume { factor } => fee_from_volume(factor)?,
+            Policy::Surplus {
+                factor,
+                max_volume_factor,
+            } => {
+                if trade.sell_amount.is_zero() || trade.buy_amount.is_zero() || factor >= 1. {
+                    return None;
+                }
+                let limit_sell = u256_to_big_rational(&trade.sell_amount);
+                let limit_buy = u256_to_big_rational(&trade.buy_amount);
+                // The surplus in the sell token that remained after the protocol fee.
+                let surplus = match kind {
+                    OrderKind::Sell => {
+                        (&buy - limit_buy * &sell / limit_sell) * uniform_buy_price
+                            / uniform_sell_price
+                    }
+                    OrderKind::Buy => limit_sell * &buy / limit_buy - &sell,
+                }
+                .max(BigRational::zero());
+                // The protocol took `factor` of the surplus so the remaining surplus is
+                // `1 - factor` of it.
+                let factor = BigRational::from_float(factor)?;
+                let fee_from_surplus = surplus * &factor / (BigRational::one() - &factor);
+                fee_from_surplus.min(fee_from_volume(max_volume_factor)?)
+            }
+        };
+
+        let native = external_prices.try_get_native_amount(*sell_token, fee)?;
+        big_rational_to_u256(&native).ok()
+    }
+
+    /// The uniform clearing price of a token. Orders with custom prices refer
+    /// to later occurrences of the token in the token list.
+    fn uniform_price(&self, token: &Address) -> Option<BigRational> {
+        let index = self.tokens.iter().position(|t| t == token)?;
+        Some(u256_to_big_rational(self.clearing_prices.get(index)?))
+    }
+
+    fn trade_fees(
+        &self,
+        trade: &DecodedTrade,
+        order: OrderUid,
+        external_prices: &ExternalPrices,
+    ) -> Fees {
+        self.fee(trade, order, external_prices).unwrap_or_else(|| {
+            tracing::warn!("possible incomplete fee calculation");
+            // we should have an order execution for every trade
+            Fees {
+                order,
+                kind: FeeKind::User,
+                sell: U256::zero(),
+                native: U256::zero(),
+            }
+        })
+    }
+
+    /// Computes the amounts transferred for a trade the same way the
+    /// settlement contract does.
+    fn executed_trade(
+        &self,
+        trade: &DecodedTrade,
+        external_prices: &ExternalPrices,
+    ) -> Option<ExecutedTrade> {
+        let sell_price = *self
+            .clearing_prices
+            .get(trade.sell_token_index.as_u64() as usize)?;
+        let buy_price = *self
+            .clearing_prices
+            .get(trade.buy_token_index.as_u64() as usize)?;
+
+        let (sell_amount, buy_amount, fee_amount) = match trade.flags.order_kind() {
+            OrderKind::Sell => {
+                let (sell_amount, fee_amount) = match trade.flags.partially_fillable() {
+                    true => (
+                        trade.executed_amount,
+                        trade
+                            .fee_amount
+                            .checked_mul(trade.executed_amount)?
+                            .checked_div(trade.sell_amount)?,
+                    ),
+                    false => (trade.sell_amount, trade.fee_amount),
+                };
+                let buy_amount = sell_amount
+                    .checked_mul(sell_price)?
+                    .checked_ceil_div(&buy_price)?;
+                (sell_amount, buy_amount, fee_amount)
+            }
+            OrderKind::Buy => {
+                let (buy_amount, fee_amount) = match trade.flags.partially_fillable() {
+                    true => (
+                        trade.executed_amount,
+                        trade
+                            .fee_amount
+                            .checked_mul(trade.executed_amount)?
+                            .checked_div(trade.buy_amount)?,
+                    ),
+                    false => (trade.buy_amount, trade.fee_amount),
+                };
+                let sell_amount = buy_amount.checked_mul(buy_price)?.checked_div(sell_price)?;
+                (sell_amount, buy_amount, fee_amount)
+            }
+        };
+
+        let surplus = surplus(trade, &self.tokens, &self.clearing_prices, external_prices)
+            .unwrap_or_else(|| {
+                tracing::warn!("possible incomplete surplus calculation");
+                0.into()
+            });
+
+        Some(ExecutedTrade {
+            sell_amount: sell_amount.checked_add(fee_amount)?,
+            buy_amount,
+            fee_amount,
+            sell_price,
+            buy_price,
+            surplus,
+        })
+    }
+
     fn fee(
         &self,
         trade: &DecodedTrade,
@@ -380,6 +811,115 @@ impl Fees {
     }
 }
 
+/// How a single trade of a settlement got executed. Can be populated multiple
+/// times for the same order (partially fillable orders).
+#[derive(Debug)]
+pub struct OrderExecution {
+    /// The fees of the trade. Also identifies the order.
+    pub fees: Fees,
+    /// The executed amounts. Only `None` if they can't be computed from the
+    /// calldata.
+    pub executed: Option<ExecutedTrade>,
+    /// The part of `fees.native` that was charged because of protocol fee
+    /// policies.
+    pub protocol_fee: U256,
+}
+
+impl OrderExecution {
+    /// The part of `fees.native` that covers the gas costs of the settlement.
+    pub fn network_fee(&self) -> U256 {
+        self.fees.native - self.protocol_fee
+    }
+}
+
+/// A trade of an order that was not placed in the orderbook but created by the
+/// solver just in time for the settlement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JitTrade {
+    /// Position of the trade within the settlement.
+    pub index: usize,
+    /// `None` if the owner could not be recovered from the signature.
+    pub uid: Option<OrderUid>,
+    pub owner: Option<Address>,
+    pub order: OrderData,
+    pub signing_scheme: SigningScheme,
+    pub signature: Vec<u8>,
+    /// Only `None` if the executed amounts can't be computed from the
+    /// calldata.
+    pub executed: Option<ExecutedTrade>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutedTrade {
+    /// The amount of sell token transferred from the owner including the fee.
+    pub sell_amount: U256,
+    /// The amount of buy token transferred to the receiver.
+    pub buy_amount: U256,
+    /// The executed signed fee in the sell token.
+    pub fee_amount: U256,
+    /// The clearing price of the sell token used for the trade.
+    pub sell_price: U256,
+    /// The clearing price of the buy token used for the trade.
+    pub buy_price: U256,
+    /// The surplus of the trade in the native token.
+    pub surplus: U256,
+}
+
+/// An interaction of a settlement as it gets stored for analytics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InteractionSummary {
+    /// 0 for pre-, 1 for intra- and 2 for post-interactions.
+    pub stage: usize,
+    pub target: Address,
+    pub value: U256,
+    /// `None` if the call data is too short to contain a function selector.
+    pub selector: Option<[u8; 4]>,
+    /// The length of the full call data.
+    pub call_data_len: usize,
+    /// The call data truncated to [`InteractionSummary::MAX_CALL_DATA_LEN`]
+    /// bytes.
+    pub call_data: Vec<u8>,
+    /// Whether the interaction got internalized, i.e. it was proposed by the
+    /// solver but not executed on-chain.
+    pub internalized: bool,
+}
+
+impl InteractionSummary {
+    /// Interactions can carry huge call data (e.g. entire routes) which would
+    /// bloat the database so only its beginning gets stored.
+    pub const MAX_CALL_DATA_LEN: usize = 256;
+
+    fn new(stage: usize, interaction: &DecodedInteraction, internalized: bool) -> Self {
+        let call_data = &interaction.call_data.0;
+        Self {
+            stage,
+            target: interaction.target,
+            value: interaction.value,
+            selector: call_data
+                .get(..4)
+                .map(|selector| selector.try_into().unwrap()),
+            call_data_len: call_data.len(),
+            call_data: call_data[..call_data.len().min(Self::MAX_CALL_DATA_LEN)].to_vec(),
+            internalized,
+        }
+    }
+}
+
+/// Returns for every proposed interaction whether it got internalized. The
+/// executed interactions have to be the proposed interactions with the
+/// internalized ones removed, otherwise `None` gets returned.
+fn internalized_interactions(
+    proposed: &[DecodedInteraction],
+    executed: &[DecodedInteraction],
+) -> Option<Vec<bool>> {
+    let mut executed = executed.iter().peekable();
+    let internalized = proposed
+        .iter()
+        .map(|interaction| executed.next_if_eq(&interaction).is_none())
+        .collect();
+    executed.next().is_none().then_some(internalized)
+}
+
 #[derive(Debug)]
 pub enum FeeKind {
     User,
@@ -744,6 +1284,491 @@ mod tests {
         assert_eq!(fee, 5163336903917741.);
     }
 
+    #[test]
+    fn order_executions_test() {
+        // transaction hash:
+        // 0x8f39bb793d3beac9aa944c5cc23e3e8677f639bdf87c2df9eb869a2875a8df7a
+
+        // A single fill-or-kill sell order selling USDC for ETH with a signed
+        // fee.
+
+        let call_data = hex_literal::hex!(
+            "13d79a0b
+            0000000000000000000000000000000000000000000000000000000000000080
+            00000000000000000000000000000000000000000000000000000000000000e0
+            0000000000000000000000000000000000000000000000000000000000000140
+            0000000000000000000000000000000000000000000000000000000000000360
+            0000000000000000000000000000000000000000000000000000000000000002
+            000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48
+            000000000000000000000000eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee
+            0000000000000000000000000000000000000000000000000000000000000002
+            00000000000000000000000000000000000000000000000008253cda5372fb00
+            00000000000000000000000000000000000000000000000000000000515289a8
+            0000000000000000000000000000000000000000000000000000000000000001
+            0000000000000000000000000000000000000000000000000000000000000020
+            0000000000000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000001
+            0000000000000000000000001daad45bafadf7c1fb26f6e9d10f2f559c3f6969
+            00000000000000000000000000000000000000000000000000000000515289aa
+            0000000000000000000000000000000000000000000000000818df7bcf8b291b
+            0000000000000000000000000000000000000000000000000000000065ba1711
+            f41dcea54d3ab11e7ad733f155df3d65d9afeacf3e73b5127fa55290ad8fdcbb
+            0000000000000000000000000000000000000000000000000000000000b834f5
+            0000000000000000000000000000000000000000000000000000000000000000
+            00000000000000000000000000000000000000000000000000000000515289aa
+            0000000000000000000000000000000000000000000000000000000000000160
+            0000000000000000000000000000000000000000000000000000000000000041
+            0914011870ad1446accb59cec344b3f5f5d4949e12ae7f37ab84d258bc69640d
+            098bacc990d6e6d3f2c40ab0271e4aba15c6aef2fb454a368ef7a94d7c1e0f8b
+            1c00000000000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000060
+            0000000000000000000000000000000000000000000000000000000000000320
+            0000000000000000000000000000000000000000000000000000000000000420
+            0000000000000000000000000000000000000000000000000000000000000001
+            0000000000000000000000000000000000000000000000000000000000000020
+            00000000000000000000000001dcb88678aedd0c4cc9552b20f4718550250574
+            0000000000000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000060
+            00000000000000000000000000000000000000000000000000000000000001e4
+            760f2a0b00000000000000000000000000000000000000000000000000000000
+            0000002000000000000000000000000000000000000000000000000000000000
+            0000000100000000000000000000000000000000000000000000000000000000
+            00000020000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce
+            3606eb4800000000000000000000000000000000000000000000000000000000
+            0000006000000000000000000000000000000000000000000000000000000000
+            0001388000000000000000000000000000000000000000000000000000000000
+            000000e4d505accf0000000000000000000000001daad45bafadf7c1fb26f6e9
+            d10f2f559c3f6969000000000000000000000000c92e8bdf79f0507f65a392b0
+            ab4667716bfe0110ffffffffffffffffffffffffffffffffffffffffffffffff
+            ffffffffffffffff000000000000000000000000000000000000000000000000
+            000000006f21b76b000000000000000000000000000000000000000000000000
+            000000000000001b411a84abb5867375378781ae7d25e93a6f49d7d2e1beed2d
+            7a329e79234674984a58fb81c64d5258ebe6928e88f447d0bdbff6c5b6a6313a
+            2ec93aede5d73954000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000001
+            0000000000000000000000000000000000000000000000000000000000000020
+            000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2
+            0000000000000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000060
+            0000000000000000000000000000000000000000000000000000000000000024
+            2e1a7d4d00000000000000000000000000000000000000000000000008253cda
+            86bb7c4900000000000000000000000000000000000000000000000000000000
+            0000000000000000000000000000000000000000000000000000000000000000
+            00000000007ff044"
+        )
+        .to_vec();
+        let settlement = DecodedSettlement::new(&call_data).unwrap();
+
+        let auction_external_prices = BTreeMap::from([
+            (
+                addr!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                U256::from(427705391752968402072764416u128),
+            ),
+            (
+                addr!("eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee"),
+                U256::from(1000000000000000000u128),
+            ),
+        ]);
+        let native_token = addr!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let external_prices =
+            ExternalPrices::try_from_auction_prices(native_token, auction_external_prices).unwrap();
+
+        let executions = settlement.order_executions(
+            &external_prices,
+            &MAINNET_DOMAIN_SEPARATOR,
+            &Default::default(),
+        );
+        assert_eq!(executions.len(), 1);
+        let execution = &executions[0];
+        assert_eq!(
+            execution.fees.order,
+            settlement.trades[0]
+                .uid(&MAINNET_DOMAIN_SEPARATOR, &settlement.tokens)
+                .unwrap()
+        );
+        assert_eq!(execution.fees.native.to_f64_lossy(), 5163336903917741.);
+        assert_eq!(execution.fees.executed_surplus_fee(), None);
+        // Orders with signed fees don't pay protocol fees.
+        assert_eq!(execution.protocol_fee, 0.into());
+        assert_eq!(execution.network_fee(), execution.fees.native);
+        assert_eq!(
+            execution.executed,
+            Some(ExecutedTrade {
+                // sell amount + fee amount
+                sell_amount: 1376435871.into(),
+                // ceil(sell amount * sell price / buy price)
+                buy_amount: 586942235702688841u128.into(),
+                fee_amount: 12072181.into(),
+                sell_price: 586942234842299136u128.into(),
+                buy_price: 1364363688.into(),
+                surplus: settlement.total_surplus(&external_prices),
+            })
+        );
+    }
+
+    /// Encodes a `settle` call with `trades` trades and the auction id
+    /// appended as metadata.
+    fn settle_call_data(trades: usize, auction_id: i64) -> Vec<u8> {
+        settle_call_data_with_token_indices(trades, auction_id, (0, 1))
+    }
+
+    fn settle_call_data_with_token_indices(
+        trades: usize,
+        auction_id: i64,
+        (sell_token_index, buy_token_index): (u64, u64),
+    ) -> Vec<u8> {
+        let trade = (
+            sell_token_index.into(),
+            buy_token_index.into(),
+            Address::zero(),
+            2.into(),
+            3.into(),
+            u32::MAX,
+            Bytes([0; 32]),
+            0.into(),
+            0.into(),
+            2.into(),
+            Bytes(vec![]),
+        );
+        let settlement: DecodedSettlementTokenized = (
+            vec![Address::zero(), Address::repeat_byte(1)],
+            vec![1.into(), 1.into()],
+            vec![trade; trades],
+            Default::default(),
+        );
+        let Token::Tuple(params) = settlement.into_token() else {
+            unreachable!()
+        };
+        let function = GPv2Settlement::raw_contract()
+            .abi
+            .function("settle")
+            .unwrap();
+        [
+            function.encode_input(&params).unwrap(),
+            auction_id.to_be_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decodes_wrapped_settlement() {
+        let settlement_contract = addr!("9008d19f58aabd9ed0d60971565aa8510560ab41");
+        let call_data = settle_call_data(2, 1118);
+        let direct = DecodedSettlement::new(&call_data).unwrap();
+        assert_eq!(direct.trades.len(), 2);
+        assert_eq!(direct.metadata, Some(Metadata::V0 { auction_id: 1118 }));
+
+        // `multiSend(bytes)` of a Safe multisend contract executing the
+        // settlement followed by another transaction.
+        let mut transactions = vec![0]; // operation: call
+        transactions.extend(settlement_contract.as_bytes());
+        transactions.extend([0; 32]); // value
+        transactions.extend(<[u8; 32]>::from(U256::from(call_data.len())));
+        transactions.extend(&call_data);
+        transactions.extend([0; 85]);
+        let multisend = [
+            hex_literal::hex!("8d80ff0a").to_vec(),
+            web3::ethabi::encode(&[Token::Bytes(transactions)]),
+        ]
+        .concat();
+        assert!(matches!(
+            DecodedSettlement::new(&multisend),
+            Err(DecodingError::InvalidSelector)
+        ));
+        assert_eq!(
+            DecodedSettlement::from_wrapped_call(&multisend, 2).unwrap(),
+            direct
+        );
+        // The located calldata has to match the emitted trades.
+        assert!(DecodedSettlement::from_wrapped_call(&multisend, 1).is_err());
+
+        // A proxy call with the settlement calldata as ABI encoded `bytes`.
+        let proxy = [
+            hex_literal::hex!("1cff79cd").to_vec(),
+            web3::ethabi::encode(&[Token::Address(settlement_contract), Token::Bytes(call_data)]),
+        ]
+        .concat();
+        assert_eq!(
+            DecodedSettlement::from_wrapped_call(&proxy, 2).unwrap(),
+            direct
+        );
+
+        // Garbage that happens to contain the selector.
+        let garbage = [
+            vec![0xff; 40],
+            hex_literal::hex!("13d79a0b").to_vec(),
+            vec![0xab; 100],
+        ]
+        .concat();
+        assert!(DecodedSettlement::new(&garbage).is_err());
+        assert!(DecodedSettlement::from_wrapped_call(&garbage, 0).is_err());
+        assert!(DecodedSettlement::from_wrapped_call(&[], 0).is_err());
+    }
+
+    #[test]
+    fn rejects_wrapped_settlement_with_out_of_range_token_indices() {
+        for indices in [(2, 1), (0, 2), (u64::MAX, 0)] {
+            let call_data = settle_call_data_with_token_indices(1, 1118, indices);
+            // The trade count matches but the trade references tokens the
+            // settlement doesn't contain.
+            let direct = DecodedSettlement::new(&call_data).unwrap();
+            assert!(direct.trades[0]
+                .uid(&MAINNET_DOMAIN_SEPARATOR, &direct.tokens)
+                .is_err());
+            assert!(direct.order_uids(&MAINNET_DOMAIN_SEPARATOR).is_empty());
+            assert!(direct
+                .jit_trades(
+                    &MAINNET_DOMAIN_SEPARATOR,
+                    &Default::default(),
+                    &Default::default()
+                )
+                .is_empty());
+
+            let proxy = [
+                hex_literal::hex!("1cff79cd").to_vec(),
+                web3::ethabi::encode(&[Token::Address(Address::zero()), Token::Bytes(call_data)]),
+            ]
+            .concat();
+            assert!(DecodedSettlement::from_wrapped_call(&proxy, 1).is_err());
+        }
+    }
+
+    #[test]
+    fn recognizes_jit_orders() {
+        let token_a = addr!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = addr!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let user = addr!("1daad45bafadf7c1fb26f6e9d10f2f559c3f6969");
+        let market_maker = addr!("9008d19f58aabd9ed0d60971565aa8510560ab41");
+        let trade = |flags: u8, signature: Vec<u8>| DecodedTrade {
+            sell_token_index: 0.into(),
+            buy_token_index: 1.into(),
+            receiver: Address::zero(),
+            sell_amount: 100.into(),
+            buy_amount: 90.into(),
+            valid_to: u32::MAX,
+            app_data: Default::default(),
+            fee_amount: 0.into(),
+            flags: TradeFlags(flags.into()),
+            executed_amount: 100.into(),
+            signature: Bytes(signature),
+        };
+        let settlement = DecodedSettlement {
+            tokens: vec![token_a, token_b],
+            clearing_prices: vec![1.into(), 1.into()],
+            trades: vec![
+                // pre-signed order of the orderbook
+                trade(0x60, user.0.to_vec()),
+                // EIP-1271 signed JIT order of a market maker
+                trade(0x40, [market_maker.as_bytes(), &[1, 2, 3]].concat()),
+                // eth_sign signature that is too short to recover the owner
+                trade(0x20, vec![1, 2, 3]),
+            ],
+            interactions: Default::default(),
+            metadata: None,
+        };
+        let external_prices = ExternalPrices::try_from_auction_prices(
+            token_b,
+            BTreeMap::from([(token_a, U256::exp10(18)), (token_b, U256::exp10(18))]),
+        )
+        .unwrap();
+
+        let uids = settlement.order_uids(&MAINNET_DOMAIN_SEPARATOR);
+        assert_eq!(uids.len(), 2);
+        let known_orders = HashSet::from([uids[0]]);
+
+        let jit_trades =
+            settlement.jit_trades(&MAINNET_DOMAIN_SEPARATOR, &external_prices, &known_orders);
+        assert_eq!(jit_trades.len(), 2);
+        let jit = &jit_trades[0];
+        assert_eq!(jit.index, 1);
+        assert_eq!(jit.owner, Some(market_maker));
+        assert_eq!(jit.uid, Some(uids[1]));
+        assert_eq!(jit.order.sell_token, token_a);
+        assert_eq!(jit.order.buy_token, token_b);
+        assert_eq!(jit.signing_scheme, SigningScheme::Eip1271);
+        assert_eq!(jit.executed.as_ref().unwrap().buy_amount, 100.into());
+        // The trade is recorded even though its owner is unknown.
+        let unknown = &jit_trades[1];
+        assert_eq!(unknown.index, 2);
+        assert_eq!(unknown.owner, None);
+        assert_eq!(unknown.uid, None);
+        assert_eq!(unknown.signature, vec![1, 2, 3]);
+
+        // The executions contain the user and the JIT order.
+        let executions = settlement.order_executions(
+            &external_prices,
+            &MAINNET_DOMAIN_SEPARATOR,
+            &Default::default(),
+        );
+        let orders: Vec<_> = executions
+            .iter()
+            .map(|execution| execution.fees.order)
+            .collect();
+        assert_eq!(orders, uids);
+    }
+
+    #[test]
+    fn protocol_fee_of_limit_order() {
+        // A limit order selling 100 A for at least 90 B while the market price
+        // is 1:1. The solver charges a network fee of 2 A which leaves a surplus
+        // of 8 B of which a protocol fee policy takes half. So the user receives
+        // 94 B and the total fee is 6 A.
+        let token_a = addr!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = addr!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let owner = addr!("1daad45bafadf7c1fb26f6e9d10f2f559c3f6969");
+        let settlement = DecodedSettlement {
+            tokens: vec![token_a, token_b, token_a, token_b],
+            // uniform prices followed by the custom prices of the limit order
+            clearing_prices: vec![1.into(), 1.into(), 94.into(), 100.into()],
+            trades: vec![DecodedTrade {
+                sell_token_index: 2.into(),
+                buy_token_index: 3.into(),
+                receiver: owner,
+                sell_amount: 100.into(),
+                buy_amount: 90.into(),
+                valid_to: u32::MAX,
+                app_data: Default::default(),
+                fee_amount: 0.into(),
+                // fill-or-kill pre-signed sell order
+                flags: TradeFlags(0x60.into()),
+                executed_amount: 100.into(),
+                signature: Bytes(owner.0.to_vec()),
+            }],
+            interactions: Default::default(),
+            metadata: None,
+        };
+        let external_prices = ExternalPrices::try_from_auction_prices(
+            token_b,
+            BTreeMap::from([(token_a, U256::exp10(18)), (token_b, U256::exp10(18))]),
+        )
+        .unwrap();
+        let order = settlement.trades[0]
+            .uid(&MAINNET_DOMAIN_SEPARATOR, &settlement.tokens)
+            .unwrap();
+
+        let executions = settlement.order_executions(
+            &external_prices,
+            &MAINNET_DOMAIN_SEPARATOR,
+            &HashMap::from([(
+                order,
+                vec![Policy::Surplus {
+                    factor: 0.5,
+                    max_volume_factor: 1.,
+                }],
+            )]),
+        );
+        assert_eq!(executions.len(), 1);
+        let execution = &executions[0];
+        assert_eq!(execution.executed.as_ref().unwrap().buy_amount, 94.into());
+        assert_eq!(execution.fees.native, 6.into());
+        assert_eq!(execution.protocol_fee, 4.into());
+        assert_eq!(execution.network_fee(), 2.into());
+
+        // The protocol fee is capped by the volume of the order.
+        let executions = settlement.order_executions(
+            &external_prices,
+            &MAINNET_DOMAIN_SEPARATOR,
+            &HashMap::from([(
+                order,
+                vec![Policy::Surplus {
+                    factor: 0.5,
+                    max_volume_factor: 0.01,
+                }],
+            )]),
+        );
+        assert_eq!(executions[0].protocol_fee, 1.into());
+        assert_eq!(executions[0].network_fee(), 5.into());
+
+        // Without fee policies the whole fee covers the network costs.
+        let executions = settlement.order_executions(
+            &external_prices,
+            &MAINNET_DOMAIN_SEPARATOR,
+            &Default::default(),
+        );
+        assert_eq!(executions[0].protocol_fee, 0.into());
+        assert_eq!(executions[0].network_fee(), 6.into());
+    }
+
+    #[test]
+    fn interaction_summaries_test() {
+        // Interactions modeled after transaction
+        // 0x8f39bb793d3beac9aa944c5cc23e3e8677f639bdf87c2df9eb869a2875a8df7a
+        // where a permit gets executed in a pre-interaction and WETH gets
+        // unwrapped in an intra-interaction.
+        let permit = || DecodedInteraction {
+            target: addr!("01dcb88678aedd0c4cc9552b20f4718550250574"),
+            value: 0.into(),
+            call_data: Bytes([&hex_literal::hex!("760f2a0b")[..], &[1; 480][..]].concat()),
+        };
+        let unwrap = || DecodedInteraction {
+            target: addr!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            value: 0.into(),
+            call_data: Bytes([&hex_literal::hex!("2e1a7d4d")[..], &[2; 32][..]].concat()),
+        };
+        let swap = || DecodedInteraction {
+            target: addr!("e592427a0aece92de3edee1f18e0157c05861564"),
+            value: 1.into(),
+            call_data: Bytes(vec![3; 3]),
+        };
+        let settlement = |interactions| DecodedSettlement {
+            tokens: Default::default(),
+            clearing_prices: Default::default(),
+            trades: Default::default(),
+            interactions,
+            metadata: None,
+        };
+        let executed = settlement([vec![permit()], vec![unwrap()], vec![]]);
+
+        let permit_summary = InteractionSummary {
+            stage: 0,
+            target: permit().target,
+            value: 0.into(),
+            selector: Some(hex_literal::hex!("760f2a0b")),
+            call_data_len: 484,
+            call_data: permit().call_data.0[..256].to_vec(),
+            internalized: false,
+        };
+        let unwrap_summary = InteractionSummary {
+            stage: 1,
+            target: unwrap().target,
+            value: 0.into(),
+            selector: Some(hex_literal::hex!("2e1a7d4d")),
+            call_data_len: 36,
+            call_data: unwrap().call_data.0,
+            internalized: false,
+        };
+        let swap_summary = InteractionSummary {
+            stage: 1,
+            target: swap().target,
+            value: 1.into(),
+            selector: None,
+            call_data_len: 3,
+            call_data: vec![3; 3],
+            internalized: true,
+        };
+
+        assert_eq!(
+            executed.interaction_summaries(None),
+            [permit_summary.clone(), unwrap_summary.clone()]
+        );
+
+        // The solver proposed a swap before unwrapping which got internalized.
+        let uninternalized = settlement([vec![permit()], vec![swap(), unwrap()], vec![]]);
+        assert_eq!(
+            executed.interaction_summaries(Some(&uninternalized)),
+            [permit_summary.clone(), swap_summary, unwrap_summary.clone()]
+        );
+
+        // Uninternalized settlements that don't match the executed one get
+        // ignored.
+        let unrelated = settlement([vec![], vec![swap()], vec![]]);
+        assert_eq!(
+            executed.interaction_summaries(Some(&unrelated)),
+            [permit_summary, unwrap_summary]
+        );
+    }
+
     #[test]
     fn total_fees_test_partial_limit_order() {
         // transaction hash:
@@ -1019,7 +2044,12 @@ mod tests {
         let metadata = [42; DecodedSettlement::META_DATA_LEN];
         let with_metadata = [call_data, metadata.to_vec()].concat();
         let with_metadata = DecodedSettlement::new(&with_metadata).unwrap();
-        assert_eq!(with_metadata.metadata, Some(Bytes(metadata)));
+        assert_eq!(
+            with_metadata.metadata,
+            Some(Metadata::V0 {
+                auction_id: i64::from_be_bytes(metadata)
+            })
+        );
 
         // Content of the remaining fields is identical to the original
         let metadata_removed_again = DecodedSettlement {
@@ -1029,6 +2059,74 @@ mod tests {
         assert_eq!(original, metadata_removed_again);
     }
 
+    #[test]
+    fn decodes_versioned_metadata() {
+        let call_data = settle_call_data(1, 0);
+        let call_data = &call_data[..call_data.len() - DecodedSettlement::META_DATA_LEN];
+        let original = DecodedSettlement::new(call_data).unwrap();
+        assert_eq!(original.metadata, None);
+        let metadata = |trailer: &[u8]| {
+            let settlement = DecodedSettlement::new(&[call_data, trailer].concat()).unwrap();
+            assert_eq!(original.trades, settlement.trades);
+            settlement.metadata
+        };
+
+        // Version 0 is the plain auction id.
+        assert_eq!(
+            metadata(&1127i64.to_be_bytes()),
+            Some(Metadata::V0 { auction_id: 1127 })
+        );
+        // Auction ids whose bytes look like a versioned trailer are still
+        // parsed as version 0.
+        assert_eq!(
+            metadata(&0x0507i64.to_be_bytes()),
+            Some(Metadata::V0 { auction_id: 0x0507 })
+        );
+
+        // {"auctionId": 1127, "solutionId": 3, "solverFlags": 1}
+        let payload = hex_literal::hex!(
+            "a3
+             69 61756374696f6e4964 19 0467
+             6a 736f6c7574696f6e4964 03
+             6b 736f6c766572466c616773 01"
+        );
+        let v1 = [payload.as_slice(), &[0x00, 0x27, 0x01]].concat();
+        assert_eq!(
+            metadata(&v1),
+            Some(Metadata::V1(MetadataV1 {
+                auction_id: 1127,
+                solution_id: Some(3),
+                solver_flags: Some(1),
+            }))
+        );
+        // Only the auction id is required.
+        let payload = hex_literal::hex!("a1 69 61756374696f6e4964 19 0467");
+        assert_eq!(
+            metadata(&[payload.as_slice(), &[0x00, 0x0e, 0x01]].concat()),
+            Some(Metadata::V1(MetadataV1 {
+                auction_id: 1127,
+                solution_id: None,
+                solver_flags: None,
+            }))
+        );
+
+        // A truncated trailer is not recognized.
+        assert_eq!(metadata(&v1[5..]), None);
+        assert_eq!(metadata(&v1[..v1.len() - 1]), None);
+
+        // Garbage that does not decode as CBOR.
+        assert_eq!(
+            metadata(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x06, 0x01]),
+            None
+        );
+        assert_eq!(metadata(&[0xff; 33]), None);
+
+        // Versions this code does not know are recognized but not parsed.
+        let unknown = metadata(&[1, 2, 3, 4, 0x00, 0x04, 0x02]).unwrap();
+        assert_eq!(unknown, Metadata::Unknown { version: 2 });
+        assert_eq!(unknown.auction_id(), None);
+    }
+
     #[test]
     fn test_signature_collision() {
         // 0xd881e90f4afb020d92b8fa1b4931d2352aab4179e4f8d9a4aeafd01ebc75f808
@@ -28,6 +28,8 @@ pub struct Order {
     pub buy_token_balance: BuyTokenDestination,
     pub app_data: AppDataHash,
     pub signature: Signature,
+    /// Whether the owner repeatedly didn't have enough sell token balance.
+    pub insufficient_balance: bool,
 }
 
 // uid as 56 bytes: 32 for orderDigest, 20 for ownerAddress and 4 for validTo
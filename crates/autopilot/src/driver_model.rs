@@ -173,6 +173,11 @@ pub mod settle {
     pub struct Response {
         pub calldata: Calldata,
         pub tx_hash: H256,
+        /// The transaction that was broadcast first for the solution. The
+        /// mined transaction replaced it if the two differ. Drivers whose
+        /// submission logic doesn't report its transactions leave it out.
+        #[serde(default)]
+        pub submitted_tx_hash: Option<H256>,
     }
 
     #[serde_as]
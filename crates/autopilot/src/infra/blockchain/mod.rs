@@ -4,8 +4,10 @@ use {
     ethcontract::dyns::DynWeb3,
     ethrpc::current_block::CurrentBlockStream,
     primitive_types::{H256, U256},
+    serde::Deserialize,
     std::{sync::Arc, time::Duration},
     thiserror::Error,
+    web3::Transport,
 };
 
 pub mod contracts;
@@ -22,6 +24,40 @@ impl From<U256> for ChainId {
     }
 }
 
+impl ChainId {
+    /// The kind of rollup the chain is. `None` for chains that don't post
+    /// their transaction data to L1.
+    pub fn rollup(&self) -> Option<Rollup> {
+        match self.0.low_u64() {
+            // Optimism and Base
+            10 | 8453 => Some(Rollup::OpStack),
+            // Arbitrum One
+            42161 => Some(Rollup::Arbitrum),
+            _ => None,
+        }
+    }
+}
+
+/// Rollups charge for posting the transaction data to L1 on top of the
+/// execution. How that fee shows up in the receipt depends on the rollup.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rollup {
+    /// The L1 data fee is reported as `l1Fee` in wei and is not part of
+    /// `gasUsed * effectiveGasPrice`.
+    OpStack,
+    /// The L1 data fee is reported as `gasUsedForL1` in L2 gas and is already
+    /// part of `gasUsed`.
+    Arbitrum,
+}
+
+/// The receipt fields rollups add to the standard transaction receipt.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RollupReceipt {
+    pub l1_fee: Option<U256>,
+    pub gas_used_for_l1: Option<U256>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NetworkId(pub String);
 
@@ -140,6 +176,30 @@ impl Ethereum {
             .await
             .map_err(Into::into)
     }
+
+    /// Returns the timestamp of the block or `None` if the block is not known
+    /// yet.
+    pub async fn block_timestamp(&self, number: u64) -> Result<Option<u64>, Error> {
+        let block = self
+            .web3
+            .eth()
+            .block(web3::types::BlockId::Number(number.into()))
+            .await?;
+        Ok(block.map(|block| block.timestamp.as_u64()))
+    }
+
+    /// Fetches the rollup specific fields of a transaction receipt which the
+    /// standard receipt type does not know about.
+    pub async fn rollup_receipt(&self, hash: H256) -> Result<Option<RollupReceipt>, Error> {
+        let hash = serde_json::to_value(hash).expect("hashes serialize to JSON");
+        let receipt = self
+            .web3
+            .transport()
+            .execute("eth_getTransactionReceipt", vec![hash])
+            .await?;
+        serde_json::from_value(receipt)
+            .map_err(|err| web3::error::Error::Decoder(err.to_string()).into())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -39,6 +39,8 @@ pub struct Order {
     pub app_data: boundary::AppDataHash,
     #[serde(flatten)]
     pub signature: boundary::Signature,
+    #[serde(default)]
+    pub insufficient_balance: bool,
 }
 
 pub fn from_domain(order: domain::Order) -> Order {
@@ -67,6 +69,7 @@ pub fn from_domain(order: domain::Order) -> Order {
         class: order.class.into(),
         app_data: order.app_data.into(),
         signature: order.signature.into(),
+        insufficient_balance: order.insufficient_balance,
     }
 }
 
@@ -96,6 +99,7 @@ pub fn to_domain(order: Order) -> domain::Order {
         class: order.class.into(),
         app_data: order.app_data.into(),
         signature: order.signature.into(),
+        insufficient_balance: order.insufficient_balance,
     }
 }
 
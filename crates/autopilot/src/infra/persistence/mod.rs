@@ -3,6 +3,7 @@ use {
     anyhow::Context,
     chrono::Utc,
     itertools::Itertools,
+    primitive_types::{H160, H256},
     std::sync::Arc,
     tokio::time::Instant,
     tracing::Instrument,
@@ -30,7 +31,8 @@ impl Persistence {
 
     /// There is always only one `current` auction.
     ///
-    /// This method replaces the current auction with the given one.
+    /// This method replaces the current auction with the given one. A
+    /// configurable number of previous auctions is kept for inspection.
     ///
     /// If the given auction is successfully saved, it is also archived.
     pub async fn replace_current_auction(
@@ -116,6 +118,26 @@ impl Persistence {
         );
     }
 
+    /// Records the transaction the driver reported to have broadcast first
+    /// for the settlement of an auction. This makes it possible to later
+    /// detect that the mined transaction replaced it.
+    pub async fn store_settlement_submission(
+        &self,
+        auction_id: domain::AuctionId,
+        tx_from: H160,
+        tx_hash: H256,
+    ) -> anyhow::Result<()> {
+        let mut ex = self.postgres.pool.acquire().await.context("acquire")?;
+        database::settlement_submissions::insert(
+            &mut ex,
+            auction_id,
+            &database::byte_array::ByteArray(tx_from.0),
+            &database::byte_array::ByteArray(tx_hash.0),
+        )
+        .await
+        .context("settlement_submissions::insert")
+    }
+
     /// Saves the given fee policies to the DB as a single batch.
     pub async fn store_fee_policies(
         &self,
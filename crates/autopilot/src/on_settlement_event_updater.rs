@@ -30,23 +30,233 @@
 use {
     crate::{
         database::{
-            on_settlement_event_updater::{AuctionData, SettlementUpdate},
+            on_settlement_event_updater::{
+                AuctionData,
+                ExecutedAmounts,
+                LateCancellation,
+                OrderExecution,
+                SettlementUpdate,
+            },
             Postgres,
         },
-        decoded_settlement::DecodedSettlement,
-        infra,
+        decoded_settlement::{DecodedSettlement, DecodingError, Metadata},
+        domain::fee::Policy,
+        infra::{
+            self,
+            blockchain::{Rollup, RollupReceipt},
+        },
     },
     anyhow::{Context, Result},
-    futures::StreamExt,
-    primitive_types::H256,
+    chrono::{TimeZone, Utc},
+    contracts::GPv2Settlement,
+    database::{
+        events::EventIndex,
+        failed_settlement_updates::FailedSettlementUpdate,
+        settlement_scores::Score,
+        settlements::{SettlementEvent, UpdatedSettlement},
+    },
+    ethrpc::current_block::BlockInfo,
+    futures::{stream, Future, Stream, StreamExt},
+    model::{order::OrderUid, DomainSeparator},
+    number::conversions::big_decimal_to_u256,
+    primitive_types::{H160, H256, U256},
     shared::external_prices::ExternalPrices,
-    sqlx::PgConnection,
-    web3::types::Transaction,
+    sqlx::{Connection, PgConnection},
+    std::{
+        collections::{HashMap, HashSet},
+        fmt::Debug,
+        num::NonZeroUsize,
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
+    },
+    tokio_util::sync::CancellationToken,
+    web3::types::{Transaction, TransactionReceipt},
 };
 
+/// How many settlement transactions are fetched from the node concurrently.
+const MAX_CONCURRENT_RPC_REQUESTS: usize = 10;
+
+/// How often a failing RPC request is retried before the update attempt of
+/// the settlement fails.
+const RPC_RETRIES: u32 = 3;
+
+/// How often the receipt of a mined settlement transaction is requested before
+/// giving up until the next update.
+const RECEIPT_ATTEMPTS: u32 = 3;
+
+/// How long to wait before requesting a missing receipt again.
+const RECEIPT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How long to wait before re-subscribing to the block stream after it closed
+/// for the first time. The delay doubles every time it closes right away.
+const BLOCK_STREAM_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound of the delay before re-subscribing to the block stream.
+const BLOCK_STREAM_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How long to wait before retrying a failed RPC request for the first time.
+/// The delay doubles with every retry.
+const RPC_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
 pub struct OnSettlementEventUpdater {
     pub eth: infra::Ethereum,
     pub db: Postgres,
+    /// Maximum number of settlement events processed in a single update.
+    pub batch_size: NonZeroUsize,
+    /// How often updating a settlement event may fail before it gets marked
+    /// as failed and is no longer retried.
+    pub max_update_attempts: NonZeroUsize,
+    /// Whether settlement events that were marked as failed should be
+    /// re-enqueued on startup.
+    pub requeue_failed_updates: bool,
+    /// The auction ids this environment assigns. Settlements of other auction
+    /// ids belong to a different environment.
+    pub auction_ids: AuctionIdRange,
+    pub heartbeat: Arc<Heartbeat>,
+}
+
+/// Time at which the updater last completed an update successfully. Lets
+/// liveness probes detect an updater that got stuck.
+#[derive(Debug)]
+pub struct Heartbeat(RwLock<Instant>);
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self(RwLock::new(Instant::now()))
+    }
+}
+
+impl Heartbeat {
+    fn beat(&self) {
+        *self.0.write().unwrap() = Instant::now();
+        Metrics::get()
+            .last_successful_update
+            .set(chrono::Utc::now().timestamp());
+    }
+
+    /// Time since the updater last completed an update successfully (or since
+    /// it was created if it never did).
+    pub fn age(&self) -> Duration {
+        self.0.read().unwrap().elapsed()
+    }
+}
+
+/// Stream of new blocks that gets re-subscribed when it closes, e.g. because
+/// the node it was polled from failed over. Re-subscribing backs off
+/// exponentially as long as the stream keeps closing right away.
+struct Blocks<F, S> {
+    subscribe: F,
+    stream: S,
+    backoff: Duration,
+}
+
+impl<F, S> Blocks<F, S>
+where
+    F: FnMut() -> S,
+    S: Stream<Item = BlockInfo> + Unpin,
+{
+    fn new(mut subscribe: F) -> Self {
+        let stream = subscribe();
+        Self {
+            subscribe,
+            stream,
+            backoff: BLOCK_STREAM_INITIAL_BACKOFF,
+        }
+    }
+
+    /// Waits for the next block. Returns `None` if the stream closed, in which
+    /// case it got re-subscribed after waiting for the backoff.
+    async fn next(&mut self) -> Option<BlockInfo> {
+        if let Some(block) = self.stream.next().await {
+            self.backoff = BLOCK_STREAM_INITIAL_BACKOFF;
+            return Some(block);
+        }
+        tracing::warn!(backoff = ?self.backoff, "block stream closed, re-subscribing");
+        tokio::time::sleep(self.backoff).await;
+        self.backoff = (self.backoff * 2).min(BLOCK_STREAM_MAX_BACKOFF);
+        self.stream = (self.subscribe)();
+        None
+    }
+}
+
+/// Runs `future` unless `shutdown` gets cancelled first, in which case the
+/// future is dropped and `None` is returned.
+async fn cancellable<T>(
+    shutdown: &CancellationToken,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        biased;
+        _ = shutdown.cancelled() => None,
+        output = future => Some(output),
+    }
+}
+
+/// Range of auction ids assigned by a single environment (e.g. barn or prod).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuctionIdRange {
+    /// The smallest auction id of the environment.
+    pub start: i64,
+    /// The first auction id no longer belonging to the environment. `None`
+    /// means the range is unbounded.
+    pub end: Option<i64>,
+}
+
+impl AuctionIdRange {
+    pub fn contains(&self, auction_id: i64) -> bool {
+        auction_id >= self.start && self.end.map_or(true, |end| auction_id < end)
+    }
+}
+
+/// Outcome of backfilling the auction data of settlements.
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    /// Settlements whose auction data got recomputed.
+    pub backfilled: Vec<H256>,
+    /// Settlements whose auction data was left untouched.
+    pub skipped: Vec<H256>,
+    pub failed: Vec<(H256, anyhow::Error)>,
+}
+
+/// The on-chain data of a settlement event that is needed to update it.
+struct SettlementTransaction {
+    transaction: Transaction,
+    receipt: TransactionReceipt,
+}
+
+/// The result of fetching the on-chain data of a settlement event.
+enum FetchedSettlement {
+    Found(SettlementTransaction),
+    /// The transaction no longer exists because of a reorg.
+    Reorged,
+    /// The transaction exists but the node does not return its complete
+    /// receipt yet. The event should be updated again later.
+    ReceiptPending,
+}
+
+/// How updating a settlement event turned out.
+#[derive(Clone, Copy, Debug)]
+enum Outcome {
+    /// The details of the settlement were stored.
+    Processed,
+    /// The calldata could not be attributed to an auction so only the default
+    /// auction id was stored.
+    InvalidCalldata,
+    /// The calldata claimed an auction that a different settlement already
+    /// settled so only the default auction id and the claim were stored.
+    DuplicateAuctionClaim,
+    Error,
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Processed => "processed",
+            Outcome::InvalidCalldata => "invalid_calldata",
+            Outcome::DuplicateAuctionClaim => "duplicate_auction_claim",
+            Outcome::Error => "error",
+        }
+    }
 }
 
 enum AuctionIdRecoveryStatus {
@@ -54,25 +264,53 @@ enum AuctionIdRecoveryStatus {
     AddAuctionData(i64, DecodedSettlement),
     /// The auction id was recovered but the auction data should not be added.
     DoNotAddAuctionData(i64),
+    /// The auction id was recovered but the settlement was not submitted by
+    /// the winner of the auction so the auction data should not be added.
+    UnexpectedSubmitter { auction_id: i64, winner: H160 },
+    /// The auction id was recovered but a different settlement already
+    /// settled the auction so the settlement must not be associated with it.
+    DuplicateAuctionClaim(i64),
     /// The auction id was not recovered.
     InvalidCalldata,
 }
 
 impl OnSettlementEventUpdater {
-    pub async fn run_forever(self) -> ! {
+    /// Updates settlement events whenever a new block arrives until
+    /// `shutdown` gets cancelled. Cancelling in the middle of an update rolls
+    /// back the batch so the events get updated again after a restart.
+    pub async fn run(self, shutdown: CancellationToken) {
+        if self.requeue_failed_updates {
+            match self.requeue_failed_updates().await {
+                Ok(requeued) => tracing::info!(requeued, "re-enqueued failed settlement updates"),
+                Err(err) => tracing::error!(?err, "failed to re-enqueue failed settlement updates"),
+            }
+        }
+
         let mut current_block = self.eth.current_block().borrow().to_owned();
-        let mut block_stream = ethrpc::current_block::into_stream(self.eth.current_block().clone());
+        let mut blocks =
+            Blocks::new(|| ethrpc::current_block::into_stream(self.eth.current_block().clone()));
+        let mut attempts = FailedAttempts::default();
         loop {
-            match self.update().await {
-                Ok(true) => {
+            let Some(result) = cancellable(&shutdown, self.update(&mut attempts)).await else {
+                break;
+            };
+            if let Err(err) = report_progress(&self.db).await {
+                tracing::warn!(?err, "failed to report settlement update progress");
+            }
+            if result.is_ok() {
+                self.heartbeat.beat();
+            }
+            match result {
+                Ok(updated) if updated > 0 => {
                     tracing::debug!(
                         block = current_block.number,
-                        "on settlement event updater ran and processed event"
+                        updated,
+                        "on settlement event updater ran and processed events"
                     );
                     // Don't wait until next block in case there are more pending events to process.
                     continue;
                 }
-                Ok(false) => {
+                Ok(_) => {
                     tracing::debug!(
                         block = current_block.number,
                         "on settlement event updater ran without update"
@@ -82,92 +320,455 @@ impl OnSettlementEventUpdater {
                     tracing::error!(?err, "on settlement event update task failed");
                 }
             }
-            current_block = block_stream.next().await.expect("blockchains never end");
+            match cancellable(&shutdown, blocks.next()).await {
+                Some(Some(block)) => current_block = block,
+                // The block stream was re-subscribed after it closed.
+                Some(None) => (),
+                None => break,
+            }
         }
+        tracing::info!("on settlement event updater shut down");
     }
 
-    /// Update database for settlement events that have not been processed yet.
+    /// Recomputes the auction data of the settlements between `from_block` and
+    /// `to_block` (inclusive) that were already associated with an auction of
+    /// this environment, e.g. after a bug in computing the observations got
+    /// fixed. Only settlements without observations are backfilled unless
+    /// `recompute` is set, in which case existing auction data gets
+    /// overwritten.
     ///
-    /// Returns whether an update was performed.
-    async fn update(&self) -> Result<bool> {
+    /// Settlements that fail to backfill are reported instead of aborting the
+    /// backfill so it can be re-run for them later.
+    pub async fn backfill(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        recompute: bool,
+    ) -> Result<BackfillReport> {
+        let block_range = i64::try_from(from_block)?..i64::try_from(to_block)?.saturating_add(1);
         let mut ex = self
             .db
             .pool
-            .begin()
+            .acquire()
             .await
             .context("acquire DB connection")?;
-        let event = match database::settlements::get_settlement_without_auction(&mut ex)
+        let settlements = database::settlements::updated_settlements(&mut ex, block_range)
             .await
-            .context("get_settlement_event_without_tx_info")?
-        {
-            Some(event) => event,
-            None => return Ok(false),
+            .context("updated_settlements")?;
+
+        let mut report = BackfillReport::default();
+        let (settlements, skipped): (Vec<_>, Vec<_>) = settlements
+            .into_iter()
+            .partition(|settlement| needs_backfill(settlement, recompute, &self.auction_ids));
+        report
+            .skipped
+            .extend(skipped.iter().map(|settlement| H256(settlement.tx_hash.0)));
+        tracing::info!(
+            from_block,
+            to_block,
+            recompute,
+            settlements = settlements.len(),
+            skipped = skipped.len(),
+            "backfilling settlements"
+        );
+
+        for batch in settlements.chunks(self.batch_size.get()) {
+            let transactions = fetch_in_order(
+                batch.iter().collect(),
+                MAX_CONCURRENT_RPC_REQUESTS,
+                |settlement| self.fetch_settlement_transaction(H256(settlement.tx_hash.0)),
+            )
+            .await;
+            for (settlement, transaction) in transactions {
+                let hash = H256(settlement.tx_hash.0);
+                let result = match transaction {
+                    Ok(FetchedSettlement::Found(transaction)) => {
+                        self.backfill_settlement(&mut ex, settlement, transaction)
+                            .await
+                    }
+                    Ok(FetchedSettlement::Reorged) => Ok(false),
+                    Ok(FetchedSettlement::ReceiptPending) => {
+                        Err(anyhow::anyhow!("receipt not available yet"))
+                    }
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(true) => {
+                        tracing::info!(?hash, auction_id = settlement.auction_id, "backfilled");
+                        report.backfilled.push(hash);
+                    }
+                    Ok(false) => {
+                        tracing::info!(?hash, "settlement does not get auction data, skipped");
+                        report.skipped.push(hash);
+                    }
+                    Err(err) => {
+                        tracing::warn!(?hash, ?err, "failed to backfill settlement");
+                        report.failed.push((hash, err));
+                    }
+                }
+            }
+            tracing::info!(
+                backfilled = report.backfilled.len(),
+                failed = report.failed.len(),
+                total = settlements.len(),
+                "backfill progress"
+            );
+        }
+        Ok(report)
+    }
+
+    /// Recomputes and stores the auction data of a single settlement. Returns
+    /// `false` if the settlement should not have auction data (e.g. because
+    /// it was not submitted by the winner) or got reorged in the meantime.
+    async fn backfill_settlement(
+        &self,
+        ex: &mut PgConnection,
+        settlement: &UpdatedSettlement,
+        transaction: SettlementTransaction,
+    ) -> Result<bool> {
+        let hash = H256(settlement.tx_hash.0);
+        let SettlementTransaction {
+            transaction,
+            receipt,
+        } = transaction;
+        let tx_from = transaction.from.context("tx is missing sender")?;
+        let decoded = decode_settlement(
+            &transaction,
+            &receipt,
+            self.eth.contracts().settlement().address(),
+        )
+        .map_err(|err| anyhow::anyhow!("could not decode settlement: {err:?}"))?;
+        let auction_id = decoded
+            .metadata
+            .as_ref()
+            .and_then(Metadata::auction_id)
+            .context("could not recover the auction_id from the calldata")?;
+        anyhow::ensure!(
+            auction_id == settlement.auction_id,
+            "settlement is associated with auction {} but its calldata belongs to auction \
+             {auction_id}",
+            settlement.auction_id,
+        );
+
+        let mut ex = ex.begin().await.context("begin transaction")?;
+        let score = database::settlement_scores::fetch(&mut ex, auction_id).await?;
+        // The settlement itself already counts as recorded data of the auction
+        // so that check is skipped.
+        let decoded = match recovery_status(
+            auction_id,
+            decoded,
+            tx_from,
+            &self.auction_ids,
+            score,
+            false,
+        ) {
+            AuctionIdRecoveryStatus::AddAuctionData(_, decoded) => decoded,
+            _ => return Ok(false),
+        };
+        let auction_data = self
+            .fetch_auction_data(hash, receipt, decoded, auction_id, &mut ex)
+            .await?;
+        let event = EventIndex {
+            block_number: settlement.block_number,
+            log_index: settlement.log_index,
         };
+        let stored =
+            Postgres::backfill_auction_data(&mut ex, &event, hash, auction_id, auction_data)
+                .await
+                .with_context(|| format!("backfill_auction_data: {event:?}"))?;
+        ex.commit().await?;
+        Ok(stored)
+    }
+
+    async fn requeue_failed_updates(&self) -> Result<u64> {
+        let mut ex = self
+            .db
+            .pool
+            .acquire()
+            .await
+            .context("acquire DB connection")?;
+        Ok(database::failed_settlement_updates::requeue_all(&mut ex).await?)
+    }
+
+    /// Update database for a batch of settlement events that have not been
+    /// processed yet. Events that fail to update are retried in later updates
+    /// until they exhausted their attempts, at which point they get marked as
+    /// failed.
+    ///
+    /// The transactions of the events get fetched before the events get locked
+    /// so no locks are held during the RPC requests. The locked events stay
+    /// locked until the transaction is committed so multiple autopilot
+    /// instances can update settlements concurrently without processing the
+    /// same event twice.
+    ///
+    /// Returns the number of settlement events that were updated or marked as
+    /// failed.
+    async fn update(&self, attempts: &mut FailedAttempts) -> Result<usize> {
+        let mut ex = self
+            .db
+            .pool
+            .acquire()
+            .await
+            .context("acquire DB connection")?;
+        let limit = i64::try_from(self.batch_size.get()).unwrap_or(i64::MAX);
+        let events = database::settlements::get_settlements_without_auction(&mut ex, limit)
+            .await
+            .context("get_settlements_without_auction")?;
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        // The RPC requests of the different settlements are independent of
+        // each other but the database updates are applied in the order of the
+        // events so that processing a batch is reproducible.
+        let settlements = fetch_in_order(events, MAX_CONCURRENT_RPC_REQUESTS, |event| {
+            let fetch = self.fetch_settlement_transaction(H256(event.tx_hash.0));
+            async move {
+                let start = Instant::now();
+                let settlement = fetch.await;
+                (settlement, start.elapsed())
+            }
+        })
+        .await;
 
+        // Another instance might have updated some of the events while their
+        // transactions were fetched.
+        let mut ex = ex.begin().await.context("begin transaction")?;
+        let indices: Vec<_> = settlements
+            .iter()
+            .map(|(event, _)| EventIndex {
+                block_number: event.block_number,
+                log_index: event.log_index,
+            })
+            .collect();
+        let locked: HashSet<_> =
+            database::settlements::lock_settlements_without_auction(&mut ex, &indices)
+                .await
+                .context("lock_settlements_without_auction")?
+                .into_iter()
+                .collect();
+
+        let mut processed = 0;
+        for (event, (settlement, fetch_time)) in settlements {
+            let index = EventIndex {
+                block_number: event.block_number,
+                log_index: event.log_index,
+            };
+            if !locked.contains(&index) {
+                continue;
+            }
+            let hash = H256(event.tx_hash.0);
+            let start = Instant::now();
+            let result = match settlement {
+                Ok(FetchedSettlement::Found(settlement)) => {
+                    self.apply_update(&mut ex, &event, settlement).await
+                }
+                Ok(FetchedSettlement::Reorged) => continue,
+                // Left untouched so the event gets updated again once the
+                // node returns the receipt.
+                Ok(FetchedSettlement::ReceiptPending) => {
+                    tracing::debug!(?hash, "receipt not available yet, retrying later");
+                    Metrics::get().pending_receipts.inc();
+                    continue;
+                }
+                Err(err) => Err(err),
+            };
+            let observe = |outcome: Outcome| {
+                Metrics::get()
+                    .event_processing_time
+                    .with_label_values(&[outcome.label()])
+                    .observe((fetch_time + start.elapsed()).as_secs_f64())
+            };
+            let err = match result {
+                Ok(Some(outcome)) => {
+                    observe(outcome);
+                    attempts.clear(&event);
+                    processed += 1;
+                    continue;
+                }
+                Ok(None) => {
+                    tracing::debug!(?hash, "settlement got reorged while updating it");
+                    attempts.clear(&event);
+                    continue;
+                }
+                Err(err) => {
+                    observe(Outcome::Error);
+                    err
+                }
+            };
+
+            let failed = match attempts.record(&event, self.max_update_attempts) {
+                Failure::Retry(failed) => {
+                    tracing::warn!(
+                        ?hash,
+                        failed,
+                        ?err,
+                        "failed to update settlement, retrying later"
+                    );
+                    Metrics::get()
+                        .failed_updates
+                        .with_label_values(&["retried"])
+                        .inc();
+                    continue;
+                }
+                Failure::GiveUp(failed) => failed,
+            };
+
+            tracing::error!(?hash, failed, ?err, "giving up on updating settlement");
+            let failure = FailedSettlementUpdate {
+                block_number: event.block_number,
+                log_index: event.log_index,
+                attempts: i64::try_from(failed).unwrap_or(i64::MAX),
+                error: format!("{err:?}"),
+            };
+            database::failed_settlement_updates::insert(&mut ex, &failure)
+                .await
+                .with_context(|| format!("insert_failed_settlement_update: {failure:?}"))?;
+            Metrics::get()
+                .failed_updates
+                .with_label_values(&["given_up"])
+                .inc();
+            processed += 1;
+        }
+        ex.commit().await?;
+        Ok(processed)
+    }
+
+    /// Updates the details of a single settlement event. The changes are made
+    /// in a savepoint so that a failing update leaves the rest of the batch
+    /// intact.
+    ///
+    /// Returns `None` if the event got reorged in the meantime.
+    async fn apply_update(
+        &self,
+        ex: &mut PgConnection,
+        event: &SettlementEvent,
+        settlement: SettlementTransaction,
+    ) -> Result<Option<Outcome>> {
+        let mut savepoint = ex.begin().await.context("begin savepoint")?;
+        let (update, outcome) = self
+            .settlement_update(&mut savepoint, event, settlement)
+            .await?;
         let hash = H256(event.tx_hash.0);
-        tracing::debug!("updating settlement details for tx {hash:?}");
+        tracing::debug!(?hash, ?update, "updating settlement details for tx");
+        let updated = Postgres::update_settlement_details(&mut savepoint, update.clone())
+            .await
+            .with_context(|| format!("insert_settlement_details: {update:?}"))?;
+        savepoint.commit().await?;
+        Ok(updated.then_some(outcome))
+    }
 
-        let Some(transaction) = self.eth.transaction(hash).await? else {
+    /// Fetches the transaction and receipt of a settlement.
+    async fn fetch_settlement_transaction(&self, hash: H256) -> Result<FetchedSettlement> {
+        tracing::debug!("fetching settlement details for tx {hash:?}");
+        let (transaction, receipt) = futures::try_join!(
+            with_backoff(|| self.eth.transaction(hash)),
+            wait_for_receipt(|| with_backoff(|| self.eth.transaction_receipt(hash))),
+        )?;
+        let Some(transaction) = transaction else {
             tracing::warn!(?hash, "no tx found, reorg happened");
-            return Ok(false);
+            return Ok(FetchedSettlement::Reorged);
+        };
+        let Some(receipt) = receipt else {
+            return Ok(FetchedSettlement::ReceiptPending);
         };
+        Ok(FetchedSettlement::Found(SettlementTransaction {
+            transaction,
+            receipt,
+        }))
+    }
 
-        let (auction_id, auction_data) =
-            match Self::recover_auction_id_from_calldata(&mut ex, &transaction).await? {
-                AuctionIdRecoveryStatus::InvalidCalldata => {
-                    // To not get stuck on indexing the same transaction over and over again, we
-                    // insert the default auction ID (0)
-                    (Default::default(), None)
-                }
-                AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id) => (auction_id, None),
-                AuctionIdRecoveryStatus::AddAuctionData(auction_id, settlement) => (
-                    auction_id,
-                    Some(
-                        self.fetch_auction_data(hash, settlement, auction_id, &mut ex)
-                            .await?,
-                    ),
+    async fn settlement_update(
+        &self,
+        ex: &mut PgConnection,
+        event: &SettlementEvent,
+        settlement: SettlementTransaction,
+    ) -> Result<(SettlementUpdate, Outcome)> {
+        let hash = H256(event.tx_hash.0);
+        let SettlementTransaction {
+            transaction,
+            receipt,
+        } = settlement;
+
+        let mut expected_winner = None;
+        let mut duplicate_auction_claim = None;
+        let (status, metadata) = Self::recover_auction_id_from_calldata(
+            ex,
+            &transaction,
+            &receipt,
+            self.eth.contracts().settlement().address(),
+            &self.auction_ids,
+        )
+        .await?;
+        let (auction_id, auction_data, outcome) = match status {
+            AuctionIdRecoveryStatus::InvalidCalldata => {
+                // To not get stuck on indexing the same transaction over and over again, we
+                // insert the default auction ID (0)
+                (Default::default(), None, Outcome::InvalidCalldata)
+            }
+            AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id) => {
+                (auction_id, None, Outcome::Processed)
+            }
+            AuctionIdRecoveryStatus::UnexpectedSubmitter { auction_id, winner } => {
+                expected_winner = Some(winner);
+                (auction_id, None, Outcome::Processed)
+            }
+            AuctionIdRecoveryStatus::DuplicateAuctionClaim(claimed) => {
+                // The auction stays associated with the settlement that
+                // settled it first.
+                duplicate_auction_claim = Some(claimed);
+                (Default::default(), None, Outcome::DuplicateAuctionClaim)
+            }
+            AuctionIdRecoveryStatus::AddAuctionData(auction_id, settlement) => (
+                auction_id,
+                Some(
+                    self.fetch_auction_data(hash, receipt, settlement, auction_id, ex)
+                        .await?,
                 ),
-            };
+                Outcome::Processed,
+            ),
+        };
 
+        let (tx_from, tx_nonce) = sender_and_nonce(&transaction)?;
         let update = SettlementUpdate {
             block_number: event.block_number,
             log_index: event.log_index,
+            tx_hash: hash,
+            tx_from,
+            tx_nonce,
             auction_id,
             auction_data,
+            expected_winner,
+            duplicate_auction_claim,
+            metadata,
         };
-
-        tracing::debug!(?hash, ?update, "updating settlement details for tx");
-
-        Postgres::update_settlement_details(&mut ex, update.clone())
-            .await
-            .with_context(|| format!("insert_settlement_details: {update:?}"))?;
-        ex.commit().await?;
-        Ok(true)
+        Ok((update, outcome))
     }
 
+    /// Computes the observations of a settlement. The receipt was already
+    /// fetched together with the transaction (concurrently for the whole
+    /// batch) so the only round trip left here is the auction prices query.
     async fn fetch_auction_data(
         &self,
         hash: H256,
+        receipt: TransactionReceipt,
         settlement: DecodedSettlement,
         auction_id: i64,
         ex: &mut PgConnection,
     ) -> Result<AuctionData> {
-        let receipt = self
-            .eth
-            .transaction_receipt(hash)
-            .await?
-            .with_context(|| format!("no receipt {hash:?}"))?;
-        let gas_used = receipt
-            .gas_used
-            .with_context(|| format!("no gas used {hash:?}"))?;
-        let effective_gas_price = receipt
-            .effective_gas_price
-            .with_context(|| format!("no effective gas price {hash:?}"))?;
-        let auction_external_prices = Postgres::get_auction_prices(ex, auction_id)
-            .await
-            .with_context(|| {
-                format!("no external prices for auction id {auction_id:?} and tx {hash:?}")
-            })?;
+        // The rollup receipt doesn't depend on the database so it gets fetched
+        // while the auction prices are queried.
+        let (auction_external_prices, rollup) = join_in_order(
+            async {
+                Postgres::get_auction_prices(ex, auction_id)
+                    .await
+                    .with_context(|| {
+                        format!("no external prices for auction id {auction_id:?} and tx {hash:?}")
+                    })
+            },
+            self.rollup_receipt(hash),
+        )
+        .await?;
         let external_prices = ExternalPrices::try_from_auction_prices(
             self.eth.contracts().weth().address(),
             auction_external_prices.clone(),
@@ -180,30 +781,154 @@ impl OnSettlementEventUpdater {
             "observations input"
         );
 
-        // surplus and fees calculation
-        let surplus = settlement.total_surplus(&external_prices);
-        let (fee, order_executions) = {
-            let domain_separator = self.eth.contracts().settlement_domain_separator();
-            let all_fees = settlement.all_fees(&external_prices, domain_separator);
-            // total fee used for CIP20 rewards
-            let fee = all_fees
-                .iter()
-                .fold(0.into(), |acc, fees| acc + fees.native);
-            // executed surplus fees for each order execution
-            let order_executions = all_fees
-                .into_iter()
-                .map(|fee| (fee.order, fee.executed_surplus_fee().unwrap_or(0.into())))
-                .collect();
-            (fee, order_executions)
+        // The interactions the solver proposed before internalization are only
+        // known for auctions this autopilot ran itself.
+        let uninternalized = database::settlement_call_data::fetch(ex, auction_id)
+            .await
+            .context("fetch settlement call data")?
+            .and_then(|call_data| {
+                match DecodedSettlement::new(&call_data.uninternalized_call_data) {
+                    Ok(settlement) => Some(settlement),
+                    Err(err) => {
+                        tracing::warn!(
+                            ?err,
+                            ?auction_id,
+                            "could not decode uninternalized calldata"
+                        );
+                        None
+                    }
+                }
+            });
+
+        let mut fee_policies = HashMap::<OrderUid, Vec<Policy>>::new();
+        for policy in crate::database::fee_policies::fetch_by_auction(ex, auction_id)
+            .await
+            .context("fetch fee policies")?
+        {
+            fee_policies
+                .entry(OrderUid(policy.order_uid.0))
+                .or_default()
+                .push(policy.into());
+        }
+
+        // Trades of orders that are not in the orderbook are JIT orders.
+        let uids: Vec<_> = settlement
+            .order_uids(self.eth.contracts().settlement_domain_separator())
+            .into_iter()
+            .map(|uid| database::byte_array::ByteArray(uid.0))
+            .collect();
+        let known_orders: HashSet<_> = database::orders::existing_uids(ex, &uids)
+            .await
+            .context("existing_uids")?
+            .into_iter()
+            .map(|uid| OrderUid(uid.0))
+            .collect();
+
+        // Auctions ranked before the gas price was recorded don't have one.
+        let auction_gas_price = database::settlement_scores::fetch(ex, auction_id)
+            .await
+            .context("fetch settlement score")?
+            .and_then(|score| score.gas_price)
+            .and_then(|gas_price| big_decimal_to_u256(&gas_price));
+
+        let mut data = auction_data(
+            hash,
+            &receipt,
+            &settlement,
+            uninternalized.as_ref(),
+            &external_prices,
+            self.eth.contracts().settlement_domain_separator(),
+            &fee_policies,
+        )?;
+        data.gas_price_ratio = gas_price_ratio(data.effective_gas_price, auction_gas_price);
+        if let Some((rollup, receipt)) = rollup {
+            (data.l1_fee, data.total_cost) =
+                rollup_costs(rollup, &receipt, data.gas_used, data.effective_gas_price);
+        }
+        data.jit_orders = settlement.jit_trades(
+            self.eth.contracts().settlement_domain_separator(),
+            &external_prices,
+            &known_orders,
+        );
+        data.late_cancellations = self
+            .late_cancellations(ex, &receipt, &data.order_executions)
+            .await?;
+        if let Some(ratio) = data.gas_price_ratio {
+            Metrics::get()
+                .gas_price_ratio
+                .with_label_values(&[&format!("{:?}", receipt.from)])
+                .observe(ratio);
+        }
+        for cancellation in &data.late_cancellations {
+            tracing::info!(
+                ?hash,
+                order = ?cancellation.order,
+                cancelled_at = ?cancellation.cancelled_at,
+                settled_at = ?cancellation.settled_at,
+                "settled order that was cancelled after the auction was cut"
+            );
+            Metrics::get()
+                .late_settled_cancellations
+                .with_label_values(&[&format!("{:?}", cancellation.solver)])
+                .inc();
+        }
+        Ok(data)
+    }
+
+    /// The standard receipt misses the fee rollups charge for posting the
+    /// settlement data to L1. Returns `None` on chains that aren't rollups.
+    async fn rollup_receipt(&self, hash: H256) -> Result<Option<(Rollup, RollupReceipt)>> {
+        let Some(rollup) = self.eth.network().chain.rollup() else {
+            return Ok(None);
         };
+        let receipt = with_backoff(|| self.eth.rollup_receipt(hash))
+            .await
+            .context("rollup_receipt")?
+            .with_context(|| format!("no rollup receipt {hash:?}"))?;
+        Ok(Some((rollup, receipt)))
+    }
 
-        Ok(AuctionData {
-            surplus,
-            fee,
-            gas_used,
-            effective_gas_price,
-            order_executions,
-        })
+    /// Finds the executed orders that were cancelled before the block of the
+    /// settlement, i.e. after the auction containing them was cut.
+    async fn late_cancellations(
+        &self,
+        ex: &mut PgConnection,
+        receipt: &TransactionReceipt,
+        executions: &[OrderExecution],
+    ) -> Result<Vec<LateCancellation>> {
+        if executions.is_empty() {
+            return Ok(Vec::new());
+        }
+        let block = receipt
+            .block_number
+            .context("receipt is missing block number")?
+            .as_u64();
+        let timestamp = with_backoff(|| self.eth.block_timestamp(block))
+            .await
+            .context("block_timestamp")?
+            .with_context(|| format!("no block {block}"))?;
+        let settled_at = Utc
+            .timestamp_opt(timestamp.try_into().context("block timestamp overflow")?, 0)
+            .single()
+            .context("invalid block timestamp")?;
+
+        let uids: Vec<_> = executions
+            .iter()
+            .map(|execution| database::byte_array::ByteArray(execution.order.0))
+            .collect();
+        let cancelled =
+            database::late_settled_cancellations::cancelled_before(ex, &uids, settled_at)
+                .await
+                .context("cancelled_before")?;
+        Ok(cancelled
+            .into_iter()
+            .map(|order| LateCancellation {
+                order: OrderUid(order.uid.0),
+                solver: receipt.from,
+                cancelled_at: order.cancellation_timestamp,
+                settled_at,
+            })
+            .collect())
     }
 
     /// With solver driver colocation solvers are supposed to append the
@@ -211,14 +936,18 @@ impl OnSettlementEventUpdater {
     /// recover that `auction_id`. It also indicates whether the auction
     /// should be indexed with its metadata. (ie. if it comes from this
     /// environment and not from a different instance of the autopilot, e.g.
-    /// running in barn/prod). This function only returns an error
-    /// if retrying the operation makes sense.
+    /// running in barn/prod). Also returns the metadata the auction id was
+    /// recovered from. This function only returns an error if retrying the
+    /// operation makes sense.
     async fn recover_auction_id_from_calldata(
         ex: &mut PgConnection,
         tx: &Transaction,
-    ) -> Result<AuctionIdRecoveryStatus> {
+        receipt: &TransactionReceipt,
+        settlement_contract: H160,
+        auction_ids: &AuctionIdRange,
+    ) -> Result<(AuctionIdRecoveryStatus, Option<Metadata>)> {
         let tx_from = tx.from.context("tx is missing sender")?;
-        let settlement = match DecodedSettlement::new(&tx.input.0) {
+        let settlement = match decode_settlement(tx, receipt, settlement_contract) {
             Ok(settlement) => settlement,
             Err(err) => {
                 tracing::warn!(
@@ -226,47 +955,1009 @@ impl OnSettlementEventUpdater {
                     ?err,
                     "could not decode settlement tx, unclear which auction it belongs to"
                 );
-                return Ok(AuctionIdRecoveryStatus::InvalidCalldata);
+                return Ok((AuctionIdRecoveryStatus::InvalidCalldata, None));
             }
         };
-        let auction_id = match settlement.metadata {
-            Some(bytes) => i64::from_be_bytes(bytes.0),
+        let metadata = settlement.metadata.clone();
+        let auction_id = match metadata.as_ref().map(Metadata::auction_id) {
+            Some(Some(auction_id)) => auction_id,
+            Some(None) => {
+                tracing::warn!(?tx, ?metadata, "unknown version of the calldata metadata");
+                return Ok((AuctionIdRecoveryStatus::InvalidCalldata, metadata));
+            }
             None => {
                 tracing::warn!(?tx, "could not recover the auction_id from the calldata");
-                return Ok(AuctionIdRecoveryStatus::InvalidCalldata);
+                return Ok((AuctionIdRecoveryStatus::InvalidCalldata, None));
             }
         };
+        let (score, data_already_recorded) = match auction_ids.contains(auction_id) {
+            true => (
+                database::settlement_scores::fetch(ex, auction_id).await?,
+                database::settlements::already_processed(ex, auction_id).await?,
+            ),
+            // The competition data of other environments is not ours to check.
+            false => (None, false),
+        };
+        let status = recovery_status(
+            auction_id,
+            settlement,
+            tx_from,
+            auction_ids,
+            score,
+            data_already_recorded,
+        );
+        Ok((status, metadata))
+    }
+}
 
-        let score = database::settlement_scores::fetch(ex, auction_id).await?;
-        let data_already_recorded =
-            database::settlements::already_processed(ex, auction_id).await?;
-        match (score, data_already_recorded) {
-            (None, _) => {
-                tracing::debug!(
-                    auction_id,
-                    "calldata claims to settle auction that has no competition"
-                );
-                Ok(AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id))
+/// Decodes the settlement from the input of the transaction. Locating the
+/// settlement in the input of a wrapper contract requires the receipt.
+fn decode_settlement(
+    tx: &Transaction,
+    receipt: &TransactionReceipt,
+    settlement_contract: H160,
+) -> Result<DecodedSettlement, DecodingError> {
+    match DecodedSettlement::new(&tx.input.0) {
+        // The settlement was not executed by calling the settlement contract
+        // directly but through a wrapper contract.
+        Err(DecodingError::InvalidSelector) => DecodedSettlement::from_wrapped_call(
+            &tx.input.0,
+            trade_events(receipt, settlement_contract),
+        ),
+        result => result,
+    }
+}
+
+/// Some nodes return a mined transaction slightly before its receipt. Calls
+/// `fetch` until it returns a receipt with the gas information that is needed
+/// to compute the observations, at most `RECEIPT_ATTEMPTS` times. Returns
+/// `None` if the receipt is still incomplete after that.
+async fn wait_for_receipt<E, Fut>(
+    mut fetch: impl FnMut() -> Fut,
+) -> Result<Option<TransactionReceipt>, E>
+where
+    Fut: Future<Output = Result<Option<TransactionReceipt>, E>>,
+{
+    for attempt in 1..=RECEIPT_ATTEMPTS {
+        let receipt = fetch()
+            .await?
+            .filter(|receipt| receipt.gas_used.is_some() && receipt.effective_gas_price.is_some());
+        if receipt.is_some() {
+            return Ok(receipt);
+        }
+        if attempt < RECEIPT_ATTEMPTS {
+            tokio::time::sleep(RECEIPT_RETRY_DELAY).await;
+        }
+    }
+    Ok(None)
+}
+
+/// Whether a backfill recomputes the auction data of a settlement that was
+/// already associated with an auction.
+fn needs_backfill(
+    settlement: &UpdatedSettlement,
+    recompute: bool,
+    auction_ids: &AuctionIdRange,
+) -> bool {
+    auction_ids.contains(settlement.auction_id) && (recompute || !settlement.observed)
+}
+
+/// Counts the `Trade` events the settlement contract emitted in the
+/// transaction.
+fn trade_events(receipt: &TransactionReceipt, settlement_contract: H160) -> usize {
+    let trade = GPv2Settlement::raw_contract()
+        .abi
+        .event("Trade")
+        .unwrap()
+        .signature();
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == settlement_contract && log.topics.first() == Some(&trade))
+        .count()
+}
+
+/// Decides whether the auction data of a settlement claiming to settle
+/// `auction_id` gets indexed based on the competition of the auction.
+fn recovery_status(
+    auction_id: i64,
+    settlement: DecodedSettlement,
+    tx_from: H160,
+    auction_ids: &AuctionIdRange,
+    score: Option<Score>,
+    data_already_recorded: bool,
+) -> AuctionIdRecoveryStatus {
+    if !auction_ids.contains(auction_id) {
+        tracing::debug!(auction_id, "settlement belongs to a different environment");
+        Metrics::get().foreign_auction_ids.inc();
+        return AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id);
+    }
+
+    match (score, data_already_recorded) {
+        (None, _) => {
+            tracing::debug!(
+                auction_id,
+                "calldata claims to settle auction that has no competition"
+            );
+            AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id)
+        }
+        // Checked before the submitter so that no one can associate their
+        // settlement with an auction that was already settled.
+        (Some(_), true) => {
+            tracing::warn!(
+                auction_id,
+                ?tx_from,
+                "calldata claims to settle auction that was already settled"
+            );
+            Metrics::get().duplicate_auction_claims.inc();
+            AuctionIdRecoveryStatus::DuplicateAuctionClaim(auction_id)
+        }
+        (Some(score), _) if score.winner.0 != tx_from.0 => {
+            tracing::warn!(
+                auction_id,
+                ?tx_from,
+                winner = ?score.winner,
+                "solution submitted by solver other than the winner"
+            );
+            Metrics::get()
+                .settlements_with_unexpected_submitter
+                .with_label_values(&[&format!("{tx_from:?}")])
+                .inc();
+            AuctionIdRecoveryStatus::UnexpectedSubmitter {
+                auction_id,
+                winner: H160(score.winner.0),
             }
-            (Some(score), _) if score.winner.0 != tx_from.0 => {
-                tracing::warn!(
-                    auction_id,
-                    ?tx_from,
-                    winner = ?score.winner,
-                    "solution submitted by solver other than the winner"
-                );
-                Ok(AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id))
+        }
+        (Some(_), false) => AuctionIdRecoveryStatus::AddAuctionData(auction_id, settlement),
+    }
+}
+
+/// The sender and nonce of a settlement transaction. Together they identify the
+/// mined transaction even if it replaced a previously submitted one.
+fn sender_and_nonce(transaction: &Transaction) -> Result<(H160, i64)> {
+    let sender = transaction.from.context("tx is missing sender")?;
+    anyhow::ensure!(transaction.nonce <= i64::MAX.into(), "tx nonce overflow");
+    Ok((sender, transaction.nonce.as_u64() as i64))
+}
+
+/// Updates the metrics tracking how far behind updating settlement events is.
+async fn report_progress(db: &Postgres) -> Result<()> {
+    let mut ex = db.pool.acquire().await.context("acquire DB connection")?;
+    let progress = database::settlements::update_progress(&mut ex)
+        .await
+        .context("update_progress")?;
+    let metrics = Metrics::get();
+    metrics.pending_events.set(progress.pending);
+    metrics.update_lag_blocks.set(
+        progress.latest_block.unwrap_or_default()
+            - progress.latest_updated_block.unwrap_or_default(),
+    );
+    Ok(())
+}
+
+/// Computes the observations of a settlement from its receipt and decoded
+/// calldata. `uninternalized` is the settlement the solver proposed and is used
+/// to tell which interactions got internalized.
+fn auction_data(
+    hash: H256,
+    receipt: &TransactionReceipt,
+    settlement: &DecodedSettlement,
+    uninternalized: Option<&DecodedSettlement>,
+    external_prices: &ExternalPrices,
+    domain_separator: &DomainSeparator,
+    fee_policies: &HashMap<OrderUid, Vec<Policy>>,
+) -> Result<AuctionData> {
+    let gas_used = receipt
+        .gas_used
+        .with_context(|| format!("no gas used {hash:?}"))?;
+    let effective_gas_price = receipt
+        .effective_gas_price
+        .with_context(|| format!("no effective gas price {hash:?}"))?;
+
+    // surplus and fees calculation
+    let surplus = settlement.total_surplus(external_prices);
+    let executions = settlement.order_executions(external_prices, domain_separator, fee_policies);
+    // total fee used for CIP20 rewards
+    let fee = executions
+        .iter()
+        .fold(0.into(), |acc, execution| acc + execution.fees.native);
+    let protocol_fee = executions
+        .iter()
+        .fold(0.into(), |acc, execution| acc + execution.protocol_fee);
+    // executed surplus fees and amounts for each order execution
+    let order_executions = executions
+        .into_iter()
+        .map(|execution| OrderExecution {
+            order: execution.fees.order,
+            executed_fee: execution.fees.executed_surplus_fee().unwrap_or(0.into()),
+            amounts: execution.executed.map(|executed| ExecutedAmounts {
+                sell_amount: executed.sell_amount,
+                buy_amount: executed.buy_amount,
+                fee_amount: executed.fee_amount,
+                sell_price: executed.sell_price,
+                buy_price: executed.buy_price,
+                surplus: executed.surplus,
+                native_fee: execution.fees.native,
+                network_fee: execution.network_fee(),
+                protocol_fee: execution.protocol_fee,
+            }),
+        })
+        .collect();
+
+    Ok(AuctionData {
+        surplus,
+        fee,
+        network_fee: fee - protocol_fee,
+        protocol_fee,
+        gas_used,
+        effective_gas_price,
+        order_executions,
+        interactions: settlement.interaction_summaries(uninternalized),
+        gas_price_ratio: None,
+        jit_orders: Vec::new(),
+        late_cancellations: Vec::new(),
+        l1_fee: 0.into(),
+        total_cost: gas_used * effective_gas_price,
+    })
+}
+
+/// Returns the fee the rollup charged for posting the settlement data to L1
+/// and the total cost of the settlement including that fee.
+fn rollup_costs(
+    rollup: Rollup,
+    receipt: &RollupReceipt,
+    gas_used: U256,
+    effective_gas_price: U256,
+) -> (U256, U256) {
+    let execution_cost = gas_used * effective_gas_price;
+    match rollup {
+        Rollup::OpStack => {
+            let l1_fee = receipt.l1_fee.unwrap_or_default();
+            (l1_fee, execution_cost + l1_fee)
+        }
+        // The L1 gas is already included in the gas used.
+        Rollup::Arbitrum => {
+            let l1_fee = receipt.gas_used_for_l1.unwrap_or_default() * effective_gas_price;
+            (l1_fee, execution_cost)
+        }
+    }
+}
+
+/// How much more (or less) the settlement paid for gas than the gas price
+/// assumed when ranking the solutions of the auction. `None` if the gas price
+/// of the auction is unknown.
+fn gas_price_ratio(effective_gas_price: U256, auction_gas_price: Option<U256>) -> Option<f64> {
+    let auction_gas_price = auction_gas_price.filter(|price| !price.is_zero())?;
+    Some(effective_gas_price.to_f64_lossy() / auction_gas_price.to_f64_lossy())
+}
+
+/// Calls `fetch` for every item with at most `concurrency` calls in flight at
+/// a time. The results are returned in the order of the items regardless of
+/// the order in which the calls complete.
+async fn fetch_in_order<T, R, F, Fut>(items: Vec<T>, concurrency: usize, fetch: F) -> Vec<(T, R)>
+where
+    F: Fn(&T) -> Fut,
+    Fut: Future<Output = R>,
+{
+    stream::iter(items)
+        .map(|item| {
+            let result = fetch(&item);
+            async move { (item, result.await) }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await
+}
+
+/// Awaits both futures concurrently. Unlike with `futures::try_join!` an error
+/// of `first` takes precedence no matter which future fails first so errors
+/// get reported as if the futures ran one after the other.
+async fn join_in_order<A, B>(
+    first: impl Future<Output = Result<A>>,
+    second: impl Future<Output = Result<B>>,
+) -> Result<(A, B)> {
+    let (first, second) = futures::join!(first, second);
+    Ok((first?, second?))
+}
+
+/// Retries `call` with exponential backoff until it succeeds or ran out of
+/// retries. Used for RPC requests that may fail transiently.
+async fn with_backoff<T, E, Fut>(mut call: impl FnMut() -> Fut) -> Result<T, E>
+where
+    E: Debug,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = RPC_INITIAL_BACKOFF;
+    for _ in 0..RPC_RETRIES {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                tracing::debug!(?err, ?backoff, "RPC request failed, retrying");
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
             }
-            (Some(_), true) => {
-                tracing::warn!(
-                    auction_id,
-                    "settlement data already recorded for this auction"
-                );
-                Ok(AuctionIdRecoveryStatus::DoNotAddAuctionData(auction_id))
+        }
+    }
+    call().await
+}
+
+/// Counts how often updating settlement events failed in a row.
+#[derive(Debug, Default)]
+struct FailedAttempts(HashMap<(i64, i64), usize>);
+
+/// What to do with a settlement event whose update failed. Both variants
+/// carry the number of consecutive failures.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    Retry(usize),
+    GiveUp(usize),
+}
+
+impl FailedAttempts {
+    /// Records a failed update of the settlement event. Once the event failed
+    /// `max_attempts` times in a row it is given up on and forgotten.
+    fn record(&mut self, event: &SettlementEvent, max_attempts: NonZeroUsize) -> Failure {
+        let key = (event.block_number, event.log_index);
+        let failed = self.0.entry(key).or_default();
+        *failed += 1;
+        if *failed < max_attempts.get() {
+            return Failure::Retry(*failed);
+        }
+        Failure::GiveUp(self.0.remove(&key).unwrap_or_default())
+    }
+
+    fn clear(&mut self, event: &SettlementEvent) {
+        self.0.remove(&(event.block_number, event.log_index));
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "on_settlement_event_updater")]
+struct Metrics {
+    /// Number of failed settlement event updates by whether they will be
+    /// retried or were given up on.
+    #[metric(labels("outcome"))]
+    failed_updates: prometheus::IntCounterVec,
+
+    /// Number of times updating a settlement event was postponed because the
+    /// node did not return the receipt of its transaction yet.
+    pending_receipts: prometheus::IntCounter,
+
+    /// Number of settlement events whose details still need to be updated.
+    pending_events: prometheus::IntGauge,
+
+    /// Number of blocks between the newest settlement event and the newest
+    /// one whose details were updated.
+    update_lag_blocks: prometheus::IntGauge,
+
+    /// Time it took to update a single settlement event by outcome.
+    #[metric(labels("outcome"))]
+    event_processing_time: prometheus::HistogramVec,
+
+    /// Number of settlements of auction ids outside of the range of this
+    /// environment.
+    foreign_auction_ids: prometheus::IntCounter,
+
+    /// Number of settlements claiming to settle an auction that a different
+    /// settlement already settled.
+    duplicate_auction_claims: prometheus::IntCounter,
+
+    /// Number of settled orders that were cancelled before the block of the
+    /// settlement.
+    #[metric(labels("solver"))]
+    late_settled_cancellations: prometheus::IntCounterVec,
+
+    /// Number of settlements not submitted by the winner of the auction they
+    /// settle by the account that actually submitted them.
+    #[metric(labels("solver"))]
+    settlements_with_unexpected_submitter: prometheus::IntCounterVec,
+
+    /// Effective gas price of settlements divided by the gas price assumed
+    /// when ranking the solutions of their auction by the submitting solver.
+    #[metric(labels("solver"), buckets(0.5, 0.8, 0.9, 1., 1.1, 1.25, 1.5, 2., 3.))]
+    gas_price_ratio: prometheus::HistogramVec,
+
+    /// Unix timestamp of the last successful update.
+    last_successful_update: prometheus::IntGauge,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(observe::metrics::get_storage_registry()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    fn event(block_number: i64, log_index: i64) -> SettlementEvent {
+        SettlementEvent {
+            block_number,
+            log_index,
+            tx_hash: Default::default(),
+        }
+    }
+
+    #[test]
+    fn extracts_sender_and_nonce() {
+        let sender = H160::from_low_u64_be(1);
+        let transaction = Transaction {
+            from: Some(sender),
+            nonce: 42.into(),
+            ..Default::default()
+        };
+        assert_eq!(sender_and_nonce(&transaction).unwrap(), (sender, 42));
+
+        let overflowing = Transaction {
+            nonce: U256::from(i64::MAX) + 1,
+            ..transaction.clone()
+        };
+        assert!(sender_and_nonce(&overflowing).is_err());
+
+        let without_sender = Transaction {
+            from: None,
+            ..transaction
+        };
+        assert!(sender_and_nonce(&without_sender).is_err());
+    }
+
+    fn empty_settlement() -> DecodedSettlement {
+        DecodedSettlement {
+            tokens: Default::default(),
+            clearing_prices: Default::default(),
+            trades: Default::default(),
+            interactions: Default::default(),
+            metadata: None,
+        }
+    }
+
+    fn score(auction_id: i64, winner: H160) -> Score {
+        Score {
+            auction_id,
+            winner: database::byte_array::ByteArray(winner.0),
+            winning_score: Default::default(),
+            reference_score: Default::default(),
+            block_deadline: 0,
+            simulation_block: 0,
+            gas_price: None,
+        }
+    }
+
+    #[test]
+    fn computes_gas_price_ratio() {
+        assert_eq!(gas_price_ratio(30.into(), Some(20.into())), Some(1.5));
+        assert_eq!(gas_price_ratio(15.into(), Some(20.into())), Some(0.75));
+        // The gas price of the auction is unknown.
+        assert_eq!(gas_price_ratio(30.into(), None), None);
+        assert_eq!(gas_price_ratio(30.into(), Some(0.into())), None);
+    }
+
+    #[test]
+    fn adds_l1_fee_of_rollups() {
+        let receipt: RollupReceipt = serde_json::from_value(serde_json::json!({
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "gasUsed": "0x30d40",
+            "effectiveGasPrice": "0x3b9aca00",
+            "l1Fee": "0x2540be400",
+            "l1GasUsed": "0x640",
+            "l1GasPrice": "0x5f5e100",
+        }))
+        .unwrap();
+        assert_eq!(
+            rollup_costs(
+                Rollup::OpStack,
+                &receipt,
+                200_000.into(),
+                1_000_000_000.into()
+            ),
+            (10_000_000_000u64.into(), 200_010_000_000_000u64.into())
+        );
+
+        let receipt: RollupReceipt = serde_json::from_value(serde_json::json!({
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "gasUsed": "0x30d40",
+            "effectiveGasPrice": "0x5f5e100",
+            "gasUsedForL1": "0xc350",
+        }))
+        .unwrap();
+        assert_eq!(
+            rollup_costs(
+                Rollup::Arbitrum,
+                &receipt,
+                200_000.into(),
+                100_000_000.into()
+            ),
+            (5_000_000_000_000u64.into(), 20_000_000_000_000u64.into())
+        );
+
+        // Receipts without the rollup fields, e.g. on mainnet.
+        let receipt: RollupReceipt = serde_json::from_value(serde_json::json!({
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+        }))
+        .unwrap();
+        assert_eq!(receipt, RollupReceipt::default());
+        for rollup in [Rollup::OpStack, Rollup::Arbitrum] {
+            assert_eq!(
+                rollup_costs(rollup, &receipt, 21000.into(), 1.into()),
+                (0.into(), 21000.into())
+            );
+        }
+    }
+
+    #[test]
+    fn backfills_settlements_of_this_environment() {
+        let auction_ids = AuctionIdRange {
+            start: 10,
+            end: None,
+        };
+        let settlement = |auction_id, observed| UpdatedSettlement {
+            block_number: 1,
+            log_index: 0,
+            tx_hash: Default::default(),
+            auction_id,
+            observed,
+        };
+        let range = [
+            settlement(10, true),
+            settlement(11, false),
+            // Belongs to a different environment.
+            settlement(1, false),
+        ];
+        let backfilled = |recompute| -> Vec<_> {
+            range
+                .iter()
+                .filter(|settlement| needs_backfill(settlement, recompute, &auction_ids))
+                .map(|settlement| settlement.auction_id)
+                .collect()
+        };
+        assert_eq!(backfilled(false), [11]);
+        assert_eq!(backfilled(true), [10, 11]);
+    }
+
+    #[test]
+    fn recovery_status_checks_submitter() {
+        let winner = H160::from_low_u64_be(1);
+        let other_solver = H160::from_low_u64_be(0x1117);
+        let auction_ids = AuctionIdRange {
+            start: 10,
+            end: Some(20),
+        };
+        let unexpected_submitters = || {
+            Metrics::get()
+                .settlements_with_unexpected_submitter
+                .with_label_values(&[&format!("{other_solver:?}")])
+                .get()
+        };
+
+        let status = recovery_status(
+            10,
+            empty_settlement(),
+            winner,
+            &auction_ids,
+            Some(score(10, winner)),
+            false,
+        );
+        assert!(matches!(
+            status,
+            AuctionIdRecoveryStatus::AddAuctionData(10, _)
+        ));
+
+        // The auction was already settled. The claim is a duplicate no matter
+        // who submitted it.
+        let duplicates = Metrics::get().duplicate_auction_claims.get();
+        for submitter in [winner, other_solver] {
+            let status = recovery_status(
+                10,
+                empty_settlement(),
+                submitter,
+                &auction_ids,
+                Some(score(10, winner)),
+                true,
+            );
+            assert!(matches!(
+                status,
+                AuctionIdRecoveryStatus::DuplicateAuctionClaim(10)
+            ));
+        }
+        assert!(Metrics::get().duplicate_auction_claims.get() >= duplicates + 2);
+
+        let before = unexpected_submitters();
+        let status = recovery_status(
+            19,
+            empty_settlement(),
+            other_solver,
+            &auction_ids,
+            Some(score(19, winner)),
+            false,
+        );
+        assert!(matches!(
+            status,
+            AuctionIdRecoveryStatus::UnexpectedSubmitter { auction_id: 19, winner: w } if w == winner
+        ));
+        assert_eq!(unexpected_submitters(), before + 1);
+
+        // Settlements of auctions of a different deployment are not flagged
+        // even if there is competition data with another winner for the id.
+        let status = recovery_status(
+            20,
+            empty_settlement(),
+            other_solver,
+            &auction_ids,
+            Some(score(20, winner)),
+            false,
+        );
+        assert!(matches!(
+            status,
+            AuctionIdRecoveryStatus::DoNotAddAuctionData(20)
+        ));
+        assert_eq!(unexpected_submitters(), before + 1);
+
+        let status = recovery_status(11, empty_settlement(), winner, &auction_ids, None, false);
+        assert!(matches!(
+            status,
+            AuctionIdRecoveryStatus::DoNotAddAuctionData(11)
+        ));
+    }
+
+    #[test]
+    fn auction_id_range_contains() {
+        let range = AuctionIdRange {
+            start: 100,
+            end: Some(200),
+        };
+        assert!(range.contains(150));
+        assert!(!range.contains(50));
+        assert!(!range.contains(250));
+        // The start is inclusive and the end exclusive.
+        assert!(!range.contains(99));
+        assert!(range.contains(100));
+        assert!(range.contains(199));
+        assert!(!range.contains(200));
+
+        let unbounded = AuctionIdRange {
+            start: 100,
+            end: None,
+        };
+        assert!(!unbounded.contains(99));
+        assert!(unbounded.contains(100));
+        assert!(unbounded.contains(i64::MAX));
+        assert!(AuctionIdRange::default().contains(0));
+        assert!(!AuctionIdRange::default().contains(-1));
+    }
+
+    #[tokio::test]
+    async fn fetches_whole_batch_in_stable_order() {
+        let events: Vec<_> = (0..20).map(|i| event(i / 4, i % 4)).collect();
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let fetched = fetch_in_order(events, 5, |event| {
+            let index = event.block_number * 4 + event.log_index;
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                // Later events complete first.
+                tokio::time::sleep(Duration::from_millis(20 - index as u64)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                index
             }
-            (Some(_), false) => Ok(AuctionIdRecoveryStatus::AddAuctionData(
-                auction_id, settlement,
-            )),
+        })
+        .await;
+
+        // All pending events are drained in a single call and in the order in
+        // which they were emitted.
+        assert_eq!(fetched.len(), 20);
+        for (i, (event, index)) in fetched.into_iter().enumerate() {
+            assert_eq!(index, i as i64);
+            assert_eq!(
+                (event.block_number, event.log_index),
+                (index / 4, index % 4)
+            );
         }
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanently_failing_receipt_is_given_up() {
+        let max_attempts = NonZeroUsize::new(3).unwrap();
+        let event = event(1, 0);
+        let mut attempts = FailedAttempts::default();
+        let calls = AtomicUsize::new(0);
+
+        let mut failures = Vec::new();
+        for _ in 0..max_attempts.get() {
+            let receipt = with_backoff(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err::<TransactionReceipt, _>("receipt unavailable") }
+            })
+            .await;
+            assert!(receipt.is_err());
+            failures.push(attempts.record(&event, max_attempts));
+        }
+
+        // Every update attempt retries the request before failing.
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            max_attempts.get() * (RPC_RETRIES as usize + 1)
+        );
+        assert_eq!(
+            failures,
+            [Failure::Retry(1), Failure::Retry(2), Failure::GiveUp(3)]
+        );
+        // A re-enqueued event gets the full number of attempts again.
+        assert_eq!(attempts.record(&event, max_attempts), Failure::Retry(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_failure_eventually_succeeds() {
+        let calls = AtomicUsize::new(0);
+        let start = tokio::time::Instant::now();
+        let receipt = with_backoff(|| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call {
+                    0 | 1 => Err("connection reset"),
+                    _ => Ok(call),
+                }
+            }
+        })
+        .await;
+        assert_eq!(receipt, Ok(2));
+        // Backed off before both retries.
+        assert!(start.elapsed() >= RPC_INITIAL_BACKOFF * 3);
+
+        // A failed attempt doesn't count against an event once it succeeded.
+        let max_attempts = NonZeroUsize::new(2).unwrap();
+        let event = event(1, 0);
+        let mut attempts = FailedAttempts::default();
+        assert_eq!(attempts.record(&event, max_attempts), Failure::Retry(1));
+        attempts.clear(&event);
+        assert_eq!(attempts.record(&event, max_attempts), Failure::Retry(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missing_receipt_is_retried() {
+        let complete = TransactionReceipt {
+            gas_used: Some(21000.into()),
+            effective_gas_price: Some(1.into()),
+            ..Default::default()
+        };
+        let responses = |responses: Vec<Option<TransactionReceipt>>| {
+            let calls = AtomicUsize::new(0);
+            move || {
+                let response = responses[calls.fetch_add(1, Ordering::SeqCst)].clone();
+                async move { Ok::<_, ()>(response) }
+            }
+        };
+
+        // The receipt is missing at first.
+        let start = tokio::time::Instant::now();
+        let receipt = wait_for_receipt(responses(vec![None, Some(complete.clone())])).await;
+        assert_eq!(receipt, Ok(Some(complete.clone())));
+        assert!(start.elapsed() >= RECEIPT_RETRY_DELAY);
+
+        // The receipt lacks the gas information at first.
+        let incomplete = TransactionReceipt {
+            gas_used: Some(21000.into()),
+            ..Default::default()
+        };
+        let receipt =
+            wait_for_receipt(responses(vec![Some(incomplete), Some(complete.clone())])).await;
+        assert_eq!(receipt, Ok(Some(complete)));
+
+        // Still missing after all attempts so the update gets postponed.
+        let receipt = wait_for_receipt(responses(vec![None; RECEIPT_ATTEMPTS as usize])).await;
+        assert_eq!(receipt, Ok(None));
+
+        // Errors are not retried here since the request is already retried
+        // with backoff.
+        let calls = AtomicUsize::new(0);
+        let receipt = wait_for_receipt(|| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<Option<TransactionReceipt>, _>("connection reset") }
+        })
+        .await;
+        assert_eq!(receipt, Err("connection reset"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transactions_and_receipts_are_fetched_concurrently() {
+        let delay = Duration::from_millis(100);
+        let events: Vec<_> = (0..5).map(|i| event(i, 0)).collect();
+        let delayed = |value: i64| async move {
+            tokio::time::sleep(delay).await;
+            Ok::<_, anyhow::Error>(value)
+        };
+
+        let start = tokio::time::Instant::now();
+        let fetched = fetch_in_order(events, MAX_CONCURRENT_RPC_REQUESTS, |event| {
+            let block = event.block_number;
+            async move { futures::try_join!(delayed(block), delayed(-block)) }
+        })
+        .await;
+
+        // Fetching sequentially would take 10 delays.
+        assert!(start.elapsed() < delay * 2);
+        for (event, result) in fetched {
+            assert_eq!(result.unwrap(), (event.block_number, -event.block_number));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn auction_prices_and_rollup_receipt_are_fetched_concurrently() {
+        let delay = Duration::from_millis(100);
+        let delayed = |value: Result<i64>| async move {
+            tokio::time::sleep(delay).await;
+            value
+        };
+
+        let start = tokio::time::Instant::now();
+        let result = join_in_order(delayed(Ok(1)), delayed(Ok(2))).await;
+        assert_eq!(result.unwrap(), (1, 2));
+        // Fetching sequentially would take two delays.
+        assert_eq!(start.elapsed(), delay);
+
+        // The error of the prices query gets reported even if the receipt
+        // fails first.
+        let prices = async {
+            tokio::time::sleep(delay * 2).await;
+            Err::<i64, _>(anyhow::anyhow!("no external prices"))
+        };
+        let err = join_in_order(prices, delayed(Err(anyhow::anyhow!("no rollup receipt"))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no external prices");
+        let err = join_in_order(
+            delayed(Ok(1)),
+            delayed(Err(anyhow::anyhow!("no rollup receipt"))),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no rollup receipt");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancellation_interrupts_iteration() {
+        let shutdown = CancellationToken::new();
+        assert_eq!(cancellable(&shutdown, async { 1 }).await, Some(1));
+
+        // An update that takes long gets interrupted as soon as the token is
+        // cancelled.
+        let start = tokio::time::Instant::now();
+        let cancel = {
+            let shutdown = shutdown.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                shutdown.cancel();
+            }
+        };
+        let update = tokio::time::sleep(Duration::from_secs(60));
+        let (result, ()) = tokio::join!(cancellable(&shutdown, update), cancel);
+        assert_eq!(result, None);
+        assert!(start.elapsed() < Duration::from_secs(60));
+
+        // Once cancelled nothing runs anymore.
+        assert_eq!(cancellable(&shutdown, async { 1 }).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn closed_block_stream_is_resubscribed() {
+        let block = |number| BlockInfo {
+            number,
+            ..Default::default()
+        };
+        // The first two subscriptions close right away.
+        let subscriptions = AtomicUsize::new(0);
+        let mut blocks = Blocks::new(|| {
+            let blocks = match subscriptions.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => vec![],
+                _ => vec![block(1), block(2)],
+            };
+            stream::iter(blocks)
+        });
+
+        let start = tokio::time::Instant::now();
+        assert_eq!(blocks.next().await, None);
+        assert_eq!(blocks.next().await, None);
+        // Backed off before both re-subscriptions.
+        assert!(start.elapsed() >= BLOCK_STREAM_INITIAL_BACKOFF * 3);
+        assert_eq!(subscriptions.load(Ordering::SeqCst), 3);
+
+        assert_eq!(blocks.next().await, Some(block(1)));
+        assert_eq!(blocks.backoff, BLOCK_STREAM_INITIAL_BACKOFF);
+        assert_eq!(blocks.next().await, Some(block(2)));
+    }
+
+    #[test]
+    fn auction_data_requires_gas_of_receipt() {
+        let settlement = empty_settlement();
+        let external_prices =
+            ExternalPrices::try_from_auction_prices(Default::default(), Default::default())
+                .unwrap();
+        let hash = H256::from_low_u64_be(1);
+
+        let err = auction_data(
+            hash,
+            &Default::default(),
+            &settlement,
+            None,
+            &external_prices,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), format!("no gas used {hash:?}"));
+
+        let receipt = TransactionReceipt {
+            gas_used: Some(21000.into()),
+            effective_gas_price: Some(1.into()),
+            ..Default::default()
+        };
+        let data = auction_data(
+            hash,
+            &receipt,
+            &settlement,
+            None,
+            &external_prices,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(data.gas_used, 21000.into());
+        assert_eq!(data.effective_gas_price, 1.into());
+        // Chains that are not rollups don't charge an L1 fee.
+        assert_eq!(data.l1_fee, 0.into());
+        assert_eq!(data.total_cost, 21000.into());
+        assert!(data.order_executions.is_empty());
+        assert!(data.interactions.is_empty());
+        assert_eq!(data.fee, data.network_fee + data.protocol_fee);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_progress_metrics_follow_queue() {
+        let db = Postgres::with_defaults().await.unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let metrics = Metrics::get();
+        let mut ex = db.pool.acquire().await.unwrap();
+
+        for block_number in [1, 2, 3] {
+            let event = database::events::EventIndex {
+                block_number,
+                log_index: 0,
+            };
+            database::events::insert_settlement(&mut ex, &event, &Default::default())
+                .await
+                .unwrap();
+        }
+        report_progress(&db).await.unwrap();
+        assert_eq!(metrics.pending_events.get(), 3);
+        assert_eq!(metrics.update_lag_blocks.get(), 3);
+
+        database::settlements::update_settlement_auction(&mut ex, 1, 0, 1)
+            .await
+            .unwrap();
+        database::settlements::update_settlement_auction(&mut ex, 2, 0, 2)
+            .await
+            .unwrap();
+        report_progress(&db).await.unwrap();
+        assert_eq!(metrics.pending_events.get(), 1);
+        assert_eq!(metrics.update_lag_blocks.get(), 1);
+
+        database::settlements::update_settlement_auction(&mut ex, 3, 0, 3)
+            .await
+            .unwrap();
+        report_progress(&db).await.unwrap();
+        assert_eq!(metrics.pending_events.get(), 0);
+        assert_eq!(metrics.update_lag_blocks.get(), 0);
     }
 }
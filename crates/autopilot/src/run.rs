@@ -14,6 +14,7 @@ use {
         domain,
         event_updater::EventUpdater,
         infra::{self},
+        on_settlement_event_updater::Heartbeat,
         run_loop::RunLoop,
         shadow,
         solvable_orders::SolvableOrdersCache,
@@ -59,6 +60,7 @@ use {
         sync::{Arc, RwLock},
         time::{Duration, Instant},
     },
+    tokio_util::sync::CancellationToken,
     tracing::Instrument,
     url::Url,
 };
@@ -66,6 +68,9 @@ use {
 pub struct Liveness {
     max_auction_age: Duration,
     last_auction_time: RwLock<Instant>,
+    /// Heartbeat of the settlement event updater and the maximum time
+    /// between two of its updates.
+    settlement_updates: Option<(Arc<Heartbeat>, Duration)>,
 }
 
 #[async_trait::async_trait]
@@ -73,7 +78,11 @@ impl LivenessChecking for Liveness {
     async fn is_alive(&self) -> bool {
         let last_auction_time = self.last_auction_time.read().unwrap();
         let auction_age = last_auction_time.elapsed();
-        auction_age <= self.max_auction_age
+        let updating_settlements = self
+            .settlement_updates
+            .as_ref()
+            .map_or(true, |(heartbeat, max_age)| heartbeat.age() <= *max_age);
+        auction_age <= self.max_auction_age && updating_settlements
     }
 }
 
@@ -82,9 +91,17 @@ impl Liveness {
         Liveness {
             max_auction_age,
             last_auction_time: RwLock::new(Instant::now()),
+            settlement_updates: None,
         }
     }
 
+    /// Also fails the liveness check if the settlement event updater didn't
+    /// complete an update for longer than `max_age`.
+    pub fn with_settlement_updates(mut self, heartbeat: Arc<Heartbeat>, max_age: Duration) -> Self {
+        self.settlement_updates = Some((heartbeat, max_age));
+        self
+    }
+
     pub fn auction(&self) {
         *self.last_auction_time.write().unwrap() = Instant::now();
     }
@@ -129,9 +146,13 @@ pub async fn start(args: impl Iterator<Item = String>) {
 pub async fn run(args: Arguments) {
     assert!(args.shadow.is_none(), "cannot run in shadow mode");
 
-    let db = Postgres::new(args.db_url.as_str(), args.insert_batch_size)
-        .await
-        .unwrap();
+    let db = Postgres::new(
+        args.db_url.as_str(),
+        args.insert_batch_size,
+        args.shared.auction_retention,
+    )
+    .await
+    .unwrap();
     crate::database::run_database_metrics_work(db.clone());
 
     let http_factory = HttpClientFactory::new(&args.http_client);
@@ -459,7 +480,7 @@ pub async fn run(args: Arguments) {
     let quoter = Arc::new(OrderQuoter::new(
         price_estimator,
         native_price_estimator.clone(),
-        gas_price_estimator,
+        gas_price_estimator.clone(),
         Arc::new(db.clone()),
         order_quoting::Validity {
             eip1271_onchain_quote: chrono::Duration::from_std(
@@ -560,17 +581,30 @@ pub async fn run(args: Arguments) {
         .await
         .expect("failed to perform initial solvable orders update");
 
-    let liveness = Arc::new(Liveness::new(args.max_auction_age));
+    let heartbeat = Arc::new(Heartbeat::default());
+    let liveness = Arc::new(
+        Liveness::new(args.max_auction_age)
+            .with_settlement_updates(heartbeat.clone(), args.max_settlement_update_age),
+    );
     shared::metrics::serve_metrics(liveness.clone(), args.metrics_address);
 
     let on_settlement_event_updater =
         crate::on_settlement_event_updater::OnSettlementEventUpdater {
             eth: eth.clone(),
             db: db.clone(),
+            batch_size: args.settlement_update_batch_size,
+            max_update_attempts: args.settlement_update_max_attempts,
+            requeue_failed_updates: args.requeue_failed_settlement_updates,
+            auction_ids: crate::on_settlement_event_updater::AuctionIdRange {
+                start: args.auction_id_range_start,
+                end: args.auction_id_range_end,
+            },
+            heartbeat,
         };
-    tokio::task::spawn(
+    let shutdown = CancellationToken::new();
+    let on_settlement_event_updater = tokio::task::spawn(
         on_settlement_event_updater
-            .run_forever()
+            .run(shutdown.clone())
             .instrument(tracing::info_span!("on_settlement_event_updater")),
     );
 
@@ -615,9 +649,26 @@ pub async fn run(args: Arguments) {
         in_flight_orders: Default::default(),
         persistence: persistence.clone(),
         liveness: liveness.clone(),
+        gas_price_estimator,
     };
-    run.run_forever().await;
-    unreachable!("run loop exited");
+    tokio::select! {
+        _ = run.run_forever() => unreachable!("run loop exited"),
+        _ = shutdown_signal() => tracing::info!("received shutdown signal"),
+    }
+    shutdown.cancel();
+    if let Err(err) = on_settlement_event_updater.await {
+        tracing::error!(?err, "on settlement event updater panicked");
+    }
+}
+
+/// Resolves once the process is asked to terminate.
+async fn shutdown_signal() {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .expect("install SIGTERM handler");
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result.expect("install SIGINT handler"),
+        _ = sigterm.recv() => (),
+    }
 }
 
 async fn shadow_mode(args: Arguments) -> ! {
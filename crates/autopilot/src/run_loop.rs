@@ -15,6 +15,7 @@ use {
     anyhow::Result,
     chrono::Utc,
     database::order_events::OrderEventLabel,
+    gas_estimation::GasPriceEstimating,
     itertools::Itertools,
     model::solver_competition::{
         CompetitionAuction,
@@ -51,6 +52,7 @@ pub struct RunLoop {
     pub solve_deadline: Duration,
     pub in_flight_orders: Arc<Mutex<Option<InFlightOrders>>>,
     pub liveness: Arc<Liveness>,
+    pub gas_price_estimator: Arc<dyn GasPriceEstimating>,
 }
 
 impl RunLoop {
@@ -166,6 +168,15 @@ impl RunLoop {
                 .map(|participant| participant.solution.account)
                 .collect::<HashSet<_>>();
 
+            // Kept to compare it with the gas price the settlement pays.
+            let gas_price = match self.gas_price_estimator.estimate().await {
+                Ok(gas_price) => Some(U256::from_f64_lossy(gas_price.effective_gas_price())),
+                Err(err) => {
+                    tracing::warn!(?err, "failed to estimate gas price of auction");
+                    None
+                }
+            };
+
             let mut prices = BTreeMap::new();
             let mut fee_policies = Vec::new();
             let block_deadline = competition_simulation_block
@@ -263,6 +274,7 @@ impl RunLoop {
                 prices,
                 block_deadline,
                 competition_simulation_block,
+                gas_price,
                 call_data,
                 uninternalized_call_data,
                 competition_table,
@@ -286,7 +298,7 @@ impl RunLoop {
 
             tracing::info!(driver = %driver.name, "settling");
             let submission_start = Instant::now();
-            match self.settle(driver, solution).await {
+            match self.settle(driver, auction_id, solution).await {
                 Ok(()) => Metrics::settle_ok(driver, submission_start.elapsed()),
                 Err(err) => {
                     Metrics::settle_err(driver, &err, submission_start.elapsed());
@@ -415,7 +427,12 @@ impl RunLoop {
 
     /// Execute the solver's solution. Returns Ok when the corresponding
     /// transaction has been mined.
-    async fn settle(&self, driver: &infra::Driver, solved: &Solution) -> Result<(), SettleError> {
+    async fn settle(
+        &self,
+        driver: &infra::Driver,
+        auction_id: domain::AuctionId,
+        solved: &Solution,
+    ) -> Result<(), SettleError> {
         let order_ids = solved.order_ids().copied().collect();
         self.persistence
             .store_order_events(order_ids, OrderEventLabel::Executing);
@@ -424,11 +441,21 @@ impl RunLoop {
             solution_id: solved.id,
         };
 
-        let tx_hash = driver
+        let response = driver
             .settle(&request, self.max_settlement_transaction_wait)
             .await
-            .map_err(SettleError::Failure)?
-            .tx_hash;
+            .map_err(SettleError::Failure)?;
+        let tx_hash = response.tx_hash;
+
+        if let Some(submitted) = response.submitted_tx_hash {
+            if let Err(err) = self
+                .persistence
+                .store_settlement_submission(auction_id, solved.account, submitted)
+                .await
+            {
+                tracing::warn!(?err, ?submitted, "failed to store settlement submission");
+            }
+        }
 
         *self.in_flight_orders.lock().await = Some(InFlightOrders {
             tx_hash,
@@ -738,11 +738,16 @@ mod tests {
 
         let native_price_estimator = CachingNativePriceEstimator::new(
             Box::new(native_price_estimator),
+            Default::default(),
             Duration::from_secs(10),
             Duration::MAX,
             None,
             Default::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
         let metrics = Metrics::instance(observe::metrics::get_storage_registry()).unwrap();
 
@@ -1,6 +1,13 @@
-use {crate::AppId, sqlx::PgConnection};
+use {
+    crate::AppId,
+    sqlx::{
+        types::chrono::{DateTime, Utc},
+        PgConnection,
+    },
+};
 
-/// Tries to associate the contract app data with the full app data.
+/// Tries to associate the contract app data with the full app data and the
+/// app code extracted from it.
 ///
 /// If this contract app data already existed then the existing full app data is
 /// returned, otherwise `None` is returned.
@@ -8,11 +15,12 @@ pub async fn insert(
     ex: &mut PgConnection,
     contract_app_data: &AppId,
     full_app_data: &[u8],
+    app_code: Option<&str>,
 ) -> Result<Option<Vec<u8>>, sqlx::Error> {
     const QUERY: &str = r#"
 WITH inserted AS (
-    INSERT INTO app_data (contract_app_data, full_app_data)
-    VALUES ($1, $2)
+    INSERT INTO app_data (contract_app_data, full_app_data, app_code)
+    VALUES ($1, $2, $3)
     -- returns null on conflict
     ON CONFLICT DO NOTHING
     -- returns TRUE if the insertion succeeded
@@ -29,10 +37,27 @@ SELECT
     sqlx::query_scalar(QUERY)
         .bind(contract_app_data)
         .bind(full_app_data)
+        .bind(app_code)
         .fetch_one(ex)
         .await
 }
 
+pub async fn fetch_app_code(
+    ex: &mut PgConnection,
+    contract_app_data: &AppId,
+) -> Result<Option<String>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT app_code
+FROM app_data
+WHERE contract_app_data = $1
+;"#;
+    sqlx::query_scalar::<_, Option<String>>(QUERY)
+        .bind(contract_app_data)
+        .fetch_optional(ex)
+        .await
+        .map(Option::flatten)
+}
+
 pub async fn fetch(
     ex: &mut PgConnection,
     contract_app_data: &AppId,
@@ -48,9 +73,70 @@ WHERE contract_app_data = $1
         .await
 }
 
+/// How some contract app data is used by orders.
+#[derive(Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct Usage {
+    /// Whether full app data is stored for the contract app data.
+    pub registered: bool,
+    pub registration_timestamp: Option<DateTime<Utc>>,
+    /// Number of orders referencing the contract app data.
+    pub orders: i64,
+    /// Creation timestamp of the most recent order referencing the contract
+    /// app data.
+    pub latest_order: Option<DateTime<Utc>>,
+}
+
+/// Returns how the contract app data is used. The contract app data doesn't
+/// need to be registered since orders can reference unknown app data.
+pub async fn usage(ex: &mut PgConnection, contract_app_data: &AppId) -> Result<Usage, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    EXISTS (SELECT 1 FROM app_data WHERE contract_app_data = $1) AS registered,
+    (
+        SELECT registration_timestamp FROM app_data WHERE contract_app_data = $1
+    ) AS registration_timestamp,
+    COUNT(*) AS orders,
+    MAX(o.creation_timestamp) AS latest_order
+FROM orders o
+WHERE o.app_data = $1
+;"#;
+    sqlx::query_as(QUERY)
+        .bind(contract_app_data)
+        .fetch_one(ex)
+        .await
+}
+
+/// Contract app data registered before `registered_before` that isn't
+/// referenced by any order, ordered from the oldest registration. App data
+/// registered before registration timestamps got recorded counts as old.
+pub async fn unused(
+    ex: &mut PgConnection,
+    registered_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<AppId>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT ad.contract_app_data
+FROM app_data ad
+WHERE
+    COALESCE(ad.registration_timestamp < $1, true) AND
+    NOT EXISTS (SELECT 1 FROM orders o WHERE o.app_data = ad.contract_app_data)
+ORDER BY ad.registration_timestamp NULLS FIRST, ad.contract_app_data
+LIMIT $2
+;"#;
+    sqlx::query_scalar(QUERY)
+        .bind(registered_before)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::byte_array::ByteArray, sqlx::Connection};
+    use {
+        super::*,
+        crate::byte_array::ByteArray,
+        sqlx::{types::chrono::TimeZone, Connection},
+    };
 
     #[tokio::test]
     #[ignore]
@@ -66,19 +152,122 @@ mod tests {
         assert!(result.is_none());
 
         let full = vec![1u8];
-        let result = insert(&mut db, &contract, &full).await.unwrap();
+        let result = insert(&mut db, &contract, &full, Some("app"))
+            .await
+            .unwrap();
         assert_eq!(result, None);
 
         // now exists
         let result = fetch(&mut db, &contract).await.unwrap();
         assert_eq!(result, Some(full.clone()));
+        let result = fetch_app_code(&mut db, &contract).await.unwrap();
+        assert_eq!(result.as_deref(), Some("app"));
 
         // insert again with same app data
-        let result = insert(&mut db, &contract, &full).await.unwrap();
+        let result = insert(&mut db, &contract, &full, Some("app"))
+            .await
+            .unwrap();
         assert_eq!(result, Some(full.clone()));
 
         // insert again with different app data fails
-        let result = insert(&mut db, &contract, &[4, 2]).await.unwrap();
-        assert_eq!(result, Some(full));
+        let result = insert(&mut db, &contract, &[4, 2], Some("other"))
+            .await
+            .unwrap();
+        assert_eq!(result, Some(full.clone()));
+        let result = fetch_app_code(&mut db, &contract).await.unwrap();
+        assert_eq!(result.as_deref(), Some("app"));
+
+        // app data without app code
+        let contract = ByteArray([1u8; 32]);
+        insert(&mut db, &contract, &full, None).await.unwrap();
+        let result = fetch_app_code(&mut db, &contract).await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_app_data_usage() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let used = ByteArray([1; 32]);
+        let unreferenced = ByteArray([2; 32]);
+        let unknown = ByteArray([3; 32]);
+        insert(&mut db, &used, b"used", None).await.unwrap();
+        insert(&mut db, &unreferenced, b"unreferenced", None).await.unwrap();
+        let registered_at = Utc.timestamp_opt(1_000, 0).unwrap();
+        sqlx::query("UPDATE app_data SET registration_timestamp = $1")
+            .bind(registered_at)
+            .execute(&mut *db)
+            .await
+            .unwrap();
+        for (uid, app_data, timestamp) in [(1, used, 2_000), (2, used, 3_000), (3, unknown, 4_000)]
+        {
+            crate::orders::insert_order(
+                &mut db,
+                &crate::orders::Order {
+                    uid: ByteArray([uid; 56]),
+                    app_data,
+                    creation_timestamp: Utc.timestamp_opt(timestamp, 0).unwrap(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            usage(&mut db, &used).await.unwrap(),
+            Usage {
+                registered: true,
+                registration_timestamp: Some(registered_at),
+                orders: 2,
+                latest_order: Utc.timestamp_opt(3_000, 0).single(),
+            }
+        );
+        assert_eq!(
+            usage(&mut db, &unreferenced).await.unwrap(),
+            Usage {
+                registered: true,
+                registration_timestamp: Some(registered_at),
+                orders: 0,
+                latest_order: None,
+            }
+        );
+        assert_eq!(
+            usage(&mut db, &unknown).await.unwrap(),
+            Usage {
+                registered: false,
+                registration_timestamp: None,
+                orders: 1,
+                latest_order: Utc.timestamp_opt(4_000, 0).single(),
+            }
+        );
+
+        // App data registered before the timestamps got recorded counts as
+        // old.
+        let legacy = ByteArray([4; 32]);
+        insert(&mut db, &legacy, b"legacy", None).await.unwrap();
+        sqlx::query(
+            "UPDATE app_data SET registration_timestamp = NULL WHERE contract_app_data = $1",
+        )
+        .bind(legacy)
+        .execute(&mut *db)
+        .await
+        .unwrap();
+        let recent = ByteArray([5; 32]);
+        insert(&mut db, &recent, b"recent", None).await.unwrap();
+
+        let before = Utc.timestamp_opt(1_001, 0).unwrap();
+        assert_eq!(
+            unused(&mut db, before, 10).await.unwrap(),
+            vec![legacy, unreferenced]
+        );
+        assert_eq!(unused(&mut db, before, 1).await.unwrap(), vec![legacy]);
+        assert_eq!(
+            unused(&mut db, registered_at, 10).await.unwrap(),
+            vec![legacy]
+        );
     }
 }
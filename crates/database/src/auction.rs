@@ -1,4 +1,10 @@
-use sqlx::{types::JsonValue, PgConnection};
+use sqlx::{
+    types::{
+        chrono::{DateTime, Utc},
+        JsonValue,
+    },
+    PgConnection,
+};
 
 pub type AuctionId = i64;
 
@@ -12,11 +18,12 @@ RETURNING id
     Ok(id)
 }
 
+/// Loads the most recent auction together with when it got stored.
 pub async fn load_most_recent(
     ex: &mut PgConnection,
-) -> Result<Option<(AuctionId, JsonValue)>, sqlx::Error> {
+) -> Result<Option<(AuctionId, JsonValue, DateTime<Utc>)>, sqlx::Error> {
     const QUERY: &str = r#"
-SELECT id, json
+SELECT id, json, created
 FROM auctions
 ORDER BY id DESC
 LIMIT 1
@@ -24,9 +31,48 @@ LIMIT 1
     sqlx::query_as(QUERY).fetch_optional(ex).await
 }
 
-pub async fn delete_all_auctions(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
-    const QUERY: &str = "TRUNCATE auctions;";
-    sqlx::query(QUERY).execute(ex).await.map(|_| ())
+pub async fn load_most_recent_id(ex: &mut PgConnection) -> Result<Option<AuctionId>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT id
+FROM auctions
+ORDER BY id DESC
+LIMIT 1
+    ;"#;
+    sqlx::query_scalar(QUERY).fetch_optional(ex).await
+}
+
+/// When the most recent auction got stored.
+pub async fn load_most_recent_created(
+    ex: &mut PgConnection,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT created
+FROM auctions
+ORDER BY id DESC
+LIMIT 1
+    ;"#;
+    sqlx::query_scalar(QUERY).fetch_optional(ex).await
+}
+
+pub async fn load_by_id(
+    ex: &mut PgConnection,
+    id: AuctionId,
+) -> Result<Option<JsonValue>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT json
+FROM auctions
+WHERE id = $1
+    ;"#;
+    sqlx::query_scalar(QUERY).bind(id).fetch_optional(ex).await
+}
+
+/// Deletes all auctions with an id smaller than `id`.
+pub async fn delete_auctions_before(
+    ex: &mut PgConnection,
+    id: AuctionId,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "DELETE FROM auctions WHERE id < $1;";
+    sqlx::query(QUERY).bind(id).execute(ex).await.map(|_| ())
 }
 
 #[cfg(test)]
@@ -42,18 +88,23 @@ mod tests {
 
         let value = JsonValue::Number(1.into());
         let id = save(&mut db, &value).await.unwrap();
-        let (id_, value_) = load_most_recent(&mut db).await.unwrap().unwrap();
+        let (id_, value_, created) = load_most_recent(&mut db).await.unwrap().unwrap();
         assert_eq!(id, id_);
         assert_eq!(value, value_);
+        assert_eq!(load_most_recent_id(&mut db).await.unwrap(), Some(id));
+        assert_eq!(
+            load_most_recent_created(&mut db).await.unwrap(),
+            Some(created)
+        );
 
         let value = JsonValue::Number(2.into());
         let id_ = save(&mut db, &value).await.unwrap();
         assert_eq!(id + 1, id_);
-        let (id, value_) = load_most_recent(&mut db).await.unwrap().unwrap();
+        let (id, value_, _) = load_most_recent(&mut db).await.unwrap().unwrap();
         assert_eq!(value, value_);
         assert_eq!(id_, id);
 
-        delete_all_auctions(&mut db).await.unwrap();
+        delete_auctions_before(&mut db, id + 1).await.unwrap();
         let result = load_most_recent(&mut db).await.unwrap();
         assert!(result.is_none());
 
@@ -61,8 +112,31 @@ mod tests {
         let value = JsonValue::Number(3.into());
         let id_ = save(&mut db, &value).await.unwrap();
         assert_eq!(id + 1, id_);
-        let (id, value_) = load_most_recent(&mut db).await.unwrap().unwrap();
+        let (id, value_, _) = load_most_recent(&mut db).await.unwrap().unwrap();
         assert_eq!(value, value_);
         assert_eq!(id_, id);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_load_and_prune_by_id() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            ids.push(save(&mut db, &JsonValue::Number(i.into())).await.unwrap());
+        }
+        assert_eq!(
+            load_by_id(&mut db, ids[1]).await.unwrap(),
+            Some(JsonValue::Number(1.into()))
+        );
+        assert_eq!(load_by_id(&mut db, ids[2] + 1).await.unwrap(), None);
+
+        delete_auctions_before(&mut db, ids[1]).await.unwrap();
+        assert_eq!(load_by_id(&mut db, ids[0]).await.unwrap(), None);
+        assert!(load_by_id(&mut db, ids[1]).await.unwrap().is_some());
+        assert!(load_by_id(&mut db, ids[2]).await.unwrap().is_some());
+    }
 }
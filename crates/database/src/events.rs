@@ -75,15 +75,7 @@ pub async fn delete(
     // Observations and order executions are not events but data derived from the
     // onchain data. The reason we delete them here is that we want to keep the
     // database state consistent.
-
-    const QUERY_OBSERVATIONS: &str =
-        "DELETE FROM settlement_observations WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_OBSERVATIONS).bind(delete_from_block_number))
-        .await?;
-
-    const QUERY_ORDER_EXECUTIONS: &str = "DELETE FROM order_execution WHERE block_number >= $1;";
-    ex.execute(sqlx::query(QUERY_ORDER_EXECUTIONS).bind(delete_from_block_number))
-        .await?;
+    crate::settlements::invalidate_updates(ex, delete_from_block_number..i64::MAX).await?;
 
     Ok(())
 }
@@ -96,6 +88,14 @@ pub async fn append(
     // COPY but my tests show that even if we sleep during the transaction it
     // does not block other connections from using the database, so it's not
     // high priority.
+    let traded_orders: Vec<_> = events
+        .iter()
+        .filter_map(|(_, event)| match event {
+            Event::Trade(trade) => Some(trade.order_uid),
+            _ => None,
+        })
+        .collect();
+    lock_traded_orders(ex, &traded_orders).await?;
     for (index, event) in events {
         match event {
             Event::Trade(event) => insert_trade(ex, index, event).await?,
@@ -126,6 +126,20 @@ async fn insert_invalidation(
     Ok(())
 }
 
+/// Cancellations lock the rows of the orders they cancel (see
+/// `orders::lock_orders`). Waiting for that lock ensures that an order can't
+/// get cancelled while its execution is being indexed. The rows get locked in
+/// the same order as cancellations do so the two can't deadlock.
+async fn lock_traded_orders(ex: &mut PgConnection, uids: &[OrderUid]) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT uid FROM orders
+WHERE uid = ANY($1)
+ORDER BY uid
+FOR SHARE
+    "#;
+    sqlx::query(QUERY).bind(uids).execute(ex).await.map(|_| ())
+}
+
 pub async fn insert_trade(
     ex: &mut PgConnection,
     index: &EventIndex,
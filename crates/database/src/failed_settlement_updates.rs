@@ -0,0 +1,115 @@
+use {crate::events::EventIndex, sqlx::PgConnection};
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct FailedSettlementUpdate {
+    pub block_number: i64,
+    pub log_index: i64,
+    /// How often updating the settlement was attempted before giving up.
+    pub attempts: i64,
+    /// The error of the last attempt.
+    pub error: String,
+}
+
+/// Marks a settlement event as failed so it no longer gets returned by
+/// [`crate::settlements::get_settlements_without_auction`].
+pub async fn insert(
+    ex: &mut PgConnection,
+    failure: &FailedSettlementUpdate,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO failed_settlement_updates (block_number, log_index, attempts, error)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (block_number, log_index) DO UPDATE
+SET attempts = EXCLUDED.attempts, error = EXCLUDED.error, failed_at = now()
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(failure.block_number)
+        .bind(failure.log_index)
+        .bind(failure.attempts)
+        .bind(&failure.error)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn fetch_all(ex: &mut PgConnection) -> Result<Vec<FailedSettlementUpdate>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT block_number, log_index, attempts, error
+FROM failed_settlement_updates
+ORDER BY block_number ASC, log_index ASC
+    "#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+/// Re-enqueues a failed settlement event so that updating it gets attempted
+/// again. Returns whether the event was marked as failed.
+pub async fn requeue(ex: &mut PgConnection, event: &EventIndex) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+DELETE FROM failed_settlement_updates
+WHERE block_number = $1 AND log_index = $2
+    ;"#;
+    let result = sqlx::query(QUERY)
+        .bind(event.block_number)
+        .bind(event.log_index)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Re-enqueues all failed settlement events. Returns how many there were.
+pub async fn requeue_all(ex: &mut PgConnection) -> Result<u64, sqlx::Error> {
+    const QUERY: &str = r#"DELETE FROM failed_settlement_updates;"#;
+    let result = sqlx::query(QUERY).execute(ex).await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::settlements::get_settlements_without_auction, sqlx::Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_failed_settlements_are_skipped_until_requeued() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let events = [
+            EventIndex {
+                block_number: 1,
+                log_index: 0,
+            },
+            EventIndex {
+                block_number: 2,
+                log_index: 0,
+            },
+        ];
+        for event in &events {
+            crate::events::insert_settlement(&mut db, event, &Default::default())
+                .await
+                .unwrap();
+        }
+
+        let failure = FailedSettlementUpdate {
+            block_number: 1,
+            log_index: 0,
+            attempts: 5,
+            error: "no receipt".to_string(),
+        };
+        insert(&mut db, &failure).await.unwrap();
+        assert_eq!(fetch_all(&mut db).await.unwrap(), [failure]);
+
+        let settlements = get_settlements_without_auction(&mut db, 10).await.unwrap();
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].block_number, 2);
+
+        assert!(requeue(&mut db, &events[0]).await.unwrap());
+        assert!(!requeue(&mut db, &events[0]).await.unwrap());
+        let settlements = get_settlements_without_auction(&mut db, 10).await.unwrap();
+        assert_eq!(settlements.len(), 2);
+
+        insert(&mut db, &Default::default()).await.unwrap();
+        assert_eq!(requeue_all(&mut db).await.unwrap(), 1);
+        assert!(fetch_all(&mut db).await.unwrap().is_empty());
+    }
+}
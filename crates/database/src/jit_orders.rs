@@ -0,0 +1,166 @@
+use {
+    crate::{
+        events::EventIndex,
+        orders::{BuyTokenDestination, OrderKind, SellTokenSource, SigningScheme},
+        Address,
+        AppId,
+        OrderUid,
+    },
+    bigdecimal::BigDecimal,
+    sqlx::PgConnection,
+};
+
+/// An order that was created by a solver just in time for a settlement.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct JitOrder {
+    /// Position of the trade within the settlement.
+    pub trade_index: i64,
+    /// `None` if the owner could not be recovered from the signature.
+    pub uid: Option<OrderUid>,
+    pub owner: Option<Address>,
+    pub sell_token: Address,
+    pub buy_token: Address,
+    pub receiver: Address,
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub valid_to: i64,
+    pub app_data: AppId,
+    pub fee_amount: BigDecimal,
+    pub kind: OrderKind,
+    pub partially_fillable: bool,
+    pub signing_scheme: SigningScheme,
+    pub signature: Vec<u8>,
+    pub sell_token_balance: SellTokenSource,
+    pub buy_token_balance: BuyTokenDestination,
+    /// The executed amounts are `None` if they could not be computed from the
+    /// calldata.
+    pub executed_sell_amount: Option<BigDecimal>,
+    pub executed_buy_amount: Option<BigDecimal>,
+    pub executed_fee_amount: Option<BigDecimal>,
+}
+
+/// Stores the JIT orders of a settlement. Inserting the orders of the same
+/// settlement again overwrites them.
+pub async fn insert(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+    orders: &[JitOrder],
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO jit_orders (block_number, log_index, trade_index, uid, owner, sell_token, buy_token, receiver, sell_amount, buy_amount, valid_to, app_data, fee_amount, kind, partially_fillable, signing_scheme, signature, sell_token_balance, buy_token_balance, executed_sell_amount, executed_buy_amount, executed_fee_amount)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
+ON CONFLICT (block_number, log_index, trade_index) DO UPDATE
+SET uid = EXCLUDED.uid, owner = EXCLUDED.owner, sell_token = EXCLUDED.sell_token,
+buy_token = EXCLUDED.buy_token, receiver = EXCLUDED.receiver,
+sell_amount = EXCLUDED.sell_amount, buy_amount = EXCLUDED.buy_amount,
+valid_to = EXCLUDED.valid_to, app_data = EXCLUDED.app_data,
+fee_amount = EXCLUDED.fee_amount, kind = EXCLUDED.kind,
+partially_fillable = EXCLUDED.partially_fillable,
+signing_scheme = EXCLUDED.signing_scheme, signature = EXCLUDED.signature,
+sell_token_balance = EXCLUDED.sell_token_balance,
+buy_token_balance = EXCLUDED.buy_token_balance,
+executed_sell_amount = EXCLUDED.executed_sell_amount,
+executed_buy_amount = EXCLUDED.executed_buy_amount,
+executed_fee_amount = EXCLUDED.executed_fee_amount
+    ;"#;
+    for order in orders {
+        sqlx::query(QUERY)
+            .bind(event.block_number)
+            .bind(event.log_index)
+            .bind(order.trade_index)
+            .bind(order.uid)
+            .bind(order.owner)
+            .bind(order.sell_token)
+            .bind(order.buy_token)
+            .bind(order.receiver)
+            .bind(&order.sell_amount)
+            .bind(&order.buy_amount)
+            .bind(order.valid_to)
+            .bind(order.app_data)
+            .bind(&order.fee_amount)
+            .bind(order.kind)
+            .bind(order.partially_fillable)
+            .bind(order.signing_scheme)
+            .bind(order.signature.as_slice())
+            .bind(order.sell_token_balance)
+            .bind(order.buy_token_balance)
+            .bind(&order.executed_sell_amount)
+            .bind(&order.executed_buy_amount)
+            .bind(&order.executed_fee_amount)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+) -> Result<Vec<JitOrder>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT trade_index, uid, owner, sell_token, buy_token, receiver, sell_amount, buy_amount, valid_to, app_data, fee_amount, kind, partially_fillable, signing_scheme, signature, sell_token_balance, buy_token_balance, executed_sell_amount, executed_buy_amount, executed_fee_amount
+FROM jit_orders
+WHERE block_number = $1 AND log_index = $2
+ORDER BY trade_index ASC
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(event.block_number)
+        .bind(event.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::byte_array::ByteArray, sqlx::Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_jit_orders_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        let orders = vec![
+            JitOrder {
+                trade_index: 1,
+                uid: Some(ByteArray([1; 56])),
+                owner: Some(ByteArray([2; 20])),
+                sell_token: ByteArray([3; 20]),
+                buy_token: ByteArray([4; 20]),
+                sell_amount: 5.into(),
+                buy_amount: 6.into(),
+                kind: OrderKind::Sell,
+                signing_scheme: SigningScheme::Eip1271,
+                signature: vec![7; 65],
+                executed_sell_amount: Some(5.into()),
+                executed_buy_amount: Some(7.into()),
+                executed_fee_amount: Some(0.into()),
+                ..Default::default()
+            },
+            // The owner could not be recovered.
+            JitOrder {
+                trade_index: 2,
+                signature: vec![8; 3],
+                ..Default::default()
+            },
+        ];
+        insert(&mut db, &event, &orders).await.unwrap();
+        assert_eq!(fetch(&mut db, &event).await.unwrap(), orders);
+
+        // Storing the orders again overwrites them.
+        let mut updated = orders.clone();
+        updated[0].executed_buy_amount = None;
+        insert(&mut db, &event, &updated).await.unwrap();
+        assert_eq!(fetch(&mut db, &event).await.unwrap(), updated);
+
+        crate::settlements::invalidate_updates(&mut db, 1..2)
+            .await
+            .unwrap();
+        assert!(fetch(&mut db, &event).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use {
+    crate::{auction::AuctionId, events::EventIndex, Address, OrderUid},
+    chrono::{DateTime, Utc},
+    sqlx::PgConnection,
+};
+
+/// An order that got settled although it was cancelled before the block of the
+/// settlement.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct LateSettledCancellation {
+    pub order_uid: OrderUid,
+    pub auction_id: AuctionId,
+    /// The account that submitted the settlement.
+    pub solver: Address,
+    pub cancellation_timestamp: DateTime<Utc>,
+    /// Timestamp of the block of the settlement.
+    pub settlement_timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct CancelledOrder {
+    pub uid: OrderUid,
+    pub cancellation_timestamp: DateTime<Utc>,
+}
+
+/// Returns the orders among `uids` that were cancelled before `timestamp`.
+pub async fn cancelled_before(
+    ex: &mut PgConnection,
+    uids: &[OrderUid],
+    timestamp: DateTime<Utc>,
+) -> Result<Vec<CancelledOrder>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT uid, cancellation_timestamp
+FROM orders
+WHERE uid = ANY($1) AND cancellation_timestamp < $2
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(uids)
+        .bind(timestamp)
+        .fetch_all(ex)
+        .await
+}
+
+/// Stores the cancelled orders executed by a settlement. Inserting the orders
+/// of the same settlement again overwrites them.
+pub async fn insert(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+    cancellations: &[LateSettledCancellation],
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO late_settled_cancellations (block_number, log_index, order_uid, auction_id, solver, cancellation_timestamp, settlement_timestamp)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+ON CONFLICT (block_number, log_index, order_uid) DO UPDATE
+SET auction_id = EXCLUDED.auction_id, solver = EXCLUDED.solver,
+cancellation_timestamp = EXCLUDED.cancellation_timestamp,
+settlement_timestamp = EXCLUDED.settlement_timestamp
+    ;"#;
+    for cancellation in cancellations {
+        sqlx::query(QUERY)
+            .bind(event.block_number)
+            .bind(event.log_index)
+            .bind(cancellation.order_uid)
+            .bind(cancellation.auction_id)
+            .bind(cancellation.solver)
+            .bind(cancellation.cancellation_timestamp)
+            .bind(cancellation.settlement_timestamp)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+) -> Result<Vec<LateSettledCancellation>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT order_uid, auction_id, solver, cancellation_timestamp, settlement_timestamp
+FROM late_settled_cancellations
+WHERE block_number = $1 AND log_index = $2
+ORDER BY order_uid ASC
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(event.block_number)
+        .bind(event.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{byte_array::ByteArray, orders::Order},
+        chrono::TimeZone,
+        sqlx::Connection,
+    };
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_late_settled_cancellations() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let cancelled_at = Utc.timestamp_opt(1_000, 0).unwrap();
+        let orders = [
+            Order {
+                uid: ByteArray([1; 56]),
+                cancellation_timestamp: Some(cancelled_at),
+                ..Default::default()
+            },
+            Order {
+                uid: ByteArray([2; 56]),
+                ..Default::default()
+            },
+        ];
+        for order in &orders {
+            crate::orders::insert_order(&mut db, order).await.unwrap();
+        }
+        let uids = [ByteArray([1; 56]), ByteArray([2; 56]), ByteArray([3; 56])];
+
+        // The settlement happened before the cancellation.
+        assert!(cancelled_before(&mut db, &uids, cancelled_at)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let settled_at = Utc.timestamp_opt(1_012, 0).unwrap();
+        assert_eq!(
+            cancelled_before(&mut db, &uids, settled_at).await.unwrap(),
+            [CancelledOrder {
+                uid: ByteArray([1; 56]),
+                cancellation_timestamp: cancelled_at,
+            }]
+        );
+
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        let cancellations = vec![LateSettledCancellation {
+            order_uid: ByteArray([1; 56]),
+            auction_id: 3,
+            solver: ByteArray([4; 20]),
+            cancellation_timestamp: cancelled_at,
+            settlement_timestamp: settled_at,
+        }];
+        insert(&mut db, &event, &cancellations).await.unwrap();
+        assert_eq!(fetch(&mut db, &event).await.unwrap(), cancellations);
+
+        crate::settlements::invalidate_updates(&mut db, 1..2)
+            .await
+            .unwrap();
+        assert!(fetch(&mut db, &event).await.unwrap().is_empty());
+    }
+}
@@ -5,6 +5,9 @@ pub mod auction_prices;
 pub mod byte_array;
 pub mod ethflow_orders;
 pub mod events;
+pub mod failed_settlement_updates;
+pub mod jit_orders;
+pub mod late_settled_cancellations;
 pub mod onchain_broadcasted_orders;
 pub mod onchain_invalidations;
 pub mod order_events;
@@ -12,11 +15,14 @@ pub mod order_execution;
 pub mod orders;
 pub mod quotes;
 pub mod settlement_call_data;
+pub mod settlement_interactions;
 pub mod settlement_observations;
 pub mod settlement_scores;
+pub mod settlement_submissions;
 pub mod settlements;
 pub mod solver_competition;
 pub mod trades;
+pub mod unexpected_settlement_submitters;
 
 use {
     byte_array::ByteArray,
@@ -64,6 +70,16 @@ pub const TABLES: &[&str] = &[
     "auction_prices",
     "auction_participants",
     "app_data",
+    "order_amendments",
+    "order_cancellations",
+    "cancellation_proofs",
+    "order_replacements",
+    "failed_settlement_updates",
+    "settlement_interactions",
+    "settlement_submissions",
+    "unexpected_settlement_submitters",
+    "jit_orders",
+    "late_settled_cancellations",
 ];
 
 /// The names of potentially big volume tables we use in the db.
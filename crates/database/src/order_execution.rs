@@ -28,6 +28,81 @@ DO UPDATE SET reward = $3, surplus_fee = $4, block_number = $5
     Ok(())
 }
 
+/// How an order got executed according to the settlement calldata.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct ExecutedAmounts {
+    /// Sell token amount transferred from the owner including the fee.
+    pub executed_sell_amount: BigDecimal,
+    pub executed_buy_amount: BigDecimal,
+    /// Signed fee in the sell token.
+    pub executed_fee_amount: BigDecimal,
+    /// Clearing price of the sell token used for the trade.
+    pub sell_price: BigDecimal,
+    /// Clearing price of the buy token used for the trade.
+    pub buy_price: BigDecimal,
+    /// Surplus in the native token.
+    pub surplus: BigDecimal,
+    /// Total fee (signed or surplus fee) in the native token.
+    pub native_fee: BigDecimal,
+    /// Part of `native_fee` covering the gas costs. `None` for executions
+    /// stored before the fee breakdown was recorded.
+    pub network_fee: Option<BigDecimal>,
+    /// Part of `native_fee` charged by protocol fee policies.
+    pub protocol_fee: Option<BigDecimal>,
+}
+
+/// Stores the executed amounts of an order execution that was previously
+/// saved with [`save`].
+pub async fn save_executed_amounts(
+    ex: &mut PgConnection,
+    order: &OrderUid,
+    auction: AuctionId,
+    amounts: &ExecutedAmounts,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE order_execution
+SET executed_sell_amount = $3, executed_buy_amount = $4, executed_fee_amount = $5,
+    sell_price = $6, buy_price = $7, surplus = $8, native_fee = $9, network_fee = $10,
+    protocol_fee = $11
+WHERE order_uid = $1 AND auction_id = $2
+;"#;
+    sqlx::query(QUERY)
+        .bind(order)
+        .bind(auction)
+        .bind(&amounts.executed_sell_amount)
+        .bind(&amounts.executed_buy_amount)
+        .bind(&amounts.executed_fee_amount)
+        .bind(&amounts.sell_price)
+        .bind(&amounts.buy_price)
+        .bind(&amounts.surplus)
+        .bind(&amounts.native_fee)
+        .bind(&amounts.network_fee)
+        .bind(&amounts.protocol_fee)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Returns the executed amounts of an order execution. Returns `None` for
+/// executions that were indexed before executed amounts were recorded.
+pub async fn executed_amounts(
+    ex: &mut PgConnection,
+    order: &OrderUid,
+    auction: AuctionId,
+) -> Result<Option<ExecutedAmounts>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT executed_sell_amount, executed_buy_amount, executed_fee_amount, sell_price, buy_price,
+    surplus, native_fee, network_fee, protocol_fee
+FROM order_execution
+WHERE order_uid = $1 AND auction_id = $2 AND executed_sell_amount IS NOT NULL
+;"#;
+    sqlx::query_as(QUERY)
+        .bind(order)
+        .bind(auction)
+        .fetch_optional(ex)
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, sqlx::Connection};
@@ -43,4 +118,47 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_executed_amounts_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Default::default();
+        save(&mut db, &order, 1, 0, &Default::default())
+            .await
+            .unwrap();
+        // Rows without executed amounts are still readable.
+        assert_eq!(executed_amounts(&mut db, &order, 1).await.unwrap(), None);
+
+        let amounts = ExecutedAmounts {
+            executed_sell_amount: 1.into(),
+            executed_buy_amount: 2.into(),
+            executed_fee_amount: 3.into(),
+            sell_price: 4.into(),
+            buy_price: 5.into(),
+            surplus: 6.into(),
+            native_fee: 7.into(),
+            network_fee: Some(4.into()),
+            protocol_fee: Some(3.into()),
+        };
+        save_executed_amounts(&mut db, &order, 1, &amounts)
+            .await
+            .unwrap();
+        assert_eq!(
+            executed_amounts(&mut db, &order, 1).await.unwrap(),
+            Some(amounts)
+        );
+
+        // Saving the execution again keeps the amounts.
+        save(&mut db, &order, 1, 0, &Default::default())
+            .await
+            .unwrap();
+        assert!(executed_amounts(&mut db, &order, 1)
+            .await
+            .unwrap()
+            .is_some());
+    }
 }
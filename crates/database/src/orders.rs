@@ -1,5 +1,6 @@
 use {
     crate::{
+        byte_array::ByteArray,
         onchain_broadcasted_orders::OnchainOrderPlacementError,
         order_events::{insert_order_event, OrderEvent, OrderEventLabel},
         Address,
@@ -12,6 +13,7 @@ use {
         types::{
             chrono::{DateTime, Utc},
             BigDecimal,
+            JsonValue,
         },
         PgConnection,
         QueryBuilder,
@@ -99,6 +101,8 @@ pub struct Order {
     pub full_fee_amount: BigDecimal,
     pub cancellation_timestamp: Option<DateTime<Utc>>,
     pub class: OrderClass,
+    pub balance_check_skipped: bool,
+    pub source: Option<JsonValue>,
 }
 
 pub async fn insert_orders_and_ignore_conflicts(
@@ -142,9 +146,11 @@ INSERT INTO orders (
     buy_token_balance,
     full_fee_amount,
     cancellation_timestamp,
-    class
+    class,
+    balance_check_skipped,
+    source
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23)
     "#;
 
 pub async fn insert_order_and_ignore_conflicts(
@@ -186,6 +192,8 @@ async fn insert_order_execute_sqlx(
         .bind(&order.full_fee_amount)
         .bind(order.cancellation_timestamp)
         .bind(order.class)
+        .bind(order.balance_check_skipped)
+        .bind(&order.source)
         .execute(ex)
         .await?;
     Ok(())
@@ -329,6 +337,11 @@ pub struct Quote {
     pub sell_amount: BigDecimal,
     pub buy_amount: BigDecimal,
     pub solver: Address,
+    /// Id of the quote in the `quotes` table.
+    pub quote_id: Option<i64>,
+    pub expiration_timestamp: Option<DateTime<Utc>>,
+    /// Whether the quote got verified by simulating the settlement.
+    pub verified: bool,
 }
 
 pub async fn insert_quotes(ex: &mut PgConnection, quotes: &[Quote]) -> Result<(), sqlx::Error> {
@@ -346,9 +359,12 @@ INSERT INTO order_quotes (
     sell_token_price,
     sell_amount,
     buy_amount,
-    solver
+    solver,
+    quote_id,
+    expiration_timestamp,
+    verified
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7)"#;
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#;
 
 pub async fn insert_quote_and_update_on_conflict(
     ex: &mut PgConnection,
@@ -362,7 +378,9 @@ pub async fn insert_quote_and_update_on_conflict(
         " ON CONFLICT (order_uid) DO UPDATE
 SET gas_amount = $2, gas_price = $3,
 sell_token_price = $4, sell_amount = $5,
-buy_amount = $6
+buy_amount = $6,
+quote_id = $8, expiration_timestamp = $9,
+verified = $10
     "
     );
     sqlx::query(QUERY)
@@ -373,6 +391,9 @@ buy_amount = $6
         .bind(&quote.sell_amount)
         .bind(&quote.buy_amount)
         .bind(quote.solver)
+        .bind(quote.quote_id)
+        .bind(quote.expiration_timestamp)
+        .bind(quote.verified)
         .execute(ex)
         .await?;
     Ok(())
@@ -387,6 +408,9 @@ pub async fn insert_quote(ex: &mut PgConnection, quote: &Quote) -> Result<(), sq
         .bind(&quote.sell_amount)
         .bind(&quote.buy_amount)
         .bind(quote.solver)
+        .bind(quote.quote_id)
+        .bind(quote.expiration_timestamp)
+        .bind(quote.verified)
         .execute(ex)
         .await?;
     Ok(())
@@ -423,6 +447,55 @@ pub async fn read_quotes(
     query.fetch_all(ex).await
 }
 
+/// Reduces the amounts of an order to the specified ones.
+///
+/// The amendment only gets stored if it doesn't increase the amounts of the
+/// order (or of a previous amendment) and the order has not already executed
+/// more than the new amounts. Checking the executed amounts in the same query
+/// guards against executions that got indexed after the caller checked them.
+///
+/// Returns whether the amendment got stored.
+pub async fn amend_order(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+    sell_amount: &BigDecimal,
+    buy_amount: &BigDecimal,
+    timestamp: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_amendments (order_uid, sell_amount, buy_amount, timestamp)
+SELECT o.uid, $2, $3, $4
+FROM orders o
+WHERE
+    o.uid = $1 AND
+    $2 <= o.sell_amount AND
+    $3 <= o.buy_amount AND
+    CASE o.kind
+        WHEN 'sell' THEN (
+            SELECT COALESCE(SUM(t.sell_amount - t.fee_amount), 0)
+            FROM trades t WHERE t.order_uid = o.uid
+        ) <= $2
+        WHEN 'buy' THEN (
+            SELECT COALESCE(SUM(t.buy_amount), 0)
+            FROM trades t WHERE t.order_uid = o.uid
+        ) <= $3
+    END
+ON CONFLICT (order_uid) DO UPDATE
+SET sell_amount = EXCLUDED.sell_amount, buy_amount = EXCLUDED.buy_amount, timestamp = EXCLUDED.timestamp
+WHERE
+    EXCLUDED.sell_amount <= order_amendments.sell_amount AND
+    EXCLUDED.buy_amount <= order_amendments.buy_amount
+    "#;
+    let result = sqlx::query(QUERY)
+        .bind(order_uid)
+        .bind(sell_amount)
+        .bind(buy_amount)
+        .bind(timestamp)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
 pub async fn cancel_order(
     ex: &mut PgConnection,
     order_uid: &OrderUid,
@@ -445,6 +518,157 @@ AND cancellation_timestamp IS NULL
         .map(|_| ())
 }
 
+/// Locks the rows of the orders until the end of the transaction. Indexing
+/// trades of these orders waits for the lock so their status can't change
+/// while the lock is held. The rows get locked ordered by uid like indexing
+/// does to avoid deadlocks.
+pub async fn lock_orders(ex: &mut PgConnection, uids: &[OrderUid]) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT uid FROM orders
+WHERE uid = ANY($1)
+ORDER BY uid
+FOR UPDATE
+    "#;
+    sqlx::query(QUERY).bind(uids).execute(ex).await.map(|_| ())
+}
+
+/// Returns the uids of the specified orders that exist in the orderbook.
+pub async fn existing_uids(
+    ex: &mut PgConnection,
+    uids: &[OrderUid],
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT uid FROM orders
+WHERE uid = ANY($1)
+    "#;
+    sqlx::query_scalar(QUERY).bind(uids).fetch_all(ex).await
+}
+
+/// Records that the cancellation message with the specified hash got applied
+/// to the order. Returns `false` if the message was already applied to it
+/// before.
+pub async fn insert_cancellation(
+    ex: &mut PgConnection,
+    hash: &ByteArray<32>,
+    order_uid: &OrderUid,
+    timestamp: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_cancellations (hash, order_uid, timestamp)
+VALUES ($1, $2, $3)
+ON CONFLICT (hash, order_uid) DO NOTHING
+    "#;
+    let result = sqlx::query(QUERY)
+        .bind(hash)
+        .bind(order_uid)
+        .bind(timestamp)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Records that the old order got replaced by the new one.
+pub async fn insert_replacement(
+    ex: &mut PgConnection,
+    old_order: &OrderUid,
+    new_order: &OrderUid,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO order_replacements (old_order_uid, new_order_uid)
+VALUES ($1, $2)
+    "#;
+    sqlx::query(QUERY)
+        .bind(old_order)
+        .bind(new_order)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+/// Returns all orders of the replacement chain the order is part of, from the
+/// original order to the most recent replacement. Contains only the order
+/// itself if it never got replaced and doesn't replace another order.
+pub async fn replacement_chain(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+WITH RECURSIVE predecessors AS (
+    SELECT $1::bytea AS uid
+    UNION
+    SELECT r.old_order_uid
+    FROM order_replacements r
+    JOIN predecessors p ON r.new_order_uid = p.uid
+),
+chain AS (
+    SELECT p.uid, 0 AS position
+    FROM predecessors p
+    WHERE NOT EXISTS (SELECT 1 FROM order_replacements r WHERE r.new_order_uid = p.uid)
+    UNION ALL
+    SELECT r.new_order_uid, c.position + 1
+    FROM order_replacements r
+    JOIN chain c ON r.old_order_uid = c.uid
+)
+SELECT uid FROM chain ORDER BY position
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(order_uid)
+        .fetch_all(ex)
+        .await
+}
+
+/// Signed off-chain cancellation of an order kept as proof that its owner
+/// cancelled it.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct CancellationProof {
+    pub order_uid: OrderUid,
+    pub signature: Vec<u8>,
+    pub signing_scheme: SigningScheme,
+    pub signer: Address,
+    pub timestamp: DateTime<Utc>,
+    /// JSON encoded signed message.
+    pub message: Option<Vec<u8>>,
+    pub domain_separator: Option<ByteArray<32>>,
+}
+
+/// Stores the proof of a cancellation. An already existing proof for the order
+/// is kept.
+pub async fn insert_cancellation_proof(
+    ex: &mut PgConnection,
+    proof: &CancellationProof,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO cancellation_proofs (order_uid, signature, signing_scheme, signer, timestamp, message, domain_separator)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+ON CONFLICT (order_uid) DO NOTHING
+    "#;
+    sqlx::query(QUERY)
+        .bind(proof.order_uid)
+        .bind(&proof.signature)
+        .bind(proof.signing_scheme)
+        .bind(proof.signer)
+        .bind(proof.timestamp)
+        .bind(&proof.message)
+        .bind(proof.domain_separator)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+pub async fn read_cancellation_proof(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Option<CancellationProof>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM cancellation_proofs
+WHERE order_uid = $1
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(order_uid)
+        .fetch_optional(ex)
+        .await
+}
+
 /// Interactions are read as arrays of their fields: target, value, data.
 /// This is done as sqlx does not support reading arrays of more complicated
 /// types than just one field. The pre_ and post_interaction's data of
@@ -487,6 +711,14 @@ pub struct FullOrder {
     pub onchain_placement_error: Option<OnchainOrderPlacementError>,
     pub executed_surplus_fee: BigDecimal,
     pub full_app_data: Option<Vec<u8>>,
+    pub amended_sell_amount: Option<BigDecimal>,
+    pub amended_buy_amount: Option<BigDecimal>,
+    pub replaces: Option<OrderUid>,
+    pub replaced_by: Option<OrderUid>,
+    pub quote_verified: bool,
+    pub balance_check_skipped: bool,
+    pub source: Option<JsonValue>,
+    pub insufficient_balance: bool,
 }
 
 impl FullOrder {
@@ -526,7 +758,7 @@ const ORDERS_SELECT: &str = r#"
 o.uid, o.owner, o.creation_timestamp, o.sell_token, o.buy_token, o.sell_amount, o.buy_amount,
 o.valid_to, o.app_data, o.fee_amount, o.full_fee_amount, o.kind, o.partially_fillable, o.signature,
 o.receiver, o.signing_scheme, o.settlement_contract, o.sell_token_balance, o.buy_token_balance,
-o.class,
+o.class, o.balance_check_skipped, o.source, o.insufficient_balance,
 (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_buy,
 (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_sell,
 (SELECT COALESCE(SUM(t.fee_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_fee,
@@ -549,7 +781,12 @@ array(Select (p.target, p.value, p.data) from interactions p where p.order_uid =
 (SELECT onchain_o.sender from onchain_placed_orders onchain_o where onchain_o.uid = o.uid limit 1) as onchain_user,
 (SELECT onchain_o.placement_error from onchain_placed_orders onchain_o where onchain_o.uid = o.uid limit 1) as onchain_placement_error,
 COALESCE((SELECT SUM(surplus_fee) FROM order_execution oe WHERE oe.order_uid = o.uid), 0) as executed_surplus_fee,
-(SELECT full_app_data FROM app_data ad WHERE o.app_data = ad.contract_app_data LIMIT 1) as full_app_data
+(SELECT full_app_data FROM app_data ad WHERE o.app_data = ad.contract_app_data LIMIT 1) as full_app_data,
+(SELECT a.sell_amount FROM order_amendments a WHERE a.order_uid = o.uid) as amended_sell_amount,
+(SELECT a.buy_amount FROM order_amendments a WHERE a.order_uid = o.uid) as amended_buy_amount,
+(SELECT r.old_order_uid FROM order_replacements r WHERE r.new_order_uid = o.uid) as replaces,
+(SELECT r.new_order_uid FROM order_replacements r WHERE r.old_order_uid = o.uid) as replaced_by,
+COALESCE((SELECT q.verified FROM order_quotes q WHERE q.order_uid = o.uid), false) as quote_verified
 "#;
 
 const ORDERS_FROM: &str = "orders o";
@@ -567,6 +804,20 @@ pub async fn single_full_order(
     sqlx::query_as(QUERY).bind(uid).fetch_optional(ex).await
 }
 
+/// The orders with the specified uids. Uids without an order are ignored.
+pub fn many_full_orders<'a>(
+    ex: &'a mut PgConnection,
+    uids: &'a [OrderUid],
+) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
+    #[rustfmt::skip]
+    const QUERY: &str = const_format::concatcp!(
+"SELECT ", ORDERS_SELECT,
+" FROM ", ORDERS_FROM,
+" WHERE o.uid = ANY($1) ",
+    );
+    sqlx::query_as(QUERY).bind(uids).fetch(ex)
+}
+
 // Partial query for getting the log indices of events of a single settlement.
 //
 // This will fail if we ever have multiple settlements in the same transaction
@@ -616,6 +867,91 @@ pub fn user_orders<'a>(
     owner: &'a Address,
     offset: i64,
     limit: Option<i64>,
+) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
+    const NO_FILTER: &UserOrdersFilter = &UserOrdersFilter {
+        status: None,
+        sell_token: None,
+        buy_token: None,
+        min_valid_to: None,
+        created_after: None,
+    };
+    user_orders_with_filter(ex, owner, NO_FILTER, offset, limit)
+}
+
+/// The status of an order as it gets computed from its trades, cancellations
+/// and validity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OrderStatus {
+    PresignaturePending,
+    Open,
+    Fulfilled,
+    Cancelled,
+    Expired,
+}
+
+impl OrderStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::PresignaturePending => "presignaturePending",
+            Self::Open => "open",
+            Self::Fulfilled => "fulfilled",
+            Self::Cancelled => "cancelled",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// Optional filters for the orders of a user. Filters that are not set match
+/// every order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserOrdersFilter {
+    pub status: Option<OrderStatus>,
+    pub sell_token: Option<Address>,
+    pub buy_token: Option<Address>,
+    /// Only orders that are valid at least until this unix timestamp. For
+    /// eth-flow orders the user valid to is used.
+    pub min_valid_to: Option<i64>,
+    /// Only orders that were created strictly after this time.
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+/// Filters on plain order columns. These get applied before computing the
+/// derived order columns so they can make use of indices.
+const USER_ORDERS_COLUMN_FILTER: &str = r#"
+AND ($4::bytea IS NULL OR o.sell_token = $4)
+AND ($5::bytea IS NULL OR o.buy_token = $5)
+AND ($6::timestamptz IS NULL OR o.creation_timestamp > $6)
+"#;
+
+/// The valid to of an order taking the user valid to of eth-flow orders into
+/// account.
+const EFFECTIVE_VALID_TO: &str = r#"
+COALESCE((SELECT eth_o.valid_to FROM ethflow_orders eth_o WHERE eth_o.uid = o.uid), o.valid_to) AS effective_valid_to
+"#;
+
+/// Filters on the derived order columns. The status computation mirrors the
+/// status calculation of the orderbook.
+const USER_ORDERS_DERIVED_FILTER: &str = r#"
+WHERE ($7::bigint IS NULL OR effective_valid_to >= $7)
+AND ($8::text IS NULL OR $8 = CASE
+    WHEN CASE kind
+        WHEN 'sell' THEN sum_sell > 0 AND sum_sell - sum_fee >= COALESCE(amended_sell_amount, sell_amount)
+        WHEN 'buy' THEN sum_buy > 0 AND sum_buy >= COALESCE(amended_buy_amount, buy_amount)
+    END THEN 'fulfilled'
+    WHEN invalidated THEN 'cancelled'
+    WHEN effective_valid_to < EXTRACT(EPOCH FROM NOW()) THEN 'expired'
+    WHEN presignature_pending THEN 'presignaturePending'
+    ELSE 'open'
+END)
+"#;
+
+/// Like `user_orders` but only returns orders matching the filter.
+pub fn user_orders_with_filter<'a>(
+    ex: &'a mut PgConnection,
+    owner: &'a Address,
+    filter: &'a UserOrdersFilter,
+    offset: i64,
+    limit: Option<i64>,
 ) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
     // As a future consideration for this query we could move from offset to an
     // approach called keyset pagination where the offset is identified by "key"
@@ -627,16 +963,19 @@ pub fn user_orders<'a>(
     // see that these queries are taking too long in practice.
     #[rustfmt::skip]
     const QUERY: &str = const_format::concatcp!(
-"(SELECT ", ORDERS_SELECT,
-" FROM ", ORDERS_FROM,
-" LEFT OUTER JOIN onchain_placed_orders onchain_o on onchain_o.uid = o.uid",
-" WHERE o.owner = $1",
+"(SELECT * FROM (",
+    "SELECT ", ORDERS_SELECT, ", ", EFFECTIVE_VALID_TO,
+    " FROM ", ORDERS_FROM,
+    " WHERE o.owner = $1", USER_ORDERS_COLUMN_FILTER,
+") AS owner_orders ", USER_ORDERS_DERIVED_FILTER,
 " ORDER BY creation_timestamp DESC LIMIT $2 + $3 ) ",
 " UNION ",
-" (SELECT ", ORDERS_SELECT,
-" FROM ", ORDERS_FROM,
-" LEFT OUTER JOIN onchain_placed_orders onchain_o on onchain_o.uid = o.uid",
-" WHERE onchain_o.sender = $1 ",
+" (SELECT * FROM (",
+    "SELECT ", ORDERS_SELECT, ", ", EFFECTIVE_VALID_TO,
+    " FROM ", ORDERS_FROM,
+    " LEFT OUTER JOIN onchain_placed_orders onchain_o on onchain_o.uid = o.uid",
+    " WHERE onchain_o.sender = $1", USER_ORDERS_COLUMN_FILTER,
+") AS sender_orders ", USER_ORDERS_DERIVED_FILTER,
 " ORDER BY creation_timestamp DESC LIMIT $2 + $3 ) ",
 " ORDER BY creation_timestamp DESC ",
 " LIMIT $2 ",
@@ -646,6 +985,100 @@ pub fn user_orders<'a>(
         .bind(owner)
         .bind(limit)
         .bind(offset)
+        .bind(filter.sell_token)
+        .bind(filter.buy_token)
+        .bind(filter.created_after)
+        .bind(filter.min_valid_to)
+        .bind(filter.status.map(|status| status.as_str()))
+        .fetch(ex)
+}
+
+/// An order together with the number of orders matching the query ignoring
+/// `LIMIT` and `OFFSET`.
+#[derive(Debug, sqlx::FromRow)]
+pub struct CountedFullOrder {
+    #[sqlx(flatten)]
+    pub order: FullOrder,
+    pub total_count: i64,
+}
+
+/// Like `user_orders_with_filter` but every order also carries the total
+/// number of the user's orders matching the filter. The count comes from a
+/// window function so it doesn't need a separate query. Since pages past the
+/// last order are empty they don't carry the count.
+pub fn user_orders_with_count<'a>(
+    ex: &'a mut PgConnection,
+    owner: &'a Address,
+    filter: &'a UserOrdersFilter,
+    offset: i64,
+    limit: Option<i64>,
+) -> BoxStream<'a, Result<CountedFullOrder, sqlx::Error>> {
+    // Unlike in `user_orders_with_filter` the subqueries can't be limited
+    // because all matching orders need to be counted.
+    #[rustfmt::skip]
+    const QUERY: &str = const_format::concatcp!(
+"SELECT *, COUNT(*) OVER () AS total_count FROM (",
+" (SELECT * FROM (",
+    "SELECT ", ORDERS_SELECT, ", ", EFFECTIVE_VALID_TO,
+    " FROM ", ORDERS_FROM,
+    " WHERE o.owner = $1", USER_ORDERS_COLUMN_FILTER,
+") AS owner_orders ", USER_ORDERS_DERIVED_FILTER, ") ",
+" UNION ",
+" (SELECT * FROM (",
+    "SELECT ", ORDERS_SELECT, ", ", EFFECTIVE_VALID_TO,
+    " FROM ", ORDERS_FROM,
+    " LEFT OUTER JOIN onchain_placed_orders onchain_o on onchain_o.uid = o.uid",
+    " WHERE onchain_o.sender = $1", USER_ORDERS_COLUMN_FILTER,
+") AS sender_orders ", USER_ORDERS_DERIVED_FILTER, ") ",
+") AS user_orders ",
+" ORDER BY creation_timestamp DESC ",
+" LIMIT $2 ",
+" OFFSET $3 ",
+    );
+    sqlx::query_as(QUERY)
+        .bind(owner)
+        .bind(limit)
+        .bind(offset)
+        .bind(filter.sell_token)
+        .bind(filter.buy_token)
+        .bind(filter.created_after)
+        .bind(filter.min_valid_to)
+        .bind(filter.status.map(|status| status.as_str()))
+        .fetch(ex)
+}
+
+/// Returns up to `limit` orders of the user (newest first) that come strictly
+/// after the order identified by `before` (its creation timestamp and uid) in
+/// that ordering. Unlike offset based pagination, pages stay stable when new
+/// orders get inserted while paginating.
+pub fn user_orders_before<'a>(
+    ex: &'a mut PgConnection,
+    owner: &'a Address,
+    before: Option<(DateTime<Utc>, OrderUid)>,
+    limit: i64,
+) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
+    const BEFORE_FILTER: &str =
+        " AND ($2::timestamptz IS NULL OR (o.creation_timestamp, o.uid) < ($2, $3::bytea))";
+    #[rustfmt::skip]
+    const QUERY: &str = const_format::concatcp!(
+"(SELECT ", ORDERS_SELECT,
+" FROM ", ORDERS_FROM,
+" WHERE o.owner = $1", BEFORE_FILTER,
+" ORDER BY creation_timestamp DESC, uid DESC LIMIT $4 ) ",
+" UNION ",
+" (SELECT ", ORDERS_SELECT,
+" FROM ", ORDERS_FROM,
+" LEFT OUTER JOIN onchain_placed_orders onchain_o on onchain_o.uid = o.uid",
+" WHERE onchain_o.sender = $1", BEFORE_FILTER,
+" ORDER BY creation_timestamp DESC, uid DESC LIMIT $4 ) ",
+" ORDER BY creation_timestamp DESC, uid DESC ",
+" LIMIT $4 ",
+    );
+    sqlx::query_as(QUERY)
+        .bind(owner)
+        .bind(before.map(|(timestamp, _)| timestamp))
+        .bind(before.map(|(_, uid)| uid))
+        .bind(limit)
         .fetch(ex)
 }
 
@@ -669,8 +1102,8 @@ const OPEN_ORDERS: &str = const_format::concatcp!(
 r#") AS unfiltered
 WHERE
     CASE kind
-        WHEN 'sell' THEN sum_sell < sell_amount
-        WHEN 'buy' THEN sum_buy < buy_amount
+        WHEN 'sell' THEN sum_sell < COALESCE(amended_sell_amount, sell_amount)
+        WHEN 'buy' THEN sum_buy < COALESCE(amended_buy_amount, buy_amount)
     END AND
     (NOT invalidated) AND
     (onchain_placement_error IS NULL)
@@ -716,6 +1149,176 @@ pub async fn count_limit_orders_by_owner(
         .await
 }
 
+/// Counts the number of orders of the owner with the conditions of
+/// OPEN_ORDERS. Used to enforce a maximum number of open orders per owner.
+pub async fn count_open_orders_by_owner(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+    owner: &Address,
+) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = const_format::concatcp!(
+        "SELECT COUNT (*) FROM (",
+        OPEN_ORDERS,
+        " AND owner = $2",
+        " ) AS subquery"
+    );
+    sqlx::query_scalar(QUERY)
+        .bind(min_valid_to)
+        .bind(owner)
+        .fetch_one(ex)
+        .await
+}
+
+/// Counts the orders with the conditions of OPEN_ORDERS per token pair. The
+/// rows are `(sell_token, buy_token, count)` ordered by descending count.
+pub async fn count_open_orders_by_pair(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+) -> Result<Vec<(Address, Address, i64)>, sqlx::Error> {
+    const QUERY: &str = const_format::concatcp!(
+        "SELECT sell_token, buy_token, COUNT(*) FROM (",
+        OPEN_ORDERS,
+        " ) AS subquery",
+        " GROUP BY sell_token, buy_token",
+        " ORDER BY COUNT(*) DESC, sell_token, buy_token"
+    );
+    sqlx::query_as(QUERY).bind(min_valid_to).fetch_all(ex).await
+}
+
+/// Counts the orders with the conditions of OPEN_ORDERS per app code of their
+/// full app data. Orders without a known app code are not counted. The rows
+/// are `(app_code, count)` ordered by descending count.
+pub async fn count_open_orders_by_app_code(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    const QUERY: &str = const_format::concatcp!(
+        "SELECT ad.app_code, COUNT(*) FROM (",
+        OPEN_ORDERS,
+        " ) AS subquery",
+        " JOIN app_data ad ON ad.contract_app_data = subquery.app_data",
+        " WHERE ad.app_code IS NOT NULL",
+        " GROUP BY ad.app_code",
+        " ORDER BY COUNT(*) DESC, ad.app_code"
+    );
+    sqlx::query_as(QUERY).bind(min_valid_to).fetch_all(ex).await
+}
+
+/// Counts the orders created at or after `since` per app code of their full
+/// app data. Orders without a known app code are not counted. The rows are
+/// `(app_code, count)` ordered by descending count.
+pub async fn count_orders_by_app_code(
+    ex: &mut PgConnection,
+    since: DateTime<Utc>,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT ad.app_code, COUNT(*)
+FROM orders o
+JOIN app_data ad ON ad.contract_app_data = o.app_data
+WHERE o.creation_timestamp >= $1 AND ad.app_code IS NOT NULL
+GROUP BY ad.app_code
+ORDER BY COUNT(*) DESC, ad.app_code
+    "#;
+    sqlx::query_as(QUERY).bind(since).fetch_all(ex).await
+}
+
+/// Orders of the owner with the conditions of OPEN_ORDERS whose valid to lies
+/// between `min_valid_to` and `max_valid_to` (both inclusive). Orders with a
+/// pending pre-signature are excluded. The orders are ordered by ascending
+/// valid to.
+pub fn expiring_orders_by_owner<'a>(
+    ex: &'a mut PgConnection,
+    owner: &'a Address,
+    min_valid_to: i64,
+    max_valid_to: i64,
+) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
+    const QUERY: &str = const_format::concatcp!(
+        OPEN_ORDERS,
+        " AND owner = $2",
+        " AND valid_to <= $3",
+        " AND NOT presignature_pending",
+        " ORDER BY valid_to, uid"
+    );
+    sqlx::query_as(QUERY)
+        .bind(min_valid_to)
+        .bind(owner)
+        .bind(max_valid_to)
+        .fetch(ex)
+}
+
+/// Orders of the owner with the conditions of OPEN_ORDERS that are signed with
+/// ECDSA and were created at or before `created_before`. These are the orders
+/// a signed cancellation of all orders of the owner applies to.
+pub fn cancellable_orders_by_owner<'a>(
+    ex: &'a mut PgConnection,
+    owner: &'a Address,
+    min_valid_to: i64,
+    created_before: DateTime<Utc>,
+) -> BoxStream<'a, Result<FullOrder, sqlx::Error>> {
+    const QUERY: &str = const_format::concatcp!(
+        OPEN_ORDERS,
+        " AND owner = $2",
+        " AND signing_scheme IN ('eip712', 'ethsign')",
+        " AND creation_timestamp <= $3"
+    );
+    sqlx::query_as(QUERY)
+        .bind(min_valid_to)
+        .bind(owner)
+        .bind(created_before)
+        .fetch(ex)
+}
+
+/// A random sample of at most `limit` fill-or-kill orders with the conditions
+/// of OPEN_ORDERS. Orders with a pending pre-signature are excluded.
+pub fn sample_open_fill_or_kill_orders(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+    limit: i64,
+) -> BoxStream<'_, Result<FullOrder, sqlx::Error>> {
+    const QUERY: &str = const_format::concatcp!(
+        OPEN_ORDERS,
+        " AND NOT partially_fillable",
+        " AND NOT presignature_pending",
+        " ORDER BY random()",
+        " LIMIT $2"
+    );
+    sqlx::query_as(QUERY)
+        .bind(min_valid_to)
+        .bind(limit)
+        .fetch(ex)
+}
+
+/// Sets whether the owners of the orders lack the sell token balance.
+pub async fn set_insufficient_balance(
+    ex: &mut PgConnection,
+    uids: &[OrderUid],
+    insufficient_balance: bool,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE orders
+SET insufficient_balance = $2
+WHERE uid = ANY($1)
+    "#;
+    sqlx::query(QUERY)
+        .bind(uids)
+        .bind(insufficient_balance)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+/// Whether the pre-signature of the order was ever set on-chain, even if it
+/// got revoked afterwards.
+pub async fn was_presigned(ex: &mut PgConnection, uid: &OrderUid) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT EXISTS (
+    SELECT 1 FROM presignature_events
+    WHERE order_uid = $1 AND signed
+)
+"#;
+    sqlx::query_scalar(QUERY).bind(uid).fetch_one(ex).await
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -1122,6 +1725,9 @@ mod tests {
             sell_amount: 4.into(),
             buy_amount: 5.into(),
             solver: ByteArray([1; 20]),
+            quote_id: Some(6),
+            expiration_timestamp: Some(Utc.timestamp_opt(7, 0).unwrap()),
+            verified: false,
         };
         insert_quote(&mut db, &quote).await.unwrap();
         insert_quote_and_update_on_conflict(&mut db, &quote)
@@ -1134,6 +1740,7 @@ mod tests {
         assert_eq!(quote, quote_);
         let mut quote2 = quote.clone();
         quote2.gas_amount = 2.0;
+        quote2.verified = true;
         insert_quote_and_update_on_conflict(&mut db, &quote2)
             .await
             .unwrap();
@@ -1182,6 +1789,9 @@ mod tests {
             sell_amount: 4.into(),
             buy_amount: 5.into(),
             solver: ByteArray([1; 20]),
+            quote_id: Some(6),
+            expiration_timestamp: Some(Utc.timestamp_opt(7, 0).unwrap()),
+            verified: true,
         };
         insert_quote(&mut db, &quote).await.unwrap();
         let quote_ = read_quote(&mut db, &quote.order_uid)
@@ -1348,13 +1958,56 @@ mod tests {
 
     #[tokio::test]
     #[ignore]
-    async fn postgres_onchain_invalidated_orders() {
+    async fn postgres_was_presigned() {
         let mut db = PgConnection::connect("postgresql://").await.unwrap();
         let mut db = db.begin().await.unwrap();
         crate::clear_DANGER_(&mut db).await.unwrap();
 
-        let order = Order {
-            uid: ByteArray([2u8; 56]),
+        let uid = ByteArray([1; 56]);
+        let pre_signature_event = |block_number: i64, signed: bool| {
+            [(
+                EventIndex {
+                    block_number,
+                    log_index: 0,
+                },
+                Event::PreSignature(PreSignature {
+                    order_uid: uid,
+                    signed,
+                    ..Default::default()
+                }),
+            )]
+        };
+
+        assert!(!was_presigned(&mut db, &uid).await.unwrap());
+
+        // Revoking a pre-signature that was never set doesn't count.
+        crate::events::append(&mut db, &pre_signature_event(0, false))
+            .await
+            .unwrap();
+        assert!(!was_presigned(&mut db, &uid).await.unwrap());
+
+        crate::events::append(&mut db, &pre_signature_event(1, true))
+            .await
+            .unwrap();
+        assert!(was_presigned(&mut db, &uid).await.unwrap());
+
+        // Revoking the pre-signature doesn't undo that it was set.
+        crate::events::append(&mut db, &pre_signature_event(2, false))
+            .await
+            .unwrap();
+        assert!(was_presigned(&mut db, &uid).await.unwrap());
+        assert!(!was_presigned(&mut db, &ByteArray([2; 56])).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_onchain_invalidated_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            uid: ByteArray([2u8; 56]),
             kind: OrderKind::Sell,
             sell_amount: 10.into(),
             buy_amount: 100.into(),
@@ -1496,6 +2149,388 @@ mod tests {
         assert!(get_order(&mut db, 2).await.is_none());
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_count_open_orders_by_pair() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let a = ByteArray([1; 20]);
+        let b = ByteArray([2; 20]);
+        let c = ByteArray([3; 20]);
+        let order = |uid: u8, sell_token, buy_token, valid_to| Order {
+            uid: ByteArray([uid; 56]),
+            sell_token,
+            buy_token,
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            buy_amount: 10.into(),
+            valid_to,
+            ..Default::default()
+        };
+        let orders = [
+            order(1, a, b, 10),
+            order(2, a, b, 10),
+            order(3, a, b, 10),
+            order(4, b, a, 10),
+            order(5, a, c, 10),
+            order(6, a, c, 10),
+            // Expired.
+            order(7, c, a, 1),
+            // Fully executed.
+            order(8, c, a, 10),
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex {
+                    block_number: 0,
+                    log_index: 0,
+                },
+                Event::Trade(Trade {
+                    order_uid: orders[7].uid,
+                    sell_amount_including_fee: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            count_open_orders_by_pair(&mut db, 5).await.unwrap(),
+            vec![(a, b, 3), (a, c, 2), (b, a, 1)]
+        );
+        assert!(count_open_orders_by_pair(&mut db, 11)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_count_orders_by_app_code() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let a = ByteArray([1; 32]);
+        let b = ByteArray([2; 32]);
+        let without_code = ByteArray([3; 32]);
+        let unknown = ByteArray([4; 32]);
+        crate::app_data::insert(&mut db, &a, b"a", Some("a"))
+            .await
+            .unwrap();
+        crate::app_data::insert(&mut db, &b, b"b", Some("b"))
+            .await
+            .unwrap();
+        crate::app_data::insert(&mut db, &without_code, b"c", None)
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let order = |uid: u8, app_data, valid_to, creation_timestamp| Order {
+            uid: ByteArray([uid; 56]),
+            app_data,
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            buy_amount: 10.into(),
+            valid_to,
+            creation_timestamp,
+            ..Default::default()
+        };
+        let old = now - chrono::Duration::hours(1);
+        let orders = [
+            order(1, a, 10, now),
+            order(2, a, 10, now),
+            // Expired.
+            order(3, a, 1, now),
+            order(4, b, 10, old),
+            order(5, without_code, 10, now),
+            order(6, unknown, 10, now),
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+
+        assert_eq!(
+            count_open_orders_by_app_code(&mut db, 5).await.unwrap(),
+            vec![("a".to_string(), 2), ("b".to_string(), 1)]
+        );
+        assert!(count_open_orders_by_app_code(&mut db, 11)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            count_orders_by_app_code(&mut db, old).await.unwrap(),
+            vec![("a".to_string(), 3), ("b".to_string(), 1)]
+        );
+        assert_eq!(
+            count_orders_by_app_code(&mut db, now - chrono::Duration::minutes(1))
+                .await
+                .unwrap(),
+            vec![("a".to_string(), 3)]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_many_full_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for uid in 1..=3 {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+
+        let mut uids = many_full_orders(
+            &mut db,
+            &[ByteArray([3; 56]), ByteArray([4; 56]), ByteArray([1; 56])],
+        )
+        .map(|order| order.unwrap().uid)
+        .collect::<Vec<_>>()
+        .await;
+        uids.sort_by_key(|uid| uid.0);
+        assert_eq!(uids, vec![ByteArray([1; 56]), ByteArray([3; 56])]);
+        assert!(many_full_orders(&mut db, &[]).next().await.is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_expiring_orders_by_owner() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let order = |uid: u8, owner, valid_to| Order {
+            uid: ByteArray([uid; 56]),
+            owner,
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            buy_amount: 10.into(),
+            valid_to,
+            ..Default::default()
+        };
+        let orders = [
+            order(1, owner, 20),
+            // Expires exactly at the end of the window.
+            order(2, owner, 30),
+            // Expires exactly at the start of the window.
+            order(3, owner, 10),
+            order(4, owner, 15),
+            // Expires after the window.
+            order(5, owner, 31),
+            // Already expired.
+            order(6, owner, 9),
+            // Different owner.
+            order(7, ByteArray([2; 20]), 20),
+            // Fully executed.
+            order(8, owner, 20),
+            // Cancelled.
+            Order {
+                cancellation_timestamp: Some(Utc::now()),
+                ..order(9, owner, 20)
+            },
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex {
+                    block_number: 0,
+                    log_index: 0,
+                },
+                Event::Trade(Trade {
+                    order_uid: orders[7].uid,
+                    sell_amount_including_fee: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let expiring = expiring_orders_by_owner(&mut db, &owner, 10, 30)
+            .map(|order| order.unwrap().uid)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            expiring,
+            vec![
+                ByteArray([3; 56]),
+                ByteArray([4; 56]),
+                ByteArray([1; 56]),
+                ByteArray([2; 56]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancellable_orders_by_owner() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let now = Utc::now();
+        let order = |uid: u8| Order {
+            uid: ByteArray([uid; 56]),
+            owner,
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            buy_amount: 10.into(),
+            valid_to: 20,
+            creation_timestamp: now,
+            ..Default::default()
+        };
+        let orders = [
+            order(1),
+            Order {
+                signing_scheme: SigningScheme::EthSign,
+                ..order(2)
+            },
+            // Already expired.
+            Order {
+                valid_to: 9,
+                ..order(3)
+            },
+            // Different owner.
+            Order {
+                owner: ByteArray([2; 20]),
+                ..order(4)
+            },
+            // Not signed with ECDSA.
+            Order {
+                signing_scheme: SigningScheme::PreSign,
+                ..order(5)
+            },
+            // Created after the cancellation.
+            Order {
+                creation_timestamp: now + chrono::Duration::seconds(1),
+                ..order(6)
+            },
+            // Cancelled.
+            Order {
+                cancellation_timestamp: Some(now),
+                ..order(7)
+            },
+            // Fully executed.
+            order(8),
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex {
+                    block_number: 0,
+                    log_index: 0,
+                },
+                Event::Trade(Trade {
+                    order_uid: orders[7].uid,
+                    sell_amount_including_fee: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let mut cancellable = cancellable_orders_by_owner(&mut db, &owner, 10, now)
+            .map(|order| order.unwrap().uid.0)
+            .collect::<Vec<_>>()
+            .await;
+        cancellable.sort();
+        assert_eq!(cancellable, vec![[1; 56], [2; 56]]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_sample_open_fill_or_kill_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = |uid: u8| Order {
+            uid: ByteArray([uid; 56]),
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            buy_amount: 10.into(),
+            valid_to: 10,
+            ..Default::default()
+        };
+        let orders = [
+            order(1),
+            order(2),
+            // Partially fillable.
+            Order {
+                partially_fillable: true,
+                ..order(3)
+            },
+            // Expired.
+            Order {
+                valid_to: 9,
+                ..order(4)
+            },
+            // Cancelled.
+            Order {
+                cancellation_timestamp: Some(Utc::now()),
+                ..order(5)
+            },
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+
+        let mut sampled = sample_open_fill_or_kill_orders(&mut db, 10, 10)
+            .map(|order| order.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        sampled.sort_by_key(|order| order.uid.0);
+        assert_eq!(
+            sampled.iter().map(|order| order.uid).collect::<Vec<_>>(),
+            vec![ByteArray([1; 56]), ByteArray([2; 56])]
+        );
+        assert!(sampled.iter().all(|order| !order.insufficient_balance));
+        let sampled = sample_open_fill_or_kill_orders(&mut db, 10, 1)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(sampled.len(), 1);
+
+        set_insufficient_balance(&mut db, &[ByteArray([1; 56])], true)
+            .await
+            .unwrap();
+        let flagged = single_full_order(&mut db, &ByteArray([1; 56]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(flagged.insufficient_balance);
+
+        set_insufficient_balance(&mut db, &[ByteArray([1; 56])], false)
+            .await
+            .unwrap();
+        let unflagged = single_full_order(&mut db, &ByteArray([1; 56]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!unflagged.insufficient_balance);
+    }
+
     type Data = ([u8; 56], Address, DateTime<Utc>);
     async fn user_orders(
         ex: &mut PgConnection,
@@ -1684,6 +2719,568 @@ mod tests {
         assert_eq!(result, vec![orders[0]]);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_user_orders_with_filter() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let (token_a, token_b, token_c) = (
+            ByteArray([0xa; 20]),
+            ByteArray([0xb; 20]),
+            ByteArray([0xc; 20]),
+        );
+        let valid_to = Utc::now().timestamp() + 3600;
+        let order = |uid: u8, sell_token, buy_token, valid_to, created| Order {
+            uid: ByteArray([uid; 56]),
+            owner,
+            sell_token,
+            buy_token,
+            valid_to,
+            creation_timestamp: Utc.timestamp_opt(created, 0).unwrap(),
+            kind: OrderKind::Buy,
+            buy_amount: 10.into(),
+            ..Default::default()
+        };
+        let open = order(0, token_a, token_b, valid_to, 1);
+        let fulfilled = order(1, token_a, token_b, valid_to, 2);
+        let cancelled = order(2, token_a, token_c, valid_to, 3);
+        let expired = order(3, token_b, token_a, 1, 4);
+        let open_other_pair = order(4, token_b, token_c, valid_to + 1, 5);
+        for order in [&open, &fulfilled, &cancelled, &expired, &open_other_pair] {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex::default(),
+                Event::Trade(Trade {
+                    order_uid: fulfilled.uid,
+                    buy_amount: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+        cancel_order(&mut db, &cancelled.uid, Utc::now())
+            .await
+            .unwrap();
+
+        async fn filtered(
+            ex: &mut PgConnection,
+            owner: &Address,
+            filter: UserOrdersFilter,
+        ) -> Vec<OrderUid> {
+            super::user_orders_with_filter(ex, owner, &filter, 0, None)
+                .map(|o| o.unwrap().uid)
+                .collect::<Vec<_>>()
+                .await
+        }
+
+        // No filter.
+        let result = filtered(&mut db, &owner, Default::default()).await;
+        assert_eq!(
+            result,
+            vec![
+                open_other_pair.uid,
+                expired.uid,
+                cancelled.uid,
+                fulfilled.uid,
+                open.uid
+            ]
+        );
+
+        // Status.
+        for (status, expected) in [
+            (OrderStatus::Open, vec![open_other_pair.uid, open.uid]),
+            (OrderStatus::Fulfilled, vec![fulfilled.uid]),
+            (OrderStatus::Cancelled, vec![cancelled.uid]),
+            (OrderStatus::Expired, vec![expired.uid]),
+        ] {
+            let filter = UserOrdersFilter {
+                status: Some(status),
+                ..Default::default()
+            };
+            assert_eq!(filtered(&mut db, &owner, filter).await, expected);
+        }
+
+        // Sell token.
+        let filter = UserOrdersFilter {
+            sell_token: Some(token_b),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![open_other_pair.uid, expired.uid]);
+
+        // Buy token.
+        let filter = UserOrdersFilter {
+            buy_token: Some(token_c),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![open_other_pair.uid, cancelled.uid]);
+
+        // Min valid to.
+        let filter = UserOrdersFilter {
+            min_valid_to: Some(valid_to + 1),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![open_other_pair.uid]);
+
+        // Created after.
+        let filter = UserOrdersFilter {
+            created_after: Some(Utc.timestamp_opt(3, 0).unwrap()),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![open_other_pair.uid, expired.uid]);
+
+        // Combined filters.
+        let filter = UserOrdersFilter {
+            status: Some(OrderStatus::Open),
+            sell_token: Some(token_a),
+            buy_token: Some(token_b),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![open.uid]);
+
+        let filter = UserOrdersFilter {
+            status: Some(OrderStatus::Open),
+            sell_token: Some(token_a),
+            created_after: Some(Utc.timestamp_opt(1, 0).unwrap()),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &owner, filter).await;
+        assert_eq!(result, vec![]);
+
+        // Filters also apply to orders placed on-chain by the user.
+        let sender = ByteArray([2; 20]);
+        insert_onchain_order(
+            &mut db,
+            &EventIndex::default(),
+            &OnchainOrderPlacement {
+                order_uid: open.uid,
+                sender,
+                placement_error: None,
+            },
+        )
+        .await
+        .unwrap();
+        let filter = UserOrdersFilter {
+            status: Some(OrderStatus::Open),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &sender, filter).await;
+        assert_eq!(result, vec![open.uid]);
+        let filter = UserOrdersFilter {
+            status: Some(OrderStatus::Expired),
+            ..Default::default()
+        };
+        let result = filtered(&mut db, &sender, filter).await;
+        assert_eq!(result, vec![]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_user_orders_with_count() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let (token_a, token_b) = (ByteArray([0xa; 20]), ByteArray([0xb; 20]));
+        // 25 orders of the user where every third one sells token b and one
+        // order of another user.
+        let orders: Vec<_> = (0u8..25)
+            .map(|i| Order {
+                uid: ByteArray([i; 56]),
+                owner,
+                sell_token: if i % 3 == 0 { token_b } else { token_a },
+                creation_timestamp: Utc.timestamp_opt(i.into(), 0).unwrap(),
+                ..Default::default()
+            })
+            .collect();
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        insert_order(
+            &mut db,
+            &Order {
+                uid: ByteArray([0xff; 56]),
+                owner: ByteArray([2; 20]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        async fn page(
+            ex: &mut PgConnection,
+            owner: &Address,
+            filter: UserOrdersFilter,
+            offset: i64,
+            limit: i64,
+        ) -> Vec<(OrderUid, i64)> {
+            super::user_orders_with_count(ex, owner, &filter, offset, Some(limit))
+                .map(|o| {
+                    let o = o.unwrap();
+                    (o.order.uid, o.total_count)
+                })
+                .collect::<Vec<_>>()
+                .await
+        }
+        let uids = |range: std::ops::Range<u8>, total_count: i64| {
+            range
+                .rev()
+                .map(|i| (ByteArray([i; 56]), total_count))
+                .collect::<Vec<_>>()
+        };
+
+        // Every page carries the total count.
+        let result = page(&mut db, &owner, Default::default(), 0, 10).await;
+        assert_eq!(result, uids(15..25, 25));
+        let result = page(&mut db, &owner, Default::default(), 10, 10).await;
+        assert_eq!(result, uids(5..15, 25));
+        let result = page(&mut db, &owner, Default::default(), 20, 10).await;
+        assert_eq!(result, uids(0..5, 25));
+        let result = page(&mut db, &owner, Default::default(), 30, 10).await;
+        assert_eq!(result, vec![]);
+
+        // The count respects the filter.
+        let filter = UserOrdersFilter {
+            sell_token: Some(token_b),
+            ..Default::default()
+        };
+        let result = page(&mut db, &owner, filter.clone(), 0, 5).await;
+        assert_eq!(
+            result,
+            [24, 21, 18, 15, 12]
+                .into_iter()
+                .map(|i| (ByteArray([i; 56]), 9))
+                .collect::<Vec<_>>()
+        );
+        let result = page(&mut db, &owner, filter, 5, 5).await;
+        assert_eq!(
+            result,
+            [9, 6, 3, 0]
+                .into_iter()
+                .map(|i| (ByteArray([i; 56]), 9))
+                .collect::<Vec<_>>()
+        );
+
+        // Orders placed on-chain by the user are counted once even if the user
+        // is also the owner.
+        insert_onchain_order(
+            &mut db,
+            &EventIndex::default(),
+            &OnchainOrderPlacement {
+                order_uid: orders[24].uid,
+                sender: owner,
+                placement_error: None,
+            },
+        )
+        .await
+        .unwrap();
+        let result = page(&mut db, &owner, Default::default(), 0, 1).await;
+        assert_eq!(result, uids(24..25, 25));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_user_orders_before_is_stable_with_new_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let order = |uid: u8, created| Order {
+            uid: ByteArray([uid; 56]),
+            owner,
+            creation_timestamp: Utc.timestamp_opt(created, 0).unwrap(),
+            ..Default::default()
+        };
+        // Orders 1 and 2 share the same creation timestamp so the uid has to
+        // break the tie.
+        for order in [order(0, 0), order(1, 1), order(2, 1), order(3, 2)] {
+            insert_order(&mut db, &order).await.unwrap();
+        }
+
+        async fn page(
+            ex: &mut PgConnection,
+            owner: &Address,
+            before: Option<(DateTime<Utc>, OrderUid)>,
+        ) -> Vec<FullOrder> {
+            super::user_orders_before(ex, owner, before, 2)
+                .try_collect()
+                .await
+                .unwrap()
+        }
+        let cursor = |order: &FullOrder| Some((order.creation_timestamp, order.uid));
+        let uids = |orders: &[FullOrder]| orders.iter().map(|o| o.uid.0[0]).collect::<Vec<_>>();
+
+        let first = page(&mut db, &owner, None).await;
+        assert_eq!(uids(&first), vec![3, 2]);
+
+        // New orders show up at the start of the listing and don't shift the
+        // following pages.
+        insert_order(&mut db, &order(4, 3)).await.unwrap();
+        insert_order(&mut db, &order(5, 4)).await.unwrap();
+
+        let second = page(&mut db, &owner, cursor(first.last().unwrap())).await;
+        assert_eq!(uids(&second), vec![1, 0]);
+
+        let third = page(&mut db, &owner, cursor(second.last().unwrap())).await;
+        assert_eq!(uids(&third), Vec::<u8>::new());
+
+        let newest = page(&mut db, &owner, None).await;
+        assert_eq!(uids(&newest), vec![5, 4]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_insert_cancellation() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let uid = ByteArray([1; 56]);
+        assert!(
+            insert_cancellation(&mut db, &ByteArray([1; 32]), &uid, Utc::now())
+                .await
+                .unwrap()
+        );
+        // The same message can't be applied twice.
+        assert!(
+            !insert_cancellation(&mut db, &ByteArray([1; 32]), &uid, Utc::now())
+                .await
+                .unwrap()
+        );
+        assert!(
+            insert_cancellation(&mut db, &ByteArray([2; 32]), &uid, Utc::now())
+                .await
+                .unwrap()
+        );
+        // Batch cancellations apply the same message to multiple orders.
+        assert!(insert_cancellation(
+            &mut db,
+            &ByteArray([1; 32]),
+            &ByteArray([2; 56]),
+            Utc::now()
+        )
+        .await
+        .unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancellation_proof_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let proof = CancellationProof {
+            order_uid: ByteArray([1; 56]),
+            signature: vec![1, 2, 3],
+            signing_scheme: SigningScheme::Eip1271,
+            signer: ByteArray([2; 20]),
+            timestamp: Utc.timestamp_opt(1234567890, 0).unwrap(),
+            message: Some(b"{}".to_vec()),
+            domain_separator: Some(ByteArray([3; 32])),
+        };
+        assert_eq!(
+            read_cancellation_proof(&mut db, &proof.order_uid)
+                .await
+                .unwrap(),
+            None
+        );
+        insert_cancellation_proof(&mut db, &proof).await.unwrap();
+        assert_eq!(
+            read_cancellation_proof(&mut db, &proof.order_uid)
+                .await
+                .unwrap(),
+            Some(proof.clone())
+        );
+
+        // The first proof is kept.
+        insert_cancellation_proof(
+            &mut db,
+            &CancellationProof {
+                signature: vec![4],
+                ..proof.clone()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            read_cancellation_proof(&mut db, &proof.order_uid)
+                .await
+                .unwrap(),
+            Some(proof)
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replacement_chain() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let uid = |byte: u8| ByteArray([byte; 56]);
+        for byte in 1..=4 {
+            insert_order(
+                &mut db,
+                &Order {
+                    uid: uid(byte),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+        insert_replacement(&mut db, &uid(1), &uid(2)).await.unwrap();
+        insert_replacement(&mut db, &uid(2), &uid(3)).await.unwrap();
+
+        // The chain is the same no matter which link it gets queried from.
+        for byte in 1..=3 {
+            assert_eq!(
+                replacement_chain(&mut db, &uid(byte)).await.unwrap(),
+                vec![uid(1), uid(2), uid(3)]
+            );
+        }
+        assert_eq!(
+            replacement_chain(&mut db, &uid(4)).await.unwrap(),
+            vec![uid(4)]
+        );
+
+        let middle = single_full_order(&mut db, &uid(2)).await.unwrap().unwrap();
+        assert_eq!(middle.replaces, Some(uid(1)));
+        assert_eq!(middle.replaced_by, Some(uid(3)));
+        let unrelated = single_full_order(&mut db, &uid(4)).await.unwrap().unwrap();
+        assert_eq!(unrelated.replaces, None);
+        assert_eq!(unrelated.replaced_by, None);
+
+        // An order can only be replaced once.
+        assert!(insert_replacement(&mut db, &uid(1), &uid(4)).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_amend_order() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            kind: OrderKind::Sell,
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            partially_fillable: true,
+            valid_to: i64::from(u32::MAX),
+            ..Default::default()
+        };
+        insert_order(&mut db, &order).await.unwrap();
+
+        async fn amend(ex: &mut PgConnection, uid: &OrderUid, sell: u32, buy: u32) -> bool {
+            amend_order(ex, uid, &sell.into(), &buy.into(), Utc::now())
+                .await
+                .unwrap()
+        }
+        async fn amended(
+            ex: &mut PgConnection,
+            uid: &OrderUid,
+        ) -> (Option<BigDecimal>, Option<BigDecimal>) {
+            let order = single_full_order(ex, uid).await.unwrap().unwrap();
+            (order.amended_sell_amount, order.amended_buy_amount)
+        }
+
+        assert_eq!(amended(&mut db, &order.uid).await, (None, None));
+
+        // Amounts can't exceed the signed ones.
+        assert!(!amend(&mut db, &order.uid, 101, 202).await);
+        assert_eq!(amended(&mut db, &order.uid).await, (None, None));
+
+        assert!(amend(&mut db, &order.uid, 80, 160).await);
+        assert_eq!(
+            amended(&mut db, &order.uid).await,
+            (Some(80.into()), Some(160.into()))
+        );
+
+        // Amendments can't increase previously amended amounts.
+        assert!(!amend(&mut db, &order.uid, 90, 180).await);
+        assert!(amend(&mut db, &order.uid, 50, 100).await);
+        assert_eq!(
+            amended(&mut db, &order.uid).await,
+            (Some(50.into()), Some(100.into()))
+        );
+
+        // Unknown orders can't be amended.
+        assert!(!amend(&mut db, &ByteArray([1; 56]), 1, 1).await);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_amend_order_races_with_execution() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            kind: OrderKind::Sell,
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            partially_fillable: true,
+            valid_to: i64::from(u32::MAX),
+            ..Default::default()
+        };
+        insert_order(&mut db, &order).await.unwrap();
+
+        // The owner checked the order when only 20 were executed but by the
+        // time the amendment gets stored a settlement executing 40 more got
+        // indexed.
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex::default(),
+                Event::Trade(Trade {
+                    order_uid: order.uid,
+                    sell_amount_including_fee: 61.into(),
+                    buy_amount: 120.into(),
+                    fee_amount: 1.into(),
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let stored = amend_order(&mut db, &order.uid, &50.into(), &100.into(), Utc::now())
+            .await
+            .unwrap();
+        assert!(!stored);
+        let full_order = single_full_order(&mut db, &order.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(full_order.amended_sell_amount, None);
+
+        // Reducing to exactly the executed amount is fine and makes the order
+        // no longer solvable.
+        let stored = amend_order(&mut db, &order.uid, &60.into(), &120.into(), Utc::now())
+            .await
+            .unwrap();
+        assert!(stored);
+        let solvable = solvable_orders(&mut db, 0)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(solvable.is_empty());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_orders_in_tx() {
@@ -1843,7 +3440,7 @@ mod tests {
             .unwrap();
         assert!(full_order.full_app_data.is_none());
         let full_app_data = vec![0u8, 1, 2];
-        crate::app_data::insert(&mut db, &order.app_data, &full_app_data)
+        crate::app_data::insert(&mut db, &order.app_data, &full_app_data, None)
             .await
             .unwrap();
         let full_order = single_full_order(&mut db, &order.uid)
@@ -0,0 +1,130 @@
+use {
+    crate::{byte_array::ByteArray, events::EventIndex, Address},
+    bigdecimal::BigDecimal,
+    sqlx::PgConnection,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct Interaction {
+    /// Position of the interaction within the settlement.
+    pub index: i64,
+    /// 0 for pre-, 1 for intra- and 2 for post-interactions.
+    pub stage: i16,
+    pub target: Address,
+    pub value: BigDecimal,
+    /// `None` if the call data is too short to contain a function selector.
+    pub selector: Option<ByteArray<4>>,
+    /// Length of the full call data.
+    pub call_data_len: i64,
+    /// Potentially truncated call data.
+    pub call_data: Vec<u8>,
+    /// Whether the interaction was proposed by the solver but got internalized
+    /// and therefore not executed on-chain.
+    pub internalized: bool,
+}
+
+/// Stores the interactions of a settlement. Inserting the interactions of the
+/// same settlement again overwrites them.
+pub async fn insert(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+    interactions: &[Interaction],
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO settlement_interactions (block_number, log_index, index, stage, target, value, selector, call_data_len, call_data, internalized)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+ON CONFLICT (block_number, log_index, index) DO UPDATE
+SET stage = EXCLUDED.stage, target = EXCLUDED.target, value = EXCLUDED.value,
+selector = EXCLUDED.selector, call_data_len = EXCLUDED.call_data_len,
+call_data = EXCLUDED.call_data, internalized = EXCLUDED.internalized
+    ;"#;
+    for interaction in interactions {
+        sqlx::query(QUERY)
+            .bind(event.block_number)
+            .bind(event.log_index)
+            .bind(interaction.index)
+            .bind(interaction.stage)
+            .bind(interaction.target)
+            .bind(&interaction.value)
+            .bind(interaction.selector)
+            .bind(interaction.call_data_len)
+            .bind(interaction.call_data.as_slice())
+            .bind(interaction.internalized)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    event: &EventIndex,
+) -> Result<Vec<Interaction>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT index, stage, target, value, selector, call_data_len, call_data, internalized
+FROM settlement_interactions
+WHERE block_number = $1 AND log_index = $2
+ORDER BY index ASC
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(event.block_number)
+        .bind(event.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, sqlx::Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_interactions_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        let interactions = vec![
+            Interaction {
+                index: 0,
+                stage: 0,
+                target: ByteArray([1; 20]),
+                value: 3.into(),
+                selector: Some(ByteArray([4; 4])),
+                call_data_len: 300,
+                call_data: vec![4; 256],
+                internalized: false,
+            },
+            Interaction {
+                index: 1,
+                stage: 1,
+                target: ByteArray([5; 20]),
+                internalized: true,
+                ..Default::default()
+            },
+        ];
+        insert(&mut db, &event, &interactions).await.unwrap();
+        assert_eq!(fetch(&mut db, &event).await.unwrap(), interactions);
+
+        let other_event = EventIndex {
+            block_number: 1,
+            log_index: 3,
+        };
+        assert!(fetch(&mut db, &other_event).await.unwrap().is_empty());
+
+        // Storing the interactions again overwrites them.
+        let mut updated = interactions.clone();
+        updated[1].internalized = false;
+        insert(&mut db, &event, &updated).await.unwrap();
+        assert_eq!(fetch(&mut db, &event).await.unwrap(), updated);
+
+        crate::settlements::invalidate_updates(&mut db, 1..2)
+            .await
+            .unwrap();
+        assert!(fetch(&mut db, &event).await.unwrap().is_empty());
+    }
+}
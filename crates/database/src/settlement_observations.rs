@@ -8,14 +8,27 @@ pub struct Observation {
     pub fee: BigDecimal,
     pub block_number: i64,
     pub log_index: i64,
+    /// Part of `fee` covering the gas costs. `None` for observations stored
+    /// before the fee breakdown was recorded.
+    pub network_fee: Option<BigDecimal>,
+    /// Part of `fee` charged by protocol fee policies.
+    pub protocol_fee: Option<BigDecimal>,
+    /// `effective_gas_price` divided by the gas price of the auction. `None`
+    /// if the gas price of the auction is unknown.
+    pub gas_price_ratio: Option<f64>,
+    /// Fee a rollup charged for posting the settlement data to L1. Zero on
+    /// chains that are not rollups.
+    pub l1_fee: BigDecimal,
+    /// Execution cost plus `l1_fee`.
+    pub total_cost: BigDecimal,
 }
 
 pub async fn upsert(ex: &mut PgConnection, observation: Observation) -> Result<(), sqlx::Error> {
     const QUERY: &str = r#"
-INSERT INTO settlement_observations (gas_used, effective_gas_price, surplus, fee, block_number, log_index)
-VALUES ($1, $2, $3, $4, $5, $6)
+INSERT INTO settlement_observations (gas_used, effective_gas_price, surplus, fee, block_number, log_index, network_fee, protocol_fee, gas_price_ratio, l1_fee, total_cost)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
 ON CONFLICT (block_number, log_index) DO UPDATE 
-SET gas_used = $1, effective_gas_price = $2, surplus = $3, fee = $4
+SET gas_used = $1, effective_gas_price = $2, surplus = $3, fee = $4, network_fee = $7, protocol_fee = $8, gas_price_ratio = $9, l1_fee = $10, total_cost = $11
     ;"#;
     sqlx::query(QUERY)
         .bind(observation.gas_used)
@@ -24,6 +37,11 @@ SET gas_used = $1, effective_gas_price = $2, surplus = $3, fee = $4
         .bind(observation.fee)
         .bind(observation.block_number)
         .bind(observation.log_index)
+        .bind(observation.network_fee)
+        .bind(observation.protocol_fee)
+        .bind(observation.gas_price_ratio)
+        .bind(observation.l1_fee)
+        .bind(observation.total_cost)
         .execute(ex)
         .await?;
     Ok(())
@@ -78,6 +96,11 @@ mod tests {
             fee: 4.into(),
             block_number: 1,
             log_index: 1,
+            network_fee: None,
+            protocol_fee: None,
+            gas_price_ratio: None,
+            l1_fee: 0.into(),
+            total_cost: 2.into(),
         };
 
         upsert(&mut db, input.clone()).await.unwrap();
@@ -100,6 +123,11 @@ mod tests {
             fee: 8.into(),
             block_number: 1,
             log_index: 1,
+            network_fee: Some(5.into()),
+            protocol_fee: Some(3.into()),
+            gas_price_ratio: Some(1.5),
+            l1_fee: 9.into(),
+            total_cost: 39.into(),
         };
         upsert(&mut db, new_input.clone()).await.unwrap();
         let output = fetch(
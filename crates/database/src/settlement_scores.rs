@@ -13,10 +13,13 @@ pub struct Score {
     pub reference_score: BigDecimal,
     pub block_deadline: i64,
     pub simulation_block: i64,
+    /// Gas price estimated when the solutions were ranked. `None` for
+    /// auctions ranked before it was recorded.
+    pub gas_price: Option<BigDecimal>,
 }
 
 pub async fn insert(ex: &mut PgTransaction<'_>, score: Score) -> Result<(), sqlx::Error> {
-    const QUERY: &str = r#"INSERT INTO settlement_scores (auction_id, winner, winning_score, reference_score, block_deadline, simulation_block) VALUES ($1, $2, $3, $4, $5, $6);"#;
+    const QUERY: &str = r#"INSERT INTO settlement_scores (auction_id, winner, winning_score, reference_score, block_deadline, simulation_block, gas_price) VALUES ($1, $2, $3, $4, $5, $6, $7);"#;
     sqlx::query(QUERY)
         .bind(score.auction_id)
         .bind(score.winner)
@@ -24,6 +27,7 @@ pub async fn insert(ex: &mut PgTransaction<'_>, score: Score) -> Result<(), sqlx
         .bind(score.reference_score)
         .bind(score.block_deadline)
         .bind(score.simulation_block)
+        .bind(score.gas_price)
         .execute(ex.deref_mut())
         .await?;
     Ok(())
@@ -58,6 +62,7 @@ mod tests {
             reference_score: 9.into(),
             block_deadline: 1000,
             simulation_block: 2000,
+            gas_price: Some(30_000_000_000u64.into()),
         };
         insert(&mut db, input.clone()).await.unwrap();
 
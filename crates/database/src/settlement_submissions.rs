@@ -0,0 +1,74 @@
+use {
+    crate::{auction::AuctionId, Address, TransactionHash},
+    sqlx::PgConnection,
+};
+
+/// Records the hash of the settlement transaction that the sender broadcast
+/// first for an auction. Later submissions for the same auction and sender
+/// (e.g. rebroadcasts with a higher gas price) don't overwrite it.
+pub async fn insert(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+    tx_from: &Address,
+    tx_hash: &TransactionHash,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO settlement_submissions (auction_id, tx_from, tx_hash)
+VALUES ($1, $2, $3)
+ON CONFLICT (auction_id, tx_from) DO NOTHING
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(auction_id)
+        .bind(tx_from)
+        .bind(tx_hash)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+    tx_from: &Address,
+) -> Result<Option<TransactionHash>, sqlx::Error> {
+    const QUERY: &str =
+        r#"SELECT tx_hash FROM settlement_submissions WHERE auction_id = $1 AND tx_from = $2"#;
+    sqlx::query_scalar(QUERY)
+        .bind(auction_id)
+        .bind(tx_from)
+        .fetch_optional(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::byte_array::ByteArray, sqlx::Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let sender = ByteArray([1; 20]);
+        assert_eq!(fetch(&mut db, 1, &sender).await.unwrap(), None);
+        insert(&mut db, 1, &sender, &ByteArray([1; 32]))
+            .await
+            .unwrap();
+        assert_eq!(
+            fetch(&mut db, 1, &sender).await.unwrap(),
+            Some(ByteArray([1; 32]))
+        );
+        assert_eq!(fetch(&mut db, 1, &ByteArray([2; 20])).await.unwrap(), None);
+
+        // A later submission for the same auction keeps the first one.
+        insert(&mut db, 1, &sender, &ByteArray([2; 32]))
+            .await
+            .unwrap();
+        assert_eq!(
+            fetch(&mut db, 1, &sender).await.unwrap(),
+            Some(ByteArray([1; 32]))
+        );
+    }
+}
@@ -1,5 +1,13 @@
 use {
-    crate::{events::EventIndex, TransactionHash},
+    crate::{
+        auction::AuctionId,
+        events::EventIndex,
+        order_execution::ExecutedAmounts,
+        Address,
+        OrderUid,
+        TransactionHash,
+    },
+    bigdecimal::BigDecimal,
     sqlx::PgConnection,
     std::ops::Range,
 };
@@ -47,29 +55,130 @@ pub struct SettlementEvent {
     pub tx_hash: TransactionHash,
 }
 
-pub async fn get_settlement_without_auction(
+/// Returns up to `limit` settlement events that have not been associated with
+/// an auction yet, oldest first. Events whose update failed permanently are
+/// skipped.
+///
+/// The events don't get locked so that no locks are held while fetching their
+/// transactions. Use [`lock_settlements_without_auction`] before updating them.
+pub async fn get_settlements_without_auction(
     ex: &mut PgConnection,
-) -> Result<Option<SettlementEvent>, sqlx::Error> {
+    limit: i64,
+) -> Result<Vec<SettlementEvent>, sqlx::Error> {
     const QUERY: &str = r#"
-SELECT block_number, log_index, tx_hash
-FROM settlements
-WHERE auction_id IS NULL
-ORDER BY block_number ASC
-LIMIT 1
+SELECT s.block_number, s.log_index, s.tx_hash
+FROM settlements s
+WHERE
+    s.auction_id IS NULL AND
+    NOT EXISTS (
+        SELECT 1
+        FROM failed_settlement_updates f
+        WHERE f.block_number = s.block_number AND f.log_index = s.log_index
+    )
+ORDER BY s.block_number ASC, s.log_index ASC
+LIMIT $1
+    "#;
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+/// Locks the events that still have not been associated with an auction until
+/// the end of the transaction and returns them, oldest first.
+///
+/// Events locked by other transactions or updated in the meantime are skipped
+/// so that concurrent updaters never update the same event twice.
+pub async fn lock_settlements_without_auction(
+    ex: &mut PgConnection,
+    events: &[EventIndex],
+) -> Result<Vec<EventIndex>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT s.block_number, s.log_index
+FROM settlements s
+JOIN UNNEST($1::bigint[], $2::bigint[]) AS e(block_number, log_index)
+    ON s.block_number = e.block_number AND s.log_index = e.log_index
+WHERE
+    s.auction_id IS NULL AND
+    NOT EXISTS (
+        SELECT 1
+        FROM failed_settlement_updates f
+        WHERE f.block_number = s.block_number AND f.log_index = s.log_index
+    )
+ORDER BY s.block_number ASC, s.log_index ASC
+FOR UPDATE OF s SKIP LOCKED
+    "#;
+    let (block_numbers, log_indices): (Vec<_>, Vec<_>) = events
+        .iter()
+        .map(|event| (event.block_number, event.log_index))
+        .unzip();
+    sqlx::query_as(QUERY)
+        .bind(block_numbers)
+        .bind(log_indices)
+        .fetch_all(ex)
+        .await
+}
+
+#[derive(Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct UpdateProgress {
+    /// Number of settlement events that still need to be updated.
+    pub pending: i64,
+    /// Block of the newest settlement event.
+    pub latest_block: Option<i64>,
+    /// Block of the newest settlement event that was already updated.
+    pub latest_updated_block: Option<i64>,
+}
+
+/// Returns how far updating the details of settlement events has progressed.
+pub async fn update_progress(ex: &mut PgConnection) -> Result<UpdateProgress, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    (
+        SELECT COUNT(*)
+        FROM settlements s
+        WHERE
+            s.auction_id IS NULL AND
+            NOT EXISTS (
+                SELECT 1
+                FROM failed_settlement_updates f
+                WHERE f.block_number = s.block_number AND f.log_index = s.log_index
+            )
+    ) AS pending,
+    (SELECT MAX(block_number) FROM settlements) AS latest_block,
+    (SELECT MAX(block_number) FROM settlements WHERE auction_id IS NOT NULL) AS latest_updated_block
     "#;
-    sqlx::query_as(QUERY).fetch_optional(ex).await
+    sqlx::query_as(QUERY).fetch_one(ex).await
 }
 
+/// Whether a settlement event received the auction data of the auction, i.e.
+/// it has observations or was submitted by the winner. Settlements of other
+/// solvers claiming the auction don't count so they can't prevent the winner's
+/// settlement from getting indexed.
 pub async fn already_processed(
     ex: &mut PgConnection,
     auction_id: i64,
 ) -> Result<bool, sqlx::Error> {
-    const QUERY: &str = r#"SELECT COUNT(*) FROM settlements WHERE auction_id = $1;"#;
-    let count: i64 = sqlx::query_scalar(QUERY)
+    const QUERY: &str = r#"
+SELECT EXISTS (
+    SELECT 1
+    FROM settlements s
+    WHERE
+        s.auction_id = $1 AND
+        (
+            EXISTS (
+                SELECT 1
+                FROM settlement_observations o
+                WHERE o.block_number = s.block_number AND o.log_index = s.log_index
+            ) OR
+            EXISTS (
+                SELECT 1
+                FROM settlement_scores sc
+                WHERE sc.auction_id = s.auction_id AND sc.winner = s.tx_from
+            )
+        )
+)
+    "#;
+    sqlx::query_scalar(QUERY)
         .bind(auction_id)
         .fetch_one(ex)
-        .await?;
-    Ok(count >= 1)
+        .await
 }
 
 pub async fn update_settlement_auction(
@@ -92,6 +201,496 @@ WHERE block_number = $2 AND log_index = $3
         .map(|_| ())
 }
 
+/// Whether a settlement event other than the specified one received the
+/// auction data of the auction. Like for [`already_processed`] settlements
+/// submitted by other solvers than the winner don't count.
+pub async fn settled_by_other(
+    ex: &mut PgConnection,
+    auction_id: i64,
+    block_number: i64,
+    log_index: i64,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT EXISTS (
+    SELECT 1
+    FROM settlements s
+    WHERE
+        s.auction_id = $1 AND
+        (s.block_number, s.log_index) <> ($2, $3) AND
+        (
+            EXISTS (
+                SELECT 1
+                FROM settlement_observations o
+                WHERE o.block_number = s.block_number AND o.log_index = s.log_index
+            ) OR
+            EXISTS (
+                SELECT 1
+                FROM settlement_scores sc
+                WHERE sc.auction_id = s.auction_id AND sc.winner = s.tx_from
+            )
+        )
+)
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(auction_id)
+        .bind(block_number)
+        .bind(log_index)
+        .fetch_one(ex)
+        .await
+}
+
+/// Records that the settlement claimed to settle an auction that a different
+/// settlement is already associated with.
+pub async fn update_duplicate_auction_claim(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    auction_id: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE settlements
+SET duplicate_auction_claim = $1
+WHERE block_number = $2 AND log_index = $3
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(auction_id)
+        .bind(block_number)
+        .bind(log_index)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+/// The metadata appended to the calldata of a settlement.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SettlementMetadata {
+    #[sqlx(rename = "metadata_version")]
+    pub version: i16,
+    pub solution_id: Option<BigDecimal>,
+    pub solver_flags: Option<i64>,
+}
+
+pub async fn update_settlement_metadata(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    metadata: &SettlementMetadata,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE settlements
+SET metadata_version = $1, solution_id = $2, solver_flags = $3
+WHERE block_number = $4 AND log_index = $5
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(metadata.version)
+        .bind(&metadata.solution_id)
+        .bind(metadata.solver_flags)
+        .bind(block_number)
+        .bind(log_index)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+/// Returns `None` if no metadata was stored for the settlement.
+pub async fn settlement_metadata(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+) -> Result<Option<SettlementMetadata>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT metadata_version, solution_id, solver_flags
+FROM settlements
+WHERE block_number = $1 AND log_index = $2 AND metadata_version IS NOT NULL
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(block_number)
+        .bind(log_index)
+        .fetch_optional(ex)
+        .await
+}
+
+/// Locks the settlement event for updating its details. Returns `false` if the
+/// event no longer belongs to the transaction or was already updated, e.g.
+/// because the block was reorged in the meantime. The lock is held until the
+/// end of the transaction so the event can't get reorged while it is updated.
+pub async fn lock_for_update(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    tx_hash: &TransactionHash,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT 1
+FROM settlements
+WHERE block_number = $1 AND log_index = $2 AND tx_hash = $3 AND auction_id IS NULL
+FOR UPDATE
+    ;"#;
+    let row: Option<i32> = sqlx::query_scalar(QUERY)
+        .bind(block_number)
+        .bind(log_index)
+        .bind(tx_hash)
+        .fetch_optional(ex)
+        .await?;
+    Ok(row.is_some())
+}
+
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+pub struct UpdatedSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: TransactionHash,
+    pub auction_id: i64,
+    /// Whether observations were stored for the settlement.
+    pub observed: bool,
+}
+
+/// Returns the settlement events in the block range that were already
+/// associated with an auction, oldest first.
+pub async fn updated_settlements(
+    ex: &mut PgConnection,
+    block_range: Range<i64>,
+) -> Result<Vec<UpdatedSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    s.block_number, s.log_index, s.tx_hash, s.auction_id,
+    EXISTS (
+        SELECT 1
+        FROM settlement_observations o
+        WHERE o.block_number = s.block_number AND o.log_index = s.log_index
+    ) AS observed
+FROM settlements s
+WHERE
+    s.block_number >= $1 AND
+    s.block_number < $2 AND
+    s.auction_id IS NOT NULL
+ORDER BY s.block_number ASC, s.log_index ASC
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .fetch_all(ex)
+        .await
+}
+
+/// Locks a settlement event that was already associated with the auction for
+/// updating its details again. Returns `false` if the event no longer belongs
+/// to the transaction or auction, e.g. because the block was reorged in the
+/// meantime.
+pub async fn lock_updated(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    tx_hash: &TransactionHash,
+    auction_id: i64,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT 1
+FROM settlements
+WHERE block_number = $1 AND log_index = $2 AND tx_hash = $3 AND auction_id = $4
+FOR UPDATE
+    ;"#;
+    let row: Option<i32> = sqlx::query_scalar(QUERY)
+        .bind(block_number)
+        .bind(log_index)
+        .bind(tx_hash)
+        .bind(auction_id)
+        .fetch_optional(ex)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Invalidates the details of the settlements in the block range so that they
+/// get updated again. This is needed when the blocks got reorged.
+pub async fn invalidate_updates(
+    ex: &mut PgConnection,
+    block_range: Range<i64>,
+) -> Result<(), sqlx::Error> {
+    const QUERY_OBSERVATIONS: &str = r#"
+DELETE FROM settlement_observations WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_OBSERVATIONS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_ORDER_EXECUTIONS: &str = r#"
+DELETE FROM order_execution WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_ORDER_EXECUTIONS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_INTERACTIONS: &str = r#"
+DELETE FROM settlement_interactions WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_INTERACTIONS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_UNEXPECTED_SUBMITTERS: &str = r#"
+DELETE FROM unexpected_settlement_submitters WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_UNEXPECTED_SUBMITTERS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_JIT_ORDERS: &str = r#"
+DELETE FROM jit_orders WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_JIT_ORDERS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_LATE_CANCELLATIONS: &str = r#"
+DELETE FROM late_settled_cancellations WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_LATE_CANCELLATIONS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_FAILED_UPDATES: &str = r#"
+DELETE FROM failed_settlement_updates WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_FAILED_UPDATES)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(&mut *ex)
+        .await?;
+
+    const QUERY_SETTLEMENTS: &str = r#"
+UPDATE settlements
+SET auction_id = NULL, tx_replaced = NULL, duplicate_auction_claim = NULL,
+    metadata_version = NULL, solution_id = NULL, solver_flags = NULL
+WHERE block_number >= $1 AND block_number < $2
+    ;"#;
+    sqlx::query(QUERY_SETTLEMENTS)
+        .bind(block_range.start)
+        .bind(block_range.end)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Stores the sender and nonce of the transaction that emitted the settlement
+/// event. These are needed to find the mined transaction when it replaced
+/// other transactions with the same nonce.
+pub async fn update_settlement_sender(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    tx_from: &Address,
+    tx_nonce: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE settlements
+SET tx_from = $1, tx_nonce = $2
+WHERE block_number = $3 AND log_index = $4
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(tx_from)
+        .bind(tx_nonce)
+        .bind(block_number)
+        .bind(log_index)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+/// Records whether the mined settlement transaction differs from the first
+/// submission its sender broadcast for the auction. Both use the same sender
+/// and nonce, so a difference means the submission got replaced. Stays `NULL`
+/// if no submission was observed.
+pub async fn update_settlement_replaced(
+    ex: &mut PgConnection,
+    block_number: i64,
+    log_index: i64,
+    auction_id: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE settlements s
+SET tx_replaced = (
+    SELECT sub.tx_hash <> s.tx_hash
+    FROM settlement_submissions sub
+    WHERE sub.auction_id = $1 AND sub.tx_from = s.tx_from
+)
+WHERE block_number = $2 AND log_index = $3
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(auction_id)
+        .bind(block_number)
+        .bind(log_index)
+        .execute(ex)
+        .await
+        .map(|_| ())
+}
+
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+pub struct SenderNonceSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    /// Hash of the mined transaction.
+    pub tx_hash: TransactionHash,
+    /// Whether the mined transaction replaced the observed submission. `None`
+    /// if no submission was observed.
+    pub tx_replaced: Option<bool>,
+}
+
+/// The mined settlement transaction with the specified sender and nonce.
+pub async fn settlement_by_sender_nonce(
+    ex: &mut PgConnection,
+    tx_from: &Address,
+    tx_nonce: i64,
+) -> Result<Option<SenderNonceSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT block_number, log_index, tx_hash, tx_replaced
+FROM settlements
+WHERE tx_from = $1 AND tx_nonce = $2
+LIMIT 1
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(tx_from)
+        .bind(tx_nonce)
+        .fetch_optional(ex)
+        .await
+}
+
+/// What happened in the settlement of an auction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettlementSummary {
+    pub auction_id: AuctionId,
+    pub tx_hash: TransactionHash,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub gas_used: BigDecimal,
+    pub effective_gas_price: BigDecimal,
+    /// Total surplus in the native token.
+    pub surplus: BigDecimal,
+    /// Total fee in the native token.
+    pub fee: BigDecimal,
+    /// The orders executed by the settlement ordered by uid.
+    pub executions: Vec<OrderExecutionSummary>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderExecutionSummary {
+    pub order_uid: OrderUid,
+    /// Executed surplus fee in the sell token.
+    pub executed_fee: Option<BigDecimal>,
+    /// `None` for executions that were indexed before executed amounts were
+    /// recorded.
+    pub amounts: Option<ExecutedAmounts>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SummaryRow {
+    tx_hash: TransactionHash,
+    block_number: i64,
+    log_index: i64,
+    solver: Address,
+    gas_used: BigDecimal,
+    effective_gas_price: BigDecimal,
+    surplus: BigDecimal,
+    fee: BigDecimal,
+    order_uid: Option<OrderUid>,
+    surplus_fee: Option<BigDecimal>,
+    executed_sell_amount: Option<BigDecimal>,
+    executed_buy_amount: Option<BigDecimal>,
+    executed_fee_amount: Option<BigDecimal>,
+    sell_price: Option<BigDecimal>,
+    buy_price: Option<BigDecimal>,
+    order_surplus: Option<BigDecimal>,
+    native_fee: Option<BigDecimal>,
+    network_fee: Option<BigDecimal>,
+    protocol_fee: Option<BigDecimal>,
+}
+
+impl SummaryRow {
+    /// `None` if the settlement did not execute any orders.
+    fn into_execution(self) -> Option<OrderExecutionSummary> {
+        Some(OrderExecutionSummary {
+            order_uid: self.order_uid?,
+            executed_fee: self.surplus_fee.clone(),
+            amounts: self.into_amounts(),
+        })
+    }
+
+    fn into_amounts(self) -> Option<ExecutedAmounts> {
+        Some(ExecutedAmounts {
+            executed_sell_amount: self.executed_sell_amount?,
+            executed_buy_amount: self.executed_buy_amount?,
+            executed_fee_amount: self.executed_fee_amount?,
+            sell_price: self.sell_price?,
+            buy_price: self.buy_price?,
+            surplus: self.order_surplus?,
+            native_fee: self.native_fee?,
+            network_fee: self.network_fee,
+            protocol_fee: self.protocol_fee,
+        })
+    }
+}
+
+/// Summarizes the settlement of an auction from the settlement event, its
+/// observations and the order executions. Returns `None` if the auction was not
+/// settled or the settlement was not observed yet.
+pub async fn settlement_summary(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+) -> Result<Option<SettlementSummary>, sqlx::Error> {
+    const QUERY: &str = r#"
+WITH settlement AS (
+    SELECT s.tx_hash, s.block_number, s.log_index, s.solver, so.gas_used,
+        so.effective_gas_price, so.surplus, so.fee
+    FROM settlements s
+    JOIN settlement_observations so
+        ON so.block_number = s.block_number AND so.log_index = s.log_index
+    WHERE s.auction_id = $1
+    ORDER BY s.block_number ASC, s.log_index ASC
+    LIMIT 1
+)
+SELECT
+    s.*, oe.order_uid, oe.surplus_fee, oe.executed_sell_amount, oe.executed_buy_amount,
+    oe.executed_fee_amount, oe.sell_price, oe.buy_price, oe.surplus AS order_surplus,
+    oe.native_fee, oe.network_fee, oe.protocol_fee
+FROM settlement s
+LEFT JOIN order_execution oe ON oe.auction_id = $1
+ORDER BY oe.order_uid ASC
+    "#;
+    let rows: Vec<SummaryRow> = sqlx::query_as(QUERY).bind(auction_id).fetch_all(ex).await?;
+    let Some(first) = rows.first() else {
+        return Ok(None);
+    };
+    let mut summary = SettlementSummary {
+        auction_id,
+        tx_hash: first.tx_hash,
+        block_number: first.block_number,
+        log_index: first.log_index,
+        solver: first.solver,
+        gas_used: first.gas_used.clone(),
+        effective_gas_price: first.effective_gas_price.clone(),
+        surplus: first.surplus.clone(),
+        fee: first.fee.clone(),
+        executions: Vec::new(),
+    };
+    summary.executions = rows
+        .into_iter()
+        .filter_map(SummaryRow::into_execution)
+        .collect();
+    Ok(Some(summary))
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -173,20 +772,596 @@ mod tests {
             .await
             .unwrap();
 
-        let settlement = get_settlement_without_auction(&mut db)
+        let settlements = get_settlements_without_auction(&mut db, 1).await.unwrap();
+
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].block_number, event.block_number);
+        assert_eq!(settlements[0].log_index, event.log_index);
+
+        update_settlement_auction(&mut db, event.block_number, event.log_index, 1)
             .await
-            .unwrap()
             .unwrap();
 
-        assert_eq!(settlement.block_number, event.block_number);
-        assert_eq!(settlement.log_index, event.log_index);
+        let settlements = get_settlements_without_auction(&mut db, 1).await.unwrap();
 
-        update_settlement_auction(&mut db, event.block_number, event.log_index, 1)
+        assert!(settlements.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_metadata_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        crate::events::insert_settlement(&mut db, &event, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(settlement_metadata(&mut db, 1, 2).await.unwrap(), None);
+
+        let metadata = SettlementMetadata {
+            version: 1,
+            solution_id: Some(3.into()),
+            solver_flags: Some(4),
+        };
+        update_settlement_metadata(&mut db, 1, 2, &metadata)
+            .await
+            .unwrap();
+        assert_eq!(
+            settlement_metadata(&mut db, 1, 2).await.unwrap(),
+            Some(metadata)
+        );
+
+        invalidate_updates(&mut db, 1..2).await.unwrap();
+        assert_eq!(settlement_metadata(&mut db, 1, 2).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_duplicate_auction_claim() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let original = EventIndex {
+            block_number: 1,
+            log_index: 0,
+        };
+        let duplicate = EventIndex {
+            block_number: 2,
+            log_index: 0,
+        };
+        for event in [&original, &duplicate] {
+            crate::events::insert_settlement(&mut db, event, &Default::default())
+                .await
+                .unwrap();
+        }
+        assert!(!settled_by_other(&mut db, 1, 2, 0).await.unwrap());
+
+        update_settlement_auction(&mut db, 1, 0, 1).await.unwrap();
+        crate::settlement_observations::upsert(
+            &mut db,
+            crate::settlement_observations::Observation {
+                block_number: 1,
+                log_index: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(settled_by_other(&mut db, 1, 2, 0).await.unwrap());
+        // The settlement associated with the auction is not a different one.
+        assert!(!settled_by_other(&mut db, 1, 1, 0).await.unwrap());
+
+        update_settlement_auction(&mut db, 2, 0, 0).await.unwrap();
+        update_duplicate_auction_claim(&mut db, 2, 0, 1)
+            .await
+            .unwrap();
+        let claim = |block_number: i64| {
+            sqlx::query_scalar::<_, Option<i64>>(
+                "SELECT duplicate_auction_claim FROM settlements WHERE block_number = $1",
+            )
+            .bind(block_number)
+        };
+        assert_eq!(claim(1).fetch_one(&mut *db).await.unwrap(), None);
+        assert_eq!(claim(2).fetch_one(&mut *db).await.unwrap(), Some(1));
+        // The duplicate claim does not count as settling the auction.
+        assert!(!settled_by_other(&mut db, 1, 1, 0).await.unwrap());
+
+        invalidate_updates(&mut db, 2..3).await.unwrap();
+        assert_eq!(claim(2).fetch_one(&mut *db).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_concurrent_updaters_process_settlements_once() {
+        let pool = sqlx::PgPool::connect("postgresql://").await.unwrap();
+        crate::clear_DANGER(&pool).await.unwrap();
+
+        let mut db = pool.acquire().await.unwrap();
+        for block_number in 0..20 {
+            let event = EventIndex {
+                block_number,
+                log_index: 0,
+            };
+            crate::events::insert_settlement(&mut db, &event, &Default::default())
+                .await
+                .unwrap();
+        }
+
+        let updater = |auction_id: i64| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut processed = Vec::new();
+                loop {
+                    let mut ex = pool.acquire().await.unwrap();
+                    let settlements = get_settlements_without_auction(&mut ex, 3).await.unwrap();
+                    if settlements.is_empty() {
+                        break processed;
+                    }
+                    // Both updaters might fetch the transactions of the same
+                    // events while no locks are held.
+                    tokio::task::yield_now().await;
+
+                    let mut ex = ex.begin().await.unwrap();
+                    let events: Vec<_> = settlements
+                        .iter()
+                        .map(|settlement| EventIndex {
+                            block_number: settlement.block_number,
+                            log_index: settlement.log_index,
+                        })
+                        .collect();
+                    let locked = lock_settlements_without_auction(&mut ex, &events)
+                        .await
+                        .unwrap();
+                    for event in locked {
+                        update_settlement_auction(
+                            &mut ex,
+                            event.block_number,
+                            event.log_index,
+                            auction_id,
+                        )
+                        .await
+                        .unwrap();
+                        processed.push(event.block_number);
+                    }
+                    // Give the other updater a chance to lock events while this
+                    // one holds its locks.
+                    tokio::task::yield_now().await;
+                    ex.commit().await.unwrap();
+                }
+            })
+        };
+        let (first, second) = futures::join!(updater(1), updater(2));
+        let mut processed = [first.unwrap(), second.unwrap()].concat();
+        processed.sort();
+        assert_eq!(processed, (0..20).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_of_non_winner_does_not_process_auction() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let winner = ByteArray([1; 20]);
+        crate::settlement_scores::insert(
+            &mut db,
+            crate::settlement_scores::Score {
+                auction_id: 1,
+                winner,
+                winning_score: Default::default(),
+                reference_score: Default::default(),
+                block_deadline: 0,
+                simulation_block: 0,
+                gas_price: None,
+            },
+        )
+        .await
+        .unwrap();
+        for block_number in [1, 2] {
+            let event = EventIndex {
+                block_number,
+                log_index: 0,
+            };
+            crate::events::insert_settlement(&mut db, &event, &Default::default())
+                .await
+                .unwrap();
+        }
+
+        // A different solver settles the auction first.
+        update_settlement_auction(&mut db, 1, 0, 1).await.unwrap();
+        update_settlement_sender(&mut db, 1, 0, &ByteArray([2; 20]), 0)
+            .await
+            .unwrap();
+        assert!(!already_processed(&mut db, 1).await.unwrap());
+        assert!(!settled_by_other(&mut db, 1, 2, 0).await.unwrap());
+
+        // The winner's settlement gets processed afterwards.
+        update_settlement_auction(&mut db, 2, 0, 1).await.unwrap();
+        update_settlement_sender(&mut db, 2, 0, &winner, 0)
+            .await
+            .unwrap();
+        assert!(already_processed(&mut db, 1).await.unwrap());
+        assert!(settled_by_other(&mut db, 1, 1, 0).await.unwrap());
+        assert!(!settled_by_other(&mut db, 1, 2, 0).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_update_progress() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        assert_eq!(
+            update_progress(&mut db).await.unwrap(),
+            UpdateProgress::default()
+        );
+
+        for block_number in [1, 2, 3] {
+            let event = EventIndex {
+                block_number,
+                log_index: 0,
+            };
+            crate::events::insert_settlement(&mut db, &event, &Default::default())
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            update_progress(&mut db).await.unwrap(),
+            UpdateProgress {
+                pending: 3,
+                latest_block: Some(3),
+                latest_updated_block: None,
+            }
+        );
+
+        update_settlement_auction(&mut db, 1, 0, 1).await.unwrap();
+        update_settlement_auction(&mut db, 2, 0, 2).await.unwrap();
+        assert_eq!(
+            update_progress(&mut db).await.unwrap(),
+            UpdateProgress {
+                pending: 1,
+                latest_block: Some(3),
+                latest_updated_block: Some(2),
+            }
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlements_without_auction_batch() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let events = [
+            EventIndex {
+                block_number: 2,
+                log_index: 0,
+            },
+            EventIndex {
+                block_number: 1,
+                log_index: 1,
+            },
+            EventIndex {
+                block_number: 1,
+                log_index: 0,
+            },
+        ];
+        for event in &events {
+            crate::events::insert_settlement(&mut db, event, &Default::default())
+                .await
+                .unwrap();
+        }
+
+        let settlements = get_settlements_without_auction(&mut db, 2).await.unwrap();
+        let indices: Vec<_> = settlements
+            .iter()
+            .map(|settlement| (settlement.block_number, settlement.log_index))
+            .collect();
+        assert_eq!(indices, [(1, 0), (1, 1)]);
+
+        let settlements = get_settlements_without_auction(&mut db, 10).await.unwrap();
+        assert_eq!(settlements.len(), 3);
+
+        // Only requested events that weren't updated yet get locked.
+        update_settlement_auction(&mut db, 1, 0, 1).await.unwrap();
+        let locked = lock_settlements_without_auction(&mut db, &events[..2])
+            .await
+            .unwrap();
+        assert_eq!(locked, [events[1], events[0]]);
+        let locked = lock_settlements_without_auction(&mut db, &events[2..])
+            .await
+            .unwrap();
+        assert!(locked.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_updated_settlements() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let events = [
+            EventIndex {
+                block_number: 1,
+                log_index: 0,
+            },
+            EventIndex {
+                block_number: 2,
+                log_index: 0,
+            },
+            EventIndex {
+                block_number: 2,
+                log_index: 1,
+            },
+            EventIndex {
+                block_number: 3,
+                log_index: 0,
+            },
+        ];
+        for event in &events {
+            crate::events::insert_settlement(&mut db, event, &Default::default())
+                .await
+                .unwrap();
+        }
+        // (2, 1) was not updated yet.
+        for (event, auction_id) in [(&events[0], 1), (&events[1], 2), (&events[3], 3)] {
+            update_settlement_auction(&mut db, event.block_number, event.log_index, auction_id)
+                .await
+                .unwrap();
+        }
+        crate::settlement_observations::upsert(
+            &mut db,
+            crate::settlement_observations::Observation {
+                block_number: 2,
+                log_index: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let settlements = updated_settlements(&mut db, 1..3).await.unwrap();
+        let updated: Vec<_> = settlements
+            .iter()
+            .map(|s| (s.block_number, s.log_index, s.auction_id, s.observed))
+            .collect();
+        assert_eq!(updated, [(1, 0, 1, false), (2, 0, 2, true)]);
+
+        let hash = ByteArray::default();
+        assert!(lock_updated(&mut db, 2, 0, &hash, 2).await.unwrap());
+        assert!(!lock_updated(&mut db, 2, 0, &hash, 3).await.unwrap());
+        assert!(!lock_updated(&mut db, 2, 1, &hash, 2).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_by_sender_nonce() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let event = Default::default();
+        crate::events::insert_settlement(
+            &mut db,
+            &event,
+            &Settlement {
+                solver: ByteArray([1; 20]),
+                transaction_hash: ByteArray([2; 32]),
+            },
+        )
+        .await
+        .unwrap();
+
+        // The sender is only known once the settlement got updated.
+        let sender = ByteArray([1; 20]);
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            None
+        );
+
+        update_settlement_sender(&mut db, event.block_number, event.log_index, &sender, 3)
+            .await
+            .unwrap();
+        let settlement = |tx_replaced| SenderNonceSettlement {
+            block_number: event.block_number,
+            log_index: event.log_index,
+            tx_hash: ByteArray([2; 32]),
+            tx_replaced,
+        };
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            Some(settlement(None))
+        );
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 4)
+                .await
+                .unwrap(),
+            None
+        );
+
+        // No submission was observed for the auction.
+        update_settlement_replaced(&mut db, event.block_number, event.log_index, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            Some(settlement(None))
+        );
+
+        // Submissions of other senders don't count.
+        crate::settlement_submissions::insert(&mut db, 1, &ByteArray([9; 20]), &ByteArray([3; 32]))
+            .await
+            .unwrap();
+        update_settlement_replaced(&mut db, event.block_number, event.log_index, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            Some(settlement(None))
+        );
+
+        // The mined transaction is the first submission.
+        crate::settlement_submissions::insert(&mut db, 1, &sender, &ByteArray([2; 32]))
+            .await
+            .unwrap();
+        update_settlement_replaced(&mut db, event.block_number, event.log_index, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            Some(settlement(Some(false)))
+        );
+
+        // The first submission got replaced by the mined transaction.
+        crate::settlement_submissions::insert(&mut db, 2, &sender, &ByteArray([3; 32]))
+            .await
+            .unwrap();
+        update_settlement_replaced(&mut db, event.block_number, event.log_index, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            settlement_by_sender_nonce(&mut db, &sender, 3)
+                .await
+                .unwrap(),
+            Some(settlement(Some(true)))
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_summary() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let event = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        crate::events::insert_settlement(
+            &mut db,
+            &event,
+            &Settlement {
+                solver: ByteArray([1; 20]),
+                transaction_hash: ByteArray([2; 32]),
+            },
+        )
+        .await
+        .unwrap();
+        update_settlement_auction(&mut db, event.block_number, event.log_index, 3)
             .await
             .unwrap();
+        crate::settlement_scores::insert(
+            &mut db,
+            crate::settlement_scores::Score {
+                auction_id: 3,
+                winner: ByteArray([1; 20]),
+                winning_score: 4.into(),
+                reference_score: 5.into(),
+                block_deadline: 6,
+                simulation_block: 7,
+                gas_price: None,
+            },
+        )
+        .await
+        .unwrap();
+        // The settlement was not observed yet.
+        assert_eq!(settlement_summary(&mut db, 3).await.unwrap(), None);
+
+        crate::settlement_observations::upsert(
+            &mut db,
+            crate::settlement_observations::Observation {
+                block_number: event.block_number,
+                log_index: event.log_index,
+                gas_used: 8.into(),
+                effective_gas_price: 9.into(),
+                surplus: 10.into(),
+                fee: 11.into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        // The settlement did not execute any orders.
+        let summary = SettlementSummary {
+            auction_id: 3,
+            tx_hash: ByteArray([2; 32]),
+            block_number: 1,
+            log_index: 2,
+            solver: ByteArray([1; 20]),
+            gas_used: 8.into(),
+            effective_gas_price: 9.into(),
+            surplus: 10.into(),
+            fee: 11.into(),
+            executions: vec![],
+        };
+        assert_eq!(
+            settlement_summary(&mut db, 3).await.unwrap(),
+            Some(summary.clone())
+        );
 
-        let settlement = get_settlement_without_auction(&mut db).await.unwrap();
+        let amounts = ExecutedAmounts {
+            executed_sell_amount: 12.into(),
+            executed_buy_amount: 13.into(),
+            executed_fee_amount: 14.into(),
+            sell_price: 15.into(),
+            buy_price: 16.into(),
+            surplus: 17.into(),
+            native_fee: 18.into(),
+            network_fee: Some(19.into()),
+            protocol_fee: None,
+        };
+        for order in [ByteArray([4; 56]), ByteArray([5; 56])] {
+            crate::order_execution::save(&mut db, &order, 3, 1, &20.into())
+                .await
+                .unwrap();
+        }
+        crate::order_execution::save_executed_amounts(&mut db, &ByteArray([5; 56]), 3, &amounts)
+            .await
+            .unwrap();
+        // Executions of other auctions are not part of the summary.
+        crate::order_execution::save(&mut db, &ByteArray([6; 56]), 4, 1, &20.into())
+            .await
+            .unwrap();
 
-        assert!(settlement.is_none());
+        assert_eq!(
+            settlement_summary(&mut db, 3).await.unwrap(),
+            Some(SettlementSummary {
+                executions: vec![
+                    OrderExecutionSummary {
+                        order_uid: ByteArray([4; 56]),
+                        executed_fee: Some(20.into()),
+                        amounts: None,
+                    },
+                    OrderExecutionSummary {
+                        order_uid: ByteArray([5; 56]),
+                        executed_fee: Some(20.into()),
+                        amounts: Some(amounts),
+                    },
+                ],
+                ..summary
+            })
+        );
+        assert_eq!(settlement_summary(&mut db, 4).await.unwrap(), None);
     }
 }
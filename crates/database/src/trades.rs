@@ -2,7 +2,10 @@ use {
     crate::{Address, OrderUid, TransactionHash},
     bigdecimal::BigDecimal,
     futures::stream::BoxStream,
-    sqlx::PgConnection,
+    sqlx::{
+        types::chrono::{DateTime, Utc},
+        PgConnection,
+    },
 };
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
@@ -64,17 +67,132 @@ ON o.uid = t.order_uid"#;
         .fetch(ex)
 }
 
+/// A single fill of an order.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct OrderExecutionRow {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: Option<TransactionHash>,
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub fee_amount: BigDecimal,
+    /// Set once the settlement updater processed the settlement.
+    pub surplus_fee: Option<BigDecimal>,
+}
+
+/// Returns every trade of the order ordered by block and log index together
+/// with the hash of the settling transaction and the surplus fee recorded for
+/// the order in that block.
+pub fn order_executions<'a>(
+    ex: &'a mut PgConnection,
+    order_uid: &'a OrderUid,
+) -> BoxStream<'a, Result<OrderExecutionRow, sqlx::Error>> {
+    const QUERY: &str = r#"
+SELECT
+    t.block_number,
+    t.log_index,
+    settlement.tx_hash,
+    t.sell_amount,
+    t.buy_amount,
+    t.fee_amount,
+    execution.surplus_fee
+FROM trades t
+LEFT OUTER JOIN LATERAL (
+    SELECT tx_hash FROM settlements s
+    WHERE s.block_number = t.block_number
+    AND   s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) AS settlement ON true
+LEFT OUTER JOIN LATERAL (
+    SELECT surplus_fee FROM order_execution oe
+    WHERE oe.order_uid = t.order_uid
+    AND   oe.block_number = t.block_number
+    LIMIT 1
+) AS execution ON true
+WHERE t.order_uid = $1
+ORDER BY t.block_number ASC, t.log_index ASC
+"#;
+
+    sqlx::query_as(QUERY).bind(order_uid).fetch(ex)
+}
+
+/// Surplus an owner received, denominated in the native token (wei).
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct UserSurplusRow {
+    /// Amount the owner received on top of their limit price.
+    pub surplus: BigDecimal,
+    /// Surplus fees the protocol charged the owner.
+    pub surplus_fee: BigDecimal,
+}
+
+/// Sums up the surplus of all trades of the owner's orders created in the
+/// `[from, to)` window. Amounts get converted to the native token with the
+/// prices of the auction that got settled by the trade. Trades of settlements
+/// that were not yet associated with an auction are not counted.
+pub async fn user_surplus(
+    ex: &mut PgConnection,
+    owner: &Address,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<UserSurplusRow, sqlx::Error> {
+    // Surplus is the amount received in the buy token (sell orders) or saved
+    // in the sell token (buy orders) compared to the order's limit price.
+    const QUERY: &str = r#"
+SELECT
+    COALESCE(FLOOR(SUM(GREATEST(
+        CASE o.kind
+            WHEN 'sell' THEN
+                (t.buy_amount - (t.sell_amount - t.fee_amount) * o.buy_amount / NULLIF(o.sell_amount, 0))
+                * buy_price.price
+            ELSE
+                (t.buy_amount * o.sell_amount / NULLIF(o.buy_amount, 0) - (t.sell_amount - t.fee_amount))
+                * sell_price.price
+        END,
+        0
+    )) / 1e18), 0) AS surplus,
+    COALESCE(FLOOR(SUM(COALESCE(execution.surplus_fee, 0) * sell_price.price) / 1e18), 0) AS surplus_fee
+FROM orders o
+JOIN trades t ON t.order_uid = o.uid
+JOIN LATERAL (
+    SELECT auction_id FROM settlements s
+    WHERE s.block_number = t.block_number
+    AND   s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) AS settlement ON true
+JOIN auction_prices sell_price
+    ON sell_price.auction_id = settlement.auction_id AND sell_price.token = o.sell_token
+JOIN auction_prices buy_price
+    ON buy_price.auction_id = settlement.auction_id AND buy_price.token = o.buy_token
+LEFT OUTER JOIN order_execution execution
+    ON execution.order_uid = o.uid AND execution.auction_id = settlement.auction_id
+WHERE o.owner = $1
+AND   o.creation_timestamp >= $2
+AND   o.creation_timestamp < $3
+"#;
+
+    sqlx::query_as(QUERY)
+        .bind(owner)
+        .bind(from)
+        .bind(to)
+        .fetch_one(ex)
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use {
         super::*,
         crate::{
+            auction_prices::AuctionPrice,
             byte_array::ByteArray,
             events::{Event, EventIndex, Settlement, Trade},
             onchain_broadcasted_orders::{insert_onchain_order, OnchainOrderPlacement},
-            orders::Order,
+            orders::{Order, OrderKind},
             PgTransaction,
         },
+        chrono::TimeZone,
         futures::TryStreamExt,
         sqlx::Connection,
     };
@@ -495,4 +613,216 @@ mod tests {
         .await;
         assert_trades(&mut db, None, None, &[trade_a, trade_b]).await;
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_executions_across_settlements() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order_uid = ByteArray([1; 56]);
+        let other_order_uid = ByteArray([2; 56]);
+        let trade = |block_number, log_index, order_uid, amount: u32| {
+            (
+                EventIndex {
+                    block_number,
+                    log_index,
+                },
+                Event::Trade(Trade {
+                    order_uid,
+                    sell_amount_including_fee: amount.into(),
+                    buy_amount: (2 * amount).into(),
+                    fee_amount: 1.into(),
+                }),
+            )
+        };
+        let settlement = |block_number, log_index, tx_hash| {
+            (
+                EventIndex {
+                    block_number,
+                    log_index,
+                },
+                Event::Settlement(Settlement {
+                    solver: Default::default(),
+                    transaction_hash: ByteArray([tx_hash; 32]),
+                }),
+            )
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                trade(1, 0, order_uid, 10),
+                trade(1, 1, other_order_uid, 100),
+                settlement(1, 2, 1),
+                trade(2, 0, order_uid, 20),
+                settlement(2, 1, 2),
+            ],
+        )
+        .await
+        .unwrap();
+        // Only the first settlement got processed by the settlement updater.
+        crate::order_execution::save(&mut db, &order_uid, 1, 1, &5.into())
+            .await
+            .unwrap();
+
+        let executions = order_executions(&mut db, &order_uid)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            executions,
+            vec![
+                OrderExecutionRow {
+                    block_number: 1,
+                    log_index: 0,
+                    tx_hash: Some(ByteArray([1; 32])),
+                    sell_amount: 10.into(),
+                    buy_amount: 20.into(),
+                    fee_amount: 1.into(),
+                    surplus_fee: Some(5.into()),
+                },
+                OrderExecutionRow {
+                    block_number: 2,
+                    log_index: 0,
+                    tx_hash: Some(ByteArray([2; 32])),
+                    sell_amount: 20.into(),
+                    buy_amount: 40.into(),
+                    fee_amount: 1.into(),
+                    surplus_fee: None,
+                },
+            ]
+        );
+
+        let executions = order_executions(&mut db, &ByteArray([3; 56]))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(executions.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_user_surplus() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let owner = ByteArray([1; 20]);
+        let sell_token = ByteArray([2; 20]);
+        let buy_token = ByteArray([3; 20]);
+        let window_start = Utc.timestamp_opt(1_000, 0).unwrap();
+        let window_end = Utc.timestamp_opt(2_000, 0).unwrap();
+        let order = |uid: u8, owner, kind, sell_amount: u32, buy_amount: u32, created: i64| Order {
+            uid: ByteArray([uid; 56]),
+            owner,
+            creation_timestamp: Utc.timestamp_opt(created, 0).unwrap(),
+            sell_token,
+            buy_token,
+            sell_amount: sell_amount.into(),
+            buy_amount: buy_amount.into(),
+            kind,
+            ..Default::default()
+        };
+        // Partially fillable sell order at a limit price of 1:1.
+        let partial = Order {
+            partially_fillable: true,
+            ..order(1, owner, OrderKind::Sell, 100, 100, 1_000)
+        };
+        // Buy order willing to pay up to 3 sell tokens per buy token.
+        let buy = order(2, owner, OrderKind::Buy, 30, 10, 1_500);
+        let other_owner = order(3, ByteArray([4; 20]), OrderKind::Sell, 100, 100, 1_500);
+        let outside_window = order(4, owner, OrderKind::Sell, 100, 100, 2_000);
+        for order in [&partial, &buy, &other_owner, &outside_window] {
+            crate::orders::insert_order(&mut db, order).await.unwrap();
+        }
+
+        let trade = |block_number, log_index, order: &Order, sell: u32, buy: u32| {
+            (
+                EventIndex {
+                    block_number,
+                    log_index,
+                },
+                Event::Trade(Trade {
+                    order_uid: order.uid,
+                    sell_amount_including_fee: sell.into(),
+                    buy_amount: buy.into(),
+                    fee_amount: 0.into(),
+                }),
+            )
+        };
+        let settlement = |block_number, log_index| {
+            (
+                EventIndex {
+                    block_number,
+                    log_index,
+                },
+                Event::Settlement(Default::default()),
+            )
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                trade(1, 0, &partial, 40, 50),
+                trade(1, 1, &other_owner, 100, 200),
+                settlement(1, 2),
+                trade(2, 0, &partial, 30, 36),
+                trade(2, 1, &buy, 20, 10),
+                trade(2, 2, &outside_window, 100, 200),
+                settlement(2, 3),
+                // Not yet associated with an auction.
+                trade(3, 0, &partial, 30, 40),
+                settlement(3, 1),
+            ],
+        )
+        .await
+        .unwrap();
+        crate::settlements::update_settlement_auction(&mut db, 1, 2, 1)
+            .await
+            .unwrap();
+        crate::settlements::update_settlement_auction(&mut db, 2, 3, 2)
+            .await
+            .unwrap();
+        let price = |auction_id, token, price: u64| AuctionPrice {
+            auction_id,
+            token,
+            price: price.into(),
+        };
+        crate::auction_prices::insert(
+            &mut db,
+            &[
+                price(1, sell_token, 1_000_000_000_000_000_000),
+                price(1, buy_token, 2_000_000_000_000_000_000),
+                price(2, sell_token, 2_000_000_000_000_000_000),
+                price(2, buy_token, 1_000_000_000_000_000_000),
+            ],
+        )
+        .await
+        .unwrap();
+        crate::order_execution::save(&mut db, &buy.uid, 2, 2, &2.into())
+            .await
+            .unwrap();
+
+        // Partial fills: 10 and 6 buy tokens surplus worth 2 and 1 wei each.
+        // Buy order: saved 10 sell tokens worth 2 wei each.
+        assert_eq!(
+            user_surplus(&mut db, &owner, window_start, window_end)
+                .await
+                .unwrap(),
+            UserSurplusRow {
+                surplus: 46.into(),
+                surplus_fee: 4.into(),
+            }
+        );
+
+        assert_eq!(
+            user_surplus(&mut db, &ByteArray([5; 20]), window_start, window_end)
+                .await
+                .unwrap(),
+            UserSurplusRow {
+                surplus: 0.into(),
+                surplus_fee: 0.into(),
+            }
+        );
+    }
 }
@@ -0,0 +1,81 @@
+use {
+    crate::{auction::AuctionId, Address},
+    sqlx::PgConnection,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct UnexpectedSubmitter {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub auction_id: AuctionId,
+    /// The winner of the solver competition of the auction.
+    pub expected_winner: Address,
+    /// The account that actually submitted the settlement.
+    pub actual_sender: Address,
+}
+
+pub async fn insert(
+    ex: &mut PgConnection,
+    submitter: &UnexpectedSubmitter,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO unexpected_settlement_submitters (block_number, log_index, auction_id, expected_winner, actual_sender)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (block_number, log_index) DO UPDATE
+SET auction_id = EXCLUDED.auction_id, expected_winner = EXCLUDED.expected_winner,
+actual_sender = EXCLUDED.actual_sender
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(submitter.block_number)
+        .bind(submitter.log_index)
+        .bind(submitter.auction_id)
+        .bind(submitter.expected_winner)
+        .bind(submitter.actual_sender)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Settlements of the auction that were not submitted by its winner.
+pub async fn fetch(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+) -> Result<Vec<UnexpectedSubmitter>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT *
+FROM unexpected_settlement_submitters
+WHERE auction_id = $1
+ORDER BY block_number ASC, log_index ASC
+    "#;
+    sqlx::query_as(QUERY).bind(auction_id).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::byte_array::ByteArray, sqlx::Connection};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let submitter = UnexpectedSubmitter {
+            block_number: 1,
+            log_index: 2,
+            auction_id: 3,
+            expected_winner: ByteArray([4; 20]),
+            actual_sender: ByteArray([5; 20]),
+        };
+        insert(&mut db, &submitter).await.unwrap();
+        assert_eq!(fetch(&mut db, 3).await.unwrap(), [submitter.clone()]);
+        assert!(fetch(&mut db, 4).await.unwrap().is_empty());
+
+        // The settlement got reorged.
+        crate::settlements::invalidate_updates(&mut db, 1..2)
+            .await
+            .unwrap();
+        assert!(fetch(&mut db, 3).await.unwrap().is_empty());
+    }
+}
@@ -35,6 +35,12 @@ pub struct Config {
     pub target_confirm_time: std::time::Duration,
     pub max_confirm_time: std::time::Duration,
     pub retry_interval: std::time::Duration,
+    /// The number of blocks mined on top of a settlement transaction before
+    /// it is considered executed. Only used by the native submission logic.
+    pub confirmation_blocks: u64,
+    /// How often a cancellation gets replaced with a higher gas price before
+    /// giving up. Only used by the native submission logic.
+    pub max_cancellation_attempts: usize,
     pub kind: Kind,
     pub submission: SubmissionLogic,
 }
@@ -205,7 +211,10 @@ impl Mempool {
             ))
             .await
             .map_err(|err| match err {
-                SubmissionError::SimulationRevert(_) => mempools::Error::SimulationRevert,
+                SubmissionError::SimulationRevert(_) => mempools::Error::SimulationRevert {
+                    tx_id: None,
+                    cancellation: None,
+                },
                 SubmissionError::Revert(hash) => mempools::Error::Revert(hash.into()),
                 _ => mempools::Error::Other(anyhow::Error::from(err)),
             })?;
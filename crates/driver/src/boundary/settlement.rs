@@ -311,6 +311,14 @@ fn to_boundary_order(order: &competition::Order) -> Order {
             onchain_order_data: Default::default(),
             is_liquidity_order: order.is_liquidity(),
             full_app_data: Default::default(),
+            amended_amounts: Default::default(),
+            replaces: Default::default(),
+            replaced_by: Default::default(),
+            quote_verified: Default::default(),
+            balance_check_skipped: Default::default(),
+            settlement_tx_hashes: Default::default(),
+            source: Default::default(),
+            insufficient_balance: Default::default(),
         },
         signature: to_boundary_signature(&order.signature),
         interactions: Interactions {
@@ -273,7 +273,7 @@ impl Competition {
 
         match executed {
             Err(_) => Err(Error::SubmissionError),
-            Ok(tx_hash) => Ok(Settled {
+            Ok(mined) => Ok(Settled {
                 internalized_calldata: settlement
                     .calldata(
                         self.eth.contracts().settlement(),
@@ -286,7 +286,8 @@ impl Competition {
                         settlement::Internalization::Disable,
                     )
                     .into(),
-                tx_hash,
+                tx_hash: mined.tx_id,
+                submitted_tx_hash: mined.submitted,
             }),
         }
     }
@@ -382,6 +383,9 @@ pub struct Revealed {
 pub struct Settled {
     /// The transaction hash in which the solution was submitted.
     pub tx_hash: eth::TxId,
+    /// The transaction that was broadcast first for the solution, if known.
+    /// The mined transaction replaced it if the two differ.
+    pub submitted_tx_hash: Option<eth::TxId>,
     pub internalized_calldata: Bytes<Vec<u8>>,
     /// The uninternalized calldata must be known so that the CoW solver team
     /// can manually enforce certain rules which can not be enforced
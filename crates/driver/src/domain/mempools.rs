@@ -2,10 +2,17 @@ use {
     super::{competition, eth},
     crate::{
         domain::{competition::solution::Settlement, eth::TxStatus},
-        infra::{self, observe, solver::Solver, Ethereum},
+        infra::{self, blockchain, observe, solver::Solver, Ethereum},
     },
     ethrpc::current_block::into_stream,
-    futures::{future::select_ok, FutureExt, StreamExt},
+    futures::{
+        future::{select_ok, BoxFuture},
+        FutureExt,
+        Stream,
+        StreamExt,
+        TryFutureExt,
+    },
+    std::time::Duration,
     thiserror::Error,
     tracing::Instrument,
 };
@@ -22,47 +29,79 @@ const CANCELLATION_GAS_AMOUNT: u64 = 21000;
 pub struct Mempools {
     mempools: Vec<infra::Mempool>,
     ethereum: Ethereum,
+    strategy: Strategy,
 }
 
 impl Mempools {
-    pub fn new(mempools: Vec<infra::Mempool>, ethereum: Ethereum) -> Result<Self, NoMempools> {
+    pub fn new(
+        mempools: Vec<infra::Mempool>,
+        ethereum: Ethereum,
+        strategy: Strategy,
+    ) -> Result<Self, NoMempools> {
         if mempools.is_empty() {
             Err(NoMempools)
         } else {
-            Ok(Self { mempools, ethereum })
+            Ok(Self {
+                mempools,
+                ethereum,
+                strategy,
+            })
         }
     }
 
     /// Publish a settlement to the mempools.
-    pub async fn execute(
+    pub async fn execute(&self, solver: &Solver, settlement: &Settlement) -> Result<Mined, Error> {
+        tracing::info!(
+            strategy = self.strategy.format_variant(),
+            auction_id = ?settlement.auction_id,
+            "executing settlement"
+        );
+        let execute = |mempools: Vec<infra::Mempool>| {
+            mempools
+                .into_iter()
+                .map(|mempool| {
+                    async move { self.execute_in(&mempool, solver, settlement).await }.boxed()
+                })
+                .collect::<Vec<_>>()
+        };
+        match self.strategy {
+            Strategy::Race => race(execute(self.mempools.clone())).await,
+            Strategy::Sequential { private_timeout } => {
+                let (private, public): (Vec<_>, Vec<_>) = self
+                    .mempools
+                    .iter()
+                    .cloned()
+                    .partition(|mempool| mempool.is_private());
+                sequential(execute(private), execute(public), private_timeout).await
+            }
+        }
+    }
+
+    /// Publish a settlement to a single mempool.
+    async fn execute_in(
         &self,
+        mempool: &infra::Mempool,
         solver: &Solver,
         settlement: &Settlement,
-    ) -> Result<eth::TxId, Error> {
-        let (tx_hash, _remaining_futures) =
-            select_ok(self.mempools.iter().cloned().map(|mempool| {
-                async move {
-                    let result = match &mempool {
-                        infra::Mempool::Boundary(mempool) => {
-                            mempool.execute(solver, settlement.clone()).await
-                        }
-                        infra::Mempool::Native(inner) => {
-                            self.submit(inner, solver, settlement)
-                                .instrument(tracing::info_span!(
-                                    "mempool",
-                                    kind = inner.to_string()
-                                ))
-                                .await
-                        }
-                    };
-                    observe::mempool_executed(&mempool, settlement, &result);
-                    result
-                }
-                .boxed()
-            }))
-            .await?;
-
-        Ok(tx_hash)
+    ) -> Result<Mined, Error> {
+        let result = match mempool {
+            // The legacy submission logic doesn't report the transactions it
+            // broadcast.
+            infra::Mempool::Boundary(mempool) => mempool
+                .execute(solver, settlement.clone())
+                .await
+                .map(|tx_id| Mined {
+                    tx_id,
+                    submitted: None,
+                }),
+            infra::Mempool::Native(inner) => {
+                self.submit(inner, solver, settlement)
+                    .instrument(tracing::info_span!("mempool", kind = inner.to_string()))
+                    .await
+            }
+        };
+        observe::mempool_executed(mempool, self.strategy, settlement, &result);
+        result
     }
 
     /// Defines if the mempools are configured in a way that guarantees that
@@ -85,13 +124,14 @@ impl Mempools {
         mempool: &infra::mempool::Inner,
         solver: &Solver,
         settlement: &Settlement,
-    ) -> Result<eth::TxId, Error> {
+    ) -> Result<Mined, Error> {
         // Don't submit risky transactions if revert protection is
         // enabled and the settlement may revert in this mempool.
         if settlement.boundary.revertable()
             && matches!(self.revert_protection(), RevertProtection::Enabled)
             && mempool.may_revert()
         {
+            Metrics::outcome(&mempool.config().kind, "disabled");
             return Err(Error::Disabled);
         }
 
@@ -104,63 +144,445 @@ impl Mempools {
                 competition::solution::settlement::Internalization::Enable,
             )
         };
-        let hash = mempool.submit(tx.clone(), settlement.gas, solver).await?;
-        let mut block_stream = into_stream(self.ethereum.current_block().clone());
-        loop {
-            // Wait for the next block to be mined or we time out. Block stream immediately
-            // yields the latest block, thus the first iteration starts immediately.
-            if tokio::time::timeout_at(mempool.config().deadline(), block_stream.next())
-                .await
-                .is_err()
-            {
-                tracing::info!(?hash, "tx not confirmed in time, cancelling");
-                self.cancel(mempool, settlement.gas.price, solver).await?;
-                return Err(Error::Expired);
-            }
-            tracing::debug!(?hash, "checking if tx is confirmed");
+        // All submissions of the settlement use the same nonce so that they
+        // replace each other.
+        let nonce = self
+            .ethereum
+            .nonce(solver.address())
+            .await
+            .map_err(anyhow::Error::from)?;
+        let submission = NativeSubmission {
+            mempool,
+            ethereum: &self.ethereum,
+            solver,
+            tx,
+            gas: settlement.gas,
+            nonce,
+        };
+        submit_until_confirmed(
+            &submission,
+            into_stream(self.ethereum.current_block().clone()),
+            settlement.gas.price,
+            mempool.config(),
+        )
+        .await
+    }
+}
+
+/// Executes the settlement in all mempools at the same time and returns the
+/// first successful execution.
+async fn race<T>(executions: Vec<BoxFuture<'_, Result<T, Error>>>) -> Result<T, Error> {
+    let (mined, _remaining_futures) = select_ok(executions).await?;
+    Ok(mined)
+}
+
+/// Executes the settlement in the private mempools first. The public mempools
+/// only get the settlement if it did not get included within
+/// `private_timeout` or the private executions failed. The private executions
+/// keep going after the timeout.
+async fn sequential<'a, T: Send + 'a>(
+    private: Vec<BoxFuture<'a, Result<T, Error>>>,
+    public: Vec<BoxFuture<'a, Result<T, Error>>>,
+    private_timeout: Duration,
+) -> Result<T, Error> {
+    if private.is_empty() || public.is_empty() {
+        return race(private.into_iter().chain(public).collect()).await;
+    }
+
+    let mut private = select_ok(private).map_ok(|(mined, _)| mined).boxed();
+    match tokio::time::timeout(private_timeout, &mut private).await {
+        Ok(Ok(mined)) => Ok(mined),
+        // The settlement got included or would revert, so there is nothing to
+        // fall back to.
+        Ok(Err(err @ (Error::Revert(_) | Error::SimulationRevert { .. }))) => Err(err),
+        Ok(Err(err)) => {
+            tracing::info!(
+                ?err,
+                "private mempools failed, falling back to public mempools"
+            );
+            race(public).await
+        }
+        Err(_) => {
+            tracing::info!(
+                ?private_timeout,
+                "settlement not included by private mempools in time, falling back to public \
+                 mempools"
+            );
+            race(public.into_iter().chain([private]).collect()).await
+        }
+    }
+}
 
-            let receipt = self
-                .ethereum
-                .transaction_status(&hash)
+/// Submits a transaction and waits for it to get mined. Whenever the network
+/// gas price rises too far above the gas price of the pending transaction, the
+/// transaction gets replaced by one with a bumped gas price. Returns whichever
+/// of the submitted transactions got mined once the configured number of
+/// confirmation blocks were mined on top of it.
+async fn submit_until_confirmed(
+    submission: &impl Submission,
+    mut blocks: impl Stream + Unpin,
+    gas_price: eth::GasPrice,
+    config: &infra::mempool::Config,
+) -> Result<Mined, Error> {
+    let deadline = config.deadline();
+    let initial_gas_price = gas_price;
+    let mut gas_price = gas_price;
+    let mut hashes = vec![submission.submit(gas_price).await?];
+    let mut gas_prices = vec![gas_price];
+    // The index of the mined transaction, the number of blocks until its
+    // inclusion and the number of blocks mined on top of it.
+    let mut mined: Option<(usize, u64, u64)> = None;
+    let mut blocks_seen: u64 = 0;
+    loop {
+        // Wait for the next block to be mined or we time out. Block stream immediately
+        // yields the latest block, thus the first iteration starts immediately. A
+        // mined transaction can't be cancelled anymore, so the deadline only
+        // applies while it is pending.
+        if mined.is_some() {
+            blocks.next().await;
+        } else if tokio::time::timeout_at(deadline, blocks.next())
+            .await
+            .is_err()
+        {
+            tracing::info!(?hashes, "tx not confirmed in time, cancelling");
+            return abandon(
+                submission,
+                &mut blocks,
+                &hashes,
+                gas_price,
+                config,
+                "expired",
+                |cancellation| Error::Expired {
+                    tx_id: last_submitted(&hashes),
+                    cancellation,
+                },
+            )
+            .await;
+        }
+        blocks_seen += 1;
+
+        // Any of the submitted transactions may have been mined.
+        let mut executed = None;
+        for (i, hash) in hashes.iter().enumerate() {
+            tracing::debug!(?hash, "checking if tx is confirmed");
+            let status = submission
+                .transaction_status(hash.clone())
                 .await
                 .unwrap_or_else(|err| {
                     tracing::warn!(?hash, ?err, "failed to get transaction status",);
                     TxStatus::Pending
                 });
-            match receipt {
-                TxStatus::Executed => return Ok(hash),
-                TxStatus::Reverted => return Err(Error::Revert(hash)),
-                TxStatus::Pending => {
-                    // Check if transaction still simulates
-                    if let Err(err) = self.ethereum.estimate_gas(tx.clone()).await {
-                        if err.is_revert() {
-                            tracing::info!(
-                                ?hash,
-                                ?err,
-                                "tx started failing in mempool, cancelling"
-                            );
-                            self.cancel(mempool, settlement.gas.price, solver).await?;
-                            return Err(Error::SimulationRevert);
-                        } else {
-                            tracing::warn!(?hash, ?err, "couldn't re-simulate tx");
-                        }
-                    }
+            match status {
+                TxStatus::Executed => {
+                    executed = Some(i);
+                    break;
+                }
+                TxStatus::Reverted => {
+                    Metrics::outcome(&config.kind, "reverted");
+                    return Err(Error::Revert(hash.clone()));
+                }
+                TxStatus::Pending => {}
+            }
+        }
+        mined = match (executed, mined) {
+            (Some(i), Some((mined, inclusion, depth))) if i == mined => {
+                Some((mined, inclusion, depth + 1))
+            }
+            // The stream immediately yields the block the transaction was
+            // submitted in, so that block doesn't count.
+            (Some(i), _) => Some((i, blocks_seen - 1, 0)),
+            (None, Some((mined, _, depth))) => {
+                tracing::warn!(hash = ?hashes[mined], depth, "mined tx got reorged out");
+                None
+            }
+            (None, None) => None,
+        };
+        if let Some((i, inclusion, depth)) = mined {
+            if depth >= config.confirmation_blocks {
+                Metrics::mined(config, inclusion, initial_gas_price, gas_prices[i]);
+                return Ok(Mined {
+                    tx_id: hashes[i].clone(),
+                    submitted: Some(hashes[0].clone()),
+                });
+            }
+            tracing::debug!(hash = ?hashes[i], depth, "waiting for tx confirmations");
+            continue;
+        }
+
+        // Check if transaction still simulates
+        if let Err(err) = submission.estimate_gas().await {
+            if err.is_revert() {
+                tracing::info!(?hashes, ?err, "tx started failing in mempool, cancelling");
+                return abandon(
+                    submission,
+                    &mut blocks,
+                    &hashes,
+                    gas_price,
+                    config,
+                    "reverted",
+                    |cancellation| Error::SimulationRevert {
+                        tx_id: Some(last_submitted(&hashes)),
+                        cancellation,
+                    },
+                )
+                .await;
+            } else {
+                tracing::warn!(?hashes, ?err, "couldn't re-simulate tx");
+            }
+        }
+
+        let bumped = match submission.gas_price().await {
+            Ok(network_gas_price) => escalate(gas_price, network_gas_price, config.gas_price_cap),
+            Err(err) => {
+                tracing::warn!(?err, "failed to get network gas price");
+                None
+            }
+        };
+        if let Some(bumped) = bumped {
+            match submission.submit(bumped).await {
+                Ok(hash) => {
+                    tracing::info!(?hash, ?bumped, "replaced tx with a higher gas price");
+                    hashes.push(hash);
+                    gas_prices.push(bumped);
+                    gas_price = bumped;
+                }
+                Err(err) => tracing::warn!(?err, "failed to replace tx with a higher gas price"),
+            }
+        }
+    }
+}
+
+/// A settlement transaction that got mined.
+#[derive(Debug, Clone)]
+pub struct Mined {
+    /// The mined transaction.
+    pub tx_id: eth::TxId,
+    /// The transaction that was broadcast first for the settlement. It differs
+    /// from the mined one if that replaced it at the same nonce, e.g. with a
+    /// higher gas price. `None` if the mempool doesn't report its submissions.
+    pub submitted: Option<eth::TxId>,
+}
+
+/// Returns the most recently submitted of the settlement transactions.
+fn last_submitted(hashes: &[eth::TxId]) -> eth::TxId {
+    hashes
+        .last()
+        .expect("settlement was submitted at least once")
+        .clone()
+}
+
+/// Cancels the pending `settlements` and returns the `error` built from the
+/// last cancellation transaction that was sent, if any. A settlement that got
+/// mined before it could be cancelled is reported like any other mined one.
+async fn abandon(
+    submission: &impl Submission,
+    blocks: &mut (impl Stream + Unpin),
+    settlements: &[eth::TxId],
+    pending: eth::GasPrice,
+    config: &infra::mempool::Config,
+    outcome: &str,
+    error: impl FnOnce(Option<eth::TxId>) -> Error,
+) -> Result<Mined, Error> {
+    let cancellation =
+        match cancel_until_confirmed(submission, blocks, settlements, pending, config).await {
+            Ok((hash, cancellation)) => {
+                cancellation.log(settlements);
+                if let Cancellation::SettlementMined {
+                    settlement,
+                    reverted,
+                } = cancellation
+                {
+                    return if reverted {
+                        Metrics::outcome(&config.kind, "reverted");
+                        Err(Error::Revert(settlement))
+                    } else {
+                        Metrics::outcome(&config.kind, "mined");
+                        Ok(Mined {
+                            tx_id: settlement,
+                            submitted: settlements.first().cloned(),
+                        })
+                    };
                 }
+                Some(hash)
+            }
+            Err(err) => {
+                tracing::warn!(?err, ?settlements, "failed to cancel tx");
+                None
             }
+        };
+    Metrics::outcome(&config.kind, outcome);
+    Err(error(cancellation))
+}
+
+/// Cancels the pending `settlements` by sending a transaction to self at the
+/// same nonce with a slightly higher gas price. The cancellation gets replaced
+/// with a higher gas price every block until the nonce is used, the configured
+/// number of cancellation attempts is reached or the gas price cap would be
+/// exceeded. Returns the last cancellation transaction that was sent.
+async fn cancel_until_confirmed(
+    submission: &impl Submission,
+    blocks: &mut (impl Stream + Unpin),
+    settlements: &[eth::TxId],
+    pending: eth::GasPrice,
+    config: &infra::mempool::Config,
+) -> Result<(eth::TxId, Cancellation), Error> {
+    let mut gas_price = pending * GAS_PRICE_BUMP;
+    let mut hash = submission.cancel(gas_price).await?;
+    tracing::debug!(?hash, "submitted cancellation");
+    let mut attempts = 1;
+    loop {
+        blocks.next().await;
+
+        let nonce_used = submission.nonce_used().await.unwrap_or_else(|err| {
+            tracing::warn!(?err, "failed to check if nonce is used");
+            false
+        });
+        if nonce_used {
+            // Check the settlements last in case they got mined in the
+            // meantime.
+            for settlement in settlements {
+                if let Ok(status @ (TxStatus::Executed | TxStatus::Reverted)) =
+                    submission.transaction_status(settlement.clone()).await
+                {
+                    return Ok((
+                        hash,
+                        Cancellation::SettlementMined {
+                            settlement: settlement.clone(),
+                            reverted: matches!(status, TxStatus::Reverted),
+                        },
+                    ));
+                }
+            }
+            Metrics::outcome(&config.kind, "cancelled");
+            return Ok((hash, Cancellation::Cancelled));
+        }
+
+        if attempts >= config.max_cancellation_attempts {
+            return Ok((hash, Cancellation::GaveUp));
+        }
+        let bumped = gas_price * GAS_PRICE_BUMP;
+        if eth::U256::from(bumped.max()) > config.gas_price_cap {
+            return Ok((hash, Cancellation::GaveUp));
+        }
+        attempts += 1;
+        match submission.cancel(bumped).await {
+            Ok(replacement) => {
+                tracing::debug!(
+                    hash = ?replacement,
+                    ?bumped,
+                    "replaced cancellation with a higher gas price"
+                );
+                hash = replacement;
+                gas_price = bumped;
+            }
+            Err(err) => tracing::warn!(?err, "failed to replace cancellation"),
         }
     }
+}
 
-    /// Cancel a pending settlement by sending a transaction to self with a
-    /// slightly higher gas price than the existing one.
-    async fn cancel(
-        &self,
-        mempool: &infra::mempool::Inner,
-        pending: eth::GasPrice,
-        solver: &Solver,
-    ) -> Result<(), Error> {
+/// How the cancellation of pending settlement transactions ended.
+#[derive(Debug)]
+enum Cancellation {
+    /// The nonce of the settlements got used by another transaction.
+    Cancelled,
+    /// One of the settlements got mined before it could be cancelled, either
+    /// successfully or reverting.
+    SettlementMined {
+        settlement: eth::TxId,
+        reverted: bool,
+    },
+    /// The cancellation did not get mined within the configured number of
+    /// attempts or the gas price cap.
+    GaveUp,
+}
+
+impl Cancellation {
+    fn log(&self, settlements: &[eth::TxId]) {
+        match self {
+            Self::Cancelled => tracing::info!(?settlements, "cancelled tx"),
+            Self::SettlementMined {
+                settlement,
+                reverted,
+            } => {
+                tracing::warn!(
+                    ?settlement,
+                    reverted,
+                    "tx got mined although it was cancelled"
+                )
+            }
+            Self::GaveUp => {
+                tracing::warn!(
+                    ?settlements,
+                    "gave up cancelling tx, it may still get mined"
+                )
+            }
+        }
+    }
+}
+
+/// Returns the gas price to replace a pending transaction with if the network
+/// gas price exceeds the one of the pending transaction by more than
+/// [`GAS_PRICE_BUMP`]. Returns `None` if the transaction doesn't need to be
+/// replaced or the replacement would exceed the gas price cap.
+fn escalate(
+    pending: eth::GasPrice,
+    network: eth::GasPrice,
+    cap: eth::U256,
+) -> Option<eth::GasPrice> {
+    let bumped = pending * GAS_PRICE_BUMP;
+    if eth::U256::from(network.max()) <= eth::U256::from(bumped.max()) {
+        return None;
+    }
+    if eth::U256::from(bumped.max()) > cap {
+        tracing::warn!(?bumped, ?cap, "not replacing tx, gas price cap reached");
+        return None;
+    }
+    Some(bumped)
+}
+
+/// The interactions of the submission loop with the mempool and the chain.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+trait Submission: Send + Sync {
+    /// Submits the transaction with the given gas price.
+    async fn submit(&self, gas_price: eth::GasPrice) -> Result<eth::TxId, Error>;
+    /// Submits a transaction with the given gas price that replaces the pending
+    /// transaction without any effect.
+    async fn cancel(&self, gas_price: eth::GasPrice) -> Result<eth::TxId, Error>;
+    /// Whether a transaction with the nonce of the submission got mined.
+    async fn nonce_used(&self) -> Result<bool, blockchain::Error>;
+    async fn transaction_status(&self, hash: eth::TxId) -> Result<TxStatus, blockchain::Error>;
+    async fn estimate_gas(&self) -> Result<eth::Gas, blockchain::Error>;
+    async fn gas_price(&self) -> Result<eth::GasPrice, blockchain::Error>;
+}
+
+/// Submission of a settlement transaction to a native mempool.
+struct NativeSubmission<'a> {
+    mempool: &'a infra::mempool::Inner,
+    ethereum: &'a Ethereum,
+    solver: &'a Solver,
+    tx: eth::Tx,
+    gas: competition::solution::settlement::Gas,
+    nonce: eth::U256,
+}
+
+#[async_trait::async_trait]
+impl Submission for NativeSubmission<'_> {
+    async fn submit(&self, gas_price: eth::GasPrice) -> Result<eth::TxId, Error> {
+        let gas = competition::solution::settlement::Gas {
+            price: gas_price,
+            ..self.gas
+        };
+        self.mempool
+            .submit(self.tx.clone(), gas, self.solver, self.nonce)
+            .await
+    }
+
+    /// Cancel a pending settlement by sending a transaction to self.
+    async fn cancel(&self, gas_price: eth::GasPrice) -> Result<eth::TxId, Error> {
         let cancellation = eth::Tx {
-            from: solver.address(),
-            to: solver.address(),
+            from: self.solver.address(),
+            to: self.solver.address(),
             value: 0.into(),
             input: Default::default(),
             access_list: Default::default(),
@@ -168,10 +590,79 @@ impl Mempools {
         let gas = competition::solution::settlement::Gas {
             estimate: CANCELLATION_GAS_AMOUNT.into(),
             limit: CANCELLATION_GAS_AMOUNT.into(),
-            price: pending * GAS_PRICE_BUMP,
+            price: gas_price,
         };
-        mempool.submit(cancellation, gas, solver).await?;
-        Ok(())
+        self.mempool
+            .submit(cancellation, gas, self.solver, self.nonce)
+            .await
+    }
+
+    async fn nonce_used(&self) -> Result<bool, blockchain::Error> {
+        Ok(self.ethereum.nonce(self.solver.address()).await? > self.nonce)
+    }
+
+    async fn transaction_status(&self, hash: eth::TxId) -> Result<TxStatus, blockchain::Error> {
+        self.ethereum.transaction_status(&hash).await
+    }
+
+    async fn estimate_gas(&self) -> Result<eth::Gas, blockchain::Error> {
+        self.ethereum.estimate_gas(self.tx.clone()).await
+    }
+
+    async fn gas_price(&self) -> Result<eth::GasPrice, blockchain::Error> {
+        self.ethereum.gas_price().await
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "mempools")]
+struct Metrics {
+    /// Outcomes of settlement submissions and cancellations by mempool.
+    #[metric(labels("mempool", "outcome"))]
+    outcomes: prometheus::IntCounterVec,
+
+    /// Number of blocks between the submission and the inclusion of a
+    /// settlement.
+    #[metric(labels("mempool"), buckets(0, 1, 2, 3, 5, 10, 20))]
+    blocks_until_inclusion: prometheus::HistogramVec,
+
+    /// Gas price of the mined settlement transaction relative to the gas price
+    /// it was initially submitted with.
+    #[metric(labels("mempool"), buckets(1., 1.125, 1.25, 1.5, 2., 3.))]
+    gas_price_increase: prometheus::HistogramVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(::observe::metrics::get_storage_registry()).unwrap()
+    }
+
+    fn outcome(kind: &infra::mempool::Kind, outcome: &str) {
+        Self::get()
+            .outcomes
+            .with_label_values(&[kind.format_variant(), outcome])
+            .inc();
+    }
+
+    fn mined(
+        config: &infra::mempool::Config,
+        blocks: u64,
+        initial_gas_price: eth::GasPrice,
+        gas_price: eth::GasPrice,
+    ) {
+        let kind = config.kind.format_variant();
+        Self::outcome(&config.kind, "mined");
+        Self::get()
+            .blocks_until_inclusion
+            .with_label_values(&[kind])
+            .observe(blocks as f64);
+        Self::get()
+            .gas_price_increase
+            .with_label_values(&[kind])
+            .observe(
+                eth::U256::from(gas_price.max()).to_f64_lossy()
+                    / eth::U256::from(initial_gas_price.max()).to_f64_lossy(),
+            );
     }
 }
 
@@ -179,6 +670,29 @@ impl Mempools {
 #[error("no mempools configured, cannot execute settlements")]
 pub struct NoMempools;
 
+/// How a settlement gets distributed over the mempools.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Strategy {
+    /// Execute the settlement in all mempools at the same time.
+    #[default]
+    Race,
+    /// Execute the settlement in the private mempools first and only fall back
+    /// to the public mempools if it did not get included within
+    /// `private_timeout`. This avoids leaking the settlement to the public
+    /// mempool when the private mempools would have included it.
+    Sequential { private_timeout: Duration },
+}
+
+impl Strategy {
+    /// for instrumentization purposes
+    pub fn format_variant(&self) -> &'static str {
+        match self {
+            Strategy::Race => "Race",
+            Strategy::Sequential { .. } => "Sequential",
+        }
+    }
+}
+
 /// Defines if the mempools are configured in a way that guarantees that
 /// /settle'd solution will not revert.
 #[derive(Debug, Clone, Copy)]
@@ -191,12 +705,474 @@ pub enum RevertProtection {
 pub enum Error {
     #[error("Mined reverted transaction: {0:?}")]
     Revert(eth::TxId),
-    #[error("Simulation started reverting during submission")]
-    SimulationRevert,
-    #[error("Settlement did not get included in time")]
-    Expired,
+    #[error("Simulation started reverting during submission: {tx_id:?}")]
+    SimulationRevert {
+        /// The most recently submitted settlement transaction. `None` if the
+        /// submission logic does not report it.
+        tx_id: Option<eth::TxId>,
+        /// The last transaction sent to cancel the settlement, if any.
+        cancellation: Option<eth::TxId>,
+    },
+    #[error("Settlement did not get included in time: {tx_id:?}")]
+    Expired {
+        /// The most recently submitted settlement transaction.
+        tx_id: eth::TxId,
+        /// The last transaction sent to cancel the settlement, if any.
+        cancellation: Option<eth::TxId>,
+    },
     #[error("Strategy disabled for this tx")]
     Disabled,
     #[error("Failed to submit: {0:?}")]
     Other(#[from] anyhow::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+            Mutex,
+        },
+    };
+
+    fn gas_price(max: u64) -> eth::GasPrice {
+        eth::GasPrice::new(
+            eth::U256::from(max).into(),
+            eth::U256::one().into(),
+            eth::U256::from(max / 2).into(),
+        )
+    }
+
+    fn tx(n: u64) -> eth::TxId {
+        eth::TxId(eth::H256::from_low_u64_be(n))
+    }
+
+    fn config() -> infra::mempool::Config {
+        infra::mempool::Config {
+            additional_tip_percentage: Default::default(),
+            gas_price_cap: 1000.into(),
+            target_confirm_time: Default::default(),
+            max_confirm_time: Duration::from_secs(60),
+            retry_interval: Default::default(),
+            confirmation_blocks: 0,
+            max_cancellation_attempts: 3,
+            kind: infra::mempool::Kind::Public(infra::mempool::RevertProtection::Disabled),
+            submission: infra::mempool::SubmissionLogic::Native,
+        }
+    }
+
+    /// Mocks a submission whose second transaction gets mined in the third
+    /// block while the network gas price stays at `network_gas_price`.
+    /// Returns the max fees of all submitted transactions.
+    fn submission(network_gas_price: u64) -> (MockSubmission, Arc<Mutex<Vec<eth::U256>>>) {
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let mut submission = MockSubmission::new();
+        submission.expect_submit().returning({
+            let submitted = submitted.clone();
+            move |price| {
+                let mut submitted = submitted.lock().unwrap();
+                submitted.push(price.max().into());
+                Ok(tx(submitted.len() as u64))
+            }
+        });
+        let mut checks = 0;
+        submission
+            .expect_transaction_status()
+            .returning(move |hash| {
+                if hash.0 == tx(2).0 {
+                    checks += 1;
+                    if checks == 2 {
+                        return Ok(TxStatus::Executed);
+                    }
+                }
+                Ok(TxStatus::Pending)
+            });
+        submission
+            .expect_estimate_gas()
+            .returning(|| Ok(eth::U256::from(100_000).into()));
+        submission
+            .expect_gas_price()
+            .returning(move || Ok(gas_price(network_gas_price)));
+        submission.expect_cancel().never();
+        (submission, submitted)
+    }
+
+    #[tokio::test]
+    async fn replaces_underpriced_tx_until_mined() {
+        let (submission, submitted) = submission(200);
+
+        let mined = submit_until_confirmed(
+            &submission,
+            futures::stream::repeat(()),
+            gas_price(100),
+            &config(),
+        )
+        .await
+        .unwrap();
+
+        // The first replacement got mined although another one was submitted
+        // afterwards.
+        assert_eq!(mined.tx_id.0, tx(2).0);
+        assert_eq!(mined.submitted.unwrap().0, tx(1).0);
+        assert_eq!(
+            *submitted.lock().unwrap(),
+            [100, 113, 128].map(eth::U256::from)
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_replace_tx_above_gas_price_cap() {
+        let (submission, submitted) = submission(200);
+
+        let mined = submit_until_confirmed(
+            &submission,
+            futures::stream::repeat(()),
+            gas_price(100),
+            &infra::mempool::Config {
+                gas_price_cap: 120.into(),
+                ..config()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(mined.tx_id.0, tx(2).0);
+        assert_eq!(*submitted.lock().unwrap(), [100, 113].map(eth::U256::from));
+    }
+
+    #[tokio::test]
+    async fn records_submission_metrics() {
+        let (submission, _) = submission(200);
+        // No other test submits to MEVBlocker, so only this test changes its
+        // metrics.
+        let config = infra::mempool::Config {
+            kind: infra::mempool::Kind::MEVBlocker {
+                url: "https://rpc.mevblocker.io".parse().unwrap(),
+                max_additional_tip: 0.into(),
+                use_soft_cancellations: false,
+            },
+            ..config()
+        };
+        let metrics = Metrics::get();
+        let mined = metrics.outcomes.with_label_values(&["MEVBlocker", "mined"]);
+        let blocks = metrics
+            .blocks_until_inclusion
+            .with_label_values(&["MEVBlocker"]);
+        let gas_price_increase = metrics
+            .gas_price_increase
+            .with_label_values(&["MEVBlocker"]);
+
+        submit_until_confirmed(
+            &submission,
+            futures::stream::repeat(()),
+            gas_price(100),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(mined.get(), 1);
+        // The second transaction got mined two blocks after the submission.
+        assert_eq!(blocks.get_sample_count(), 1);
+        assert_eq!(blocks.get_sample_sum(), 2.);
+        assert_eq!(gas_price_increase.get_sample_sum(), 1.13);
+    }
+
+    #[tokio::test]
+    async fn waits_for_confirmations_of_mined_tx() {
+        let mut submission = MockSubmission::new();
+        submission.expect_submit().times(1).returning(|_| Ok(tx(1)));
+        // The transaction gets reorged out right after it was mined.
+        let mut statuses = vec![
+            TxStatus::Executed,
+            TxStatus::Pending,
+            TxStatus::Executed,
+            TxStatus::Executed,
+            TxStatus::Executed,
+        ]
+        .into_iter();
+        submission
+            .expect_transaction_status()
+            .times(5)
+            .returning(move |_| Ok(statuses.next().unwrap()));
+        // Only the pending transaction gets re-simulated and possibly replaced.
+        submission
+            .expect_estimate_gas()
+            .times(1)
+            .returning(|| Ok(eth::U256::from(100_000).into()));
+        submission
+            .expect_gas_price()
+            .times(1)
+            .returning(|| Ok(gas_price(100)));
+        submission.expect_cancel().never();
+
+        let mined = submit_until_confirmed(
+            &submission,
+            futures::stream::repeat(()),
+            gas_price(100),
+            &infra::mempool::Config {
+                confirmation_blocks: 2,
+                ..config()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(mined.tx_id.0, tx(1).0);
+        assert_eq!(mined.submitted.unwrap().0, tx(1).0);
+    }
+
+    /// Mocks a cancellation whose nonce gets used in the given block. Returns
+    /// the max fees of all submitted cancellations.
+    fn cancellation(nonce_used_in_block: usize) -> (MockSubmission, Arc<Mutex<Vec<eth::U256>>>) {
+        let cancellations = Arc::new(Mutex::new(Vec::new()));
+        let mut submission = MockSubmission::new();
+        submission.expect_cancel().returning({
+            let cancellations = cancellations.clone();
+            move |price| {
+                let mut cancellations = cancellations.lock().unwrap();
+                cancellations.push(price.max().into());
+                Ok(tx(100 + cancellations.len() as u64))
+            }
+        });
+        let mut block = 0;
+        submission.expect_nonce_used().returning(move || {
+            block += 1;
+            Ok(block >= nonce_used_in_block)
+        });
+        (submission, cancellations)
+    }
+
+    #[tokio::test]
+    async fn replaces_stuck_cancellation() {
+        let (mut submission, cancellations) = cancellation(3);
+        submission
+            .expect_transaction_status()
+            .returning(|_| Ok(TxStatus::Pending));
+        let cancelled = Metrics::get()
+            .outcomes
+            .with_label_values(&["PublicMempool", "cancelled"]);
+        let before = cancelled.get();
+
+        let (hash, cancellation) = cancel_until_confirmed(
+            &submission,
+            &mut futures::stream::repeat(()),
+            &[tx(1)],
+            gas_price(100),
+            &config(),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(cancellation, Cancellation::Cancelled));
+        assert_eq!(hash.0, tx(103).0);
+        assert_eq!(
+            *cancellations.lock().unwrap(),
+            [113, 128, 144].map(eth::U256::from)
+        );
+        assert_eq!(cancelled.get(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_cancelling_after_max_attempts() {
+        let (submission, cancellations) = cancellation(usize::MAX);
+
+        let (hash, cancellation) = cancel_until_confirmed(
+            &submission,
+            &mut futures::stream::repeat(()),
+            &[tx(1)],
+            gas_price(100),
+            &infra::mempool::Config {
+                max_cancellation_attempts: 2,
+                ..config()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(cancellation, Cancellation::GaveUp));
+        assert_eq!(hash.0, tx(102).0);
+        assert_eq!(
+            *cancellations.lock().unwrap(),
+            [113, 128].map(eth::U256::from)
+        );
+    }
+
+    #[tokio::test]
+    async fn settlement_mined_before_cancellation() {
+        let (mut submission, cancellations) = cancellation(1);
+        submission.expect_transaction_status().returning(|hash| {
+            Ok(if hash.0 == tx(2).0 {
+                TxStatus::Executed
+            } else {
+                TxStatus::Pending
+            })
+        });
+
+        let (hash, cancellation) = cancel_until_confirmed(
+            &submission,
+            &mut futures::stream::repeat(()),
+            &[tx(1), tx(2)],
+            gas_price(100),
+            &config(),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            cancellation,
+            Cancellation::SettlementMined {
+                settlement,
+                reverted: false,
+            } if settlement.0 == tx(2).0
+        ));
+        assert_eq!(hash.0, tx(101).0);
+        assert_eq!(*cancellations.lock().unwrap(), [113].map(eth::U256::from));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expired_error_contains_transactions() {
+        let mut submission = MockSubmission::new();
+        submission.expect_submit().times(1).returning(|_| Ok(tx(1)));
+        submission
+            .expect_transaction_status()
+            .returning(|_| Ok(TxStatus::Pending));
+        submission
+            .expect_estimate_gas()
+            .returning(|| Ok(eth::U256::from(100_000).into()));
+        submission
+            .expect_gas_price()
+            .returning(|| Ok(gas_price(100)));
+        submission
+            .expect_cancel()
+            .times(1)
+            .returning(|_| Ok(tx(100)));
+        submission.expect_nonce_used().returning(|| Ok(true));
+        // A block every 12 seconds, so the settlement expires while pending.
+        let blocks = futures::stream::unfold((), |()| async {
+            tokio::time::sleep(Duration::from_secs(12)).await;
+            Some(((), ()))
+        });
+
+        let result =
+            submit_until_confirmed(&submission, Box::pin(blocks), gas_price(100), &config()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Expired {
+                tx_id,
+                cancellation: Some(cancellation),
+            }) if tx_id.0 == tx(1).0 && cancellation.0 == tx(100).0
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn settlement_mined_during_cancellation_is_reported_as_mined() {
+        let mut submission = MockSubmission::new();
+        submission.expect_submit().times(1).returning(|_| Ok(tx(1)));
+        let cancelled = Arc::new(Mutex::new(false));
+        submission.expect_transaction_status().returning({
+            let cancelled = cancelled.clone();
+            move |_| {
+                Ok(if *cancelled.lock().unwrap() {
+                    TxStatus::Executed
+                } else {
+                    TxStatus::Pending
+                })
+            }
+        });
+        submission
+            .expect_estimate_gas()
+            .returning(|| Ok(eth::U256::from(100_000).into()));
+        submission
+            .expect_gas_price()
+            .returning(|| Ok(gas_price(100)));
+        submission.expect_cancel().times(1).returning(move |_| {
+            *cancelled.lock().unwrap() = true;
+            Ok(tx(100))
+        });
+        submission.expect_nonce_used().returning(|| Ok(true));
+        // A block every 12 seconds, so the settlement expires while pending.
+        let blocks = futures::stream::unfold((), |()| async {
+            tokio::time::sleep(Duration::from_secs(12)).await;
+            Some(((), ()))
+        });
+
+        let mined =
+            submit_until_confirmed(&submission, Box::pin(blocks), gas_price(100), &config())
+                .await
+                .unwrap();
+
+        assert_eq!(mined.tx_id.0, tx(1).0);
+        assert_eq!(mined.submitted.unwrap().0, tx(1).0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sequential_falls_back_to_public_mempools_after_timeout() {
+        let start = tokio::time::Instant::now();
+        let public_started = Arc::new(Mutex::new(None));
+        let private: Vec<BoxFuture<_>> = vec![futures::future::pending().boxed()];
+        let public: Vec<BoxFuture<_>> = vec![{
+            let public_started = public_started.clone();
+            async move {
+                *public_started.lock().unwrap() = Some(start.elapsed());
+                Ok(tx(2))
+            }
+            .boxed()
+        }];
+
+        let hash = sequential(private, public, Duration::from_secs(12))
+            .await
+            .unwrap();
+
+        assert_eq!(hash.0, tx(2).0);
+        assert!(public_started.lock().unwrap().unwrap() >= Duration::from_secs(12));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sequential_skips_public_mempools_after_private_inclusion() {
+        let public_started = Arc::new(AtomicBool::new(false));
+        let private: Vec<BoxFuture<_>> = vec![async {
+            tokio::time::sleep(Duration::from_secs(6)).await;
+            Ok(tx(1))
+        }
+        .boxed()];
+        let public: Vec<BoxFuture<_>> = vec![{
+            let public_started = public_started.clone();
+            async move {
+                public_started.store(true, Ordering::SeqCst);
+                Ok(tx(2))
+            }
+            .boxed()
+        }];
+
+        let hash = sequential(private, public, Duration::from_secs(12))
+            .await
+            .unwrap();
+
+        assert_eq!(hash.0, tx(1).0);
+        assert!(!public_started.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sequential_does_not_fall_back_after_private_revert() {
+        let private: Vec<BoxFuture<_>> = vec![async { Err(Error::Revert(tx(1))) }.boxed()];
+        let public: Vec<BoxFuture<_>> = vec![async { Ok(tx(2)) }.boxed()];
+
+        let result = sequential(private, public, Duration::from_secs(12)).await;
+
+        assert!(matches!(result, Err(Error::Revert(hash)) if hash.0 == tx(1).0));
+    }
+
+    #[test]
+    fn escalates_only_when_network_gas_price_is_higher_than_bump() {
+        let cap = eth::U256::from(1000);
+        assert!(escalate(gas_price(100), gas_price(100), cap).is_none());
+        assert!(escalate(gas_price(100), gas_price(113), cap).is_none());
+        let bumped = escalate(gas_price(100), gas_price(114), cap).unwrap();
+        assert_eq!(eth::U256::from(bumped.max()), 113.into());
+        assert_eq!(eth::U256::from(bumped.tip()), 2.into());
+    }
+}
@@ -12,6 +12,7 @@ impl Settled {
                 uninternalized: settled.uninternalized_calldata.into(),
             },
             tx_hash: settled.tx_hash.0,
+            submitted_tx_hash: settled.submitted_tx_hash.map(|tx_hash| tx_hash.0),
         }
     }
 }
@@ -22,6 +23,8 @@ impl Settled {
 pub struct Settled {
     calldata: CalldataInner,
     tx_hash: primitive_types::H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submitted_tx_hash: Option<primitive_types::H256>,
 }
 
 #[serde_as]
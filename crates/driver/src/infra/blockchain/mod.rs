@@ -191,6 +191,15 @@ impl Ethereum {
             .map_err(Into::into)
     }
 
+    /// Returns the nonce of the next transaction the account gets mined.
+    pub async fn nonce(&self, address: eth::Address) -> Result<eth::U256, Error> {
+        self.web3
+            .eth()
+            .transaction_count(address.into(), None)
+            .await
+            .map_err(Into::into)
+    }
+
     /// Returns a [`token::Erc20`] for the specified address.
     pub fn erc20(&self, address: eth::TokenAddress) -> token::Erc20 {
         token::Erc20::new(self, address)
@@ -1,6 +1,6 @@
 use {
     crate::{
-        domain::eth,
+        domain::{eth, mempools},
         infra::{self, blockchain, config::file, liquidity, mempool, simulator, solver},
     },
     futures::future::join_all,
@@ -259,6 +259,8 @@ pub async fn load(network: &blockchain::Network, path: &Path) -> infra::Config {
                 target_confirm_time: config.submission.target_confirm_time,
                 max_confirm_time: config.submission.max_confirm_time,
                 retry_interval: config.submission.retry_interval,
+                confirmation_blocks: config.submission.confirmation_blocks,
+                max_cancellation_attempts: config.submission.max_cancellation_attempts,
                 kind: match mempool {
                     file::Mempool::Public => {
                         // If there is no private mempool, revert protection is
@@ -293,6 +295,12 @@ pub async fn load(network: &blockchain::Network, path: &Path) -> infra::Config {
                 },
             })
             .collect(),
+        mempool_strategy: match config.submission.strategy {
+            file::SubmissionStrategy::Race => mempools::Strategy::Race,
+            file::SubmissionStrategy::Sequential { private_timeout } => {
+                mempools::Strategy::Sequential { private_timeout }
+            }
+        },
         simulator: match (config.tenderly, config.enso) {
             (Some(config), None) => {
                 Some(simulator::Config::Tenderly(simulator::tenderly::Config {
@@ -83,15 +83,47 @@ struct SubmissionConfig {
     #[serde(with = "humantime_serde", default = "default_max_confirm_time")]
     max_confirm_time: Duration,
 
+    /// The number of blocks that need to be mined on top of a settlement
+    /// transaction before it is considered executed. Protects against
+    /// reporting settlements that get reorged out.
+    #[serde(default)]
+    confirmation_blocks: u64,
+
+    /// How often a cancellation of a settlement transaction gets replaced
+    /// with a higher gas price before giving up.
+    #[serde(default = "default_max_cancellation_attempts")]
+    max_cancellation_attempts: usize,
+
     /// The mempools to submit settlement transactions to. Can be the public
     /// mempool of a node or the private MEVBlocker mempool.
     #[serde(rename = "mempool", default)]
     mempools: Vec<Mempool>,
 
+    /// How settlements get distributed over the mempools.
+    #[serde(default)]
+    strategy: SubmissionStrategy,
+
     #[serde(default)]
     logic: Logic,
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+enum SubmissionStrategy {
+    /// Submit settlements to all mempools at the same time (default).
+    #[default]
+    Race,
+    /// Submit settlements to the private mempools first and only fall back to
+    /// the public mempools if they did not get included in time.
+    #[serde(rename_all = "kebab-case")]
+    Sequential {
+        /// How long to wait for the private mempools to include a settlement
+        /// before also submitting it to the public mempools.
+        #[serde(with = "humantime_serde")]
+        private_timeout: Duration,
+    },
+}
+
 #[serde_as]
 #[derive(Debug, Deserialize)]
 #[serde(tag = "mempool")]
@@ -138,6 +170,10 @@ fn default_max_confirm_time() -> Duration {
     Duration::from_secs(120)
 }
 
+fn default_max_cancellation_attempts() -> usize {
+    3
+}
+
 /// 3 gwei
 fn default_max_additional_tip() -> eth::U256 {
     eth::U256::from(3) * eth::U256::exp10(9)
@@ -1,5 +1,5 @@
 use crate::{
-    domain::eth,
+    domain::{eth, mempools},
     infra::{blockchain, liquidity, mempool, simulator, solver},
 };
 
@@ -14,5 +14,6 @@ pub struct Config {
     pub liquidity: liquidity::Config,
     pub simulator: Option<simulator::Config>,
     pub mempools: Vec<mempool::Config>,
+    pub mempool_strategy: mempools::Strategy,
     pub contracts: blockchain::contracts::Addresses,
 }
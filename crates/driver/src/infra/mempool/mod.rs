@@ -33,6 +33,15 @@ impl Mempool {
             Self::Native(mempool) => &mempool.config,
         }
     }
+
+    /// Whether the mempool keeps transactions private instead of broadcasting
+    /// them to the public network.
+    pub fn is_private(&self) -> bool {
+        match &self.config().kind {
+            Kind::Public(_) => false,
+            Kind::MEVBlocker { .. } => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,15 +65,20 @@ impl Inner {
         Self { config, transport }
     }
 
+    /// Submits the transaction at the given nonce. Submitting another
+    /// transaction at the same nonce replaces the pending one if its fees are
+    /// sufficiently higher.
     pub async fn submit(
         &self,
         tx: eth::Tx,
         gas: competition::solution::settlement::Gas,
         solver: &infra::Solver,
+        nonce: eth::U256,
     ) -> Result<eth::TxId, mempools::Error> {
         ethcontract::transaction::TransactionBuilder::new(self.transport.clone())
             .from(solver.account().clone())
             .to(tx.to.into())
+            .nonce(nonce)
             .gas_price(ethcontract::GasPrice::Eip1559 {
                 max_fee_per_gas: gas.price.max().into(),
                 max_priority_fee_per_gas: gas.price.tip().into(),
@@ -8,7 +8,10 @@ mod notification;
 pub use notification::{Kind, Notification, ScoreKind, Settlement, SimulationSucceededAtLeastOnce};
 use {
     super::simulator,
-    crate::domain::{competition::score, eth, mempools::Error},
+    crate::domain::{
+        competition::score,
+        mempools::{Error, Mined},
+    },
 };
 
 pub fn solver_timeout(solver: &Solver, auction_id: Option<auction::Id>) {
@@ -106,17 +109,30 @@ pub fn executed(
     solver: &Solver,
     auction_id: auction::Id,
     solution_id: Option<solution::Id>,
-    res: &Result<eth::TxId, Error>,
+    res: &Result<Mined, Error>,
 ) {
     if solution_id.is_none() {
         return;
     };
 
     let kind = match res {
-        Ok(hash) => notification::Settlement::Success(hash.clone()),
+        Ok(mined) => notification::Settlement::Success(mined.tx_id.clone()),
         Err(Error::Revert(hash)) => notification::Settlement::Revert(hash.clone()),
-        Err(Error::SimulationRevert) => notification::Settlement::SimulationRevert,
-        Err(Error::Other(_) | Error::Expired | Error::Disabled) => notification::Settlement::Fail,
+        Err(Error::SimulationRevert {
+            tx_id,
+            cancellation,
+        }) => notification::Settlement::SimulationRevert {
+            transaction: tx_id.clone(),
+            cancellation: cancellation.clone(),
+        },
+        Err(Error::Expired {
+            tx_id,
+            cancellation,
+        }) => notification::Settlement::Expired {
+            transaction: tx_id.clone(),
+            cancellation: cancellation.clone(),
+        },
+        Err(Error::Other(_) | Error::Disabled) => notification::Settlement::Fail,
     };
 
     solver.notify(
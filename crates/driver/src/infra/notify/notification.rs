@@ -77,8 +77,18 @@ pub enum Settlement {
     Success(TransactionHash),
     /// Winning solver mined reverted transaction.
     Revert(TransactionHash),
-    /// Transaction started reverting during the submission.
-    SimulationRevert,
+    /// Transaction started reverting during the submission. Contains the last
+    /// submitted transaction, if known, and the transaction sent to cancel it.
+    SimulationRevert {
+        transaction: Option<TransactionHash>,
+        cancellation: Option<TransactionHash>,
+    },
+    /// Transaction did not get included in time. Contains the last submitted
+    /// transaction and the transaction sent to cancel it.
+    Expired {
+        transaction: TransactionHash,
+        cancellation: Option<TransactionHash>,
+    },
     /// Winning solver failed to settle the transaction onchain.
     Fail,
 }
@@ -17,7 +17,7 @@ pub struct Metrics {
     #[metric(labels("solver", "result"))]
     pub quotes: prometheus::IntCounterVec,
     /// The results of the mempool submission.
-    #[metric(labels("mempool", "result"))]
+    #[metric(labels("mempool", "strategy", "result"))]
     pub mempool_submission: prometheus::IntCounterVec,
 }
 
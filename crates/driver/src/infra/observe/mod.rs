@@ -313,14 +313,16 @@ pub fn solver_response(endpoint: &Url, res: Result<&str, &http::Error>) {
 /// Observe the result of mempool transaction execution.
 pub fn mempool_executed(
     mempool: &Mempool,
+    strategy: mempools::Strategy,
     settlement: &Settlement,
-    res: &Result<eth::TxId, mempools::Error>,
+    res: &Result<mempools::Mined, mempools::Error>,
 ) {
     match res {
-        Ok(txid) => {
+        Ok(mined) => {
             tracing::info!(
-                ?txid,
+                ?mined,
                 %mempool,
+                ?strategy,
                 ?settlement,
                 "sending transaction via mempool succeeded",
             );
@@ -329,6 +331,7 @@ pub fn mempool_executed(
             tracing::warn!(
                 ?err,
                 %mempool,
+                ?strategy,
                 ?settlement,
                 "sending transaction via mempool failed",
             );
@@ -336,14 +339,14 @@ pub fn mempool_executed(
     }
     let result = match res {
         Ok(_) => "Success",
-        Err(mempools::Error::Revert(_) | mempools::Error::SimulationRevert) => "Revert",
-        Err(mempools::Error::Expired) => "Expired",
+        Err(mempools::Error::Revert(_) | mempools::Error::SimulationRevert { .. }) => "Revert",
+        Err(mempools::Error::Expired { .. }) => "Expired",
         Err(mempools::Error::Other(_)) => "Other",
         Err(mempools::Error::Disabled) => "Disabled",
     };
     metrics::get()
         .mempool_submission
-        .with_label_values(&[&mempool.to_string(), result])
+        .with_label_values(&[&mempool.to_string(), strategy.format_variant(), result])
         .inc();
 }
 
@@ -75,8 +75,24 @@ impl Notification {
                     notify::Settlement::Revert(hash) => Kind::Revert {
                         transaction: hash.0,
                     },
-                    notify::Settlement::SimulationRevert => Kind::Cancelled,
-                    notify::Settlement::Fail => Kind::Fail,
+                    notify::Settlement::SimulationRevert {
+                        transaction,
+                        cancellation,
+                    } => Kind::Cancelled {
+                        transaction: transaction.map(|hash| hash.0),
+                        cancellation: cancellation.map(|hash| hash.0),
+                    },
+                    notify::Settlement::Expired {
+                        transaction,
+                        cancellation,
+                    } => Kind::Fail {
+                        transaction: Some(transaction.0),
+                        cancellation: cancellation.map(|hash| hash.0),
+                    },
+                    notify::Settlement::Fail => Kind::Fail {
+                        transaction: None,
+                        cancellation: None,
+                    },
                 },
                 notify::Kind::PostprocessingTimedOut => Kind::PostprocessingTimedOut,
             },
@@ -140,8 +156,22 @@ pub enum Kind {
     DriverError {
         reason: String,
     },
-    Cancelled,
-    Fail,
+    Cancelled {
+        /// The last submitted settlement transaction, if known.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        transaction: Option<eth::H256>,
+        /// The transaction sent to cancel the settlement, if any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cancellation: Option<eth::H256>,
+    },
+    Fail {
+        /// The last submitted settlement transaction, if any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        transaction: Option<eth::H256>,
+        /// The transaction sent to cancel the settlement, if any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cancellation: Option<eth::H256>,
+    },
     PostprocessingTimedOut,
 }
 
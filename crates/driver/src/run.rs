@@ -76,6 +76,7 @@ async fn run_with(args: cli::Args, addr_sender: Option<oneshot::Sender<SocketAdd
                 })
                 .collect(),
             eth.clone(),
+            config.mempool_strategy,
         )
         .unwrap(),
         eth,
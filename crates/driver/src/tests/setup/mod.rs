@@ -1101,7 +1101,7 @@ impl<'a> Settle<'a> {
         assert_eq!(self.status, hyper::StatusCode::OK);
         let result: serde_json::Value = serde_json::from_str(&self.body).unwrap();
         assert!(result.is_object());
-        assert_eq!(result.as_object().unwrap().len(), 2);
+        assert_eq!(result.as_object().unwrap().len(), 3);
         assert!(!result
             .get("calldata")
             .unwrap()
@@ -1144,6 +1144,11 @@ impl<'a> Settle<'a> {
         let tx_auction_id = u64::from_be_bytes((&input[len - 8..]).try_into().unwrap());
         assert_eq!(tx_auction_id.to_string(), "1");
         assert_eq!(reported_tx_hash, tx.hash);
+        // The settlement got mined without being replaced.
+        let submitted_tx_hash =
+            serde_json::from_value::<eth::H256>(result.get("submittedTxHash").unwrap().clone())
+                .unwrap();
+        assert_eq!(submitted_tx_hash, tx.hash);
 
         // Ensure that the internalized calldata returned by the driver is equal to the
         // calldata published to the blockchain.
@@ -208,6 +208,8 @@ impl Solver {
                     target_confirm_time: Default::default(),
                     max_confirm_time: Default::default(),
                     retry_interval: Default::default(),
+                    confirmation_blocks: Default::default(),
+                    max_cancellation_attempts: Default::default(),
                     kind: infra::mempool::Kind::Public(infra::mempool::RevertProtection::Disabled),
                     submission: infra::mempool::SubmissionLogic::Native,
                 }],
@@ -5,6 +5,7 @@ use {
     model::{
         order::{
             CancellationPayload,
+            CancellationSignature,
             OrderCancellation,
             OrderCancellations,
             OrderCreation,
@@ -14,7 +15,7 @@ use {
             SignedOrderCancellations,
         },
         quote::{OrderQuoteRequest, OrderQuoteSide, SellAmount},
-        signature::{EcdsaSignature, EcdsaSigningScheme},
+        signature::EcdsaSigningScheme,
     },
     number::nonzero::U256 as NonZeroU256,
     secp256k1::SecretKey,
@@ -123,8 +124,8 @@ async fn order_cancellation(web3: Web3) {
             let cancellation = client
                 .delete(&format!("{API_HOST}{ORDERS_ENDPOINT}/{order_uid}"))
                 .json(&CancellationPayload {
+                    valid_to: cancellation.valid_to,
                     signature: cancellation.signature,
-                    signing_scheme: cancellation.signing_scheme,
                 })
                 .send()
                 .await
@@ -136,9 +137,11 @@ async fn order_cancellation(web3: Web3) {
 
     let cancel_orders = |order_uids: Vec<OrderUid>| {
         let client = services.client();
-        let cancellations = OrderCancellations { order_uids };
-        let signing_scheme = EcdsaSigningScheme::Eip712;
-        let signature = EcdsaSignature::sign(
+        let cancellations = OrderCancellations {
+            order_uids,
+            valid_to: None,
+        };
+        let signature = CancellationSignature::sign(
             EcdsaSigningScheme::Eip712,
             &onchain.contracts().domain_separator,
             &cancellations.hash_struct(),
@@ -148,7 +151,6 @@ async fn order_cancellation(web3: Web3) {
         let signed_cancellations = SignedOrderCancellations {
             data: cancellations,
             signature,
-            signing_scheme,
         };
 
         async move {
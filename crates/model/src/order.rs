@@ -4,14 +4,14 @@
 use {
     crate::{
         app_data::AppDataHash,
-        bytes_hex::BytesHex,
+        bytes_hex::{self, BytesHex},
         interaction::InteractionData,
         quote::QuoteId,
-        signature::{self, EcdsaSignature, EcdsaSigningScheme, Signature},
+        signature::{self, EcdsaSignature, EcdsaSigningScheme, Signature, SigningScheme},
         DomainSeparator,
         TokenPair,
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, bail, Context as _, Result},
     chrono::{offset::Utc, DateTime},
     derivative::Derivative,
     hex_literal::hex,
@@ -23,6 +23,7 @@ use {
     std::{
         collections::HashSet,
         fmt::{self, Debug, Display, Formatter},
+        net::IpAddr,
         str::FromStr,
     },
     strum::{AsRefStr, EnumString, EnumVariantNames},
@@ -386,6 +387,11 @@ pub struct OrderCreation {
     #[serde(flatten)]
     pub signature: Signature,
     pub quote_id: Option<QuoteId>,
+    /// Whether a fresh quote may be used if the quote specified by
+    /// `quote_id` expired or doesn't match the order. Otherwise such orders
+    /// get rejected.
+    #[serde(default)]
+    pub allow_requote: bool,
     #[serde(flatten)]
     pub app_data: OrderCreationAppData,
 }
@@ -539,6 +545,9 @@ pub enum VerificationError {
 #[serde(rename_all = "camelCase")]
 pub struct OrderCancellations {
     pub order_uids: Vec<OrderUid>,
+    /// Unix timestamp after which the cancellations can no longer be applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<u32>,
 }
 
 impl OrderCancellations {
@@ -546,6 +555,11 @@ impl OrderCancellations {
     /// `keccak256("OrderCancellations(bytes[] orderUid)")`.
     const TYPE_HASH: [u8; 32] =
         hex!("4c89efb91ae246f78d2fe68b47db2fa1444a121a4f2dc3fda7a5a408c2e3588e");
+    /// The EIP-712 type hash for order cancellations with an expiry. Computed
+    /// with: `keccak256("OrderCancellations(bytes[] orderUids,uint32
+    /// validTo)")`.
+    const TYPE_HASH_WITH_VALID_TO: [u8; 32] =
+        hex!("f27b81723adbab8f66bc6a8dbfd5a6c22f1b5a018efee810501524e032992d84");
 
     pub fn hash_struct(&self) -> [u8; 32] {
         let mut encoded_uids = Vec::with_capacity(32 * self.order_uids.len());
@@ -555,10 +569,214 @@ impl OrderCancellations {
 
         let array_hash = signing::keccak256(&encoded_uids);
 
-        let mut hash_data = [0u8; 64];
-        hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
-        hash_data[32..64].copy_from_slice(&array_hash);
-        signing::keccak256(&hash_data)
+        match self.valid_to {
+            None => {
+                let mut hash_data = [0u8; 64];
+                hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
+                hash_data[32..64].copy_from_slice(&array_hash);
+                signing::keccak256(&hash_data)
+            }
+            Some(valid_to) => {
+                let mut hash_data = [0u8; 96];
+                hash_data[0..32].copy_from_slice(&Self::TYPE_HASH_WITH_VALID_TO);
+                hash_data[32..64].copy_from_slice(&array_hash);
+                hash_data[92..96].copy_from_slice(&valid_to.to_be_bytes());
+                signing::keccak256(&hash_data)
+            }
+        }
+    }
+}
+
+/// Signature authorizing order cancellations.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(
+    into = "JsonCancellationSignature",
+    try_from = "JsonCancellationSignature"
+)]
+pub enum CancellationSignature {
+    /// ECDSA signature from which the signer gets recovered.
+    Ecdsa {
+        signature: EcdsaSignature,
+        signing_scheme: EcdsaSigningScheme,
+    },
+    /// Signature of a smart contract wallet. The signer can't be recovered so
+    /// the claimed `owner` has to be provided and the signature gets verified
+    /// by calling its `isValidSignature` function as defined by EIP-1271.
+    Eip1271 { owner: H160, signature: Vec<u8> },
+}
+
+impl CancellationSignature {
+    pub fn sign(
+        signing_scheme: EcdsaSigningScheme,
+        domain_separator: &DomainSeparator,
+        struct_hash: &[u8; 32],
+        key: SecretKeyRef,
+    ) -> Self {
+        Self::Ecdsa {
+            signature: EcdsaSignature::sign(signing_scheme, domain_separator, struct_hash, key),
+            signing_scheme,
+        }
+    }
+
+    /// Recovers the signer of an ECDSA signature. EIP-1271 signatures can only
+    /// be verified on-chain and result in an error.
+    pub fn recover(
+        &self,
+        domain_separator: &DomainSeparator,
+        struct_hash: &[u8; 32],
+    ) -> Result<H160> {
+        match self {
+            Self::Ecdsa {
+                signature,
+                signing_scheme,
+            } => Ok(signature
+                .recover(*signing_scheme, domain_separator, struct_hash)?
+                .signer),
+            Self::Eip1271 { .. } => bail!("EIP-1271 signatures must be verified on-chain"),
+        }
+    }
+
+    pub fn scheme(&self) -> SigningScheme {
+        match self {
+            Self::Ecdsa { signing_scheme, .. } => (*signing_scheme).into(),
+            Self::Eip1271 { .. } => SigningScheme::Eip1271,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Ecdsa { signature, .. } => signature.to_bytes().to_vec(),
+            Self::Eip1271 { signature, .. } => signature.clone(),
+        }
+    }
+
+    /// Reconstructs a signature from its scheme and bytes. The `signer` is
+    /// only needed for EIP-1271 signatures where it is the owner.
+    pub fn from_bytes(scheme: SigningScheme, bytes: Vec<u8>, signer: H160) -> Result<Self> {
+        JsonCancellationSignature {
+            signing_scheme: scheme,
+            signature: bytes,
+            owner: Some(signer),
+        }
+        .try_into()
+    }
+}
+
+/// An internal type used for deriving `serde` implementations for the
+/// `CancellationSignature` type.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonCancellationSignature {
+    signing_scheme: SigningScheme,
+    #[serde(with = "bytes_hex")]
+    signature: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<H160>,
+}
+
+impl From<CancellationSignature> for JsonCancellationSignature {
+    fn from(signature: CancellationSignature) -> Self {
+        match signature {
+            CancellationSignature::Ecdsa {
+                signature,
+                signing_scheme,
+            } => Self {
+                signing_scheme: signing_scheme.into(),
+                signature: signature.to_bytes().to_vec(),
+                owner: None,
+            },
+            CancellationSignature::Eip1271 { owner, signature } => Self {
+                signing_scheme: SigningScheme::Eip1271,
+                signature,
+                owner: Some(owner),
+            },
+        }
+    }
+}
+
+impl TryFrom<JsonCancellationSignature> for CancellationSignature {
+    type Error = anyhow::Error;
+
+    fn try_from(json: JsonCancellationSignature) -> Result<Self, Self::Error> {
+        match json.signing_scheme {
+            SigningScheme::Eip712 | SigningScheme::EthSign => Ok(Self::Ecdsa {
+                signature: EcdsaSignature::from_bytes(
+                    json.signature
+                        .as_slice()
+                        .try_into()
+                        .context("ECDSA signature must be 65 bytes long")?,
+                ),
+                signing_scheme: json.signing_scheme.try_to_ecdsa_scheme().unwrap(),
+            }),
+            SigningScheme::Eip1271 => Ok(Self::Eip1271 {
+                owner: json
+                    .owner
+                    .context("EIP-1271 cancellations require an owner")?,
+                signature: json.signature,
+            }),
+            SigningScheme::PreSign => bail!("presign orders must be cancelled on-chain"),
+        }
+    }
+}
+
+/// Proof that an order got cancelled with an off-chain signature.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationProof {
+    #[serde(flatten)]
+    pub signature: CancellationSignature,
+    /// The address that signed the cancellation.
+    pub signer: H160,
+    /// When the order got cancelled.
+    pub timestamp: DateTime<Utc>,
+    /// The signed cancellation message. Missing for proofs that were stored
+    /// before the messages were kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<CancellationMessage>,
+    /// The EIP-712 domain separator the message was signed for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_separator: Option<H256>,
+}
+
+impl CancellationProof {
+    /// Recovers the signer of the signed message so it can be checked against
+    /// `signer`. EIP-1271 signatures can only be verified on-chain and result
+    /// in an error.
+    pub fn recover_signer(&self) -> Result<H160> {
+        let (message, domain_separator) = self
+            .message
+            .as_ref()
+            .zip(self.domain_separator)
+            .context("proof does not include the signed message")?;
+        self.signature
+            .recover(&DomainSeparator(domain_separator.0), &message.hash_struct())
+    }
+}
+
+/// Message that got signed to cancel orders off-chain.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CancellationMessage {
+    #[serde(rename_all = "camelCase")]
+    OrderCancellation {
+        order_uid: OrderUid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        valid_to: Option<u32>,
+    },
+    OrderCancellations(OrderCancellations),
+    CancelAll(CancelAll),
+}
+
+impl CancellationMessage {
+    pub fn hash_struct(&self) -> [u8; 32] {
+        match self {
+            Self::OrderCancellation {
+                order_uid,
+                valid_to,
+            } => OrderCancellation::struct_hash(order_uid, *valid_to),
+            Self::OrderCancellations(cancellations) => cancellations.hash_struct(),
+            Self::CancelAll(cancel_all) => cancel_all.hash_struct(),
+        }
     }
 }
 
@@ -568,11 +786,72 @@ impl OrderCancellations {
 pub struct SignedOrderCancellations {
     #[serde(flatten)]
     pub data: OrderCancellations,
+    #[serde(flatten)]
+    pub signature: CancellationSignature,
+}
+
+impl SignedOrderCancellations {
+    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
+        self.signature
+            .recover(domain_separator, &self.data.hash_struct())
+    }
+}
+
+/// Cancellation of all orders of an owner.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelAll {
+    pub owner: H160,
+    /// Unix timestamp in seconds. Only orders created at or before this time
+    /// get cancelled so replaying the message can't cancel orders placed
+    /// afterwards.
+    pub timestamp: u64,
+}
+
+impl CancelAll {
+    /// The EIP-712 type hash for cancelling all orders. Computed with:
+    /// `keccak256("CancelAll(address owner,uint256 timestamp)")`.
+    const TYPE_HASH: [u8; 32] =
+        hex!("ee8514a3c961d129877935cb5954401ed33d66d0d4763755f2411ec43643dfaf");
+
+    pub fn hash_struct(&self) -> [u8; 32] {
+        let mut hash_data = [0u8; 96];
+        hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
+        hash_data[44..64].copy_from_slice(self.owner.as_bytes());
+        U256::from(self.timestamp).to_big_endian(&mut hash_data[64..96]);
+        signing::keccak256(&hash_data)
+    }
+}
+
+/// Signed cancellation of all orders of an owner.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedCancelAll {
+    #[serde(flatten)]
+    pub data: CancelAll,
     pub signature: EcdsaSignature,
     pub signing_scheme: EcdsaSigningScheme,
 }
 
-impl SignedOrderCancellations {
+impl SignedCancelAll {
+    pub fn sign(
+        data: CancelAll,
+        signing_scheme: EcdsaSigningScheme,
+        domain_separator: &DomainSeparator,
+        key: SecretKeyRef,
+    ) -> Self {
+        Self {
+            data,
+            signature: EcdsaSignature::sign(
+                signing_scheme,
+                domain_separator,
+                &data.hash_struct(),
+                key,
+            ),
+            signing_scheme,
+        }
+    }
+
     pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
         Ok(self
             .signature
@@ -586,11 +865,14 @@ impl SignedOrderCancellations {
 }
 
 /// An order cancellation as provided to the orderbook by the frontend.
-#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+#[derive(Eq, PartialEq, Clone, Debug)]
 pub struct OrderCancellation {
     pub order_uid: OrderUid,
-    pub signature: EcdsaSignature,
-    pub signing_scheme: EcdsaSigningScheme,
+    /// Unix timestamp in seconds after which the cancellation can no longer
+    /// be applied. Cancellations without it are only accepted for backwards
+    /// compatibility.
+    pub valid_to: Option<u32>,
+    pub signature: CancellationSignature,
 }
 
 impl Default for OrderCancellation {
@@ -613,14 +895,105 @@ impl OrderCancellation {
     // keccak256("OrderCancellation(bytes orderUid)")
     const TYPE_HASH: [u8; 32] =
         hex!("7b41b3a6e2b3cae020a3b2f9cdc997e0d420643957e7fea81747e984e47c88ec");
+    // keccak256("OrderCancellation(bytes orderUid,uint32 validTo)")
+    const TYPE_HASH_WITH_VALID_TO: [u8; 32] =
+        hex!("4d5eb84bf308c2b33e5b127431bd8cce66c4316ee9a40ffa12308e6e084ff13e");
+
+    pub fn for_order(
+        order_uid: OrderUid,
+        domain_separator: &DomainSeparator,
+        key: SecretKeyRef,
+    ) -> Self {
+        Self::sign(order_uid, None, domain_separator, key)
+    }
+
+    /// Cancellation that can only be applied until `valid_to`.
+    pub fn for_order_valid_to(
+        order_uid: OrderUid,
+        valid_to: u32,
+        domain_separator: &DomainSeparator,
+        key: SecretKeyRef,
+    ) -> Self {
+        Self::sign(order_uid, Some(valid_to), domain_separator, key)
+    }
+
+    fn sign(
+        order_uid: OrderUid,
+        valid_to: Option<u32>,
+        domain_separator: &DomainSeparator,
+        key: SecretKeyRef,
+    ) -> Self {
+        let struct_hash = Self::struct_hash(&order_uid, valid_to);
+        Self {
+            order_uid,
+            valid_to,
+            signature: CancellationSignature::sign(
+                EcdsaSigningScheme::Eip712,
+                domain_separator,
+                &struct_hash,
+                key,
+            ),
+        }
+    }
+
+    pub fn hash_struct(&self) -> [u8; 32] {
+        Self::struct_hash(&self.order_uid, self.valid_to)
+    }
+
+    fn struct_hash(order_uid: &OrderUid, valid_to: Option<u32>) -> [u8; 32] {
+        let uid_hash = signing::keccak256(&order_uid.0);
+        match valid_to {
+            None => {
+                let mut hash_data = [0u8; 64];
+                hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
+                hash_data[32..64].copy_from_slice(&uid_hash);
+                signing::keccak256(&hash_data)
+            }
+            Some(valid_to) => {
+                let mut hash_data = [0u8; 96];
+                hash_data[0..32].copy_from_slice(&Self::TYPE_HASH_WITH_VALID_TO);
+                hash_data[32..64].copy_from_slice(&uid_hash);
+                hash_data[92..96].copy_from_slice(&valid_to.to_be_bytes());
+                signing::keccak256(&hash_data)
+            }
+        }
+    }
+
+    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
+        self.signature
+            .recover(domain_separator, &self.hash_struct())
+    }
+}
+
+/// Reduction of the amounts of a partially fillable order as provided to the
+/// orderbook by the order owner.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct OrderAmendment {
+    pub order_uid: OrderUid,
+    pub sell_amount: U256,
+    pub buy_amount: U256,
+    pub signature: EcdsaSignature,
+    pub signing_scheme: EcdsaSigningScheme,
+}
+
+// EIP-712
+impl OrderAmendment {
+    // keccak256("OrderAmendment(bytes orderUid,uint256 sellAmount,uint256
+    // buyAmount)")
+    const TYPE_HASH: [u8; 32] =
+        hex!("e63c7fd93ed19182f577ba7bba15b4d26b41e111661c1a892b0ba46b6e46ce5c");
 
     pub fn for_order(
         order_uid: OrderUid,
+        sell_amount: U256,
+        buy_amount: U256,
         domain_separator: &DomainSeparator,
         key: SecretKeyRef,
     ) -> Self {
         let mut result = Self {
             order_uid,
+            sell_amount,
+            buy_amount,
             signature: Default::default(),
             signing_scheme: EcdsaSigningScheme::Eip712,
         };
@@ -634,9 +1007,11 @@ impl OrderCancellation {
     }
 
     pub fn hash_struct(&self) -> [u8; 32] {
-        let mut hash_data = [0u8; 64];
+        let mut hash_data = [0u8; 128];
         hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
         hash_data[32..64].copy_from_slice(&signing::keccak256(&self.order_uid.0));
+        self.sell_amount.to_big_endian(&mut hash_data[64..96]);
+        self.buy_amount.to_big_endian(&mut hash_data[96..128]);
         signing::keccak256(&hash_data)
     }
 
@@ -652,8 +1027,10 @@ impl OrderCancellation {
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CancellationPayload {
-    pub signature: EcdsaSignature,
-    pub signing_scheme: EcdsaSigningScheme,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<u32>,
+    #[serde(flatten)]
+    pub signature: CancellationSignature,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
@@ -784,6 +1161,92 @@ pub struct OrderMetadata {
     /// Full app data that `OrderData::app_data` is a hash of. Can be None if
     /// the backend doesn't know about the full app data.
     pub full_app_data: Option<String>,
+    /// Reduced amounts of a partially fillable order. The signed order data
+    /// stays unchanged but only the remainder up to these amounts can get
+    /// executed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amended_amounts: Option<AmendedAmounts>,
+    /// The order this order replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<OrderUid>,
+    /// The order that replaced this order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<OrderUid>,
+    /// Whether the order was created with a quote that got verified by
+    /// simulating the settlement.
+    #[serde(default)]
+    pub quote_verified: bool,
+    /// Whether the order of a trusted liquidity provider got accepted without
+    /// checking that the owner can transfer the sell amount.
+    #[serde(default)]
+    pub balance_check_skipped: bool,
+    /// Hashes of the mined settlement transactions that executed the order
+    /// in the order of execution. Only gets populated when explicitly
+    /// requested since it requires looking up the order's trades.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_tx_hashes: Option<Vec<H256>>,
+    /// How the order got submitted. Unknown for orders created before the
+    /// source got recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<OrderSource>,
+    /// Whether the owner of the open fill-or-kill order repeatedly didn't
+    /// have enough sell token balance. Gets cleared once the balance is
+    /// sufficient again.
+    #[serde(default)]
+    pub insufficient_balance: bool,
+}
+
+/// How an order got submitted to the protocol.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+pub enum OrderSource {
+    /// The order got posted to the orderbook API.
+    Api {
+        /// Salted hash of the IP address the order got posted from. The
+        /// address itself never gets stored.
+        client_ip_hash: Option<H256>,
+        /// The `appCode` of the order's app data.
+        app_code: Option<String>,
+    },
+    /// The order got placed by an on-chain transaction (e.g. an ethflow
+    /// order).
+    Onchain { tx_hash: H256 },
+}
+
+impl OrderSource {
+    /// Hashes a client's IP address so orders posted from the same address
+    /// can be grouped without storing the address. The salt prevents
+    /// recovering addresses by hashing all of them.
+    pub fn hash_client_ip(salt: &[u8], ip: IpAddr) -> H256 {
+        let ip = match ip {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+            IpAddr::V6(ip) => ip.octets(),
+        };
+        H256(signing::keccak256(&[salt, ip.as_slice()].concat()))
+    }
+
+    /// Returns the source as a string label that can be used in metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Api { .. } => "api",
+            Self::Onchain { .. } => "onchain",
+        }
+    }
+}
+
+/// Amounts a partially fillable order got reduced to by its owner.
+#[serde_as]
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendedAmounts {
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub sell_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub buy_amount: U256,
 }
 
 // uid as 56 bytes: 32 for orderDigest, 20 for ownerAddress and 4 for validTo
@@ -1118,6 +1581,14 @@ mod tests {
                     "post": [],
             },
             "fullAppData": "123",
+            "quoteVerified": true,
+            "balanceCheckSkipped": true,
+            "source": {
+                "type": "api",
+                "clientIpHash": "0x000000000000000000000000000000000000000000000000000000000000000c",
+                "appCode": "CoW Swap",
+            },
+            "insufficientBalance": true,
         });
         let signing_scheme = EcdsaSigningScheme::Eip712;
         let expected = Order {
@@ -1138,6 +1609,13 @@ mod tests {
                 full_fee_amount: U256::MAX,
                 solver_fee: U256::MAX,
                 full_app_data: Some("123".to_string()),
+                quote_verified: true,
+                balance_check_skipped: true,
+                source: Some(OrderSource::Api {
+                    client_ip_hash: Some(H256::from_low_u64_be(12)),
+                    app_code: Some("CoW Swap".to_string()),
+                }),
+                insufficient_balance: true,
                 ..Default::default()
             },
             data: OrderData {
@@ -1216,6 +1694,7 @@ mod tests {
                 from,
                 signature,
                 quote_id: Some(42),
+                allow_requote: true,
             };
             let order_json = json!({
                 "sellToken": "0x1111111111111111111111111111111111111111",
@@ -1231,6 +1710,7 @@ mod tests {
                 "sellTokenBalance": "erc20",
                 "buyTokenBalance": "erc20",
                 "quoteId": 42,
+                "allowRequote": true,
                 "signingScheme": signing_scheme,
                 "signature": signature_bytes,
                 "from": from,
@@ -1390,14 +1870,42 @@ mod tests {
         ] {
             let cancellation = OrderCancellation {
                 order_uid: OrderUid(hex!("2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a")),
-                signature: EcdsaSignature::from_bytes(signature),
-                signing_scheme: *signing_scheme,
+                valid_to: None,
+                signature: CancellationSignature::Ecdsa {
+                    signature: EcdsaSignature::from_bytes(signature),
+                    signing_scheme: *signing_scheme,
+                },
             };
             let owner = cancellation.validate(&domain_separator).unwrap();
             assert_eq!(owner, expected_owner);
         }
     }
 
+    #[test]
+    fn cancellation_signature_bytes_roundtrip() {
+        let signer = H160([1; 20]);
+        for signature in [
+            CancellationSignature::Ecdsa {
+                signature: EcdsaSignature::from_bytes(&[2; 65]),
+                signing_scheme: EcdsaSigningScheme::EthSign,
+            },
+            CancellationSignature::Eip1271 {
+                owner: signer,
+                signature: vec![1, 2, 3],
+            },
+        ] {
+            let bytes = signature.to_bytes();
+            assert_eq!(
+                CancellationSignature::from_bytes(signature.scheme(), bytes, signer).unwrap(),
+                signature
+            );
+        }
+        assert!(
+            CancellationSignature::from_bytes(SigningScheme::Eip712, vec![1, 2, 3], signer)
+                .is_err()
+        );
+    }
+
     #[test]
     fn domain_separator_does_not_panic_in_debug() {
         println!("{:?}", DomainSeparator::default());
@@ -1484,8 +1992,194 @@ mod tests {
                 hex!("405f6cb53d87901a5385a824a99c94b43146547f5ea3623f8d2f50b925e97a8b"),
             ),
         ] {
-            let cancellations = OrderCancellations { order_uids };
+            let cancellations = OrderCancellations {
+                order_uids,
+                valid_to: None,
+            };
             assert_eq!(cancellations.hash_struct(), struct_hash);
         }
     }
+
+    #[test]
+    fn order_cancellations_sign_valid_to() {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner =
+            h160_from_public_key(PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk));
+        let domain_separator = DomainSeparator([2; 32]);
+
+        let data = OrderCancellations {
+            order_uids: vec![OrderUid([3; 56]), OrderUid([4; 56])],
+            valid_to: Some(1_700_000_000),
+        };
+        let cancellations = SignedOrderCancellations {
+            signature: CancellationSignature::sign(
+                EcdsaSigningScheme::Eip712,
+                &domain_separator,
+                &data.hash_struct(),
+                SecretKeyRef::from(&sk),
+            ),
+            data,
+        };
+        assert_eq!(cancellations.validate(&domain_separator).unwrap(), owner);
+
+        for valid_to in [Some(u32::MAX), None] {
+            let mut tampered = cancellations.clone();
+            tampered.data.valid_to = valid_to;
+            assert_ne!(tampered.validate(&domain_separator).ok(), Some(owner));
+        }
+    }
+
+    #[test]
+    fn cancellation_proof_recovers_signer() {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner =
+            h160_from_public_key(PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk));
+        let domain_separator = DomainSeparator([2; 32]);
+
+        let cancellation = OrderCancellation::for_order_valid_to(
+            OrderUid([3; 56]),
+            1_700_000_000,
+            &domain_separator,
+            SecretKeyRef::from(&sk),
+        );
+        let proof = CancellationProof {
+            signature: cancellation.signature,
+            signer: owner,
+            timestamp: Utc::now(),
+            message: Some(CancellationMessage::OrderCancellation {
+                order_uid: cancellation.order_uid,
+                valid_to: cancellation.valid_to,
+            }),
+            domain_separator: Some(H256(domain_separator.0)),
+        };
+        assert_eq!(proof.recover_signer().unwrap(), owner);
+
+        // The message survives a serialization roundtrip.
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["message"]["type"], "orderCancellation");
+        assert_eq!(
+            serde_json::from_value::<CancellationProof>(json).unwrap(),
+            proof
+        );
+
+        let legacy = CancellationProof {
+            message: None,
+            ..proof
+        };
+        assert!(legacy.recover_signer().is_err());
+    }
+
+    #[test]
+    fn order_cancellation_signs_valid_to() {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner =
+            h160_from_public_key(PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk));
+        let domain_separator = DomainSeparator([2; 32]);
+
+        let cancellation = OrderCancellation::for_order_valid_to(
+            OrderUid([3; 56]),
+            1_700_000_000,
+            &domain_separator,
+            SecretKeyRef::from(&sk),
+        );
+        assert_eq!(cancellation.validate(&domain_separator).unwrap(), owner);
+
+        // The signature commits to the validity so it can't be extended or
+        // dropped.
+        for valid_to in [Some(u32::MAX), None] {
+            let tampered = OrderCancellation {
+                valid_to,
+                ..cancellation.clone()
+            };
+            assert_ne!(tampered.validate(&domain_separator).ok(), Some(owner));
+        }
+    }
+
+    #[test]
+    fn cancel_all_signature_recovery() {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner =
+            h160_from_public_key(PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk));
+        let domain_separator = DomainSeparator([2; 32]);
+        let data = CancelAll {
+            owner,
+            timestamp: 1_700_000_000,
+        };
+
+        for signing_scheme in [EcdsaSigningScheme::Eip712, EcdsaSigningScheme::EthSign] {
+            let cancellation = SignedCancelAll::sign(
+                data,
+                signing_scheme,
+                &domain_separator,
+                SecretKeyRef::from(&sk),
+            );
+            assert_eq!(cancellation.validate(&domain_separator).unwrap(), owner);
+
+            // The signature commits to the timestamp.
+            let tampered = SignedCancelAll {
+                data: CancelAll {
+                    timestamp: 0,
+                    ..data
+                },
+                ..cancellation
+            };
+            assert_ne!(tampered.validate(&domain_separator).ok(), Some(owner));
+        }
+    }
+
+    #[test]
+    fn order_amendment_signature_recovery() {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner =
+            h160_from_public_key(PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk));
+        let domain_separator = DomainSeparator([2; 32]);
+        let amendment = OrderAmendment::for_order(
+            OrderUid([3; 56]),
+            10.into(),
+            20.into(),
+            &domain_separator,
+            SecretKeyRef::from(&sk),
+        );
+        assert_eq!(amendment.validate(&domain_separator).unwrap(), owner);
+
+        // The signature commits to the amounts.
+        let tampered = OrderAmendment {
+            sell_amount: 11.into(),
+            ..amendment
+        };
+        assert_ne!(tampered.validate(&domain_separator).ok(), Some(owner));
+    }
+
+    #[test]
+    fn onchain_order_source_serialization() {
+        let source = OrderSource::Onchain {
+            tx_hash: H256([1; 32]),
+        };
+        let value = json!({
+            "type": "onchain",
+            "txHash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+        });
+        assert_eq!(serde_json::to_value(&source).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<OrderSource>(value).unwrap(),
+            source
+        );
+    }
+
+    #[test]
+    fn client_ip_hash_depends_on_salt() {
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let hash = OrderSource::hash_client_ip(b"salt", ip);
+        assert_eq!(hash, OrderSource::hash_client_ip(b"salt", ip));
+        assert_ne!(hash, OrderSource::hash_client_ip(b"other", ip));
+        assert_ne!(
+            hash,
+            OrderSource::hash_client_ip(b"salt", "192.0.2.2".parse().unwrap())
+        );
+        // IPv4 addresses hash like their IPv6 mapped form.
+        assert_eq!(
+            hash,
+            OrderSource::hash_client_ip(b"salt", "::ffff:192.0.2.1".parse().unwrap())
+        );
+    }
 }
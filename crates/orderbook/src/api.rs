@@ -1,10 +1,14 @@
 use {
     crate::{app_data, database::Postgres, orderbook::Orderbook, quoter::QuoteHandler},
+    model::order::OrderSource,
     shared::{
         api::{box_filter, error, finalize_router, ApiReply},
         price_estimation::native::NativePriceEstimating,
     },
-    std::sync::Arc,
+    std::{
+        net::{IpAddr, SocketAddr},
+        sync::Arc,
+    },
     warp::{Filter, Rejection, Reply},
 };
 
@@ -105,3 +109,64 @@ pub fn handle_all_routes(
 
     finalize_router(routes, "orderbook::api::request_summary")
 }
+
+/// Addresses a request got sent from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct ClientAddress {
+    /// First address of the `X-Forwarded-For` header. Only meaningful if the
+    /// peer is a trusted reverse proxy.
+    forwarded_for: Option<IpAddr>,
+    /// Address of the peer the request was received from.
+    peer: Option<IpAddr>,
+}
+
+/// Extracts the addresses a request got sent from.
+fn client_address() -> impl Filter<Extract = (ClientAddress,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-forwarded-for")
+        .and(warp::addr::remote())
+        .map(
+            |forwarded_for: Option<String>, remote: Option<SocketAddr>| ClientAddress {
+                forwarded_for: forwarded_for
+                    .and_then(|header| header.split(',').next()?.trim().parse().ok()),
+                peer: remote.map(|remote| remote.ip()),
+            },
+        )
+}
+
+/// Source of an order posted from the address. The app code gets filled in
+/// by the orderbook once the order's full app data is known.
+fn api_order_source(orderbook: &Orderbook, address: ClientAddress) -> OrderSource {
+    let client_ip = orderbook.client_ip(address.forwarded_for, address.peer);
+    OrderSource::Api {
+        client_ip_hash: client_ip.and_then(|ip| orderbook.client_ip_hash(ip)),
+        app_code: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn client_address_extracts_forwarded_for_and_peer() {
+        let address = warp::test::request()
+            .header("x-forwarded-for", "192.0.2.1, 198.51.100.1")
+            .remote_addr("203.0.113.1:80".parse().unwrap())
+            .filter(&client_address())
+            .await
+            .unwrap();
+        assert_eq!(
+            address,
+            ClientAddress {
+                forwarded_for: Some("192.0.2.1".parse().unwrap()),
+                peer: Some("203.0.113.1".parse().unwrap()),
+            }
+        );
+
+        let address = warp::test::request()
+            .filter(&client_address())
+            .await
+            .unwrap();
+        assert_eq!(address, ClientAddress::default());
+    }
+}
@@ -14,47 +14,80 @@ pub fn cancel_order_request(
         .and(extract_payload())
         .map(|uid, payload: CancellationPayload| OrderCancellation {
             order_uid: uid,
+            valid_to: payload.valid_to,
             signature: payload.signature,
-            signing_scheme: payload.signing_scheme,
         })
 }
 
+/// Status code, error type and description of the API error for
+/// cancellation errors that aren't internal errors.
+fn api_error(err: &OrderCancellationError) -> Option<(StatusCode, &'static str, String)> {
+    let (status, error_type, description) = match err {
+        OrderCancellationError::InvalidSignature => (
+            StatusCode::BAD_REQUEST,
+            "InvalidSignature",
+            "Malformed signature",
+        ),
+        OrderCancellationError::InvalidEip1271Signature => (
+            StatusCode::BAD_REQUEST,
+            "InvalidEip1271Signature",
+            "On-chain signature verification failed",
+        ),
+        OrderCancellationError::AlreadyCancelled => (
+            StatusCode::BAD_REQUEST,
+            "AlreadyCancelled",
+            "Order is already cancelled",
+        ),
+        OrderCancellationError::OrderFullyExecuted => (
+            StatusCode::BAD_REQUEST,
+            "OrderFullyExecuted",
+            "Order is fully executed",
+        ),
+        OrderCancellationError::OrderExpired => {
+            (StatusCode::BAD_REQUEST, "OrderExpired", "Order is expired")
+        }
+        OrderCancellationError::OrderNotFound => (
+            StatusCode::NOT_FOUND,
+            "OrderNotFound",
+            "Order not located in database",
+        ),
+        OrderCancellationError::WrongOwner => (
+            StatusCode::UNAUTHORIZED,
+            "WrongOwner",
+            "Signature recovery's owner doesn't match order's",
+        ),
+        OrderCancellationError::OnChainOrder => (
+            StatusCode::BAD_REQUEST,
+            "OnChainOrder",
+            "On-chain orders must be cancelled on-chain",
+        ),
+        OrderCancellationError::CancellationExpired => (
+            StatusCode::BAD_REQUEST,
+            "CancellationExpired",
+            "Cancellation is expired",
+        ),
+        OrderCancellationError::TooManyOrders { .. } => {
+            return Some((StatusCode::BAD_REQUEST, "TooManyOrders", err.to_string()))
+        }
+        // Reported like the error of a single order but the description
+        // points out which order of the batch caused it.
+        OrderCancellationError::InvalidOrder { uid, reason } => {
+            let (status, error_type, description) = api_error(reason)?;
+            return Some((status, error_type, format!("{description}: {uid}")));
+        }
+        OrderCancellationError::Other(_) => return None,
+    };
+    Some((status, error_type, description.to_string()))
+}
+
 impl IntoWarpReply for OrderCancellationError {
     fn into_warp_reply(self) -> super::ApiReply {
-        match self {
-            Self::InvalidSignature => with_status(
-                super::error("InvalidSignature", "Malformed signature"),
-                StatusCode::BAD_REQUEST,
-            ),
-            Self::AlreadyCancelled => with_status(
-                super::error("AlreadyCancelled", "Order is already cancelled"),
-                StatusCode::BAD_REQUEST,
-            ),
-            Self::OrderFullyExecuted => with_status(
-                super::error("OrderFullyExecuted", "Order is fully executed"),
-                StatusCode::BAD_REQUEST,
-            ),
-            Self::OrderExpired => with_status(
-                super::error("OrderExpired", "Order is expired"),
-                StatusCode::BAD_REQUEST,
-            ),
-            Self::OrderNotFound => with_status(
-                super::error("OrderNotFound", "Order not located in database"),
-                StatusCode::NOT_FOUND,
-            ),
-            Self::WrongOwner => with_status(
-                super::error(
-                    "WrongOwner",
-                    "Signature recovery's owner doesn't match order's",
-                ),
-                StatusCode::UNAUTHORIZED,
-            ),
-            Self::OnChainOrder => with_status(
-                super::error("OnChainOrder", "On-chain orders must be cancelled on-chain"),
-                StatusCode::BAD_REQUEST,
-            ),
-            Self::Other(err) => {
-                tracing::error!(?err, "cancel_order");
+        match api_error(&self) {
+            Some((status, error_type, description)) => {
+                with_status(super::error(error_type, description), status)
+            }
+            None => {
+                tracing::error!(err = ?self, "cancel_order");
                 shared::api::internal_error_reply()
             }
         }
@@ -83,7 +116,11 @@ mod tests {
         super::*,
         ethcontract::H256,
         hex_literal::hex,
-        model::signature::{EcdsaSignature, EcdsaSigningScheme},
+        model::{
+            order::CancellationSignature,
+            signature::{EcdsaSignature, EcdsaSigningScheme},
+        },
+        primitive_types::H160,
         serde_json::json,
         warp::{test::request, Reply},
     };
@@ -100,32 +137,70 @@ mod tests {
             }))
             .unwrap(),
             CancellationPayload {
-                signature: EcdsaSignature {
-                    r: H256(hex!(
-                        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
-                    )),
-                    s: H256(hex!(
-                        "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"
-                    )),
-                    v: 27,
+                valid_to: None,
+                signature: CancellationSignature::Ecdsa {
+                    signature: EcdsaSignature {
+                        r: H256(hex!(
+                            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+                        )),
+                        s: H256(hex!(
+                            "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"
+                        )),
+                        v: 27,
+                    },
+                    signing_scheme: EcdsaSigningScheme::Eip712,
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn eip1271_cancellation_payload_deserialization() {
+        assert_eq!(
+            serde_json::from_value::<CancellationPayload>(json!({
+                "signature": "0x010203",
+                "signingScheme": "eip1271",
+                "owner": "0x4242424242424242424242424242424242424242",
+            }))
+            .unwrap(),
+            CancellationPayload {
+                valid_to: None,
+                signature: CancellationSignature::Eip1271 {
+                    owner: H160([0x42; 20]),
+                    signature: vec![1, 2, 3],
                 },
-                signing_scheme: EcdsaSigningScheme::Eip712,
             },
         );
+
+        // The owner can't be recovered from EIP-1271 signatures.
+        assert!(serde_json::from_value::<CancellationPayload>(json!({
+            "signature": "0x010203",
+            "signingScheme": "eip1271",
+        }))
+        .is_err());
+        // Pre-signed orders can only be cancelled on-chain.
+        assert!(serde_json::from_value::<CancellationPayload>(json!({
+            "signature": "0x",
+            "signingScheme": "presign",
+        }))
+        .is_err());
     }
 
     #[tokio::test]
     async fn cancel_order_request_ok() {
         let filter = cancel_order_request();
-        let cancellation = OrderCancellation::default();
+        let cancellation = OrderCancellation {
+            valid_to: Some(1_700_000_000),
+            ..Default::default()
+        };
 
         let request = request()
             .path(&format!("/v1/orders/{}", cancellation.order_uid))
             .method("DELETE")
             .header("content-type", "application/json")
             .json(&CancellationPayload {
-                signature: cancellation.signature,
-                signing_scheme: cancellation.signing_scheme,
+                valid_to: cancellation.valid_to,
+                signature: cancellation.signature.clone(),
             });
         let result = request.filter(&filter).await.unwrap();
         assert_eq!(result, cancellation);
@@ -155,6 +230,14 @@ mod tests {
             cancel_order_response(Err(OrderCancellationError::OrderExpired)).into_response();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
+        let response = cancel_order_response(Err(OrderCancellationError::InvalidEip1271Signature))
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response =
+            cancel_order_response(Err(OrderCancellationError::CancellationExpired)).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
         let response =
             cancel_order_response(Err(OrderCancellationError::WrongOwner)).into_response();
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
@@ -163,6 +246,20 @@ mod tests {
             cancel_order_response(Err(OrderCancellationError::OrderNotFound)).into_response();
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
+        let response = cancel_order_response(Err(OrderCancellationError::TooManyOrders {
+            count: 2,
+            max: 1,
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = cancel_order_response(Err(OrderCancellationError::InvalidOrder {
+            uid: Default::default(),
+            reason: Box::new(OrderCancellationError::WrongOwner),
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
         let response = cancel_order_response(Err(OrderCancellationError::Other(
             anyhow::Error::msg("test error"),
         )))
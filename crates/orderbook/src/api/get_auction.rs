@@ -1,5 +1,5 @@
 use {
-    crate::orderbook::Orderbook,
+    crate::orderbook::{AuctionRetrievalError, Orderbook},
     anyhow::Result,
     reqwest::StatusCode,
     shared::api::ApiReply,
@@ -24,6 +24,13 @@ pub fn get_auction(
                     super::error("NotFound", "There is no active auction"),
                     StatusCode::NOT_FOUND,
                 ),
+                Err(AuctionRetrievalError::Stale { .. }) => with_status(
+                    super::error(
+                        "AuctionStale",
+                        "The current auction is outdated because no new auctions are being created",
+                    ),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ),
                 Err(err) => {
                     tracing::error!(?err, "/api/v1/get_auction");
                     shared::api::internal_error_reply()
@@ -3,27 +3,40 @@ use {
     anyhow::Result,
     ethcontract::H256,
     reqwest::StatusCode,
-    shared::api::ApiReply,
     std::{convert::Infallible, sync::Arc},
-    warp::{reply::with_status, Filter, Rejection},
+    warp::{reply::with_status, Filter, Rejection, Reply},
 };
 
+/// Header containing the hash of the mined transaction if the requested
+/// transaction got replaced.
+const REMAPPED_TX_HASH_HEADER: &str = "X-Remapped-Tx-Hash";
+
 pub fn get_orders_by_tx_request() -> impl Filter<Extract = (H256,), Error = Rejection> + Clone {
     warp::path!("v1" / "transactions" / H256 / "orders").and(warp::get())
 }
 
 pub fn get_orders_by_tx(
     orderbook: Arc<Orderbook>,
-) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+) -> impl Filter<Extract = (Box<dyn Reply>,), Error = Rejection> + Clone {
     get_orders_by_tx_request().and_then(move |hash: H256| {
         let orderbook = orderbook.clone();
         async move {
             let result = orderbook.get_orders_for_tx(&hash).await;
             Result::<_, Infallible>::Ok(match result {
-                Ok(response) => with_status(warp::reply::json(&response), StatusCode::OK),
+                Ok(response) => {
+                    let reply = with_status(warp::reply::json(&response.orders), StatusCode::OK);
+                    match response.remapped_tx_hash {
+                        Some(mined) => Box::new(warp::reply::with_header(
+                            reply,
+                            REMAPPED_TX_HASH_HEADER,
+                            format!("{mined:?}"),
+                        )) as Box<dyn Reply>,
+                        None => Box::new(reply),
+                    }
+                }
                 Err(err) => {
                     tracing::error!(?err, "get_orders_by_tx");
-                    shared::api::internal_error_reply()
+                    Box::new(shared::api::internal_error_reply())
                 }
             })
         }
@@ -7,7 +7,7 @@ use {
         signature,
     },
     shared::{
-        api::{error, extract_payload, ApiReply, IntoWarpReply},
+        api::{error, extract_payload, rich_error, ApiReply, IntoWarpReply},
         order_validation::{
             AppDataValidationError,
             OrderValidToError,
@@ -131,6 +131,14 @@ impl IntoWarpReply for ValidationErrorWrapper {
                 ),
                 StatusCode::BAD_REQUEST,
             ),
+            ValidationError::QuoteExpired {
+                quote_id,
+                expired_at,
+            } => AddOrderError::QuoteExpired {
+                quote_id,
+                expired_at,
+            }
+            .into_warp_reply(),
             ValidationError::PriceForQuote(err) => err.into_warp_reply(),
             ValidationError::MissingFrom => with_status(
                 error(
@@ -233,10 +241,90 @@ impl IntoWarpReply for AddOrderError {
     fn into_warp_reply(self) -> ApiReply {
         match self {
             Self::OrderValidation(err) => ValidationErrorWrapper(err).into_warp_reply(),
-            Self::DuplicatedOrder => with_status(
-                error("DuplicatedOrder", "order already exists"),
+            Self::DuplicatedOrder {
+                uid,
+                status,
+                signature_matches,
+            } => with_status(
+                rich_error(
+                    "DuplicatedOrder",
+                    "order already exists",
+                    serde_json::json!({
+                        "uid": uid,
+                        "status": status,
+                        "signatureMatches": signature_matches,
+                    }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::TooManyOpenOrders { limit, current } => with_status(
+                rich_error(
+                    "TooManyOpenOrders",
+                    "too many open orders",
+                    serde_json::json!({
+                        "limit": limit,
+                        "current": current,
+                    }),
+                ),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            Self::ExcessiveHookGas { requested, max } => with_status(
+                rich_error(
+                    "ExcessiveHookGas",
+                    "app data hooks request too much gas",
+                    serde_json::json!({
+                        "requested": requested,
+                        "max": max,
+                    }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::QuoteExpired {
+                quote_id,
+                expired_at,
+            } => with_status(
+                rich_error(
+                    "QuoteExpired",
+                    "the quote with the specified ID expired or does not match the order",
+                    serde_json::json!({
+                        "quoteId": quote_id,
+                        "expiredAt": expired_at,
+                    }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::OrderValueTooSmall { min, actual } => with_status(
+                rich_error(
+                    "OrderValueTooSmall",
+                    "the order is worth less than the minimum order value",
+                    serde_json::json!({
+                        "min": min.to_string(),
+                        "actual": actual.to_string(),
+                    }),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::UnsupportedToken { token, reason } => with_status(
+                rich_error(
+                    "UnsupportedToken",
+                    format!("Token {token:?} is unsupported: {reason}"),
+                    serde_json::json!({
+                        "token": token,
+                        "reason": reason,
+                    }),
+                ),
                 StatusCode::BAD_REQUEST,
             ),
+            Self::RateLimited { retry_after } => with_status(
+                rich_error(
+                    "RateLimited",
+                    "too many orders submitted recently",
+                    serde_json::json!({
+                        "retryAfter": retry_after.as_secs_f64().ceil() as u64,
+                    }),
+                ),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
             Self::Database(err) => {
                 tracing::error!(?err, "AddOrderError");
                 shared::api::internal_error_reply()
@@ -266,29 +354,35 @@ pub fn create_order_response(
 pub fn post_order(
     orderbook: Arc<Orderbook>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    create_order_request().and_then(move |order: OrderCreation| {
-        let orderbook = orderbook.clone();
-        async move {
-            let result = orderbook.add_order(order.clone()).await;
-            match &result {
-                Ok((order_uid, quote_id)) => {
-                    tracing::debug!(%order_uid, ?quote_id, "order created")
+    create_order_request()
+        .and(super::client_address())
+        .and_then(move |order: OrderCreation, address: super::ClientAddress| {
+            let orderbook = orderbook.clone();
+            async move {
+                let source = super::api_order_source(&orderbook, address);
+                let result = orderbook.add_order(order.clone(), source).await;
+                match &result {
+                    Ok((order_uid, quote_id)) => {
+                        tracing::debug!(%order_uid, ?quote_id, "order created")
+                    }
+                    Err(err) => tracing::debug!(?order, ?err, "error creating order"),
                 }
-                Err(err) => tracing::debug!(?order, ?err, "error creating order"),
-            }
 
-            Result::<_, Infallible>::Ok(create_order_response(result))
-        }
-    })
+                Result::<_, Infallible>::Ok(create_order_response(result))
+            }
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use {
         super::*,
-        model::order::{OrderCreation, OrderUid},
+        chrono::{TimeZone, Utc},
+        model::order::{OrderCreation, OrderStatus, OrderUid},
+        primitive_types::H160,
         serde_json::json,
         shared::api::response_body,
+        std::time::Duration,
         warp::{test::request, Reply},
     };
 
@@ -320,12 +414,148 @@ mod tests {
 
     #[tokio::test]
     async fn create_order_response_duplicate() {
-        let response = create_order_response(Err(AddOrderError::DuplicatedOrder)).into_response();
+        let response = create_order_response(Err(AddOrderError::DuplicatedOrder {
+            uid: OrderUid([1u8; 56]),
+            status: OrderStatus::Open,
+            signature_matches: true,
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "DuplicatedOrder",
+            "description": "order already exists",
+            "data": {
+                "uid": "0x0101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101",
+                "status": "open",
+                "signatureMatches": true,
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_too_many_open_orders() {
+        let response = create_order_response(Err(AddOrderError::TooManyOpenOrders {
+            limit: 2,
+            current: 3,
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "TooManyOpenOrders",
+            "description": "too many open orders",
+            "data": {
+                "limit": 2,
+                "current": 3,
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_excessive_hook_gas() {
+        let response = create_order_response(Err(AddOrderError::ExcessiveHookGas {
+            requested: 200_000,
+            max: 100_000,
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "ExcessiveHookGas",
+            "description": "app data hooks request too much gas",
+            "data": {
+                "requested": 200_000,
+                "max": 100_000,
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_quote_expired() {
+        let response = create_order_response(Err(AddOrderError::QuoteExpired {
+            quote_id: 42,
+            expired_at: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "QuoteExpired",
+            "description": "the quote with the specified ID expired or does not match the order",
+            "data": {
+                "quoteId": 42,
+                "expiredAt": "2023-11-14T22:13:20Z",
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_order_value_too_small() {
+        let response = create_order_response(Err(AddOrderError::OrderValueTooSmall {
+            min: 1_000.into(),
+            actual: 999.into(),
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "OrderValueTooSmall",
+            "description": "the order is worth less than the minimum order value",
+            "data": {
+                "min": "1000",
+                "actual": "999",
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_rate_limited() {
+        let response = create_order_response(Err(AddOrderError::RateLimited {
+            retry_after: Duration::from_millis(1500),
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        let expected_error = json!({
+            "errorType": "RateLimited",
+            "description": "too many orders submitted recently",
+            "data": {
+                "retryAfter": 2,
+            },
+        });
+        assert_eq!(body, expected_error);
+    }
+
+    #[tokio::test]
+    async fn create_order_response_unsupported_token() {
+        let response = create_order_response(Err(AddOrderError::UnsupportedToken {
+            token: H160([1; 20]),
+            reason: "token is on the deny list".to_string(),
+        }))
+        .into_response();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let body = response_body(response).await;
         let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
-        let expected_error =
-            json!({"errorType": "DuplicatedOrder", "description": "order already exists"});
+        let expected_error = json!({
+            "errorType": "UnsupportedToken",
+            "description": "Token 0x0101010101010101010101010101010101010101 is unsupported: token is on the deny list",
+            "data": {
+                "token": "0x0101010101010101010101010101010101010101",
+                "reason": "token is on the deny list",
+            },
+        });
         assert_eq!(body, expected_error);
     }
 }
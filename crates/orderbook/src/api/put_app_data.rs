@@ -65,6 +65,10 @@ impl IntoWarpReply for app_data::RegisterError {
                 super::error("AppDataMismatch", err.to_string()),
                 StatusCode::BAD_REQUEST,
             ),
+            Self::ExcessiveHookGas(err) => reply::with_status(
+                super::error("ExcessiveHookGas", err.to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
             Self::Other(err) => {
                 tracing::error!(?err, "app_data::SaveError::Other");
                 internal_error_reply()
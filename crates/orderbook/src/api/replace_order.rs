@@ -3,9 +3,9 @@ use {
     anyhow::Result,
     model::order::{OrderCreation, OrderUid},
     reqwest::StatusCode,
-    shared::api::{extract_payload, IntoWarpReply},
+    shared::api::{extract_payload, rich_error, IntoWarpReply},
     std::{convert::Infallible, sync::Arc},
-    warp::{reply, Filter, Rejection},
+    warp::{reply, Filter, Rejection, Reply},
 };
 
 fn request() -> impl Filter<Extract = (OrderUid, OrderCreation), Error = Rejection> + Clone {
@@ -24,13 +24,16 @@ fn response(result: Result<OrderUid, ReplaceOrderError>) -> super::ApiReply {
 pub fn filter(
     orderbook: Arc<Orderbook>,
 ) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
-    request().and_then(move |old_order, new_order| {
-        let orderbook = orderbook.clone();
-        async move {
-            let result = orderbook.replace_order(old_order, new_order).await;
-            Result::<_, Infallible>::Ok(response(result))
-        }
-    })
+    request().and(super::client_address()).and_then(
+        move |old_order, new_order, address: super::ClientAddress| {
+            let orderbook = orderbook.clone();
+            async move {
+                let source = super::api_order_source(&orderbook, address);
+                let result = orderbook.replace_order(old_order, new_order, source).await;
+                Result::<_, Infallible>::Ok(response(result))
+            }
+        },
+    )
 }
 
 impl IntoWarpReply for ReplaceOrderError {
@@ -42,13 +45,31 @@ impl IntoWarpReply for ReplaceOrderError {
                 super::error("InvalidReplacement", err.to_string()),
                 StatusCode::UNAUTHORIZED,
             ),
+            err @ ReplaceOrderError::InvalidReplacementMarker => reply::with_status(
+                super::error("InvalidReplacementMarker", err.to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            // The failed replacement determines the status code. Its error
+            // gets wrapped so clients know which replacement to fix.
+            ReplaceOrderError::Batch { index, source } => {
+                let description = format!("replacement {index} is invalid: {source}");
+                let status = source.into_warp_reply().into_response().status();
+                reply::with_status(
+                    rich_error(
+                        "InvalidBatchReplacement",
+                        description,
+                        serde_json::json!({ "index": index }),
+                    ),
+                    status,
+                )
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, serde_json::json, shared::api::response_body};
 
     #[tokio::test]
     async fn replace_order_request_filter() {
@@ -66,4 +87,25 @@ mod tests {
 
         assert_eq!(result, (old_order, new_order));
     }
+
+    #[tokio::test]
+    async fn batch_error_response_contains_index() {
+        let response = response(Err(ReplaceOrderError::Batch {
+            index: 2,
+            source: Box::new(ReplaceOrderError::InvalidReplacementMarker),
+        }))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "errorType": "InvalidBatchReplacement",
+                "description": "replacement 2 is invalid: the app data of the new order does not \
+                                mark the order it replaces",
+                "data": { "index": 2 },
+            })
+        );
+    }
 }
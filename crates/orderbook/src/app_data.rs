@@ -1,18 +1,43 @@
 use {
     crate::{
         database::{app_data::InsertError, Postgres},
-        ipfs_app_data::IpfsAppData,
+        ipfs_app_data::AppDataFetching,
     },
     anyhow::{Context, Result},
-    model::app_data::AppDataHash,
-    shared::app_data,
+    chrono::{DateTime, Utc},
+    model::{app_data::AppDataHash, order::OrderUid},
+    shared::app_data::{self, ExcessiveHookGas},
+    std::{sync::Arc, time::Duration},
 };
 
+/// How long to wait for app data from IPFS by default.
+const DEFAULT_IPFS_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// CoW Protocol API app-data registry.
 pub struct Registry {
     validator: app_data::Validator,
     database: Postgres,
-    ipfs: Option<IpfsAppData>,
+    ipfs: Option<Arc<dyn AppDataFetching>>,
+    ipfs_timeout: Duration,
+}
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "app_data_registry")]
+struct Metrics {
+    /// Number of IPFS app data lookups that could not be used by reason.
+    #[metric(labels("reason"))]
+    ipfs_failures: prometheus::IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Self::instance(observe::metrics::get_storage_registry())
+            .expect("unexpected error getting metrics instance")
+    }
+
+    fn on_ipfs_failure(reason: &str) {
+        Self::get().ipfs_failures.with_label_values(&[reason]).inc();
+    }
 }
 
 impl Registry {
@@ -20,20 +45,47 @@ impl Registry {
     pub fn new(
         validator: app_data::Validator,
         database: Postgres,
-        ipfs: Option<IpfsAppData>,
+        ipfs: Option<Arc<dyn AppDataFetching>>,
     ) -> Self {
         Self {
             validator,
             database,
             ipfs,
+            ipfs_timeout: DEFAULT_IPFS_TIMEOUT,
         }
     }
 
+    /// Sets how long `find` waits for app data from IPFS.
+    pub fn with_ipfs_timeout(mut self, timeout: Duration) -> Self {
+        self.ipfs_timeout = timeout;
+        self
+    }
+
     /// Returns the size limit, in bytes, of an app-data document.
     pub fn size_limit(&self) -> usize {
         self.validator.size_limit()
     }
 
+    /// Checks that the hooks of an app-data document don't request more gas
+    /// than allowed. Invalid documents pass the check since they get rejected
+    /// by the regular app-data validation.
+    pub fn check_hook_gas(&self, document: &str) -> Result<(), ExcessiveHookGas> {
+        match self.validator.validate(document.as_bytes()) {
+            Ok(validated) => self.validator.check_hook_gas(&validated.protocol),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Returns the uid of the order an app-data document marks as replaced by
+    /// the order using it. Invalid documents don't mark any order.
+    pub fn replaced_order(&self, document: &str) -> Option<OrderUid> {
+        let validated = self.validator.validate(document.as_bytes()).ok()?;
+        validated
+            .protocol
+            .replaced_order
+            .map(|replaced| replaced.uid)
+    }
+
     /// Registers an app-data document matching the specified app-data hash to
     /// the registry, ensuring that there exists an entry linking the specified
     /// app data hash with the document.
@@ -55,6 +107,9 @@ impl Registry {
                 computed: validated.hash,
             });
         }
+        self.validator
+            .check_hook_gas(&validated.protocol)
+            .map_err(RegisterError::ExcessiveHookGas)?;
 
         match self
             .database
@@ -71,7 +126,10 @@ impl Registry {
     /// Finds full app data for an order that only has the contract app data
     /// hash.
     ///
-    /// The full app data can be located in the database or on IPFS.
+    /// The full app data can be located in the database or on IPFS. App data
+    /// from IPFS gets stored in the database if it matches the hash. Failing
+    /// to get app data from IPFS is not an error, it is treated as if the app
+    /// data is unknown.
     pub async fn find(&self, contract_app_data: &AppDataHash) -> Result<Option<String>> {
         // we reserve the 0 app data to indicate empty app data.
         if contract_app_data.is_zero() {
@@ -91,10 +149,100 @@ impl Registry {
         let Some(ipfs) = &self.ipfs else {
             return Ok(None);
         };
-        ipfs.fetch(contract_app_data).await.context("from ipfs")
+        Ok(self.find_on_ipfs(ipfs.as_ref(), contract_app_data).await)
+    }
+
+    /// Returns how many orders reference the app-data hash and when the most
+    /// recent of them got created. Works for hashes without a registered
+    /// document as well since orders can reference unknown app data.
+    pub async fn usage(&self, hash: &AppDataHash) -> Result<AppDataUsage> {
+        self.database.app_data_usage(hash).await
+    }
+
+    /// Returns up to `limit` hashes of registered documents that are at least
+    /// `min_age` old and not referenced by any order, starting with the
+    /// oldest. Documents registered before registration times got recorded
+    /// count as old.
+    pub async fn unused(&self, min_age: Duration, limit: u64) -> Result<Vec<AppDataHash>> {
+        let registered_before = Utc::now() - chrono::Duration::from_std(min_age)?;
+        self.database
+            .unused_app_data(registered_before, limit)
+            .await
+    }
+
+    async fn find_on_ipfs(
+        &self,
+        ipfs: &dyn AppDataFetching,
+        contract_app_data: &AppDataHash,
+    ) -> Option<String> {
+        let document =
+            match tokio::time::timeout(self.ipfs_timeout, ipfs.fetch(contract_app_data)).await {
+                Ok(Ok(document)) => document?,
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        ?contract_app_data,
+                        ?err,
+                        "failed to fetch app data from ipfs"
+                    );
+                    Metrics::on_ipfs_failure("error");
+                    return None;
+                }
+                Err(_) => {
+                    tracing::warn!(?contract_app_data, "timed out fetching app data from ipfs");
+                    Metrics::on_ipfs_failure("timeout");
+                    return None;
+                }
+            };
+
+        let validated = match self.validator.validate(document.as_bytes()) {
+            Ok(validated) => validated,
+            Err(err) => {
+                tracing::warn!(?contract_app_data, ?err, "invalid app data on ipfs");
+                Metrics::on_ipfs_failure("invalid");
+                return None;
+            }
+        };
+        if validated.hash != *contract_app_data {
+            tracing::warn!(
+                ?contract_app_data,
+                computed = ?validated.hash,
+                "app data on ipfs doesn't match hash"
+            );
+            Metrics::on_ipfs_failure("hash_mismatch");
+            return None;
+        }
+
+        tracing::debug!(?contract_app_data, "full app data on ipfs");
+        match self
+            .database
+            .insert_full_app_data(&validated.hash, &validated.document)
+            .await
+        {
+            Ok(()) | Err(InsertError::Duplicate) => (),
+            Err(err) => tracing::warn!(
+                ?contract_app_data,
+                ?err,
+                "failed to store app data from ipfs"
+            ),
+        }
+        Some(validated.document)
     }
 }
 
+/// How an app-data hash is used by orders.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct AppDataUsage {
+    /// Whether a document is registered for the hash.
+    pub registered: bool,
+    /// When the document got registered. Unknown for documents registered
+    /// before registration times got recorded.
+    pub registered_at: Option<DateTime<Utc>>,
+    /// Number of orders referencing the hash.
+    pub orders: u64,
+    /// When the most recent order referencing the hash got created.
+    pub latest_order: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug)]
 pub enum Registered {
     /// The app data was newly added to the registry.
@@ -115,5 +263,166 @@ pub enum RegisterError {
     #[error("stored appData {existing:?} is different than the specified data")]
     DataMismatch { existing: String },
     #[error(transparent)]
+    ExcessiveHookGas(ExcessiveHookGas),
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{database::orders::OrderStoring, ipfs_app_data::MockAppDataFetching},
+        anyhow::anyhow,
+        chrono::TimeZone,
+        model::order::{Order, OrderData, OrderMetadata},
+    };
+
+    const DOCUMENT: &str = r#"{"appCode":"test"}"#;
+
+    fn registry(ipfs: impl AppDataFetching + 'static) -> (Registry, Postgres) {
+        let database = Postgres::new("postgresql://").unwrap();
+        let registry = Registry::new(
+            app_data::Validator::new(8192),
+            database.clone(),
+            Some(Arc::new(ipfs)),
+        );
+        (registry, database)
+    }
+
+    fn hash(document: &str) -> AppDataHash {
+        AppDataHash(app_data_hash::hash_full_app_data(document.as_bytes()))
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_find_stores_app_data_from_ipfs() {
+        let mut ipfs = MockAppDataFetching::new();
+        ipfs.expect_fetch()
+            .times(1)
+            .returning(|_| Ok(Some(DOCUMENT.to_string())));
+        let (registry, database) = registry(ipfs);
+        database::clear_DANGER(&database.pool).await.unwrap();
+
+        let hash = hash(DOCUMENT);
+        assert_eq!(
+            registry.find(&hash).await.unwrap().as_deref(),
+            Some(DOCUMENT)
+        );
+        assert_eq!(
+            database.get_full_app_data(&hash).await.unwrap().as_deref(),
+            Some(DOCUMENT)
+        );
+
+        // The second lookup is served from the database.
+        assert_eq!(
+            registry.find(&hash).await.unwrap().as_deref(),
+            Some(DOCUMENT)
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_find_ignores_mismatching_app_data_from_ipfs() {
+        let mut ipfs = MockAppDataFetching::new();
+        ipfs.expect_fetch()
+            .returning(|_| Ok(Some(r#"{"appCode":"other"}"#.to_string())));
+        let (registry, database) = registry(ipfs);
+        database::clear_DANGER(&database.pool).await.unwrap();
+
+        let hash = hash(DOCUMENT);
+        assert_eq!(registry.find(&hash).await.unwrap(), None);
+        assert_eq!(database.get_full_app_data(&hash).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_find_tolerates_ipfs_failures() {
+        struct Stalling;
+
+        #[async_trait::async_trait]
+        impl AppDataFetching for Stalling {
+            async fn fetch(&self, _: &AppDataHash) -> Result<Option<String>> {
+                futures::future::pending().await
+            }
+        }
+
+        let (stalling, database) = registry(Stalling);
+        let stalling = stalling.with_ipfs_timeout(Duration::from_millis(10));
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let hash = hash(DOCUMENT);
+        assert_eq!(stalling.find(&hash).await.unwrap(), None);
+
+        let mut ipfs = MockAppDataFetching::new();
+        ipfs.expect_fetch()
+            .returning(|_| Err(anyhow!("gateway unavailable")));
+        let (failing, _) = registry(ipfs);
+        assert_eq!(failing.find(&hash).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_app_data_usage() {
+        let (registry, database) = registry(MockAppDataFetching::new());
+        database::clear_DANGER(&database.pool).await.unwrap();
+
+        const UNUSED: &str = r#"{"appCode":"unused"}"#;
+        let (_, used) = registry.register(None, DOCUMENT.as_bytes()).await.unwrap();
+        let (_, unused) = registry.register(None, UNUSED.as_bytes()).await.unwrap();
+        let unknown = hash(r#"{"appCode":"unknown"}"#);
+        for (byte, app_data, created) in [(1, used, 1_000), (2, used, 2_000), (3, unknown, 3_000)] {
+            let order = Order {
+                metadata: OrderMetadata {
+                    uid: OrderUid([byte; 56]),
+                    creation_date: Utc.timestamp_opt(created, 0).unwrap(),
+                    ..Default::default()
+                },
+                data: OrderData {
+                    app_data,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            database.insert_order(&order, None).await.unwrap();
+        }
+
+        let usage = registry.usage(&used).await.unwrap();
+        assert!(usage.registered);
+        assert!(usage.registered_at.is_some());
+        assert_eq!(usage.orders, 2);
+        assert_eq!(usage.latest_order, Utc.timestamp_opt(2_000, 0).single());
+
+        let usage = registry.usage(&unused).await.unwrap();
+        assert!(usage.registered);
+        assert_eq!(usage.orders, 0);
+        assert_eq!(usage.latest_order, None);
+
+        assert_eq!(
+            registry.usage(&unknown).await.unwrap(),
+            AppDataUsage {
+                registered: false,
+                registered_at: None,
+                orders: 1,
+                latest_order: Utc.timestamp_opt(3_000, 0).single(),
+            }
+        );
+
+        // Only documents registered long enough ago are reported.
+        assert!(registry
+            .unused(Duration::from_secs(3600), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        sqlx::query("UPDATE app_data SET registration_timestamp = now() - interval '1 day'")
+            .execute(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            registry
+                .unused(Duration::from_secs(3600), 10)
+                .await
+                .unwrap(),
+            vec![unused]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use {
-    primitive_types::H160,
+    crate::orderbook::DEFAULT_MAX_CANCELLATION_BATCH_SIZE,
+    primitive_types::{H160, U256},
     reqwest::Url,
     shared::{
         arguments::{display_option, display_secret_option},
@@ -7,7 +8,11 @@ use {
         http_client,
         price_estimation::{self, NativePriceEstimators},
     },
-    std::{net::SocketAddr, num::NonZeroUsize, time::Duration},
+    std::{
+        net::{IpAddr, SocketAddr},
+        num::{NonZeroU32, NonZeroUsize},
+        time::Duration,
+    },
 };
 
 #[derive(clap::Parser)]
@@ -125,6 +130,23 @@ pub struct Arguments {
     #[clap(long, env, default_value = "10")]
     pub max_limit_orders_per_user: u64,
 
+    /// Max number of open orders per owner regardless of their class. Orders
+    /// exceeding the limit get rejected before they are validated. No limit
+    /// if unset.
+    #[clap(long, env)]
+    pub max_open_orders_per_owner: Option<u64>,
+
+    /// Owners that are exempt from `max_open_orders_per_owner`, e.g. market
+    /// makers placing liquidity orders.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub open_order_limit_exempt_owners: Vec<H160>,
+
+    /// Minimum value in Ether of an order's sell amount. Orders worth less get
+    /// rejected unless there is no native price for their sell token. No
+    /// minimum if unset.
+    #[clap(long, env, value_parser = shared::arguments::wei_from_ether)]
+    pub min_order_value: Option<U256>,
+
     /// If set, the orderbook will use this IPFS gateway to fetch full app data
     /// for orders that only specify the contract app data hash.
     #[clap(long, env)]
@@ -134,6 +156,16 @@ pub struct Arguments {
     #[clap(long, env)]
     pub ipfs_pinata_auth: Option<String>,
 
+    /// How long to wait for app data from IPFS when placing an order before
+    /// continuing without it.
+    #[clap(
+        long,
+        env,
+        default_value = "5s",
+        value_parser = humantime::parse_duration,
+    )]
+    pub ipfs_timeout: Duration,
+
     /// Override the address of the `HooksTrampoline` contract used for
     /// trampolining custom order interactions. If not specified, the default
     /// contract deployment for the current network will be used.
@@ -143,6 +175,89 @@ pub struct Arguments {
     /// Set the maximum size in bytes of order app data.
     #[clap(long, env, default_value = "8192")]
     pub app_data_size_limit: usize,
+
+    /// The maximum total gas the pre and post hooks of an order's app data may
+    /// request. Unlimited if not specified.
+    #[clap(long, env)]
+    pub app_data_max_hook_gas: Option<u64>,
+
+    /// Accept order cancellations that don't specify a `validTo`. The signed
+    /// messages of such cancellations never expire.
+    #[clap(long, env, action = clap::ArgAction::Set, default_value = "true")]
+    pub accept_cancellations_without_valid_to: bool,
+
+    /// How long the database may be unreachable before the liveness check
+    /// fails and the service gets restarted.
+    #[clap(
+        long,
+        env,
+        default_value = "2m",
+        value_parser = humantime::parse_duration,
+    )]
+    pub db_unreachable_grace_period: Duration,
+
+    /// Maximum age of the current auction. Older auctions don't get served
+    /// and fail the liveness check because the autopilot apparently stopped
+    /// producing auctions. Auctions never get stale if unset.
+    #[clap(long, env, value_parser = humantime::parse_duration)]
+    pub max_auction_age: Option<Duration>,
+
+    /// Maximum number of orders that can get cancelled with a single signed
+    /// cancellation.
+    #[clap(long, env, default_value_t = DEFAULT_MAX_CANCELLATION_BATCH_SIZE)]
+    pub max_cancellation_batch_size: usize,
+
+    /// List of owners whose liquidity orders get accepted without checking
+    /// whether they can transfer the sell amount. Their orders need to
+    /// specify the `from` field.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub trusted_liquidity_providers: Vec<H160>,
+
+    /// Salt for hashing the IP addresses orders get posted from. The hashes
+    /// get stored with the orders. IP addresses don't get recorded if this
+    /// is not set.
+    #[clap(long, env)]
+    pub client_ip_hash_salt: Option<String>,
+
+    /// Addresses of the reverse proxies in front of the API. Only requests
+    /// they forward get attributed to the address in their `X-Forwarded-For`
+    /// header. Otherwise the peer address gets recorded.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub trusted_proxies: Vec<IpAddr>,
+
+    /// Number of orders an owner can place at once before getting rate
+    /// limited. Submissions don't get rate limited if unset.
+    #[clap(long, env)]
+    pub order_submission_burst: Option<NonZeroU32>,
+
+    /// Time after which a rate limited owner can place one more order.
+    #[clap(
+        long,
+        env,
+        default_value = "1s",
+        value_parser = humantime::parse_duration,
+    )]
+    pub order_submission_refill_interval: Duration,
+
+    /// Rate limit the orders of an owner separately per app code.
+    #[clap(long, env, action = clap::ArgAction::Set, default_value = "false")]
+    pub order_submission_rate_limit_per_app_code: bool,
+
+    /// How often the balances of the owners of a sample of open fill-or-kill
+    /// orders get checked. Orders whose owners lack the sell amount in two
+    /// checks in a row get flagged until the balance is restored.
+    #[clap(
+        long,
+        env,
+        default_value = "1m",
+        value_parser = humantime::parse_duration,
+    )]
+    pub insufficient_balance_check_interval: Duration,
+
+    /// Maximum number of open fill-or-kill orders whose balances get checked
+    /// at once.
+    #[clap(long, env, default_value = "1000")]
+    pub insufficient_balance_check_sample_size: u64,
 }
 
 impl std::fmt::Display for Arguments {
@@ -168,11 +283,28 @@ impl std::fmt::Display for Arguments {
             native_price_estimators,
             fast_price_estimation_results_required,
             max_limit_orders_per_user,
+            max_open_orders_per_owner,
+            open_order_limit_exempt_owners,
+            min_order_value,
             ipfs_gateway,
             ipfs_pinata_auth,
+            ipfs_timeout,
             hooks_contract_address,
             app_data_size_limit,
+            app_data_max_hook_gas,
             db_url,
+            accept_cancellations_without_valid_to,
+            db_unreachable_grace_period,
+            max_auction_age,
+            max_cancellation_batch_size,
+            trusted_liquidity_providers,
+            client_ip_hash_salt,
+            trusted_proxies,
+            order_submission_burst,
+            order_submission_refill_interval,
+            order_submission_rate_limit_per_app_code,
+            insufficient_balance_check_interval,
+            insufficient_balance_check_sample_size,
         } = self;
 
         write!(f, "{}", shared)?;
@@ -229,14 +361,71 @@ impl std::fmt::Display for Arguments {
             "max_limit_orders_per_user: {}",
             max_limit_orders_per_user
         )?;
+        display_option(f, "max_open_orders_per_owner", max_open_orders_per_owner)?;
+        writeln!(
+            f,
+            "open_order_limit_exempt_owners: {:?}",
+            open_order_limit_exempt_owners
+        )?;
+        display_option(f, "min_order_value", min_order_value)?;
         writeln!(f, "ipfs_gateway: {:?}", ipfs_gateway)?;
         display_secret_option(f, "ipfs_pinata_auth", ipfs_pinata_auth)?;
+        writeln!(f, "ipfs_timeout: {:?}", ipfs_timeout)?;
         display_option(
             f,
             "hooks_contract_address",
             &hooks_contract_address.map(|a| format!("{a:?}")),
         )?;
         writeln!(f, "app_data_size_limit: {}", app_data_size_limit)?;
+        display_option(f, "app_data_max_hook_gas", app_data_max_hook_gas)?;
+        writeln!(
+            f,
+            "accept_cancellations_without_valid_to: {}",
+            accept_cancellations_without_valid_to
+        )?;
+        writeln!(
+            f,
+            "db_unreachable_grace_period: {:?}",
+            db_unreachable_grace_period
+        )?;
+        display_option(
+            f,
+            "max_auction_age",
+            &max_auction_age.map(|age| format!("{age:?}")),
+        )?;
+        writeln!(
+            f,
+            "max_cancellation_batch_size: {}",
+            max_cancellation_batch_size
+        )?;
+        writeln!(
+            f,
+            "trusted_liquidity_providers: {:?}",
+            trusted_liquidity_providers
+        )?;
+        display_secret_option(f, "client_ip_hash_salt", client_ip_hash_salt)?;
+        writeln!(f, "trusted_proxies: {:?}", trusted_proxies)?;
+        display_option(f, "order_submission_burst", order_submission_burst)?;
+        writeln!(
+            f,
+            "order_submission_refill_interval: {:?}",
+            order_submission_refill_interval
+        )?;
+        writeln!(
+            f,
+            "order_submission_rate_limit_per_app_code: {}",
+            order_submission_rate_limit_per_app_code
+        )?;
+        writeln!(
+            f,
+            "insufficient_balance_check_interval: {:?}",
+            insufficient_balance_check_interval
+        )?;
+        writeln!(
+            f,
+            "insufficient_balance_check_sample_size: {}",
+            insufficient_balance_check_sample_size
+        )?;
 
         Ok(())
     }
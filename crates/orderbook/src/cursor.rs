@@ -0,0 +1,136 @@
+use {
+    anyhow::{ensure, Context, Result},
+    chrono::{DateTime, TimeZone, Utc},
+    model::order::{Order, OrderUid},
+    std::{fmt, str::FromStr},
+    web3::signing::keccak256,
+};
+
+/// Opaque cursor for paginating through the orders of a user. It points to the
+/// last order of the previous page.
+///
+/// The encoded cursor contains a checksum so modified or truncated cursors get
+/// rejected instead of silently returning an unexpected page. The checksum is
+/// not a security measure: cursors don't grant access to anything that isn't
+/// public anyway.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cursor {
+    pub creation_date: DateTime<Utc>,
+    pub uid: OrderUid,
+}
+
+const VERSION: u8 = 1;
+const CHECKSUM_LEN: usize = 4;
+const ENCODED_LEN: usize = 1 + 8 + 56 + CHECKSUM_LEN;
+
+impl Cursor {
+    /// Cursor pointing at the specified order.
+    pub fn for_order(order: &Order) -> Self {
+        Self {
+            creation_date: order.metadata.creation_date,
+            uid: order.metadata.uid,
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_LEN] {
+    keccak256(data)[..CHECKSUM_LEN].try_into().unwrap()
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = Vec::with_capacity(ENCODED_LEN);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.creation_date.timestamp_micros().to_be_bytes());
+        bytes.extend_from_slice(&self.uid.0);
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        f.write_str(&hex::encode(bytes))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s).context("cursor is not hex encoded")?;
+        ensure!(bytes.len() == ENCODED_LEN, "cursor has wrong length");
+        let (data, expected_checksum) = bytes.split_at(ENCODED_LEN - CHECKSUM_LEN);
+        ensure!(
+            checksum(data) == expected_checksum,
+            "cursor checksum mismatch"
+        );
+        ensure!(data[0] == VERSION, "unsupported cursor version");
+
+        let micros = i64::from_be_bytes(data[1..9].try_into().unwrap());
+        let creation_date = Utc
+            .timestamp_opt(
+                micros.div_euclid(1_000_000),
+                (micros.rem_euclid(1_000_000) * 1_000) as u32,
+            )
+            .single()
+            .context("cursor timestamp out of range")?;
+        let uid = OrderUid(data[9..].try_into().unwrap());
+        Ok(Self { creation_date, uid })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor() -> Cursor {
+        Cursor {
+            creation_date: Utc.timestamp_opt(1_700_000_000, 123_456_000).unwrap(),
+            uid: OrderUid([0x42; 56]),
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let cursor = cursor();
+        let encoded = cursor.to_string();
+        assert_eq!(encoded.len(), ENCODED_LEN * 2);
+        assert_eq!(encoded.parse::<Cursor>().unwrap(), cursor);
+    }
+
+    #[test]
+    fn roundtrip_before_epoch() {
+        let cursor = Cursor {
+            creation_date: Utc.timestamp_opt(-2, 500_000_000).unwrap(),
+            ..cursor()
+        };
+        assert_eq!(cursor.to_string().parse::<Cursor>().unwrap(), cursor);
+    }
+
+    #[test]
+    fn rejects_tampered_cursors() {
+        let encoded = cursor().to_string();
+
+        // Every single modified byte gets detected.
+        let bytes = hex::decode(&encoded).unwrap();
+        for i in 0..bytes.len() {
+            let mut tampered = bytes.clone();
+            tampered[i] ^= 1;
+            assert!(hex::encode(tampered).parse::<Cursor>().is_err());
+        }
+
+        // Truncated or extended cursors.
+        assert!(encoded[..encoded.len() - 2].parse::<Cursor>().is_err());
+        assert!(format!("{encoded}00").parse::<Cursor>().is_err());
+
+        // Garbage.
+        assert!("".parse::<Cursor>().is_err());
+        assert!("not a cursor".parse::<Cursor>().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut data = hex::decode(cursor().to_string()).unwrap();
+        data.truncate(ENCODED_LEN - CHECKSUM_LEN);
+        data[0] = VERSION + 1;
+        let checksum = checksum(&data);
+        data.extend_from_slice(&checksum);
+        assert!(hex::encode(data).parse::<Cursor>().is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use {
+    crate::app_data::AppDataUsage,
     anyhow::{Context, Result},
+    chrono::{DateTime, Utc},
     database::byte_array::ByteArray,
     model::app_data::AppDataHash,
     std::string::FromUtf8Error,
@@ -40,6 +42,7 @@ impl super::Postgres {
             &mut ex,
             &ByteArray(contract_app_data.0),
             full_app_data.as_bytes(),
+            app_code(full_app_data).as_deref(),
         )
         .await?
         {
@@ -53,6 +56,53 @@ impl super::Postgres {
 
         Ok(())
     }
+
+    pub async fn app_data_usage(&self, contract_app_data: &AppDataHash) -> Result<AppDataUsage> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["app_data_usage"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let usage = database::app_data::usage(&mut ex, &ByteArray(contract_app_data.0)).await?;
+        Ok(AppDataUsage {
+            registered: usage.registered,
+            registered_at: usage.registration_timestamp,
+            orders: usage.orders.try_into().context("negative order count")?,
+            latest_order: usage.latest_order,
+        })
+    }
+
+    pub async fn unused_app_data(
+        &self,
+        registered_before: DateTime<Utc>,
+        limit: u64,
+    ) -> Result<Vec<AppDataHash>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unused_app_data"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let unused = database::app_data::unused(
+            &mut ex,
+            registered_before,
+            limit.try_into().unwrap_or(i64::MAX),
+        )
+        .await?;
+        Ok(unused
+            .into_iter()
+            .map(|contract_app_data| AppDataHash(contract_app_data.0))
+            .collect())
+    }
+}
+
+/// Extracts the `appCode` identifying the integrator from a full app data
+/// document. Documents that are not JSON objects or whose app code is not a
+/// string don't have an app code.
+pub fn app_code(full_app_data: &str) -> Option<String> {
+    let document: serde_json::Value = serde_json::from_str(full_app_data).ok()?;
+    document.get("appCode")?.as_str().map(str::to_owned)
 }
 
 #[derive(Debug)]
@@ -73,3 +123,44 @@ impl From<FromUtf8Error> for InsertError {
         Self::Other(err.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_app_code() {
+        assert_eq!(
+            app_code(r#"{"appCode": "CoW Swap", "metadata": {}}"#).as_deref(),
+            Some("CoW Swap")
+        );
+        assert_eq!(app_code(r#"{"metadata": {}}"#), None);
+        assert_eq!(app_code(r#"{"appCode": 1}"#), None);
+        assert_eq!(app_code(r#"["appCode"]"#), None);
+        assert_eq!(app_code("not json"), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_stores_app_code_on_registration() {
+        let db = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let with_code = AppDataHash([1; 32]);
+        db.insert_full_app_data(&with_code, r#"{"appCode": "CoW Swap"}"#)
+            .await
+            .unwrap();
+        let without_code = AppDataHash([2; 32]);
+        db.insert_full_app_data(&without_code, "{}").await.unwrap();
+
+        let mut ex = db.pool.acquire().await.unwrap();
+        let stored = database::app_data::fetch_app_code(&mut ex, &ByteArray(with_code.0))
+            .await
+            .unwrap();
+        assert_eq!(stored.as_deref(), Some("CoW Swap"));
+        let stored = database::app_data::fetch_app_code(&mut ex, &ByteArray(without_code.0))
+            .await
+            .unwrap();
+        assert_eq!(stored, None);
+    }
+}
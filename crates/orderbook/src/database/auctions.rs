@@ -1,19 +1,58 @@
-use {crate::dto, anyhow::Result};
+use {
+    crate::dto,
+    anyhow::Result,
+    chrono::{DateTime, Utc},
+};
 
 impl super::Postgres {
-    pub async fn most_recent_auction(&self) -> Result<Option<dto::AuctionWithId>> {
+    /// The most recent auction together with when it got created.
+    pub async fn most_recent_auction(&self) -> Result<Option<(dto::AuctionWithId, DateTime<Utc>)>> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["load_most_recent_auction"])
             .start_timer();
 
         let mut ex = self.pool.acquire().await?;
-        let (id, json) = match database::auction::load_most_recent(&mut ex).await? {
+        let (id, json, created) = match database::auction::load_most_recent(&mut ex).await? {
             Some(inner) => inner,
             None => return Ok(None),
         };
         let auction: dto::Auction = serde_json::from_value(json)?;
         let auction = dto::AuctionWithId { id, auction };
-        Ok(Some(auction))
+        Ok(Some((auction, created)))
+    }
+
+    pub async fn most_recent_auction_created(&self) -> Result<Option<DateTime<Utc>>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_most_recent_auction_created"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::auction::load_most_recent_created(&mut ex).await?)
+    }
+
+    pub async fn auction_by_id(&self, id: dto::AuctionId) -> Result<Option<dto::AuctionWithId>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_auction_by_id"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let Some(json) = database::auction::load_by_id(&mut ex, id).await? else {
+            return Ok(None);
+        };
+        let auction: dto::Auction = serde_json::from_value(json)?;
+        Ok(Some(dto::AuctionWithId { id, auction }))
+    }
+
+    pub async fn most_recent_auction_id(&self) -> Result<Option<dto::AuctionId>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_most_recent_auction_id"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::auction::load_most_recent_id(&mut ex).await?)
     }
 }
@@ -6,13 +6,16 @@ use {
     database::{
         byte_array::ByteArray,
         order_events::{insert_order_event, OrderEvent, OrderEventLabel},
-        orders::{FullOrder, OrderKind as DbOrderKind},
+        orders::{FullOrder, OrderKind as DbOrderKind, OrderStatus as DbOrderStatus},
     },
     ethcontract::H256,
     futures::{stream::TryStreamExt, FutureExt, StreamExt},
     model::{
         app_data::AppDataHash,
         order::{
+            AmendedAmounts,
+            CancellationProof,
+            CancellationSignature,
             EthflowData,
             Interactions,
             OnchainOrderData,
@@ -28,7 +31,7 @@ use {
     },
     num::Zero,
     number::conversions::{big_decimal_to_big_uint, big_decimal_to_u256, u256_to_big_decimal},
-    primitive_types::H160,
+    primitive_types::{H160, U256},
     shared::{
         db_order_conversions::{
             buy_token_destination_from,
@@ -39,16 +42,20 @@ use {
             order_class_into,
             order_kind_from,
             order_kind_into,
+            order_source_from,
+            order_source_into,
             sell_token_source_from,
             sell_token_source_into,
             signing_scheme_from,
             signing_scheme_into,
         },
-        order_quoting::Quote,
+        fee::FeeParameters,
+        order_quoting::{Quote, QuoteData},
         order_validation::LimitOrderCounting,
     },
     sqlx::{types::BigDecimal, Connection, PgConnection},
-    std::convert::TryInto,
+    std::{collections::HashMap, convert::TryInto},
+    thiserror::Error,
 };
 
 #[cfg_attr(test, mockall::automock)]
@@ -56,24 +63,192 @@ use {
 pub trait OrderStoring: Send + Sync {
     async fn insert_order(&self, order: &Order, quote: Option<Quote>)
         -> Result<(), InsertionError>;
-    async fn cancel_orders(&self, order_uids: Vec<OrderUid>, now: DateTime<Utc>) -> Result<()>;
-    async fn cancel_order(&self, order_uid: &OrderUid, now: DateTime<Utc>) -> Result<()>;
+    /// Inserts multiple orders in a single transaction. Every order gets
+    /// inserted independently so failing to insert one of them (e.g. because
+    /// it already exists) does not affect the others. The results are in the
+    /// same order as the passed in orders.
+    async fn insert_orders(
+        &self,
+        orders: Vec<(Order, Option<Quote>)>,
+    ) -> Result<Vec<Result<(), InsertionError>>>;
+    /// Cancels the orders at the proof's timestamp and stores the proof for
+    /// every one of them. Either all or none of the orders get cancelled and
+    /// the cancellation message with the specified hash can only be applied
+    /// once.
+    async fn cancel_orders(
+        &self,
+        order_uids: Vec<OrderUid>,
+        cancellation_hash: [u8; 32],
+        proof: &CancellationProof,
+    ) -> Result<(), CancellationError>;
+    /// Cancels the order at the proof's timestamp and stores the proof unless
+    /// the cancellation message with the specified hash was already applied
+    /// before.
+    async fn cancel_order(
+        &self,
+        order_uid: &OrderUid,
+        cancellation_hash: [u8; 32],
+        proof: &CancellationProof,
+    ) -> Result<(), CancellationError>;
+    /// Cancels all open orders of the owner that were created at or before
+    /// `created_before` at the proof's timestamp in a single transaction and
+    /// stores the proof for every one of them. Orders that are not open or
+    /// can't be cancelled with an off-chain signature are skipped. Returns the
+    /// cancelled orders.
+    async fn cancel_all_orders(
+        &self,
+        owner: &H160,
+        created_before: DateTime<Utc>,
+        proof: &CancellationProof,
+    ) -> Result<Vec<Order>>;
     async fn replace_order(
         &self,
         old_order: &OrderUid,
         new_order: &Order,
         new_quote: Option<Quote>,
     ) -> Result<(), InsertionError>;
+    /// Replaces multiple orders in a single transaction. Either all or none
+    /// of the replacements get applied. If a replacement fails its index is
+    /// returned together with the error.
+    async fn replace_orders(
+        &self,
+        replacements: Vec<(OrderUid, Order, Option<Quote>)>,
+    ) -> Result<Result<(), (usize, InsertionError)>>;
+    /// Number of open orders of the owner.
+    async fn count_open_orders(&self, owner: &H160) -> Result<u64>;
+    /// Number of open orders per `(sell_token, buy_token)` pair that are valid
+    /// at least until `min_valid_to`, ordered by descending count.
+    async fn count_open_orders_by_pair(&self, min_valid_to: u32) -> Result<Vec<(H160, H160, u64)>>;
+    /// Number of open orders per app code that are valid at least until
+    /// `min_valid_to`, ordered by descending count.
+    async fn count_open_orders_by_app_code(&self, min_valid_to: u32) -> Result<Vec<(String, u64)>>;
+    /// Number of orders created at or after `since` per app code, ordered by
+    /// descending count.
+    async fn count_orders_by_app_code(&self, since: DateTime<Utc>) -> Result<Vec<(String, u64)>>;
+    /// All orders of the replacement chain the order is part of, from the
+    /// original order to the most recent replacement.
+    async fn replacement_chain(&self, uid: &OrderUid) -> Result<Vec<OrderUid>>;
+    /// Reduces the amounts of an order. Returns `false` if the amendment got
+    /// rejected because it would increase the amounts or the order already
+    /// executed more than the new amounts.
+    async fn amend_order(
+        &self,
+        order_uid: &OrderUid,
+        amounts: AmendedAmounts,
+        now: DateTime<Utc>,
+    ) -> Result<bool>;
     async fn orders_for_tx(&self, tx_hash: &H256) -> Result<Vec<Order>>;
+    /// Hash of the mined settlement transaction with the specified sender and
+    /// nonce.
+    async fn settlement_tx_hash(&self, sender: &H160, nonce: i64) -> Result<Option<H256>>;
     async fn single_order(&self, uid: &OrderUid) -> Result<Option<Order>>;
-    /// All orders of a single user ordered by creation date descending (newest
-    /// orders first).
+    /// The orders with the specified uids keyed by their uid. Uids without an
+    /// order are ignored.
+    async fn orders_by_uids(&self, uids: &[OrderUid]) -> Result<HashMap<OrderUid, Order>>;
+    /// Whether the pre-signature of the order was ever set on-chain.
+    async fn was_presigned(&self, uid: &OrderUid) -> Result<bool>;
+    /// The signed off-chain cancellation of the order if it got cancelled
+    /// that way.
+    async fn get_cancellation_proof(&self, uid: &OrderUid) -> Result<Option<CancellationProof>>;
+    /// The quote that was stored together with the order. Only the fields
+    /// that get stored for orders are set.
+    async fn order_quote(&self, uid: &OrderUid) -> Result<Option<Quote>>;
+    /// All orders of a single user matching the filter ordered by creation
+    /// date descending (newest orders first).
     async fn user_orders(
         &self,
         owner: &H160,
+        filter: &UserOrderFilter,
         offset: u64,
         limit: Option<u64>,
     ) -> Result<Vec<Order>>;
+    /// Like `user_orders` but also returns the total number of the user's
+    /// orders matching the filter.
+    async fn user_orders_with_count(
+        &self,
+        owner: &H160,
+        filter: &UserOrderFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(Vec<Order>, u64)>;
+    /// Up to `limit` orders of a single user ordered by creation date and uid
+    /// descending that come after the `before` order in that ordering.
+    async fn user_orders_before(
+        &self,
+        owner: &H160,
+        before: Option<(DateTime<Utc>, OrderUid)>,
+        limit: u64,
+    ) -> Result<Vec<Order>>;
+    /// Open orders of the owner that expire between `min_valid_to` and
+    /// `max_valid_to` (both inclusive) ordered by ascending expiry.
+    async fn expiring_orders(
+        &self,
+        owner: &H160,
+        min_valid_to: u32,
+        max_valid_to: u32,
+    ) -> Result<Vec<Order>>;
+    /// A random sample of at most `limit` open fill-or-kill orders.
+    async fn sample_open_fill_or_kill_orders(
+        &self,
+        min_valid_to: u32,
+        limit: u64,
+    ) -> Result<Vec<Order>>;
+    /// Sets whether the owners of the orders lack the sell token balance.
+    async fn set_insufficient_balance(
+        &self,
+        uids: &[OrderUid],
+        insufficient_balance: bool,
+    ) -> Result<()>;
+}
+
+/// Optional filters for the orders of a user. Filters that are not set match
+/// every order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserOrderFilter {
+    pub status: Option<OrderStatus>,
+    pub sell_token: Option<H160>,
+    pub buy_token: Option<H160>,
+    /// Only orders that are valid at least until this unix timestamp.
+    pub min_valid_to: Option<u32>,
+    /// Only orders that were created strictly after this time.
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+impl From<&UserOrderFilter> for database::orders::UserOrdersFilter {
+    fn from(filter: &UserOrderFilter) -> Self {
+        Self {
+            status: filter.status.map(|status| match status {
+                OrderStatus::PresignaturePending => DbOrderStatus::PresignaturePending,
+                OrderStatus::Open => DbOrderStatus::Open,
+                OrderStatus::Fulfilled => DbOrderStatus::Fulfilled,
+                OrderStatus::Cancelled => DbOrderStatus::Cancelled,
+                OrderStatus::Expired => DbOrderStatus::Expired,
+            }),
+            sell_token: filter.sell_token.map(|token| ByteArray(token.0)),
+            buy_token: filter.buy_token.map(|token| ByteArray(token.0)),
+            min_valid_to: filter.min_valid_to.map(i64::from),
+            created_after: filter.created_after,
+        }
+    }
+}
+
+/// Result of looking up the quote of an order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderQuote {
+    OrderNotFound,
+    /// The order exists but was created without a quote.
+    NoQuote,
+    Quote(Quote),
+}
+
+impl OrderQuote {
+    /// The stored quote if the order exists and was created with one.
+    pub fn into_quote(self) -> Option<Quote> {
+        match self {
+            Self::Quote(quote) => Some(quote),
+            Self::OrderNotFound | Self::NoQuote => None,
+        }
+    }
 }
 
 pub struct SolvableOrders {
@@ -89,6 +264,18 @@ pub enum InsertionError {
     AppDataMismatch(Vec<u8>),
 }
 
+/// Reasons for orders not getting cancelled that are only detected while
+/// cancelling them, e.g. because the order got executed concurrently.
+#[derive(Debug, Error)]
+pub enum CancellationError {
+    #[error("order {0} is already cancelled")]
+    AlreadyCancelled(OrderUid),
+    #[error("order {0} is fully executed")]
+    FullyExecuted(OrderUid),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
 impl From<sqlx::Error> for InsertionError {
     fn from(err: sqlx::Error) -> Self {
         Self::DbError(err)
@@ -115,6 +302,63 @@ async fn cancel_order(
     Ok(())
 }
 
+/// Locks the orders until the end of the transaction and checks that they can
+/// still be cancelled. Trades of locked orders can't get indexed so their
+/// status can't change before the transaction ends.
+async fn lock_cancellable_orders(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<(), CancellationError> {
+    let uids: Vec<_> = order_uids.iter().map(|uid| ByteArray(uid.0)).collect();
+    database::orders::lock_orders(ex, &uids)
+        .await
+        .context("lock orders")?;
+    for (order_uid, uid) in order_uids.iter().zip(&uids) {
+        let order = database::orders::single_full_order(ex, uid)
+            .await
+            .context("load locked order")?;
+        match order.as_ref().map(calculate_status) {
+            Some(OrderStatus::Fulfilled) => {
+                return Err(CancellationError::FullyExecuted(*order_uid))
+            }
+            Some(OrderStatus::Cancelled) => {
+                return Err(CancellationError::AlreadyCancelled(*order_uid))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Cancels a single order with a signed off-chain cancellation and stores
+/// the cancellation as proof.
+async fn cancel_order_with_proof(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+    proof: &CancellationProof,
+) -> Result<()> {
+    cancel_order(ex, order_uid, proof.timestamp).await?;
+    database::orders::insert_cancellation_proof(
+        ex,
+        &database::orders::CancellationProof {
+            order_uid: ByteArray(order_uid.0),
+            signature: proof.signature.to_bytes(),
+            signing_scheme: signing_scheme_into(proof.signature.scheme()),
+            signer: ByteArray(proof.signer.0),
+            timestamp: proof.timestamp,
+            message: proof
+                .message
+                .as_ref()
+                .map(serde_json::to_vec)
+                .transpose()
+                .context("serialize cancellation message")?,
+            domain_separator: proof.domain_separator.map(|domain| ByteArray(domain.0)),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
 async fn insert_order(order: &Order, ex: &mut PgConnection) -> Result<(), InsertionError> {
     insert_order_event(
         ex,
@@ -176,6 +420,8 @@ async fn insert_order(order: &Order, ex: &mut PgConnection) -> Result<(), Insert
         buy_token_balance: buy_token_destination_into(order.data.buy_token_balance),
         full_fee_amount: u256_to_big_decimal(&order.metadata.full_fee_amount),
         cancellation_timestamp: None,
+        balance_check_skipped: order.metadata.balance_check_skipped,
+        source: order.metadata.source.as_ref().map(order_source_into),
     };
 
     database::orders::insert_order(ex, &order)
@@ -207,6 +453,9 @@ async fn insert_quote(
         sell_amount: u256_to_big_decimal(&quote.sell_amount),
         buy_amount: u256_to_big_decimal(&quote.buy_amount),
         solver: ByteArray(quote.data.solver.0),
+        quote_id: quote.id,
+        expiration_timestamp: Some(quote.data.expiration),
+        verified: quote.data.verified,
     };
     database::orders::insert_quote(ex, &quote)
         .await
@@ -214,6 +463,59 @@ async fn insert_quote(
     Ok(())
 }
 
+/// Inserts an order together with its quote and full app data.
+async fn insert_full_order(
+    order: &Order,
+    quote: Option<Quote>,
+    ex: &mut PgConnection,
+) -> Result<(), InsertionError> {
+    insert_order(order, ex).await?;
+    if let Some(quote) = quote {
+        insert_quote(&order.metadata.uid, &quote, ex).await?;
+    }
+    if let Some(full_app_data) = &order.metadata.full_app_data {
+        let contract_app_data = &ByteArray(order.data.app_data.0);
+        let app_code = super::app_data::app_code(full_app_data);
+        let full_app_data = full_app_data.as_bytes();
+        if let Some(existing) =
+            database::app_data::insert(ex, contract_app_data, full_app_data, app_code.as_deref())
+                .await?
+        {
+            if full_app_data != existing {
+                return Err(InsertionError::AppDataMismatch(existing));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Cancels the old order at the creation date of the new order, inserts the
+/// new order and links both of them.
+async fn replace_order(
+    old_order: &OrderUid,
+    new_order: &Order,
+    new_quote: Option<Quote>,
+    ex: &mut PgConnection,
+) -> Result<(), InsertionError> {
+    database::orders::cancel_order(
+        ex,
+        &ByteArray(old_order.0),
+        new_order.metadata.creation_date,
+    )
+    .await?;
+    insert_order(new_order, ex).await?;
+    database::orders::insert_replacement(
+        ex,
+        &ByteArray(old_order.0),
+        &ByteArray(new_order.metadata.uid.0),
+    )
+    .await?;
+    if let Some(quote) = new_quote {
+        insert_quote(&new_order.metadata.uid, &quote, ex).await?;
+    }
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl OrderStoring for Postgres {
     async fn insert_order(
@@ -226,55 +528,151 @@ impl OrderStoring for Postgres {
             .with_label_values(&["insert_order"])
             .start_timer();
 
-        let order = order.clone();
         let mut connection = self.pool.acquire().await?;
         let mut ex = connection.begin().await?;
+        insert_full_order(order, quote, &mut ex).await?;
+        ex.commit().await?;
+        Ok(())
+    }
 
-        insert_order(&order, &mut ex).await?;
-        if let Some(quote) = quote {
-            insert_quote(&order.metadata.uid, &quote, &mut ex).await?;
-        }
-        if let Some(full_app_data) = order.metadata.full_app_data {
-            let contract_app_data = &ByteArray(order.data.app_data.0);
-            let full_app_data = full_app_data.as_bytes();
-            if let Some(existing) =
-                database::app_data::insert(&mut ex, contract_app_data, full_app_data).await?
-            {
-                if full_app_data != existing {
-                    return Err(InsertionError::AppDataMismatch(existing));
-                }
+    async fn insert_orders(
+        &self,
+        orders: Vec<(Order, Option<Quote>)>,
+    ) -> Result<Vec<Result<(), InsertionError>>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_orders"])
+            .start_timer();
+
+        let mut ex = self.pool.begin().await?;
+        let mut results = Vec::with_capacity(orders.len());
+        for (order, quote) in orders {
+            // Every order gets its own savepoint so a failed insertion only
+            // rolls back that order instead of aborting the whole transaction.
+            let mut savepoint = ex.begin().await?;
+            let result = insert_full_order(&order, quote, &mut savepoint).await;
+            if result.is_ok() {
+                savepoint.commit().await?;
+            } else {
+                savepoint.rollback().await?;
             }
+            results.push(result);
         }
-
-        ex.commit().await?;
-        Ok(())
+        ex.commit().await.context("commit insert multiple orders")?;
+        Ok(results)
     }
 
-    async fn cancel_orders(&self, order_uids: Vec<OrderUid>, now: DateTime<Utc>) -> Result<()> {
+    async fn cancel_orders(
+        &self,
+        order_uids: Vec<OrderUid>,
+        cancellation_hash: [u8; 32],
+        proof: &CancellationProof,
+    ) -> Result<(), CancellationError> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["cancel_orders"])
             .start_timer();
 
-        let mut connection = self.pool.begin().await?;
+        let mut connection = self.pool.begin().await.context("begin transaction")?;
+        lock_cancellable_orders(&mut connection, &order_uids).await?;
         for order_uid in order_uids {
-            cancel_order(&mut connection, &order_uid, now).await?;
+            let applied = database::orders::insert_cancellation(
+                &mut connection,
+                &ByteArray(cancellation_hash),
+                &ByteArray(order_uid.0),
+                proof.timestamp,
+            )
+            .await
+            .context("insert cancellation")?;
+            if !applied {
+                return Err(CancellationError::AlreadyCancelled(order_uid));
+            }
+            cancel_order_with_proof(&mut connection, &order_uid, proof).await?;
         }
         connection
             .commit()
             .await
-            .context("commit cancel multiple orders")
+            .context("commit cancel multiple orders")?;
+        Ok(())
     }
 
-    async fn cancel_order(&self, order_uid: &OrderUid, now: DateTime<Utc>) -> Result<()> {
+    async fn cancel_order(
+        &self,
+        order_uid: &OrderUid,
+        cancellation_hash: [u8; 32],
+        proof: &CancellationProof,
+    ) -> Result<(), CancellationError> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["cancel_order"])
             .start_timer();
 
+        let mut ex = self.pool.begin().await.context("begin transaction")?;
+        lock_cancellable_orders(&mut ex, &[*order_uid]).await?;
+        let applied = database::orders::insert_cancellation(
+            &mut ex,
+            &ByteArray(cancellation_hash),
+            &ByteArray(order_uid.0),
+            proof.timestamp,
+        )
+        .await
+        .context("insert cancellation")?;
+        if !applied {
+            return Err(CancellationError::AlreadyCancelled(*order_uid));
+        }
+        cancel_order_with_proof(&mut ex, order_uid, proof).await?;
+        ex.commit().await.context("commit cancel single order")?;
+        Ok(())
+    }
+
+    async fn cancel_all_orders(
+        &self,
+        owner: &H160,
+        created_before: DateTime<Utc>,
+        proof: &CancellationProof,
+    ) -> Result<Vec<Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["cancel_all_orders"])
+            .start_timer();
+
         let mut ex = self.pool.begin().await?;
-        cancel_order(&mut ex, order_uid, now).await?;
-        ex.commit().await.context("commit cancel single order")
+        let candidates: Vec<_> = database::orders::cancellable_orders_by_owner(
+            &mut ex,
+            &ByteArray(owner.0),
+            now_in_epoch_seconds().into(),
+            created_before,
+        )
+        .map_ok(|order| order.uid)
+        .try_collect()
+        .await?;
+        // The orders can get executed until they are locked so their status
+        // gets checked again afterwards. Locking them in the same order as
+        // trades get indexed avoids deadlocks.
+        database::orders::lock_orders(&mut ex, &candidates)
+            .await
+            .context("lock orders")?;
+        let mut orders = Vec::new();
+        for uid in &candidates {
+            let Some(order) = database::orders::single_full_order(&mut ex, uid)
+                .await
+                .context("load locked order")?
+            else {
+                continue;
+            };
+            if matches!(
+                calculate_status(&order),
+                OrderStatus::Fulfilled | OrderStatus::Cancelled
+            ) {
+                continue;
+            }
+            orders.push(full_order_into_model_order(order)?);
+        }
+        for order in &orders {
+            cancel_order_with_proof(&mut ex, &order.metadata.uid, proof).await?;
+        }
+        ex.commit().await.context("commit cancel all orders")?;
+        Ok(orders)
     }
 
     async fn replace_order(
@@ -293,24 +691,111 @@ impl OrderStoring for Postgres {
         let mut connection = self.pool.acquire().await?;
         connection
             .transaction(move |ex| {
-                async move {
-                    database::orders::cancel_order(
-                        ex,
-                        &ByteArray(old_order.0),
-                        new_order.metadata.creation_date,
-                    )
-                    .await?;
-                    insert_order(&new_order, ex).await?;
-                    if let Some(quote) = new_quote {
-                        insert_quote(&new_order.metadata.uid, &quote, ex).await?;
-                    }
-                    Ok(())
-                }
-                .boxed()
+                async move { replace_order(&old_order, &new_order, new_quote, ex).await }.boxed()
             })
             .await
     }
 
+    async fn replace_orders(
+        &self,
+        replacements: Vec<(OrderUid, Order, Option<Quote>)>,
+    ) -> Result<Result<(), (usize, InsertionError)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["replace_orders"])
+            .start_timer();
+
+        let mut ex = self.pool.begin().await?;
+        for (i, (old_order, new_order, new_quote)) in replacements.into_iter().enumerate() {
+            if let Err(err) = replace_order(&old_order, &new_order, new_quote, &mut ex).await {
+                ex.rollback().await?;
+                return Ok(Err((i, err)));
+            }
+        }
+        ex.commit()
+            .await
+            .context("commit replace multiple orders")?;
+        Ok(Ok(()))
+    }
+
+    async fn count_open_orders(&self, owner: &H160) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["count_open_orders_by_owner"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::orders::count_open_orders_by_owner(
+            &mut ex,
+            now_in_epoch_seconds().into(),
+            &ByteArray(owner.0),
+        )
+        .await?
+        .try_into()
+        .context("negative count")
+    }
+
+    async fn count_open_orders_by_pair(&self, min_valid_to: u32) -> Result<Vec<(H160, H160, u64)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["count_open_orders_by_pair"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let counts =
+            database::orders::count_open_orders_by_pair(&mut ex, min_valid_to.into()).await?;
+        counts
+            .into_iter()
+            .map(|(sell_token, buy_token, count)| {
+                Ok((
+                    H160(sell_token.0),
+                    H160(buy_token.0),
+                    count.try_into().context("negative count")?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn count_open_orders_by_app_code(&self, min_valid_to: u32) -> Result<Vec<(String, u64)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["count_open_orders_by_app_code"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let counts =
+            database::orders::count_open_orders_by_app_code(&mut ex, min_valid_to.into()).await?;
+        counts
+            .into_iter()
+            .map(|(app_code, count)| Ok((app_code, count.try_into().context("negative count")?)))
+            .collect()
+    }
+
+    async fn count_orders_by_app_code(&self, since: DateTime<Utc>) -> Result<Vec<(String, u64)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["count_orders_by_app_code"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let counts = database::orders::count_orders_by_app_code(&mut ex, since).await?;
+        counts
+            .into_iter()
+            .map(|(app_code, count)| Ok((app_code, count.try_into().context("negative count")?)))
+            .collect()
+    }
+
+    async fn replacement_chain(&self, uid: &OrderUid) -> Result<Vec<OrderUid>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["replacement_chain"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let chain = database::orders::replacement_chain(&mut ex, &ByteArray(uid.0)).await?;
+        Ok(chain.into_iter().map(|uid| OrderUid(uid.0)).collect())
+    }
+
     async fn single_order(&self, uid: &OrderUid) -> Result<Option<Order>> {
         let _timer = super::Metrics::get()
             .database_queries
@@ -322,6 +807,121 @@ impl OrderStoring for Postgres {
         order.map(full_order_into_model_order).transpose()
     }
 
+    async fn orders_by_uids(&self, uids: &[OrderUid]) -> Result<HashMap<OrderUid, Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["orders_by_uids"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let uids = uids.iter().map(|uid| ByteArray(uid.0)).collect::<Vec<_>>();
+        database::orders::many_full_orders(&mut ex, &uids)
+            .map(|result| -> Result<_> {
+                let order = full_order_into_model_order(result?)?;
+                Ok((order.metadata.uid, order))
+            })
+            .try_collect()
+            .await
+    }
+
+    async fn was_presigned(&self, uid: &OrderUid) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["was_presigned"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::orders::was_presigned(&mut ex, &ByteArray(uid.0)).await?)
+    }
+
+    async fn get_cancellation_proof(&self, uid: &OrderUid) -> Result<Option<CancellationProof>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["get_cancellation_proof"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::orders::read_cancellation_proof(&mut ex, &ByteArray(uid.0))
+            .await?
+            .map(|proof| {
+                let signer = H160(proof.signer.0);
+                Ok(CancellationProof {
+                    signature: CancellationSignature::from_bytes(
+                        signing_scheme_from(proof.signing_scheme),
+                        proof.signature,
+                        signer,
+                    )?,
+                    signer,
+                    timestamp: proof.timestamp,
+                    message: proof
+                        .message
+                        .map(|message| serde_json::from_slice(&message))
+                        .transpose()
+                        .context("deserialize cancellation message")?,
+                    domain_separator: proof.domain_separator.map(|domain| H256(domain.0)),
+                })
+            })
+            .transpose()
+    }
+
+    async fn amend_order(
+        &self,
+        order_uid: &OrderUid,
+        amounts: AmendedAmounts,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["amend_order"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::orders::amend_order(
+            &mut ex,
+            &ByteArray(order_uid.0),
+            &u256_to_big_decimal(&amounts.sell_amount),
+            &u256_to_big_decimal(&amounts.buy_amount),
+            now,
+        )
+        .await?)
+    }
+
+    async fn order_quote(&self, uid: &OrderUid) -> Result<Option<Quote>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_quote"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let quote = database::orders::read_quote(&mut ex, &ByteArray(uid.0)).await?;
+        quote
+            .map(|quote| {
+                let fee_parameters = FeeParameters {
+                    gas_amount: quote.gas_amount,
+                    gas_price: quote.gas_price,
+                    sell_token_price: quote.sell_token_price,
+                };
+                Ok(Quote {
+                    id: quote.quote_id,
+                    data: QuoteData {
+                        fee_parameters,
+                        solver: H160(quote.solver.0),
+                        verified: quote.verified,
+                        // Orders created before quote expirations got stored
+                        // have none.
+                        expiration: quote.expiration_timestamp.unwrap_or_default(),
+                        ..Default::default()
+                    },
+                    sell_amount: big_decimal_to_u256(&quote.sell_amount)
+                        .context("quote sell amount is not a valid u256")?,
+                    buy_amount: big_decimal_to_u256(&quote.buy_amount)
+                        .context("quote buy amount is not a valid u256")?,
+                    fee_amount: fee_parameters.fee(),
+                })
+            })
+            .transpose()
+    }
+
     async fn orders_for_tx(&self, tx_hash: &H256) -> Result<Vec<Order>> {
         let _timer = super::Metrics::get()
             .database_queries
@@ -338,9 +938,23 @@ impl OrderStoring for Postgres {
             .await
     }
 
+    async fn settlement_tx_hash(&self, sender: &H160, nonce: i64) -> Result<Option<H256>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settlement_tx_hash"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let settlement =
+            database::settlements::settlement_by_sender_nonce(&mut ex, &ByteArray(sender.0), nonce)
+                .await?;
+        Ok(settlement.map(|settlement| H256(settlement.tx_hash.0)))
+    }
+
     async fn user_orders(
         &self,
         owner: &H160,
+        filter: &UserOrderFilter,
         offset: u64,
         limit: Option<u64>,
     ) -> Result<Vec<Order>> {
@@ -350,9 +964,11 @@ impl OrderStoring for Postgres {
             .start_timer();
 
         let mut ex = self.pool.acquire().await?;
-        database::orders::user_orders(
+        let filter = filter.into();
+        database::orders::user_orders_with_filter(
             &mut ex,
             &ByteArray(owner.0),
+            &filter,
             offset as i64,
             limit.map(|l| l as i64),
         )
@@ -363,45 +979,190 @@ impl OrderStoring for Postgres {
         .try_collect()
         .await
     }
-}
 
-#[async_trait]
-impl LimitOrderCounting for Postgres {
-    async fn count(&self, owner: H160) -> Result<u64> {
+    async fn user_orders_with_count(
+        &self,
+        owner: &H160,
+        filter: &UserOrderFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(Vec<Order>, u64)> {
         let _timer = super::Metrics::get()
             .database_queries
-            .with_label_values(&["count_limit_orders_by_owner"])
+            .with_label_values(&["user_orders_with_count"])
             .start_timer();
 
         let mut ex = self.pool.acquire().await?;
-        Ok(database::orders::count_limit_orders_by_owner(
+        let owner = ByteArray(owner.0);
+        let filter = filter.into();
+        let rows: Vec<_> = database::orders::user_orders_with_count(
             &mut ex,
-            now_in_epoch_seconds().into(),
-            &ByteArray(owner.0),
+            &owner,
+            &filter,
+            offset.try_into().context("offset too large")?,
+            Some(limit.try_into().context("limit too large")?),
         )
-        .await?
-        .try_into()
-        .unwrap())
-    }
-}
-
-fn calculate_status(order: &FullOrder) -> OrderStatus {
-    match order.kind {
-        DbOrderKind::Buy => {
-            if is_buy_order_filled(&order.buy_amount, &order.sum_buy) {
-                return OrderStatus::Fulfilled;
-            }
-        }
-        DbOrderKind::Sell => {
-            if is_sell_order_filled(&order.sell_amount, &order.sum_sell, &order.sum_fee) {
-                return OrderStatus::Fulfilled;
+        .try_collect()
+        .await?;
+        let total_count = match rows.first() {
+            Some(row) => row.total_count,
+            // Pages past the last order don't carry the count so it has to be
+            // read from the first page. This only happens for clients that
+            // request a page that doesn't exist.
+            None if offset > 0 => {
+                database::orders::user_orders_with_count(&mut ex, &owner, &filter, 0, Some(1))
+                    .try_next()
+                    .await?
+                    .map_or(0, |row| row.total_count)
             }
-        }
-    }
-    if order.invalidated {
-        return OrderStatus::Cancelled;
+            None => 0,
+        };
+        let orders = rows
+            .into_iter()
+            .map(|row| full_order_into_model_order(row.order))
+            .collect::<Result<_>>()?;
+        Ok((orders, total_count.try_into().context("negative count")?))
     }
-    if order.valid_to() < Utc::now().timestamp() {
+
+    async fn user_orders_before(
+        &self,
+        owner: &H160,
+        before: Option<(DateTime<Utc>, OrderUid)>,
+        limit: u64,
+    ) -> Result<Vec<Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["user_orders_before"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::orders::user_orders_before(
+            &mut ex,
+            &ByteArray(owner.0),
+            before.map(|(creation_date, uid)| (creation_date, ByteArray(uid.0))),
+            limit.try_into().context("limit too large")?,
+        )
+        .map(|result| match result {
+            Ok(order) => full_order_into_model_order(order),
+            Err(err) => Err(anyhow::Error::from(err)),
+        })
+        .try_collect()
+        .await
+    }
+
+    async fn expiring_orders(
+        &self,
+        owner: &H160,
+        min_valid_to: u32,
+        max_valid_to: u32,
+    ) -> Result<Vec<Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["expiring_orders_by_owner"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::orders::expiring_orders_by_owner(
+            &mut ex,
+            &ByteArray(owner.0),
+            min_valid_to.into(),
+            max_valid_to.into(),
+        )
+        .map(|result| match result {
+            Ok(order) => full_order_into_model_order(order),
+            Err(err) => Err(anyhow::Error::from(err)),
+        })
+        .try_collect()
+        .await
+    }
+
+    async fn sample_open_fill_or_kill_orders(
+        &self,
+        min_valid_to: u32,
+        limit: u64,
+    ) -> Result<Vec<Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["sample_open_fill_or_kill_orders"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::orders::sample_open_fill_or_kill_orders(
+            &mut ex,
+            min_valid_to.into(),
+            limit.try_into().unwrap_or(i64::MAX),
+        )
+        .map(|result| match result {
+            Ok(order) => full_order_into_model_order(order),
+            Err(err) => Err(anyhow::Error::from(err)),
+        })
+        .try_collect()
+        .await
+    }
+
+    async fn set_insufficient_balance(
+        &self,
+        uids: &[OrderUid],
+        insufficient_balance: bool,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["set_insufficient_balance"])
+            .start_timer();
+
+        let uids = uids.iter().map(|uid| ByteArray(uid.0)).collect::<Vec<_>>();
+        let mut ex = self.pool.acquire().await?;
+        database::orders::set_insufficient_balance(&mut ex, &uids, insufficient_balance).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LimitOrderCounting for Postgres {
+    async fn count(&self, owner: H160) -> Result<u64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["count_limit_orders_by_owner"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::orders::count_limit_orders_by_owner(
+            &mut ex,
+            now_in_epoch_seconds().into(),
+            &ByteArray(owner.0),
+        )
+        .await?
+        .try_into()
+        .unwrap())
+    }
+}
+
+fn calculate_status(order: &FullOrder) -> OrderStatus {
+    // Amended orders are fulfilled once they executed their reduced amounts.
+    match order.kind {
+        DbOrderKind::Buy => {
+            let amount = order
+                .amended_buy_amount
+                .as_ref()
+                .unwrap_or(&order.buy_amount);
+            if is_buy_order_filled(amount, &order.sum_buy) {
+                return OrderStatus::Fulfilled;
+            }
+        }
+        DbOrderKind::Sell => {
+            let amount = order
+                .amended_sell_amount
+                .as_ref()
+                .unwrap_or(&order.sell_amount);
+            if is_sell_order_filled(amount, &order.sum_sell, &order.sum_fee) {
+                return OrderStatus::Fulfilled;
+            }
+        }
+    }
+    if order.invalidated {
+        return OrderStatus::Cancelled;
+    }
+    if order.valid_to() < Utc::now().timestamp() {
         return OrderStatus::Expired;
     }
     if order.presignature_pending {
@@ -429,6 +1190,15 @@ fn full_order_into_model_order(order: FullOrder) -> Result<Order> {
         sender: onchain_user,
         placement_error: onchain_placement_error,
     });
+    let amended_amounts = match (&order.amended_sell_amount, &order.amended_buy_amount) {
+        (Some(sell_amount), Some(buy_amount)) => Some(AmendedAmounts {
+            sell_amount: big_decimal_to_u256(sell_amount)
+                .context("amended sell amount is not U256")?,
+            buy_amount: big_decimal_to_u256(buy_amount)
+                .context("amended buy amount is not U256")?,
+        }),
+        _ => None,
+    };
     let metadata = OrderMetadata {
         creation_date: order.creation_timestamp,
         owner: H160(order.owner.0),
@@ -467,6 +1237,14 @@ fn full_order_into_model_order(order: FullOrder) -> Result<Order> {
             .map(String::from_utf8)
             .transpose()
             .context("full app data isn't utf-8")?,
+        amended_amounts,
+        replaces: order.replaces.map(|uid| OrderUid(uid.0)),
+        replaced_by: order.replaced_by.map(|uid| OrderUid(uid.0)),
+        quote_verified: order.quote_verified,
+        balance_check_skipped: order.balance_check_skipped,
+        settlement_tx_hashes: None,
+        source: order.source.map(order_source_from).transpose()?,
+        insufficient_balance: order.insufficient_balance,
     };
     let data = OrderData {
         sell_token: H160(order.sell_token.0),
@@ -504,18 +1282,18 @@ fn is_sell_order_filled(
         return false;
     }
     let total_amount = executed_amount - executed_fee;
-    total_amount == *amount
+    total_amount >= *amount
 }
 
 fn is_buy_order_filled(amount: &BigDecimal, executed_amount: &BigDecimal) -> bool {
-    !executed_amount.is_zero() && *amount == *executed_amount
+    !executed_amount.is_zero() && *executed_amount >= *amount
 }
 
 #[cfg(test)]
 mod tests {
     use {
         super::*,
-        chrono::Duration,
+        chrono::{Duration, TimeZone},
         database::{
             byte_array::ByteArray,
             orders::{
@@ -529,12 +1307,37 @@ mod tests {
         },
         model::{
             interaction::InteractionData,
-            order::{Order, OrderData, OrderMetadata, OrderStatus, OrderUid},
-            signature::{Signature, SigningScheme},
+            order::{
+                CancelAll,
+                CancellationMessage,
+                Order,
+                OrderData,
+                OrderMetadata,
+                OrderSource,
+                OrderStatus,
+                OrderUid,
+            },
+            signature::{EcdsaSigningScheme, Signature, SigningScheme},
         },
         std::sync::atomic::{AtomicI64, Ordering},
     };
 
+    fn cancellation_proof(timestamp: DateTime<Utc>) -> CancellationProof {
+        CancellationProof {
+            signature: CancellationSignature::Ecdsa {
+                signature: Default::default(),
+                signing_scheme: EcdsaSigningScheme::Eip712,
+            },
+            signer: H160([1; 20]),
+            timestamp,
+            message: Some(CancellationMessage::CancelAll(CancelAll {
+                owner: H160([1; 20]),
+                timestamp: timestamp.timestamp() as u64,
+            })),
+            domain_separator: Some(H256([2; 32])),
+        }
+    }
+
     #[test]
     fn order_status() {
         let valid_to_timestamp = Utc::now() + Duration::days(1);
@@ -572,6 +1375,14 @@ mod tests {
             onchain_placement_error: None,
             executed_surplus_fee: Default::default(),
             full_app_data: Default::default(),
+            amended_sell_amount: None,
+            amended_buy_amount: None,
+            replaces: None,
+            replaced_by: None,
+            quote_verified: false,
+            balance_check_skipped: false,
+            source: None,
+            insufficient_balance: false,
         };
 
         // Open - sell (filled - 0%)
@@ -748,6 +1559,62 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_count_orders_by_app_code() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |uid: u8, app_data: AppDataHash, full_app_data: Option<&str>| Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                app_data,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                full_app_data: full_app_data.map(str::to_owned),
+                creation_date: Utc::now(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // The app code gets extracted from app data registered with an order
+        // as well as from app data registered on its own.
+        let a = AppDataHash([1; 32]);
+        let b = AppDataHash([2; 32]);
+        db.insert_full_app_data(&b, r#"{"appCode": "b"}"#)
+            .await
+            .unwrap();
+        for order in [
+            order(1, a, Some(r#"{"appCode": "a"}"#)),
+            order(2, a, None),
+            order(3, b, None),
+            order(4, AppDataHash([3; 32]), Some("{}")),
+        ] {
+            db.insert_order(&order, None).await.unwrap();
+        }
+
+        let expected = vec![("a".to_string(), 2), ("b".to_string(), 1)];
+        assert_eq!(
+            db.count_open_orders_by_app_code(now_in_epoch_seconds())
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            db.count_orders_by_app_code(Utc::now() - Duration::minutes(1))
+                .await
+                .unwrap(),
+            expected
+        );
+        assert!(db
+            .count_orders_by_app_code(Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_replace_order() {
@@ -788,7 +1655,7 @@ mod tests {
             .unwrap();
 
         let order_statuses = db
-            .user_orders(&owner, 0, None)
+            .user_orders(&owner, &Default::default(), 0, None)
             .await
             .unwrap()
             .iter()
@@ -814,6 +1681,85 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_order_chain() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |byte: u8| Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order(1), None).await.unwrap();
+        db.replace_order(&OrderUid([1; 56]), &order(2), None)
+            .await
+            .unwrap();
+        db.replace_order(&OrderUid([2; 56]), &order(3), None)
+            .await
+            .unwrap();
+
+        let chain = vec![OrderUid([1; 56]), OrderUid([2; 56]), OrderUid([3; 56])];
+        for uid in &chain {
+            assert_eq!(db.replacement_chain(uid).await.unwrap(), chain);
+        }
+
+        let middle = db.single_order(&OrderUid([2; 56])).await.unwrap().unwrap();
+        assert_eq!(middle.metadata.replaces, Some(OrderUid([1; 56])));
+        assert_eq!(middle.metadata.replaced_by, Some(OrderUid([3; 56])));
+        let latest = db.single_order(&OrderUid([3; 56])).await.unwrap().unwrap();
+        assert_eq!(latest.metadata.replaces, Some(OrderUid([2; 56])));
+        assert_eq!(latest.metadata.replaced_by, None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_count_open_orders() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        let order = |byte: u8, owner: H160, valid_to: u32| Order {
+            data: OrderData {
+                valid_to,
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                owner,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order(1, owner, u32::MAX), None)
+            .await
+            .unwrap();
+        db.insert_order(&order(2, owner, u32::MAX), None)
+            .await
+            .unwrap();
+        db.insert_order(&order(3, owner, 0), None).await.unwrap();
+        db.insert_order(&order(4, H160([2; 20]), u32::MAX), None)
+            .await
+            .unwrap();
+        assert_eq!(db.count_open_orders(&owner).await.unwrap(), 2);
+
+        // Replacing an order doesn't count twice because the replaced order
+        // gets cancelled.
+        db.replace_order(&OrderUid([1; 56]), &order(5, owner, u32::MAX), None)
+            .await
+            .unwrap();
+        assert_eq!(db.count_open_orders(&owner).await.unwrap(), 2);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_replace_order_no_cancellation_on_error() {
@@ -860,6 +1806,137 @@ mod tests {
         assert_eq!(old_order_cancellation, None);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_insert_orders_continues_after_failed_insertion() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |uid| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order(1), None).await.unwrap();
+
+        let results = db
+            .insert_orders(vec![
+                (order(2), None),
+                (order(1), None),
+                (order(2), None),
+                (order(3), None),
+            ])
+            .await
+            .unwrap();
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(InsertionError::DuplicatedRecord)));
+        assert!(matches!(results[2], Err(InsertionError::DuplicatedRecord)));
+        assert!(results[3].is_ok());
+
+        for uid in [1, 2, 3] {
+            let uid = OrderUid([uid; 56]);
+            assert!(db.single_order(&uid).await.unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_all_orders() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        let now = Utc::now();
+        let order = |uid, owner, signature, valid_to, creation_date| Order {
+            data: OrderData {
+                valid_to,
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                owner,
+                creation_date,
+                ..Default::default()
+            },
+            signature,
+            ..Default::default()
+        };
+        let eip712 = Signature::default_with(SigningScheme::Eip712);
+        let open = order(1, owner, eip712.clone(), u32::MAX, now - Duration::hours(1));
+        let other_owner = order(
+            2,
+            H160([2; 20]),
+            eip712.clone(),
+            u32::MAX,
+            now - Duration::hours(1),
+        );
+        let presign_pending = order(
+            3,
+            owner,
+            Signature::PreSign,
+            u32::MAX,
+            now - Duration::hours(1),
+        );
+        let expired = order(4, owner, eip712.clone(), 0, now - Duration::hours(1));
+        let created_later = order(5, owner, eip712.clone(), u32::MAX, now + Duration::hours(1));
+        let already_cancelled = order(6, owner, eip712, u32::MAX, now - Duration::hours(1));
+        for order in [
+            &open,
+            &other_owner,
+            &presign_pending,
+            &expired,
+            &created_later,
+            &already_cancelled,
+        ] {
+            db.insert_order(order, None).await.unwrap();
+        }
+        db.cancel_order(
+            &already_cancelled.metadata.uid,
+            Default::default(),
+            &cancellation_proof(now),
+        )
+        .await
+        .unwrap();
+
+        let proof = cancellation_proof(now);
+        let cancelled = db.cancel_all_orders(&owner, now, &proof).await.unwrap();
+        assert_eq!(
+            cancelled
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>(),
+            vec![open.metadata.uid],
+        );
+        assert_eq!(
+            db.get_cancellation_proof(&open.metadata.uid).await.unwrap(),
+            Some(proof)
+        );
+
+        let status = |uid| {
+            let db = &db;
+            async move {
+                db.single_order(&uid)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .metadata
+                    .status
+            }
+        };
+        assert_eq!(status(open.metadata.uid).await, OrderStatus::Cancelled);
+        assert_eq!(status(other_owner.metadata.uid).await, OrderStatus::Open);
+        assert_eq!(
+            status(presign_pending.metadata.uid).await,
+            OrderStatus::PresignaturePending
+        );
+        assert_eq!(status(expired.metadata.uid).await, OrderStatus::Expired);
+        assert_eq!(status(created_later.metadata.uid).await, OrderStatus::Open);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_presignature_status() {
@@ -973,13 +2050,398 @@ mod tests {
         assert_eq!(order_status(2).await, OrderStatus::Open);
         assert_eq!(order_status(3).await, OrderStatus::Open);
 
-        db.cancel_orders(vec![uid(1), uid(2)], Utc::now())
+        let proof = cancellation_proof(Utc.timestamp_opt(1234567890, 0).unwrap());
+        db.cancel_orders(vec![uid(1), uid(2)], [1; 32], &proof)
             .await
             .unwrap();
 
         assert_eq!(order_status(1).await, OrderStatus::Cancelled);
         assert_eq!(order_status(2).await, OrderStatus::Cancelled);
         assert_eq!(order_status(3).await, OrderStatus::Open);
+
+        // Every order of the batch references the same signature.
+        assert_eq!(
+            db.get_cancellation_proof(&uid(1)).await.unwrap(),
+            Some(proof.clone())
+        );
+        assert_eq!(
+            db.get_cancellation_proof(&uid(2)).await.unwrap(),
+            Some(proof)
+        );
+        assert_eq!(db.get_cancellation_proof(&uid(3)).await.unwrap(), None);
+    }
+
+    /// Starts indexing a settlement that fully executes the order. The
+    /// settlement is only visible to others once the transaction commits.
+    async fn start_settlement(db: &Postgres, uid: OrderUid) -> database::PgTransaction<'static> {
+        let mut settlement = db.pool.begin().await.unwrap();
+        database::events::append(
+            &mut settlement,
+            &[(
+                Default::default(),
+                database::events::Event::Trade(database::events::Trade {
+                    order_uid: ByteArray(uid.0),
+                    sell_amount_including_fee: 1.into(),
+                    buy_amount: 1.into(),
+                    fee_amount: 0.into(),
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+        settlement
+    }
+
+    fn executable_order(uid: OrderUid) -> Order {
+        Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_orders_races_with_settlement() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let (open, settled) = (OrderUid([1; 56]), OrderUid([2; 56]));
+        db.insert_order(&executable_order(open), None)
+            .await
+            .unwrap();
+        db.insert_order(&executable_order(settled), None)
+            .await
+            .unwrap();
+
+        let settlement = start_settlement(&db, settled).await;
+        let cancellation = tokio::spawn({
+            let db = db.clone();
+            async move {
+                db.cancel_orders(
+                    vec![open, settled],
+                    [1; 32],
+                    &cancellation_proof(Utc::now()),
+                )
+                .await
+            }
+        });
+
+        // The cancellation waits for the settlement to be indexed and then
+        // notices that one of the orders got executed.
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!cancellation.is_finished());
+        settlement.commit().await.unwrap();
+        assert!(matches!(
+            cancellation.await.unwrap(),
+            Err(CancellationError::FullyExecuted(uid)) if uid == settled
+        ));
+
+        // No order of the batch got cancelled.
+        let status = |uid| {
+            let db = &db;
+            async move {
+                db.single_order(&uid)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .metadata
+                    .status
+            }
+        };
+        assert_eq!(status(open).await, OrderStatus::Open);
+        assert_eq!(status(settled).await, OrderStatus::Fulfilled);
+        assert_eq!(db.get_cancellation_proof(&open).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_order_races_with_settlement() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let uid = OrderUid([1; 56]);
+        db.insert_order(&executable_order(uid), None).await.unwrap();
+
+        let settlement = start_settlement(&db, uid).await;
+        let cancellation = tokio::spawn({
+            let db = db.clone();
+            async move {
+                db.cancel_order(&uid, [1; 32], &cancellation_proof(Utc::now()))
+                    .await
+            }
+        });
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!cancellation.is_finished());
+        settlement.commit().await.unwrap();
+        assert!(matches!(
+            cancellation.await.unwrap(),
+            Err(CancellationError::FullyExecuted(_))
+        ));
+        let order = db.single_order(&uid).await.unwrap().unwrap();
+        assert_eq!(order.metadata.status, OrderStatus::Fulfilled);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_all_orders_races_with_settlement() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        let order = |uid| {
+            let mut order = executable_order(uid);
+            order.metadata.owner = owner;
+            order.signature = Signature::default_with(SigningScheme::Eip712);
+            order
+        };
+        let (open, settled) = (OrderUid([1; 56]), OrderUid([2; 56]));
+        db.insert_order(&order(open), None).await.unwrap();
+        db.insert_order(&order(settled), None).await.unwrap();
+
+        let settlement = start_settlement(&db, settled).await;
+        let cancellation = tokio::spawn({
+            let db = db.clone();
+            async move {
+                let now = Utc::now();
+                db.cancel_all_orders(&owner, now, &cancellation_proof(now))
+                    .await
+            }
+        });
+
+        // The cancellation waits for the settlement to be indexed and then
+        // only cancels the order that didn't get executed.
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!cancellation.is_finished());
+        settlement.commit().await.unwrap();
+        assert_eq!(
+            cancellation
+                .await
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>(),
+            vec![open],
+        );
+
+        let status = |uid| {
+            let db = &db;
+            async move {
+                db.single_order(&uid)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .metadata
+                    .status
+            }
+        };
+        assert_eq!(status(open).await, OrderStatus::Cancelled);
+        assert_eq!(status(settled).await, OrderStatus::Fulfilled);
+        assert_eq!(db.get_cancellation_proof(&settled).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_order_stores_proof() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None).await.unwrap();
+        let uid = order.metadata.uid;
+
+        let proof = CancellationProof {
+            signature: CancellationSignature::Eip1271 {
+                owner: H160([2; 20]),
+                signature: vec![1, 2, 3],
+            },
+            signer: H160([2; 20]),
+            timestamp: Utc.timestamp_opt(1234567890, 0).unwrap(),
+            message: None,
+            domain_separator: None,
+        };
+        db.cancel_order(&uid, [1; 32], &proof).await.unwrap();
+        assert_eq!(db.get_cancellation_proof(&uid).await.unwrap(), Some(proof));
+
+        // A replayed cancellation neither cancels again nor stores a proof.
+        let replayed = cancellation_proof(Utc::now());
+        assert!(matches!(
+            db.cancel_order(&uid, [1; 32], &replayed).await,
+            Err(CancellationError::AlreadyCancelled(_))
+        ));
+        assert_ne!(
+            db.get_cancellation_proof(&uid).await.unwrap(),
+            Some(replayed)
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_quote() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quote = Quote {
+            id: Some(5),
+            data: QuoteData {
+                fee_parameters: FeeParameters {
+                    gas_amount: 100.,
+                    gas_price: 2.,
+                    sell_token_price: 0.5,
+                },
+                solver: H160([3; 20]),
+                expiration: Utc.timestamp_opt(1234567890, 0).unwrap(),
+                ..Default::default()
+            },
+            sell_amount: 10.into(),
+            buy_amount: 20.into(),
+            ..Default::default()
+        };
+        db.insert_order(&order(1), Some(quote)).await.unwrap();
+        db.insert_order(&order(2), None).await.unwrap();
+
+        assert_eq!(
+            db.order_quote(&OrderUid([1; 56])).await.unwrap(),
+            Some(Quote {
+                id: Some(5),
+                data: QuoteData {
+                    fee_parameters: FeeParameters {
+                        gas_amount: 100.,
+                        gas_price: 2.,
+                        sell_token_price: 0.5,
+                    },
+                    solver: H160([3; 20]),
+                    expiration: Utc.timestamp_opt(1234567890, 0).unwrap(),
+                    ..Default::default()
+                },
+                sell_amount: 10.into(),
+                buy_amount: 20.into(),
+                fee_amount: 400.into(),
+            })
+        );
+        assert_eq!(db.order_quote(&OrderUid([2; 56])).await.unwrap(), None);
+        assert_eq!(db.order_quote(&OrderUid([3; 56])).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_quote_verified_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quote = |verified: bool| Quote {
+            data: QuoteData {
+                verified,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order(1), Some(quote(true))).await.unwrap();
+        db.insert_order(&order(2), Some(quote(false)))
+            .await
+            .unwrap();
+        db.insert_order(&order(3), None).await.unwrap();
+
+        for (byte, verified) in [(1, true), (2, false), (3, false)] {
+            let uid = OrderUid([byte; 56]);
+            let order = db.single_order(&uid).await.unwrap().unwrap();
+            assert_eq!(order.metadata.quote_verified, verified);
+            let quote = db.order_quote(&uid).await.unwrap();
+            assert_eq!(
+                quote.map(|quote| quote.data.verified),
+                (byte != 3).then_some(verified)
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_balance_check_skipped_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        for (byte, skipped) in [(1, true), (2, false)] {
+            let order = Order {
+                metadata: OrderMetadata {
+                    uid: OrderUid([byte; 56]),
+                    balance_check_skipped: skipped,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            db.insert_order(&order, None).await.unwrap();
+            let order = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+            assert_eq!(order.metadata.balance_check_skipped, skipped);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_source_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let sources = [
+            Some(OrderSource::Api {
+                client_ip_hash: Some(H256([1; 32])),
+                app_code: Some("CoW Swap".to_string()),
+            }),
+            Some(OrderSource::Api {
+                client_ip_hash: None,
+                app_code: None,
+            }),
+            Some(OrderSource::Onchain {
+                tx_hash: H256([2; 32]),
+            }),
+            None,
+        ];
+        for (byte, source) in sources.into_iter().enumerate() {
+            let order = Order {
+                metadata: OrderMetadata {
+                    uid: OrderUid([byte as u8; 56]),
+                    source: source.clone(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            db.insert_order(&order, None).await.unwrap();
+            let order = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+            assert_eq!(order.metadata.source, source);
+        }
     }
 
     #[tokio::test]
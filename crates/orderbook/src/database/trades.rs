@@ -1,18 +1,58 @@
 use {
     crate::database::Postgres,
     anyhow::{Context, Result},
-    database::{byte_array::ByteArray, trades::TradesQueryRow},
+    chrono::{DateTime, Utc},
+    database::{
+        byte_array::ByteArray,
+        trades::{OrderExecutionRow, TradesQueryRow, UserSurplusRow},
+    },
     ethcontract::H160,
     futures::{stream::TryStreamExt, StreamExt},
     model::{order::OrderUid, trade::Trade},
-    number::conversions::big_decimal_to_big_uint,
-    primitive_types::H256,
+    number::conversions::{big_decimal_to_big_uint, big_decimal_to_u256},
+    primitive_types::{H256, U256},
     std::convert::TryInto,
 };
 
 #[async_trait::async_trait]
 pub trait TradeRetrieving: Send + Sync {
     async fn trades(&self, filter: &TradeFilter) -> Result<Vec<Trade>>;
+    /// All fills of the order ordered by block.
+    async fn order_executions(&self, uid: &OrderUid) -> Result<Vec<OrderExecution>>;
+    /// Total surplus of the owner's orders created in the `[from, to)` window.
+    async fn user_surplus(
+        &self,
+        owner: &H160,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<UserSurplus>;
+}
+
+/// A single (partial) fill of an order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OrderExecution {
+    pub block_number: u64,
+    pub log_index: u64,
+    /// Hash of the settlement transaction. `None` until the hash got
+    /// back-filled for the settlement.
+    pub tx_hash: Option<H256>,
+    /// Executed sell amount including the fee.
+    pub sell_amount: U256,
+    pub buy_amount: U256,
+    /// Fee taken from the signed fee amount of the order.
+    pub fee_amount: U256,
+    /// Surplus fee charged for the fill. `None` until the settlement updater
+    /// processed the settlement.
+    pub surplus_fee: Option<U256>,
+}
+
+/// Surplus received by a user, denominated in the native token.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserSurplus {
+    /// Amount received on top of the limit prices of the user's orders.
+    pub surplus: U256,
+    /// Surplus fees charged by the protocol.
+    pub surplus_fee: U256,
 }
 
 /// Any default value means that this field is unfiltered.
@@ -43,6 +83,63 @@ impl TradeRetrieving for Postgres {
         .try_collect()
         .await
     }
+
+    async fn order_executions(&self, uid: &OrderUid) -> Result<Vec<OrderExecution>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_executions"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::trades::order_executions(&mut ex, &ByteArray(uid.0))
+            .map(|result| match result {
+                Ok(row) => order_execution_from(row),
+                Err(err) => Err(anyhow::Error::from(err)),
+            })
+            .try_collect()
+            .await
+    }
+
+    async fn user_surplus(
+        &self,
+        owner: &H160,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<UserSurplus> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["user_surplus"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let row = database::trades::user_surplus(&mut ex, &ByteArray(owner.0), from, to).await?;
+        user_surplus_from(row)
+    }
+}
+
+fn user_surplus_from(row: UserSurplusRow) -> Result<UserSurplus> {
+    Ok(UserSurplus {
+        surplus: big_decimal_to_u256(&row.surplus).context("surplus is not u256")?,
+        surplus_fee: big_decimal_to_u256(&row.surplus_fee).context("surplus_fee is not u256")?,
+    })
+}
+
+fn order_execution_from(row: OrderExecutionRow) -> Result<OrderExecution> {
+    Ok(OrderExecution {
+        block_number: row
+            .block_number
+            .try_into()
+            .context("block_number is not u64")?,
+        log_index: row.log_index.try_into().context("log_index is not u64")?,
+        tx_hash: row.tx_hash.map(|hash| H256(hash.0)),
+        sell_amount: big_decimal_to_u256(&row.sell_amount).context("sell_amount is not u256")?,
+        buy_amount: big_decimal_to_u256(&row.buy_amount).context("buy_amount is not u256")?,
+        fee_amount: big_decimal_to_u256(&row.fee_amount).context("fee_amount is not u256")?,
+        surplus_fee: row
+            .surplus_fee
+            .map(|fee| big_decimal_to_u256(&fee).context("surplus_fee is not u256"))
+            .transpose()?,
+    })
 }
 
 fn trade_from(row: TradesQueryRow) -> Result<Trade> {
@@ -84,4 +181,14 @@ mod tests {
     fn convert_trade() {
         trade_from(TradesQueryRow::default()).unwrap();
     }
+
+    #[test]
+    fn convert_order_execution() {
+        order_execution_from(OrderExecutionRow::default()).unwrap();
+    }
+
+    #[test]
+    fn convert_user_surplus() {
+        user_surplus_from(UserSurplusRow::default()).unwrap();
+    }
 }
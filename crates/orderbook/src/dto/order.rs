@@ -41,6 +41,9 @@ pub struct Order {
     pub app_data: AppDataHash,
     #[serde(flatten)]
     pub signature: Signature,
+    /// Whether the owner repeatedly didn't have enough sell token balance.
+    #[serde(default)]
+    pub insufficient_balance: bool,
 }
 
 #[serde_as]
@@ -0,0 +1,253 @@
+use {
+    crate::dto::AuctionId,
+    anyhow::Result,
+    std::{sync::Arc, time::Duration},
+    tokio::{sync::Mutex, time::Instant},
+};
+
+/// How long the result of a database probe gets reused. This keeps frequent
+/// health checks from adding load to the database.
+const PROBE_CACHE_DURATION: Duration = Duration::from_secs(5);
+
+/// Readiness information about the orderbook.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Health {
+    /// Whether the last database probe succeeded.
+    pub db_reachable: bool,
+    /// Time since the most recent auction was first observed. This is `None`
+    /// if no auction has been observed yet.
+    pub latest_auction_age: Option<Duration>,
+    /// Time since an order was last inserted successfully. This is `None` if
+    /// no order has been inserted since the service started.
+    pub last_successful_order_insert: Option<Duration>,
+}
+
+/// Database access needed to assess the health of the orderbook.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait HealthProbing: Send + Sync {
+    /// Returns the id of the most recent auction.
+    async fn most_recent_auction_id(&self) -> Result<Option<AuctionId>>;
+}
+
+#[async_trait::async_trait]
+impl HealthProbing for crate::database::Postgres {
+    async fn most_recent_auction_id(&self) -> Result<Option<AuctionId>> {
+        self.most_recent_auction_id().await
+    }
+}
+
+/// Keeps track of the orderbook's health.
+///
+/// Readiness (see [`HealthMonitor::health`]) reflects the last database probe
+/// while liveness (see [`HealthMonitor::is_alive`]) only fails once the
+/// database has been unreachable for longer than the grace period. This
+/// avoids restarting the service because of short database outages.
+pub struct HealthMonitor {
+    probe: Arc<dyn HealthProbing>,
+    grace_period: Duration,
+    state: Mutex<State>,
+    last_order_insert: std::sync::Mutex<Option<Instant>>,
+}
+
+#[derive(Default)]
+struct State {
+    last_probe: Option<Instant>,
+    /// Since when the database has been failing the probe. `None` if the last
+    /// probe succeeded.
+    unreachable_since: Option<Instant>,
+    /// The most recent auction and when it was first observed.
+    latest_auction: Option<(AuctionId, Instant)>,
+}
+
+impl HealthMonitor {
+    pub fn new(probe: Arc<dyn HealthProbing>, grace_period: Duration) -> Self {
+        Self {
+            probe,
+            grace_period,
+            state: Default::default(),
+            last_order_insert: Default::default(),
+        }
+    }
+
+    /// Records that an order got inserted successfully.
+    pub fn on_order_inserted(&self) {
+        *self.last_order_insert.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Returns the current health. The database only gets probed if the
+    /// cached probe result is outdated.
+    pub async fn health(&self) -> Health {
+        let mut state = self.state.lock().await;
+        self.update(&mut state).await;
+        let now = Instant::now();
+        Health {
+            db_reachable: state.unreachable_since.is_none(),
+            latest_auction_age: state
+                .latest_auction
+                .map(|(_, observed)| now.duration_since(observed)),
+            last_successful_order_insert: self
+                .last_order_insert
+                .lock()
+                .unwrap()
+                .map(|inserted| now.duration_since(inserted)),
+        }
+    }
+
+    /// Whether the service should be considered alive. This is only false if
+    /// the database has been unreachable for longer than the grace period.
+    pub async fn is_alive(&self) -> bool {
+        let mut state = self.state.lock().await;
+        self.update(&mut state).await;
+        state
+            .unreachable_since
+            .map_or(true, |since| since.elapsed() <= self.grace_period)
+    }
+
+    async fn update(&self, state: &mut State) {
+        let now = Instant::now();
+        if state
+            .last_probe
+            .is_some_and(|probe| now.duration_since(probe) < PROBE_CACHE_DURATION)
+        {
+            return;
+        }
+        state.last_probe = Some(now);
+
+        match self.probe.most_recent_auction_id().await {
+            Ok(id) => {
+                state.unreachable_since = None;
+                state.latest_auction = match (id, state.latest_auction) {
+                    (Some(id), Some((latest, observed))) if id == latest => Some((id, observed)),
+                    (Some(id), _) => Some((id, now)),
+                    (None, _) => None,
+                };
+            }
+            Err(err) => {
+                tracing::warn!(?err, "database health probe failed");
+                state.unreachable_since.get_or_insert(now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::anyhow, mockall::Sequence};
+
+    const GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+    #[tokio::test(start_paused = true)]
+    async fn liveness_tolerates_short_outages() {
+        let mut probe = MockHealthProbing::new();
+        let mut seq = Sequence::new();
+        probe
+            .expect_most_recent_auction_id()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(Some(1)));
+        probe
+            .expect_most_recent_auction_id()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|| Err(anyhow!("connection refused")));
+        probe
+            .expect_most_recent_auction_id()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(Some(1)));
+        probe
+            .expect_most_recent_auction_id()
+            .in_sequence(&mut seq)
+            .returning(|| Err(anyhow!("connection refused")));
+        let monitor = HealthMonitor::new(Arc::new(probe), GRACE_PERIOD);
+
+        assert!(monitor.is_alive().await);
+        assert!(monitor.health().await.db_reachable);
+
+        // The database becomes unreachable but the service stays alive during
+        // the grace period.
+        tokio::time::advance(PROBE_CACHE_DURATION).await;
+        assert!(!monitor.health().await.db_reachable);
+        assert!(monitor.is_alive().await);
+        tokio::time::advance(GRACE_PERIOD / 2).await;
+        assert!(!monitor.health().await.db_reachable);
+        assert!(monitor.is_alive().await);
+
+        // A single successful probe resets the grace period.
+        tokio::time::advance(GRACE_PERIOD / 2).await;
+        assert!(monitor.health().await.db_reachable);
+        tokio::time::advance(PROBE_CACHE_DURATION).await;
+        assert!(monitor.is_alive().await);
+        tokio::time::advance(GRACE_PERIOD).await;
+        assert!(monitor.is_alive().await);
+
+        // Only an outage longer than the grace period fails liveness.
+        tokio::time::advance(PROBE_CACHE_DURATION).await;
+        assert!(!monitor.is_alive().await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn caches_probe_results() {
+        let mut probe = MockHealthProbing::new();
+        probe
+            .expect_most_recent_auction_id()
+            .times(2)
+            .returning(|| Ok(None));
+        let monitor = HealthMonitor::new(Arc::new(probe), GRACE_PERIOD);
+
+        monitor.health().await;
+        monitor.is_alive().await;
+        tokio::time::advance(PROBE_CACHE_DURATION / 2).await;
+        monitor.health().await;
+        tokio::time::advance(PROBE_CACHE_DURATION / 2).await;
+        monitor.health().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_ages() {
+        let mut probe = MockHealthProbing::new();
+        let mut seq = Sequence::new();
+        probe
+            .expect_most_recent_auction_id()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(Some(1)));
+        probe
+            .expect_most_recent_auction_id()
+            .in_sequence(&mut seq)
+            .returning(|| Ok(Some(2)));
+        let monitor = HealthMonitor::new(Arc::new(probe), GRACE_PERIOD);
+
+        assert_eq!(
+            monitor.health().await,
+            Health {
+                db_reachable: true,
+                latest_auction_age: Some(Duration::ZERO),
+                last_successful_order_insert: None,
+            }
+        );
+
+        monitor.on_order_inserted();
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(
+            monitor.health().await,
+            Health {
+                db_reachable: true,
+                latest_auction_age: Some(Duration::from_secs(10)),
+                last_successful_order_insert: Some(Duration::from_secs(10)),
+            }
+        );
+
+        // A new auction resets the age.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(
+            monitor.health().await,
+            Health {
+                db_reachable: true,
+                latest_auction_age: Some(Duration::ZERO),
+                last_successful_order_insert: Some(Duration::from_secs(20)),
+            }
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    model::order::{Order, OrderUid},
+    shared::account_balances::{BalanceFetching, Query},
+    std::{
+        collections::{hash_map::Entry, HashMap},
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::time::Instant,
+};
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "insufficient_balances")]
+struct Metrics {
+    /// Orders whose insufficient balance flag got changed by its new value.
+    #[metric(labels("insufficient_balance"))]
+    flag_changes: prometheus::IntCounterVec,
+
+    /// Orders that lacked the balance in a single check and get flagged if
+    /// the next check agrees.
+    suspects: prometheus::IntGauge,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Self::instance(observe::metrics::get_storage_registry())
+            .expect("unexpected error getting metrics instance")
+    }
+}
+
+/// Orders whose insufficient balance flag has to change.
+#[derive(Debug, Default, PartialEq)]
+pub struct FlagChanges {
+    /// Orders whose owners repeatedly lacked the balance.
+    pub insufficient: Vec<OrderUid>,
+    /// Flagged orders whose owners have enough balance again.
+    pub sufficient: Vec<OrderUid>,
+}
+
+/// Checks whether the owners of open fill-or-kill orders can still transfer
+/// the sell amount.
+///
+/// Balances fluctuate (e.g. while a user moves funds around) so an order only
+/// gets flagged once it lacked the balance in two consecutive checks. Orders
+/// that don't get checked again in time are forgotten, which bounds the
+/// memory to the recently checked orders.
+pub struct InsufficientBalances {
+    balance_fetcher: Arc<dyn BalanceFetching>,
+    /// How long an order that lacked the balance once is remembered.
+    suspect_ttl: Duration,
+    suspects: HashMap<OrderUid, Instant>,
+}
+
+impl InsufficientBalances {
+    pub fn new(balance_fetcher: Arc<dyn BalanceFetching>, suspect_ttl: Duration) -> Self {
+        Self {
+            balance_fetcher,
+            suspect_ttl,
+            suspects: Default::default(),
+        }
+    }
+
+    /// Fetches the balances of the orders' owners and returns the orders
+    /// whose flag has to change. Orders whose balance can't be fetched are
+    /// left as they are.
+    pub async fn check(&mut self, orders: &[Order]) -> FlagChanges {
+        let queries = orders.iter().map(Query::from_order).collect::<Vec<_>>();
+        let balances = self.balance_fetcher.get_balances(&queries).await;
+
+        let now = Instant::now();
+        let ttl = self.suspect_ttl;
+        self.suspects
+            .retain(|_, since| now.duration_since(*since) < ttl);
+
+        let mut changes = FlagChanges::default();
+        for (order, balance) in orders.iter().zip(balances) {
+            let uid = order.metadata.uid;
+            let balance = match balance {
+                Ok(balance) => balance,
+                Err(err) => {
+                    tracing::debug!(?err, %uid, "failed to fetch balance of order owner");
+                    continue;
+                }
+            };
+            let required = order.data.sell_amount.saturating_add(order.data.fee_amount);
+            if balance >= required {
+                self.suspects.remove(&uid);
+                if order.metadata.insufficient_balance {
+                    changes.sufficient.push(uid);
+                }
+            } else if !order.metadata.insufficient_balance {
+                match self.suspects.entry(uid) {
+                    Entry::Occupied(entry) => {
+                        entry.remove();
+                        changes.insufficient.push(uid);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(now);
+                    }
+                }
+            }
+        }
+
+        let metrics = Metrics::get();
+        metrics
+            .flag_changes
+            .with_label_values(&["true"])
+            .inc_by(changes.insufficient.len() as u64);
+        metrics
+            .flag_changes
+            .with_label_values(&["false"])
+            .inc_by(changes.sufficient.len() as u64);
+        metrics.suspects.set(self.suspects.len() as i64);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        model::order::{OrderData, OrderMetadata},
+        primitive_types::U256,
+        shared::account_balances::MockBalanceFetching,
+        std::sync::Mutex,
+    };
+
+    fn order(uid: u8, insufficient_balance: bool) -> Order {
+        Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                insufficient_balance,
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_amount: 90.into(),
+                fee_amount: 10.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Balance fetcher returning the currently configured balance for every
+    /// query.
+    fn fetcher(balance: Arc<Mutex<Option<U256>>>) -> Arc<dyn BalanceFetching> {
+        let mut fetcher = MockBalanceFetching::new();
+        fetcher.expect_get_balances().returning(move |queries| {
+            let balance = *balance.lock().unwrap();
+            queries
+                .iter()
+                .map(|_| balance.ok_or_else(|| anyhow::anyhow!("node error")))
+                .collect()
+        });
+        Arc::new(fetcher)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flags_orders_lacking_the_balance_twice() {
+        let balance = Arc::new(Mutex::new(Some(99.into())));
+        let mut checker =
+            InsufficientBalances::new(fetcher(balance.clone()), Duration::from_secs(60));
+        let orders = [order(1, false)];
+
+        // A single check isn't enough to flag the order.
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+        assert_eq!(
+            checker.check(&orders).await,
+            FlagChanges {
+                insufficient: vec![OrderUid([1; 56])],
+                sufficient: vec![],
+            }
+        );
+
+        // The balance recovered in between so the order doesn't get flagged.
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+        *balance.lock().unwrap() = Some(100.into());
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+        *balance.lock().unwrap() = Some(99.into());
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+
+        // Failing to fetch the balance doesn't change anything.
+        *balance.lock().unwrap() = None;
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+        *balance.lock().unwrap() = Some(99.into());
+        assert_eq!(
+            checker.check(&orders).await,
+            FlagChanges {
+                insufficient: vec![OrderUid([1; 56])],
+                sufficient: vec![],
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clears_flag_once_balance_is_restored() {
+        let balance = Arc::new(Mutex::new(Some(99.into())));
+        let mut checker =
+            InsufficientBalances::new(fetcher(balance.clone()), Duration::from_secs(60));
+        let orders = [order(1, true), order(2, false)];
+
+        // Flagged orders stay flagged while the balance is missing.
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+
+        *balance.lock().unwrap() = Some(100.into());
+        assert_eq!(
+            checker.check(&orders).await,
+            FlagChanges {
+                insufficient: vec![],
+                sufficient: vec![OrderUid([1; 56])],
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn forgets_orders_that_are_not_checked_again_in_time() {
+        let balance = Arc::new(Mutex::new(Some(0.into())));
+        let mut checker = InsufficientBalances::new(fetcher(balance), Duration::from_secs(60));
+        let orders = [order(1, false)];
+
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(checker.check(&[]).await, FlagChanges::default());
+        assert!(checker.suspects.is_empty());
+        assert_eq!(checker.check(&orders).await, FlagChanges::default());
+    }
+}
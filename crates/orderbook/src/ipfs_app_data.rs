@@ -6,6 +6,13 @@ use {
     std::sync::Mutex,
 };
 
+/// Fetches full app data documents for contract app data hashes.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait AppDataFetching: Send + Sync {
+    async fn fetch(&self, contract_app_data: &AppDataHash) -> Result<Option<String>>;
+}
+
 pub struct IpfsAppData {
     ipfs: Ipfs,
     cache: Mutex<TimedSizedCache<AppDataHash, Option<String>>>,
@@ -119,6 +126,13 @@ impl IpfsAppData {
     }
 }
 
+#[async_trait::async_trait]
+impl AppDataFetching for IpfsAppData {
+    async fn fetch(&self, contract_app_data: &AppDataHash) -> Result<Option<String>> {
+        self.fetch(contract_app_data).await
+    }
+}
+
 fn new_app_data_cid(contract_app_data: &AppDataHash) -> String {
     let raw_cid = app_data_hash::create_ipfs_cid(&contract_app_data.0);
     multibase::encode(multibase::Base::Base32Lower, raw_cid)
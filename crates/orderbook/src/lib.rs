@@ -1,12 +1,18 @@
 pub mod api;
 pub mod app_data;
 pub mod arguments;
+pub mod cursor;
 pub mod database;
 pub mod dto;
+pub mod health;
+pub mod insufficient_balances;
 mod ipfs;
 mod ipfs_app_data;
+pub mod order_cache;
+pub mod order_events;
 pub mod orderbook;
 mod quoter;
+pub mod rate_limit;
 pub mod run;
 pub mod solver_competition;
 
@@ -0,0 +1,231 @@
+use {
+    crate::database::orders::OrderStoring,
+    anyhow::Result,
+    model::order::{Order, OrderUid},
+    std::{collections::HashMap, sync::Mutex, time::Duration},
+    tokio::time::Instant,
+};
+
+/// How long a looked up order gets reused. Orders can change without the
+/// orderbook noticing (e.g. when they get traded) so this has to stay short.
+const DEFAULT_TTL: Duration = Duration::from_secs(3);
+
+/// Maximum number of cached orders. Lookups of further orders don't get
+/// cached until entries expire.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "order_cache")]
+struct Metrics {
+    /// Order lookups by whether they were served from the cache.
+    #[metric(labels("result"))]
+    lookups: prometheus::IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Self::instance(observe::metrics::get_storage_registry())
+            .expect("unexpected error getting metrics instance")
+    }
+}
+
+/// Short lived read-through cache for single order lookups.
+///
+/// Orders have to be invalidated whenever the process changes them. Lookups
+/// that started before an invalidation don't get cached so a concurrent
+/// lookup can't bring back the state from before the change.
+pub struct OrderCache {
+    ttl: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<OrderUid, (Instant, Order)>,
+    /// Incremented on every invalidation.
+    generation: u64,
+}
+
+impl Default for OrderCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl OrderCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the order from the cache or looks it up in the database.
+    /// Orders that don't exist don't get cached so they can be found as soon
+    /// as they get inserted.
+    pub async fn get(&self, uid: &OrderUid, database: &impl OrderStoring) -> Result<Option<Order>> {
+        let generation = {
+            let state = self.state.lock().unwrap();
+            if let Some((cached_at, order)) = state.entries.get(uid) {
+                if cached_at.elapsed() < self.ttl {
+                    Metrics::get().lookups.with_label_values(&["hit"]).inc();
+                    return Ok(Some(order.clone()));
+                }
+            }
+            state.generation
+        };
+        Metrics::get().lookups.with_label_values(&["miss"]).inc();
+
+        let order = database.single_order(uid).await?;
+        if let Some(order) = &order {
+            let mut state = self.state.lock().unwrap();
+            if state.generation == generation {
+                let now = Instant::now();
+                let ttl = self.ttl;
+                if state.entries.len() >= MAX_ENTRIES {
+                    state
+                        .entries
+                        .retain(|_, (cached_at, _)| now.duration_since(*cached_at) < ttl);
+                }
+                if state.entries.len() < MAX_ENTRIES {
+                    state.entries.insert(*uid, (now, order.clone()));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Removes the orders from the cache. Needs to be called once a change
+    /// of the orders got stored.
+    pub fn invalidate<'a>(&self, uids: impl IntoIterator<Item = &'a OrderUid>) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        for uid in uids {
+            state.entries.remove(uid);
+        }
+    }
+
+    /// Removes all orders from the cache.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::database::orders::MockOrderStoring,
+        model::order::{OrderMetadata, OrderStatus},
+        std::sync::Arc,
+    };
+
+    fn order(uid: OrderUid, status: OrderStatus) -> Order {
+        Order {
+            metadata: OrderMetadata {
+                uid,
+                status,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn caches_orders_until_ttl() {
+        let uid = OrderUid([1; 56]);
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_single_order()
+            .times(2)
+            .returning(|uid| Ok(Some(order(*uid, OrderStatus::Open))));
+        let cache = OrderCache::new(Duration::from_secs(3));
+
+        for _ in 0..3 {
+            let cached = cache.get(&uid, &database).await.unwrap().unwrap();
+            assert_eq!(cached.metadata.uid, uid);
+            tokio::time::advance(Duration::from_secs(1)).await;
+        }
+        // The entry expired so the order gets looked up again.
+        cache.get(&uid, &database).await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_cache_missing_orders() {
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_single_order()
+            .times(2)
+            .returning(|_| Ok(None));
+        let cache = OrderCache::default();
+
+        assert!(cache
+            .get(&OrderUid([1; 56]), &database)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .get(&OrderUid([1; 56]), &database)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn invalidation_removes_orders() {
+        let uid = OrderUid([1; 56]);
+        let other = OrderUid([2; 56]);
+        let mut database = MockOrderStoring::new();
+        let mut seq = mockall::Sequence::new();
+        database
+            .expect_single_order()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|uid| Ok(Some(order(*uid, OrderStatus::Open))));
+        database
+            .expect_single_order()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|uid| Ok(Some(order(*uid, OrderStatus::Cancelled))));
+        let cache = OrderCache::default();
+
+        cache.get(&uid, &database).await.unwrap();
+        cache.get(&other, &database).await.unwrap();
+        cache.invalidate([&uid]);
+
+        // Only the invalidated order gets looked up again.
+        let cached = cache.get(&uid, &database).await.unwrap().unwrap();
+        assert_eq!(cached.metadata.status, OrderStatus::Cancelled);
+        let cached = cache.get(&other, &database).await.unwrap().unwrap();
+        assert_eq!(cached.metadata.status, OrderStatus::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lookups_racing_with_invalidation_are_not_cached() {
+        let uid = OrderUid([1; 56]);
+        let cache = Arc::new(OrderCache::default());
+        let mut database = MockOrderStoring::new();
+        let mut seq = mockall::Sequence::new();
+        // The order gets cancelled while its lookup is in flight.
+        let cancelling = cache.clone();
+        database
+            .expect_single_order()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |uid| {
+                cancelling.invalidate([uid]);
+                Ok(Some(order(*uid, OrderStatus::Open)))
+            });
+        database
+            .expect_single_order()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|uid| Ok(Some(order(*uid, OrderStatus::Cancelled))));
+
+        cache.get(&uid, &database).await.unwrap();
+        let cached = cache.get(&uid, &database).await.unwrap().unwrap();
+        assert_eq!(cached.metadata.status, OrderStatus::Cancelled);
+    }
+}
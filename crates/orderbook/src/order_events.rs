@@ -0,0 +1,160 @@
+use {
+    chrono::{DateTime, Utc},
+    model::order::OrderUid,
+    tokio::sync::broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    },
+};
+
+/// How many events get buffered for subscribers. Subscribers that fall
+/// further behind miss the oldest events.
+const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "order_events")]
+struct Metrics {
+    /// Number of order events missed by subscribers that fell behind.
+    dropped: prometheus::IntCounter,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Self::instance(observe::metrics::get_storage_registry())
+            .expect("unexpected error getting metrics instance")
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OrderEventKind {
+    Created,
+    Cancelled,
+    /// The order got cancelled by a replacement order. The replacement gets a
+    /// separate `Created` event.
+    Replaced,
+}
+
+/// Change of an order's lifecycle caused by the orderbook.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderEvent {
+    pub uid: OrderUid,
+    pub kind: OrderEventKind,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Notifies internal consumers about order lifecycle changes as they happen.
+///
+/// Publishing never waits for subscribers. Subscribers that can't keep up
+/// miss events instead of slowing down order placement.
+pub struct OrderEventBus {
+    sender: broadcast::Sender<OrderEvent>,
+}
+
+impl Default for OrderEventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl OrderEventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+        }
+    }
+
+    pub fn publish(&self, uid: OrderUid, kind: OrderEventKind) {
+        // Sending only fails if there are no subscribers, in which case nobody
+        // is interested in the event.
+        let _ = self.sender.send(OrderEvent {
+            uid,
+            kind,
+            timestamp: Utc::now(),
+        });
+    }
+
+    /// Subscribes to all events published from now on.
+    pub fn subscribe(&self) -> OrderEventSubscription {
+        OrderEventSubscription {
+            receiver: self.sender.subscribe(),
+        }
+    }
+}
+
+pub struct OrderEventSubscription {
+    receiver: broadcast::Receiver<OrderEvent>,
+}
+
+impl OrderEventSubscription {
+    /// Returns the next event. Events the subscriber missed because it fell
+    /// behind get skipped. Returns `None` once the bus got dropped.
+    pub async fn recv(&mut self) -> Option<OrderEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "order event subscriber fell behind");
+                    Metrics::get().dropped.inc_by(missed);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Returns the next event if one is available without waiting.
+    pub fn try_recv(&mut self) -> Option<OrderEvent> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "order event subscriber fell behind");
+                    Metrics::get().dropped.inc_by(missed);
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_events_to_all_subscribers() {
+        let bus = OrderEventBus::default();
+        // Publishing without subscribers is fine.
+        bus.publish(OrderUid([0; 56]), OrderEventKind::Created);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.publish(OrderUid([1; 56]), OrderEventKind::Cancelled);
+
+        for subscription in [&mut first, &mut second] {
+            let event = subscription.recv().await.unwrap();
+            assert_eq!(event.uid, OrderUid([1; 56]));
+            assert_eq!(event.kind, OrderEventKind::Cancelled);
+            assert!(subscription.try_recv().is_none());
+        }
+
+        drop(bus);
+        assert!(first.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn slow_subscribers_miss_events() {
+        let bus = OrderEventBus::new(2);
+        let mut subscription = bus.subscribe();
+        let dropped = Metrics::get().dropped.get();
+
+        // Publishing doesn't block even though nobody consumes the events.
+        for i in 0..5 {
+            bus.publish(OrderUid([i; 56]), OrderEventKind::Created);
+        }
+
+        assert_eq!(subscription.recv().await.unwrap().uid, OrderUid([3; 56]));
+        assert_eq!(subscription.recv().await.unwrap().uid, OrderUid([4; 56]));
+        assert!(subscription.try_recv().is_none());
+        assert!(Metrics::get().dropped.get() >= dropped + 3);
+    }
+}
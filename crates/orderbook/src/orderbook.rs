@@ -1,56 +1,146 @@
 use {
     crate::{
         app_data,
-        database::orders::{InsertionError, OrderStoring},
+        cursor::Cursor,
+        database::{
+            orders::{
+                CancellationError,
+                InsertionError,
+                OrderQuote,
+                OrderStoring,
+                UserOrderFilter,
+            },
+            trades::{OrderExecution, TradeRetrieving, UserSurplus},
+        },
         dto,
+        health::{Health, HealthMonitor},
+        insufficient_balances::InsufficientBalances,
+        order_cache::OrderCache,
+        order_events::{OrderEventBus, OrderEventKind, OrderEventSubscription},
+        rate_limit::{SubmissionRateLimit, SubmissionRateLimiter},
     },
-    anyhow::{Context, Result},
-    chrono::Utc,
+    anyhow::{anyhow, Context, Result},
+    chrono::{DateTime, TimeZone, Utc},
     ethcontract::H256,
+    futures::StreamExt,
     model::{
         app_data::AppDataHash,
         order::{
+            AmendedAmounts,
+            CancellationMessage,
+            CancellationProof,
+            CancellationSignature,
             Order,
+            OrderAmendment,
             OrderCancellation,
             OrderClass,
             OrderCreation,
             OrderCreationAppData,
+            OrderKind,
+            OrderSource,
             OrderStatus,
             OrderUid,
+            SignedCancelAll,
             SignedOrderCancellations,
         },
         quote::QuoteId,
+        signature::{hashed_eip712_message, SigningScheme},
+        time::now_in_epoch_seconds,
         DomainSeparator,
     },
-    primitive_types::H160,
+    number::conversions::big_uint_to_u256,
+    primitive_types::{H160, U256},
     shared::{
+        ethrpc::Web3,
         metrics::LivenessChecking,
-        order_validation::{OrderValidating, ValidationError},
+        order_quoting::Quote,
+        order_validation::{OrderValidating, PartialValidationError, ValidationError},
+        price_estimation::native::NativePriceEstimating,
+        signature_validator::{SignatureCheck, SignatureValidating, SignatureValidationError},
+    },
+    std::{
+        borrow::Cow,
+        collections::{hash_map::Entry, HashMap, HashSet},
+        net::IpAddr,
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
     },
-    std::{borrow::Cow, sync::Arc},
     thiserror::Error,
+    tokio::sync::watch,
+    web3::types::TransactionId,
 };
 
+/// Maximum number of orders of a bulk submission that get validated
+/// concurrently.
+const MAX_CONCURRENT_VALIDATIONS: usize = 10;
+
+/// Maximum number of orders that can get cancelled with a single signed
+/// cancellation unless configured otherwise.
+pub const DEFAULT_MAX_CANCELLATION_BATCH_SIZE: usize = 1024;
+
+/// Number of token pairs with the most open orders that get reported in the
+/// open orders metric. This bounds the metric's label cardinality.
+const MAX_REPORTED_PAIRS: usize = 50;
+
+/// Number of app codes with the most open orders that get reported in the
+/// open orders metric. This bounds the metric's label cardinality.
+const MAX_REPORTED_APP_CODES: usize = 50;
+
 #[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
 #[metric(subsystem = "orderbook")]
 struct Metrics {
     /// Counter for measuring order statistics.
-    #[metric(labels("kind", "operation"))]
+    #[metric(labels("kind", "operation", "quote_verified", "source"))]
     orders: prometheus::IntCounterVec,
+
+    /// Counter for rejected order operations by reason.
+    #[metric(labels("operation", "reason"))]
+    rejected_orders: prometheus::IntCounterVec,
+
+    /// Counter for failed order replacements by error.
+    #[metric(labels("error"))]
+    failed_replacements: prometheus::IntCounterVec,
+
+    /// Orders accepted without checking the minimum order value because the
+    /// native price of their sell token was not available.
+    orders_without_native_price: prometheus::IntCounter,
+
+    /// Open orders of the token pairs with the most open orders.
+    #[metric(labels("sell_token", "buy_token"))]
+    open_orders_by_pair: prometheus::IntGaugeVec,
+
+    /// Open orders of the app codes with the most open orders.
+    #[metric(labels("app_code"))]
+    open_orders_by_app_code: prometheus::IntGaugeVec,
+
+    /// Time spent in the individual stages of placing or replacing an order.
+    #[metric(
+        labels("stage"),
+        buckets(0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5)
+    )]
+    stage_latency: prometheus::HistogramVec,
 }
 
 enum OrderOperation {
     Created,
     Cancelled,
+    /// An order got replaced by a new one. Replacements are only counted as
+    /// such and not as a cancellation plus a creation.
+    Replaced,
 }
 
 fn operation_label(op: &OrderOperation) -> &'static str {
     match op {
         OrderOperation::Created => "created",
         OrderOperation::Cancelled => "cancelled",
+        OrderOperation::Replaced => "replaced",
     }
 }
 
+fn order_source_label(source: Option<&OrderSource>) -> &'static str {
+    source.map_or("unknown", OrderSource::label)
+}
+
 fn order_class_label(class: &OrderClass) -> &'static str {
     match class {
         OrderClass::Market => "user",
@@ -68,26 +158,183 @@ impl Metrics {
     fn on_order_operation(order: &Order, operation: OrderOperation) {
         let class = order_class_label(&order.metadata.class);
         let op = operation_label(&operation);
-        Self::get().orders.with_label_values(&[class, op]).inc();
+        let verified = if order.metadata.quote_verified {
+            "true"
+        } else {
+            "false"
+        };
+        let source = order_source_label(order.metadata.source.as_ref());
+        Self::get()
+            .orders
+            .with_label_values(&[class, op, verified, source])
+            .inc();
+    }
+
+    fn on_stage_completed(stage: &'static str, start: Instant) {
+        Self::get()
+            .stage_latency
+            .with_label_values(&[stage])
+            .observe(start.elapsed().as_secs_f64());
+    }
+
+    fn on_order_rejection(operation: OrderOperation, reason: &'static str) {
+        let op = operation_label(&operation);
+        Self::get()
+            .rejected_orders
+            .with_label_values(&[op, reason])
+            .inc();
+    }
+
+    fn on_replacement_failure(err: &ReplaceOrderError) {
+        Self::get()
+            .failed_replacements
+            .with_label_values(&[err.variant_label()])
+            .inc();
     }
 
     // Resets all the counters to 0 so we can always use them in Grafana queries.
     fn initialize() {
         let metrics = Self::get();
-        for op in &[OrderOperation::Created, OrderOperation::Cancelled] {
+        for op in &[
+            OrderOperation::Created,
+            OrderOperation::Cancelled,
+            OrderOperation::Replaced,
+        ] {
             let op = operation_label(op);
             for class in &[OrderClass::Market, OrderClass::Liquidity, OrderClass::Limit] {
                 let class = order_class_label(class);
-                metrics.orders.with_label_values(&[class, op]).reset();
+                for verified in ["true", "false"] {
+                    for source in ["api", "onchain", "unknown"] {
+                        metrics
+                            .orders
+                            .with_label_values(&[class, op, verified, source])
+                            .reset();
+                    }
+                }
             }
         }
+        for error in ReplaceOrderError::VARIANT_LABELS {
+            metrics
+                .failed_replacements
+                .with_label_values(&[error])
+                .reset();
+        }
+    }
+}
+
+/// Records the reason for a failed order operation.
+fn record_rejection<T, E: RejectionReason>(
+    operation: OrderOperation,
+    result: Result<T, E>,
+) -> Result<T, E> {
+    if let Err(err) = &result {
+        Metrics::on_order_rejection(operation, err.rejection_reason());
+    }
+    result
+}
+
+/// Records the reason and the error variant of a failed replacement. The
+/// rejected new order never got created so its rejection keeps the label of
+/// order creations.
+fn record_replacement_failure<T>(
+    result: Result<T, ReplaceOrderError>,
+) -> Result<T, ReplaceOrderError> {
+    if let Err(err) = &result {
+        Metrics::on_replacement_failure(err);
+    }
+    record_rejection(OrderOperation::Created, result)
+}
+
+/// Maps errors to a bounded set of metric labels.
+trait RejectionReason {
+    fn rejection_reason(&self) -> &'static str;
+}
+
+impl RejectionReason for AddOrderError {
+    fn rejection_reason(&self) -> &'static str {
+        match self {
+            Self::DuplicatedOrder { .. } => "duplicated",
+            Self::OrderValidation(err) => err.rejection_reason(),
+            Self::AppDataMismatch { .. } => "app_data_mismatch",
+            Self::TooManyOpenOrders { .. } => "too_many_open_orders",
+            Self::ExcessiveHookGas { .. } => "excessive_hook_gas",
+            Self::QuoteExpired { .. } => "quote_expired",
+            Self::OrderValueTooSmall { .. } => "order_value_too_small",
+            Self::UnsupportedToken { .. } => "unsupported_token",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Database(_) => "other",
+        }
+    }
+}
+
+impl RejectionReason for ValidationError {
+    fn rejection_reason(&self) -> &'static str {
+        match self {
+            Self::Partial(PartialValidationError::Forbidden) => "forbidden",
+            Self::Partial(PartialValidationError::ValidTo(_)) => "invalid_valid_to",
+            Self::Partial(PartialValidationError::UnsupportedToken { .. }) => "unsupported_token",
+            Self::Partial(PartialValidationError::Other(_)) => "other",
+            Self::Partial(_) => "unsupported_order",
+            Self::AppData(_) => "invalid_app_data",
+            Self::QuoteNotFound | Self::InvalidQuote | Self::PriceForQuote(_) => "invalid_quote",
+            Self::QuoteExpired { .. } => "quote_expired",
+            Self::InsufficientFee => "insufficient_fee",
+            Self::InsufficientBalance => "insufficient_balance",
+            Self::InsufficientAllowance => "insufficient_allowance",
+            Self::InvalidSignature
+            | Self::InvalidEip1271Signature(_)
+            | Self::IncompatibleSigningScheme => "invalid_signature",
+            Self::WrongOwner(_) | Self::MissingFrom | Self::AppdataFromMismatch(_) => "wrong_owner",
+            Self::SellAmountOverflow | Self::ZeroAmount => "invalid_amount",
+            Self::TransferSimulationFailed => "transfer_simulation_failed",
+            Self::TooManyLimitOrders => "too_many_limit_orders",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
+impl RejectionReason for OrderCancellationError {
+    fn rejection_reason(&self) -> &'static str {
+        match self {
+            Self::InvalidSignature | Self::InvalidEip1271Signature => "invalid_signature",
+            Self::WrongOwner => "wrong_owner",
+            Self::OrderNotFound => "order_not_found",
+            Self::AlreadyCancelled => "already_cancelled",
+            Self::OrderFullyExecuted => "order_fully_executed",
+            Self::OrderExpired => "order_expired",
+            Self::OnChainOrder => "on_chain_order",
+            Self::CancellationExpired => "cancellation_expired",
+            Self::TooManyOrders { .. } => "too_many_orders",
+            Self::InvalidOrder { reason, .. } => reason.rejection_reason(),
+            Self::Other(_) => "other",
+        }
+    }
+}
+
+impl RejectionReason for ReplaceOrderError {
+    fn rejection_reason(&self) -> &'static str {
+        match self {
+            Self::Cancellation(err) => err.rejection_reason(),
+            Self::Add(err) => err.rejection_reason(),
+            Self::InvalidReplacement => "invalid_replacement",
+            Self::InvalidReplacementMarker => "invalid_replacement_marker",
+            Self::Batch { source, .. } => source.rejection_reason(),
+        }
     }
 }
 
 #[derive(Debug, Error)]
 pub enum AddOrderError {
-    #[error("duplicated order")]
-    DuplicatedOrder,
+    #[error("duplicated order {uid} with status {status:?}")]
+    DuplicatedOrder {
+        uid: OrderUid,
+        /// Current status of the already existing order.
+        status: OrderStatus,
+        /// Whether the existing order has the same signature as the submitted
+        /// one. This is the case when a client retries a submission that
+        /// already succeeded.
+        signature_matches: bool,
+    },
     #[error("{0:?}")]
     OrderValidation(ValidationError),
     #[error("database error: {0}")]
@@ -101,12 +348,50 @@ pub enum AddOrderError {
         provided: String,
         existing: String,
     },
+    #[error("owner has {current} open orders which reaches the limit of {limit}")]
+    TooManyOpenOrders { limit: u64, current: u64 },
+    #[error("app data hooks request {requested} gas which is more than the maximum of {max}")]
+    ExcessiveHookGas { requested: u64, max: u64 },
+    /// The quote the order was created with expired or doesn't match the
+    /// order and the order doesn't allow using a fresh quote instead.
+    #[error("quote {quote_id} expired at {expired_at}")]
+    QuoteExpired {
+        quote_id: QuoteId,
+        expired_at: DateTime<Utc>,
+    },
+    /// The sell amount of the order is worth less than the minimum order
+    /// value. Both values are denominated in the native token.
+    #[error("order value {actual} is less than the minimum of {min}")]
+    OrderValueTooSmall { min: U256, actual: U256 },
+    /// The sell or buy token of the order is on the token deny list.
+    #[error("token {token:?} is unsupported: {reason}")]
+    UnsupportedToken { token: H160, reason: String },
+    /// The owner placed too many orders recently.
+    #[error("too many submissions, retry after {retry_after:?}")]
+    RateLimited { retry_after: Duration },
 }
 
 impl AddOrderError {
-    fn from_insertion(err: InsertionError, order: &Order) -> Self {
+    async fn from_insertion(
+        err: InsertionError,
+        order: &Order,
+        database: &impl OrderStoring,
+    ) -> Self {
         match err {
-            InsertionError::DuplicatedRecord => AddOrderError::DuplicatedOrder,
+            InsertionError::DuplicatedRecord => {
+                let uid = order.metadata.uid;
+                match database.single_order(&uid).await {
+                    Ok(Some(existing)) => AddOrderError::DuplicatedOrder {
+                        uid,
+                        status: existing.metadata.status,
+                        signature_matches: existing.signature == order.signature,
+                    },
+                    Ok(None) => {
+                        AddOrderError::Database(anyhow!("duplicated order {uid} does not exist"))
+                    }
+                    Err(err) => AddOrderError::Database(err),
+                }
+            }
             InsertionError::DbError(err) => AddOrderError::Database(err.into()),
             InsertionError::AppDataMismatch(existing) => AddOrderError::AppDataMismatch {
                 contract_app_data: order.data.app_data,
@@ -130,7 +415,16 @@ impl AddOrderError {
 // which it currently does not!
 impl From<ValidationError> for AddOrderError {
     fn from(err: ValidationError) -> Self {
-        Self::OrderValidation(err)
+        match err {
+            ValidationError::QuoteExpired {
+                quote_id,
+                expired_at,
+            } => Self::QuoteExpired {
+                quote_id,
+                expired_at,
+            },
+            err => Self::OrderValidation(err),
+        }
     }
 }
 
@@ -138,6 +432,8 @@ impl From<ValidationError> for AddOrderError {
 pub enum OrderCancellationError {
     #[error("invalid signature")]
     InvalidSignature,
+    #[error("on-chain EIP-1271 signature verification failed")]
+    InvalidEip1271Signature,
     #[error("signer does not match order owner")]
     WrongOwner,
     #[error("order not found")]
@@ -150,6 +446,51 @@ pub enum OrderCancellationError {
     OrderExpired,
     #[error("on-chain orders cannot be cancelled with off-chain signature")]
     OnChainOrder,
+    /// The cancellation is past its `valid_to` or doesn't specify one while
+    /// such cancellations are not accepted.
+    #[error("cancellation expired")]
+    CancellationExpired,
+    #[error("cancellation of {count} orders exceeds the maximum of {max}")]
+    TooManyOrders { count: usize, max: usize },
+    /// An order of a batch cancellation can't be cancelled so none of the
+    /// orders got cancelled.
+    #[error("order {uid} can't be cancelled: {reason}")]
+    InvalidOrder {
+        uid: OrderUid,
+        reason: Box<OrderCancellationError>,
+    },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<CancellationError> for OrderCancellationError {
+    fn from(err: CancellationError) -> Self {
+        match err {
+            CancellationError::AlreadyCancelled(_) => Self::AlreadyCancelled,
+            CancellationError::FullyExecuted(_) => Self::OrderFullyExecuted,
+            CancellationError::Other(err) => Self::Other(err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum OrderAmendmentError {
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("signer does not match order owner")]
+    WrongOwner,
+    #[error("order not found")]
+    OrderNotFound,
+    #[error("order is not open")]
+    NotOpen,
+    #[error("only partially fillable orders can be reduced")]
+    NotPartiallyFillable,
+    #[error("amendments can not increase order amounts")]
+    IncreasedAmounts,
+    #[error("amendments can not change the limit price")]
+    ChangedLimitPrice,
+    #[error("order already executed more than the new amounts")]
+    BelowExecutedAmount,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -162,6 +503,35 @@ pub enum ReplaceOrderError {
     Add(#[from] AddOrderError),
     #[error("the new order is not a valid replacement for the old one")]
     InvalidReplacement,
+    #[error("the app data of the new order does not mark the order it replaces")]
+    InvalidReplacementMarker,
+    /// A replacement of a batch is invalid so none of them got applied.
+    #[error("replacement {index} is invalid: {source}")]
+    Batch {
+        index: usize,
+        source: Box<ReplaceOrderError>,
+    },
+}
+
+impl ReplaceOrderError {
+    const VARIANT_LABELS: [&'static str; 4] = [
+        "cancellation",
+        "add",
+        "invalid_replacement",
+        "invalid_replacement_marker",
+    ];
+
+    /// Metric label of the error variant. Failed batches are labeled with the
+    /// variant of the replacement that failed.
+    fn variant_label(&self) -> &'static str {
+        match self {
+            Self::Cancellation(_) => "cancellation",
+            Self::Add(_) => "add",
+            Self::InvalidReplacement => "invalid_replacement",
+            Self::InvalidReplacementMarker => "invalid_replacement_marker",
+            Self::Batch { source, .. } => source.variant_label(),
+        }
+    }
 }
 
 impl From<ValidationError> for ReplaceOrderError {
@@ -170,12 +540,154 @@ impl From<ValidationError> for ReplaceOrderError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum AuctionRetrievalError {
+    /// The auction is older than the retention window so it might have
+    /// existed but is no longer stored.
+    #[error("auction {0} is older than the retention window and got pruned")]
+    Pruned(dto::AuctionId),
+    /// The most recent auction is older than the maximum auction age. This
+    /// indicates that the autopilot stopped producing auctions.
+    #[error("the most recent auction {id} is stale, it was created {age:?} ago")]
+    Stale { id: dto::AuctionId, age: Duration },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Orders settled in a transaction.
+#[derive(Debug, Default)]
+pub struct TxOrders {
+    pub orders: Vec<Order>,
+    /// Hash of the mined transaction if it differs from the requested one. This
+    /// happens when the requested transaction got replaced by one with the same
+    /// sender and nonce (e.g. re-broadcast with a higher gas price).
+    pub remapped_tx_hash: Option<H256>,
+}
+
+/// Fetches the sender and nonce of transactions. This also works for
+/// transactions that got replaced as long as the node still knows about them.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait TransactionSenderFetching: Send + Sync {
+    async fn sender_and_nonce(&self, tx_hash: H256) -> Result<Option<(H160, u64)>>;
+}
+
+#[async_trait::async_trait]
+impl TransactionSenderFetching for Web3 {
+    async fn sender_and_nonce(&self, tx_hash: H256) -> Result<Option<(H160, u64)>> {
+        let tx = self.eth().transaction(TransactionId::Hash(tx_hash)).await?;
+        Ok(tx.and_then(|tx| Some((tx.from?, tx.nonce.low_u64()))))
+    }
+}
+
+/// Access to the auctions created by the autopilot.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait AuctionRetrieving: Send + Sync {
+    /// The most recent auction together with when it got created.
+    async fn most_recent_auction(&self) -> Result<Option<(dto::AuctionWithId, DateTime<Utc>)>>;
+    /// When the most recent auction got created.
+    async fn most_recent_auction_created(&self) -> Result<Option<DateTime<Utc>>>;
+}
+
+#[async_trait::async_trait]
+impl AuctionRetrieving for crate::database::Postgres {
+    async fn most_recent_auction(&self) -> Result<Option<(dto::AuctionWithId, DateTime<Utc>)>> {
+        self.most_recent_auction().await
+    }
+
+    async fn most_recent_auction_created(&self) -> Result<Option<DateTime<Utc>>> {
+        self.most_recent_auction_created().await
+    }
+}
+
+/// Limits the number of open orders per owner.
+#[derive(Clone, Debug, Default)]
+pub struct OpenOrderLimit {
+    /// Maximum number of open orders an owner can have. `None` disables the
+    /// limit.
+    pub max_open_orders_per_owner: Option<u64>,
+    /// Owners the limit doesn't apply to, e.g. market makers placing
+    /// liquidity orders.
+    pub exempt_owners: HashSet<H160>,
+}
+
+impl OpenOrderLimit {
+    /// The limit that applies to the owner if there is one.
+    fn limit_for(&self, owner: &H160) -> Option<u64> {
+        if self.exempt_owners.contains(owner) {
+            return None;
+        }
+        self.max_open_orders_per_owner
+    }
+}
+
+/// Rejects orders whose sell amount is worth less than a minimum amount of
+/// native token.
+#[derive(Clone)]
+pub struct MinOrderValue {
+    /// Minimum value of an order's sell amount in wei of the native token.
+    pub min: U256,
+    pub native_price_estimator: Arc<dyn NativePriceEstimating>,
+}
+
+/// A page of a user's orders together with how many orders there are in
+/// total.
+#[derive(Debug)]
+pub struct CountedUserOrders {
+    pub orders: Vec<Order>,
+    /// Number of the user's orders matching the filter across all pages.
+    pub total_count: u64,
+    /// Whether there are more orders after this page.
+    pub has_more: bool,
+}
+
+/// A page of a user's orders.
+#[derive(Debug)]
+pub struct UserOrdersPage {
+    pub orders: Vec<Order>,
+    /// Cursor for fetching the next page. `None` if this is the last page.
+    pub next: Option<Cursor>,
+}
+
 pub struct Orderbook {
     domain_separator: DomainSeparator,
     settlement_contract: H160,
     database: crate::database::Postgres,
+    auctions: Arc<dyn AuctionRetrieving>,
     order_validator: Arc<dyn OrderValidating>,
+    signature_validator: Arc<dyn SignatureValidating>,
+    transactions: Arc<dyn TransactionSenderFetching>,
     app_data: Arc<app_data::Registry>,
+    accept_cancellations_without_valid_to: bool,
+    health: HealthMonitor,
+    open_order_limit: OpenOrderLimit,
+    min_order_value: Option<MinOrderValue>,
+    /// How many of the most recent auctions are kept in the database.
+    auction_retention: u64,
+    /// Auctions older than this are considered stale.
+    max_auction_age: Option<Duration>,
+    /// Id of the most recent auction observed so far.
+    latest_auction_id: watch::Sender<Option<dto::AuctionId>>,
+    order_events: OrderEventBus,
+    order_cache: OrderCache,
+    /// Maximum number of orders of a single batch cancellation.
+    max_cancellation_batch_size: usize,
+    /// Owners whose liquidity orders get accepted without checking their
+    /// balance.
+    trusted_liquidity_providers: HashSet<H160>,
+    /// Orders selling or buying these tokens get rejected before validation.
+    /// Can be updated at runtime.
+    unsupported_tokens: RwLock<HashSet<H160>>,
+    /// Salt for hashing the IP addresses orders get posted from. Addresses
+    /// don't get recorded without it.
+    client_ip_hash_salt: Option<Vec<u8>>,
+    /// Reverse proxies whose `X-Forwarded-For` header is trusted to contain
+    /// the address of the client.
+    trusted_proxies: HashSet<IpAddr>,
+    /// Limits how many orders an owner can place in a short time. Disabled
+    /// if not set.
+    submission_rate_limiter: Option<SubmissionRateLimiter>,
 }
 
 impl Orderbook {
@@ -185,63 +697,498 @@ impl Orderbook {
         settlement_contract: H160,
         database: crate::database::Postgres,
         order_validator: Arc<dyn OrderValidating>,
+        signature_validator: Arc<dyn SignatureValidating>,
+        transactions: Arc<dyn TransactionSenderFetching>,
         app_data: Arc<app_data::Registry>,
+        accept_cancellations_without_valid_to: bool,
+        db_unreachable_grace_period: Duration,
+        open_order_limit: OpenOrderLimit,
     ) -> Self {
         Metrics::initialize();
+        let health = HealthMonitor::new(Arc::new(database.clone()), db_unreachable_grace_period);
         Self {
             domain_separator,
             settlement_contract,
+            auctions: Arc::new(database.clone()),
             database,
             order_validator,
+            signature_validator,
+            transactions,
             app_data,
+            accept_cancellations_without_valid_to,
+            health,
+            open_order_limit,
+            min_order_value: None,
+            auction_retention: 1,
+            max_auction_age: None,
+            latest_auction_id: watch::channel(None).0,
+            order_events: Default::default(),
+            order_cache: Default::default(),
+            max_cancellation_batch_size: DEFAULT_MAX_CANCELLATION_BATCH_SIZE,
+            trusted_liquidity_providers: Default::default(),
+            unsupported_tokens: Default::default(),
+            client_ip_hash_salt: None,
+            trusted_proxies: Default::default(),
+            submission_rate_limiter: None,
+        }
+    }
+
+    /// Accepts liquidity orders of these owners without checking whether they
+    /// can transfer the sell amount. The owners have to set the `from` field
+    /// of their orders.
+    pub fn with_trusted_liquidity_providers(
+        mut self,
+        owners: impl IntoIterator<Item = H160>,
+    ) -> Self {
+        self.trusted_liquidity_providers = owners.into_iter().collect();
+        self
+    }
+
+    /// Rejects orders selling or buying any of the tokens.
+    pub fn with_unsupported_tokens(self, tokens: impl IntoIterator<Item = H160>) -> Self {
+        self.set_unsupported_tokens(tokens);
+        self
+    }
+
+    /// Replaces the tokens whose orders get rejected. Takes effect for all
+    /// orders placed afterwards.
+    pub fn set_unsupported_tokens(&self, tokens: impl IntoIterator<Item = H160>) {
+        *self.unsupported_tokens.write().unwrap() = tokens.into_iter().collect();
+    }
+
+    /// Records salted hashes of the IP addresses orders get posted from.
+    pub fn with_client_ip_hash_salt(mut self, salt: Option<String>) -> Self {
+        self.client_ip_hash_salt = salt.map(String::into_bytes);
+        self
+    }
+
+    /// Trusts the `X-Forwarded-For` header of requests sent by these proxies.
+    pub fn with_trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.trusted_proxies = proxies.into_iter().collect();
+        self
+    }
+
+    /// Determines the address of the client that sent a request. The
+    /// forwarded address is only used if the request came from a trusted
+    /// proxy since anyone else can set arbitrary headers.
+    pub fn client_ip(&self, forwarded_for: Option<IpAddr>, peer: Option<IpAddr>) -> Option<IpAddr> {
+        match peer {
+            Some(peer) if self.trusted_proxies.contains(&peer) => forwarded_for.or(Some(peer)),
+            peer => peer,
         }
     }
 
+    /// Hashes the IP address an order got posted from. Returns `None` if no
+    /// salt is configured since unsalted hashes of IP addresses are trivial to
+    /// reverse.
+    pub fn client_ip_hash(&self, ip: IpAddr) -> Option<H256> {
+        let salt = self.client_ip_hash_salt.as_ref()?;
+        Some(OrderSource::hash_client_ip(salt, ip))
+    }
+
+    /// Limits how many orders an owner can place in a short time.
+    pub fn with_submission_rate_limit(mut self, limit: Option<SubmissionRateLimit>) -> Self {
+        self.submission_rate_limiter = limit.map(SubmissionRateLimiter::new);
+        self
+    }
+
+    /// Limits how many orders can get cancelled with a single signed
+    /// cancellation.
+    pub fn with_max_cancellation_batch_size(mut self, max_cancellation_batch_size: usize) -> Self {
+        self.max_cancellation_batch_size = max_cancellation_batch_size;
+        self
+    }
+
+    /// Reports the current auction as stale once it is older than
+    /// `max_auction_age`. Auctions never get stale by default.
+    pub fn with_max_auction_age(mut self, max_auction_age: Option<Duration>) -> Self {
+        self.max_auction_age = max_auction_age;
+        self
+    }
+
+    /// Sets how many of the most recent auctions are kept in the database.
+    /// Older auctions are reported as pruned. Defaults to only the current
+    /// auction.
+    pub fn with_auction_retention(mut self, auction_retention: u64) -> Self {
+        self.auction_retention = auction_retention;
+        self
+    }
+
+    /// Rejects orders worth less than the minimum order value. Disabled by
+    /// default.
+    pub fn with_min_order_value(mut self, min_order_value: Option<MinOrderValue>) -> Self {
+        self.min_order_value = min_order_value;
+        self
+    }
+
+    /// Subscribes to lifecycle events of orders created, cancelled or replaced
+    /// from now on. Events are only published once the change got stored.
+    pub fn subscribe_order_events(&self) -> OrderEventSubscription {
+        self.order_events.subscribe()
+    }
+
     pub async fn add_order(
         &self,
         payload: OrderCreation,
+        source: OrderSource,
     ) -> Result<(OrderUid, Option<QuoteId>), AddOrderError> {
-        let full_app_data_override = match payload.app_data {
-            OrderCreationAppData::Hash { hash } => self.app_data.find(&hash).await?,
-            _ => None,
-        };
+        record_rejection(
+            OrderOperation::Created,
+            self.add_order_inner(payload, source).await,
+        )
+    }
 
-        let (order, quote) = self
-            .order_validator
-            .validate_and_construct_order(
-                payload,
-                &self.domain_separator,
-                self.settlement_contract,
-                full_app_data_override,
-            )
+    async fn add_order_inner(
+        &self,
+        payload: OrderCreation,
+        source: OrderSource,
+    ) -> Result<(OrderUid, Option<QuoteId>), AddOrderError> {
+        self.check_unsupported_tokens(&payload)?;
+        self.check_submission_rate(&payload)?;
+        self.check_open_order_limit(&payload, &mut HashMap::new())
             .await?;
+        let (order, quote) = self.validate_order(payload, Some(source)).await?;
+        self.check_validated_submission_rate(&order)?;
         let quote_id = quote.as_ref().and_then(|quote| quote.id);
 
-        self.database
-            .insert_order(&order, quote)
-            .await
-            .map_err(|err| AddOrderError::from_insertion(err, &order))?;
+        let start = Instant::now();
+        let result = self.database.insert_order(&order, quote).await;
+        Metrics::on_stage_completed("db_insert", start);
+        if let Err(err) = result {
+            return Err(AddOrderError::from_insertion(err, &order, &self.database).await);
+        }
+        self.health.on_order_inserted();
         Metrics::on_order_operation(&order, OrderOperation::Created);
+        self.order_events
+            .publish(order.metadata.uid, OrderEventKind::Created);
 
         Ok((order.metadata.uid, quote_id))
     }
 
-    /// Finds an order for cancellation.
+    /// Adds multiple orders at once.
+    ///
+    /// Orders get validated concurrently and all valid orders get inserted in
+    /// a single database transaction. The results are reported per order (in
+    /// the same order as the payloads) so a single invalid or duplicated order
+    /// does not prevent the others from getting added.
+    pub async fn add_orders(
+        &self,
+        payloads: Vec<OrderCreation>,
+        source: OrderSource,
+    ) -> Vec<Result<(OrderUid, Option<QuoteId>), AddOrderError>> {
+        // Orders of the batch count towards the limit of their owner as well.
+        let mut open_orders = HashMap::new();
+        let mut limit_checks = Vec::with_capacity(payloads.len());
+        for payload in &payloads {
+            let limit_check = match self
+                .check_unsupported_tokens(payload)
+                .and_then(|()| self.check_submission_rate(payload))
+            {
+                Ok(()) => self.check_open_order_limit(payload, &mut open_orders).await,
+                Err(err) => Err(err),
+            };
+            limit_checks.push(limit_check);
+        }
+
+        let validated: Vec<_> = futures::stream::iter(payloads.into_iter().zip(limit_checks))
+            .map(|(payload, limit_check)| {
+                let source = source.clone();
+                async move {
+                    limit_check?;
+                    let (order, quote) = self.validate_order(payload, Some(source)).await?;
+                    self.check_validated_submission_rate(&order)?;
+                    Ok((order, quote))
+                }
+            })
+            .buffered(MAX_CONCURRENT_VALIDATIONS)
+            .collect()
+            .await;
+
+        let mut results = Vec::with_capacity(validated.len());
+        let mut valid = Vec::new();
+        for (index, result) in validated.into_iter().enumerate() {
+            match result {
+                Ok(order) => {
+                    valid.push((index, order));
+                    results.push(None);
+                }
+                Err(err) => results.push(Some(Err(err))),
+            }
+        }
+
+        let insertions = self
+            .database
+            .insert_orders(valid.iter().cloned().map(|(_, order)| order).collect())
+            .await;
+        match insertions {
+            Ok(insertions) => {
+                for ((index, (order, quote)), insertion) in valid.into_iter().zip(insertions) {
+                    results[index] = Some(match insertion {
+                        Ok(()) => {
+                            self.health.on_order_inserted();
+                            Metrics::on_order_operation(&order, OrderOperation::Created);
+                            self.order_events
+                                .publish(order.metadata.uid, OrderEventKind::Created);
+                            Ok((order.metadata.uid, quote.and_then(|quote| quote.id)))
+                        }
+                        Err(err) => {
+                            Err(AddOrderError::from_insertion(err, &order, &self.database).await)
+                        }
+                    });
+                }
+            }
+            Err(err) => {
+                for (index, _) in valid {
+                    results[index] = Some(Err(AddOrderError::Database(
+                        shared::clone_anyhow_error(&err),
+                    )));
+                }
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|result| {
+                let result = result.expect("every order has a result");
+                record_rejection(OrderOperation::Created, result)
+            })
+            .collect()
+    }
+
+    /// Rejects orders selling or buying an unsupported token. This is cheap so
+    /// it happens before any other check.
+    fn check_unsupported_tokens(&self, payload: &OrderCreation) -> Result<(), AddOrderError> {
+        let unsupported_tokens = self.unsupported_tokens.read().unwrap();
+        for token in [payload.sell_token, payload.buy_token] {
+            if unsupported_tokens.contains(&token) {
+                return Err(AddOrderError::UnsupportedToken {
+                    token,
+                    reason: "token is on the deny list".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects the order if its owner placed too many orders recently.
+    ///
+    /// Only ECDSA signed orders get charged before the validation since their
+    /// owner gets recovered from the signature. The owner of other orders is
+    /// just the unverified `from` field at this point so charging it would
+    /// let anyone exhaust the limit of other owners. These orders get charged
+    /// by [`Self::check_validated_submission_rate`] instead.
+    fn check_submission_rate(&self, payload: &OrderCreation) -> Result<(), AddOrderError> {
+        if !payload.signature.scheme().is_ecdsa_scheme() {
+            return Ok(());
+        }
+        let Ok(owner) = payload.verify_owner(&self.domain_separator, None) else {
+            return Ok(());
+        };
+        let app_code = match &payload.app_data {
+            OrderCreationAppData::Full { full } | OrderCreationAppData::Both { full, .. } => {
+                crate::database::app_data::app_code(full)
+            }
+            OrderCreationAppData::Hash { .. } => None,
+        };
+        self.charge_submission(owner, app_code.as_deref())
+    }
+
+    /// Charges orders that didn't get charged before the validation once
+    /// their owner is verified.
+    fn check_validated_submission_rate(&self, order: &Order) -> Result<(), AddOrderError> {
+        if order.signature.scheme().is_ecdsa_scheme() {
+            return Ok(());
+        }
+        let app_code = order
+            .metadata
+            .full_app_data
+            .as_deref()
+            .and_then(crate::database::app_data::app_code);
+        self.charge_submission(order.metadata.owner, app_code.as_deref())
+    }
+
+    fn charge_submission(&self, owner: H160, app_code: Option<&str>) -> Result<(), AddOrderError> {
+        let Some(limiter) = &self.submission_rate_limiter else {
+            return Ok(());
+        };
+        limiter
+            .check(owner, app_code)
+            .map_err(|retry_after| AddOrderError::RateLimited { retry_after })
+    }
+
+    /// Rejects the order if its owner already reached the open order limit.
+    /// This happens before the comparatively expensive validation. Orders
+    /// whose owner can't be determined yet are left to the validation.
+    ///
+    /// `open_orders` caches the number of open orders per owner and counts
+    /// the orders that passed this check so it can be shared between the
+    /// orders of a batch.
+    async fn check_open_order_limit(
+        &self,
+        payload: &OrderCreation,
+        open_orders: &mut HashMap<H160, u64>,
+    ) -> Result<(), AddOrderError> {
+        let Ok(owner) = payload.verify_owner(&self.domain_separator, None) else {
+            return Ok(());
+        };
+        let Some(limit) = self.open_order_limit.limit_for(&owner) else {
+            return Ok(());
+        };
+        let current = match open_orders.entry(owner) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.database.count_open_orders(&owner).await?),
+        };
+        check_open_orders(limit, *current)?;
+        *current += 1;
+        Ok(())
+    }
+
+    /// Validates an order creation payload and constructs the order to be
+    /// stored from it.
+    async fn validate_order(
+        &self,
+        payload: OrderCreation,
+        source: Option<OrderSource>,
+    ) -> Result<(Order, Option<Quote>), AddOrderError> {
+        let full_app_data_override = match payload.app_data {
+            OrderCreationAppData::Hash { hash } => {
+                let start = Instant::now();
+                let result = self.app_data.find(&hash).await;
+                Metrics::on_stage_completed("app_data_lookup", start);
+                result?
+            }
+            _ => None,
+        };
+        let full_app_data = match (&payload.app_data, &full_app_data_override) {
+            (_, Some(full)) => Some(full),
+            (OrderCreationAppData::Full { full } | OrderCreationAppData::Both { full, .. }, _) => {
+                Some(full)
+            }
+            (OrderCreationAppData::Hash { .. }, None) => None,
+        };
+        self.check_hook_gas(full_app_data.map(String::as_str))?;
+
+        let start = Instant::now();
+        let result = self
+            .validate_and_check_order_value(payload, full_app_data_override)
+            .await;
+        Metrics::on_stage_completed("validation", start);
+        let (mut order, quote) = result?;
+        order.metadata.source = source.map(|source| with_app_code(source, &order));
+        Ok((order, quote))
+    }
+
+    /// Rejects orders whose app data requests more hook gas than allowed.
+    fn check_hook_gas(&self, full_app_data: Option<&str>) -> Result<(), AddOrderError> {
+        let Some(full_app_data) = full_app_data else {
+            return Ok(());
+        };
+        self.app_data
+            .check_hook_gas(full_app_data)
+            .map_err(|err| AddOrderError::ExcessiveHookGas {
+                requested: err.requested,
+                max: err.max,
+            })
+    }
+
+    async fn validate_and_check_order_value(
+        &self,
+        payload: OrderCreation,
+        full_app_data_override: Option<String>,
+    ) -> Result<(Order, Option<Quote>), AddOrderError> {
+        // The owner only gets recovered during validation so trusted liquidity
+        // providers are identified by `from`. Validation rejects orders whose
+        // `from` doesn't match the signature.
+        let trusted = payload
+            .from
+            .is_some_and(|from| self.trusted_liquidity_providers.contains(&from));
+        let order = if trusted {
+            self.order_validator
+                .validate_and_construct_order_without_balance_check(
+                    payload,
+                    &self.domain_separator,
+                    self.settlement_contract,
+                    full_app_data_override,
+                )
+                .await?
+        } else {
+            self.order_validator
+                .validate_and_construct_order(
+                    payload,
+                    &self.domain_separator,
+                    self.settlement_contract,
+                    full_app_data_override,
+                )
+                .await?
+        };
+        self.check_order_value(&order.0).await?;
+        Ok(order)
+    }
+
+    /// Rejects orders whose sell amount is worth less than the minimum order
+    /// value. Orders whose sell token has no native price get accepted.
+    async fn check_order_value(&self, order: &Order) -> Result<(), AddOrderError> {
+        let Some(MinOrderValue {
+            min,
+            native_price_estimator,
+        }) = &self.min_order_value
+        else {
+            return Ok(());
+        };
+        let token = order.data.sell_token;
+        let price = match native_price_estimator.estimate_native_price(token).await {
+            Ok(price) => price,
+            Err(err) => {
+                tracing::debug!(?token, ?err, "no native price to check order value");
+                Metrics::get().orders_without_native_price.inc();
+                return Ok(());
+            }
+        };
+        let actual = U256::from_f64_lossy(order.data.sell_amount.to_f64_lossy() * price);
+        if actual < *min {
+            return Err(AddOrderError::OrderValueTooSmall { min: *min, actual });
+        }
+        Ok(())
+    }
+
+    /// Finds an order for cancellation with the specified signature.
+    /// Replacements don't have a cancellation signature.
     ///
     /// Returns an error if the order cannot be found or cannot be cancelled.
     async fn find_order_for_cancellation(
         &self,
         order_uid: &OrderUid,
+        signature: Option<&CancellationSignature>,
     ) -> Result<Order, OrderCancellationError> {
         let order = self
-            .database
-            .single_order(order_uid)
+            .order_cache
+            .get(order_uid, &self.database)
             .await?
             .ok_or(OrderCancellationError::OrderNotFound)?;
+        self.check_cancellable(&order, signature).await?;
+        Ok(order)
+    }
+
+    /// Checks that the order is in a state that allows cancelling it with the
+    /// off-chain signature.
+    async fn check_cancellable(
+        &self,
+        order: &Order,
+        signature: Option<&CancellationSignature>,
+    ) -> Result<(), OrderCancellationError> {
+        // Orders whose pre-signature was never set have no on-chain commitment
+        // so their owner can also cancel them off-chain. Orders that were
+        // pre-signed at some point have to be cancelled on-chain because the
+        // pre-signature could be set again.
+        if order.metadata.status == OrderStatus::PresignaturePending {
+            let ecdsa_signed = matches!(signature, Some(CancellationSignature::Ecdsa { .. }));
+            if !ecdsa_signed || self.database.was_presigned(&order.metadata.uid).await? {
+                return Err(OrderCancellationError::OnChainOrder);
+            }
+        }
 
         match order.metadata.status {
-            OrderStatus::PresignaturePending => return Err(OrderCancellationError::OnChainOrder),
-            OrderStatus::Open if !order.signature.scheme().is_ecdsa_scheme() => {
+            OrderStatus::Open if order.signature.scheme() == SigningScheme::PreSign => {
                 return Err(OrderCancellationError::OnChainOrder);
             }
             OrderStatus::Fulfilled => return Err(OrderCancellationError::OrderFullyExecuted),
@@ -250,35 +1197,130 @@ impl Orderbook {
             _ => {}
         }
 
-        Ok(order)
+        Ok(())
+    }
+
+    /// Returns the signer of an order cancellation. For EIP-1271 signatures
+    /// this is the claimed owner after its `isValidSignature` function
+    /// accepted the signature.
+    async fn cancellation_signer(
+        &self,
+        signature: &CancellationSignature,
+        struct_hash: &[u8; 32],
+    ) -> Result<H160, OrderCancellationError> {
+        match signature {
+            CancellationSignature::Ecdsa { .. } => signature
+                .recover(&self.domain_separator, struct_hash)
+                .map_err(|_| OrderCancellationError::InvalidSignature),
+            CancellationSignature::Eip1271 { owner, signature } => {
+                let check = SignatureCheck {
+                    signer: *owner,
+                    hash: hashed_eip712_message(&self.domain_separator, struct_hash),
+                    signature: signature.clone(),
+                    interactions: Vec::new(),
+                };
+                let result = self
+                    .signature_validator
+                    .validate_signatures(vec![check])
+                    .await
+                    .pop()
+                    .context("missing signature validation result")?;
+                match result {
+                    Ok(()) => Ok(*owner),
+                    Err(SignatureValidationError::Invalid) => {
+                        Err(OrderCancellationError::InvalidEip1271Signature)
+                    }
+                    Err(SignatureValidationError::Other(err)) => Err(err.into()),
+                }
+            }
+        }
     }
 
     pub async fn cancel_orders(
         &self,
         cancellation: SignedOrderCancellations,
     ) -> Result<(), OrderCancellationError> {
-        let mut orders = Vec::new();
-        for order_uid in &cancellation.data.order_uids {
-            orders.push(self.find_order_for_cancellation(order_uid).await?);
+        record_rejection(
+            OrderOperation::Cancelled,
+            self.cancel_orders_inner(cancellation).await,
+        )
+    }
+
+    async fn cancel_orders_inner(
+        &self,
+        cancellation: SignedOrderCancellations,
+    ) -> Result<(), OrderCancellationError> {
+        check_cancellation_valid_to(
+            cancellation.data.valid_to,
+            self.accept_cancellations_without_valid_to,
+            now_in_epoch_seconds(),
+        )?;
+        let order_uids = &cancellation.data.order_uids;
+        if order_uids.len() > self.max_cancellation_batch_size {
+            return Err(OrderCancellationError::TooManyOrders {
+                count: order_uids.len(),
+                max: self.max_cancellation_batch_size,
+            });
         }
+        let invalid_order = |uid: &OrderUid, reason| OrderCancellationError::InvalidOrder {
+            uid: *uid,
+            reason: Box::new(reason),
+        };
 
-        // Verify the cancellation signer is the same as the order signers
-        let signer = cancellation
-            .validate(&self.domain_separator)
-            .map_err(|_| OrderCancellationError::InvalidSignature)?;
-        if orders.iter().any(|order| signer != order.metadata.owner) {
-            return Err(OrderCancellationError::WrongOwner);
+        // All orders get loaded with a single query. This bypasses the order
+        // cache which is only meant for single order lookups.
+        let found = self.database.orders_by_uids(order_uids).await?;
+        let mut orders = Vec::with_capacity(order_uids.len());
+        for uid in order_uids {
+            let order = found
+                .get(uid)
+                .ok_or_else(|| invalid_order(uid, OrderCancellationError::OrderNotFound))?;
+            self.check_cancellable(order, Some(&cancellation.signature))
+                .await
+                .map_err(|err| invalid_order(uid, err))?;
+            orders.push(order.clone());
+        }
+
+        // Verify the cancellation signer is the same as the order signers
+        let signer = self
+            .cancellation_signer(&cancellation.signature, &cancellation.data.hash_struct())
+            .await?;
+        if let Some(order) = orders.iter().find(|order| signer != order.metadata.owner) {
+            return Err(invalid_order(
+                &order.metadata.uid,
+                OrderCancellationError::WrongOwner,
+            ));
         };
 
         // orders are already known to exist in DB at this point, and signer is
-        // known to be correct!
-        self.database
-            .cancel_orders(cancellation.data.order_uids, Utc::now())
-            .await?;
+        // known to be correct! The same signed message can only be applied
+        // once though and the orders might have been executed in the meantime
+        // which gets checked again while cancelling.
+        let hash = cancellation.data.hash_struct();
+        let proof = CancellationProof {
+            signature: cancellation.signature,
+            signer,
+            timestamp: Utc::now(),
+            message: Some(CancellationMessage::OrderCancellations(
+                cancellation.data.clone(),
+            )),
+            domain_separator: Some(H256(self.domain_separator.0)),
+        };
+        let result = self
+            .database
+            .cancel_orders(cancellation.data.order_uids, hash, &proof)
+            .await;
+        // Even failed cancellations can reveal that the cached orders are
+        // outdated, e.g. because they got executed in the meantime.
+        self.order_cache
+            .invalidate(orders.iter().map(|order| &order.metadata.uid));
+        result?;
 
         for order in &orders {
             tracing::debug!(order_uid =% order.metadata.uid, "order cancelled");
             Metrics::on_order_operation(order, OrderOperation::Cancelled);
+            self.order_events
+                .publish(order.metadata.uid, OrderEventKind::Cancelled);
         }
 
         Ok(())
@@ -288,27 +1330,208 @@ impl Orderbook {
         &self,
         cancellation: OrderCancellation,
     ) -> Result<(), OrderCancellationError> {
+        record_rejection(
+            OrderOperation::Cancelled,
+            self.cancel_order_inner(cancellation).await,
+        )
+    }
+
+    async fn cancel_order_inner(
+        &self,
+        cancellation: OrderCancellation,
+    ) -> Result<(), OrderCancellationError> {
+        check_cancellation_valid_to(
+            cancellation.valid_to,
+            self.accept_cancellations_without_valid_to,
+            now_in_epoch_seconds(),
+        )?;
         let order = self
-            .find_order_for_cancellation(&cancellation.order_uid)
+            .find_order_for_cancellation(&cancellation.order_uid, Some(&cancellation.signature))
             .await?;
 
         // Verify the cancellation signer is the same as the order signer.
-        let signer = cancellation
-            .validate(&self.domain_separator)
-            .map_err(|_| OrderCancellationError::InvalidSignature)?;
+        let signer = self
+            .cancellation_signer(&cancellation.signature, &cancellation.hash_struct())
+            .await?;
         if signer != order.metadata.owner {
             return Err(OrderCancellationError::WrongOwner);
         };
 
         // order is already known to exist in DB at this point, and signer is
-        // known to be correct!
-        self.database
-            .cancel_order(&order.metadata.uid, Utc::now())
-            .await?;
+        // known to be correct! The same signed message can only be applied
+        // once though and the order might have been executed in the meantime
+        // which gets checked again while cancelling.
+        let hash = cancellation.hash_struct();
+        let proof = CancellationProof {
+            signature: cancellation.signature,
+            signer,
+            timestamp: Utc::now(),
+            message: Some(CancellationMessage::OrderCancellation {
+                order_uid: cancellation.order_uid,
+                valid_to: cancellation.valid_to,
+            }),
+            domain_separator: Some(H256(self.domain_separator.0)),
+        };
+        let result = self
+            .database
+            .cancel_order(&order.metadata.uid, hash, &proof)
+            .await;
+        self.order_cache.invalidate([&order.metadata.uid]);
+        result?;
 
         tracing::debug!(order_uid =% order.metadata.uid, "order cancelled");
         Metrics::on_order_operation(&order, OrderOperation::Cancelled);
+        self.order_events
+            .publish(order.metadata.uid, OrderEventKind::Cancelled);
+
+        Ok(())
+    }
+
+    /// Returns the orders of the replacement chain the order is part of, from
+    /// the original order to the most recent replacement. Orders can get
+    /// replaced multiple times so chains can be arbitrarily long.
+    pub async fn get_replacement_chain(&self, uid: &OrderUid) -> Result<Vec<OrderUid>> {
+        self.database.replacement_chain(uid).await
+    }
+
+    /// Returns the signed off-chain cancellation of the order if it got
+    /// cancelled that way.
+    pub async fn get_cancellation_proof(
+        &self,
+        uid: &OrderUid,
+    ) -> Result<Option<CancellationProof>> {
+        self.database.get_cancellation_proof(uid).await
+    }
+
+    /// Returns the quote the order was created with. Use
+    /// [`OrderQuote::into_quote`] if it doesn't matter whether the order
+    /// doesn't exist or was created without a quote.
+    pub async fn get_order_quote(&self, uid: &OrderUid) -> Result<OrderQuote> {
+        if let Some(quote) = self.database.order_quote(uid).await? {
+            return Ok(OrderQuote::Quote(quote));
+        }
+        Ok(match self.database.single_order(uid).await? {
+            Some(_) => OrderQuote::NoQuote,
+            None => OrderQuote::OrderNotFound,
+        })
+    }
+
+    /// Returns the individual fills of an order ordered by block. Orders that
+    /// don't exist or were never filled have no executions.
+    pub async fn get_order_executions(&self, uid: &OrderUid) -> Result<Vec<OrderExecution>> {
+        self.database
+            .order_executions(uid)
+            .await
+            .context("get_order_executions error")
+    }
+
+    /// Returns the surplus the owner received with orders created in the
+    /// `[from, to)` window. Amounts are converted to the native token with the
+    /// prices of the auctions in which the orders got settled.
+    pub async fn get_user_surplus(
+        &self,
+        owner: &H160,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<UserSurplus> {
+        self.database
+            .user_surplus(owner, from, to)
+            .await
+            .context("get_user_surplus error")
+    }
+
+    /// Cancels all open orders of the cancellation's owner that were created
+    /// at or before its timestamp and returns their uids. Orders that are not
+    /// open or can only be cancelled on-chain are skipped.
+    pub async fn cancel_all_orders(
+        &self,
+        cancellation: SignedCancelAll,
+    ) -> Result<Vec<OrderUid>, OrderCancellationError> {
+        record_rejection(
+            OrderOperation::Cancelled,
+            self.cancel_all_orders_inner(cancellation).await,
+        )
+    }
+
+    async fn cancel_all_orders_inner(
+        &self,
+        cancellation: SignedCancelAll,
+    ) -> Result<Vec<OrderUid>, OrderCancellationError> {
+        let signer = cancellation
+            .validate(&self.domain_separator)
+            .map_err(|_| OrderCancellationError::InvalidSignature)?;
+        if signer != cancellation.data.owner {
+            return Err(OrderCancellationError::WrongOwner);
+        }
+
+        let created_before = i64::try_from(cancellation.data.timestamp)
+            .ok()
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+            .context("invalid cancellation timestamp")?;
+        let proof = CancellationProof {
+            signature: CancellationSignature::Ecdsa {
+                signature: cancellation.signature,
+                signing_scheme: cancellation.signing_scheme,
+            },
+            signer,
+            timestamp: Utc::now(),
+            message: Some(CancellationMessage::CancelAll(cancellation.data)),
+            domain_separator: Some(H256(self.domain_separator.0)),
+        };
+        let orders = self
+            .database
+            .cancel_all_orders(&cancellation.data.owner, created_before, &proof)
+            .await?;
+        self.order_cache
+            .invalidate(orders.iter().map(|order| &order.metadata.uid));
+
+        for order in &orders {
+            tracing::debug!(order_uid =% order.metadata.uid, "order cancelled");
+            Metrics::on_order_operation(order, OrderOperation::Cancelled);
+            self.order_events
+                .publish(order.metadata.uid, OrderEventKind::Cancelled);
+        }
+
+        Ok(orders.into_iter().map(|order| order.metadata.uid).collect())
+    }
+
+    /// Reduces the amounts of a partially fillable order without changing its
+    /// uid. Only the remainder up to the new amounts gets exposed in the
+    /// auction.
+    pub async fn reduce_order(&self, amendment: OrderAmendment) -> Result<(), OrderAmendmentError> {
+        let order = self
+            .order_cache
+            .get(&amendment.order_uid, &self.database)
+            .await?
+            .ok_or(OrderAmendmentError::OrderNotFound)?;
+
+        let signer = amendment
+            .validate(&self.domain_separator)
+            .map_err(|_| OrderAmendmentError::InvalidSignature)?;
+        if signer != order.metadata.owner {
+            return Err(OrderAmendmentError::WrongOwner);
+        }
+        validate_amendment(&order, &amendment)?;
+
+        // The database checks the executed amounts again in case a settlement
+        // got indexed since we fetched the order.
+        let amended = self
+            .database
+            .amend_order(
+                &amendment.order_uid,
+                AmendedAmounts {
+                    sell_amount: amendment.sell_amount,
+                    buy_amount: amendment.buy_amount,
+                },
+                Utc::now(),
+            )
+            .await;
+        self.order_cache.invalidate([&amendment.order_uid]);
+        if !amended? {
+            return Err(OrderAmendmentError::BelowExecutedAmount);
+        }
 
+        tracing::debug!(order_uid =% order.metadata.uid, "order reduced");
         Ok(())
     }
 
@@ -316,18 +1539,160 @@ impl Orderbook {
         &self,
         old_order: OrderUid,
         new_order: OrderCreation,
+        source: OrderSource,
     ) -> Result<OrderUid, ReplaceOrderError> {
-        // Replacement order signatures need to be validated meaning we cannot
-        // accept `PreSign` orders, otherwise anyone can cancel a user order by
-        // submitting a `PreSign` order on someone's behalf.
-        new_order
-            .signature
-            .scheme()
-            .try_to_ecdsa_scheme()
-            .ok_or(ReplaceOrderError::InvalidReplacement)?;
-
-        let old_order = self.find_order_for_cancellation(&old_order).await?;
-        let (new_order, new_quote) = self
+        record_replacement_failure(self.replace_order_inner(old_order, new_order, source).await)
+    }
+
+    async fn replace_order_inner(
+        &self,
+        old_order: OrderUid,
+        new_order: OrderCreation,
+        source: OrderSource,
+    ) -> Result<OrderUid, ReplaceOrderError> {
+        let (old_order, new_order, new_quote) = self
+            .validate_replacement(old_order, new_order, Some(source))
+            .await?;
+
+        let start = Instant::now();
+        let result = self
+            .database
+            .replace_order(&old_order.metadata.uid, &new_order, new_quote)
+            .await;
+        Metrics::on_stage_completed("db_replace", start);
+        self.order_cache
+            .invalidate([&old_order.metadata.uid, &new_order.metadata.uid]);
+        if let Err(err) = result {
+            return Err(
+                AddOrderError::from_insertion(err, &new_order, &self.database)
+                    .await
+                    .into(),
+            );
+        }
+        self.on_order_replaced(&old_order, &new_order);
+
+        Ok(new_order.metadata.uid)
+    }
+
+    /// Replaces multiple orders in a single transaction. All replacements get
+    /// validated like individual replacements before any of them gets stored
+    /// and a single invalid replacement rejects the whole batch.
+    pub async fn replace_orders(
+        &self,
+        replacements: Vec<(OrderUid, OrderCreation)>,
+        source: OrderSource,
+    ) -> Result<Vec<OrderUid>, ReplaceOrderError> {
+        record_replacement_failure(self.replace_orders_inner(replacements, source).await)
+    }
+
+    async fn replace_orders_inner(
+        &self,
+        replacements: Vec<(OrderUid, OrderCreation)>,
+        source: OrderSource,
+    ) -> Result<Vec<OrderUid>, ReplaceOrderError> {
+        let start = Instant::now();
+        let old_orders = replaced_orders(&self.database, &replacements).await;
+        Metrics::on_stage_completed("replacement_lookup", start);
+        let mut old_orders = old_orders?;
+
+        let mut validated = Vec::with_capacity(replacements.len());
+        let mut replaced = HashSet::new();
+        for (index, (old_order, new_order)) in replacements.into_iter().enumerate() {
+            let batch_error = |source| ReplaceOrderError::Batch {
+                index,
+                source: Box::new(source),
+            };
+            // Every order can only be replaced once.
+            if !replaced.insert(old_order) {
+                return Err(batch_error(ReplaceOrderError::InvalidReplacement));
+            }
+            let result = async {
+                check_replacement_signature(&new_order)?;
+                let old_order = old_orders
+                    .remove(&old_order)
+                    .ok_or(OrderCancellationError::OrderNotFound)?;
+                self.check_cancellable(&old_order, None).await?;
+                self.validate_replacement_of(old_order, new_order, Some(source.clone()))
+                    .await
+            }
+            .await;
+            validated.push(result.map_err(batch_error)?);
+        }
+
+        let start = Instant::now();
+        let result = self
+            .database
+            .replace_orders(
+                validated
+                    .iter()
+                    .map(|(old_order, new_order, new_quote)| {
+                        (old_order.metadata.uid, new_order.clone(), new_quote.clone())
+                    })
+                    .collect(),
+            )
+            .await;
+        Metrics::on_stage_completed("db_replace", start);
+        self.order_cache
+            .invalidate(validated.iter().flat_map(|(old_order, new_order, _)| {
+                [&old_order.metadata.uid, &new_order.metadata.uid]
+            }));
+        if let Err((index, err)) = result.map_err(AddOrderError::Database)? {
+            let (_, new_order, _) = &validated[index];
+            let err = AddOrderError::from_insertion(err, new_order, &self.database).await;
+            return Err(ReplaceOrderError::Batch {
+                index,
+                source: Box::new(err.into()),
+            });
+        }
+
+        for (old_order, new_order, _) in &validated {
+            self.on_order_replaced(old_order, new_order);
+        }
+
+        Ok(validated
+            .into_iter()
+            .map(|(_, new_order, _)| new_order.metadata.uid)
+            .collect())
+    }
+
+    fn on_order_replaced(&self, old_order: &Order, new_order: &Order) {
+        Metrics::on_order_operation(new_order, OrderOperation::Replaced);
+        self.order_events
+            .publish(old_order.metadata.uid, OrderEventKind::Replaced);
+        self.order_events
+            .publish(new_order.metadata.uid, OrderEventKind::Created);
+    }
+
+    /// Checks that the new order is a valid replacement of the old one and
+    /// returns both orders together with the quote to store for the new one.
+    async fn validate_replacement(
+        &self,
+        old_order: OrderUid,
+        new_order: OrderCreation,
+        source: Option<OrderSource>,
+    ) -> Result<(Order, Order, Option<Quote>), ReplaceOrderError> {
+        check_replacement_signature(&new_order)?;
+
+        let start = Instant::now();
+        let old_order = self.find_order_for_cancellation(&old_order, None).await;
+        Metrics::on_stage_completed("replacement_lookup", start);
+
+        self.validate_replacement_of(old_order?, new_order, source)
+            .await
+    }
+
+    /// Like `validate_replacement` for an old order that was already looked
+    /// up and checked to be cancellable.
+    async fn validate_replacement_of(
+        &self,
+        old_order: Order,
+        new_order: OrderCreation,
+        source: Option<OrderSource>,
+    ) -> Result<(Order, Order, Option<Quote>), ReplaceOrderError> {
+        self.check_unsupported_tokens(&new_order)?;
+        self.check_submission_rate(&new_order)?;
+        let start = Instant::now();
+        let new_order = self
             .order_validator
             .validate_and_construct_order(
                 new_order,
@@ -335,84 +1700,594 @@ impl Orderbook {
                 self.settlement_contract,
                 None,
             )
-            .await?;
+            .await;
+        Metrics::on_stage_completed("replacement_validation", start);
+        let (mut new_order, new_quote) = new_order?;
+        self.check_validated_submission_rate(&new_order)?;
+        self.check_hook_gas(new_order.metadata.full_app_data.as_deref())?;
+        self.check_order_value(&new_order).await?;
 
         // Verify that the new order is a valid replacement order by checking
-        // that the `app_data` encodes an order cancellation and that both the
-        // old and new orders have the same signer.
+        // that both the old and new orders have the same signer and that the
+        // `app_data` marks the old order as replaced. Either the full app data
+        // contains a `replacedOrder` marker or, for legacy integrations, the
+        // app data hash encodes an order cancellation.
+        if new_order.metadata.owner != old_order.metadata.owner {
+            return Err(ReplaceOrderError::InvalidReplacement);
+        }
         let cancellation = OrderCancellation {
             order_uid: old_order.metadata.uid,
             ..Default::default()
         };
-        if new_order.data.app_data != cancellation.hash_struct()
-            || new_order.metadata.owner != old_order.metadata.owner
-        {
-            return Err(ReplaceOrderError::InvalidReplacement);
+        if new_order.data.app_data != cancellation.hash_struct() {
+            let replaced_order = new_order
+                .metadata
+                .full_app_data
+                .as_deref()
+                .and_then(|document| self.app_data.replaced_order(document))
+                .ok_or(ReplaceOrderError::InvalidReplacementMarker)?;
+            if replaced_order != old_order.metadata.uid {
+                return Err(ReplaceOrderError::InvalidReplacement);
+            }
         }
 
-        self.database
-            .replace_order(&old_order.metadata.uid, &new_order, new_quote)
-            .await
-            .map_err(|err| AddOrderError::from_insertion(err, &new_order))?;
-        Metrics::on_order_operation(&old_order, OrderOperation::Cancelled);
-        Metrics::on_order_operation(&new_order, OrderOperation::Created);
+        // Replacements that keep the economic parameters of the old order (e.g.
+        // only extend its validity) keep the quote of the old order.
+        let new_quote = if has_same_economic_parameters(&old_order, &new_order) {
+            self.database
+                .order_quote(&old_order.metadata.uid)
+                .await
+                .map_err(AddOrderError::Database)?
+                .or(new_quote)
+        } else {
+            new_quote
+        };
+        new_order.metadata.quote_verified =
+            new_quote.as_ref().is_some_and(|quote| quote.data.verified);
+        new_order.metadata.source = source.map(|source| with_app_code(source, &new_order));
 
-        Ok(new_order.metadata.uid)
+        Ok((old_order, new_order, new_quote))
     }
 
+    /// Returns the order. Recently looked up orders are served from a short
+    /// lived cache so their status can lag behind changes made by other
+    /// processes (e.g. trades) by a few seconds.
     pub async fn get_order(&self, uid: &OrderUid) -> Result<Option<Order>> {
-        self.database.single_order(uid).await
+        self.order_cache.get(uid, &self.database).await
+    }
+
+    /// Like `get_order` but also returns the hashes of the mined settlement
+    /// transactions that executed the order. Fills whose settlement hash
+    /// didn't get back-filled yet are left out. The order doesn't get served
+    /// from the cache so its executed amounts match the returned hashes.
+    pub async fn get_order_with_executions(&self, uid: &OrderUid) -> Result<Option<Order>> {
+        let Some(mut order) = self.database.single_order(uid).await? else {
+            return Ok(None);
+        };
+        let executions = self.get_order_executions(uid).await?;
+        let mut hashes = Vec::new();
+        for tx_hash in executions
+            .into_iter()
+            .filter_map(|execution| execution.tx_hash)
+        {
+            // A settlement can fill the same order multiple times.
+            if !hashes.contains(&tx_hash) {
+                hashes.push(tx_hash);
+            }
+        }
+        order.metadata.settlement_tx_hashes = Some(hashes);
+        Ok(Some(order))
+    }
+
+    /// Orders settled in the transaction. If the transaction got replaced by
+    /// another one with the same sender and nonce, the orders of the mined
+    /// transaction get returned instead.
+    pub async fn get_orders_for_tx(&self, hash: &H256) -> Result<TxOrders> {
+        let orders = self.database.orders_for_tx(hash).await?;
+        if !orders.is_empty() {
+            return Ok(TxOrders {
+                orders,
+                remapped_tx_hash: None,
+            });
+        }
+
+        let sender_and_nonce = match self.transactions.sender_and_nonce(*hash).await {
+            Ok(Some(sender_and_nonce)) => sender_and_nonce,
+            Ok(None) => return Ok(Default::default()),
+            Err(err) => {
+                tracing::warn!(?err, ?hash, "failed to fetch transaction");
+                return Ok(Default::default());
+            }
+        };
+        let (sender, nonce) = sender_and_nonce;
+        let nonce = i64::try_from(nonce).context("nonce overflow")?;
+        let mined = match self.database.settlement_tx_hash(&sender, nonce).await? {
+            Some(mined) if mined != *hash => mined,
+            _ => return Ok(Default::default()),
+        };
+        Ok(TxOrders {
+            orders: self.database.orders_for_tx(&mined).await?,
+            remapped_tx_hash: Some(mined),
+        })
     }
 
-    pub async fn get_orders_for_tx(&self, hash: &H256) -> Result<Vec<Order>> {
-        self.database.orders_for_tx(hash).await
+    /// Returns readiness information. Unlike liveness this reflects short
+    /// database outages immediately.
+    pub async fn health(&self) -> Health {
+        self.health.health().await
     }
 
-    pub async fn get_auction(&self) -> Result<Option<dto::AuctionWithId>> {
-        let auction = match self.database.most_recent_auction().await? {
+    /// Returns the current auction. Returns an error if the auction is older
+    /// than the maximum auction age so solvers don't work on outdated data.
+    pub async fn get_auction(&self) -> Result<Option<dto::AuctionWithId>, AuctionRetrievalError> {
+        let (auction, created) = match self.auctions.most_recent_auction().await? {
             Some(auction) => auction,
             None => {
                 tracing::warn!("there is no current auction");
                 return Ok(None);
             }
         };
+        self.on_auction_observed(auction.id);
+        if let Some(age) = self.stale_auction_age(created) {
+            tracing::warn!(id = auction.id, ?age, "current auction is stale");
+            return Err(AuctionRetrievalError::Stale {
+                id: auction.id,
+                age,
+            });
+        }
         Ok(Some(auction))
     }
 
+    /// Returns the age of an auction created at `created` if it exceeds the
+    /// maximum auction age.
+    fn stale_auction_age(&self, created: DateTime<Utc>) -> Option<Duration> {
+        let max_age = self.max_auction_age?;
+        let age = (Utc::now() - created).to_std().unwrap_or_default();
+        (age > max_age).then_some(age)
+    }
+
+    /// Whether the most recent auction is stale. Failing to load the auction
+    /// doesn't count as stale since database outages are tracked separately.
+    async fn is_auction_stale(&self) -> bool {
+        if self.max_auction_age.is_none() {
+            return false;
+        }
+        match self.auctions.most_recent_auction_created().await {
+            Ok(Some(created)) => self.stale_auction_age(created).is_some(),
+            Ok(None) => false,
+            Err(err) => {
+                tracing::warn!(?err, "failed to load creation time of current auction");
+                false
+            }
+        }
+    }
+
+    /// Returns the auction with the specified id. Returns an error for
+    /// auctions that are older than the retention window and `None` for
+    /// auctions that never existed.
+    pub async fn get_auction_by_id(
+        &self,
+        auction_id: dto::AuctionId,
+    ) -> Result<Option<dto::AuctionWithId>, AuctionRetrievalError> {
+        let Some(latest) = self.database.most_recent_auction_id().await? else {
+            return Ok(None);
+        };
+        let retention = i64::try_from(self.auction_retention).unwrap_or(i64::MAX);
+        if auction_id <= latest.saturating_sub(retention) {
+            return Err(AuctionRetrievalError::Pruned(auction_id));
+        }
+        Ok(self.database.auction_by_id(auction_id).await?)
+    }
+
+    /// Returns the current auction if it is newer than `last_seen_id`.
+    /// Otherwise waits for a newer auction for up to `timeout` and returns
+    /// `None` if none appeared.
+    pub async fn get_auction_after(
+        &self,
+        last_seen_id: dto::AuctionId,
+        timeout: Duration,
+    ) -> Result<Option<dto::AuctionWithId>> {
+        let mut latest = self.latest_auction_id.subscribe();
+        if let Some(auction) = self.get_auction().await? {
+            if auction.id > last_seen_id {
+                return Ok(Some(auction));
+            }
+        }
+
+        let is_newer = |id: &Option<dto::AuctionId>| id.is_some_and(|id| id > last_seen_id);
+        if !matches!(
+            tokio::time::timeout(timeout, latest.wait_for(is_newer)).await,
+            Ok(Ok(_))
+        ) {
+            return Ok(None);
+        }
+        Ok(self
+            .get_auction()
+            .await?
+            .filter(|auction| auction.id > last_seen_id))
+    }
+
+    /// Returns the number of open orders per `(sell_token, buy_token)` pair
+    /// that are valid at least until `min_valid_to`, ordered by descending
+    /// count.
+    pub async fn count_open_orders_by_pair(
+        &self,
+        min_valid_to: u32,
+    ) -> Result<Vec<(H160, H160, u64)>> {
+        self.database.count_open_orders_by_pair(min_valid_to).await
+    }
+
+    /// Periodically updates the metric of open orders per token pair. Only
+    /// the pairs with the most open orders get reported.
+    pub async fn observe_open_orders_by_pair(self: Arc<Self>, interval: Duration) {
+        loop {
+            match self.count_open_orders_by_pair(now_in_epoch_seconds()).await {
+                Ok(counts) => {
+                    let gauge = &Metrics::get().open_orders_by_pair;
+                    // Pairs that dropped out of the top get removed.
+                    gauge.reset();
+                    for (sell_token, buy_token, count) in
+                        counts.into_iter().take(MAX_REPORTED_PAIRS)
+                    {
+                        gauge
+                            .with_label_values(&[
+                                &format!("{sell_token:?}"),
+                                &format!("{buy_token:?}"),
+                            ])
+                            .set(count.try_into().unwrap_or(i64::MAX));
+                    }
+                }
+                Err(err) => tracing::warn!(?err, "failed to count open orders by pair"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Returns the number of orders created at or after `since` per app code
+    /// of their full app data, ordered by descending count. Orders whose app
+    /// data has no app code are not counted.
+    pub async fn count_orders_by_app_code(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(String, u64)>> {
+        self.database.count_orders_by_app_code(since).await
+    }
+
+    /// Periodically updates the metric of open orders per app code. Only the
+    /// app codes with the most open orders get reported.
+    pub async fn observe_open_orders_by_app_code(self: Arc<Self>, interval: Duration) {
+        loop {
+            match self
+                .database
+                .count_open_orders_by_app_code(now_in_epoch_seconds())
+                .await
+            {
+                Ok(counts) => {
+                    let gauge = &Metrics::get().open_orders_by_app_code;
+                    // App codes that dropped out of the top get removed.
+                    gauge.reset();
+                    for (app_code, count) in counts.into_iter().take(MAX_REPORTED_APP_CODES) {
+                        gauge
+                            .with_label_values(&[&app_code])
+                            .set(count.try_into().unwrap_or(i64::MAX));
+                    }
+                }
+                Err(err) => tracing::warn!(?err, "failed to count open orders by app code"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Periodically checks for new auctions and wakes up requests waiting in
+    /// `get_auction_after`. Only the auction id gets queried so this is much
+    /// cheaper than every waiting request polling the full auction.
+    pub async fn observe_auctions(self: Arc<Self>, interval: Duration) {
+        loop {
+            match self.database.most_recent_auction_id().await {
+                Ok(Some(id)) => self.on_auction_observed(id),
+                Ok(None) => (),
+                Err(err) => tracing::warn!(?err, "failed to load most recent auction id"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn on_auction_observed(&self, id: dto::AuctionId) {
+        let modified = self.latest_auction_id.send_if_modified(|latest| {
+            if latest.is_some_and(|latest| latest >= id) {
+                return false;
+            }
+            *latest = Some(id);
+            true
+        });
+        if modified {
+            // A new auction gets cut after the autopilot indexed the latest
+            // settlements so cached orders might be outdated.
+            self.order_cache.clear();
+        }
+    }
+
+    /// Periodically checks whether the owners of a random sample of open
+    /// fill-or-kill orders can still transfer the sell amount and flags the
+    /// orders whose owners can't.
+    pub async fn observe_balances(
+        self: Arc<Self>,
+        mut checker: InsufficientBalances,
+        sample_size: u64,
+        interval: Duration,
+    ) {
+        loop {
+            if let Err(err) = self.check_balances(&mut checker, sample_size).await {
+                tracing::warn!(?err, "failed to check balances of open orders");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn check_balances(
+        &self,
+        checker: &mut InsufficientBalances,
+        sample_size: u64,
+    ) -> Result<()> {
+        let orders = self
+            .database
+            .sample_open_fill_or_kill_orders(now_in_epoch_seconds(), sample_size)
+            .await?;
+        let changes = checker.check(&orders).await;
+        for (uids, insufficient_balance) in
+            [(changes.insufficient, true), (changes.sufficient, false)]
+        {
+            if uids.is_empty() {
+                continue;
+            }
+            self.database
+                .set_insufficient_balance(&uids, insufficient_balance)
+                .await?;
+            self.order_cache.invalidate(&uids);
+        }
+        Ok(())
+    }
+
     pub async fn get_user_orders(
         &self,
         owner: &H160,
         offset: u64,
         limit: u64,
+    ) -> Result<Vec<Order>> {
+        self.get_user_orders_with_filter(owner, &Default::default(), offset, limit)
+            .await
+    }
+
+    /// Like `get_user_orders` but only returns orders matching the filter.
+    pub async fn get_user_orders_with_filter(
+        &self,
+        owner: &H160,
+        filter: &UserOrderFilter,
+        offset: u64,
+        limit: u64,
     ) -> Result<Vec<Order>> {
         self.database
-            .user_orders(owner, offset, Some(limit))
+            .user_orders(owner, filter, offset, Some(limit))
             .await
             .context("get_user_orders error")
     }
+
+    /// Like `get_user_orders_with_filter` but also reports how many orders
+    /// match the filter in total, for example to show the number of pages.
+    pub async fn get_user_orders_with_count(
+        &self,
+        owner: &H160,
+        filter: &UserOrderFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<CountedUserOrders> {
+        let (orders, total_count) = self
+            .database
+            .user_orders_with_count(owner, filter, offset, limit)
+            .await
+            .context("get_user_orders_with_count error")?;
+        let has_more = offset.saturating_add(orders.len() as u64) < total_count;
+        Ok(CountedUserOrders {
+            orders,
+            total_count,
+            has_more,
+        })
+    }
+
+    /// Returns the user's orders (newest first) following the cursor. Unlike
+    /// `get_user_orders` this pagination is stable even if the user creates
+    /// new orders while paginating.
+    pub async fn get_user_orders_after(
+        &self,
+        owner: &H160,
+        cursor: Option<Cursor>,
+        limit: u64,
+    ) -> Result<UserOrdersPage> {
+        let orders = self
+            .database
+            .user_orders_before(
+                owner,
+                cursor.map(|cursor| (cursor.creation_date, cursor.uid)),
+                limit,
+            )
+            .await
+            .context("get_user_orders_after error")?;
+        let next = match orders.last() {
+            Some(last) if orders.len() as u64 == limit => Some(Cursor::for_order(last)),
+            _ => None,
+        };
+        Ok(UserOrdersPage { orders, next })
+    }
+
+    /// Returns the user's open orders that expire within the specified
+    /// duration from now, soonest expiry first. Orders that are already fully
+    /// executed or cancelled are not included.
+    pub async fn get_expiring_orders(&self, owner: &H160, within: Duration) -> Result<Vec<Order>> {
+        let now = now_in_epoch_seconds();
+        let within = u32::try_from(within.as_secs()).unwrap_or(u32::MAX);
+        self.database
+            .expiring_orders(owner, now, now.saturating_add(within))
+            .await
+            .context("get_expiring_orders error")
+    }
 }
 
-#[async_trait::async_trait]
-impl LivenessChecking for Orderbook {
-    async fn is_alive(&self) -> bool {
-        self.get_auction().await.is_ok()
+/// Checks that a cancellation with the specified validity can still be applied
+/// at `now`.
+fn check_cancellation_valid_to(
+    valid_to: Option<u32>,
+    accept_without_valid_to: bool,
+    now: u32,
+) -> Result<(), OrderCancellationError> {
+    match valid_to {
+        Some(valid_to) if valid_to >= now => Ok(()),
+        None if accept_without_valid_to => Ok(()),
+        _ => Err(OrderCancellationError::CancellationExpired),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use {
-        super::*,
-        crate::database::orders::MockOrderStoring,
-        ethcontract::H160,
-        mockall::predicate::eq,
-        model::{
-            app_data::AppDataHash,
-            order::{OrderData, OrderMetadata},
-            signature::Signature,
-        },
-        shared::order_validation::MockOrderValidating,
-    };
+/// Checks that an amendment only shrinks the remainder of an open partially
+/// fillable order.
+fn validate_amendment(
+    order: &Order,
+    amendment: &OrderAmendment,
+) -> Result<(), OrderAmendmentError> {
+    if order.metadata.status != OrderStatus::Open {
+        return Err(OrderAmendmentError::NotOpen);
+    }
+    if !order.data.partially_fillable {
+        return Err(OrderAmendmentError::NotPartiallyFillable);
+    }
+
+    let (sell_amount, buy_amount) = match order.metadata.amended_amounts {
+        Some(amended) => (amended.sell_amount, amended.buy_amount),
+        None => (order.data.sell_amount, order.data.buy_amount),
+    };
+    if amendment.sell_amount > sell_amount || amendment.buy_amount > buy_amount {
+        return Err(OrderAmendmentError::IncreasedAmounts);
+    }
+
+    // The signed order still enforces its original limit price on-chain so the
+    // amended amounts have to keep it.
+    let keeps_limit_price = amendment.sell_amount.full_mul(order.data.buy_amount)
+        == amendment.buy_amount.full_mul(order.data.sell_amount);
+    if !keeps_limit_price {
+        return Err(OrderAmendmentError::ChangedLimitPrice);
+    }
+
+    let below_executed = match order.data.kind {
+        OrderKind::Sell => amendment.sell_amount < order.metadata.executed_sell_amount_before_fees,
+        OrderKind::Buy => big_uint_to_u256(&order.metadata.executed_buy_amount)
+            .map_or(true, |executed| amendment.buy_amount < executed),
+    };
+    if below_executed {
+        return Err(OrderAmendmentError::BelowExecutedAmount);
+    }
+
+    Ok(())
+}
+
+fn check_open_orders(limit: u64, current: u64) -> Result<(), AddOrderError> {
+    if current >= limit {
+        return Err(AddOrderError::TooManyOpenOrders { limit, current });
+    }
+    Ok(())
+}
+
+fn has_same_economic_parameters(a: &Order, b: &Order) -> bool {
+    a.data.sell_token == b.data.sell_token
+        && a.data.buy_token == b.data.buy_token
+        && a.data.sell_amount == b.data.sell_amount
+        && a.data.buy_amount == b.data.buy_amount
+        && a.data.kind == b.data.kind
+}
+
+/// Looks up the orders replaced by a batch with a single query.
+async fn replaced_orders(
+    database: &impl OrderStoring,
+    replacements: &[(OrderUid, OrderCreation)],
+) -> Result<HashMap<OrderUid, Order>, ReplaceOrderError> {
+    let old_uids = replacements
+        .iter()
+        .map(|(old_order, _)| *old_order)
+        .collect::<Vec<_>>();
+    database
+        .orders_by_uids(&old_uids)
+        .await
+        .map_err(|err| ReplaceOrderError::Batch {
+            index: 0,
+            source: Box::new(OrderCancellationError::Other(err).into()),
+        })
+}
+
+/// Replacement order signatures need to be validated meaning we cannot accept
+/// `PreSign` orders, otherwise anyone can cancel a user order by submitting a
+/// `PreSign` order on someone's behalf.
+fn check_replacement_signature(new_order: &OrderCreation) -> Result<(), ReplaceOrderError> {
+    new_order
+        .signature
+        .scheme()
+        .try_to_ecdsa_scheme()
+        .ok_or(ReplaceOrderError::InvalidReplacement)?;
+    Ok(())
+}
+
+/// Completes the source of an order posted to the API with the app code of
+/// the order. Orders can reference their app data by hash only so the app
+/// code is only known once the order got validated.
+fn with_app_code(source: OrderSource, order: &Order) -> OrderSource {
+    match source {
+        OrderSource::Api {
+            client_ip_hash,
+            app_code: None,
+        } => OrderSource::Api {
+            client_ip_hash,
+            app_code: order
+                .metadata
+                .full_app_data
+                .as_deref()
+                .and_then(crate::database::app_data::app_code),
+        },
+        source => source,
+    }
+}
+
+#[async_trait::async_trait]
+impl LivenessChecking for Orderbook {
+    async fn is_alive(&self) -> bool {
+        self.health.is_alive().await && !self.is_auction_stale().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{database::orders::MockOrderStoring, health::MockHealthProbing},
+        database::byte_array::ByteArray,
+        ethcontract::H160,
+        futures::FutureExt,
+        mockall::predicate::eq,
+        model::{
+            app_data::AppDataHash,
+            order::{OrderCancellations, OrderData, OrderMetadata},
+            signature::{EcdsaSigningScheme, Signature},
+        },
+        shared::{
+            account_balances::MockBalanceFetching,
+            order_validation::MockOrderValidating,
+            price_estimation::{native::MockNativePriceEstimating, PriceEstimationError},
+            signature_validator::MockSignatureValidating,
+        },
+        web3::signing::{Key, SecretKeyRef},
+    };
+
+    fn api_source() -> OrderSource {
+        OrderSource::Api {
+            client_ip_hash: None,
+            app_code: None,
+        }
+    }
 
     #[tokio::test]
     #[ignore]
@@ -467,17 +2342,9 @@ mod tests {
         let database = crate::database::Postgres::new("postgresql://").unwrap();
         database::clear_DANGER(&database.pool).await.unwrap();
         database.insert_order(&old_order, None).await.unwrap();
-        let app_data = Arc::new(app_data::Registry::new(
-            shared::app_data::Validator::new(8192),
-            database.clone(),
-            None,
-        ));
         let orderbook = Orderbook {
-            database,
             order_validator: Arc::new(order_validator),
-            domain_separator: Default::default(),
-            settlement_contract: H160([0xba; 20]),
-            app_data,
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
         };
 
         // App data does not encode cancellation.
@@ -490,9 +2357,10 @@ mod tests {
                         signature: Signature::Eip712(Default::default()),
                         ..Default::default()
                     },
+                    api_source(),
                 )
                 .await,
-            Err(ReplaceOrderError::InvalidReplacement)
+            Err(ReplaceOrderError::InvalidReplacementMarker)
         ));
 
         // Different owner
@@ -506,6 +2374,7 @@ mod tests {
                         app_data: AppDataHash(cancellation.hash_struct()).into(),
                         ..Default::default()
                     },
+                    api_source(),
                 )
                 .await,
             Err(ReplaceOrderError::InvalidReplacement)
@@ -522,6 +2391,7 @@ mod tests {
                         app_data: AppDataHash(cancellation.hash_struct()).into(),
                         ..Default::default()
                     },
+                    api_source(),
                 )
                 .await,
             Err(ReplaceOrderError::InvalidReplacement)
@@ -538,10 +2408,3133 @@ mod tests {
                         app_data: AppDataHash(cancellation.hash_struct()).into(),
                         ..Default::default()
                     },
+                    api_source(),
+                )
+                .await
+                .unwrap(),
+            new_order_uid,
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_order_with_replacement_marker() {
+        let owner = H160([1; 20]);
+        let old_order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let new_order_uid = OrderUid([2; 56]);
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(move |creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            owner,
+                            uid: new_order_uid,
+                            full_app_data: match &creation.app_data {
+                                OrderCreationAppData::Full { full } => Some(full.clone()),
+                                _ => None,
+                            },
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        database.insert_order(&old_order, None).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let replacement = |full: String| OrderCreation {
+            from: Some(owner),
+            signature: Signature::Eip712(Default::default()),
+            app_data: OrderCreationAppData::Full { full },
+            ..Default::default()
+        };
+        let marker = |uid: OrderUid| {
+            format!(r#"{{"appCode":"CoW Swap","metadata":{{"replacedOrder":{{"uid":"{uid}"}}}}}}"#)
+        };
+
+        // Structured app data without a replacement marker.
+        assert!(matches!(
+            orderbook
+                .replace_order(
+                    old_order.metadata.uid,
+                    replacement(r#"{"appCode":"CoW Swap"}"#.to_string()),
+                    api_source(),
+                )
+                .await,
+            Err(ReplaceOrderError::InvalidReplacementMarker)
+        ));
+
+        // The marker references a different order.
+        assert!(matches!(
+            orderbook
+                .replace_order(
+                    old_order.metadata.uid,
+                    replacement(marker(OrderUid([3; 56]))),
+                    api_source()
+                )
+                .await,
+            Err(ReplaceOrderError::InvalidReplacement)
+        ));
+
+        assert_eq!(
+            orderbook
+                .replace_order(
+                    old_order.metadata.uid,
+                    replacement(marker(old_order.metadata.uid)),
+                    api_source(),
                 )
                 .await
                 .unwrap(),
             new_order_uid,
         );
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_order_rejects_excessive_hook_gas() {
+        let owner = H160([1; 20]);
+        let old_order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let new_order_uid = OrderUid([2; 56]);
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(move |creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            owner,
+                            uid: new_order_uid,
+                            full_app_data: match &creation.app_data {
+                                OrderCreationAppData::Full { full } => Some(full.clone()),
+                                _ => None,
+                            },
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        database.insert_order(&old_order, None).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            app_data: Arc::new(app_data::Registry::new(
+                shared::app_data::Validator::new(8192).with_max_hook_gas(Some(100_000)),
+                database.clone(),
+                None,
+            )),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let replacement = |gas_limit: u64| OrderCreation {
+            from: Some(owner),
+            signature: Signature::Eip712(Default::default()),
+            app_data: OrderCreationAppData::Full {
+                full: format!(
+                    r#"{{"metadata":{{"replacedOrder":{{"uid":"{}"}},"hooks":{{"pre":[{{"target":"0x0101010101010101010101010101010101010101","callData":"0x","gasLimit":"{gas_limit}"}}]}}}}}}"#,
+                    old_order.metadata.uid
+                ),
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            orderbook
+                .replace_order(old_order.metadata.uid, replacement(200_000), api_source())
+                .await,
+            Err(ReplaceOrderError::Add(AddOrderError::ExcessiveHookGas {
+                requested: 200_000,
+                max: 100_000
+            }))
+        ));
+        assert_eq!(
+            orderbook
+                .replace_order(old_order.metadata.uid, replacement(100_000), api_source())
+                .await
+                .unwrap(),
+            new_order_uid,
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_add_orders_reports_results_per_order() {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|creation, _, _, _| {
+                if creation.valid_to == 0 {
+                    return Err(ValidationError::ZeroAmount);
+                }
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            uid: OrderUid([creation.valid_to as u8; 56]),
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let creation = |valid_to| OrderCreation {
+            valid_to,
+            ..Default::default()
+        };
+        let results = orderbook
+            .add_orders(
+                vec![creation(1), creation(0), creation(1), creation(2)],
+                api_source(),
+            )
+            .await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().0, OrderUid([1; 56]));
+        assert!(matches!(
+            results[1],
+            Err(AddOrderError::OrderValidation(ValidationError::ZeroAmount))
+        ));
+        assert!(matches!(
+            results[2],
+            Err(AddOrderError::DuplicatedOrder {
+                signature_matches: true,
+                ..
+            })
+        ));
+        assert_eq!(results[3].as_ref().unwrap().0, OrderUid([2; 56]));
+
+        for uid in [OrderUid([1; 56]), OrderUid([2; 56])] {
+            assert!(orderbook
+                .database
+                .single_order(&uid)
+                .await
+                .unwrap()
+                .is_some());
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_records_stage_latencies() {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            owner: creation.from.unwrap(),
+                            uid: OrderUid([creation.buy_amount.low_u32() as u8; 56]),
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        const STAGES: [&str; 6] = [
+            "app_data_lookup",
+            "validation",
+            "db_insert",
+            "replacement_lookup",
+            "replacement_validation",
+            "db_replace",
+        ];
+        let samples = || {
+            STAGES.map(|stage| {
+                Metrics::get()
+                    .stage_latency
+                    .with_label_values(&[stage])
+                    .get_sample_count()
+            })
+        };
+        let before = samples();
+
+        let owner = H160([1; 20]);
+        let (uid, _) = orderbook
+            .add_order(
+                OrderCreation {
+                    from: Some(owner),
+                    buy_amount: 1.into(),
+                    valid_to: u32::MAX,
+                    signature: Signature::Eip712(Default::default()),
+                    ..Default::default()
+                },
+                api_source(),
+            )
+            .await
+            .unwrap();
+        let cancellation = OrderCancellation {
+            order_uid: uid,
+            ..Default::default()
+        };
+        orderbook
+            .replace_order(
+                uid,
+                OrderCreation {
+                    from: Some(owner),
+                    buy_amount: 2.into(),
+                    valid_to: u32::MAX,
+                    signature: Signature::Eip712(Default::default()),
+                    app_data: AppDataHash(cancellation.hash_struct()).into(),
+                    ..Default::default()
+                },
+                api_source(),
+            )
+            .await
+            .unwrap();
+
+        for ((stage, before), after) in STAGES.iter().zip(before).zip(samples()) {
+            assert!(after > before, "no latency recorded for stage {stage}");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_duplicated_order_reports_existing_order() {
+        let uid = OrderUid([1; 56]);
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(move |creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            uid,
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let creation = OrderCreation {
+            valid_to: u32::MAX,
+            signature: Signature::Eip712(Default::default()),
+            ..Default::default()
+        };
+        orderbook
+            .add_order(creation.clone(), api_source())
+            .await
+            .unwrap();
+
+        // Retrying the same submission.
+        let result = orderbook.add_order(creation.clone(), api_source()).await;
+        assert!(matches!(
+            result,
+            Err(AddOrderError::DuplicatedOrder {
+                uid: existing,
+                status: OrderStatus::Open,
+                signature_matches: true,
+            }) if existing == uid
+        ));
+
+        // Different order colliding with the same uid.
+        let result = orderbook
+            .add_order(
+                OrderCreation {
+                    signature: Signature::EthSign(Default::default()),
+                    ..creation
+                },
+                api_source(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AddOrderError::DuplicatedOrder {
+                uid: existing,
+                status: OrderStatus::Open,
+                signature_matches: false,
+            }) if existing == uid
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_order_keeps_quote_for_same_economic_parameters() {
+        let old_order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                owner: H160([1; 20]),
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_token: H160([2; 20]),
+                buy_token: H160([3; 20]),
+                sell_amount: 100.into(),
+                buy_amount: 200.into(),
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old_quote = Quote {
+            id: Some(1),
+            data: shared::order_quoting::QuoteData {
+                fee_parameters: shared::fee::FeeParameters {
+                    gas_amount: 1.,
+                    gas_price: 2.,
+                    sell_token_price: 3.,
+                },
+                solver: H160([4; 20]),
+                expiration: Utc.timestamp_opt(1, 0).unwrap(),
+                ..Default::default()
+            },
+            sell_amount: 100.into(),
+            buy_amount: 200.into(),
+            ..Default::default()
+        };
+        let requoted = Quote {
+            id: Some(2),
+            data: shared::order_quoting::QuoteData {
+                fee_parameters: shared::fee::FeeParameters {
+                    gas_amount: 5.,
+                    gas_price: 6.,
+                    sell_token_price: 7.,
+                },
+                solver: H160([8; 20]),
+                expiration: Utc.timestamp_opt(2, 0).unwrap(),
+                ..Default::default()
+            },
+            sell_amount: 100.into(),
+            buy_amount: 210.into(),
+            ..Default::default()
+        };
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning({
+                let requoted = requoted.clone();
+                move |creation, _, _, _| {
+                    Ok((
+                        Order {
+                            metadata: OrderMetadata {
+                                owner: creation.from.unwrap(),
+                                uid: OrderUid([creation.buy_amount.low_u32() as u8; 56]),
+                                ..Default::default()
+                            },
+                            data: creation.data(),
+                            signature: creation.signature,
+                            ..Default::default()
+                        },
+                        Some(requoted.clone()),
+                    ))
+                }
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        database
+            .insert_order(&old_order, Some(old_quote.clone()))
+            .await
+            .unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let stored_quote = |quote: &Quote| Quote {
+            id: quote.id,
+            data: shared::order_quoting::QuoteData {
+                fee_parameters: quote.data.fee_parameters,
+                solver: quote.data.solver,
+                expiration: quote.data.expiration,
+                ..Default::default()
+            },
+            sell_amount: quote.sell_amount,
+            buy_amount: quote.buy_amount,
+            ..Default::default()
+        };
+        let replacement = |order: &Order, buy_amount: u32| {
+            let cancellation = OrderCancellation {
+                order_uid: order.metadata.uid,
+                ..Default::default()
+            };
+            OrderCreation {
+                from: Some(order.metadata.owner),
+                sell_token: order.data.sell_token,
+                buy_token: order.data.buy_token,
+                sell_amount: order.data.sell_amount,
+                buy_amount: buy_amount.into(),
+                valid_to: u32::MAX - 1,
+                signature: Signature::Eip712(Default::default()),
+                app_data: AppDataHash(cancellation.hash_struct()).into(),
+                ..Default::default()
+            }
+        };
+
+        // Only the validity changes so the old quote is kept.
+        let new_uid = orderbook
+            .replace_order(
+                old_order.metadata.uid,
+                replacement(&old_order, 200),
+                api_source(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            orderbook.database.order_quote(&new_uid).await.unwrap(),
+            Some(stored_quote(&old_quote)),
+        );
+
+        // Changing the amounts uses the new quote.
+        let new_order = orderbook.get_order(&new_uid).await.unwrap().unwrap();
+        let newer_uid = orderbook
+            .replace_order(new_uid, replacement(&new_order, 210), api_source())
+            .await
+            .unwrap();
+        assert_eq!(
+            orderbook.database.order_quote(&newer_uid).await.unwrap(),
+            Some(stored_quote(&requoted)),
+        );
+    }
+
+    /// Orderbook whose order validator derives the uid of new orders from
+    /// their buy amount, together with two open orders of the same owner.
+    async fn orderbook_for_batch_replacements() -> (Orderbook, [Order; 2]) {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(move |creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            owner: creation.from.unwrap(),
+                            uid: OrderUid([creation.buy_amount.low_u32() as u8; 56]),
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            });
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let old_order = |uid: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                owner: H160([1; 20]),
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old_orders = [old_order(1), old_order(2)];
+        for order in &old_orders {
+            database.insert_order(order, None).await.unwrap();
+        }
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        (orderbook, old_orders)
+    }
+
+    fn batch_replacement(old_order: &Order, owner: H160, new_uid: u8) -> (OrderUid, OrderCreation) {
+        let cancellation = OrderCancellation {
+            order_uid: old_order.metadata.uid,
+            ..Default::default()
+        };
+        (
+            old_order.metadata.uid,
+            OrderCreation {
+                from: Some(owner),
+                buy_amount: new_uid.into(),
+                valid_to: u32::MAX,
+                signature: Signature::Eip712(Default::default()),
+                app_data: AppDataHash(cancellation.hash_struct()).into(),
+                ..Default::default()
+            },
+        )
+    }
+
+    async fn order_status(orderbook: &Orderbook, uid: u8) -> Option<OrderStatus> {
+        orderbook
+            .database
+            .single_order(&OrderUid([uid; 56]))
+            .await
+            .unwrap()
+            .map(|order| order.metadata.status)
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_orders() {
+        let (orderbook, [first, second]) = orderbook_for_batch_replacements().await;
+        let owner = first.metadata.owner;
+        let mut events = orderbook.subscribe_order_events();
+
+        let new_uids = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 11),
+                    batch_replacement(&second, owner, 12),
+                ],
+                api_source(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(new_uids, vec![OrderUid([11; 56]), OrderUid([12; 56])]);
+        assert!(
+            registered_counter(
+                "orderbook_orders",
+                &[
+                    ("kind", "user"),
+                    ("operation", "replaced"),
+                    ("quote_verified", "false"),
+                    ("source", "unknown"),
+                ],
+            )
+            .unwrap()
+                >= 2.
+        );
+        assert_eq!(
+            orderbook
+                .get_replacement_chain(&first.metadata.uid)
+                .await
+                .unwrap(),
+            vec![first.metadata.uid, OrderUid([11; 56])],
+        );
+        for (uid, status) in [
+            (1, OrderStatus::Cancelled),
+            (2, OrderStatus::Cancelled),
+            (11, OrderStatus::Open),
+            (12, OrderStatus::Open),
+        ] {
+            assert_eq!(order_status(&orderbook, uid).await, Some(status));
+        }
+        let events = std::iter::from_fn(|| events.try_recv())
+            .map(|event| (event.uid, event.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (first.metadata.uid, OrderEventKind::Replaced),
+                (OrderUid([11; 56]), OrderEventKind::Created),
+                (second.metadata.uid, OrderEventKind::Replaced),
+                (OrderUid([12; 56]), OrderEventKind::Created),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_orders_rolls_back_on_invalid_replacement() {
+        let (orderbook, [first, second]) = orderbook_for_batch_replacements().await;
+        let owner = first.metadata.owner;
+        // The second replacement uses the uid of an already existing order so
+        // it only fails once the first replacement got stored.
+        let existing = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([12; 56]),
+                owner,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        orderbook
+            .database
+            .insert_order(&existing, None)
+            .await
+            .unwrap();
+
+        let result = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 11),
+                    batch_replacement(&second, owner, 12),
+                ],
+                api_source(),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ReplaceOrderError::Batch { index: 1, source })
+                if matches!(
+                    *source,
+                    ReplaceOrderError::Add(AddOrderError::DuplicatedOrder { .. })
+                )
+        ));
+        assert_eq!(order_status(&orderbook, 1).await, Some(OrderStatus::Open));
+        assert_eq!(order_status(&orderbook, 2).await, Some(OrderStatus::Open));
+        assert_eq!(order_status(&orderbook, 11).await, None);
+
+        // Replacing the same order twice is rejected before anything gets
+        // stored.
+        let result = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 11),
+                    batch_replacement(&first, owner, 13),
+                ],
+                api_source(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ReplaceOrderError::Batch { index: 1, source })
+                if matches!(*source, ReplaceOrderError::InvalidReplacement)
+        ));
+        assert_eq!(order_status(&orderbook, 1).await, Some(OrderStatus::Open));
+        assert_eq!(order_status(&orderbook, 11).await, None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_orders_reports_first_failing_replacement() {
+        let (orderbook, [first, second]) = orderbook_for_batch_replacements().await;
+        let owner = first.metadata.owner;
+
+        orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 11),
+                    batch_replacement(&second, owner, 12),
+                ],
+                api_source(),
+            )
+            .await
+            .unwrap();
+
+        // Errors are reported for the first failing replacement even though
+        // all orders got looked up at once.
+        let result = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&Order::default(), owner, 13),
+                    batch_replacement(&first, owner, 14),
+                ],
+                api_source(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ReplaceOrderError::Batch { index: 0, source })
+                if matches!(
+                    *source,
+                    ReplaceOrderError::Cancellation(OrderCancellationError::OrderNotFound)
+                )
+        ));
+        // The first order got cancelled by its replacement.
+        let result = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 14),
+                    batch_replacement(&Order::default(), owner, 13),
+                ],
+                api_source(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ReplaceOrderError::Batch { index: 0, source })
+                if matches!(
+                    *source,
+                    ReplaceOrderError::Cancellation(OrderCancellationError::AlreadyCancelled)
+                )
+        ));
+    }
+
+    #[tokio::test]
+    async fn looks_up_replaced_orders_at_once() {
+        let order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let replacements = vec![
+            (OrderUid([1; 56]), OrderCreation::default()),
+            (OrderUid([2; 56]), OrderCreation::default()),
+        ];
+
+        let mut database = MockOrderStoring::new();
+        database.expect_single_order().never();
+        database
+            .expect_orders_by_uids()
+            .with(eq(vec![OrderUid([1; 56]), OrderUid([2; 56])]))
+            .times(1)
+            .returning(move |_| Ok(HashMap::from([(OrderUid([1; 56]), order(1))])));
+        let old_orders = replaced_orders(&database, &replacements).await.unwrap();
+        assert_eq!(old_orders, HashMap::from([(OrderUid([1; 56]), order(1))]));
+
+        // Failed lookups get reported for the first replacement.
+        let mut database = MockOrderStoring::new();
+        database
+            .expect_orders_by_uids()
+            .times(1)
+            .returning(|_| Err(anyhow!("connection lost")));
+        assert!(matches!(
+            replaced_orders(&database, &replacements).await,
+            Err(ReplaceOrderError::Batch { index: 0, source })
+                if matches!(
+                    *source,
+                    ReplaceOrderError::Cancellation(OrderCancellationError::Other(_))
+                )
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_orders_verifies_owner() {
+        let (orderbook, [first, second]) = orderbook_for_batch_replacements().await;
+        let owner = first.metadata.owner;
+
+        let result = orderbook
+            .replace_orders(
+                vec![
+                    batch_replacement(&first, owner, 11),
+                    batch_replacement(&second, H160([2; 20]), 12),
+                ],
+                api_source(),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ReplaceOrderError::Batch { index: 1, source })
+                if matches!(*source, ReplaceOrderError::InvalidReplacement)
+        ));
+        for (uid, status) in [
+            (1, Some(OrderStatus::Open)),
+            (2, Some(OrderStatus::Open)),
+            (11, None),
+            (12, None),
+        ] {
+            assert_eq!(order_status(&orderbook, uid).await, status);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_expiring_orders() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let owner = H160([1; 20]);
+        let now = now_in_epoch_seconds();
+        let order = |uid: u8, valid_to: u32| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_amount: 10.into(),
+                buy_amount: 10.into(),
+                valid_to,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for order in [
+            order(1, now + 3000),
+            order(2, now + 60),
+            // Expires after the window.
+            order(3, now + 7200),
+            // Already expired.
+            order(4, now - 60),
+        ] {
+            database.insert_order(&order, None).await.unwrap();
+        }
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let expiring = orderbook
+            .get_expiring_orders(&owner, Duration::from_secs(3600))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|order| order.metadata.uid)
+            .collect::<Vec<_>>();
+        assert_eq!(expiring, vec![OrderUid([2; 56]), OrderUid([1; 56])]);
+        assert!(orderbook
+            .get_expiring_orders(&H160([2; 20]), Duration::from_secs(3600))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn validates_amendments() {
+        let order = Order {
+            metadata: OrderMetadata {
+                status: OrderStatus::Open,
+                executed_sell_amount_before_fees: 20.into(),
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_amount: 100.into(),
+                buy_amount: 50.into(),
+                kind: OrderKind::Sell,
+                partially_fillable: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let amendment = |sell_amount: u64, buy_amount: u64| OrderAmendment {
+            sell_amount: sell_amount.into(),
+            buy_amount: buy_amount.into(),
+            order_uid: Default::default(),
+            signature: Default::default(),
+            signing_scheme: EcdsaSigningScheme::Eip712,
+        };
+
+        assert!(validate_amendment(&order, &amendment(60, 30)).is_ok());
+        assert!(validate_amendment(&order, &amendment(20, 10)).is_ok());
+        assert!(matches!(
+            validate_amendment(&order, &amendment(200, 100)),
+            Err(OrderAmendmentError::IncreasedAmounts)
+        ));
+        assert!(matches!(
+            validate_amendment(&order, &amendment(60, 20)),
+            Err(OrderAmendmentError::ChangedLimitPrice)
+        ));
+        assert!(matches!(
+            validate_amendment(&order, &amendment(10, 5)),
+            Err(OrderAmendmentError::BelowExecutedAmount)
+        ));
+
+        // Already amended orders can only be reduced further.
+        let amended = Order {
+            metadata: OrderMetadata {
+                amended_amounts: Some(AmendedAmounts {
+                    sell_amount: 40.into(),
+                    buy_amount: 20.into(),
+                }),
+                ..order.metadata.clone()
+            },
+            ..order.clone()
+        };
+        assert!(matches!(
+            validate_amendment(&amended, &amendment(60, 30)),
+            Err(OrderAmendmentError::IncreasedAmounts)
+        ));
+
+        let fill_or_kill = Order {
+            data: OrderData {
+                partially_fillable: false,
+                ..order.data.clone()
+            },
+            ..order.clone()
+        };
+        assert!(matches!(
+            validate_amendment(&fill_or_kill, &amendment(60, 30)),
+            Err(OrderAmendmentError::NotPartiallyFillable)
+        ));
+
+        let cancelled = Order {
+            metadata: OrderMetadata {
+                status: OrderStatus::Cancelled,
+                ..order.metadata.clone()
+            },
+            ..order
+        };
+        assert!(matches!(
+            validate_amendment(&cancelled, &amendment(60, 30)),
+            Err(OrderAmendmentError::NotOpen)
+        ));
+    }
+
+    fn orderbook_with_signature_validator(
+        database: crate::database::Postgres,
+        signature_validator: MockSignatureValidating,
+    ) -> Orderbook {
+        let app_data = Arc::new(app_data::Registry::new(
+            shared::app_data::Validator::new(8192),
+            database.clone(),
+            None,
+        ));
+        Orderbook {
+            auctions: Arc::new(database.clone()),
+            database,
+            order_validator: Arc::new(MockOrderValidating::new()),
+            signature_validator: Arc::new(signature_validator),
+            transactions: Arc::new(MockTransactionSenderFetching::new()),
+            domain_separator: Default::default(),
+            settlement_contract: H160([0xba; 20]),
+            app_data,
+            accept_cancellations_without_valid_to: true,
+            health: HealthMonitor::new(Arc::new(MockHealthProbing::new()), Duration::MAX),
+            open_order_limit: Default::default(),
+            min_order_value: None,
+            auction_retention: 1,
+            max_auction_age: None,
+            latest_auction_id: watch::channel(None).0,
+            order_events: Default::default(),
+            order_cache: Default::default(),
+            max_cancellation_batch_size: DEFAULT_MAX_CANCELLATION_BATCH_SIZE,
+            trusted_liquidity_providers: Default::default(),
+            unsupported_tokens: Default::default(),
+            client_ip_hash_salt: None,
+            trusted_proxies: Default::default(),
+            submission_rate_limiter: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_order_quote() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quote = Quote {
+            id: Some(5),
+            sell_amount: 10.into(),
+            buy_amount: 20.into(),
+            ..Default::default()
+        };
+        database
+            .insert_order(&order(1), Some(quote.clone()))
+            .await
+            .unwrap();
+        database.insert_order(&order(2), None).await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let stored = orderbook
+            .get_order_quote(&OrderUid([1; 56]))
+            .await
+            .unwrap()
+            .into_quote()
+            .unwrap();
+        assert_eq!(stored.id, quote.id);
+        assert_eq!(
+            (stored.sell_amount, stored.buy_amount),
+            (quote.sell_amount, quote.buy_amount)
+        );
+        assert_eq!(
+            orderbook.get_order_quote(&OrderUid([2; 56])).await.unwrap(),
+            OrderQuote::NoQuote
+        );
+        assert_eq!(
+            orderbook.get_order_quote(&OrderUid([3; 56])).await.unwrap(),
+            OrderQuote::OrderNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn enforces_min_order_value() {
+        let priced_token = H160([1; 20]);
+        let mut native_price_estimator = MockNativePriceEstimating::new();
+        native_price_estimator
+            .expect_estimate_native_price()
+            .returning(move |token| {
+                async move {
+                    if token == priced_token {
+                        Ok(0.5)
+                    } else {
+                        Err(PriceEstimationError::NoLiquidity)
+                    }
+                }
+                .boxed()
+            });
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new())
+                .with_min_order_value(Some(MinOrderValue {
+                    min: 100.into(),
+                    native_price_estimator: Arc::new(native_price_estimator),
+                }));
+        let order = |sell_token, sell_amount: u32| Order {
+            data: OrderData {
+                sell_token,
+                sell_amount: sell_amount.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            orderbook.check_order_value(&order(priced_token, 199)).await,
+            Err(AddOrderError::OrderValueTooSmall { min, actual })
+                if min == 100.into() && actual == 99.into()
+        ));
+        assert!(orderbook
+            .check_order_value(&order(priced_token, 200))
+            .await
+            .is_ok());
+
+        // Orders without a native price fail open.
+        let without_price = Metrics::get().orders_without_native_price.get();
+        assert!(orderbook
+            .check_order_value(&order(H160([2; 20]), 1))
+            .await
+            .is_ok());
+        assert_eq!(
+            Metrics::get().orders_without_native_price.get(),
+            without_price + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn enforces_min_order_value_of_replacements() {
+        let mut native_price_estimator = MockNativePriceEstimating::new();
+        native_price_estimator
+            .expect_estimate_native_price()
+            .returning(|_| async { Ok(0.5) }.boxed());
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|_, _, _, _| {
+                Ok((
+                    Order {
+                        data: OrderData {
+                            sell_amount: 199.into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    None,
+                ))
+            });
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        }
+        .with_min_order_value(Some(MinOrderValue {
+            min: 100.into(),
+            native_price_estimator: Arc::new(native_price_estimator),
+        }));
+
+        assert!(matches!(
+            orderbook
+                .validate_replacement_of(Default::default(), Default::default(), None)
+                .await,
+            Err(ReplaceOrderError::Add(AddOrderError::OrderValueTooSmall { min, actual }))
+                if min == 100.into() && actual == 99.into()
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_tokens() {
+        let (a, b, c) = (H160([1; 20]), H160([2; 20]), H160([3; 20]));
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .times(2)
+            .returning(|_, _, _, _| Err(ValidationError::InsufficientBalance));
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        }
+        .with_unsupported_tokens([a]);
+        let creation = |sell_token, buy_token| OrderCreation {
+            sell_token,
+            buy_token,
+            app_data: OrderCreationAppData::Full {
+                full: "{}".to_string(),
+            },
+            ..Default::default()
+        };
+        let unsupported = |result, expected| {
+            matches!(
+                result,
+                Err(AddOrderError::UnsupportedToken { token, .. }) if token == expected
+            )
+        };
+        let validated = |result| {
+            matches!(
+                result,
+                Err(AddOrderError::OrderValidation(
+                    ValidationError::InsufficientBalance
+                ))
+            )
+        };
+
+        assert!(unsupported(
+            orderbook.add_order(creation(a, c), api_source()).await,
+            a
+        ));
+        assert!(unsupported(
+            orderbook.add_order(creation(c, a), api_source()).await,
+            a
+        ));
+        assert!(validated(
+            orderbook.add_order(creation(b, c), api_source()).await
+        ));
+
+        // Reloading the list replaces the previous tokens.
+        orderbook.set_unsupported_tokens([b]);
+        assert!(unsupported(
+            orderbook.add_order(creation(c, b), api_source()).await,
+            b
+        ));
+        assert!(validated(
+            orderbook.add_order(creation(a, c), api_source()).await
+        ));
+
+        // Replacements can't introduce unsupported tokens either.
+        assert!(matches!(
+            orderbook
+                .validate_replacement_of(Default::default(), creation(c, b), None)
+                .await,
+            Err(ReplaceOrderError::Add(AddOrderError::UnsupportedToken { token, .. }))
+                if token == b
+        ));
+    }
+
+    #[tokio::test]
+    async fn trusted_liquidity_providers_skip_balance_check() {
+        let trusted = H160([1; 20]);
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order_without_balance_check()
+            .times(1)
+            .returning(|_, _, _, _| Err(ValidationError::Other(anyhow!("skipped"))));
+        order_validator
+            .expect_validate_and_construct_order()
+            .times(2)
+            .returning(|_, _, _, _| Err(ValidationError::InsufficientBalance));
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        }
+        .with_trusted_liquidity_providers([trusted]);
+        let creation = |from| OrderCreation {
+            from,
+            app_data: OrderCreationAppData::Full {
+                full: "{}".to_string(),
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            orderbook
+                .add_order(creation(Some(trusted)), api_source())
+                .await,
+            Err(AddOrderError::OrderValidation(ValidationError::Other(_)))
+        ));
+        for from in [Some(H160([2; 20])), None] {
+            assert!(matches!(
+                orderbook.add_order(creation(from), api_source()).await,
+                Err(AddOrderError::OrderValidation(
+                    ValidationError::InsufficientBalance
+                ))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn only_trusts_forwarded_for_of_trusted_proxies() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let proxy: IpAddr = "203.0.113.1".parse().unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new())
+                .with_trusted_proxies([proxy]);
+        let client: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "198.51.100.1".parse().unwrap();
+
+        assert_eq!(orderbook.client_ip(Some(client), Some(proxy)), Some(client));
+        assert_eq!(orderbook.client_ip(None, Some(proxy)), Some(proxy));
+        // Anyone else could claim to forward requests of arbitrary clients.
+        assert_eq!(orderbook.client_ip(Some(client), Some(other)), Some(other));
+        assert_eq!(orderbook.client_ip(Some(client), None), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limits_submissions_before_validation() {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .times(7)
+            .returning(|_, _, _, _| Err(ValidationError::InsufficientBalance));
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        }
+        .with_submission_rate_limit(Some(SubmissionRateLimit {
+            burst: 2,
+            refill_interval: Duration::from_secs(60),
+            per_app_code: false,
+        }));
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let signed = |key: &secp256k1::SecretKey| {
+            OrderCreation::default().sign(
+                EcdsaSigningScheme::Eip712,
+                &orderbook.domain_separator,
+                SecretKeyRef::new(key),
+            )
+        };
+        let validated = |result| {
+            matches!(
+                result,
+                Err(AddOrderError::OrderValidation(
+                    ValidationError::InsufficientBalance
+                ))
+            )
+        };
+        let rate_limited = |result, expected| {
+            matches!(
+                result,
+                Err(AddOrderError::RateLimited { retry_after }) if retry_after == expected
+            )
+        };
+
+        // Anyone can claim to place pre-signed orders of the owner so they
+        // don't use up the owner's submissions before being validated.
+        for _ in 0..3 {
+            let spoofed = OrderCreation {
+                from: Some(owner),
+                signature: Signature::PreSign,
+                ..Default::default()
+            };
+            assert!(validated(orderbook.add_order(spoofed, api_source()).await));
+        }
+
+        for _ in 0..2 {
+            assert!(validated(
+                orderbook.add_order(signed(&key), api_source()).await
+            ));
+        }
+        assert!(rate_limited(
+            orderbook.add_order(signed(&key), api_source()).await,
+            Duration::from_secs(60),
+        ));
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(rate_limited(
+            orderbook.add_order(signed(&key), api_source()).await,
+            Duration::from_secs(30),
+        ));
+        // A single submission gets refilled per interval.
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(validated(
+            orderbook.add_order(signed(&key), api_source()).await
+        ));
+        assert!(rate_limited(
+            orderbook.add_order(signed(&key), api_source()).await,
+            Duration::from_secs(60),
+        ));
+
+        // Other owners aren't affected.
+        let other = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        assert!(validated(
+            orderbook.add_order(signed(&other), api_source()).await
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_rate_limits_batches_and_replacements() {
+        let (orderbook, [first, second]) = orderbook_for_batch_replacements().await;
+        let orderbook = orderbook.with_submission_rate_limit(Some(SubmissionRateLimit {
+            burst: 2,
+            refill_interval: Duration::from_secs(3600),
+            per_app_code: false,
+        }));
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let signed = |buy_amount: u8| {
+            OrderCreation {
+                from: Some(owner),
+                buy_amount: buy_amount.into(),
+                valid_to: u32::MAX,
+                ..Default::default()
+            }
+            .sign(
+                EcdsaSigningScheme::Eip712,
+                &orderbook.domain_separator,
+                SecretKeyRef::new(&key),
+            )
+        };
+        let presigned = |buy_amount: u8| OrderCreation {
+            from: Some(H160([3; 20])),
+            buy_amount: buy_amount.into(),
+            valid_to: u32::MAX,
+            signature: Signature::PreSign,
+            ..Default::default()
+        };
+        let rate_limited = |result: &Result<_, AddOrderError>| {
+            matches!(result, Err(AddOrderError::RateLimited { .. }))
+        };
+
+        // Pre-signed orders get charged once their owner got validated.
+        for results in [
+            orderbook
+                .add_orders(vec![signed(11), signed(12), signed(13)], api_source())
+                .await,
+            orderbook
+                .add_orders(
+                    vec![presigned(21), presigned(22), presigned(23)],
+                    api_source(),
+                )
+                .await,
+        ] {
+            assert!(results[0].is_ok());
+            assert!(results[1].is_ok());
+            assert!(rate_limited(&results[2]));
+        }
+
+        // Replacements count towards the limit of their owner as well.
+        assert!(matches!(
+            orderbook
+                .replace_order(first.metadata.uid, signed(14), api_source())
+                .await,
+            Err(ReplaceOrderError::Add(AddOrderError::RateLimited { .. }))
+        ));
+        assert!(matches!(
+            orderbook
+                .replace_orders(vec![(second.metadata.uid, signed(15))], api_source())
+                .await,
+            Err(ReplaceOrderError::Batch { index: 0, source })
+                if matches!(*source, ReplaceOrderError::Add(AddOrderError::RateLimited { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn verifies_eip1271_cancellations_on_chain() {
+        let owner = H160([1; 20]);
+        let cancellation = OrderCancellation {
+            order_uid: OrderUid([1; 56]),
+            valid_to: None,
+            signature: CancellationSignature::Eip1271 {
+                owner,
+                signature: vec![1, 2, 3],
+            },
+        };
+        let expected_check = SignatureCheck {
+            signer: owner,
+            hash: hashed_eip712_message(&Default::default(), &cancellation.hash_struct()),
+            signature: vec![1, 2, 3],
+            interactions: Vec::new(),
+        };
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+
+        let mut signature_validator = MockSignatureValidating::new();
+        signature_validator
+            .expect_validate_signatures()
+            .with(eq(vec![expected_check.clone()]))
+            .returning(|_| vec![Ok(())]);
+        let orderbook = orderbook_with_signature_validator(database.clone(), signature_validator);
+        assert_eq!(
+            orderbook
+                .cancellation_signer(&cancellation.signature, &cancellation.hash_struct())
+                .await
+                .unwrap(),
+            owner
+        );
+
+        let mut signature_validator = MockSignatureValidating::new();
+        signature_validator
+            .expect_validate_signatures()
+            .with(eq(vec![expected_check]))
+            .returning(|_| vec![Err(SignatureValidationError::Invalid)]);
+        let orderbook = orderbook_with_signature_validator(database, signature_validator);
+        assert!(matches!(
+            orderbook
+                .cancellation_signer(&cancellation.signature, &cancellation.hash_struct())
+                .await,
+            Err(OrderCancellationError::InvalidEip1271Signature)
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_eip1271_order() {
+        let owner = H160([1; 20]);
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            signature: Signature::Eip1271(vec![4, 5, 6]),
+            ..Default::default()
+        };
+        let cancellation = |signature: Vec<u8>| OrderCancellation {
+            order_uid: order.metadata.uid,
+            valid_to: None,
+            signature: CancellationSignature::Eip1271 { owner, signature },
+        };
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        database.insert_order(&order, None).await.unwrap();
+
+        let mut signature_validator = MockSignatureValidating::new();
+        signature_validator
+            .expect_validate_signatures()
+            .returning(|checks| {
+                checks
+                    .into_iter()
+                    .map(|check| match check.signature.as_slice() {
+                        [1, 2, 3] => Ok(()),
+                        _ => Err(SignatureValidationError::Invalid),
+                    })
+                    .collect()
+            });
+        let orderbook = orderbook_with_signature_validator(database.clone(), signature_validator);
+
+        // Rejected signatures leave the order open.
+        assert!(matches!(
+            orderbook.cancel_order(cancellation(vec![0])).await,
+            Err(OrderCancellationError::InvalidEip1271Signature)
+        ));
+        let stored = database.single_order(&order.metadata.uid).await.unwrap();
+        assert_eq!(stored.unwrap().metadata.status, OrderStatus::Open);
+        let cached = orderbook.get_order(&order.metadata.uid).await.unwrap();
+        assert_eq!(cached.unwrap().metadata.status, OrderStatus::Open);
+
+        orderbook
+            .cancel_order(cancellation(vec![1, 2, 3]))
+            .await
+            .unwrap();
+        let stored = database.single_order(&order.metadata.uid).await.unwrap();
+        assert_eq!(stored.unwrap().metadata.status, OrderStatus::Cancelled);
+        // The cancellation invalidated the cached order.
+        let cached = orderbook.get_order(&order.metadata.uid).await.unwrap();
+        assert_eq!(cached.unwrap().metadata.status, OrderStatus::Cancelled);
+
+        let proof = orderbook
+            .get_cancellation_proof(&order.metadata.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(proof.signature, cancellation(vec![1, 2, 3]).signature);
+        assert_eq!(proof.signer, owner);
+        assert_eq!(
+            proof.message.unwrap().hash_struct(),
+            cancellation(vec![1, 2, 3]).hash_struct()
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_orders_reports_invalid_order() {
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let uid = |byte: u8| OrderUid([byte; 56]);
+        let order = |byte: u8, owner: H160| Order {
+            metadata: OrderMetadata {
+                uid: uid(byte),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            signature: Signature::Eip712(Default::default()),
+            ..Default::default()
+        };
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        for order in [order(1, owner), order(2, owner), order(3, H160([2; 20]))] {
+            database.insert_order(&order, None).await.unwrap();
+        }
+        let orderbook = Orderbook {
+            max_cancellation_batch_size: 3,
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        let signed_cancellation = |order_uids: Vec<OrderUid>, valid_to: Option<u32>| {
+            let data = OrderCancellations {
+                order_uids,
+                valid_to,
+            };
+            SignedOrderCancellations {
+                signature: CancellationSignature::sign(
+                    EcdsaSigningScheme::Eip712,
+                    &Default::default(),
+                    &data.hash_struct(),
+                    SecretKeyRef::new(&key),
+                ),
+                data,
+            }
+        };
+        let cancellation = |order_uids| signed_cancellation(order_uids, None);
+
+        assert!(matches!(
+            orderbook
+                .cancel_orders(cancellation(vec![uid(1), uid(2), uid(1), uid(2)]))
+                .await,
+            Err(OrderCancellationError::TooManyOrders { count: 4, max: 3 })
+        ));
+
+        // The order in the middle of the batch doesn't exist.
+        assert!(matches!(
+            orderbook
+                .cancel_orders(cancellation(vec![uid(1), uid(4), uid(2)]))
+                .await,
+            Err(OrderCancellationError::InvalidOrder { uid: invalid, reason })
+                if invalid == uid(4)
+                    && matches!(*reason, OrderCancellationError::OrderNotFound)
+        ));
+
+        // The batch contains an order of a different owner.
+        assert!(matches!(
+            orderbook
+                .cancel_orders(cancellation(vec![uid(1), uid(3), uid(2)]))
+                .await,
+            Err(OrderCancellationError::InvalidOrder { uid: invalid, reason })
+                if invalid == uid(3)
+                    && matches!(*reason, OrderCancellationError::WrongOwner)
+        ));
+
+        // Failed batches don't cancel any order.
+        for byte in 1..=3 {
+            assert_eq!(
+                order_status(&orderbook, byte).await,
+                Some(OrderStatus::Open)
+            );
+        }
+
+        orderbook
+            .cancel_orders(cancellation(vec![uid(1), uid(2)]))
+            .await
+            .unwrap();
+        for byte in 1..=2 {
+            assert_eq!(
+                order_status(&orderbook, byte).await,
+                Some(OrderStatus::Cancelled)
+            );
+        }
+
+        // Cancelling an already cancelled order reports that order.
+        assert!(matches!(
+            orderbook.cancel_orders(cancellation(vec![uid(2)])).await,
+            Err(OrderCancellationError::InvalidOrder { uid: invalid, reason })
+                if invalid == uid(2)
+                    && matches!(*reason, OrderCancellationError::AlreadyCancelled)
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_orders_checks_valid_to() {
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let uid = |byte: u8| OrderUid([byte; 56]);
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        for byte in 1..=2 {
+            let order = Order {
+                metadata: OrderMetadata {
+                    uid: uid(byte),
+                    owner,
+                    ..Default::default()
+                },
+                data: OrderData {
+                    valid_to: u32::MAX,
+                    ..Default::default()
+                },
+                signature: Signature::Eip712(Default::default()),
+                ..Default::default()
+            };
+            database.insert_order(&order, None).await.unwrap();
+        }
+        let orderbook = Orderbook {
+            accept_cancellations_without_valid_to: false,
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        let cancellation = |valid_to: Option<u32>| {
+            let data = OrderCancellations {
+                order_uids: vec![uid(1), uid(2)],
+                valid_to,
+            };
+            SignedOrderCancellations {
+                signature: CancellationSignature::sign(
+                    EcdsaSigningScheme::Eip712,
+                    &Default::default(),
+                    &data.hash_struct(),
+                    SecretKeyRef::new(&key),
+                ),
+                data,
+            }
+        };
+
+        for valid_to in [None, Some(now_in_epoch_seconds() - 1)] {
+            assert!(matches!(
+                orderbook.cancel_orders(cancellation(valid_to)).await,
+                Err(OrderCancellationError::CancellationExpired)
+            ));
+        }
+        for byte in 1..=2 {
+            assert_eq!(
+                order_status(&orderbook, byte).await,
+                Some(OrderStatus::Open)
+            );
+        }
+
+        orderbook
+            .cancel_orders(cancellation(Some(u32::MAX)))
+            .await
+            .unwrap();
+        for byte in 1..=2 {
+            assert_eq!(
+                order_status(&orderbook, byte).await,
+                Some(OrderStatus::Cancelled)
+            );
+            // The stored proof can be verified without further context.
+            let proof = orderbook
+                .get_cancellation_proof(&uid(byte))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(proof.recover_signer().unwrap(), owner);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_presignature_pending_order() {
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let presign_order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            signature: Signature::PreSign,
+            ..Default::default()
+        };
+        let cancellation = |uid: OrderUid, key: &secp256k1::SecretKey| {
+            let cancellation = OrderCancellation {
+                order_uid: uid,
+                valid_to: None,
+                ..Default::default()
+            };
+            OrderCancellation {
+                signature: CancellationSignature::sign(
+                    EcdsaSigningScheme::Eip712,
+                    &Default::default(),
+                    &cancellation.hash_struct(),
+                    SecretKeyRef::new(key),
+                ),
+                ..cancellation
+            }
+        };
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let pending = presign_order(1);
+        let revoked = presign_order(2);
+        let signed = presign_order(3);
+        for order in [&pending, &revoked, &signed] {
+            database.insert_order(order, None).await.unwrap();
+        }
+        let pre_signature = |log_index: i64, order: &Order, signed: bool| {
+            (
+                database::events::EventIndex {
+                    block_number: 0,
+                    log_index,
+                },
+                database::events::Event::PreSignature(database::events::PreSignature {
+                    owner: ByteArray(owner.0),
+                    order_uid: ByteArray(order.metadata.uid.0),
+                    signed,
+                }),
+            )
+        };
+        let mut ex = database.pool.begin().await.unwrap();
+        database::events::append(
+            &mut ex,
+            &[
+                pre_signature(0, &revoked, true),
+                pre_signature(1, &revoked, false),
+                pre_signature(2, &signed, true),
+            ],
+        )
+        .await
+        .unwrap();
+        ex.commit().await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database.clone(), MockSignatureValidating::new());
+        let status = |order: &Order| {
+            let database = database.clone();
+            let uid = order.metadata.uid;
+            async move {
+                database
+                    .single_order(&uid)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .metadata
+                    .status
+            }
+        };
+        assert_eq!(status(&revoked).await, OrderStatus::PresignaturePending);
+
+        // Only the owner can cancel an order whose pre-signature was never set.
+        let other_key = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        assert!(matches!(
+            orderbook
+                .cancel_order(cancellation(pending.metadata.uid, &other_key))
+                .await,
+            Err(OrderCancellationError::WrongOwner)
+        ));
+        assert_eq!(status(&pending).await, OrderStatus::PresignaturePending);
+        orderbook
+            .cancel_order(cancellation(pending.metadata.uid, &key))
+            .await
+            .unwrap();
+        assert_eq!(status(&pending).await, OrderStatus::Cancelled);
+
+        // Orders that were pre-signed on-chain at some point can only be
+        // cancelled on-chain.
+        for order in [&revoked, &signed] {
+            assert!(matches!(
+                orderbook
+                    .cancel_order(cancellation(order.metadata.uid, &key))
+                    .await,
+                Err(OrderCancellationError::OnChainOrder)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_flags_orders_with_insufficient_balance() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let owner = H160([1; 20]);
+        let order = |byte: u8, partially_fillable| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                owner,
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_amount: 90.into(),
+                fee_amount: 10.into(),
+                buy_amount: 1.into(),
+                valid_to: u32::MAX,
+                kind: OrderKind::Sell,
+                partially_fillable,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let fill_or_kill = order(1, false);
+        database.insert_order(&fill_or_kill, None).await.unwrap();
+        database.insert_order(&order(2, true), None).await.unwrap();
+
+        let balance = Arc::new(std::sync::Mutex::new(U256::from(99)));
+        let mut balance_fetcher = MockBalanceFetching::new();
+        balance_fetcher.expect_get_balances().returning({
+            let balance = balance.clone();
+            move |queries| {
+                // Only fill-or-kill orders get checked.
+                assert_eq!(queries.len(), 1);
+                vec![Ok(*balance.lock().unwrap())]
+            }
+        });
+        let mut checker =
+            InsufficientBalances::new(Arc::new(balance_fetcher), Duration::from_secs(60));
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+        async fn flags(orderbook: &Orderbook, owner: &H160) -> HashMap<OrderUid, bool> {
+            orderbook
+                .get_user_orders(owner, 0, 10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|order| (order.metadata.uid, order.metadata.insufficient_balance))
+                .collect()
+        }
+        async fn cached_flag(orderbook: &Orderbook, uid: &OrderUid) -> bool {
+            orderbook
+                .get_order(uid)
+                .await
+                .unwrap()
+                .unwrap()
+                .metadata
+                .insufficient_balance
+        }
+        let uid = fill_or_kill.metadata.uid;
+
+        orderbook.check_balances(&mut checker, 10).await.unwrap();
+        assert!(!flags(&orderbook, &owner).await[&uid]);
+        assert!(!cached_flag(&orderbook, &uid).await);
+
+        orderbook.check_balances(&mut checker, 10).await.unwrap();
+        let flagged = flags(&orderbook, &owner).await;
+        assert!(flagged[&uid]);
+        assert!(!flagged[&OrderUid([2; 56])]);
+        // Changing the flag invalidates cached orders.
+        assert!(cached_flag(&orderbook, &uid).await);
+
+        *balance.lock().unwrap() = 100.into();
+        orderbook.check_balances(&mut checker, 10).await.unwrap();
+        assert!(!flags(&orderbook, &owner).await[&uid]);
+        assert!(!cached_flag(&orderbook, &uid).await);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_records_order_source() {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|creation, _, _, _| {
+                let byte = creation.sell_amount.low_u32() as u8;
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            uid: OrderUid([byte; 56]),
+                            full_app_data: Some(r#"{"appCode": "CoW Swap"}"#.to_string()),
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    None,
+                ))
+            });
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        // IP addresses don't get hashed without a salt.
+        assert_eq!(orderbook.client_ip_hash(ip), None);
+        let orderbook = orderbook.with_client_ip_hash_salt(Some("salt".to_string()));
+        let client_ip_hash = orderbook.client_ip_hash(ip);
+        assert_eq!(
+            client_ip_hash,
+            Some(OrderSource::hash_client_ip(b"salt", ip))
+        );
+
+        let creation = |byte: u8| OrderCreation {
+            sell_amount: byte.into(),
+            valid_to: u32::MAX,
+            ..Default::default()
+        };
+        let created = registered_counter(
+            "orderbook_orders",
+            &[
+                ("kind", "user"),
+                ("operation", "created"),
+                ("quote_verified", "false"),
+                ("source", "api"),
+            ],
+        )
+        .unwrap_or_default();
+        let sources = [
+            // The app code gets taken from the full app data.
+            (
+                OrderSource::Api {
+                    client_ip_hash,
+                    app_code: None,
+                },
+                OrderSource::Api {
+                    client_ip_hash,
+                    app_code: Some("CoW Swap".to_string()),
+                },
+            ),
+            (
+                OrderSource::Api {
+                    client_ip_hash: None,
+                    app_code: Some("explicit".to_string()),
+                },
+                OrderSource::Api {
+                    client_ip_hash: None,
+                    app_code: Some("explicit".to_string()),
+                },
+            ),
+        ];
+        for (byte, (source, expected)) in (1..).zip(sources) {
+            let (uid, _) = orderbook.add_order(creation(byte), source).await.unwrap();
+            let order = orderbook
+                .database
+                .single_order(&uid)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(order.metadata.source, Some(expected));
+        }
+        assert!(
+            registered_counter(
+                "orderbook_orders",
+                &[
+                    ("kind", "user"),
+                    ("operation", "created"),
+                    ("quote_verified", "false"),
+                    ("source", "api"),
+                ],
+            )
+            .unwrap()
+                >= created + 2.
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_publishes_order_events() {
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let owner = SecretKeyRef::new(&key).address();
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(move |creation, _, _, _| {
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            uid: OrderUid([creation.sell_amount.low_u32() as u8; 56]),
+                            owner,
+                            ..Default::default()
+                        },
+                        data: creation.data(),
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    None,
+                ))
+            });
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        let mut events = orderbook.subscribe_order_events();
+
+        let creation = |byte: u8| OrderCreation {
+            sell_amount: byte.into(),
+            valid_to: u32::MAX,
+            from: Some(owner),
+            signature: Signature::Eip712(Default::default()),
+            ..Default::default()
+        };
+        let uid = |byte: u8| OrderUid([byte; 56]);
+        for byte in 1..=3 {
+            orderbook
+                .add_order(creation(byte), api_source())
+                .await
+                .unwrap();
+        }
+
+        let cancellation = OrderCancellation {
+            order_uid: uid(1),
+            valid_to: None,
+            ..Default::default()
+        };
+        orderbook
+            .cancel_order(OrderCancellation {
+                signature: CancellationSignature::sign(
+                    EcdsaSigningScheme::Eip712,
+                    &Default::default(),
+                    &cancellation.hash_struct(),
+                    SecretKeyRef::new(&key),
+                ),
+                ..cancellation
+            })
+            .await
+            .unwrap();
+
+        let cancellations = OrderCancellations {
+            order_uids: vec![uid(2)],
+            valid_to: None,
+        };
+        orderbook
+            .cancel_orders(SignedOrderCancellations {
+                signature: CancellationSignature::sign(
+                    EcdsaSigningScheme::Eip712,
+                    &Default::default(),
+                    &cancellations.hash_struct(),
+                    SecretKeyRef::new(&key),
+                ),
+                data: cancellations,
+            })
+            .await
+            .unwrap();
+
+        let replacement = OrderCreation {
+            app_data: AppDataHash(
+                OrderCancellation {
+                    order_uid: uid(3),
+                    ..Default::default()
+                }
+                .hash_struct(),
+            )
+            .into(),
+            ..creation(4)
+        };
+        orderbook
+            .replace_order(uid(3), replacement, api_source())
+            .await
+            .unwrap();
+
+        // Failed operations don't publish events.
+        assert!(orderbook
+            .add_order(creation(4), api_source())
+            .await
+            .is_err());
+
+        let mut published = Vec::new();
+        while let Some(event) = events.try_recv() {
+            published.push((event.uid, event.kind));
+        }
+        assert_eq!(
+            published,
+            vec![
+                (uid(1), OrderEventKind::Created),
+                (uid(2), OrderEventKind::Created),
+                (uid(3), OrderEventKind::Created),
+                (uid(1), OrderEventKind::Cancelled),
+                (uid(2), OrderEventKind::Cancelled),
+                (uid(3), OrderEventKind::Replaced),
+                (uid(4), OrderEventKind::Created),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_cancellation_valid_to() {
+        let now = 1_700_000_000;
+        assert!(check_cancellation_valid_to(Some(now), false, now).is_ok());
+        assert!(check_cancellation_valid_to(Some(now + 1), false, now).is_ok());
+        assert!(matches!(
+            check_cancellation_valid_to(Some(now - 1), true, now),
+            Err(OrderCancellationError::CancellationExpired)
+        ));
+
+        // Cancellations without validity are only accepted for backwards
+        // compatibility.
+        assert!(check_cancellation_valid_to(None, true, now).is_ok());
+        assert!(matches!(
+            check_cancellation_valid_to(None, false, now),
+            Err(OrderCancellationError::CancellationExpired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn records_rejection_reasons() {
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|creation, _, _, _| {
+                Err(match creation.sell_amount.low_u32() {
+                    0 => ValidationError::InsufficientBalance,
+                    1 => ValidationError::InvalidSignature,
+                    _ => ValidationError::Other(anyhow!("unexpected")),
+                })
+            });
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            accept_cancellations_without_valid_to: false,
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let rejections = |operation: &str, reason: &str| {
+            Metrics::get()
+                .rejected_orders
+                .with_label_values(&[operation, reason])
+                .get()
+        };
+        let before = [
+            rejections("created", "insufficient_balance"),
+            rejections("created", "invalid_signature"),
+            rejections("created", "other"),
+            rejections("cancelled", "cancellation_expired"),
+        ];
+
+        for sell_amount in [0, 1, 1, 2] {
+            let creation = OrderCreation {
+                sell_amount: sell_amount.into(),
+                app_data: OrderCreationAppData::Full {
+                    full: "{}".to_string(),
+                },
+                ..Default::default()
+            };
+            assert!(orderbook.add_order(creation, api_source()).await.is_err());
+        }
+        assert!(matches!(
+            orderbook.cancel_order(Default::default()).await,
+            Err(OrderCancellationError::CancellationExpired)
+        ));
+
+        let after = [
+            rejections("created", "insufficient_balance"),
+            rejections("created", "invalid_signature"),
+            rejections("created", "other"),
+            rejections("cancelled", "cancellation_expired"),
+        ];
+        let increments: Vec<_> = before.iter().zip(&after).map(|(b, a)| a - b).collect();
+        assert_eq!(increments, [1, 2, 1, 1]);
+    }
+
+    /// Returns the value of the counter with the labels from the global
+    /// metrics registry.
+    fn registered_counter(name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        observe::metrics::get_registry()
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == name)?
+            .get_metric()
+            .iter()
+            .find(|metric| {
+                labels.iter().all(|(name, value)| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|label| label.get_name() == *name && label.get_value() == *value)
+                })
+            })
+            .map(|metric| metric.get_counter().get_value())
+    }
+
+    #[tokio::test]
+    async fn records_replacement_metrics() {
+        Metrics::initialize();
+        for kind in ["user", "liquidity", "limit"] {
+            for verified in ["true", "false"] {
+                assert!(registered_counter(
+                    "orderbook_orders",
+                    &[
+                        ("kind", kind),
+                        ("operation", "replaced"),
+                        ("quote_verified", verified),
+                        ("source", "unknown"),
+                    ],
+                )
+                .is_some());
+            }
+        }
+        for error in ReplaceOrderError::VARIANT_LABELS {
+            assert!(
+                registered_counter("orderbook_failed_replacements", &[("error", error)]).is_some()
+            );
+        }
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+        let counters = || {
+            [
+                registered_counter(
+                    "orderbook_failed_replacements",
+                    &[("error", "invalid_replacement")],
+                ),
+                registered_counter(
+                    "orderbook_rejected_orders",
+                    &[("operation", "created"), ("reason", "invalid_replacement")],
+                ),
+            ]
+            .map(Option::unwrap_or_default)
+        };
+        let before = counters();
+
+        // Pre-signed orders can't replace orders.
+        let presigned = OrderCreation {
+            signature: Signature::PreSign,
+            ..Default::default()
+        };
+        assert!(matches!(
+            orderbook
+                .replace_order(OrderUid([1; 56]), presigned.clone(), api_source())
+                .await,
+            Err(ReplaceOrderError::InvalidReplacement)
+        ));
+        assert!(matches!(
+            orderbook
+                .replace_orders(vec![(OrderUid([1; 56]), presigned)], api_source())
+                .await,
+            Err(ReplaceOrderError::Batch { index: 0, source })
+                if matches!(*source, ReplaceOrderError::InvalidReplacement)
+        ));
+
+        let after = counters();
+        assert_eq!(after[0] - before[0], 2.);
+        assert_eq!(after[1] - before[1], 2.);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_orders_for_replaced_tx() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        database.insert_order(&order, None).await.unwrap();
+
+        let solver = H160([2; 20]);
+        let mined = H256([3; 32]);
+        let replaced = H256([4; 32]);
+        let unknown = H256([5; 32]);
+        let mut ex = database.pool.begin().await.unwrap();
+        database::events::append(
+            &mut ex,
+            &[
+                (
+                    database::events::EventIndex {
+                        block_number: 0,
+                        log_index: 0,
+                    },
+                    database::events::Event::Trade(database::events::Trade {
+                        order_uid: ByteArray(order.metadata.uid.0),
+                        ..Default::default()
+                    }),
+                ),
+                (
+                    database::events::EventIndex {
+                        block_number: 0,
+                        log_index: 1,
+                    },
+                    database::events::Event::Settlement(database::events::Settlement {
+                        solver: ByteArray(solver.0),
+                        transaction_hash: ByteArray(mined.0),
+                    }),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        database::settlements::update_settlement_sender(&mut ex, 0, 1, &ByteArray(solver.0), 7)
+            .await
+            .unwrap();
+        ex.commit().await.unwrap();
+
+        let mut transactions = MockTransactionSenderFetching::new();
+        transactions
+            .expect_sender_and_nonce()
+            .returning(move |hash| {
+                Ok(match hash {
+                    hash if hash == replaced => Some((solver, 7)),
+                    hash if hash == unknown => Some((solver, 8)),
+                    _ => None,
+                })
+            });
+        let orderbook = Orderbook {
+            transactions: Arc::new(transactions),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        let uids = |orders: &[Order]| {
+            orders
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>()
+        };
+
+        let direct = orderbook.get_orders_for_tx(&mined).await.unwrap();
+        assert_eq!(uids(&direct.orders), vec![order.metadata.uid]);
+        assert_eq!(direct.remapped_tx_hash, None);
+
+        let remapped = orderbook.get_orders_for_tx(&replaced).await.unwrap();
+        assert_eq!(uids(&remapped.orders), vec![order.metadata.uid]);
+        assert_eq!(remapped.remapped_tx_hash, Some(mined));
+
+        let miss = orderbook.get_orders_for_tx(&unknown).await.unwrap();
+        assert!(miss.orders.is_empty());
+        assert_eq!(miss.remapped_tx_hash, None);
+    }
+
+    async fn save_auction(database: &crate::database::Postgres) -> dto::AuctionId {
+        let auction = dto::Auction {
+            block: 1,
+            latest_settlement_block: 0,
+            orders: vec![],
+            prices: Default::default(),
+        };
+        let mut ex = database.pool.acquire().await.unwrap();
+        database::auction::save(&mut ex, &serde_json::to_value(auction).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_auction_after_returns_newer_auction_immediately() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let id = save_auction(&database).await;
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let auction = tokio::time::timeout(
+            Duration::from_secs(1),
+            orderbook.get_auction_after(id - 1, Duration::from_secs(60)),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+        assert_eq!(auction.id, id);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_auction_after_waits_for_new_auction() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let id = save_auction(&database).await;
+        let orderbook = Arc::new(orderbook_with_signature_validator(
+            database.clone(),
+            MockSignatureValidating::new(),
+        ));
+
+        let waiting = tokio::spawn({
+            let orderbook = orderbook.clone();
+            async move {
+                orderbook
+                    .get_auction_after(id, Duration::from_secs(60))
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        let new_id = save_auction(&database).await;
+        tokio::spawn(orderbook.observe_auctions(Duration::from_millis(10)));
+        let auction = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(auction.id, new_id);
+    }
+
+    #[tokio::test]
+    async fn reports_stale_auctions() {
+        let auction = |id| dto::AuctionWithId {
+            id,
+            auction: dto::Auction {
+                block: 1,
+                latest_settlement_block: 0,
+                orders: vec![],
+                prices: Default::default(),
+            },
+        };
+        let mut auctions = MockAuctionRetrieving::new();
+        let mut seq = mockall::Sequence::new();
+        auctions
+            .expect_most_recent_auction()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move || {
+                Ok(Some((
+                    auction(1),
+                    Utc::now() - chrono::Duration::seconds(10),
+                )))
+            });
+        auctions
+            .expect_most_recent_auction()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move || Ok(Some((auction(2), Utc::now() - chrono::Duration::hours(1)))));
+        auctions
+            .expect_most_recent_auction_created()
+            .times(1)
+            .returning(|| Ok(Some(Utc::now() - chrono::Duration::hours(1))));
+        let mut probe = MockHealthProbing::new();
+        probe
+            .expect_most_recent_auction_id()
+            .returning(|| Ok(Some(2)));
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        let orderbook = Orderbook {
+            auctions: Arc::new(auctions),
+            health: HealthMonitor::new(Arc::new(probe), Duration::MAX),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        }
+        .with_max_auction_age(Some(Duration::from_secs(60)));
+
+        assert_eq!(orderbook.get_auction().await.unwrap().unwrap().id, 1);
+        assert!(matches!(
+            orderbook.get_auction().await,
+            Err(AuctionRetrievalError::Stale { id: 2, .. })
+        ));
+        assert!(!orderbook.is_alive().await);
+
+        // Auctions never get stale without a maximum age.
+        let orderbook = orderbook.with_max_auction_age(None);
+        assert!(orderbook.is_alive().await);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_auction_by_id() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database.clone(), MockSignatureValidating::new())
+                .with_auction_retention(2);
+        assert!(orderbook.get_auction_by_id(1).await.unwrap().is_none());
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(save_auction(&database).await);
+        }
+        let mut ex = database.pool.acquire().await.unwrap();
+        database::auction::delete_auctions_before(&mut ex, ids[1])
+            .await
+            .unwrap();
+
+        // Present auctions.
+        for id in &ids[1..] {
+            let auction = orderbook.get_auction_by_id(*id).await.unwrap().unwrap();
+            assert_eq!(auction.id, *id);
+        }
+        // Pruned auctions.
+        assert!(matches!(
+            orderbook.get_auction_by_id(ids[0]).await,
+            Err(AuctionRetrievalError::Pruned(id)) if id == ids[0]
+        ));
+        // Auctions that never existed.
+        assert!(orderbook
+            .get_auction_by_id(ids[2] + 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_auction_after_times_out() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let id = save_auction(&database).await;
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let auction = orderbook
+            .get_auction_after(id, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(auction.is_none());
+    }
+
+    #[test]
+    fn checks_open_order_limit() {
+        // Owners with one order less than the limit can still place an order.
+        assert!(check_open_orders(2, 1).is_ok());
+        assert!(matches!(
+            check_open_orders(2, 2),
+            Err(AddOrderError::TooManyOpenOrders {
+                limit: 2,
+                current: 2
+            })
+        ));
+        assert!(matches!(
+            check_open_orders(2, 3),
+            Err(AddOrderError::TooManyOpenOrders {
+                limit: 2,
+                current: 3
+            })
+        ));
+
+        let exempt = H160([1; 20]);
+        let limit = OpenOrderLimit {
+            max_open_orders_per_owner: Some(2),
+            exempt_owners: [exempt].into_iter().collect(),
+        };
+        assert_eq!(limit.limit_for(&H160([2; 20])), Some(2));
+        assert_eq!(limit.limit_for(&exempt), None);
+        assert_eq!(OpenOrderLimit::default().limit_for(&H160([2; 20])), None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_add_orders_enforces_open_order_limit() {
+        let owner = H160([1; 20]);
+        let market_maker = H160([2; 20]);
+        let creation = |from: H160, valid_to: u32| OrderCreation {
+            sell_amount: 1.into(),
+            buy_amount: 1.into(),
+            valid_to,
+            from: Some(from),
+            signature: Signature::PreSign,
+            ..Default::default()
+        };
+
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .returning(|creation, domain, _, _| {
+                let owner = creation.from.unwrap();
+                let data = creation.data();
+                Ok((
+                    Order {
+                        metadata: OrderMetadata {
+                            owner,
+                            uid: data.uid(domain, &owner),
+                            ..Default::default()
+                        },
+                        data,
+                        signature: creation.signature,
+                        ..Default::default()
+                    },
+                    None,
+                ))
+            });
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            open_order_limit: OpenOrderLimit {
+                max_open_orders_per_owner: Some(2),
+                exempt_owners: [market_maker].into_iter().collect(),
+            },
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+
+        orderbook
+            .add_order(creation(owner, u32::MAX), api_source())
+            .await
+            .unwrap();
+
+        // Only one more order fits within the limit, also within a batch.
+        let results = orderbook
+            .add_orders(
+                vec![creation(owner, u32::MAX - 1), creation(owner, u32::MAX - 2)],
+                api_source(),
+            )
+            .await;
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(AddOrderError::TooManyOpenOrders {
+                limit: 2,
+                current: 2
+            })
+        ));
+        assert!(matches!(
+            orderbook
+                .add_order(creation(owner, u32::MAX - 3), api_source())
+                .await,
+            Err(AddOrderError::TooManyOpenOrders {
+                limit: 2,
+                current: 2
+            })
+        ));
+
+        // Exempt owners are not limited.
+        for valid_to in 0..3 {
+            orderbook
+                .add_order(creation(market_maker, u32::MAX - valid_to), api_source())
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_user_orders_with_count() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let owner = H160([1; 20]);
+        for i in 0u8..7 {
+            let order = Order {
+                metadata: OrderMetadata {
+                    uid: OrderUid([i; 56]),
+                    owner,
+                    creation_date: Utc.timestamp_opt(i.into(), 0).unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            database.insert_order(&order, None).await.unwrap();
+        }
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let page = |offset| {
+            let orderbook = &orderbook;
+            async move {
+                let page = orderbook
+                    .get_user_orders_with_count(&owner, &Default::default(), offset, 3)
+                    .await
+                    .unwrap();
+                let uids: Vec<_> = page
+                    .orders
+                    .iter()
+                    .map(|order| order.metadata.uid.0[0])
+                    .collect();
+                (uids, page.total_count, page.has_more)
+            }
+        };
+        assert_eq!(page(0).await, (vec![6, 5, 4], 7, true));
+        assert_eq!(page(3).await, (vec![3, 2, 1], 7, true));
+        assert_eq!(page(6).await, (vec![0], 7, false));
+        assert_eq!(page(9).await, (vec![], 7, false));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_order_executions() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let order = Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        database.insert_order(&order, None).await.unwrap();
+
+        // The order gets filled in two settlements of which only the first one
+        // was processed by the settlement updater.
+        let uid = ByteArray(order.metadata.uid.0);
+        let index = |block_number, log_index| database::events::EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |sell_amount: u32, buy_amount: u32| {
+            database::events::Event::Trade(database::events::Trade {
+                order_uid: uid,
+                sell_amount_including_fee: sell_amount.into(),
+                buy_amount: buy_amount.into(),
+                fee_amount: 0.into(),
+            })
+        };
+        let settlement = |hash: u8| {
+            database::events::Event::Settlement(database::events::Settlement {
+                solver: Default::default(),
+                transaction_hash: ByteArray([hash; 32]),
+            })
+        };
+        let mut ex = database.pool.begin().await.unwrap();
+        database::events::append(
+            &mut ex,
+            &[
+                (index(1, 0), trade(10, 20)),
+                (index(1, 1), settlement(1)),
+                (index(2, 0), trade(30, 60)),
+                (index(2, 1), settlement(2)),
+            ],
+        )
+        .await
+        .unwrap();
+        database::order_execution::save(&mut ex, &uid, 1, 1, &3.into())
+            .await
+            .unwrap();
+        ex.commit().await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        assert_eq!(
+            orderbook
+                .get_order_executions(&order.metadata.uid)
+                .await
+                .unwrap(),
+            vec![
+                OrderExecution {
+                    block_number: 1,
+                    log_index: 0,
+                    tx_hash: Some(H256([1; 32])),
+                    sell_amount: 10.into(),
+                    buy_amount: 20.into(),
+                    fee_amount: 0.into(),
+                    surplus_fee: Some(3.into()),
+                },
+                OrderExecution {
+                    block_number: 2,
+                    log_index: 0,
+                    tx_hash: Some(H256([2; 32])),
+                    sell_amount: 30.into(),
+                    buy_amount: 60.into(),
+                    fee_amount: 0.into(),
+                    surplus_fee: None,
+                },
+            ]
+        );
+        assert!(orderbook
+            .get_order_executions(&OrderUid([2; 56]))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_order_with_executions() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let order = |byte: u8| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([byte; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for byte in 1..=3 {
+            database.insert_order(&order(byte), None).await.unwrap();
+        }
+
+        // Order 1 gets filled once. Order 2 gets filled twice by the first
+        // settlement and once more by the second one. Order 3 never gets
+        // filled.
+        let index = |block_number, log_index| database::events::EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |byte: u8| {
+            database::events::Event::Trade(database::events::Trade {
+                order_uid: ByteArray([byte; 56]),
+                sell_amount_including_fee: 1.into(),
+                buy_amount: 1.into(),
+                fee_amount: 0.into(),
+            })
+        };
+        let settlement = |hash: u8| {
+            database::events::Event::Settlement(database::events::Settlement {
+                solver: Default::default(),
+                transaction_hash: ByteArray([hash; 32]),
+            })
+        };
+        let mut ex = database.pool.begin().await.unwrap();
+        database::events::append(
+            &mut ex,
+            &[
+                (index(1, 0), trade(1)),
+                (index(1, 1), trade(2)),
+                (index(1, 2), trade(2)),
+                (index(1, 3), settlement(1)),
+                (index(2, 0), trade(2)),
+                (index(2, 1), settlement(2)),
+            ],
+        )
+        .await
+        .unwrap();
+        ex.commit().await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let hashes = |byte: u8| {
+            let orderbook = &orderbook;
+            async move {
+                orderbook
+                    .get_order_with_executions(&OrderUid([byte; 56]))
+                    .await
+                    .unwrap()
+                    .map(|order| order.metadata.settlement_tx_hashes)
+            }
+        };
+        assert_eq!(hashes(1).await, Some(Some(vec![H256([1; 32])])));
+        assert_eq!(
+            hashes(2).await,
+            Some(Some(vec![H256([1; 32]), H256([2; 32])]))
+        );
+        assert_eq!(hashes(3).await, Some(Some(vec![])));
+        assert_eq!(hashes(4).await, None);
+
+        // The regular lookup doesn't include the hashes.
+        let order = orderbook
+            .get_order(&OrderUid([1; 56]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.metadata.settlement_tx_hashes, None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_get_user_surplus() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let owner = H160([1; 20]);
+        let sell_token = H160([2; 20]);
+        let buy_token = H160([3; 20]);
+        let order = |uid: u8, kind, partially_fillable| Order {
+            data: OrderData {
+                sell_token,
+                buy_token,
+                sell_amount: 100.into(),
+                buy_amount: 100.into(),
+                kind,
+                partially_fillable,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                owner,
+                creation_date: Utc.timestamp_opt(1_000, 0).unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let partial = order(1, OrderKind::Sell, true);
+        let buy = order(2, OrderKind::Buy, false);
+        database.insert_order(&partial, None).await.unwrap();
+        database.insert_order(&buy, None).await.unwrap();
+
+        // The partially fillable order gets filled in two settlements.
+        let index = |block_number, log_index| database::events::EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |order: &Order, sell_amount: u32, buy_amount: u32| {
+            database::events::Event::Trade(database::events::Trade {
+                order_uid: ByteArray(order.metadata.uid.0),
+                sell_amount_including_fee: sell_amount.into(),
+                buy_amount: buy_amount.into(),
+                fee_amount: 0.into(),
+            })
+        };
+        let settlement = database::events::Event::Settlement(Default::default());
+        let mut ex = database.pool.begin().await.unwrap();
+        database::events::append(
+            &mut ex,
+            &[
+                (index(1, 0), trade(&partial, 50, 60)),
+                (index(1, 1), settlement.clone()),
+                (index(2, 0), trade(&partial, 50, 55)),
+                (index(2, 1), trade(&buy, 80, 100)),
+                (index(2, 2), settlement),
+            ],
+        )
+        .await
+        .unwrap();
+        for (block_number, log_index, auction_id, price) in [
+            (1, 1, 1, 1_000_000_000_000_000_000u64),
+            (2, 2, 2, 3_000_000_000_000_000_000),
+        ] {
+            database::settlements::update_settlement_auction(
+                &mut ex,
+                block_number,
+                log_index,
+                auction_id,
+            )
+            .await
+            .unwrap();
+            let price = |token: H160| database::auction_prices::AuctionPrice {
+                auction_id,
+                token: ByteArray(token.0),
+                price: price.into(),
+            };
+            database::auction_prices::insert(&mut ex, &[price(sell_token), price(buy_token)])
+                .await
+                .unwrap();
+        }
+        database::order_execution::save(&mut ex, &ByteArray(buy.metadata.uid.0), 2, 2, &5.into())
+            .await
+            .unwrap();
+        ex.commit().await.unwrap();
+        let orderbook =
+            orderbook_with_signature_validator(database, MockSignatureValidating::new());
+
+        let surplus = |from: i64, to: i64| {
+            orderbook.get_user_surplus(
+                &owner,
+                Utc.timestamp_opt(from, 0).unwrap(),
+                Utc.timestamp_opt(to, 0).unwrap(),
+            )
+        };
+        // Partial fills: 10 and 5 buy tokens worth 1 and 3 wei each. Buy
+        // order: saved 20 sell tokens worth 3 wei each.
+        assert_eq!(
+            surplus(1_000, 2_000).await.unwrap(),
+            UserSurplus {
+                surplus: 85.into(),
+                surplus_fee: 15.into(),
+            }
+        );
+        assert_eq!(surplus(2_000, 3_000).await.unwrap(), UserSurplus::default());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_add_order_rejects_excessive_hook_gas() {
+        let database = crate::database::Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&database.pool).await.unwrap();
+        let validator = shared::app_data::Validator::new(8192);
+        let document = |gas_limit: u64| {
+            let document = format!(
+                r#"{{"metadata":{{"hooks":{{"pre":[{{"target":"0x0101010101010101010101010101010101010101","callData":"0x","gasLimit":"{gas_limit}"}}]}}}}}}"#
+            );
+            let hash = validator.validate(document.as_bytes()).unwrap().hash;
+            (document, hash)
+        };
+
+        // App data that got registered before the limit was configured.
+        let (expensive, expensive_hash) = document(200_000);
+        let (cheap, cheap_hash) = document(100_000);
+        for (document, hash) in [(&expensive, &expensive_hash), (&cheap, &cheap_hash)] {
+            database.insert_full_app_data(hash, document).await.unwrap();
+        }
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_validate_and_construct_order()
+            .withf({
+                let cheap = cheap.clone();
+                move |_, _, _, full_app_data| full_app_data.as_ref() == Some(&cheap)
+            })
+            .returning(|_, _, _, _| Err(ValidationError::InsufficientBalance));
+        let orderbook = Orderbook {
+            order_validator: Arc::new(order_validator),
+            app_data: Arc::new(app_data::Registry::new(
+                validator.clone().with_max_hook_gas(Some(100_000)),
+                database.clone(),
+                None,
+            )),
+            ..orderbook_with_signature_validator(database, MockSignatureValidating::new())
+        };
+        let creation = |hash| OrderCreation {
+            app_data: OrderCreationAppData::Hash { hash },
+            ..Default::default()
+        };
+
+        // Hash-only submissions get checked against the resolved app data.
+        assert!(matches!(
+            orderbook
+                .add_order(creation(expensive_hash), api_source())
+                .await,
+            Err(AddOrderError::ExcessiveHookGas {
+                requested: 200_000,
+                max: 100_000
+            })
+        ));
+        assert!(matches!(
+            orderbook
+                .add_order(creation(cheap_hash), api_source())
+                .await,
+            Err(AddOrderError::OrderValidation(
+                ValidationError::InsufficientBalance
+            ))
+        ));
+
+        // Full app data gets checked as well.
+        assert!(matches!(
+            orderbook
+                .add_order(
+                    OrderCreation {
+                        app_data: OrderCreationAppData::Full {
+                            full: expensive.clone()
+                        },
+                        ..Default::default()
+                    },
+                    api_source()
+                )
+                .await,
+            Err(AddOrderError::ExcessiveHookGas { .. })
+        ));
+
+        // The app data stays readable.
+        assert_eq!(
+            orderbook.app_data.find(&expensive_hash).await.unwrap(),
+            Some(expensive)
+        );
+    }
+
+    #[test]
+    fn expired_quotes_get_dedicated_error() {
+        let expired_at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert!(matches!(
+            AddOrderError::from(ValidationError::QuoteExpired {
+                quote_id: 42,
+                expired_at,
+            }),
+            AddOrderError::QuoteExpired {
+                quote_id: 42,
+                expired_at: actual,
+            } if actual == expired_at
+        ));
+        assert!(matches!(
+            AddOrderError::from(ValidationError::InvalidQuote),
+            AddOrderError::OrderValidation(ValidationError::InvalidQuote)
+        ));
+    }
 }
@@ -0,0 +1,226 @@
+use {
+    primitive_types::H160,
+    std::{collections::HashMap, sync::Mutex, time::Duration},
+    tokio::time::Instant,
+};
+
+#[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
+#[metric(subsystem = "submission_rate_limit")]
+struct Metrics {
+    /// Number of submitters that are currently tracked by the rate limiter.
+    tracked_submitters: prometheus::IntGauge,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Self::instance(observe::metrics::get_storage_registry())
+            .expect("unexpected error getting metrics instance")
+    }
+}
+
+/// How many orders a single submitter can place.
+#[derive(Clone, Debug)]
+pub struct SubmissionRateLimit {
+    /// Number of orders that can be placed at once.
+    pub burst: u32,
+    /// Time after which a submitter can place one more order.
+    pub refill_interval: Duration,
+    /// Limit the orders of an owner per app code instead of across all app
+    /// codes.
+    pub per_app_code: bool,
+}
+
+impl SubmissionRateLimit {
+    /// Time it takes for an empty bucket to become full again.
+    fn full_refill(&self) -> Duration {
+        self.refill_interval * self.burst
+    }
+}
+
+/// Token bucket rate limiter for order submissions keyed by owner and
+/// optionally the app code.
+///
+/// Buckets that refilled completely are indistinguishable from new ones so
+/// they get dropped regularly. This bounds the memory to the submitters that
+/// were active recently.
+pub struct SubmissionRateLimiter {
+    config: SubmissionRateLimit,
+    state: Mutex<State>,
+}
+
+type Key = (H160, Option<String>);
+
+struct State {
+    buckets: HashMap<Key, Bucket>,
+    last_cleanup: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, config: &SubmissionRateLimit) {
+        let refilled = now.duration_since(self.refilled_at).as_secs_f64()
+            / config.refill_interval.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(config.burst.into());
+        self.refilled_at = now;
+    }
+
+    fn is_full(&self, config: &SubmissionRateLimit) -> bool {
+        self.tokens >= config.burst.into()
+    }
+}
+
+impl SubmissionRateLimiter {
+    pub fn new(config: SubmissionRateLimit) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State {
+                buckets: Default::default(),
+                last_cleanup: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes one submission from the submitter's bucket. Returns how long the
+    /// submitter has to wait before it can submit again if the bucket is
+    /// empty.
+    pub fn check(&self, owner: H160, app_code: Option<&str>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let key = (
+            owner,
+            app_code
+                .filter(|_| self.config.per_app_code)
+                .map(str::to_owned),
+        );
+        let mut state = self.state.lock().unwrap();
+        self.cleanup(&mut state, now);
+
+        let bucket = state.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: self.config.burst.into(),
+            refilled_at: now,
+        });
+        bucket.refill(now, &self.config);
+        let result = if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            Ok(())
+        } else {
+            Err(self.config.refill_interval.mul_f64(1. - bucket.tokens))
+        };
+        Metrics::get()
+            .tracked_submitters
+            .set(state.buckets.len() as i64);
+        result
+    }
+
+    /// Drops the buckets of idle submitters once per full refill period.
+    fn cleanup(&self, state: &mut State, now: Instant) {
+        if now.duration_since(state.last_cleanup) < self.config.full_refill() {
+            return;
+        }
+        state.buckets.retain(|_, bucket| {
+            bucket.refill(now, &self.config);
+            !bucket.is_full(&self.config)
+        });
+        state.last_cleanup = now;
+    }
+
+    #[cfg(test)]
+    fn tracked_submitters(&self) -> usize {
+        self.state.lock().unwrap().buckets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_app_code: bool) -> SubmissionRateLimiter {
+        SubmissionRateLimiter::new(SubmissionRateLimit {
+            burst: 3,
+            refill_interval: Duration::from_secs(2),
+            per_app_code,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn allows_bursts_up_to_the_limit() {
+        let limiter = limiter(false);
+        let owner = H160([1; 20]);
+
+        for _ in 0..3 {
+            limiter.check(owner, None).unwrap();
+        }
+        assert_eq!(limiter.check(owner, None), Err(Duration::from_secs(2)));
+        // Other owners have their own bucket.
+        limiter.check(H160([2; 20]), None).unwrap();
+        // App codes share the owner's bucket unless configured otherwise.
+        assert!(limiter.check(owner, Some("CoW Swap")).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_per_app_code() {
+        let limiter = limiter(true);
+        let owner = H160([1; 20]);
+
+        for _ in 0..3 {
+            limiter.check(owner, Some("CoW Swap")).unwrap();
+        }
+        assert!(limiter.check(owner, Some("CoW Swap")).is_err());
+        limiter.check(owner, Some("other")).unwrap();
+        limiter.check(owner, None).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_over_time() {
+        let limiter = limiter(false);
+        let owner = H160([1; 20]);
+        for _ in 0..3 {
+            limiter.check(owner, None).unwrap();
+        }
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(limiter.check(owner, None), Err(Duration::from_millis(1500)));
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        limiter.check(owner, None).unwrap();
+        assert!(limiter.check(owner, None).is_err());
+
+        // Buckets don't fill up beyond the burst.
+        tokio::time::advance(Duration::from_secs(60)).await;
+        for _ in 0..3 {
+            limiter.check(owner, None).unwrap();
+        }
+        assert!(limiter.check(owner, None).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drops_buckets_of_idle_submitters() {
+        let limiter = limiter(false);
+        let idle = H160([1; 20]);
+        let active = H160([2; 20]);
+        let other = H160([3; 20]);
+        limiter.check(idle, None).unwrap();
+        for _ in 0..3 {
+            limiter.check(active, None).unwrap();
+        }
+        tokio::time::advance(Duration::from_secs(4)).await;
+        for _ in 0..2 {
+            limiter.check(active, None).unwrap();
+        }
+        assert_eq!(limiter.tracked_submitters(), 2);
+
+        // Once the full refill period passed the idle submitter's bucket is
+        // full again and gets dropped while the active submitter's is not.
+        tokio::time::advance(Duration::from_secs(2)).await;
+        limiter.check(other, None).unwrap();
+        assert_eq!(limiter.tracked_submitters(), 2);
+        assert!(limiter.check(idle, None).is_ok());
+        assert_eq!(limiter.tracked_submitters(), 3);
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        limiter.check(other, None).unwrap();
+        assert_eq!(limiter.tracked_submitters(), 1);
+    }
+}
@@ -4,10 +4,12 @@ use {
         app_data,
         arguments::Arguments,
         database::Postgres,
+        insufficient_balances::InsufficientBalances,
         ipfs::Ipfs,
-        ipfs_app_data::IpfsAppData,
-        orderbook::Orderbook,
+        ipfs_app_data::{AppDataFetching, IpfsAppData},
+        orderbook::{MinOrderValue, OpenOrderLimit, Orderbook},
         quoter::QuoteHandler,
+        rate_limit::SubmissionRateLimit,
     },
     anyhow::{anyhow, Context, Result},
     clap::Parser,
@@ -59,6 +61,16 @@ use {
     warp::Filter,
 };
 
+/// How often the orderbook checks for new auctions to wake up long polling
+/// requests.
+const AUCTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the metric of open orders per token pair gets updated.
+const OPEN_ORDERS_BY_PAIR_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the metric of open orders per app code gets updated.
+const OPEN_ORDERS_BY_APP_CODE_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub async fn start(args: impl Iterator<Item = String>) {
     let args = Arguments::parse_from(args);
     observe::tracing::initialize(
@@ -438,7 +450,8 @@ pub async fn run(args: Arguments) {
     let optimal_quoter = create_quoter(price_estimator.clone());
     let fast_quoter = create_quoter(fast_price_estimator.clone());
 
-    let app_data_validator = shared::app_data::Validator::new(args.app_data_size_limit);
+    let app_data_validator = shared::app_data::Validator::new(args.app_data_size_limit)
+        .with_max_hook_gas(args.app_data_max_hook_gas);
     let order_validator = Arc::new(
         OrderValidator::new(
             native_token.clone(),
@@ -448,8 +461,8 @@ pub async fn run(args: Arguments) {
             bad_token_detector.clone(),
             hooks_contract,
             optimal_quoter.clone(),
-            balance_fetcher,
-            signature_validator,
+            balance_fetcher.clone(),
+            signature_validator.clone(),
             Arc::new(postgres.clone()),
             args.max_limit_orders_per_user,
             Arc::new(CachedCodeFetcher::new(Arc::new(web3.clone()))),
@@ -467,19 +480,51 @@ pub async fn run(args: Arguments) {
                     .map(|auth| format!("pinataGatewayToken={auth}")),
             )
         })
-        .map(IpfsAppData::new);
-    let app_data = Arc::new(app_data::Registry::new(
-        app_data_validator,
-        postgres.clone(),
-        ipfs,
-    ));
-    let orderbook = Arc::new(Orderbook::new(
-        domain_separator,
-        settlement_contract.address(),
-        postgres.clone(),
-        order_validator.clone(),
-        app_data.clone(),
-    ));
+        .map(|ipfs| Arc::new(IpfsAppData::new(ipfs)) as Arc<dyn AppDataFetching>);
+    let app_data = Arc::new(
+        app_data::Registry::new(app_data_validator, postgres.clone(), ipfs)
+            .with_ipfs_timeout(args.ipfs_timeout),
+    );
+    let orderbook = Arc::new(
+        Orderbook::new(
+            domain_separator,
+            settlement_contract.address(),
+            postgres.clone(),
+            order_validator.clone(),
+            signature_validator,
+            Arc::new(web3.clone()),
+            app_data.clone(),
+            args.accept_cancellations_without_valid_to,
+            args.db_unreachable_grace_period,
+            OpenOrderLimit {
+                max_open_orders_per_owner: args.max_open_orders_per_owner,
+                exempt_owners: args
+                    .open_order_limit_exempt_owners
+                    .iter()
+                    .copied()
+                    .collect(),
+            },
+        )
+        .with_min_order_value(args.min_order_value.map(|min| MinOrderValue {
+            min,
+            native_price_estimator: native_price_estimator.clone(),
+        }))
+        .with_auction_retention(args.shared.auction_retention.get())
+        .with_max_auction_age(args.max_auction_age)
+        .with_max_cancellation_batch_size(args.max_cancellation_batch_size)
+        .with_trusted_liquidity_providers(args.trusted_liquidity_providers.iter().copied())
+        .with_unsupported_tokens(args.unsupported_tokens.iter().copied())
+        .with_client_ip_hash_salt(args.client_ip_hash_salt.clone())
+        .with_trusted_proxies(args.trusted_proxies.iter().copied())
+        .with_submission_rate_limit(
+            args.order_submission_burst
+                .map(|burst| SubmissionRateLimit {
+                    burst: burst.get(),
+                    refill_interval: args.order_submission_refill_interval,
+                    per_app_code: args.order_submission_rate_limit_per_app_code,
+                }),
+        ),
+    );
 
     if let Some(uniswap_v3) = uniswap_v3_pool_fetcher {
         let service_maintainer = ServiceMaintenance::new(vec![uniswap_v3]);
@@ -487,6 +532,27 @@ pub async fn run(args: Arguments) {
     }
 
     check_database_connection(orderbook.as_ref()).await;
+    task::spawn(orderbook.clone().observe_auctions(AUCTION_POLL_INTERVAL));
+    task::spawn(
+        orderbook
+            .clone()
+            .observe_open_orders_by_pair(OPEN_ORDERS_BY_PAIR_UPDATE_INTERVAL),
+    );
+    task::spawn(
+        orderbook
+            .clone()
+            .observe_open_orders_by_app_code(OPEN_ORDERS_BY_APP_CODE_UPDATE_INTERVAL),
+    );
+    task::spawn(orderbook.clone().observe_balances(
+        // Orders that lacked the balance once are remembered for a few checks
+        // since they might not be part of the next sample.
+        InsufficientBalances::new(
+            balance_fetcher,
+            args.insufficient_balance_check_interval * 5,
+        ),
+        args.insufficient_balance_check_sample_size,
+        args.insufficient_balance_check_interval,
+    ));
     let quotes = Arc::new(
         QuoteHandler::new(order_validator, optimal_quoter, app_data.clone())
             .with_fast_quoter(fast_quoter),
@@ -1,6 +1,9 @@
 use {
     anyhow::{anyhow, Context, Result},
-    model::{app_data::AppDataHash, order::Hooks},
+    model::{
+        app_data::AppDataHash,
+        order::{Hooks, OrderUid},
+    },
     primitive_types::H160,
     serde::Deserialize,
 };
@@ -18,33 +21,78 @@ pub struct ValidatedAppData {
 }
 
 #[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct ProtocolAppData {
     #[serde(default)]
     pub hooks: Hooks,
     pub signer: Option<H160>,
+    /// Marks the order as the replacement of an existing order.
+    pub replaced_order: Option<ReplacedOrder>,
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct ReplacedOrder {
+    pub uid: OrderUid,
 }
 
 #[derive(Clone)]
 pub struct Validator {
     size_limit: usize,
+    /// Maximum total gas all hooks of a document may request.
+    max_hook_gas: Option<u64>,
 }
 
 #[cfg(test)]
 impl Default for Validator {
     fn default() -> Self {
-        Self { size_limit: 8192 }
+        Self {
+            size_limit: 8192,
+            max_hook_gas: None,
+        }
     }
 }
 
+/// The hooks of an app data document request more gas than allowed.
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+#[error("hooks request {requested} gas which is more than the maximum of {max}")]
+pub struct ExcessiveHookGas {
+    pub requested: u64,
+    pub max: u64,
+}
+
 impl Validator {
     pub fn new(size_limit: usize) -> Self {
-        Self { size_limit }
+        Self {
+            size_limit,
+            max_hook_gas: None,
+        }
+    }
+
+    /// Limits the total gas the hooks of a document may request. Documents
+    /// exceeding the limit still validate so existing app data stays usable,
+    /// the limit gets checked separately with [`Self::check_hook_gas`].
+    pub fn with_max_hook_gas(mut self, max_hook_gas: Option<u64>) -> Self {
+        self.max_hook_gas = max_hook_gas;
+        self
     }
 
     pub fn size_limit(&self) -> usize {
         self.size_limit
     }
 
+    /// Checks that the hooks don't request more gas than the configured
+    /// maximum.
+    pub fn check_hook_gas(&self, protocol: &ProtocolAppData) -> Result<(), ExcessiveHookGas> {
+        let Some(max) = self.max_hook_gas else {
+            return Ok(());
+        };
+        let requested = protocol.hooks.gas_limit();
+        if requested > max {
+            return Err(ExcessiveHookGas { requested, max });
+        }
+        Ok(())
+    }
+
     pub fn validate(&self, full_app_data: &[u8]) -> Result<ValidatedAppData> {
         if full_app_data.len() > self.size_limit {
             return Err(anyhow!(
@@ -245,6 +293,24 @@ mod tests {
                 ..Default::default()
             },
         );
+
+        assert_app_data!(
+            r#"
+                {
+                    "metadata": {
+                        "replacedOrder": {
+                            "uid": "0x0101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101"
+                        }
+                    }
+                }
+            "#,
+            ProtocolAppData {
+                replaced_order: Some(ReplacedOrder {
+                    uid: OrderUid([1; 56]),
+                }),
+                ..Default::default()
+            },
+        );
     }
 
     #[test]
@@ -355,4 +421,40 @@ mod tests {
         let err = validator.validate(size_limit).unwrap_err();
         dbg!(err);
     }
+
+    #[test]
+    fn hook_gas() {
+        let hook = |gas_limit| Hook {
+            target: H160([1; 20]),
+            call_data: vec![],
+            gas_limit,
+        };
+        let protocol = ProtocolAppData {
+            hooks: Hooks {
+                pre: vec![hook(100_000)],
+                post: vec![hook(50_000), hook(50_000)],
+            },
+            ..Default::default()
+        };
+
+        // Without a maximum any amount of gas is accepted.
+        let validator = Validator::default();
+        assert_eq!(validator.check_hook_gas(&protocol), Ok(()));
+
+        // Gas of pre and post hooks gets added up.
+        let validator = Validator::default().with_max_hook_gas(Some(200_000));
+        assert_eq!(validator.check_hook_gas(&protocol), Ok(()));
+        let validator = Validator::default().with_max_hook_gas(Some(199_999));
+        assert_eq!(
+            validator.check_hook_gas(&protocol),
+            Err(ExcessiveHookGas {
+                requested: 200_000,
+                max: 199_999,
+            })
+        );
+        assert_eq!(
+            validator.check_hook_gas(&ProtocolAppData::default()),
+            Ok(())
+        );
+    }
 }
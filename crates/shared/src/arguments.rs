@@ -266,6 +266,12 @@ pub struct Arguments {
     #[clap(long, env)]
     pub solver_competition_auth: Option<String>,
 
+    /// How many of the most recent auctions are kept in the database. Older
+    /// auctions get pruned by the autopilot and can no longer be retrieved
+    /// from the orderbook.
+    #[clap(long, env, default_value = "10")]
+    pub auction_retention: NonZeroU64,
+
     /// If liquidity pool fetcher has caching mechanism, this argument defines
     /// how old pool data is allowed to be before updating
     #[clap(
@@ -413,6 +419,7 @@ impl Display for Arguments {
             disabled_zeroex_sources,
             balancer_pool_deny_list,
             solver_competition_auth,
+            auction_retention,
             network_block_interval,
             settlement_contract_address,
             native_token_address,
@@ -470,6 +477,7 @@ impl Display for Arguments {
         display_list(f, "disabled_zeroex_sources", disabled_zeroex_sources)?;
         writeln!(f, "balancer_pool_deny_list: {:?}", balancer_pool_deny_list)?;
         display_secret_option(f, "solver_competition_auth", solver_competition_auth)?;
+        writeln!(f, "auction_retention: {}", auction_retention)?;
         display_option(
             f,
             "network_block_interval",
@@ -17,6 +17,7 @@ use {
         app_data::AppDataHash,
         interaction::InteractionData,
         order::{
+            AmendedAmounts,
             BuyTokenDestination,
             EthflowData,
             Interactions,
@@ -27,6 +28,7 @@ use {
             OrderData,
             OrderKind,
             OrderMetadata,
+            OrderSource,
             OrderStatus,
             OrderUid,
             SellTokenSource,
@@ -34,6 +36,7 @@ use {
         signature::{Signature, SigningScheme},
     },
     number::conversions::{big_decimal_to_big_uint, big_decimal_to_u256},
+    serde_json::Value as JsonValue,
 };
 
 pub fn full_order_into_model_order(order: database::orders::FullOrder) -> Result<Order> {
@@ -72,6 +75,15 @@ pub fn full_order_into_model_order(order: database::orders::FullOrder) -> Result
         OrderClass::Limit | OrderClass::Market => full_fee_amount,
     };
 
+    let amended_amounts = match (&order.amended_sell_amount, &order.amended_buy_amount) {
+        (Some(sell_amount), Some(buy_amount)) => Some(AmendedAmounts {
+            sell_amount: big_decimal_to_u256(sell_amount)
+                .context("amended sell amount is not U256")?,
+            buy_amount: big_decimal_to_u256(buy_amount)
+                .context("amended buy amount is not U256")?,
+        }),
+        _ => None,
+    };
     let metadata = OrderMetadata {
         creation_date: order.creation_timestamp,
         owner: H160(order.owner.0),
@@ -107,6 +119,14 @@ pub fn full_order_into_model_order(order: database::orders::FullOrder) -> Result
             .map(String::from_utf8)
             .transpose()
             .context("full app data isn't utf-8")?,
+        amended_amounts,
+        replaces: order.replaces.map(|uid| OrderUid(uid.0)),
+        replaced_by: order.replaced_by.map(|uid| OrderUid(uid.0)),
+        quote_verified: order.quote_verified,
+        balance_check_skipped: order.balance_check_skipped,
+        settlement_tx_hashes: None,
+        source: order.source.map(order_source_from).transpose()?,
+        insufficient_balance: order.insufficient_balance,
     };
     let data = OrderData {
         sell_token: H160(order.sell_token.0),
@@ -263,3 +283,11 @@ pub fn signing_scheme_from(scheme: DbSigningScheme) -> SigningScheme {
         DbSigningScheme::PreSign => SigningScheme::PreSign,
     }
 }
+
+pub fn order_source_into(source: &OrderSource) -> JsonValue {
+    serde_json::to_value(source).expect("order sources always serialize")
+}
+
+pub fn order_source_from(source: JsonValue) -> Result<OrderSource> {
+    serde_json::from_value(source).context("order source is invalid")
+}
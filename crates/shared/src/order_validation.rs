@@ -9,6 +9,7 @@ use {
             FindQuoteError,
             OrderQuoting,
             Quote,
+            QuoteData,
             QuoteParameters,
             QuoteSearchParameters,
         },
@@ -18,6 +19,7 @@ use {
     },
     anyhow::{anyhow, Result},
     async_trait::async_trait,
+    chrono::{DateTime, Utc},
     contracts::{HooksTrampoline, WETH9},
     database::onchain_broadcasted_orders::OnchainOrderPlacementError,
     ethcontract::{Bytes, H160, H256, U256},
@@ -41,7 +43,7 @@ use {
             VerificationError,
             BUY_ETH_ADDRESS,
         },
-        quote::{OrderQuoteSide, QuoteSigningScheme, SellAmount},
+        quote::{OrderQuoteSide, QuoteId, QuoteSigningScheme, SellAmount},
         signature::{self, hashed_eip712_message, Signature, SigningScheme},
         time,
         DomainSeparator,
@@ -100,6 +102,19 @@ pub trait OrderValidating: Send + Sync {
         settlement_contract: H160,
         full_app_data_override: Option<String>,
     ) -> Result<(Order, Option<Quote>), ValidationError>;
+
+    /// Like `validate_and_construct_order` but doesn't check whether the
+    /// owner can transfer the sell amount if the order is a liquidity order.
+    /// Meant for trusted liquidity providers that place too many orders to
+    /// simulate a transfer for each of them. Orders that skipped the check
+    /// are marked with `balance_check_skipped`.
+    async fn validate_and_construct_order_without_balance_check(
+        &self,
+        order: OrderCreation,
+        domain_separator: &DomainSeparator,
+        settlement_contract: H160,
+        full_app_data_override: Option<String>,
+    ) -> Result<(Order, Option<Quote>), ValidationError>;
 }
 
 #[derive(Debug)]
@@ -139,6 +154,12 @@ pub enum ValidationError {
     /// The quote specified by ID is invalid. Either it doesn't match the order
     /// or it has already expired.
     InvalidQuote,
+    /// The quote specified by ID exists but it has expired or doesn't match
+    /// the order, and the order doesn't allow computing a fresh quote instead.
+    QuoteExpired {
+        quote_id: QuoteId,
+        expired_at: DateTime<Utc>,
+    },
     /// Unable to compute quote because of a price estimation error.
     PriceForQuote(PriceEstimationError),
     InsufficientFee,
@@ -174,7 +195,9 @@ pub fn onchain_order_placement_error_from(error: ValidationError) -> OnchainOrde
     match error {
         ValidationError::QuoteNotFound => OnchainOrderPlacementError::QuoteNotFound,
         ValidationError::Partial(_) => OnchainOrderPlacementError::PreValidationError,
-        ValidationError::InvalidQuote => OnchainOrderPlacementError::InvalidQuote,
+        ValidationError::InvalidQuote | ValidationError::QuoteExpired { .. } => {
+            OnchainOrderPlacementError::InvalidQuote
+        }
         ValidationError::InsufficientFee => OnchainOrderPlacementError::InsufficientFee,
         _ => OnchainOrderPlacementError::Other,
     }
@@ -363,150 +386,73 @@ impl OrderValidator {
         Ok(())
     }
 
-    fn custom_interactions(&self, hooks: &Hooks) -> Interactions {
-        let to_interactions = |hooks: &[Hook]| -> Vec<InteractionData> {
-            if hooks.is_empty() {
-                vec![]
-            } else {
-                vec![InteractionData {
-                    target: self.hooks.address(),
-                    value: U256::zero(),
-                    call_data: self
-                        .hooks
-                        .execute(
-                            hooks
-                                .iter()
-                                .map(|hook| {
-                                    (
-                                        hook.target,
-                                        Bytes(hook.call_data.clone()),
-                                        hook.gas_limit.into(),
-                                    )
-                                })
-                                .collect(),
-                        )
-                        .tx
-                        .data
-                        .unwrap()
-                        .0,
-                }]
-            }
-        };
-
-        Interactions {
-            pre: to_interactions(&hooks.pre),
-            post: to_interactions(&hooks.post),
-        }
-    }
-}
-
-#[async_trait::async_trait]
-impl OrderValidating for OrderValidator {
-    async fn partial_validate(&self, order: PreOrderData) -> Result<(), PartialValidationError> {
-        if self.banned_users.contains(&order.owner) || self.banned_users.contains(&order.receiver) {
-            return Err(PartialValidationError::Forbidden);
-        }
-
-        if order.class == OrderClass::Market && order.partially_fillable {
-            return Err(PartialValidationError::UnsupportedOrderType);
-        }
-
-        if order.buy_token_balance != BuyTokenDestination::Erc20 {
-            return Err(PartialValidationError::UnsupportedBuyTokenDestination(
-                order.buy_token_balance,
-            ));
-        }
-        if !matches!(
-            order.sell_token_balance,
-            SellTokenSource::Erc20 | SellTokenSource::External
-        ) {
-            return Err(PartialValidationError::UnsupportedSellTokenSource(
-                order.sell_token_balance,
-            ));
-        }
-
-        self.validity_configuration.validate_period(&order)?;
-
-        if has_same_buy_and_sell_token(&order, &self.native_token) {
-            return Err(PartialValidationError::SameBuyAndSellToken);
-        }
-        if order.sell_token == BUY_ETH_ADDRESS {
-            return Err(PartialValidationError::InvalidNativeSellToken);
-        }
-
-        for &token in &[order.sell_token, order.buy_token] {
-            if let TokenQuality::Bad { reason } = self
-                .bad_token_detector
-                .detect(token)
-                .await
-                .map_err(PartialValidationError::Other)?
-            {
-                return Err(PartialValidationError::UnsupportedToken { token, reason });
-            }
-        }
-
-        Ok(())
-    }
-
-    fn validate_app_data(
+    /// Checks that the owner can transfer the sell amount of the order.
+    async fn check_transferable(
         &self,
-        app_data: &OrderCreationAppData,
-        full_app_data_override: &Option<String>,
-    ) -> Result<OrderAppData, AppDataValidationError> {
-        let validate = |app_data: &str| -> Result<_, AppDataValidationError> {
-            let app_data = self
-                .app_data_validator
-                .validate(app_data.as_bytes())
-                .map_err(AppDataValidationError::Invalid)?;
-            Ok(app_data)
-        };
+        owner: H160,
+        data: &OrderData,
+        signing_scheme: SigningScheme,
+        pre_interactions: &[InteractionData],
+    ) -> Result<(), ValidationError> {
+        let min_balance = minimum_balance(data).ok_or(ValidationError::SellAmountOverflow)?;
 
-        let app_data = match app_data {
-            OrderCreationAppData::Both { full, expected } => {
-                let validated = validate(full)?;
-                if validated.hash != *expected {
-                    return Err(AppDataValidationError::Mismatch {
-                        provided: *expected,
-                        actual: validated.hash,
-                    });
-                }
-                validated
+        // Fast path to check if transfer is possible with a single node query.
+        // If not, run extra queries for additional information.
+        match self
+            .balance_fetcher
+            .can_transfer(
+                &account_balances::Query {
+                    token: data.sell_token,
+                    owner,
+                    source: data.sell_token_balance,
+                    interactions: pre_interactions.to_vec(),
+                },
+                min_balance,
+            )
+            .await
+        {
+            Ok(_) => (),
+            Err(
+                TransferSimulationError::InsufficientAllowance
+                | TransferSimulationError::InsufficientBalance
+                | TransferSimulationError::TransferFailed,
+            ) if signing_scheme == SigningScheme::PreSign => {
+                // We have an exception for pre-sign orders where they do not
+                // require sufficient balance or allowance. The idea, is that
+                // this allows smart contracts to place orders bundled with
+                // other transactions that either produce the required balance
+                // or set the allowance. This would, for example, allow a Gnosis
+                // Safe to bundle the pre-signature transaction with a WETH wrap
+                // and WETH approval to the vault relayer contract.
             }
-            OrderCreationAppData::Hash { hash } => {
-                // Eventually we're not going to accept orders that set only a
-                // hash and where we can't find full app data elsewhere.
-                let protocol = if let Some(full) = full_app_data_override {
-                    validate(full)?.protocol
-                } else {
-                    return Err(AppDataValidationError::Invalid(anyhow!(
-                        "Unknown pre-image for app data hash {:?}",
-                        hash,
-                    )));
-                };
-
-                ValidatedAppData {
-                    hash: *hash,
-                    document: String::new(),
-                    protocol,
+            Err(err) => match err {
+                TransferSimulationError::InsufficientAllowance => {
+                    return Err(ValidationError::InsufficientAllowance);
                 }
-            }
-            OrderCreationAppData::Full { full } => validate(full)?,
-        };
-
-        let interactions = self.custom_interactions(&app_data.protocol.hooks);
-
-        Ok(OrderAppData {
-            inner: app_data,
-            interactions,
-        })
+                TransferSimulationError::InsufficientBalance => {
+                    return Err(ValidationError::InsufficientBalance);
+                }
+                TransferSimulationError::TransferFailed => {
+                    return Err(ValidationError::TransferSimulationFailed);
+                }
+                TransferSimulationError::Other(err) => {
+                    tracing::warn!("TransferSimulation failed: {:?}", err);
+                    return Err(ValidationError::TransferSimulationFailed);
+                }
+            },
+        }
+        Ok(())
     }
 
-    async fn validate_and_construct_order(
+    /// Full order validation. Liquidity orders don't get their sell amount
+    /// transfer checked if `skip_liquidity_balance_check` is set.
+    async fn validate_and_construct(
         &self,
         order: OrderCreation,
         domain_separator: &DomainSeparator,
         settlement_contract: H160,
         full_app_data_override: Option<String>,
+        skip_liquidity_balance_check: bool,
     ) -> Result<(Order, Option<Quote>), ValidationError> {
         // Happens before signature verification because a miscalculated app data hash
         // by the API user would lead to being unable to validate the signature below.
@@ -585,68 +531,30 @@ impl OrderValidating for OrderValidator {
         let quote = match class {
             OrderClass::Market => {
                 let fee = Some(data.fee_amount);
-                let quote =
-                    get_quote_and_check_fee(&*self.quoter, &quote_parameters, order.quote_id, fee)
-                        .await?;
+                let quote = get_quote_and_check_fee(
+                    &*self.quoter,
+                    &quote_parameters,
+                    order.quote_id,
+                    order.allow_requote,
+                    fee,
+                )
+                .await?;
                 Some(quote)
             }
             OrderClass::Limit => {
-                let quote =
-                    get_quote_and_check_fee(&*self.quoter, &quote_parameters, order.quote_id, None)
-                        .await?;
+                let quote = get_quote_and_check_fee(
+                    &*self.quoter,
+                    &quote_parameters,
+                    order.quote_id,
+                    order.allow_requote,
+                    None,
+                )
+                .await?;
                 Some(quote)
             }
             OrderClass::Liquidity => None,
         };
 
-        let min_balance = minimum_balance(&data).ok_or(ValidationError::SellAmountOverflow)?;
-
-        // Fast path to check if transfer is possible with a single node query.
-        // If not, run extra queries for additional information.
-        match self
-            .balance_fetcher
-            .can_transfer(
-                &account_balances::Query {
-                    token: data.sell_token,
-                    owner,
-                    source: data.sell_token_balance,
-                    interactions: app_data.interactions.pre.clone(),
-                },
-                min_balance,
-            )
-            .await
-        {
-            Ok(_) => (),
-            Err(
-                TransferSimulationError::InsufficientAllowance
-                | TransferSimulationError::InsufficientBalance
-                | TransferSimulationError::TransferFailed,
-            ) if signing_scheme == SigningScheme::PreSign => {
-                // We have an exception for pre-sign orders where they do not
-                // require sufficient balance or allowance. The idea, is that
-                // this allows smart contracts to place orders bundled with
-                // other transactions that either produce the required balance
-                // or set the allowance. This would, for example, allow a Gnosis
-                // Safe to bundle the pre-signature transaction with a WETH wrap
-                // and WETH approval to the vault relayer contract.
-            }
-            Err(err) => match err {
-                TransferSimulationError::InsufficientAllowance => {
-                    return Err(ValidationError::InsufficientAllowance);
-                }
-                TransferSimulationError::InsufficientBalance => {
-                    return Err(ValidationError::InsufficientBalance);
-                }
-                TransferSimulationError::TransferFailed => {
-                    return Err(ValidationError::TransferSimulationFailed);
-                }
-                TransferSimulationError::Other(err) => {
-                    tracing::warn!("TransferSimulation failed: {:?}", err);
-                    return Err(ValidationError::TransferSimulationFailed);
-                }
-            },
-        }
-
         tracing::debug!(
             ?uid,
             ?order,
@@ -671,35 +579,217 @@ impl OrderValidating for OrderValidator {
                     },
                 ) =>
             {
-                tracing::debug!(%uid, ?owner, ?class, "order being flagged as outside market price");
-                OrderClass::Liquidity
+                tracing::debug!(%uid, ?owner, ?class, "order being flagged as outside market price");
+                OrderClass::Liquidity
+            }
+            (_, _) => class,
+        };
+
+        let balance_check_skipped = skip_liquidity_balance_check && class == OrderClass::Liquidity;
+        if balance_check_skipped {
+            tracing::debug!(%uid, ?owner, "skipping balance check of liquidity order");
+        } else {
+            self.check_transferable(owner, &data, signing_scheme, &app_data.interactions.pre)
+                .await?;
+        }
+
+        self.check_max_limit_orders(owner, &class).await?;
+
+        let order = Order {
+            metadata: OrderMetadata {
+                owner,
+                creation_date: chrono::offset::Utc::now(),
+                uid,
+                settlement_contract,
+                full_fee_amount: data.fee_amount,
+                class,
+                full_app_data: match order.app_data {
+                    OrderCreationAppData::Both { full, .. }
+                    | OrderCreationAppData::Full { full } => Some(full),
+                    OrderCreationAppData::Hash { .. } => full_app_data_override,
+                },
+                quote_verified: quote.as_ref().is_some_and(|quote| quote.data.verified),
+                balance_check_skipped,
+                ..Default::default()
+            },
+            signature: order.signature.clone(),
+            data,
+            interactions: app_data.interactions,
+        };
+
+        Ok((order, quote))
+    }
+
+    fn custom_interactions(&self, hooks: &Hooks) -> Interactions {
+        let to_interactions = |hooks: &[Hook]| -> Vec<InteractionData> {
+            if hooks.is_empty() {
+                vec![]
+            } else {
+                vec![InteractionData {
+                    target: self.hooks.address(),
+                    value: U256::zero(),
+                    call_data: self
+                        .hooks
+                        .execute(
+                            hooks
+                                .iter()
+                                .map(|hook| {
+                                    (
+                                        hook.target,
+                                        Bytes(hook.call_data.clone()),
+                                        hook.gas_limit.into(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                        .tx
+                        .data
+                        .unwrap()
+                        .0,
+                }]
+            }
+        };
+
+        Interactions {
+            pre: to_interactions(&hooks.pre),
+            post: to_interactions(&hooks.post),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OrderValidating for OrderValidator {
+    async fn partial_validate(&self, order: PreOrderData) -> Result<(), PartialValidationError> {
+        if self.banned_users.contains(&order.owner) || self.banned_users.contains(&order.receiver) {
+            return Err(PartialValidationError::Forbidden);
+        }
+
+        if order.class == OrderClass::Market && order.partially_fillable {
+            return Err(PartialValidationError::UnsupportedOrderType);
+        }
+
+        if order.buy_token_balance != BuyTokenDestination::Erc20 {
+            return Err(PartialValidationError::UnsupportedBuyTokenDestination(
+                order.buy_token_balance,
+            ));
+        }
+        if !matches!(
+            order.sell_token_balance,
+            SellTokenSource::Erc20 | SellTokenSource::External
+        ) {
+            return Err(PartialValidationError::UnsupportedSellTokenSource(
+                order.sell_token_balance,
+            ));
+        }
+
+        self.validity_configuration.validate_period(&order)?;
+
+        if has_same_buy_and_sell_token(&order, &self.native_token) {
+            return Err(PartialValidationError::SameBuyAndSellToken);
+        }
+        if order.sell_token == BUY_ETH_ADDRESS {
+            return Err(PartialValidationError::InvalidNativeSellToken);
+        }
+
+        for &token in &[order.sell_token, order.buy_token] {
+            if let TokenQuality::Bad { reason } = self
+                .bad_token_detector
+                .detect(token)
+                .await
+                .map_err(PartialValidationError::Other)?
+            {
+                return Err(PartialValidationError::UnsupportedToken { token, reason });
             }
-            (_, _) => class,
+        }
+
+        Ok(())
+    }
+
+    fn validate_app_data(
+        &self,
+        app_data: &OrderCreationAppData,
+        full_app_data_override: &Option<String>,
+    ) -> Result<OrderAppData, AppDataValidationError> {
+        let validate = |app_data: &str| -> Result<_, AppDataValidationError> {
+            let app_data = self
+                .app_data_validator
+                .validate(app_data.as_bytes())
+                .map_err(AppDataValidationError::Invalid)?;
+            Ok(app_data)
         };
 
-        self.check_max_limit_orders(owner, &class).await?;
+        let app_data = match app_data {
+            OrderCreationAppData::Both { full, expected } => {
+                let validated = validate(full)?;
+                if validated.hash != *expected {
+                    return Err(AppDataValidationError::Mismatch {
+                        provided: *expected,
+                        actual: validated.hash,
+                    });
+                }
+                validated
+            }
+            OrderCreationAppData::Hash { hash } => {
+                // Eventually we're not going to accept orders that set only a
+                // hash and where we can't find full app data elsewhere.
+                let protocol = if let Some(full) = full_app_data_override {
+                    validate(full)?.protocol
+                } else {
+                    return Err(AppDataValidationError::Invalid(anyhow!(
+                        "Unknown pre-image for app data hash {:?}",
+                        hash,
+                    )));
+                };
 
-        let order = Order {
-            metadata: OrderMetadata {
-                owner,
-                creation_date: chrono::offset::Utc::now(),
-                uid,
-                settlement_contract,
-                full_fee_amount: data.fee_amount,
-                class,
-                full_app_data: match order.app_data {
-                    OrderCreationAppData::Both { full, .. }
-                    | OrderCreationAppData::Full { full } => Some(full),
-                    OrderCreationAppData::Hash { .. } => full_app_data_override,
-                },
-                ..Default::default()
-            },
-            signature: order.signature.clone(),
-            data,
-            interactions: app_data.interactions,
+                ValidatedAppData {
+                    hash: *hash,
+                    document: String::new(),
+                    protocol,
+                }
+            }
+            OrderCreationAppData::Full { full } => validate(full)?,
         };
 
-        Ok((order, quote))
+        let interactions = self.custom_interactions(&app_data.protocol.hooks);
+
+        Ok(OrderAppData {
+            inner: app_data,
+            interactions,
+        })
+    }
+
+    async fn validate_and_construct_order(
+        &self,
+        order: OrderCreation,
+        domain_separator: &DomainSeparator,
+        settlement_contract: H160,
+        full_app_data_override: Option<String>,
+    ) -> Result<(Order, Option<Quote>), ValidationError> {
+        self.validate_and_construct(
+            order,
+            domain_separator,
+            settlement_contract,
+            full_app_data_override,
+            false,
+        )
+        .await
+    }
+
+    async fn validate_and_construct_order_without_balance_check(
+        &self,
+        order: OrderCreation,
+        domain_separator: &DomainSeparator,
+        settlement_contract: H160,
+        full_app_data_override: Option<String>,
+    ) -> Result<(Order, Option<Quote>), ValidationError> {
+        self.validate_and_construct(
+            order,
+            domain_separator,
+            settlement_contract,
+            full_app_data_override,
+            true,
+        )
+        .await
     }
 }
 
@@ -786,9 +876,11 @@ pub async fn get_quote_and_check_fee(
     quoter: &dyn OrderQuoting,
     quote_search_parameters: &QuoteSearchParameters,
     quote_id: Option<i64>,
+    allow_requote: bool,
     fee_amount: Option<U256>,
 ) -> Result<Quote, ValidationError> {
-    let quote = get_or_create_quote(quoter, quote_search_parameters, quote_id).await?;
+    let quote =
+        get_or_create_quote(quoter, quote_search_parameters, quote_id, allow_requote).await?;
 
     if fee_amount.is_some_and(|fee| fee < quote.fee_amount) {
         return Err(ValidationError::InsufficientFee);
@@ -801,59 +893,91 @@ pub async fn get_quote_and_check_fee(
 ///
 /// This works by first trying to find an existing quote, and then falling back
 /// to calculating a brand new one if none can be found and a quote ID was not
-/// specified.
+/// specified. If the quote specified by ID expired or doesn't match the order a
+/// brand new quote is only calculated if `allow_requote` is set.
 async fn get_or_create_quote(
     quoter: &dyn OrderQuoting,
     quote_search_parameters: &QuoteSearchParameters,
     quote_id: Option<i64>,
+    allow_requote: bool,
 ) -> Result<Quote, ValidationError> {
-    let quote = match quoter
-        .find_quote(quote_id, quote_search_parameters.clone())
-        .await
-    {
-        Ok(quote) => {
+    let quote = match (
+        quoter
+            .find_quote(quote_id, quote_search_parameters.clone())
+            .await,
+        quote_id,
+    ) {
+        (Ok(quote), _) => {
             tracing::debug!(quote_id =? quote.id, "found quote for order creation");
             quote
         }
         // We couldn't find a quote, and no ID was specified. Try computing a
         // fresh quote to use instead.
-        Err(FindQuoteError::NotFound(_)) if quote_id.is_none() => {
-            let parameters = QuoteParameters {
-                sell_token: quote_search_parameters.sell_token,
-                buy_token: quote_search_parameters.buy_token,
-                side: match quote_search_parameters.kind {
-                    OrderKind::Buy => OrderQuoteSide::Buy {
-                        buy_amount_after_fee: quote_search_parameters
-                            .buy_amount
-                            .try_into()
-                            .map_err(|_| ValidationError::ZeroAmount)?,
-                    },
-                    OrderKind::Sell => OrderQuoteSide::Sell {
-                        sell_amount: SellAmount::AfterFee {
-                            value: quote_search_parameters
-                                .sell_amount
-                                .try_into()
-                                .map_err(|_| ValidationError::ZeroAmount)?,
-                        },
-                    },
-                },
-                verification: quote_search_parameters.verification.clone(),
-                signing_scheme: quote_search_parameters.signing_scheme,
-                additional_gas: quote_search_parameters.additional_gas,
-            };
+        (Err(FindQuoteError::NotFound(_)), None) => {
+            calculate_fresh_quote(quoter, quote_search_parameters).await?
+        }
+        // The quote specified by ID exists but can't be used for this order.
+        (
+            Err(
+                FindQuoteError::Expired(expired_at)
+                | FindQuoteError::ParameterMismatch(QuoteData {
+                    expiration: expired_at,
+                    ..
+                }),
+            ),
+            Some(quote_id),
+        ) => {
+            if !allow_requote {
+                return Err(ValidationError::QuoteExpired {
+                    quote_id,
+                    expired_at,
+                });
+            }
+            tracing::debug!(%quote_id, "computing fresh quote instead of unusable quote");
+            calculate_fresh_quote(quoter, quote_search_parameters).await?
+        }
+        (Err(err), _) => return Err(err.into()),
+    };
 
-            let quote = quoter.calculate_quote(parameters).await?;
-            let quote = quoter
-                .store_quote(quote)
-                .await
-                .map_err(ValidationError::Other)?;
+    Ok(quote)
+}
 
-            tracing::debug!(quote_id =? quote.id, "computed fresh quote for order creation");
-            quote
-        }
-        Err(err) => return Err(err.into()),
+/// Calculates and stores a brand new quote for an order that is being created.
+async fn calculate_fresh_quote(
+    quoter: &dyn OrderQuoting,
+    quote_search_parameters: &QuoteSearchParameters,
+) -> Result<Quote, ValidationError> {
+    let parameters = QuoteParameters {
+        sell_token: quote_search_parameters.sell_token,
+        buy_token: quote_search_parameters.buy_token,
+        side: match quote_search_parameters.kind {
+            OrderKind::Buy => OrderQuoteSide::Buy {
+                buy_amount_after_fee: quote_search_parameters
+                    .buy_amount
+                    .try_into()
+                    .map_err(|_| ValidationError::ZeroAmount)?,
+            },
+            OrderKind::Sell => OrderQuoteSide::Sell {
+                sell_amount: SellAmount::AfterFee {
+                    value: quote_search_parameters
+                        .sell_amount
+                        .try_into()
+                        .map_err(|_| ValidationError::ZeroAmount)?,
+                },
+            },
+        },
+        verification: quote_search_parameters.verification.clone(),
+        signing_scheme: quote_search_parameters.signing_scheme,
+        additional_gas: quote_search_parameters.additional_gas,
     };
 
+    let quote = quoter.calculate_quote(parameters).await?;
+    let quote = quoter
+        .store_quote(quote)
+        .await
+        .map_err(ValidationError::Other)?;
+
+    tracing::debug!(quote_id =? quote.id, "computed fresh quote for order creation");
     Ok(quote)
 }
 
@@ -906,7 +1030,6 @@ mod tests {
             signature_validator::MockSignatureValidating,
         },
         anyhow::anyhow,
-        chrono::Utc,
         contracts::dummy_contract,
         ethcontract::web3::signing::SecretKeyRef,
         futures::FutureExt,
@@ -1571,6 +1694,99 @@ mod tests {
         assert!(quote.is_some());
     }
 
+    #[tokio::test]
+    async fn post_validate_skips_balance_check_of_liquidity_orders() {
+        let expected_buy_amount = U256::from(100);
+
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter.expect_find_quote().returning(move |_, _| {
+            Ok(Quote {
+                buy_amount: expected_buy_amount,
+                sell_amount: U256::from(1),
+                fee_amount: U256::from(1),
+                ..Default::default()
+            })
+        });
+        bad_token_detector
+            .expect_detect()
+            .returning(|_| Ok(TokenQuality::Good));
+        balance_fetcher
+            .expect_can_transfer()
+            .returning(|_, _| Err(TransferSimulationError::InsufficientBalance));
+        let mut limit_order_counter = MockLimitOrderCounting::new();
+        limit_order_counter.expect_count().returning(|_| Ok(0u64));
+        let validator = OrderValidator::new(
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            OrderValidPeriodConfiguration::any(),
+            false,
+            Arc::new(bad_token_detector),
+            dummy_contract!(HooksTrampoline, [0xcf; 20]),
+            Arc::new(order_quoter),
+            Arc::new(balance_fetcher),
+            Arc::new(MockSignatureValidating::new()),
+            Arc::new(limit_order_counter),
+            0,
+            Arc::new(MockCodeFetching::new()),
+            Default::default(),
+        );
+        let order = |buy_amount| OrderCreation {
+            valid_to: time::now_in_epoch_seconds() + 2,
+            sell_token: H160::from_low_u64_be(1),
+            buy_token: H160::from_low_u64_be(2),
+            buy_amount,
+            sell_amount: U256::from(1),
+            fee_amount: U256::from(1),
+            kind: OrderKind::Sell,
+            signature: Signature::Eip712(EcdsaSignature::non_zero()),
+            app_data: OrderCreationAppData::Full {
+                full: "{}".to_string(),
+            },
+            ..Default::default()
+        };
+
+        // Out-of-price orders are liquidity orders.
+        let (liquidity_order, _) = validator
+            .validate_and_construct_order_without_balance_check(
+                order(expected_buy_amount + 1),
+                &Default::default(),
+                Default::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(liquidity_order.metadata.class, OrderClass::Liquidity);
+        assert!(liquidity_order.metadata.balance_check_skipped);
+
+        // The regular validation still checks the balance of liquidity orders.
+        assert!(matches!(
+            validator
+                .validate_and_construct_order(
+                    order(expected_buy_amount + 1),
+                    &Default::default(),
+                    Default::default(),
+                    None,
+                )
+                .await,
+            Err(ValidationError::InsufficientBalance)
+        ));
+
+        // Market orders always get their balance checked.
+        assert!(matches!(
+            validator
+                .validate_and_construct_order_without_balance_check(
+                    order(expected_buy_amount),
+                    &Default::default(),
+                    Default::default(),
+                    None,
+                )
+                .await,
+            Err(ValidationError::InsufficientBalance)
+        ));
+    }
+
     #[tokio::test]
     async fn post_validate_err_wrong_owner() {
         let mut order_quoter = MockOrderQuoting::new();
@@ -2020,6 +2236,7 @@ mod tests {
             &order_quoter,
             &quote_search_parameters,
             quote_id,
+            false,
             Some(fee_amount),
         )
         .await
@@ -2091,6 +2308,7 @@ mod tests {
             &order_quoter,
             &quote_search_parameters,
             None,
+            false,
             Some(fee_amount),
         )
         .await
@@ -2121,6 +2339,7 @@ mod tests {
             &order_quoter,
             &quote_search_parameters,
             Some(0),
+            false,
             Some(U256::zero()),
         )
         .await
@@ -2129,6 +2348,75 @@ mod tests {
         assert!(matches!(err, ValidationError::QuoteNotFound));
     }
 
+    #[tokio::test]
+    async fn get_quote_errors_on_unusable_quote_id() {
+        let expiration = Utc::now();
+        for find_err in [
+            FindQuoteError::Expired(expiration),
+            FindQuoteError::ParameterMismatch(QuoteData {
+                expiration,
+                ..Default::default()
+            }),
+        ] {
+            let mut order_quoter = MockOrderQuoting::new();
+            order_quoter
+                .expect_find_quote()
+                .return_once(|_, _| Err(find_err));
+
+            let err = get_quote_and_check_fee(
+                &order_quoter,
+                &Default::default(),
+                Some(42),
+                false,
+                Some(U256::zero()),
+            )
+            .await
+            .unwrap_err();
+
+            assert!(matches!(
+                err,
+                ValidationError::QuoteExpired {
+                    quote_id: 42,
+                    expired_at,
+                } if expired_at == expiration
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_quote_calculates_fresh_quote_for_unusable_quote_id_if_allowed() {
+        let mut order_quoter = MockOrderQuoting::new();
+        order_quoter
+            .expect_find_quote()
+            .with(eq(Some(42)), always())
+            .returning(|_, _| Err(FindQuoteError::Expired(Utc::now())));
+        order_quoter
+            .expect_calculate_quote()
+            .returning(|_| Ok(Default::default()));
+        order_quoter.expect_store_quote().returning(|quote| {
+            Ok(Quote {
+                id: Some(43),
+                ..quote
+            })
+        });
+
+        let quote = get_quote_and_check_fee(
+            &order_quoter,
+            &QuoteSearchParameters {
+                sell_amount: 1.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            Some(42),
+            true,
+            Some(U256::zero()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(quote.id, Some(43));
+    }
+
     #[tokio::test]
     async fn get_quote_errors_on_insufficient_fees() {
         let mut order_quoter = MockOrderQuoting::new();
@@ -2143,6 +2431,7 @@ mod tests {
             &order_quoter,
             &Default::default(),
             Default::default(),
+            false,
             Some(U256::one()),
         )
         .await
@@ -2167,6 +2456,7 @@ mod tests {
                         ..Default::default()
                     },
                     Default::default(),
+                    false,
                     Default::default(),
                 )
                 .await
@@ -2203,6 +2493,7 @@ mod tests {
                         ..Default::default()
                     },
                     Default::default(),
+                    false,
                     Some(U256::zero()),
                 )
                 .await
@@ -1,6 +1,6 @@
 use {
     crate::{
-        arguments::{display_option, display_secret_option, CodeSimulatorKind},
+        arguments::{display_list, display_option, display_secret_option, CodeSimulatorKind},
         conversions::U256Ext,
         trade_finding::Interaction,
     },
@@ -19,6 +19,7 @@ use {
         fmt::{self, Display, Formatter},
         future::Future,
         hash::Hash,
+        num::NonZeroUsize,
         str::FromStr,
         sync::Arc,
         time::{Duration, Instant},
@@ -267,6 +268,37 @@ pub struct Arguments {
     #[clap(long, env, default_value = "1")]
     pub native_price_cache_concurrent_requests: usize,
 
+    /// After how many consecutive "unsupported token" results a cached native
+    /// price gets frozen. Frozen prices no longer get updated by the
+    /// maintenance task. If not set prices never get frozen.
+    #[clap(long, env)]
+    pub native_price_cache_permanent_failure_threshold: Option<NonZeroUsize>,
+
+    /// Cached native prices which are still being requested but haven't been
+    /// updated for longer than this get logged as a warning.
+    #[clap(long, env, value_parser = humantime::parse_duration)]
+    pub native_price_cache_max_entry_age_warning: Option<Duration>,
+
+    /// Tokens for which native price cache hits and misses get reported
+    /// individually.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub native_price_cache_watched_tokens: Vec<H160>,
+
+    /// If set the native price cache maintenance task fetches prices with
+    /// batch requests of at most that many tokens.
+    #[clap(long, env)]
+    pub native_price_cache_batch_size: Option<usize>,
+
+    /// How long requests for a native price which is already being fetched
+    /// wait for that request before issuing their own.
+    #[clap(
+        long,
+        env,
+        default_value = "10s",
+        value_parser = humantime::parse_duration,
+    )]
+    pub native_price_cache_in_flight_timeout: Duration,
+
     /// The amount in native tokens atoms to use for price estimation. Should be
     /// reasonably large so that small pools do not influence the prices. If
     /// not set a reasonable default is used based on network id.
@@ -320,6 +352,11 @@ impl Display for Arguments {
             native_price_prefetch_time,
             native_price_cache_max_update_size,
             native_price_cache_concurrent_requests,
+            native_price_cache_permanent_failure_threshold,
+            native_price_cache_max_entry_age_warning,
+            native_price_cache_watched_tokens,
+            native_price_cache_batch_size,
+            native_price_cache_in_flight_timeout,
             amount_to_estimate_prices_with,
             balancer_sor_url,
             tenderly_save_successful_trade_simulations,
@@ -360,6 +397,33 @@ impl Display for Arguments {
             "native_price_cache_concurrent_requests: {}",
             native_price_cache_concurrent_requests
         )?;
+        display_option(
+            f,
+            "native_price_cache_permanent_failure_threshold",
+            native_price_cache_permanent_failure_threshold,
+        )?;
+        display_option(
+            f,
+            "native_price_cache_max_entry_age_warning",
+            &native_price_cache_max_entry_age_warning.map(|age| format!("{age:?}")),
+        )?;
+        display_list(
+            f,
+            "native_price_cache_watched_tokens",
+            native_price_cache_watched_tokens
+                .iter()
+                .map(|token| format!("{token:?}")),
+        )?;
+        display_option(
+            f,
+            "native_price_cache_batch_size",
+            native_price_cache_batch_size,
+        )?;
+        writeln!(
+            f,
+            "native_price_cache_in_flight_timeout: {:?}",
+            native_price_cache_in_flight_timeout
+        )?;
         display_option(
             f,
             "amount_to_estimate_prices_with",
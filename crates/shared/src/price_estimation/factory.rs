@@ -391,12 +391,24 @@ impl<'a> PriceEstimatorFactory<'a> {
                 .with_early_return(results_required);
         let native_estimator = Arc::new(CachingNativePriceEstimator::new(
             Box::new(competition_estimator),
+            Default::default(),
             self.args.native_price_cache_max_age,
             self.args.native_price_cache_refresh,
             Some(self.args.native_price_cache_max_update_size),
             self.args.native_price_prefetch_time,
             self.args.native_price_cache_concurrent_requests,
+            self.args.native_price_cache_permanent_failure_threshold,
+            self.args.native_price_cache_max_entry_age_warning,
+            self.args.native_price_cache_batch_size,
+            self.args.native_price_cache_in_flight_timeout,
         ));
+        native_estimator.set_watched_tokens(
+            self.args
+                .native_price_cache_watched_tokens
+                .iter()
+                .copied()
+                .collect(),
+        );
         Ok(native_estimator)
     }
 }
@@ -1,14 +1,15 @@
 use {
     crate::price_estimation::{PriceEstimating, PriceEstimationError, Query},
-    futures::FutureExt,
+    futures::{FutureExt, StreamExt},
     model::order::OrderKind,
     number::nonzero::U256 as NonZeroU256,
     primitive_types::{H160, U256},
     std::sync::Arc,
 };
 
+mod denominated;
 mod oneinch;
-pub use self::oneinch::OneInch;
+pub use self::{denominated::DenominatedPriceEstimator, oneinch::OneInch};
 
 pub type NativePriceEstimateResult = Result<f64, PriceEstimationError>;
 
@@ -32,6 +33,27 @@ pub trait NativePriceEstimating: Send + Sync {
         &self,
         token: H160,
     ) -> futures::future::BoxFuture<'_, NativePriceEstimateResult>;
+
+    /// Estimates the native prices of multiple tokens. The results are in the
+    /// same order as `tokens`.
+    ///
+    /// By default every token gets estimated individually with at most
+    /// `parallelism` estimates in flight. Estimators backed by APIs supporting
+    /// multi-token requests should override this.
+    fn estimate_native_prices_batch(
+        &self,
+        tokens: &[H160],
+        parallelism: usize,
+    ) -> futures::future::BoxFuture<'_, Vec<NativePriceEstimateResult>> {
+        let estimates = tokens
+            .iter()
+            .map(|token| self.estimate_native_price(*token))
+            .collect::<Vec<_>>();
+        futures::stream::iter(estimates)
+            .buffered(parallelism.max(1))
+            .collect()
+            .boxed()
+    }
 }
 
 /// Wrapper around price estimators specialized to estimate a token's price
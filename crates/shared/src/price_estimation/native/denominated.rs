@@ -0,0 +1,170 @@
+use {
+    super::{NativePriceEstimateResult, NativePriceEstimating},
+    crate::price_estimation::PriceEstimationError,
+    anyhow::anyhow,
+    futures::{future::BoxFuture, FutureExt},
+    primitive_types::H160,
+    std::sync::Arc,
+};
+
+/// Wrapper around a native price estimator which expresses prices in terms of
+/// an arbitrary numeraire token (e.g. USDC) instead of the chain's native
+/// token.
+///
+/// Every estimate also requires the numeraire's native price. To avoid
+/// fetching it over and over again `inner` should be a
+/// `CachingNativePriceEstimator`. The denominated estimator itself can be
+/// wrapped in a `CachingNativePriceEstimator` like any other estimator.
+pub struct DenominatedPriceEstimator {
+    inner: Arc<dyn NativePriceEstimating>,
+    numeraire: H160,
+}
+
+impl DenominatedPriceEstimator {
+    pub fn new(inner: Arc<dyn NativePriceEstimating>, numeraire: H160) -> Self {
+        Self { inner, numeraire }
+    }
+}
+
+impl NativePriceEstimating for DenominatedPriceEstimator {
+    fn estimate_native_price(&self, token: H160) -> BoxFuture<'_, NativePriceEstimateResult> {
+        async move {
+            if token == self.numeraire {
+                return Ok(1.);
+            }
+
+            let (price, numeraire_price) = futures::join!(
+                self.inner.estimate_native_price(token),
+                self.inner.estimate_native_price(self.numeraire),
+            );
+            let numeraire_price = numeraire_price.map_err(|err| {
+                PriceEstimationError::EstimatorInternal(anyhow!(
+                    "failed to estimate price of numeraire {:?}: {err}",
+                    self.numeraire
+                ))
+            })?;
+            let price = price?;
+
+            if numeraire_price == 0. {
+                return Err(PriceEstimationError::EstimatorInternal(anyhow!(
+                    "numeraire {:?} has a price of 0",
+                    self.numeraire
+                )));
+            }
+            let denominated = price / numeraire_price;
+            if !denominated.is_finite() {
+                return Err(PriceEstimationError::EstimatorInternal(anyhow!(
+                    "price {price} denominated in numeraire price {numeraire_price} is not finite"
+                )));
+            }
+            Ok(denominated)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::price_estimation::{
+            native::MockNativePriceEstimating,
+            native_price_cache::CachingNativePriceEstimator,
+        },
+        std::time::Duration,
+    };
+
+    const TOKEN: H160 = H160([1; 20]);
+    const NUMERAIRE: H160 = H160([2; 20]);
+
+    fn estimator(
+        token_price: NativePriceEstimateResult,
+        numeraire_price: NativePriceEstimateResult,
+    ) -> DenominatedPriceEstimator {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .withf(|token| *token == TOKEN)
+            .returning(move |_| futures::future::ready(token_price.clone()).boxed());
+        inner
+            .expect_estimate_native_price()
+            .withf(|token| *token == NUMERAIRE)
+            .returning(move |_| futures::future::ready(numeraire_price.clone()).boxed());
+        DenominatedPriceEstimator::new(Arc::new(inner), NUMERAIRE)
+    }
+
+    #[tokio::test]
+    async fn divides_by_numeraire_price() {
+        let estimator = estimator(Ok(2.), Ok(0.5));
+        let result = estimator.estimate_native_price(TOKEN).await;
+        assert_eq!(result.unwrap(), 4.);
+    }
+
+    #[tokio::test]
+    async fn numeraire_has_price_of_one() {
+        let estimator =
+            DenominatedPriceEstimator::new(Arc::new(MockNativePriceEstimating::new()), NUMERAIRE);
+        let result = estimator.estimate_native_price(NUMERAIRE).await;
+        assert_eq!(result.unwrap(), 1.);
+    }
+
+    #[tokio::test]
+    async fn token_errors_get_propagated() {
+        let estimator = estimator(Err(PriceEstimationError::NoLiquidity), Ok(0.5));
+        let result = estimator.estimate_native_price(TOKEN).await;
+        assert!(matches!(result, Err(PriceEstimationError::NoLiquidity)));
+    }
+
+    #[tokio::test]
+    async fn missing_numeraire_price_is_internal_error() {
+        let estimator = estimator(Ok(2.), Err(PriceEstimationError::NoLiquidity));
+        let result = estimator.estimate_native_price(TOKEN).await;
+        assert!(matches!(
+            result,
+            Err(PriceEstimationError::EstimatorInternal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn zero_numeraire_price_is_internal_error() {
+        let estimator = estimator(Ok(2.), Ok(0.));
+        let result = estimator.estimate_native_price(TOKEN).await;
+        assert!(matches!(
+            result,
+            Err(PriceEstimationError::EstimatorInternal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn composes_with_caching_estimator() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .times(1)
+            .withf(|token| *token == TOKEN)
+            .returning(|_| async { Ok(2.) }.boxed());
+        inner
+            .expect_estimate_native_price()
+            .times(1)
+            .withf(|token| *token == NUMERAIRE)
+            .returning(|_| async { Ok(0.5) }.boxed());
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(DenominatedPriceEstimator::new(Arc::new(inner), NUMERAIRE)),
+            Default::default(),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        for _ in 0..10 {
+            let result = estimator.estimate_native_price(TOKEN).await;
+            assert_eq!(result.unwrap(), 4.);
+        }
+    }
+}
@@ -1,21 +1,26 @@
 use {
     super::PriceEstimationError,
     crate::price_estimation::native::{NativePriceEstimateResult, NativePriceEstimating},
+    anyhow::anyhow,
     futures::{FutureExt, StreamExt},
+    model::order::BUY_ETH_ADDRESS,
     primitive_types::H160,
-    prometheus::{IntCounter, IntCounterVec, IntGauge},
+    prometheus::{Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge},
     std::{
         collections::{hash_map::Entry, HashMap, HashSet},
+        num::NonZeroUsize,
         sync::{Arc, Mutex, MutexGuard, Weak},
         time::{Duration, Instant},
     },
+    tokio::sync::watch,
     tracing::Instrument,
 };
 
 #[derive(prometheus_metric_storage::MetricStorage)]
 struct Metrics {
     /// native price cache hits misses
-    #[metric(labels("result"))]
+    /// `token` is only set for watched tokens to keep the cardinality bounded
+    #[metric(labels("result", "token"))]
     native_price_cache_access: IntCounterVec,
     /// number of items in cache
     native_price_cache_size: IntGauge,
@@ -23,6 +28,16 @@ struct Metrics {
     native_price_cache_background_updates: IntCounter,
     /// number of items in cache that are outdated
     native_price_cache_outdated_entries: IntGauge,
+    /// time it took the inner estimator to produce a price by caller
+    #[metric(labels("caller"))]
+    native_price_cache_estimation_times: HistogramVec,
+    /// time it took the inner estimator to produce a price by result
+    #[metric(labels("result"))]
+    native_price_cache_fill_times: HistogramVec,
+    /// number of items in cache that are frozen and no longer get updated
+    native_price_cache_frozen_entries: IntGauge,
+    /// age in seconds of the oldest cache entry that is still being requested
+    native_price_cache_max_entry_age: Gauge,
 }
 
 impl Metrics {
@@ -31,20 +46,65 @@ impl Metrics {
     }
 }
 
+/// Quote token of prices denominated in the chain's native token.
+pub const NATIVE_QUOTE: H160 = BUY_ETH_ADDRESS;
+
 /// Wrapper around `Box<dyn PriceEstimating>` which caches successful price
 /// estimates for some time and supports updating the cache in the background.
 ///
+/// Besides native prices it can cache prices denominated in a small set of
+/// additional quote tokens.
+///
 /// The size of the underlying cache is unbounded.
 ///
 /// Is an Arc internally.
 #[derive(Clone)]
 pub struct CachingNativePriceEstimator(Arc<Inner>);
 
+/// Returns the set of tokens whose prices should be updated with the highest
+/// priority (e.g. the tokens of all currently open orders).
+pub type PrioritySource = Box<dyn Fn() -> HashSet<H160> + Send + Sync>;
+
 struct Inner {
-    cache: Mutex<HashMap<H160, CachedResult>>,
+    cache: Mutex<HashMap<CacheKey, CachedResult>>,
     high_priority: Mutex<HashSet<H160>>,
+    /// If set the high priority tokens get refreshed from this source at the
+    /// start of every maintenance cycle.
+    priority_source: Mutex<Option<PrioritySource>>,
+    /// Tokens for which cache hits and misses get reported individually.
+    watched_tokens: Mutex<HashSet<H160>>,
     estimator: Box<dyn NativePriceEstimating>,
+    /// Estimators for prices denominated in quote tokens other than
+    /// `NATIVE_QUOTE`.
+    quote_estimators: HashMap<H160, Box<dyn NativePriceEstimating>>,
     max_age: Duration,
+    /// Prices which are currently being fetched. Other callers requesting the
+    /// same price wait for the result instead of issuing their own request.
+    in_flight: Mutex<HashMap<CacheKey, InFlight>>,
+    /// How long other callers wait for an in-flight request before ignoring
+    /// it and issuing their own request.
+    in_flight_timeout: Duration,
+    /// After how many consecutive `UnsupportedToken` results an entry gets
+    /// frozen. `None` means entries never get frozen.
+    permanent_failure_threshold: Option<NonZeroUsize>,
+}
+
+/// Who triggered a request to the inner estimator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Caller {
+    /// A user requested a price that was not cached.
+    User,
+    /// The background task refreshed an outdated price.
+    Background,
+}
+
+impl Caller {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Background => "background",
+        }
+    }
 }
 
 struct UpdateTask {
@@ -53,32 +113,81 @@ struct UpdateTask {
     update_size: Option<usize>,
     prefetch_time: Duration,
     concurrent_requests: usize,
+    max_entry_age_warning: Option<Duration>,
+    /// If set prices get updated with batch requests of at most that many
+    /// tokens.
+    batch_size: Option<usize>,
+}
+
+/// How many of the most stale cache entries get reported after every update.
+const STALE_ENTRIES_TO_REPORT: usize = 5;
+
+/// A price that is currently being fetched.
+struct InFlight {
+    started_at: Instant,
+    result: watch::Receiver<Option<NativePriceEstimateResult>>,
+}
+
+/// Outcome of trying to join a price request that is already in flight.
+enum InFlightRole {
+    /// No request is in flight. The caller has to fetch the price and
+    /// publish the result.
+    Leader {
+        started_at: Instant,
+        sender: watch::Sender<Option<NativePriceEstimateResult>>,
+    },
+    /// Another request is in flight. The caller can wait for its result.
+    Follower {
+        started_at: Instant,
+        result: watch::Receiver<Option<NativePriceEstimateResult>>,
+    },
 }
 
 type CacheEntry = Result<f64, PriceEstimationError>;
 
+/// A token and the quote token its price is denominated in.
+type CacheKey = (H160, H160);
+
 #[derive(Debug, Clone)]
 struct CachedResult {
     result: CacheEntry,
     updated_at: Instant,
     requested_at: Instant,
+    /// How many `UnsupportedToken` results were fetched in a row.
+    unsupported_count: usize,
+    /// Frozen entries are considered permanent failures. They never expire and
+    /// don't get updated by the maintenance task until they get invalidated.
+    frozen: bool,
+}
+
+impl CachedResult {
+    /// An entry which will be updated during the next maintenance cycle.
+    fn outdated(now: Instant, max_age: &Duration) -> Self {
+        Self {
+            result: Ok(0.),
+            updated_at: now.checked_sub(*max_age).unwrap(),
+            requested_at: now,
+            unsupported_count: 0,
+            frozen: false,
+        }
+    }
 }
 
 impl Inner {
     // Returns a single cached price and updates its `requested_at` field.
     fn get_cached_price(
-        token: H160,
+        key: CacheKey,
         now: Instant,
-        cache: &mut MutexGuard<HashMap<H160, CachedResult>>,
+        cache: &mut MutexGuard<HashMap<CacheKey, CachedResult>>,
         max_age: &Duration,
         create_missing_entry: bool,
     ) -> Option<CacheEntry> {
-        match cache.entry(token) {
+        match cache.entry(key) {
             Entry::Occupied(mut entry) => {
                 let entry = entry.get_mut();
                 entry.requested_at = now;
                 let is_recent = now.saturating_duration_since(entry.updated_at) < *max_age;
-                is_recent.then_some(entry.result.clone())
+                (is_recent || entry.frozen).then_some(entry.result.clone())
             }
             Entry::Vacant(entry) => {
                 if create_missing_entry {
@@ -86,18 +195,91 @@ impl Inner {
                     // will fetch the price during the next maintenance cycle.
                     // This should happen only for prices missing while building the auction.
                     // Otherwise malicious actors could easily cause the cache size to blow up.
-                    let outdated_timestamp = now.checked_sub(*max_age).unwrap();
-                    entry.insert(CachedResult {
-                        result: Ok(0.),
-                        updated_at: outdated_timestamp,
-                        requested_at: now,
-                    });
+                    entry.insert(CachedResult::outdated(now, max_age));
                 }
                 None
             }
         }
     }
 
+    /// Reports a cache hit or miss for the given token.
+    fn record_access(&self, token: &H160, hit: bool) {
+        let label = if hit { "hits" } else { "misses" };
+        let token = match self.watched_tokens.lock().unwrap().contains(token) {
+            true => format!("{token:?}"),
+            false => Default::default(),
+        };
+        Metrics::get()
+            .native_price_cache_access
+            .with_label_values(&[label, &token])
+            .inc_by(1);
+    }
+
+    /// Returns the estimator for prices denominated in `quote`.
+    fn estimator_for(&self, quote: H160) -> Option<&dyn NativePriceEstimating> {
+        if quote == NATIVE_QUOTE {
+            return Some(self.estimator.as_ref());
+        }
+        let estimator = self.quote_estimators.get(&quote)?;
+        Some(estimator.as_ref())
+    }
+
+    /// Returns the in-flight request for the given price if there is a recent
+    /// one. Otherwise marks the price as in-flight for the caller.
+    fn join_in_flight(&self, key: CacheKey, now: Instant) -> InFlightRole {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.get(&key) {
+            Some(existing)
+                if now.saturating_duration_since(existing.started_at) < self.in_flight_timeout =>
+            {
+                InFlightRole::Follower {
+                    started_at: existing.started_at,
+                    result: existing.result.clone(),
+                }
+            }
+            _ => {
+                let (sender, result) = watch::channel(None);
+                in_flight.insert(
+                    key,
+                    InFlight {
+                        started_at: now,
+                        result,
+                    },
+                );
+                InFlightRole::Leader {
+                    started_at: now,
+                    sender,
+                }
+            }
+        }
+    }
+
+    /// Publishes the result of an in-flight request to everyone waiting for it
+    /// and removes the in-flight marker.
+    fn finish_in_flight(
+        &self,
+        key: CacheKey,
+        started_at: Instant,
+        sender: watch::Sender<Option<NativePriceEstimateResult>>,
+        result: &NativePriceEstimateResult,
+    ) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        // The marker might have been replaced after it timed out.
+        if in_flight
+            .get(&key)
+            .is_some_and(|existing| existing.started_at == started_at)
+        {
+            in_flight.remove(&key);
+        }
+        // Sending only fails if nobody is waiting for the result.
+        let _ = sender.send(Some(result.clone()));
+    }
+
+    /// All quote tokens prices can be cached for.
+    fn quotes(&self) -> impl Iterator<Item = H160> + '_ {
+        std::iter::once(NATIVE_QUOTE).chain(self.quote_estimators.keys().copied())
+    }
+
     /// Checks cache for the given tokens one by one. If the price is already
     /// cached it gets returned. If it's not in the cache a new price
     /// estimation request gets issued. We check the cache before each
@@ -105,59 +287,243 @@ impl Inner {
     /// have fetched some requested price in the meantime.
     fn estimate_prices_and_update_cache<'a>(
         &'a self,
-        tokens: &'a [H160],
+        keys: &'a [CacheKey],
         max_age: Duration,
         parallelism: usize,
+        caller: Caller,
     ) -> futures::stream::BoxStream<'_, (usize, NativePriceEstimateResult)> {
-        let estimates = tokens
-            .iter()
-            .enumerate()
-            .map(move |(index, token)| async move {
-                {
-                    // check if price is cached by now
-                    let now = Instant::now();
-                    let mut cache = self.cache.lock().unwrap();
-                    let price = Self::get_cached_price(*token, now, &mut cache, &max_age, false);
-                    if let Some(price) = price {
-                        return (index, price);
+        let estimates = keys.iter().enumerate().map(move |(index, key)| async move {
+            {
+                // check if price is cached by now
+                let now = Instant::now();
+                let mut cache = self.cache.lock().unwrap();
+                let price = Self::get_cached_price(*key, now, &mut cache, &max_age, false);
+                if let Some(price) = price {
+                    return (index, price);
+                }
+            }
+
+            let (token, quote) = *key;
+            let Some(estimator) = self.estimator_for(quote) else {
+                let err = anyhow!("no estimator for quote token {quote:?}");
+                return (index, Err(PriceEstimationError::EstimatorInternal(err)));
+            };
+
+            let leader = match self.join_in_flight(*key, Instant::now()) {
+                InFlightRole::Leader { started_at, sender } => Some((started_at, sender)),
+                InFlightRole::Follower { started_at, result } => {
+                    let timeout = self.in_flight_timeout.saturating_sub(started_at.elapsed());
+                    if let Ok(Some(result)) =
+                        tokio::time::timeout(timeout, wait_for_result(result)).await
+                    {
+                        return (index, result);
                     }
+                    // The in-flight request took too long or got cancelled so we
+                    // fetch the price ourselves.
+                    None
                 }
+            };
 
-                let result = self.estimator.estimate_native_price(*token).await;
+            let start = Instant::now();
+            let result = estimator.estimate_native_price(token).await;
+            let elapsed = start.elapsed();
+            Metrics::get()
+                .native_price_cache_estimation_times
+                .with_label_values(&[caller.label()])
+                .observe(elapsed.as_secs_f64());
 
-                // update price in cache
-                if should_cache(&result) {
-                    let now = Instant::now();
-                    let mut cache = self.cache.lock().unwrap();
-                    cache.insert(
-                        *token,
-                        CachedResult {
-                            result: result.clone(),
-                            updated_at: now,
-                            requested_at: now,
-                        },
-                    );
-                };
+            self.update_cache(*key, &result, elapsed);
+            if let Some((started_at, sender)) = leader {
+                self.finish_in_flight(*key, started_at, sender, &result);
+            }
 
-                (index, result)
-            });
+            (index, result)
+        });
         futures::stream::iter(estimates)
             .buffered(parallelism)
             .boxed()
     }
 
+    /// Like `estimate_prices_and_update_cache` but fetches the prices with
+    /// batch requests of at most `batch_size` tokens. The cache gets updated
+    /// as soon as the results of a batch arrive.
+    async fn estimate_prices_in_batches_and_update_cache(
+        &self,
+        keys: &[CacheKey],
+        max_age: Duration,
+        batch_size: usize,
+        parallelism: usize,
+    ) {
+        let mut tokens_by_quote = HashMap::<H160, Vec<H160>>::new();
+        for (token, quote) in keys {
+            tokens_by_quote.entry(*quote).or_default().push(*token);
+        }
+        let batches: Vec<_> = tokens_by_quote
+            .iter()
+            .flat_map(|(quote, tokens)| {
+                tokens
+                    .chunks(batch_size.max(1))
+                    .map(move |chunk| (*quote, chunk))
+            })
+            .collect();
+
+        futures::stream::iter(batches)
+            .for_each_concurrent(parallelism, |(quote, tokens)| {
+                self.estimate_batch_and_update_cache(quote, tokens, max_age, parallelism)
+            })
+            .await;
+    }
+
+    /// Fetches the prices of all `tokens` which are neither cached nor in
+    /// flight with a single batch request and updates the cache.
+    async fn estimate_batch_and_update_cache(
+        &self,
+        quote: H160,
+        tokens: &[H160],
+        max_age: Duration,
+        parallelism: usize,
+    ) {
+        let Some(estimator) = self.estimator_for(quote) else {
+            tracing::error!(?quote, "no estimator for quote token");
+            return;
+        };
+
+        let mut leaders = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let key = (*token, quote);
+            let now = Instant::now();
+            if Self::get_cached_price(key, now, &mut self.cache.lock().unwrap(), &max_age, false)
+                .is_some()
+            {
+                continue;
+            }
+            // Prices that are already in flight will be cached by whoever
+            // requested them.
+            if let InFlightRole::Leader { started_at, sender } = self.join_in_flight(key, now) {
+                leaders.push((*token, started_at, sender));
+            }
+        }
+        if leaders.is_empty() {
+            return;
+        }
+
+        let tokens: Vec<_> = leaders.iter().map(|(token, ..)| *token).collect();
+        let start = Instant::now();
+        let results = estimator
+            .estimate_native_prices_batch(&tokens, parallelism)
+            .await;
+        let elapsed = start.elapsed();
+        Metrics::get()
+            .native_price_cache_estimation_times
+            .with_label_values(&[Caller::Background.label()])
+            .observe(elapsed.as_secs_f64());
+
+        if results.len() != tokens.len() {
+            tracing::warn!(
+                expected = tokens.len(),
+                actual = results.len(),
+                "batch price estimation returned unexpected number of results"
+            );
+        }
+        let mut results = results.into_iter();
+        for (token, started_at, sender) in leaders {
+            let result = results.next().unwrap_or_else(|| {
+                Err(PriceEstimationError::EstimatorInternal(anyhow!(
+                    "missing result in batch price estimation"
+                )))
+            });
+            self.update_cache((token, quote), &result, elapsed);
+            self.finish_in_flight((token, quote), started_at, sender, &result);
+        }
+    }
+
+    /// Stores a freshly fetched result in the cache if it's not a transient
+    /// error.
+    fn update_cache(&self, key: CacheKey, result: &NativePriceEstimateResult, elapsed: Duration) {
+        Metrics::get()
+            .native_price_cache_fill_times
+            .with_label_values(&[result_label(result)])
+            .observe(elapsed.as_secs_f64());
+
+        if !should_cache(result) {
+            return;
+        }
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        let unsupported_count = match (result, cache.get(&key)) {
+            (Err(PriceEstimationError::UnsupportedToken { .. }), Some(previous)) => {
+                previous.unsupported_count + 1
+            }
+            (Err(PriceEstimationError::UnsupportedToken { .. }), None) => 1,
+            _ => 0,
+        };
+        // Only unsupported tokens get frozen. Other results always get
+        // refreshed.
+        let frozen = matches!(result, Err(PriceEstimationError::UnsupportedToken { .. }))
+            && self
+                .permanent_failure_threshold
+                .is_some_and(|threshold| unsupported_count >= threshold.get());
+        if frozen {
+            let (token, quote) = key;
+            tracing::debug!(?token, ?quote, "freezing permanently unsupported token");
+        }
+        cache.insert(
+            key,
+            CachedResult {
+                result: result.clone(),
+                updated_at: now,
+                requested_at: now,
+                unsupported_count,
+                frozen,
+            },
+        );
+    }
+
+    /// Replaces the high priority tokens with the ones returned by the
+    /// priority source if there is one.
+    fn refresh_high_priority(&self) {
+        if let Some(source) = self.priority_source.lock().unwrap().as_ref() {
+            *self.high_priority.lock().unwrap() = source();
+        }
+    }
+
+    /// Creates outdated entries for all missing quote variants of high
+    /// priority tokens which are already cached for some quote. That way the
+    /// maintenance task fetches prices in all quote tokens for them.
+    fn add_missing_quote_variants(&self, now: Instant) {
+        if self.quote_estimators.is_empty() {
+            return;
+        }
+        let high_priority = self.high_priority.lock().unwrap().clone();
+        let mut cache = self.cache.lock().unwrap();
+        let tokens: HashSet<_> = cache
+            .keys()
+            .map(|(token, _)| *token)
+            .filter(|token| high_priority.contains(token))
+            .collect();
+        for token in tokens {
+            for quote in self.quotes() {
+                cache
+                    .entry((token, quote))
+                    .or_insert_with(|| CachedResult::outdated(now, &self.max_age));
+            }
+        }
+    }
+
     /// Tokens with highest priority first.
-    fn sorted_tokens_to_update(&self, max_age: Duration, now: Instant) -> Vec<(H160, Instant)> {
+    fn sorted_tokens_to_update(&self, max_age: Duration, now: Instant) -> Vec<(CacheKey, Instant)> {
         let mut outdated: Vec<_> = self
             .cache
             .lock()
             .unwrap()
             .iter()
-            .filter(|(_, cached)| now.saturating_duration_since(cached.updated_at) > max_age)
-            .map(|(token, cached)| (*token, cached.requested_at))
+            .filter(|(_, cached)| {
+                !cached.frozen && now.saturating_duration_since(cached.updated_at) > max_age
+            })
+            .map(|(key, cached)| (*key, cached.requested_at))
             .collect();
         let high_priority = self.high_priority.lock().unwrap().clone();
-        let priority = |token: &H160| high_priority.contains(token) as u8;
+        let priority = |(token, _): &CacheKey| high_priority.contains(token) as u8;
         outdated.sort_unstable_by_key(|entry| {
             (
                 std::cmp::Reverse(priority(&entry.0)),
@@ -166,6 +532,46 @@ impl Inner {
         });
         outdated
     }
+
+    /// Returns the `n` oldest entries which are still being requested together
+    /// with their age. Oldest entries first.
+    /// An entry counts as still being requested if it was requested within the
+    /// last `max_age`. Frozen entries get ignored since they never get updated
+    /// on purpose.
+    fn oldest_requested_entries(&self, now: Instant, n: usize) -> Vec<(CacheKey, Duration)> {
+        // Only copy the relevant data while holding the lock and do the sorting
+        // afterwards.
+        let mut entries: Vec<_> = self
+            .cache
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, cached)| {
+                !cached.frozen && now.saturating_duration_since(cached.requested_at) < self.max_age
+            })
+            .map(|(key, cached)| (*key, cached.updated_at))
+            .collect();
+        entries.sort_unstable_by_key(|(_, updated_at)| *updated_at);
+        entries
+            .into_iter()
+            .take(n)
+            .map(|(key, updated_at)| (key, now.saturating_duration_since(updated_at)))
+            .collect()
+    }
+}
+
+/// Waits until the result of an in-flight request gets published. Returns
+/// `None` if the request got cancelled.
+async fn wait_for_result(
+    mut receiver: watch::Receiver<Option<NativePriceEstimateResult>>,
+) -> Option<NativePriceEstimateResult> {
+    loop {
+        let result = receiver.borrow().clone();
+        if result.is_some() {
+            return result;
+        }
+        receiver.changed().await.ok()?;
+    }
 }
 
 fn should_cache(result: &Result<f64, PriceEstimationError>) -> bool {
@@ -184,14 +590,32 @@ fn should_cache(result: &Result<f64, PriceEstimationError>) -> bool {
     }
 }
 
+fn result_label(result: &NativePriceEstimateResult) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(PriceEstimationError::NoLiquidity) => "no_liquidity",
+        Err(PriceEstimationError::UnsupportedToken { .. }) => "unsupported_token",
+        Err(PriceEstimationError::UnsupportedOrderType(_)) => "unsupported_order_type",
+        Err(PriceEstimationError::RateLimited) => "rate_limited",
+        Err(PriceEstimationError::EstimatorInternal(_)) => "estimator_internal",
+        Err(PriceEstimationError::ProtocolInternal(_)) => "protocol_internal",
+    }
+}
+
 impl UpdateTask {
     /// Single run of the background updating process.
     async fn single_update(&self, inner: &Inner) {
         let metrics = Metrics::get();
-        metrics
-            .native_price_cache_size
-            .set(inner.cache.lock().unwrap().len() as i64);
+        {
+            let cache = inner.cache.lock().unwrap();
+            metrics.native_price_cache_size.set(cache.len() as i64);
+            metrics
+                .native_price_cache_frozen_entries
+                .set(cache.values().filter(|cached| cached.frozen).count() as i64);
+        }
 
+        inner.refresh_high_priority();
+        inner.add_missing_quote_variants(Instant::now());
         let max_age = inner.max_age.saturating_sub(self.prefetch_time);
         let outdated_entries = inner.sorted_tokens_to_update(max_age, Instant::now());
 
@@ -202,20 +626,53 @@ impl UpdateTask {
         let tokens_to_update: Vec<_> = outdated_entries
             .iter()
             .take(self.update_size.unwrap_or(outdated_entries.len()))
-            .map(|(token, _)| *token)
+            .map(|(key, _)| *key)
             .collect();
 
         if !tokens_to_update.is_empty() {
-            let mut stream = inner.estimate_prices_and_update_cache(
-                &tokens_to_update,
-                max_age,
-                self.concurrent_requests,
-            );
-            while stream.next().await.is_some() {}
+            match self.batch_size {
+                Some(batch_size) => {
+                    inner
+                        .estimate_prices_in_batches_and_update_cache(
+                            &tokens_to_update,
+                            max_age,
+                            batch_size,
+                            self.concurrent_requests,
+                        )
+                        .await
+                }
+                None => {
+                    let mut stream = inner.estimate_prices_and_update_cache(
+                        &tokens_to_update,
+                        max_age,
+                        self.concurrent_requests,
+                        Caller::Background,
+                    );
+                    while stream.next().await.is_some() {}
+                }
+            }
             metrics
                 .native_price_cache_background_updates
                 .inc_by(tokens_to_update.len() as u64);
         }
+
+        let stale_entries = inner.oldest_requested_entries(Instant::now(), STALE_ENTRIES_TO_REPORT);
+        let max_entry_age = stale_entries
+            .first()
+            .map(|(_, age)| *age)
+            .unwrap_or_default();
+        metrics
+            .native_price_cache_max_entry_age
+            .set(max_entry_age.as_secs_f64());
+        if let Some(threshold) = self.max_entry_age_warning {
+            let stale_entries: Vec<_> = stale_entries
+                .into_iter()
+                .filter(|(_, age)| *age > threshold)
+                .collect();
+            if !stale_entries.is_empty() {
+                tracing::warn!(?stale_entries, "native price cache entries are stale");
+            }
+        }
     }
 
     /// Runs background updates until inner is no longer alive.
@@ -236,19 +693,42 @@ impl CachingNativePriceEstimator {
     /// recently used prices have a higher priority. If `update_size` is
     /// `Some(n)` at most `n` prices get updated per interval.
     /// If `update_size` is `None` no limit gets applied.
+    /// If `permanent_failure_threshold` is `Some(n)` tokens which were
+    /// reported as unsupported `n` times in a row get frozen, i.e. their
+    /// error gets returned until they get invalidated explicitly.
+    /// If `max_entry_age_warning` is set the most stale entries which are still
+    /// being requested get logged when they are older than that.
+    /// `quote_estimators` are used to estimate prices denominated in other
+    /// quote tokens than `NATIVE_QUOTE` (see `estimate_price_in`).
+    /// If `batch_size` is `Some(n)` the maintenance task fetches prices with
+    /// batch requests of at most `n` tokens.
+    /// Callers requesting a price which is already being fetched wait up to
+    /// `in_flight_timeout` for that request before issuing their own.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         estimator: Box<dyn NativePriceEstimating>,
+        quote_estimators: HashMap<H160, Box<dyn NativePriceEstimating>>,
         max_age: Duration,
         update_interval: Duration,
         update_size: Option<usize>,
         prefetch_time: Duration,
         concurrent_requests: usize,
+        permanent_failure_threshold: Option<NonZeroUsize>,
+        max_entry_age_warning: Option<Duration>,
+        batch_size: Option<usize>,
+        in_flight_timeout: Duration,
     ) -> Self {
         let inner = Arc::new(Inner {
             estimator,
+            quote_estimators,
             cache: Default::default(),
             high_priority: Default::default(),
+            priority_source: Default::default(),
+            watched_tokens: Default::default(),
             max_age,
+            in_flight: Default::default(),
+            in_flight_timeout,
+            permanent_failure_threshold,
         });
 
         let update_task = UpdateTask {
@@ -257,6 +737,8 @@ impl CachingNativePriceEstimator {
             update_size,
             prefetch_time,
             concurrent_requests,
+            max_entry_age_warning,
+            batch_size,
         }
         .run()
         .instrument(tracing::info_span!("caching_native_price_estimator"));
@@ -276,12 +758,14 @@ impl CachingNativePriceEstimator {
         let mut cache = self.0.cache.lock().unwrap();
         let mut results = HashMap::default();
         for token in tokens {
-            let cached = Inner::get_cached_price(*token, now, &mut cache, &self.0.max_age, true);
-            let label = if cached.is_some() { "hits" } else { "misses" };
-            Metrics::get()
-                .native_price_cache_access
-                .with_label_values(&[label])
-                .inc_by(1);
+            let cached = Inner::get_cached_price(
+                (*token, NATIVE_QUOTE),
+                now,
+                &mut cache,
+                &self.0.max_age,
+                true,
+            );
+            self.0.record_access(token, cached.is_some());
             if let Some(result) = cached {
                 results.insert(*token, result);
             }
@@ -292,32 +776,78 @@ impl CachingNativePriceEstimator {
     pub fn replace_high_priority(&self, tokens: HashSet<H160>) {
         *self.0.high_priority.lock().unwrap() = tokens;
     }
-}
 
-impl NativePriceEstimating for CachingNativePriceEstimator {
-    fn estimate_native_price(
+    /// Replaces the tokens for which cache hits and misses get reported
+    /// individually.
+    pub fn set_watched_tokens(&self, tokens: HashSet<H160>) {
+        *self.0.watched_tokens.lock().unwrap() = tokens;
+    }
+
+    /// Makes the maintenance task fetch the high priority tokens from `source`
+    /// at the start of every cycle instead of relying on
+    /// `replace_high_priority` getting called.
+    pub fn set_priority_source(&self, source: PrioritySource) {
+        *self.0.priority_source.lock().unwrap() = Some(source);
+    }
+
+    /// Removes the cached results of the given token for all quote tokens.
+    /// This is the only way to get a frozen entry fetched again.
+    pub fn invalidate(&self, token: &H160) {
+        self.0
+            .cache
+            .lock()
+            .unwrap()
+            .retain(|(cached, _), _| cached != token);
+    }
+
+    /// Like `estimate_native_price` but only returns cached prices that are
+    /// younger than `max_age`. The requirement gets capped at the configured
+    /// cache max age. If no sufficiently recent price is cached a new
+    /// estimate gets fetched which also updates the cache for everyone else.
+    pub fn estimate_native_price_with_max_age(
         &self,
         token: H160,
+        max_age: Duration,
+    ) -> futures::future::BoxFuture<'_, NativePriceEstimateResult> {
+        self.estimate((token, NATIVE_QUOTE), max_age.min(self.0.max_age))
+    }
+
+    /// Like `estimate_native_price` but the price is denominated in `quote`
+    /// instead of the native token. `quote` has to be one of the configured
+    /// quote tokens.
+    pub fn estimate_price_in(
+        &self,
+        token: H160,
+        quote: H160,
+    ) -> futures::future::BoxFuture<'_, NativePriceEstimateResult> {
+        if self.0.estimator_for(quote).is_none() {
+            let err = anyhow!("quote token {quote:?} is not supported");
+            return futures::future::ready(Err(PriceEstimationError::EstimatorInternal(err)))
+                .boxed();
+        }
+        self.estimate((token, quote), self.0.max_age)
+    }
+
+    fn estimate(
+        &self,
+        key: CacheKey,
+        max_age: Duration,
     ) -> futures::future::BoxFuture<'_, NativePriceEstimateResult> {
         async move {
             let cached = {
                 let now = Instant::now();
                 let mut cache = self.0.cache.lock().unwrap();
-                Inner::get_cached_price(token, now, &mut cache, &self.0.max_age, false)
+                Inner::get_cached_price(key, now, &mut cache, &max_age, false)
             };
 
-            let label = if cached.is_some() { "hits" } else { "misses" };
-            Metrics::get()
-                .native_price_cache_access
-                .with_label_values(&[label])
-                .inc_by(1);
+            self.0.record_access(&key.0, cached.is_some());
 
             if let Some(price) = cached {
                 return price;
             }
 
             self.0
-                .estimate_prices_and_update_cache(&[token], self.0.max_age, 1)
+                .estimate_prices_and_update_cache(&[key], max_age, 1, Caller::User)
                 .next()
                 .await
                 .unwrap()
@@ -327,6 +857,15 @@ impl NativePriceEstimating for CachingNativePriceEstimator {
     }
 }
 
+impl NativePriceEstimating for CachingNativePriceEstimator {
+    fn estimate_native_price(
+        &self,
+        token: H160,
+    ) -> futures::future::BoxFuture<'_, NativePriceEstimateResult> {
+        self.estimate_native_price_with_max_age(token, self.0.max_age)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -353,11 +892,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Default::default(),
             None,
             Default::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         for _ in 0..10 {
@@ -376,11 +920,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Default::default(),
             None,
             Default::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         for _ in 0..10 {
@@ -402,11 +951,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Default::default(),
             None,
             Default::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         for _ in 0..10 {
@@ -456,11 +1010,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Duration::from_millis(50),
             Some(1),
             Duration::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         // fill cache with 2 different queries
@@ -494,11 +1053,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Duration::from_millis(50),
             None,
             Duration::default(),
             1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         let tokens: Vec<_> = (0..10).map(H160::from_low_u64_be).collect();
@@ -539,11 +1103,16 @@ mod tests {
 
         let estimator = CachingNativePriceEstimator::new(
             Box::new(inner),
+            Default::default(),
             Duration::from_millis(30),
             Duration::from_millis(50),
             None,
             Duration::default(),
             BATCH_SIZE,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
         );
 
         let tokens: Vec<_> = (0..BATCH_SIZE as u64).map(H160::from_low_u64_be).collect();
@@ -564,6 +1133,540 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn stricter_max_age_triggers_refetch() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .times(1)
+            .returning(|_| async { Ok(1.0) }.boxed());
+        inner
+            .expect_estimate_native_price()
+            .times(1)
+            .returning(|_| async { Ok(2.0) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 1);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // still recent enough for the default max age
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 1);
+
+        // but too old for the stricter requirement
+        let result = estimator
+            .estimate_native_price_with_max_age(token(0), Duration::from_millis(10))
+            .await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 2);
+
+        // the refetched price is shared with other callers
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn max_age_is_capped_at_cache_max_age() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .times(2)
+            .returning(|_| async { Ok(1.0) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_millis(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        let _ = estimator.estimate_native_price(token(0)).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let _ = estimator
+            .estimate_native_price_with_max_age(token(0), Duration::from_secs(60))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn freezes_permanently_unsupported_tokens() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut inner = MockNativePriceEstimating::new();
+        inner.expect_estimate_native_price().returning({
+            let calls = calls.clone();
+            move |token| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move {
+                    Err(PriceEstimationError::UnsupportedToken {
+                        token,
+                        reason: Default::default(),
+                    })
+                }
+                .boxed()
+            }
+        });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_millis(10),
+            Duration::from_millis(5),
+            None,
+            Default::default(),
+            1,
+            NonZeroUsize::new(3),
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert!(matches!(
+            result,
+            Err(PriceEstimationError::UnsupportedToken { .. })
+        ));
+
+        // the maintenance task keeps refetching the price until the entry
+        // gets frozen
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // frozen errors still get returned immediately
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert!(matches!(
+            result,
+            Err(PriceEstimationError::UnsupportedToken { .. })
+        ));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // invalidating the entry causes it to be fetched again
+        estimator.invalidate(&token(0));
+        let _ = estimator.estimate_native_price(token(0)).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_freeze_supported_tokens() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut inner = MockNativePriceEstimating::new();
+        inner.expect_estimate_native_price().returning({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async { Ok(1.0) }.boxed()
+            }
+        });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_millis(10),
+            Duration::from_millis(5),
+            None,
+            Default::default(),
+            1,
+            NonZeroUsize::new(1),
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        assert_eq!(
+            estimator.estimate_native_price(token(0)).await.unwrap(),
+            1.0
+        );
+
+        // prices keep getting refreshed by the maintenance task
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(calls.load(std::sync::atomic::Ordering::SeqCst) > 3);
+    }
+
+    #[tokio::test]
+    async fn caches_prices_per_quote_token() {
+        let quote = token(42);
+        let mut native = MockNativePriceEstimating::new();
+        native
+            .expect_estimate_native_price()
+            .times(1)
+            .returning(|_| async { Ok(1.0) }.boxed());
+        let mut in_quote = MockNativePriceEstimating::new();
+        in_quote
+            .expect_estimate_native_price()
+            .times(1)
+            .returning(|_| async { Ok(2.0) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(native),
+            HashMap::from([(quote, Box::new(in_quote) as Box<dyn NativePriceEstimating>)]),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        for _ in 0..10 {
+            let result = estimator.estimate_native_price(token(0)).await;
+            assert_eq!(result.unwrap().to_i64().unwrap(), 1);
+            let result = estimator.estimate_price_in(token(0), quote).await;
+            assert_eq!(result.unwrap().to_i64().unwrap(), 2);
+            let result = estimator.estimate_price_in(token(0), NATIVE_QUOTE).await;
+            assert_eq!(result.unwrap().to_i64().unwrap(), 1);
+        }
+
+        let result = estimator.estimate_price_in(token(0), token(43)).await;
+        assert!(matches!(
+            result,
+            Err(PriceEstimationError::EstimatorInternal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn maintenance_updates_all_quotes_of_high_priority_tokens() {
+        let quote = token(42);
+        let mut native = MockNativePriceEstimating::new();
+        native
+            .expect_estimate_native_price()
+            .times(2)
+            .returning(|_| async { Ok(1.0) }.boxed());
+        let mut in_quote = MockNativePriceEstimating::new();
+        // only the high priority token gets fetched by the maintenance task
+        in_quote
+            .expect_estimate_native_price()
+            .times(1)
+            .withf(|t| *t == token(0))
+            .returning(|_| async { Ok(2.0) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(native),
+            HashMap::from([(quote, Box::new(in_quote) as Box<dyn NativePriceEstimating>)]),
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+        estimator.replace_high_priority(HashSet::from([token(0)]));
+
+        let _ = estimator.estimate_native_price(token(0)).await;
+        let _ = estimator.estimate_native_price(token(1)).await;
+
+        // wait for maintenance cycle
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let result = estimator.estimate_price_in(token(0), quote).await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn user_request_waits_for_in_flight_background_update() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut inner = MockNativePriceEstimating::new();
+        inner.expect_estimate_native_price().returning({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(1.0)
+                }
+                .boxed()
+            }
+        });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        // creates an outdated entry the maintenance task will update soon
+        assert!(estimator.get_cached_prices(&[token(0)]).is_empty());
+
+        // wait until the maintenance task started fetching the price
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let result = estimator.estimate_native_price(token(0)).await;
+        assert_eq!(result.unwrap().to_i64().unwrap(), 1);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn records_access_of_watched_tokens() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .returning(|_| async { Ok(1.0) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+        // use unique tokens since the metrics are shared with other tests
+        let watched = H160([0x39; 20]);
+        let unwatched = H160([0x40; 20]);
+        estimator.set_watched_tokens(HashSet::from([watched]));
+
+        let accesses = |token: H160, result: &str| {
+            Metrics::get()
+                .native_price_cache_access
+                .with_label_values(&[result, &format!("{token:?}")])
+                .get()
+        };
+
+        // miss followed by a hit
+        let _ = estimator.estimate_native_price(watched).await;
+        let _ = estimator.estimate_native_price(watched).await;
+        let _ = estimator.get_cached_prices(&[watched]);
+        let _ = estimator.estimate_native_price(unwatched).await;
+        let _ = estimator.get_cached_prices(&[unwatched]);
+
+        assert_eq!(accesses(watched, "misses"), 1);
+        assert_eq!(accesses(watched, "hits"), 2);
+        assert_eq!(accesses(unwatched, "misses"), 0);
+        assert_eq!(accesses(unwatched, "hits"), 0);
+
+        // the watch list can be changed at runtime
+        estimator.set_watched_tokens(HashSet::from([unwatched]));
+        let _ = estimator.estimate_native_price(watched).await;
+        let _ = estimator.estimate_native_price(unwatched).await;
+        assert_eq!(accesses(watched, "hits"), 2);
+        assert_eq!(accesses(unwatched, "hits"), 1);
+    }
+
+    #[tokio::test]
+    async fn maintenance_updates_prices_in_batches() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .times(5)
+            .returning(|_| async { Ok(1.0) }.boxed());
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        inner.expect_estimate_native_prices_batch().returning({
+            let batches = batches.clone();
+            move |tokens: &[H160], _| {
+                batches.lock().unwrap().push(tokens.len());
+                let results: Vec<NativePriceEstimateResult> =
+                    tokens.iter().map(|_| Ok(2.0)).collect();
+                async move { results }.boxed()
+            }
+        });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_millis(30),
+            Duration::from_millis(50),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            Some(2),
+            Duration::from_secs(10),
+        );
+
+        let tokens: Vec<_> = (0..5).map(H160::from_low_u64_be).collect();
+        for token in &tokens {
+            let price = estimator.estimate_native_price(*token).await.unwrap();
+            assert_eq!(price.to_i64().unwrap(), 1);
+        }
+
+        // wait for maintenance cycle
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let mut batches = batches.lock().unwrap().clone();
+        batches.sort();
+        assert_eq!(batches, vec![1, 2, 2]);
+        for token in &tokens {
+            let price = estimator.estimate_native_price(*token).await.unwrap();
+            assert_eq!(price.to_i64().unwrap(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn records_estimation_time_metrics() {
+        let mut inner = MockNativePriceEstimating::new();
+        inner
+            .expect_estimate_native_price()
+            .times(2)
+            .returning(|_| async { Ok(1.0) }.boxed());
+        inner
+            .expect_estimate_native_price()
+            .times(1)
+            .returning(|_| async { Err(PriceEstimationError::NoLiquidity) }.boxed());
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Default::default(),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            None,
+            Default::default(),
+            1,
+            None,
+            None,
+            None,
+            Duration::from_secs(10),
+        );
+
+        let metrics = Metrics::get();
+        let user_samples = || {
+            metrics
+                .native_price_cache_estimation_times
+                .with_label_values(&["user"])
+                .get_sample_count()
+        };
+        let fill_samples = |result: &str| {
+            metrics
+                .native_price_cache_fill_times
+                .with_label_values(&[result])
+                .get_sample_count()
+        };
+        let (user, ok, no_liquidity) = (
+            user_samples(),
+            fill_samples("ok"),
+            fill_samples("no_liquidity"),
+        );
+
+        for i in 0..3 {
+            let _ = estimator.estimate_native_price(token(i)).await;
+        }
+
+        // Other tests share the same global registry so only lower bounds can
+        // be asserted.
+        assert!(user_samples() >= user + 3);
+        assert!(fill_samples("ok") >= ok + 2);
+        assert!(fill_samples("no_liquidity") >= no_liquidity + 1);
+
+        let families: HashSet<_> = observe::metrics::get_registry()
+            .gather()
+            .into_iter()
+            .map(|family| family.get_name().to_string())
+            .collect();
+        assert!(families.contains("native_price_cache_estimation_times"));
+        assert!(families.contains("native_price_cache_fill_times"));
+    }
+
+    #[test]
+    fn computes_oldest_requested_entries() {
+        let now = Instant::now();
+        let entry = |updated_at: Instant, requested_at: Instant, frozen: bool| CachedResult {
+            result: Ok(0.),
+            updated_at,
+            requested_at,
+            unsupported_count: 0,
+            frozen,
+        };
+        let inner = Inner {
+            cache: Mutex::new(
+                [
+                    // recently requested
+                    (
+                        (token(0), NATIVE_QUOTE),
+                        entry(now, now + Duration::from_secs(9), false),
+                    ),
+                    (
+                        (token(1), NATIVE_QUOTE),
+                        entry(
+                            now + Duration::from_secs(2),
+                            now + Duration::from_secs(9),
+                            false,
+                        ),
+                    ),
+                    (
+                        (token(2), NATIVE_QUOTE),
+                        entry(
+                            now + Duration::from_secs(1),
+                            now + Duration::from_secs(9),
+                            false,
+                        ),
+                    ),
+                    // no longer requested
+                    ((token(3), NATIVE_QUOTE), entry(now, now, false)),
+                    // frozen
+                    (
+                        (token(4), NATIVE_QUOTE),
+                        entry(now, now + Duration::from_secs(9), true),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            high_priority: Default::default(),
+            priority_source: Default::default(),
+            watched_tokens: Default::default(),
+            estimator: Box::new(MockNativePriceEstimating::new()),
+            quote_estimators: Default::default(),
+            max_age: Duration::from_secs(5),
+            in_flight: Default::default(),
+            in_flight_timeout: Duration::from_secs(10),
+            permanent_failure_threshold: None,
+        };
+
+        let now = now + Duration::from_secs(10);
+        let entries = inner.oldest_requested_entries(now, 2);
+        assert_eq!(
+            entries,
+            vec![
+                ((token(0), NATIVE_QUOTE), Duration::from_secs(10)),
+                ((token(2), NATIVE_QUOTE), Duration::from_secs(9)),
+            ]
+        );
+
+        let entries = inner.oldest_requested_entries(now, 10);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[2],
+            ((token(1), NATIVE_QUOTE), Duration::from_secs(8))
+        );
+    }
+
     #[test]
     fn outdated_entries_prioritized() {
         let t0 = H160::from_low_u64_be(0);
@@ -573,19 +1676,23 @@ mod tests {
             cache: Mutex::new(
                 [
                     (
-                        t0,
+                        (t0, NATIVE_QUOTE),
                         CachedResult {
                             result: Ok(0.),
                             updated_at: now,
                             requested_at: now,
+                            unsupported_count: 0,
+                            frozen: false,
                         },
                     ),
                     (
-                        t1,
+                        (t1, NATIVE_QUOTE),
                         CachedResult {
                             result: Ok(0.),
                             updated_at: now,
                             requested_at: now,
+                            unsupported_count: 0,
+                            frozen: false,
                         },
                     ),
                 ]
@@ -593,20 +1700,77 @@ mod tests {
                 .collect(),
             ),
             high_priority: Default::default(),
+            priority_source: Default::default(),
+            watched_tokens: Default::default(),
             estimator: Box::new(MockNativePriceEstimating::new()),
+            quote_estimators: Default::default(),
             max_age: Default::default(),
+            in_flight: Default::default(),
+            in_flight_timeout: Duration::from_secs(10),
+            permanent_failure_threshold: None,
         };
 
         let now = now + Duration::from_secs(1);
 
         *inner.high_priority.lock().unwrap() = std::iter::once(t0).collect();
         let tokens = inner.sorted_tokens_to_update(Duration::from_secs(0), now);
-        assert_eq!(tokens[0].0, t0);
-        assert_eq!(tokens[1].0, t1);
+        assert_eq!(tokens[0].0, (t0, NATIVE_QUOTE));
+        assert_eq!(tokens[1].0, (t1, NATIVE_QUOTE));
 
         *inner.high_priority.lock().unwrap() = std::iter::once(t1).collect();
         let tokens = inner.sorted_tokens_to_update(Duration::from_secs(0), now);
-        assert_eq!(tokens[0].0, t1);
-        assert_eq!(tokens[1].0, t0);
+        assert_eq!(tokens[0].0, (t1, NATIVE_QUOTE));
+        assert_eq!(tokens[1].0, (t0, NATIVE_QUOTE));
+    }
+
+    #[test]
+    fn priority_source_determines_order() {
+        let t0 = token(0);
+        let t1 = token(1);
+        let now = Instant::now();
+        let entry = CachedResult {
+            result: Ok(0.),
+            updated_at: now,
+            requested_at: now,
+            unsupported_count: 0,
+            frozen: false,
+        };
+        let inner = Inner {
+            cache: Mutex::new(
+                [
+                    ((t0, NATIVE_QUOTE), entry.clone()),
+                    ((t1, NATIVE_QUOTE), entry),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            high_priority: Default::default(),
+            priority_source: Default::default(),
+            watched_tokens: Default::default(),
+            estimator: Box::new(MockNativePriceEstimating::new()),
+            quote_estimators: Default::default(),
+            max_age: Default::default(),
+            in_flight: Default::default(),
+            in_flight_timeout: Duration::from_secs(10),
+            permanent_failure_threshold: None,
+        };
+        let open_orders = Arc::new(Mutex::new(HashSet::from([t0])));
+        *inner.priority_source.lock().unwrap() = Some(Box::new({
+            let open_orders = open_orders.clone();
+            move || open_orders.lock().unwrap().clone()
+        }));
+
+        let now = now + Duration::from_secs(1);
+
+        inner.refresh_high_priority();
+        let tokens = inner.sorted_tokens_to_update(Duration::from_secs(0), now);
+        assert_eq!(tokens[0].0, (t0, NATIVE_QUOTE));
+        assert_eq!(tokens[1].0, (t1, NATIVE_QUOTE));
+
+        *open_orders.lock().unwrap() = HashSet::from([t1]);
+        inner.refresh_high_priority();
+        let tokens = inner.sorted_tokens_to_update(Duration::from_secs(0), now);
+        assert_eq!(tokens[0].0, (t1, NATIVE_QUOTE));
+        assert_eq!(tokens[1].0, (t0, NATIVE_QUOTE));
     }
 }
@@ -49,20 +49,38 @@ pub struct Order {
 
 impl From<&ModelOrder> for Order {
     fn from(o: &ModelOrder) -> Self {
+        let (total, executed) = match o.data.kind {
+            // A real buy order cannot execute more than U256::MAX so in order to make this
+            // function infallible we treat a larger amount as a full execution.
+            OrderKind::Buy => (
+                o.data.buy_amount,
+                number::conversions::big_uint_to_u256(&o.metadata.executed_buy_amount)
+                    .unwrap_or(o.data.buy_amount),
+            ),
+            OrderKind::Sell => (
+                o.data.sell_amount,
+                o.metadata.executed_sell_amount_before_fees,
+            ),
+        };
+        // The signed amounts of amended orders stay unchanged so we treat the
+        // amount the order got reduced by as already executed. Executions
+        // exceeding the amended amounts (e.g. by a settlement that was already
+        // in flight) mean that the order is fully executed.
+        let executed = match (o.metadata.amended_amounts, o.data.kind) {
+            (Some(amended), OrderKind::Buy) => executed
+                .saturating_add(total.saturating_sub(amended.buy_amount))
+                .min(total),
+            (Some(amended), OrderKind::Sell) => executed
+                .saturating_add(total.saturating_sub(amended.sell_amount))
+                .min(total),
+            (None, _) => executed,
+        };
         Self {
             kind: o.data.kind,
             buy_amount: o.data.buy_amount,
             sell_amount: o.data.sell_amount,
             fee_amount: o.data.fee_amount,
-            executed_amount: match o.data.kind {
-                // A real buy order cannot execute more than U256::MAX so in order to make this
-                // function infallible we treat a larger amount as a full execution.
-                OrderKind::Buy => {
-                    number::conversions::big_uint_to_u256(&o.metadata.executed_buy_amount)
-                        .unwrap_or(o.data.buy_amount)
-                }
-                OrderKind::Sell => o.metadata.executed_sell_amount_before_fees,
-            },
+            executed_amount: executed,
             partially_fillable: o.data.partially_fillable,
         }
     }
@@ -179,7 +197,7 @@ mod ratio {
 mod tests {
     use {
         super::*,
-        model::order::{OrderData, OrderMetadata},
+        model::order::{AmendedAmounts, OrderData, OrderMetadata},
         num::BigUint,
     };
 
@@ -444,4 +462,39 @@ mod tests {
         let remaining = Remaining::from_order_with_balance(&order, balance).unwrap();
         assert_eq!(remaining.remaining(order.sell_amount).unwrap(), balance);
     }
+
+    #[test]
+    fn amended_orders_only_expose_reduced_remainder() {
+        let order = |executed: u32| -> Order {
+            ModelOrder {
+                data: OrderData {
+                    sell_amount: 100.into(),
+                    buy_amount: 200.into(),
+                    kind: OrderKind::Sell,
+                    partially_fillable: true,
+                    ..Default::default()
+                },
+                metadata: OrderMetadata {
+                    executed_sell_amount_before_fees: executed.into(),
+                    amended_amounts: Some(AmendedAmounts {
+                        sell_amount: 50.into(),
+                        buy_amount: 100.into(),
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .into()
+        };
+
+        let remaining = Remaining::from_order(&order(0)).unwrap();
+        assert_eq!(remaining.remaining(100.into()).unwrap(), 50.into());
+
+        let remaining = Remaining::from_order(&order(20)).unwrap();
+        assert_eq!(remaining.remaining(100.into()).unwrap(), 30.into());
+
+        // Executing more than the amended amount leaves nothing.
+        let remaining = Remaining::from_order(&order(60)).unwrap();
+        assert_eq!(remaining.remaining(100.into()).unwrap(), 0.into());
+    }
 }
@@ -18,7 +18,7 @@ use {
         arguments::{display_list, display_option},
         http_client,
     },
-    std::time::Duration,
+    std::{path::PathBuf, time::Duration},
 };
 
 #[derive(clap::Parser)]
@@ -353,6 +353,24 @@ pub struct Arguments {
     /// goerli).
     #[clap(long, env, action = clap::ArgAction::Set, default_value = "true")]
     pub enforce_correct_fees_for_partially_fillable_limit_orders: bool,
+
+    /// File the in flight orders get saved to after every settlement so they
+    /// survive restarts of the solver. If not specified, in flight orders are
+    /// only kept in memory.
+    #[clap(long, env)]
+    pub in_flight_orders_file: Option<PathBuf>,
+
+    /// Number of blocks after which restored in flight orders are discarded
+    /// since the api has seen them by then.
+    #[clap(long, env, default_value = "64")]
+    pub in_flight_orders_horizon: u64,
+
+    /// Number of blocks after which settled orders are no longer considered
+    /// in flight even if the api hasn't seen their settlement yet. This
+    /// prevents orders from being excluded forever when a settlement never
+    /// makes it on chain.
+    #[clap(long, env, default_value = "20")]
+    pub in_flight_orders_max_age: u64,
 }
 
 impl std::fmt::Display for Arguments {
@@ -408,6 +426,9 @@ impl std::fmt::Display for Arguments {
             enforce_correct_fees_for_partially_fillable_limit_orders,
             market_makable_token_list_update_interval,
             smallest_partial_fill,
+            in_flight_orders_file,
+            in_flight_orders_horizon,
+            in_flight_orders_max_age,
         } = self;
 
         write!(f, "{}", shared)?;
@@ -539,6 +560,13 @@ impl std::fmt::Display for Arguments {
             market_makable_token_list_update_interval
         )?;
         writeln!(f, "smallest_partial_fill: {}", smallest_partial_fill)?;
+        display_option(
+            f,
+            "in_flight_orders_file",
+            &in_flight_orders_file.as_ref().map(|file| file.display()),
+        )?;
+        writeln!(f, "in_flight_orders_horizon: {}", in_flight_orders_horizon)?;
+        writeln!(f, "in_flight_orders_max_age: {}", in_flight_orders_max_age)?;
 
         Ok(())
     }
@@ -2,7 +2,7 @@ use {
     crate::{
         auction_preprocessing,
         driver_logger::DriverLogger,
-        in_flight_orders::InFlightOrders,
+        in_flight_orders::{InFlightOrders, Submitter},
         liquidity_collector::{LiquidityCollecting, LiquidityCollector},
         metrics::SolverMetrics,
         order_balance_filter,
@@ -94,6 +94,7 @@ impl Driver {
         process_partially_fillable_limit_orders: bool,
         settlement_rater: Arc<dyn SettlementRating>,
         balance_fetcher: Arc<dyn BalanceFetching>,
+        in_flight_orders: InFlightOrders,
     ) -> Self {
         let gas_price_estimator =
             gas::Estimator::new(gas_price_estimator).with_gas_price_cap(gas_price_cap);
@@ -127,7 +128,7 @@ impl Driver {
             solution_submitter,
             run_id: 0,
             api,
-            in_flight_orders: InFlightOrders::default(),
+            in_flight_orders,
             settlement_ranker,
             logger,
             web3,
@@ -256,9 +257,11 @@ impl Driver {
         });
 
         let balance_start = Instant::now();
-        let balances =
+        let mut balances =
             order_balance_filter::fetch_balances(self.balance_fetcher.as_ref(), &auction.orders)
                 .await;
+        self.in_flight_orders
+            .reduce_balances(&mut balances, current_block_during_liquidity_fetch);
         tracing::debug!("fetching order balances took {:?}", balance_start.elapsed());
 
         tracing::info!(count =% auction.orders.len(), "got orders");
@@ -353,7 +356,14 @@ impl Driver {
             .await
             {
                 Ok(receipt) => {
-                    self.update_in_flight_orders(&receipt, &winning_settlement.settlement);
+                    self.update_in_flight_orders(
+                        &receipt,
+                        &winning_settlement.settlement,
+                        Submitter {
+                            account: address,
+                            nonce,
+                        },
+                    );
                     winning_solver.notify_auction_result(
                         auction_id,
                         AuctionResult::SubmittedOnchain(SubmissionResult::Success(
@@ -395,7 +405,12 @@ impl Driver {
     }
 
     /// Marks all orders in the winning settlement as "in flight".
-    fn update_in_flight_orders(&mut self, receipt: &TransactionReceipt, settlement: &Settlement) {
+    fn update_in_flight_orders(
+        &mut self,
+        receipt: &TransactionReceipt,
+        settlement: &Settlement,
+        submitter: Submitter,
+    ) {
         let block = match receipt.block_number {
             Some(block) => block.as_u64(),
             None => {
@@ -403,7 +418,8 @@ impl Driver {
                 0
             }
         };
-        self.in_flight_orders.mark_settled_orders(block, settlement);
+        self.in_flight_orders
+            .mark_settled_orders(block, settlement, submitter);
     }
 
     fn next_run_id(&mut self) -> u64 {
@@ -1,33 +1,146 @@
 use {
     crate::settlement::{Settlement, TradeExecution},
+    anyhow::{Context, Result},
     itertools::Itertools,
     model::{
         auction::Auction,
-        order::{Order, OrderKind, OrderUid},
+        order::{Order, OrderClass, OrderKind, OrderUid},
+    },
+    num::{BigInt, BigUint},
+    number::{
+        conversions::{u256_to_big_int, u256_to_big_uint},
+        serialization::HexOrDecimalU256,
+    },
+    primitive_types::{H160, U256},
+    serde::{Deserialize, Serialize},
+    serde_with::serde_as,
+    shared::{account_balances::Query, event_handling::MAX_REORG_BLOCK_COUNT},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        path::PathBuf,
     },
-    number::conversions::u256_to_big_uint,
-    std::collections::{BTreeMap, HashMap, HashSet},
 };
 
-#[derive(Debug, Clone)]
-struct PartiallyFilledOrder {
-    order: Order,
-    in_flight_trades: Vec<TradeExecution>,
+/// Identifies a settlement marked as in flight.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SubmissionId(u64);
+
+/// Account that submitted a settlement with the nonce of its transaction.
+#[serde_as]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Submitter {
+    pub account: H160,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub nonce: U256,
+}
+
+/// Orders and trades of a settlement that is in flight.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InFlightSettlement {
+    id: SubmissionId,
+    #[serde(default)]
+    submitter: Submitter,
+    orders: Vec<OrderUid>,
+    #[serde(default)]
+    trades: HashMap<OrderUid, Vec<InFlightTrade>>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InFlightTrade {
+    #[serde(flatten)]
+    execution: TradeExecution,
+    /// Fee solvers take from the sell amount of limit orders. Unlike the
+    /// signed fee it is already part of the executed sell amount.
+    #[serde(default)]
+    #[serde_as(as = "HexOrDecimalU256")]
+    surplus_fee: U256,
+    #[serde(default)]
+    partially_fillable: bool,
+    #[serde(default)]
+    owner: H160,
+}
+
+/// Current in flight orders for diagnostics.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InFlightSnapshot {
+    /// Orders considered in flight by the block their settlement was marked
+    /// in.
+    pub orders: BTreeMap<u64, Vec<OrderUid>>,
+    /// Executed amounts of all in flight trades of partially fillable orders.
+    pub partially_fillable: BTreeMap<OrderUid, InFlightAmounts>,
+}
+
+/// Aggregated executed amounts of in flight trades.
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InFlightAmounts {
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub sell_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub buy_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub fee_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub surplus_fee: U256,
+}
+
+/// Effect of filtering an auction by the in flight orders.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilterPreview {
+    /// Orders considered in flight.
+    pub in_flight: HashSet<OrderUid>,
+    /// Orders removed from the auction because they can't be executed
+    /// anymore.
+    pub dropped: Vec<OrderUid>,
+    /// Partially fillable orders whose executed amounts got increased by in
+    /// flight trades.
+    pub scaled: Vec<ScaledOrder>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaledOrder {
+    pub uid: OrderUid,
+    pub before: ExecutedAmounts,
+    pub after: ExecutedAmounts,
 }
 
-impl PartiallyFilledOrder {
-    pub fn order_with_remaining_amounts(&self) -> Order {
-        let mut updated_order = self.order.clone();
+/// Executed amounts that determine the remaining amount of an order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutedAmounts {
+    pub sell_amount_before_fees: U256,
+    pub buy_amount: BigUint,
+}
 
-        for trade in &self.in_flight_trades {
-            updated_order.metadata.executed_buy_amount += u256_to_big_uint(&trade.buy_amount);
-            updated_order.metadata.executed_sell_amount +=
-                u256_to_big_uint(&(trade.sell_amount + trade.fee_amount));
-            updated_order.metadata.executed_sell_amount_before_fees += trade.sell_amount;
-            updated_order.metadata.executed_fee_amount += trade.fee_amount;
+impl ExecutedAmounts {
+    fn of(order: &Order) -> Self {
+        Self {
+            sell_amount_before_fees: order.metadata.executed_sell_amount_before_fees,
+            buy_amount: order.metadata.executed_buy_amount.clone(),
         }
+    }
+}
 
-        updated_order
+/// Adds the amounts of in flight trades to the executed amounts of the order
+/// the same way the api does once it indexed the trades. The `Trade` event
+/// reports the executed sell amount including the signed fee and the signed
+/// fee itself, while surplus fees are tracked separately.
+fn add_in_flight_amounts<'a>(
+    order: &mut Order,
+    trades: impl IntoIterator<Item = &'a InFlightTrade>,
+) {
+    for trade in trades {
+        let execution = &trade.execution;
+        order.metadata.executed_buy_amount += u256_to_big_uint(&execution.buy_amount);
+        order.metadata.executed_sell_amount +=
+            u256_to_big_uint(&(execution.sell_amount + execution.fee_amount));
+        order.metadata.executed_sell_amount_before_fees += execution.sell_amount;
+        order.metadata.executed_fee_amount += execution.fee_amount;
+        order.metadata.executed_surplus_fee += trade.surplus_fee;
     }
 }
 
@@ -36,63 +149,299 @@ impl PartiallyFilledOrder {
 /// matched orders again leading to failures.
 #[derive(Default)]
 pub struct InFlightOrders {
-    /// Maps block to orders settled in that block.
+    /// Maps block to the settlements marked in that block. Their trades tell
+    /// how much liquidity of partially fillable orders and the settlement
+    /// contract's buffers is used.
+    settlements: BTreeMap<u64, Vec<InFlightSettlement>>,
+    next_submission: u64,
+    /// Settlements the api has seen. They are kept until they are reorg safe
+    /// and become in flight again if a reorg removes them from the chain.
+    confirmed: BTreeMap<u64, Vec<InFlightSettlement>>,
+    /// Highest latest settlement block reported by the api.
+    latest_settlement_block: u64,
+    /// File the state gets saved to after every settlement so it survives
+    /// restarts.
+    file: Option<PathBuf>,
+    /// Number of blocks after which orders are no longer considered in flight
+    /// even if the api hasn't seen the settlement (e.g. because the
+    /// transaction got dropped).
+    max_age: Option<u64>,
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "in_flight_orders")]
+struct Metrics {
+    /// Orders that were dropped from the in flight orders because the api
+    /// didn't see their settlement in time.
+    expired: prometheus::IntCounter,
+
+    /// Number of orders that are currently in flight.
+    orders: prometheus::IntGauge,
+
+    /// Orders removed from auctions because they are in flight.
+    filtered_orders: prometheus::IntCounter,
+
+    /// Partially fillable orders whose executed amounts got increased by in
+    /// flight trades.
+    scaled_orders: prometheus::IntCounter,
+
+    /// Liquidity orders removed from auctions or scaled down because of in
+    /// flight trades.
+    #[metric(labels("effect"))]
+    liquidity_orders: prometheus::IntCounterVec,
+
+    /// Reorgs detected by the latest settlement block decreasing.
+    reorgs: prometheus::IntCounter,
+
+    /// Number of blocks orders were in flight for before they got cleared.
+    #[metric(buckets(1, 2, 3, 4, 5, 10, 20, 50, 100))]
+    blocks_in_flight: prometheus::Histogram,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(observe::metrics::get_storage_registry()).unwrap()
+    }
+}
+
+/// Records for how many blocks the orders of cleared settlements were in
+/// flight.
+fn observe_blocks_in_flight(cleared: &BTreeMap<u64, Vec<InFlightSettlement>>, current_block: u64) {
+    let metric = &Metrics::get().blocks_in_flight;
+    for (block, settlements) in cleared {
+        let blocks = current_block.saturating_sub(*block) as f64;
+        settlements
+            .iter()
+            .flat_map(|settlement| &settlement.orders)
+            .for_each(|_| metric.observe(blocks));
+    }
+}
+
+/// Serialized state of the in flight orders. Unknown fields get ignored and
+/// missing fields default to empty so older and newer versions of the solver
+/// can read each other's state.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    #[serde(default)]
+    settlements: BTreeMap<u64, Vec<InFlightSettlement>>,
+    #[serde(default)]
+    next_submission: u64,
+    /// Orders by block as stored before in flight orders were tracked per
+    /// settlement. Only read to restore the state of older versions.
+    #[serde(default, skip_serializing)]
     in_flight: BTreeMap<u64, Vec<OrderUid>>,
-    /// Tracks in flight trades which use liquidity from partially fillable
-    /// orders.
-    in_flight_trades: HashMap<OrderUid, PartiallyFilledOrder>,
+    /// Trades by block as stored before in flight orders were tracked per
+    /// settlement.
+    #[serde(default, skip_serializing)]
+    trades: BTreeMap<u64, HashMap<OrderUid, Vec<InFlightTrade>>>,
+}
+
+impl Snapshot {
+    /// Converts the state of older versions to one settlement per block.
+    fn convert_legacy(&mut self) {
+        if !self.settlements.is_empty() {
+            return;
+        }
+        for (block, orders) in std::mem::take(&mut self.in_flight) {
+            let settlement = InFlightSettlement {
+                id: SubmissionId(self.next_submission),
+                submitter: Default::default(),
+                orders,
+                trades: self.trades.remove(&block).unwrap_or_default(),
+            };
+            self.next_submission += 1;
+            self.settlements.insert(block, vec![settlement]);
+        }
+    }
 }
 
 impl InFlightOrders {
+    /// Creates in flight orders that get saved to the file after every
+    /// settlement. The state of a previous run gets restored from the file
+    /// if it exists. Orders settled more than `horizon` blocks before
+    /// `current_block` are discarded since the api has seen them by now.
+    pub fn persisted(file: PathBuf, current_block: u64, horizon: u64) -> Self {
+        let mut in_flight_orders = match std::fs::read(&file) {
+            Ok(bytes) => Self::restore(&bytes, current_block.saturating_sub(horizon))
+                .unwrap_or_else(|err| {
+                    tracing::warn!(?err, ?file, "failed to restore in flight orders");
+                    Self::default()
+                }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                tracing::warn!(?err, ?file, "failed to read in flight orders");
+                Self::default()
+            }
+        };
+        in_flight_orders.file = Some(file);
+        in_flight_orders
+    }
+
+    /// Serializes the in flight orders so they can be restored later.
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let snapshot = Snapshot {
+            settlements: self.settlements.clone(),
+            next_submission: self.next_submission,
+            ..Default::default()
+        };
+        serde_json::to_vec(&snapshot).context("serialize in flight orders")
+    }
+
+    /// Restores serialized in flight orders. Orders settled before
+    /// `min_block` are discarded.
+    pub fn restore(bytes: &[u8], min_block: u64) -> Result<Self> {
+        let mut snapshot: Snapshot =
+            serde_json::from_slice(bytes).context("deserialize in flight orders")?;
+        snapshot.convert_legacy();
+        let settlements = snapshot.settlements.split_off(&min_block);
+        // Never hand out the id of a restored settlement again.
+        let next_submission = settlements
+            .values()
+            .flatten()
+            .map(|settlement| settlement.id.0 + 1)
+            .chain([snapshot.next_submission])
+            .max()
+            .unwrap_or_default();
+        Ok(Self {
+            settlements,
+            next_submission,
+            ..Default::default()
+        })
+    }
+
+    /// Stops considering orders in flight once they were settled more than
+    /// `blocks` blocks before the current auction.
+    pub fn with_max_age(mut self, blocks: u64) -> Self {
+        self.max_age = Some(blocks);
+        self
+    }
+
+    /// Writes the state to the file if the in flight orders are persisted.
+    /// The file gets replaced atomically so a crash can't corrupt it.
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let tmp = file.with_extension("tmp");
+        std::fs::write(&tmp, self.serialize()?).context("write in flight orders")?;
+        std::fs::rename(&tmp, file).context("replace in flight orders")
+    }
+
+    fn uids(&self) -> HashSet<OrderUid> {
+        self.settlements
+            .values()
+            .flatten()
+            .flat_map(|settlement| settlement.orders.iter().copied())
+            .collect()
+    }
+
+    fn trades(&self) -> impl Iterator<Item = (&OrderUid, &InFlightTrade)> {
+        self.settlements
+            .values()
+            .flatten()
+            .flat_map(|settlement| &settlement.trades)
+            .flat_map(|(uid, trades)| trades.iter().map(move |trade| (uid, trade)))
+    }
+
     /// Takes note of the new set of solvable orders and returns the ones that
     /// aren't in flight and scales down partially fillable orders if there
     /// are currently orders in-flight tapping into their executable
     /// amounts. Returns the set of order uids that are considered in
     /// flight.
     pub fn update_and_filter(&mut self, auction: &mut Auction) -> HashSet<OrderUid> {
-        let uids = |in_flight: &BTreeMap<u64, Vec<OrderUid>>| {
-            in_flight
-                .values()
-                .flatten()
-                .copied()
-                .collect::<HashSet<_>>()
-        };
-        let inflight_before = uids(&self.in_flight);
+        let inflight_before = self.uids();
         let orders_before = auction.orders.len();
 
+        // A decreasing latest settlement block means the api indexed a reorg
+        // so settlements it has seen on the old chain might be pending again.
+        if auction.latest_settlement_block < self.latest_settlement_block {
+            let reverted = self
+                .confirmed
+                .split_off(&(auction.latest_settlement_block + 1));
+            tracing::warn!(
+                previous_latest_settlement_block = %self.latest_settlement_block,
+                latest_settlement_block = %auction.latest_settlement_block,
+                reverted_settlements = %reverted.values().flatten().count(),
+                "latest settlement block decreased because of a reorg"
+            );
+            Metrics::get().reorgs.inc();
+            for (block, settlements) in reverted {
+                self.settlements
+                    .entry(block)
+                    .or_default()
+                    .extend(settlements);
+            }
+        }
+        self.latest_settlement_block = auction.latest_settlement_block;
+
         // If api has seen block X then trades starting at X + 1 are still in flight.
-        self.in_flight = self
-            .in_flight
+        let recent = self
+            .settlements
             .split_off(&(auction.latest_settlement_block + 1));
+        let seen = std::mem::replace(&mut self.settlements, recent);
+        observe_blocks_in_flight(&seen, auction.block);
+        for (block, settlements) in seen {
+            self.confirmed.entry(block).or_default().extend(settlements);
+        }
+        self.confirmed = self.confirmed.split_off(
+            &auction
+                .latest_settlement_block
+                .saturating_sub(MAX_REORG_BLOCK_COUNT),
+        );
+        if let Some(max_age) = self.max_age {
+            let recent = self
+                .settlements
+                .split_off(&auction.block.saturating_sub(max_age));
+            let expired = std::mem::replace(&mut self.settlements, recent);
+            observe_blocks_in_flight(&expired, auction.block);
+            let expired = expired
+                .values()
+                .flatten()
+                .flat_map(|settlement| &settlement.orders)
+                .collect::<Vec<_>>();
+            if !expired.is_empty() {
+                tracing::warn!(
+                    auction_block = %auction.block,
+                    latest_settlement_block = %auction.latest_settlement_block,
+                    ?expired,
+                    "in flight orders expired before the api saw their settlement"
+                );
+                Metrics::get().expired.inc_by(expired.len() as u64);
+            }
+        }
 
-        let in_flight = uids(&self.in_flight);
-        self.in_flight_trades
-            .retain(|uid, _| in_flight.contains(uid));
-
-        auction.orders.iter_mut().for_each(|order| {
-            let uid = &order.metadata.uid;
+        let liquidity_orders = auction
+            .orders
+            .iter()
+            .filter(|order| order.metadata.class == OrderClass::Liquidity)
+            .map(|order| order.metadata.uid)
+            .collect::<HashSet<_>>();
+        let FilterPreview {
+            in_flight,
+            dropped,
+            scaled,
+        } = self.filter(
+            auction.block,
+            auction.latest_settlement_block,
+            &mut auction.orders,
+        );
 
-            if order.data.partially_fillable {
-                if let Some(trades) = self.in_flight_trades.get(uid) {
-                    *order = trades.order_with_remaining_amounts();
-                }
-            } else if in_flight.contains(uid) {
-                // fill-or-kill orders can only be used once and there is already a trade in
-                // flight for this one => Modify it such that it gets filtered
-                // out in the next step.
-                order.metadata.executed_buy_amount = u256_to_big_uint(&order.data.buy_amount);
-                order.metadata.executed_sell_amount_before_fees = order.data.sell_amount;
-            }
-        });
-        auction.orders.retain(|order| match order.data.kind {
-            OrderKind::Sell => {
-                u256_to_big_uint(&order.data.sell_amount)
-                    > u256_to_big_uint(&order.metadata.executed_sell_amount_before_fees)
-            }
-            OrderKind::Buy => {
-                u256_to_big_uint(&order.data.buy_amount) > order.metadata.executed_buy_amount
-            }
-        });
+        let metrics = Metrics::get();
+        metrics.orders.set(in_flight.len() as i64);
+        metrics.filtered_orders.inc_by(dropped.len() as u64);
+        metrics.scaled_orders.inc_by(scaled.len() as u64);
+        let liquidity_count = |uids: &mut dyn Iterator<Item = &OrderUid>| {
+            uids.filter(|uid| liquidity_orders.contains(uid)).count() as u64
+        };
+        metrics
+            .liquidity_orders
+            .with_label_values(&["dropped"])
+            .inc_by(liquidity_count(&mut dropped.iter()));
+        metrics
+            .liquidity_orders
+            .with_label_values(&["scaled"])
+            .inc_by(liquidity_count(&mut scaled.iter().map(|order| &order.uid)));
 
         tracing::trace!(
             auction_block = %auction.block,
@@ -109,26 +458,249 @@ impl InFlightOrders {
         in_flight
     }
 
+    /// Returns what [`Self::update_and_filter`] would do to the auction
+    /// without changing the auction or the tracked in flight orders.
+    pub fn preview(&self, auction: &Auction) -> FilterPreview {
+        let mut orders = auction.orders.clone();
+        self.filter(auction.block, auction.latest_settlement_block, &mut orders)
+    }
+
+    /// Scales down partially fillable orders by the amounts of their in
+    /// flight trades and drops the orders that can't be executed anymore.
+    /// Only considers settlements that are still in flight for the given
+    /// blocks, so it has the same effect before and after cleaning up the
+    /// tracked settlements.
+    fn filter(
+        &self,
+        block: u64,
+        latest_settlement_block: u64,
+        orders: &mut Vec<Order>,
+    ) -> FilterPreview {
+        let min_block = match self.max_age {
+            Some(max_age) => (latest_settlement_block + 1).max(block.saturating_sub(max_age)),
+            None => latest_settlement_block + 1,
+        };
+        let reverted = (latest_settlement_block < self.latest_settlement_block)
+            .then(|| self.confirmed.range(min_block..))
+            .into_iter()
+            .flatten();
+        let settlements = self
+            .settlements
+            .range(min_block..)
+            .chain(reverted)
+            .flat_map(|(_, settlements)| settlements)
+            .collect::<Vec<_>>();
+        let in_flight = settlements
+            .iter()
+            .flat_map(|settlement| settlement.orders.iter().copied())
+            .collect::<HashSet<_>>();
+        let trades = settlements
+            .iter()
+            .flat_map(|settlement| &settlement.trades)
+            .flat_map(|(uid, trades)| trades.iter().map(move |trade| (uid, trade)))
+            .into_group_map();
+
+        let mut scaled = Vec::new();
+        orders.iter_mut().for_each(|order| {
+            let uid = order.metadata.uid;
+
+            if order.data.partially_fillable {
+                if let Some(trades) = trades.get(&uid) {
+                    let before = ExecutedAmounts::of(order);
+                    add_in_flight_amounts(order, trades.iter().copied());
+                    scaled.push(ScaledOrder {
+                        uid,
+                        before,
+                        after: ExecutedAmounts::of(order),
+                    });
+                }
+            } else if in_flight.contains(&uid) {
+                // fill-or-kill orders can only be used once and there is already a trade in
+                // flight for this one => Modify it such that it gets filtered
+                // out in the next step.
+                order.metadata.executed_buy_amount = u256_to_big_uint(&order.data.buy_amount);
+                order.metadata.executed_sell_amount_before_fees = order.data.sell_amount;
+            }
+        });
+        let mut dropped = Vec::new();
+        orders.retain(|order| {
+            let executable = match order.data.kind {
+                OrderKind::Sell => {
+                    u256_to_big_uint(&order.data.sell_amount)
+                        > u256_to_big_uint(&order.metadata.executed_sell_amount_before_fees)
+                }
+                OrderKind::Buy => {
+                    u256_to_big_uint(&order.data.buy_amount) > order.metadata.executed_buy_amount
+                }
+            };
+            if !executable {
+                dropped.push(order.metadata.uid);
+            }
+            executable
+        });
+
+        FilterPreview {
+            in_flight,
+            dropped,
+            scaled,
+        }
+    }
+
     /// Tracks all in_flight orders and how much of the executable amount of
     /// partially fillable orders is currently used in in-flight trades.
-    pub fn mark_settled_orders(&mut self, block: u64, settlement: &Settlement) {
-        let uids = settlement.traded_orders().map(|order| order.metadata.uid);
-        self.in_flight.entry(block).or_default().extend(uids);
+    /// Trades of several settlements of the same order add up. Returns the id
+    /// the settlement can be unmarked with.
+    pub fn mark_settled_orders(
+        &mut self,
+        block: u64,
+        settlement: &Settlement,
+        submitter: Submitter,
+    ) -> SubmissionId {
+        let id = SubmissionId(self.next_submission);
+        self.next_submission += 1;
 
+        let mut trades = HashMap::<_, Vec<_>>::new();
         settlement
             .trades()
             .zip(settlement.trade_executions())
-            .filter(|(trade, _)| trade.order.data.partially_fillable)
-            .into_group_map_by(|(trade, _)| trade.order.metadata.uid)
-            .into_iter()
-            .for_each(|(uid, trades)| {
-                let most_recent_data = PartiallyFilledOrder {
-                    order: trades[0].0.order.clone(),
-                    in_flight_trades: trades.into_iter().map(|(_, execution)| execution).collect(),
-                };
-                // always overwrite existing data with the most recent data
-                self.in_flight_trades.insert(uid, most_recent_data);
+            .for_each(|(trade, execution)| {
+                trades
+                    .entry(trade.order.metadata.uid)
+                    .or_default()
+                    .push(InFlightTrade {
+                        execution,
+                        surplus_fee: trade.surplus_fee().unwrap_or_default(),
+                        partially_fillable: trade.order.data.partially_fillable,
+                        owner: trade.order.metadata.owner,
+                    });
             });
+        self.settlements
+            .entry(block)
+            .or_default()
+            .push(InFlightSettlement {
+                id,
+                submitter,
+                orders: settlement
+                    .traded_orders()
+                    .map(|order| order.metadata.uid)
+                    .collect(),
+                trades,
+            });
+        Metrics::get().orders.set(self.uids().len() as i64);
+
+        if let Err(err) = self.save() {
+            tracing::warn!(?err, "failed to persist in flight orders");
+        }
+        id
+    }
+
+    /// Returns by how much the settlement contract's token balances change
+    /// once the in flight settlements are executed. The settlement contract
+    /// receives the sell amounts and fees of the traded orders and pays out
+    /// their buy amounts. Liquidity interactions are not accounted for, so
+    /// these are the buffer changes of fully internalized settlements.
+    pub fn pending_balance_deltas(&self) -> HashMap<H160, BigInt> {
+        let mut deltas = HashMap::<H160, BigInt>::new();
+        for (_, trade) in self.trades() {
+            let trade = &trade.execution;
+            *deltas.entry(trade.sell_token).or_default() +=
+                u256_to_big_int(&(trade.sell_amount + trade.fee_amount));
+            *deltas.entry(trade.buy_token).or_default() -= u256_to_big_int(&trade.buy_amount);
+        }
+        deltas
+    }
+
+    /// Reduces the sell token balances of order owners by the amounts their
+    /// in flight trades transfer out. Only settlements marked after
+    /// `balance_block` are accounted for since the balances already reflect
+    /// the earlier ones. Orders exceeding the remaining balances then get
+    /// filtered or scaled down by
+    /// [`crate::order_balance_filter::balance_orders`].
+    pub fn reduce_balances(&self, balances: &mut HashMap<Query, U256>, balance_block: u64) {
+        let mut in_flight = HashMap::<(H160, H160), U256>::new();
+        for trade in self
+            .settlements
+            .range(balance_block + 1..)
+            .flat_map(|(_, settlements)| settlements)
+            .flat_map(|settlement| settlement.trades.values())
+            .flatten()
+        {
+            let execution = &trade.execution;
+            let amount = in_flight
+                .entry((trade.owner, execution.sell_token))
+                .or_default();
+            *amount =
+                amount.saturating_add(execution.sell_amount.saturating_add(execution.fee_amount));
+        }
+        for (query, balance) in balances.iter_mut() {
+            if let Some(amount) = in_flight.get(&(query.owner, query.token)) {
+                *balance = balance.saturating_sub(*amount);
+            }
+        }
+    }
+
+    /// Returns the currently tracked in flight orders.
+    pub fn snapshot(&self) -> InFlightSnapshot {
+        let mut partially_fillable = BTreeMap::<_, InFlightAmounts>::new();
+        for (uid, trade) in self.trades().filter(|(_, trade)| trade.partially_fillable) {
+            let amounts = partially_fillable.entry(*uid).or_default();
+            amounts.sell_amount = amounts
+                .sell_amount
+                .saturating_add(trade.execution.sell_amount);
+            amounts.buy_amount = amounts
+                .buy_amount
+                .saturating_add(trade.execution.buy_amount);
+            amounts.fee_amount = amounts
+                .fee_amount
+                .saturating_add(trade.execution.fee_amount);
+            amounts.surplus_fee = amounts.surplus_fee.saturating_add(trade.surplus_fee);
+        }
+        InFlightSnapshot {
+            orders: self
+                .settlements
+                .iter()
+                .map(|(block, settlements)| {
+                    let orders = settlements
+                        .iter()
+                        .flat_map(|settlement| settlement.orders.iter().copied())
+                        .collect();
+                    (*block, orders)
+                })
+                .collect(),
+            partially_fillable,
+        }
+    }
+
+    /// Stops considering the orders of the settlement submitted with the nonce
+    /// in flight. The submission layer calls this when it observes that the
+    /// nonce got used by a different transaction so the settlement can't be
+    /// executed anymore.
+    pub fn clear_for_nonce(&mut self, account: H160, nonce: U256) {
+        let submitter = Submitter { account, nonce };
+        self.settlements.retain(|_, settlements| {
+            settlements.retain(|settlement| settlement.submitter != submitter);
+            !settlements.is_empty()
+        });
+
+        if let Err(err) = self.save() {
+            tracing::warn!(?err, "failed to persist in flight orders");
+        }
+    }
+
+    /// Stops considering the orders of a settlement marked in the given block
+    /// in flight, e.g. because its transaction reverted or got replaced.
+    /// Other settlements of the same orders are kept.
+    pub fn unmark_settlement(&mut self, block: u64, submission: SubmissionId) {
+        if let Some(settlements) = self.settlements.get_mut(&block) {
+            settlements.retain(|settlement| settlement.id != submission);
+            if settlements.is_empty() {
+                self.settlements.remove(&block);
+            }
+        }
+
+        if let Err(err) = self.save() {
+            tracing::warn!(?err, "failed to persist in flight orders");
+        }
     }
 }
 
@@ -136,12 +708,39 @@ impl InFlightOrders {
 mod tests {
     use {
         super::*,
-        crate::settlement::{SettlementEncoder, Trade},
-        maplit::hashmap,
-        model::order::{Order, OrderData, OrderKind, OrderMetadata},
+        crate::{
+            order_balance_filter::balance_orders,
+            settlement::{SettlementEncoder, Trade},
+        },
+        maplit::{btreemap, hashmap},
+        model::order::{Order, OrderClass, OrderData, OrderKind, OrderMetadata},
+        num::BigRational,
         primitive_types::H160,
+        shared::external_prices::ExternalPrices,
+        std::path::Path,
     };
 
+    /// File in the temporary directory that gets removed once dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{name}-{}.json", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            Self(path)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
     #[test]
     fn test() {
         let token0 = H160::from_low_u64_be(0);
@@ -208,8 +807,38 @@ mod tests {
             ..Default::default()
         };
 
+        let metrics = Metrics::get();
+        let filtered_before = metrics.filtered_orders.get();
+        let scaled_before = metrics.scaled_orders.get();
+        let cleared_before = metrics.blocks_in_flight.get_sample_count();
+
         let mut inflight = InFlightOrders::default();
-        inflight.mark_settled_orders(1, &settlement);
+        inflight.mark_settled_orders(1, &settlement, Default::default());
+        assert_eq!(
+            inflight.snapshot(),
+            InFlightSnapshot {
+                orders: btreemap! {
+                    1 => vec![
+                        OrderUid::from_integer(1),
+                        OrderUid::from_integer(3),
+                        OrderUid::from_integer(2),
+                        OrderUid::from_integer(2),
+                    ],
+                },
+                partially_fillable: btreemap! {
+                    OrderUid::from_integer(2) => InFlightAmounts {
+                        sell_amount: 70u8.into(),
+                        buy_amount: 70u8.into(),
+                        ..Default::default()
+                    },
+                    OrderUid::from_integer(3) => InFlightAmounts {
+                        sell_amount: 20u8.into(),
+                        buy_amount: 20u8.into(),
+                        ..Default::default()
+                    },
+                },
+            }
+        );
         let mut order0 = fill_or_kill.clone();
         order0.metadata.uid = OrderUid::from_integer(0);
         let mut auction = Auction {
@@ -263,6 +892,904 @@ mod tests {
         // block there is nothing left to filter solvable orders by => keep all
         // orders unaltered
         assert_eq!(filtered.len(), 4);
+
+        // Other tests update the metrics concurrently.
+        assert!(metrics.filtered_orders.get() - filtered_before >= 4);
+        assert!(metrics.scaled_orders.get() - scaled_before >= 4);
+        assert!(metrics.blocks_in_flight.get_sample_count() - cleared_before >= 4);
+    }
+
+    #[test]
+    fn in_flight_orders_survive_restarts() {
+        let file = TempFile::new("in_flight_orders_survive_restarts");
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let fill_or_kill = Order {
+            data: OrderData {
+                sell_token: token0,
+                buy_token: token1,
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut partially_fillable = fill_or_kill.clone();
+        partially_fillable.data.partially_fillable = true;
+        partially_fillable.metadata.uid = OrderUid::from_integer(2);
+        let settlement = Settlement {
+            encoder: SettlementEncoder::with_trades(
+                hashmap! {token0 => 1u8.into(), token1 => 1u8.into()},
+                vec![
+                    Trade {
+                        order: fill_or_kill.clone(),
+                        executed_amount: 100u8.into(),
+                        ..Default::default()
+                    },
+                    Trade {
+                        order: partially_fillable.clone(),
+                        executed_amount: 50u8.into(),
+                        ..Default::default()
+                    },
+                ],
+            ),
+            ..Default::default()
+        };
+        let auction = Auction {
+            block: 12,
+            latest_settlement_block: 9,
+            orders: vec![fill_or_kill, partially_fillable],
+            ..Default::default()
+        };
+
+        let mut inflight = InFlightOrders::persisted(file.path().to_owned(), 10, 5);
+        inflight.mark_settled_orders(10, &settlement, Default::default());
+        drop(inflight);
+
+        // The restarted solver still knows about the in flight trades.
+        let mut inflight = InFlightOrders::persisted(file.path().to_owned(), 12, 5);
+        let mut filtered = auction.clone();
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(filtered.orders.len(), 1);
+        assert_eq!(filtered.orders[0].metadata.uid, OrderUid::from_integer(2));
+        assert_eq!(filtered.orders[0].metadata.executed_buy_amount, 50u8.into());
+
+        // Settlements older than the horizon are forgotten.
+        let mut inflight = InFlightOrders::persisted(file.path().to_owned(), 16, 5);
+        let mut filtered = auction.clone();
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(filtered.orders, auction.orders);
+    }
+
+    #[test]
+    fn in_flight_orders_expire() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let fill_or_kill = Order {
+            data: OrderData {
+                sell_token: token0,
+                buy_token: token1,
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut partially_fillable = fill_or_kill.clone();
+        partially_fillable.data.partially_fillable = true;
+        partially_fillable.metadata.uid = OrderUid::from_integer(2);
+        let settlement = Settlement {
+            encoder: SettlementEncoder::with_trades(
+                hashmap! {token0 => 1u8.into(), token1 => 1u8.into()},
+                vec![
+                    Trade {
+                        order: fill_or_kill.clone(),
+                        executed_amount: 100u8.into(),
+                        ..Default::default()
+                    },
+                    Trade {
+                        order: partially_fillable.clone(),
+                        executed_amount: 50u8.into(),
+                        ..Default::default()
+                    },
+                ],
+            ),
+            ..Default::default()
+        };
+        let auction = |block| Auction {
+            block,
+            latest_settlement_block: 9,
+            orders: vec![fill_or_kill.clone(), partially_fillable.clone()],
+            ..Default::default()
+        };
+
+        let mut inflight = InFlightOrders::default().with_max_age(5);
+        inflight.mark_settled_orders(10, &settlement, Default::default());
+
+        // The api never sees the settlement but the orders are in flight
+        // until they get too old.
+        let mut filtered = auction(15);
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(filtered.orders.len(), 1);
+        assert_eq!(filtered.orders[0].metadata.executed_buy_amount, 50u8.into());
+
+        let mut filtered = auction(16);
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(filtered.orders, auction(16).orders);
+        assert!(inflight.settlements.is_empty());
+    }
+
+    #[test]
+    fn unmarked_settlements_keep_other_in_flight_trades() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let fill_or_kill = Order {
+            data: OrderData {
+                sell_token: token0,
+                buy_token: token1,
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut partially_fillable = fill_or_kill.clone();
+        partially_fillable.data.partially_fillable = true;
+        partially_fillable.metadata.uid = OrderUid::from_integer(2);
+        let settlement = |trades| Settlement {
+            encoder: SettlementEncoder::with_trades(
+                hashmap! {token0 => 1u8.into(), token1 => 1u8.into()},
+                trades,
+            ),
+            ..Default::default()
+        };
+        let auction = Auction {
+            block: 12,
+            latest_settlement_block: 9,
+            orders: vec![fill_or_kill.clone(), partially_fillable.clone()],
+            ..Default::default()
+        };
+
+        let mut inflight = InFlightOrders::default();
+        // The first settlement fails while the second one is still pending.
+        let failed = settlement(vec![
+            Trade {
+                order: fill_or_kill.clone(),
+                executed_amount: 100u8.into(),
+                ..Default::default()
+            },
+            Trade {
+                order: partially_fillable.clone(),
+                executed_amount: 20u8.into(),
+                ..Default::default()
+            },
+        ]);
+        let failed = inflight.mark_settled_orders(10, &failed, Default::default());
+        let pending = settlement(vec![Trade {
+            order: partially_fillable.clone(),
+            executed_amount: 30u8.into(),
+            ..Default::default()
+        }]);
+        let pending = inflight.mark_settled_orders(11, &pending, Default::default());
+
+        let mut filtered = auction.clone();
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(filtered.orders.len(), 1);
+        assert_eq!(filtered.orders[0].metadata.executed_buy_amount, 50u8.into());
+
+        inflight.unmark_settlement(10, failed);
+        let mut filtered = auction.clone();
+        let in_flight = inflight.update_and_filter(&mut filtered);
+        assert_eq!(in_flight, HashSet::from([partially_fillable.metadata.uid]));
+        assert_eq!(filtered.orders.len(), 2);
+        assert_eq!(filtered.orders[0], fill_or_kill);
+        assert_eq!(filtered.orders[1].metadata.executed_buy_amount, 30u8.into());
+        assert_eq!(
+            filtered.orders[1].metadata.executed_sell_amount_before_fees,
+            30u8.into()
+        );
+
+        inflight.unmark_settlement(11, pending);
+        assert!(inflight.settlements.is_empty());
+    }
+
+    #[test]
+    fn settlements_of_the_same_order_add_up() {
+        let order = Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                partially_fillable: true,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settlement = Settlement::with_default_prices(vec![Trade {
+            order: order.clone(),
+            executed_amount: 30u8.into(),
+            ..Default::default()
+        }]);
+        let auction = Auction {
+            block: 2,
+            orders: vec![order],
+            ..Default::default()
+        };
+
+        // Two settlements in the same block each take 30% of the order.
+        let mut inflight = InFlightOrders::default();
+        let first = inflight.mark_settled_orders(1, &settlement, Default::default());
+        let second = inflight.mark_settled_orders(1, &settlement, Default::default());
+        assert_ne!(first, second);
+
+        let mut filtered = auction.clone();
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(
+            filtered.orders[0].metadata.executed_sell_amount_before_fees,
+            60u8.into()
+        );
+
+        // Clearing one of them only removes its own contribution.
+        inflight.unmark_settlement(1, first);
+        let mut filtered = auction.clone();
+        inflight.update_and_filter(&mut filtered);
+        assert_eq!(
+            filtered.orders[0].metadata.executed_sell_amount_before_fees,
+            30u8.into()
+        );
+    }
+
+    #[test]
+    fn pending_balance_deltas_of_settlements_draining_the_same_buffer() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let order = |uid: u8, sell_amount: u8| Order {
+            data: OrderData {
+                sell_token: token0,
+                buy_token: token1,
+                sell_amount: sell_amount.into(),
+                buy_amount: (sell_amount / 2).into(),
+                fee_amount: 1u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(uid.into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settlement = |order: Order| {
+            let trade = Trade {
+                executed_amount: order.data.sell_amount,
+                order,
+                ..Default::default()
+            };
+            Settlement {
+                encoder: SettlementEncoder::with_trades(
+                    hashmap! {token0 => 1u8.into(), token1 => 2u8.into()},
+                    vec![trade],
+                ),
+                ..Default::default()
+            }
+        };
+
+        let mut inflight = InFlightOrders::default();
+        assert!(inflight.pending_balance_deltas().is_empty());
+        inflight.mark_settled_orders(10, &settlement(order(1, 100)), Default::default());
+        inflight.mark_settled_orders(11, &settlement(order(2, 50)), Default::default());
+        assert_eq!(
+            inflight.pending_balance_deltas(),
+            hashmap! {token0 => BigInt::from(152), token1 => BigInt::from(-75)}
+        );
+
+        // Deltas disappear once the api has seen the settlement.
+        inflight.update_and_filter(&mut Auction {
+            block: 12,
+            latest_settlement_block: 10,
+            ..Default::default()
+        });
+        assert_eq!(
+            inflight.pending_balance_deltas(),
+            hashmap! {token0 => BigInt::from(51), token1 => BigInt::from(-25)}
+        );
+    }
+
+    #[test]
+    fn in_flight_amounts_match_indexed_trades() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let order =
+            |uid: u32, kind, sell_amount: u8, buy_amount: u8, fee_amount: u8, class| Order {
+                data: OrderData {
+                    sell_token: token0,
+                    buy_token: token1,
+                    sell_amount: sell_amount.into(),
+                    buy_amount: buy_amount.into(),
+                    fee_amount: fee_amount.into(),
+                    kind,
+                    partially_fillable: true,
+                    ..Default::default()
+                },
+                metadata: OrderMetadata {
+                    uid: OrderUid::from_integer(uid),
+                    class,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+        let market_sell = order(1, OrderKind::Sell, 100, 40, 10, OrderClass::Market);
+        let limit_sell = order(2, OrderKind::Sell, 100, 40, 0, OrderClass::Limit);
+        let limit_buy = order(3, OrderKind::Buy, 200, 50, 0, OrderClass::Limit);
+        let settlement = Settlement {
+            encoder: SettlementEncoder::with_trades(
+                hashmap! {token0 => 1u8.into(), token1 => 2u8.into()},
+                vec![
+                    Trade {
+                        order: market_sell.clone(),
+                        executed_amount: 50u8.into(),
+                        ..Default::default()
+                    },
+                    Trade {
+                        order: limit_sell.clone(),
+                        executed_amount: 40u8.into(),
+                        fee: 2u8.into(),
+                    },
+                    Trade {
+                        order: limit_buy.clone(),
+                        executed_amount: 20u8.into(),
+                        fee: 3u8.into(),
+                    },
+                ],
+            ),
+            ..Default::default()
+        };
+        let mut auction = Auction {
+            block: 2,
+            orders: vec![market_sell, limit_sell, limit_buy],
+            ..Default::default()
+        };
+
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(1, &settlement, Default::default());
+        inflight.update_and_filter(&mut auction);
+
+        // Amounts as reported by the api once the `Trade` events are indexed:
+        // (executed sell incl. signed fee, executed sell before fees, signed
+        // fee, surplus fee, executed buy)
+        let executed = |order: &Order| {
+            (
+                order.metadata.executed_sell_amount.clone(),
+                order.metadata.executed_sell_amount_before_fees,
+                order.metadata.executed_fee_amount,
+                order.metadata.executed_surplus_fee,
+                order.metadata.executed_buy_amount.clone(),
+            )
+        };
+        // The signed fee is paid on top of the sell amount.
+        assert_eq!(
+            executed(&auction.orders[0]),
+            (
+                55u8.into(),
+                50u8.into(),
+                5u8.into(),
+                0u8.into(),
+                25u8.into()
+            )
+        );
+        // Surplus fees are part of the sell amount and not signed fees.
+        assert_eq!(
+            executed(&auction.orders[1]),
+            (
+                42u8.into(),
+                42u8.into(),
+                0u8.into(),
+                2u8.into(),
+                20u8.into()
+            )
+        );
+        assert_eq!(
+            executed(&auction.orders[2]),
+            (
+                43u8.into(),
+                43u8.into(),
+                0u8.into(),
+                3u8.into(),
+                20u8.into()
+            )
+        );
+    }
+
+    #[test]
+    fn in_flight_trades_reduce_owner_balances() {
+        let owner = H160([1; 20]);
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let order = |uid: u32, partially_fillable: bool| Order {
+            data: OrderData {
+                sell_token: token0,
+                buy_token: token1,
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                partially_fillable,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(uid),
+                owner,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let in_flight = order(1, false);
+        let fill_or_kill = order(2, false);
+        let partially_fillable = order(3, true);
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(
+            11,
+            &Settlement::with_default_prices(vec![Trade {
+                order: in_flight.clone(),
+                executed_amount: 100u8.into(),
+                ..Default::default()
+            }]),
+            Default::default(),
+        );
+        let external_prices = ExternalPrices::new(
+            token0,
+            hashmap! {
+                token0 => BigRational::from_float(1.).unwrap(),
+                token1 => BigRational::from_float(1.).unwrap(),
+            },
+        )
+        .unwrap();
+        let query = Query::from_order(&fill_or_kill);
+
+        // The balances were fetched before the settlement got mined so the
+        // owner can only afford half of one of the remaining orders.
+        let mut balances = hashmap! {query.clone() => U256::from(150)};
+        inflight.reduce_balances(&mut balances, 10);
+        assert_eq!(balances[&query], 50.into());
+        let orders = balance_orders(
+            vec![fill_or_kill.clone(), partially_fillable.clone()],
+            &mut balances,
+            None,
+            &external_prices,
+        );
+        assert_eq!(orders.len(), 1);
+        assert_eq!(
+            orders[0].order.metadata.uid,
+            partially_fillable.metadata.uid
+        );
+        assert_eq!(orders[0].available_sell_token_balance, 50.into());
+
+        // Balances fetched after the settlement already account for it.
+        let mut balances = hashmap! {query.clone() => U256::from(150)};
+        inflight.reduce_balances(&mut balances, 11);
+        assert_eq!(balances[&query], 150.into());
+    }
+
+    #[test]
+    fn reorged_settlements_are_in_flight_again() {
+        let order = Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settlement = Settlement::with_default_prices(vec![Trade {
+            order: order.clone(),
+            executed_amount: 100u8.into(),
+            ..Default::default()
+        }]);
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(10, &settlement, Default::default());
+        let mut filtered_orders = |latest_settlement_block| {
+            let mut auction = Auction {
+                block: 11,
+                latest_settlement_block,
+                orders: vec![order.clone()],
+                ..Default::default()
+            };
+            inflight.update_and_filter(&mut auction);
+            auction.orders.len()
+        };
+
+        assert_eq!(filtered_orders(9), 0);
+        // The api saw the settlement.
+        assert_eq!(filtered_orders(10), 1);
+        // A reorg removed the settlement from the chain so it's pending again.
+        assert_eq!(filtered_orders(9), 0);
+        assert_eq!(filtered_orders(9), 0);
+        assert_eq!(filtered_orders(10), 1);
+
+        // Settlements are forgotten once they are reorg safe.
+        assert_eq!(filtered_orders(10 + MAX_REORG_BLOCK_COUNT + 1), 1);
+        assert_eq!(filtered_orders(9), 1);
+    }
+
+    #[test]
+    fn in_flight_amounts_add_to_fresh_api_executions() {
+        let mut order = Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                partially_fillable: true,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(
+            10,
+            &Settlement::with_default_prices(vec![Trade {
+                order: order.clone(),
+                executed_amount: 30u8.into(),
+                ..Default::default()
+            }]),
+            Default::default(),
+        );
+
+        // Meanwhile another settlement the api already indexed filled 20%.
+        // Only the amounts of the in flight trades are added to the order of
+        // the auction instead of replacing it with the stale copy that got
+        // settled.
+        order.metadata.executed_sell_amount = 20u8.into();
+        order.metadata.executed_sell_amount_before_fees = 20u8.into();
+        order.metadata.executed_buy_amount = 20u8.into();
+        order.metadata.full_app_data = Some("fresh".to_owned());
+        let mut auction = Auction {
+            block: 11,
+            latest_settlement_block: 9,
+            orders: vec![order.clone()],
+            ..Default::default()
+        };
+        inflight.update_and_filter(&mut auction);
+
+        let mut expected = order;
+        expected.metadata.executed_sell_amount = 50u8.into();
+        expected.metadata.executed_sell_amount_before_fees = 50u8.into();
+        expected.metadata.executed_buy_amount = 50u8.into();
+        assert_eq!(auction.orders, vec![expected]);
+    }
+
+    #[test]
+    fn preview_matches_update_and_filter() {
+        let order = |uid: u32, partially_fillable: bool| Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                partially_fillable,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(uid),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settled = |order: &Order, executed_amount: u8| {
+            Settlement::with_default_prices(vec![Trade {
+                order: order.clone(),
+                executed_amount: executed_amount.into(),
+                ..Default::default()
+            }])
+        };
+        let fill_or_kill = order(1, false);
+        let partially_fillable = order(2, true);
+        let old = order(3, false);
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(8, &settled(&old, 100), Default::default());
+        inflight.mark_settled_orders(10, &settled(&fill_or_kill, 100), Default::default());
+        inflight.mark_settled_orders(10, &settled(&partially_fillable, 40), Default::default());
+        let auction = Auction {
+            block: 11,
+            latest_settlement_block: 9,
+            orders: vec![fill_or_kill, partially_fillable, old, order(4, false)],
+            ..Default::default()
+        };
+
+        let preview = inflight.preview(&auction);
+        assert_eq!(
+            preview,
+            FilterPreview {
+                in_flight: HashSet::from([OrderUid::from_integer(1), OrderUid::from_integer(2)]),
+                dropped: vec![OrderUid::from_integer(1)],
+                scaled: vec![ScaledOrder {
+                    uid: OrderUid::from_integer(2),
+                    before: ExecutedAmounts {
+                        sell_amount_before_fees: 0u8.into(),
+                        buy_amount: 0u8.into(),
+                    },
+                    after: ExecutedAmounts {
+                        sell_amount_before_fees: 40u8.into(),
+                        buy_amount: 40u8.into(),
+                    },
+                }],
+            }
+        );
+        // The preview doesn't clean up settlements the api has seen.
+        assert!(inflight.settlements.contains_key(&8));
+
+        let mut filtered = auction.clone();
+        let in_flight = inflight.update_and_filter(&mut filtered);
+        assert_eq!(in_flight, preview.in_flight);
+        assert_eq!(
+            filtered
+                .orders
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>(),
+            vec![
+                OrderUid::from_integer(2),
+                OrderUid::from_integer(3),
+                OrderUid::from_integer(4)
+            ]
+        );
+        assert_eq!(
+            filtered.orders[0].metadata.executed_sell_amount_before_fees,
+            40u8.into()
+        );
+        assert!(!inflight.settlements.contains_key(&8));
+        assert_eq!(inflight.preview(&auction), preview);
+    }
+
+    #[test]
+    fn clears_settlements_of_consumed_nonces() {
+        let order = |uid: u32| Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(uid),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settlement = |orders: &[Order]| {
+            Settlement::with_default_prices(
+                orders
+                    .iter()
+                    .map(|order| Trade {
+                        order: order.clone(),
+                        executed_amount: 100u8.into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )
+        };
+        let a = H160([1; 20]);
+        let b = H160([2; 20]);
+        let orders = [order(1), order(2), order(3)];
+        let auction = Auction {
+            block: 11,
+            latest_settlement_block: 9,
+            orders: orders.to_vec(),
+            ..Default::default()
+        };
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(
+            10,
+            &settlement(&orders[..2]),
+            Submitter {
+                account: a,
+                nonce: 7.into(),
+            },
+        );
+        inflight.mark_settled_orders(
+            10,
+            &settlement(&orders[1..]),
+            Submitter {
+                account: b,
+                nonce: 7.into(),
+            },
+        );
+        let remaining = |inflight: &InFlightOrders| {
+            let mut auction = auction.clone();
+            inflight.filter(11, 9, &mut auction.orders);
+            auction
+                .orders
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>()
+        };
+        assert!(remaining(&inflight).is_empty());
+
+        // Account B used its nonce for another transaction so only the orders
+        // of account A's settlement are still in flight.
+        inflight.clear_for_nonce(b, 7.into());
+        assert_eq!(remaining(&inflight), vec![OrderUid::from_integer(3)]);
+        inflight.clear_for_nonce(b, 8.into());
+        assert_eq!(remaining(&inflight), vec![OrderUid::from_integer(3)]);
+
+        inflight.clear_for_nonce(a, 7.into());
+        assert_eq!(remaining(&inflight).len(), 3);
+        assert!(inflight.settlements.is_empty());
+    }
+
+    #[test]
+    fn liquidity_orders_are_filtered_like_user_orders() {
+        let fill_or_kill = Order {
+            data: OrderData {
+                sell_token: H160::from_low_u64_be(0),
+                buy_token: H160::from_low_u64_be(1),
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                kind: OrderKind::Sell,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(1),
+                class: OrderClass::Liquidity,
+                is_liquidity_order: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // partially fillable liquidity order 30% filled
+        let mut partially_fillable_1 = fill_or_kill.clone();
+        partially_fillable_1.data.partially_fillable = true;
+        partially_fillable_1.metadata.uid = OrderUid::from_integer(2);
+        partially_fillable_1.metadata.executed_buy_amount = 30u8.into();
+        partially_fillable_1.metadata.executed_sell_amount = 30u8.into();
+        partially_fillable_1
+            .metadata
+            .executed_sell_amount_before_fees = 30u8.into();
+        let mut partially_fillable_2 = partially_fillable_1.clone();
+        partially_fillable_2.metadata.uid = OrderUid::from_integer(3);
+
+        let trade = |order: &Order, executed_amount: u8| Trade {
+            order: order.clone(),
+            executed_amount: executed_amount.into(),
+            ..Default::default()
+        };
+        let settlement = Settlement::with_default_prices(vec![
+            trade(&fill_or_kill, 100),
+            trade(&partially_fillable_2, 20),
+            trade(&partially_fillable_1, 50),
+            trade(&partially_fillable_1, 20),
+        ]);
+        let metrics = Metrics::get();
+        let liquidity_orders = |effect| metrics.liquidity_orders.with_label_values(&[effect]).get();
+        let dropped_before = liquidity_orders("dropped");
+        let scaled_before = liquidity_orders("scaled");
+
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_settled_orders(1, &settlement, Default::default());
+        let mut auction = Auction {
+            block: 1,
+            orders: vec![fill_or_kill, partially_fillable_1, partially_fillable_2],
+            ..Default::default()
+        };
+        inflight.update_and_filter(&mut auction);
+
+        // The fill-or-kill order is in flight and the first partially
+        // fillable order is used up by in flight trades.
+        assert_eq!(auction.orders.len(), 1);
+        assert_eq!(auction.orders[0].metadata.uid, OrderUid::from_integer(3));
+        assert_eq!(auction.orders[0].metadata.executed_buy_amount, 50u8.into());
+        assert_eq!(
+            auction.orders[0].metadata.executed_sell_amount_before_fees,
+            50u8.into()
+        );
+        // Other tests update the metrics concurrently.
+        assert!(liquidity_orders("dropped") - dropped_before >= 2);
+        assert!(liquidity_orders("scaled") - scaled_before >= 2);
+    }
+
+    #[test]
+    fn restore_tolerates_schema_changes() {
+        let uid = OrderUid::from_integer(1);
+        let snapshot = serde_json::json!({
+            "settlements": {
+                "9": [{"id": 0, "orders": [OrderUid::from_integer(2)]}],
+                "10": [{"id": 3, "orders": [uid], "unknownField": 1}],
+            },
+            "unknownField": true,
+        });
+        let restored = InFlightOrders::restore(snapshot.to_string().as_bytes(), 10).unwrap();
+        assert_eq!(restored.snapshot().orders, btreemap! {10 => vec![uid]});
+        assert!(restored.snapshot().partially_fillable.is_empty());
+        // Ids of restored settlements don't get reused.
+        assert_eq!(restored.next_submission, 4);
+
+        let restored = InFlightOrders::restore(b"{}", 0).unwrap();
+        assert!(restored.settlements.is_empty());
+        assert!(InFlightOrders::restore(b"not json", 0).is_err());
+    }
+
+    #[test]
+    fn restore_converts_legacy_snapshots() {
+        let uid = OrderUid::from_integer(1);
+        let partial = OrderUid::from_integer(2);
+        let snapshot = serde_json::json!({
+            "inFlight": {
+                "9": [OrderUid::from_integer(3)],
+                "10": [uid],
+                "11": [partial],
+            },
+            "trades": {
+                "11": {
+                    (partial.to_string()): [{
+                        "sellToken": H160::from_low_u64_be(1),
+                        "buyToken": H160::from_low_u64_be(2),
+                        "sellAmount": "10",
+                        "buyAmount": "20",
+                        "feeAmount": "1",
+                        "surplusFee": "2",
+                        "partiallyFillable": true,
+                    }],
+                },
+            },
+        });
+        let restored = InFlightOrders::restore(snapshot.to_string().as_bytes(), 10).unwrap();
+        assert_eq!(
+            restored.snapshot(),
+            InFlightSnapshot {
+                orders: btreemap! {10 => vec![uid], 11 => vec![partial]},
+                partially_fillable: btreemap! {
+                    partial => InFlightAmounts {
+                        sell_amount: 10.into(),
+                        buy_amount: 20.into(),
+                        fee_amount: 1.into(),
+                        surplus_fee: 2.into(),
+                    },
+                },
+            }
+        );
+        // Every block became a settlement with its own id.
+        assert_eq!(restored.next_submission, 3);
+
+        // Legacy fields don't get written anymore.
+        let serialized: serde_json::Value =
+            serde_json::from_slice(&restored.serialize().unwrap()).unwrap();
+        assert!(serialized.get("inFlight").is_none());
+        assert!(serialized.get("trades").is_none());
     }
 
     #[test]
@@ -2,6 +2,7 @@ use {
     crate::{
         arguments::{Arguments, TransactionStrategyArg},
         driver::Driver,
+        in_flight_orders::InFlightOrders,
         liquidity::{
             balancer_v2::BalancerV2Liquidity,
             order_converter::OrderConverter,
@@ -559,6 +560,15 @@ pub async fn run(args: Arguments) {
         },
     );
 
+    let in_flight_orders = match args.in_flight_orders_file {
+        Some(file) => InFlightOrders::persisted(
+            file,
+            current_block_stream.borrow().number,
+            args.in_flight_orders_horizon,
+        ),
+        None => InFlightOrders::default(),
+    }
+    .with_max_age(args.in_flight_orders_max_age);
     let mut driver = Driver::new(
         settlement_contract,
         liquidity_collector,
@@ -587,6 +597,7 @@ pub async fn run(args: Arguments) {
         args.process_partially_fillable_limit_orders,
         settlement_rater,
         balance_fetcher,
+        in_flight_orders,
     );
 
     let maintainer = ServiceMaintenance::new(maintainers);
@@ -5,7 +5,10 @@ use {
     anyhow::Result,
     model::order::{Order, OrderKind},
     num::{rational::Ratio, BigInt, BigRational, One, Signed, Zero},
+    number::serialization::HexOrDecimalU256,
     primitive_types::{H160, U256},
+    serde::{Deserialize, Serialize},
+    serde_with::serde_as,
     shared::{
         conversions::U256Ext as _,
         encoded_settlement::{encode_trade, EncodedSettlement, EncodedTrade},
@@ -36,12 +39,17 @@ impl Trade {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[serde_as]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TradeExecution {
     pub sell_token: H160,
     pub buy_token: H160,
+    #[serde_as(as = "HexOrDecimalU256")]
     pub sell_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
     pub buy_amount: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
     pub fee_amount: U256,
 }
 
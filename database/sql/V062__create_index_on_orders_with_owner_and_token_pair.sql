@@ -0,0 +1,3 @@
+-- Allows filtering the orders of a user by token pair without scanning all of
+-- their orders.
+CREATE INDEX CONCURRENTLY user_order_token_pair_creation_timestamp ON orders USING BTREE (owner, sell_token, buy_token, creation_timestamp DESC);
@@ -0,0 +1,9 @@
+-- Reduced amounts of partially fillable orders. The signed order stays
+-- unchanged but only the remainder up to these amounts gets exposed in the
+-- auction.
+CREATE TABLE order_amendments (
+  order_uid bytea PRIMARY KEY,
+  sell_amount numeric(78,0) NOT NULL,
+  buy_amount numeric(78,0) NOT NULL,
+  timestamp timestamptz NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Hashes of the signed off-chain cancellation messages that were applied so
+-- the same message can't be replayed.
+CREATE TABLE order_cancellations (
+  hash bytea PRIMARY KEY,
+  order_uid bytea NOT NULL,
+  timestamp timestamptz NOT NULL
+);
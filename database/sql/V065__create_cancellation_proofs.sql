@@ -0,0 +1,9 @@
+-- Signed off-chain cancellations of orders kept as proof that the owner
+-- cancelled the order.
+CREATE TABLE cancellation_proofs (
+  order_uid bytea PRIMARY KEY,
+  signature bytea NOT NULL,
+  signing_scheme SigningScheme NOT NULL,
+  signer bytea NOT NULL,
+  timestamp timestamptz NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Links orders that got replaced through the orderbook API to the orders
+-- replacing them.
+CREATE TABLE order_replacements (
+  old_order_uid bytea PRIMARY KEY,
+  new_order_uid bytea NOT NULL UNIQUE
+);
@@ -0,0 +1,7 @@
+-- Keeps track of which quote an order was created with and until when that
+-- quote was valid. Quotes get deleted from the `quotes` table once they
+-- expire so these values have to be copied. Orders created before this
+-- migration don't have them.
+ALTER TABLE order_quotes
+    ADD COLUMN quote_id bigint,
+    ADD COLUMN expiration_timestamp timestamptz;
@@ -0,0 +1,5 @@
+-- Stores when an auction got created so the orderbook can detect that the
+-- autopilot stopped producing new auctions. Existing auctions get the time of
+-- the migration.
+ALTER TABLE auctions
+    ADD COLUMN created timestamptz NOT NULL DEFAULT now();
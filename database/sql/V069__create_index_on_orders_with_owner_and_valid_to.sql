@@ -0,0 +1,3 @@
+-- Allows finding the orders of a user that expire soon without scanning all of
+-- their orders.
+CREATE INDEX CONCURRENTLY user_order_valid_to ON orders USING BTREE (owner, valid_to);
@@ -0,0 +1,5 @@
+-- Whether the quote an order was created with got verified by simulating the
+-- proposed settlement. Orders created before this migration count as created
+-- with unverified quotes.
+ALTER TABLE order_quotes
+    ADD COLUMN verified boolean NOT NULL DEFAULT false;
@@ -0,0 +1,4 @@
+-- The `appCode` of the full app data gets extracted once on registration so
+-- that orders can be grouped by integrator without parsing the JSON in every
+-- query.
+ALTER TABLE app_data ADD COLUMN app_code text;
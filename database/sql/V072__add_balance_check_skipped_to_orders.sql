@@ -0,0 +1,3 @@
+-- Marks orders of trusted liquidity providers that got accepted without
+-- checking whether the owner can transfer the sell amount.
+ALTER TABLE orders ADD COLUMN balance_check_skipped boolean NOT NULL DEFAULT false;
@@ -0,0 +1,3 @@
+-- How an order got submitted (e.g. the API or an on-chain transaction).
+-- Null for orders created before the source got recorded.
+ALTER TABLE orders ADD COLUMN source jsonb;
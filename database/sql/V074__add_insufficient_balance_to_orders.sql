@@ -0,0 +1,3 @@
+-- Set for open fill-or-kill orders whose owner repeatedly didn't have enough
+-- sell token balance. Gets cleared once the balance is sufficient again.
+ALTER TABLE orders ADD COLUMN insufficient_balance boolean NOT NULL DEFAULT false;
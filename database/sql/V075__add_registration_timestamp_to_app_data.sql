@@ -0,0 +1,4 @@
+-- When the full app data got stored. Existing rows stay null because the time
+-- of their registration is unknown.
+ALTER TABLE app_data ADD COLUMN registration_timestamp timestamptz;
+ALTER TABLE app_data ALTER COLUMN registration_timestamp SET DEFAULT now();
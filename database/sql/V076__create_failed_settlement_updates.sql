@@ -0,0 +1,13 @@
+-- Settlement events whose details could not be indexed even after repeated
+-- attempts. The autopilot skips them when looking for settlements to update so
+-- that a single broken event doesn't block indexing newer settlements.
+-- Deleting a row re-enqueues the event.
+CREATE TABLE failed_settlement_updates (
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  attempts bigint NOT NULL,
+  error text NOT NULL,
+  failed_at timestamptz NOT NULL DEFAULT now(),
+
+  PRIMARY KEY (block_number, log_index)
+);
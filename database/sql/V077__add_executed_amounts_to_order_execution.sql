@@ -0,0 +1,10 @@
+-- How an order got executed according to the settlement calldata. Rows inserted
+-- before these columns existed leave them null.
+ALTER TABLE order_execution
+  ADD COLUMN executed_sell_amount numeric,
+  ADD COLUMN executed_buy_amount numeric,
+  ADD COLUMN executed_fee_amount numeric,
+  ADD COLUMN sell_price numeric,
+  ADD COLUMN buy_price numeric,
+  ADD COLUMN surplus numeric,
+  ADD COLUMN native_fee numeric;
@@ -0,0 +1,3 @@
+-- Speeds up finding and counting the settlements whose details still need to
+-- be indexed by the autopilot.
+CREATE INDEX CONCURRENTLY settlements_without_auction ON settlements (block_number, log_index) WHERE auction_id IS NULL;
@@ -0,0 +1,18 @@
+-- Interactions executed by settlements (or proposed by the solver and then
+-- internalized) to analyze which liquidity sources solvers use.
+CREATE TABLE settlement_interactions (
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  index bigint NOT NULL,
+  stage smallint NOT NULL,
+  target bytea NOT NULL,
+  value numeric(78,0) NOT NULL,
+  selector bytea,
+  call_data_len bigint NOT NULL,
+  call_data bytea NOT NULL,
+  internalized boolean NOT NULL,
+
+  PRIMARY KEY (block_number, log_index, index)
+);
+
+CREATE INDEX settlement_interactions_target ON settlement_interactions USING HASH (target);
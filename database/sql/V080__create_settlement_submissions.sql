@@ -0,0 +1,15 @@
+-- Hash of the settlement transaction a solver broadcast first for an auction.
+-- If the transaction got replaced (e.g. rebroadcast with a higher gas price
+-- using the same nonce) the mined transaction has a different hash.
+CREATE TABLE settlement_submissions (
+  auction_id bigint NOT NULL,
+  tx_from bytea NOT NULL,
+  tx_hash bytea NOT NULL,
+  submitted_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (auction_id, tx_from)
+);
+
+-- Whether the mined settlement transaction differs from the first submission
+-- of its sender for its auction. NULL if no submission was observed.
+ALTER TABLE settlements
+  ADD COLUMN tx_replaced boolean;
@@ -0,0 +1,11 @@
+-- Splits the fees in the native token into the part that covers the gas costs
+-- (network fee) and the part charged by protocol fee policies. Both parts sum
+-- up to the existing fee columns. Rows inserted before these columns existed
+-- leave them null.
+ALTER TABLE settlement_observations
+  ADD COLUMN network_fee numeric,
+  ADD COLUMN protocol_fee numeric;
+
+ALTER TABLE order_execution
+  ADD COLUMN network_fee numeric,
+  ADD COLUMN protocol_fee numeric;
@@ -0,0 +1,13 @@
+-- Settlements of auctions that were not submitted by the winner of the
+-- competition. Used to penalize solvers settling auctions they didn't win.
+CREATE TABLE unexpected_settlement_submitters (
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  auction_id bigint NOT NULL,
+  expected_winner bytea NOT NULL,
+  actual_sender bytea NOT NULL,
+
+  PRIMARY KEY (block_number, log_index)
+);
+
+CREATE INDEX unexpected_settlement_submitters_auction_id ON unexpected_settlement_submitters (auction_id);
@@ -0,0 +1,9 @@
+-- Gas price the autopilot estimated when ranking the solutions of an auction.
+-- Auctions ranked before this column existed leave it null.
+ALTER TABLE settlement_scores
+  ADD COLUMN gas_price numeric(78,0);
+
+-- Ratio of the effective gas price the settlement paid to the gas price of the
+-- auction. Null if the gas price of the auction is unknown.
+ALTER TABLE settlement_observations
+  ADD COLUMN gas_price_ratio double precision;
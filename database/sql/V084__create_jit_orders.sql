@@ -0,0 +1,34 @@
+-- Orders that were never placed in the orderbook but created by solvers just
+-- in time for a settlement. They get reconstructed from the settlement
+-- calldata.
+CREATE TABLE jit_orders (
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  trade_index bigint NOT NULL,
+  -- Null if the owner could not be recovered from the signature.
+  uid bytea,
+  owner bytea,
+  sell_token bytea NOT NULL,
+  buy_token bytea NOT NULL,
+  receiver bytea NOT NULL,
+  sell_amount numeric(78,0) NOT NULL,
+  buy_amount numeric(78,0) NOT NULL,
+  valid_to bigint NOT NULL,
+  app_data bytea NOT NULL,
+  fee_amount numeric(78,0) NOT NULL,
+  kind OrderKind NOT NULL,
+  partially_fillable boolean NOT NULL,
+  signing_scheme SigningScheme NOT NULL,
+  signature bytea NOT NULL,
+  sell_token_balance SellTokenSource NOT NULL,
+  buy_token_balance BuyTokenDestination NOT NULL,
+  -- Null if the executed amounts could not be computed from the calldata.
+  executed_sell_amount numeric(78,0),
+  executed_buy_amount numeric(78,0),
+  executed_fee_amount numeric(78,0),
+
+  PRIMARY KEY (block_number, log_index, trade_index)
+);
+
+CREATE INDEX jit_orders_uid ON jit_orders USING HASH (uid);
+CREATE INDEX jit_orders_owner ON jit_orders USING HASH (owner);
@@ -0,0 +1,16 @@
+-- Fee a rollup charged for posting the settlement data to L1. Zero on chains
+-- that are not rollups.
+ALTER TABLE settlement_observations
+  ADD COLUMN l1_fee numeric(78,0) NOT NULL DEFAULT 0;
+
+-- Total cost of the settlement transaction in wei, i.e. the execution cost
+-- plus the L1 data fee. Observations stored before the L1 fee was recorded
+-- only account for the execution cost.
+ALTER TABLE settlement_observations
+  ADD COLUMN total_cost numeric(78,0);
+
+UPDATE settlement_observations
+  SET total_cost = gas_used * effective_gas_price;
+
+ALTER TABLE settlement_observations
+  ALTER COLUMN total_cost SET NOT NULL;
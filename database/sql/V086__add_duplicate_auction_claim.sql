@@ -0,0 +1,6 @@
+-- Auction id a settlement claimed to settle in its calldata although a
+-- different settlement was already associated with that auction. Such
+-- settlements are stored with the default auction id (0) so the original
+-- association stays untouched.
+ALTER TABLE settlements
+  ADD COLUMN duplicate_auction_claim bigint;
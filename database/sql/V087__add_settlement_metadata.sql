@@ -0,0 +1,7 @@
+-- Fields of the versioned metadata trailer of the settlement calldata. All of
+-- them are null if the calldata did not contain metadata. Version 0 trailers
+-- only contain the auction id.
+ALTER TABLE settlements
+  ADD COLUMN metadata_version smallint,
+  ADD COLUMN solution_id numeric(20,0),
+  ADD COLUMN solver_flags bigint;
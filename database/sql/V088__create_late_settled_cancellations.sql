@@ -0,0 +1,16 @@
+-- Orders that got settled although their owner cancelled them before the
+-- block of the settlement. This happens when the order gets cancelled after
+-- the auction containing it was cut.
+CREATE TABLE late_settled_cancellations (
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  order_uid bytea NOT NULL,
+  auction_id bigint NOT NULL,
+  solver bytea NOT NULL,
+  cancellation_timestamp timestamptz NOT NULL,
+  settlement_timestamp timestamptz NOT NULL,
+
+  PRIMARY KEY (block_number, log_index, order_uid)
+);
+
+CREATE INDEX late_settled_cancellations_order_uid ON late_settled_cancellations USING HASH (order_uid);
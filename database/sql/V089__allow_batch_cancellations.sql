@@ -0,0 +1,5 @@
+-- A signed batch cancellation applies to multiple orders with the same hash
+-- so the hash alone no longer identifies an applied cancellation.
+ALTER TABLE order_cancellations
+  DROP CONSTRAINT order_cancellations_pkey,
+  ADD PRIMARY KEY (hash, order_uid);
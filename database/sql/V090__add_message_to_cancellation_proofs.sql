@@ -0,0 +1,5 @@
+-- The signed message (JSON encoded) and the EIP-712 domain separator it was
+-- signed for make the proofs verifiable. Proofs stored before don't have them.
+ALTER TABLE cancellation_proofs
+  ADD COLUMN message bytea,
+  ADD COLUMN domain_separator bytea;
@@ -0,0 +1,21 @@
+-- Extracts the `appCode` of app data that got registered before the app code
+-- was extracted on registration (see V071). Documents that aren't valid JSON
+-- or have no string `appCode` keep no app code, like on registration.
+DO $$
+DECLARE
+  app_data_row record;
+BEGIN
+  FOR app_data_row IN
+    SELECT contract_app_data, full_app_data FROM app_data WHERE app_code IS NULL
+  LOOP
+    BEGIN
+      UPDATE app_data
+      SET app_code = document ->> 'appCode'
+      FROM (SELECT convert_from(app_data_row.full_app_data, 'UTF8')::jsonb AS document) AS parsed
+      WHERE contract_app_data = app_data_row.contract_app_data
+        AND jsonb_typeof(document -> 'appCode') = 'string';
+    EXCEPTION WHEN others THEN
+      -- Invalid documents are skipped.
+    END;
+  END LOOP;
+END $$;
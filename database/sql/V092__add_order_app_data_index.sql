@@ -0,0 +1,3 @@
+-- Allows looking up the orders referencing some app data, e.g. to find out
+-- whether registered app data is used at all.
+CREATE INDEX CONCURRENTLY order_app_data ON orders USING BTREE (app_data, creation_timestamp);